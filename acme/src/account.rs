@@ -1,16 +1,26 @@
 use crate::prelude::*;
+use jwt_simple::prelude::Jwk;
 use rusty_jwt_tools::prelude::*;
 
 impl RustyAcme {
     /// 5. Create a new acme account
     /// see [RFC 8555 Section 7.3](https://www.rfc-editor.org/rfc/rfc8555.html#section-7.3)
+    ///
+    /// # Arguments
+    /// * `eab` - when the ACME server requires binding the account to an external one
     pub fn new_account_request(
         directory: &AcmeDirectory,
-        alg: JwsAlgorithm,
+        alg: impl Into<AcmeJwsAlgorithm>,
         kp: &Pem,
+        eab: Option<&ExternalAccountBinding>,
         previous_nonce: String,
     ) -> RustyAcmeResult<AcmeJws> {
         const DEFAULT_CONTACT: &str = "anonymous@anonymous.invalid";
+        let alg = alg.into();
+
+        if eab.is_none() && directory.external_account_required() {
+            return Err(AcmeAccountError::ExternalAccountRequired)?;
+        }
 
         // explicitly set an invalid email so that if someday it is required to set one we do not
         // set it by accident
@@ -19,6 +29,7 @@ impl RustyAcme {
             terms_of_service_agreed: Some(true),
             contact,
             only_return_existing: Some(false),
+            external_account_binding: eab.map(|eab| eab.sign(&directory.new_account, alg, kp)).transpose()?,
         };
         let req = AcmeJws::new(alg, previous_nonce, &directory.new_account, None, Some(payload), kp)?;
         Ok(req)
@@ -27,11 +38,92 @@ impl RustyAcme {
     /// 6. parse the response from `POST /acme/new-account`
     /// see [RFC 8555 Section 7.3](https://www.rfc-editor.org/rfc/rfc8555.html#section-7.3)
     pub fn new_account_response(response: serde_json::Value) -> RustyAcmeResult<AcmeAccount> {
+        if let Some(problem) = AcmeProblem::try_from_response(&response) {
+            if problem.typ == AcmeProblemType::ExternalAccountRequired {
+                return Err(AcmeAccountError::ExternalAccountRequired)?;
+            }
+            return Err(problem.into());
+        }
         let account = serde_json::from_value::<AcmeAccount>(response)
             .map_err(|_| RustyAcmeError::SmallstepImplementationError("Invalid account response"))?;
         account.verify()?;
         Ok(account)
     }
+
+    /// For replacing the account key e.g. when rotating the device key, without creating a new
+    /// account. The new key signs an inner JWS which the old key wraps.
+    /// see [RFC 8555 Section 7.3.5](https://www.rfc-editor.org/rfc/rfc8555.html#section-7.3.5)
+    ///
+    /// # Arguments
+    /// * `old_alg`/`old_kp` - current account key
+    /// * `new_alg`/`new_kp` - key replacing it, to sign every subsequent request with
+    #[allow(clippy::too_many_arguments)]
+    pub fn key_change_request(
+        directory: &AcmeDirectory,
+        account: &AcmeAccount,
+        old_alg: impl Into<AcmeJwsAlgorithm>,
+        old_kp: &Pem,
+        new_alg: impl Into<AcmeJwsAlgorithm>,
+        new_kp: &Pem,
+        previous_nonce: String,
+    ) -> RustyAcmeResult<AcmeJws> {
+        let url = directory
+            .key_change
+            .as_ref()
+            .ok_or(AcmeAccountError::KeyChangeNotSupported)?;
+        let acct_url = account.acct_url()?;
+
+        let old_alg = old_alg.into();
+        let old_key = old_alg.public_jwk(old_kp)?;
+        let payload = AcmeKeyChangeRequest {
+            account: acct_url.clone(),
+            old_key,
+        };
+        let inner = AcmeJws::new_inner(new_alg, url, payload, new_kp)?;
+        let req = AcmeJws::new(old_alg, previous_nonce, url, Some(&acct_url), Some(inner), old_kp)?;
+        Ok(req)
+    }
+
+    /// parse the response from `POST /acme/key-change`
+    /// see [RFC 8555 Section 7.3.5](https://www.rfc-editor.org/rfc/rfc8555.html#section-7.3.5)
+    pub fn key_change_response(response: serde_json::Value) -> RustyAcmeResult<AcmeAccount> {
+        if let Some(problem) = AcmeProblem::try_from_response(&response) {
+            return Err(AcmeAccountError::KeyChangeRejected(problem.reason()))?;
+        }
+        Self::new_account_response(response)
+    }
+
+    /// For deactivating an account e.g. when retiring an enrollment identity. Once deactivated, the
+    /// ACME server rejects every request signed by the account key. Sent to the account URL.
+    /// see [RFC 8555 Section 7.3.6](https://www.rfc-editor.org/rfc/rfc8555.html#section-7.3.6)
+    pub fn deactivate_account_request(
+        account: &AcmeAccount,
+        alg: impl Into<AcmeJwsAlgorithm>,
+        kp: &Pem,
+        previous_nonce: String,
+    ) -> RustyAcmeResult<AcmeJws> {
+        let acct_url = account.acct_url()?;
+        let payload = Some(serde_json::json!({
+            "status": AcmeAccountStatus::Deactivated,
+        }));
+        let req = AcmeJws::new(alg, previous_nonce, &acct_url, Some(&acct_url), payload, kp)?;
+        Ok(req)
+    }
+
+    /// parse the response from `POST /acme/account/{account-id}`
+    /// see [RFC 8555 Section 7.3.6](https://www.rfc-editor.org/rfc/rfc8555.html#section-7.3.6)
+    pub fn deactivate_account_response(response: serde_json::Value) -> RustyAcmeResult<AcmeAccount> {
+        if let Some(problem) = AcmeProblem::try_from_response(&response) {
+            return Err(AcmeAccountError::DeactivationRejected(problem.reason()))?;
+        }
+        let account = serde_json::from_value::<AcmeAccount>(response)
+            .map_err(|_| RustyAcmeError::SmallstepImplementationError("Invalid account response"))?;
+        match account.status {
+            AcmeAccountStatus::Deactivated => Ok(account),
+            AcmeAccountStatus::Revoked => Err(AcmeAccountError::Revoked)?,
+            AcmeAccountStatus::Valid => Err(AcmeAccountError::NotDeactivated)?,
+        }
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -45,6 +137,21 @@ pub enum AcmeAccountError {
     /// A client deactivated this account
     #[error("A client deactivated this account")]
     Deactivated,
+    /// The ACME server does not advertise a 'keyChange' URL
+    #[error("The ACME server does not support changing the account key")]
+    KeyChangeNotSupported,
+    /// The ACME server refused to change the account key e.g. the new one is already in use
+    #[error("The ACME server refused to change the account key because {0}")]
+    KeyChangeRejected(String),
+    /// The ACME server answered a deactivation with an account still valid
+    #[error("The account is still valid after being deactivated")]
+    NotDeactivated,
+    /// The ACME server refused to deactivate the account
+    #[error("The ACME server refused to deactivate the account because {0}")]
+    DeactivationRejected(String),
+    /// The ACME server only creates accounts bound to an external one
+    #[error("The ACME server requires an external account binding")]
+    ExternalAccountRequired,
 }
 
 /// For creating an account
@@ -67,6 +174,21 @@ pub struct AcmeAccountRequest {
     /// see [Section 7.3.1](https://www.rfc-editor.org/rfc/rfc8555.html#section-7.3.1) for more details.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub only_return_existing: Option<bool>,
+    /// Binds the account to one the client has with the ACME server operator
+    /// see [Section 7.3.4](https://www.rfc-editor.org/rfc/rfc8555.html#section-7.3.4)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub external_account_binding: Option<AcmeJws>,
+}
+
+/// For changing the account key, signed by the new key
+/// see [RFC 8555 Section 7.3.5](https://www.rfc-editor.org/rfc/rfc8555.html#section-7.3.5)
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AcmeKeyChangeRequest {
+    /// URL of the account whose key is changed
+    pub account: url::Url,
+    /// public key currently associated with the account
+    pub old_key: Jwk,
 }
 
 /// Account creation response
@@ -233,6 +355,216 @@ pub mod tests {
         }
     }
 
+    mod eab {
+        use base64::Engine;
+        use jwt_simple::prelude::*;
+
+        use crate::directory::tests::directory;
+
+        use super::*;
+
+        #[test]
+        #[wasm_bindgen_test]
+        fn should_bind_external_account() {
+            let kp = Pem::from(Ed25519KeyPair::generate().to_pem());
+            let eab = ExternalAccountBinding {
+                kid: "eab-kid".to_string(),
+                hmac_key: base64::prelude::BASE64_URL_SAFE_NO_PAD.encode([7u8; 32]),
+            };
+            let req = RustyAcme::new_account_request(
+                &directory(None, None, None),
+                JwsAlgorithm::Ed25519,
+                &kp,
+                Some(&eab),
+                "nonce".to_string(),
+            )
+            .unwrap();
+            let payload = req.decoded_payload().unwrap().unwrap();
+            let payload = serde_json::from_value::<AcmeAccountRequest>(payload).unwrap();
+            let binding = payload.external_account_binding.unwrap();
+            let header = binding.decoded_protected().unwrap();
+            assert_eq!(header["alg"], json!("HS256"));
+            assert_eq!(header["kid"], json!("eab-kid"));
+            assert_eq!(header["url"], json!("https://example.com/acme/new-account"));
+
+            let req = RustyAcme::new_account_request(
+                &directory(None, None, None),
+                JwsAlgorithm::Ed25519,
+                &kp,
+                None,
+                "nonce".to_string(),
+            )
+            .unwrap();
+            let payload = req.decoded_payload().unwrap().unwrap();
+            assert!(payload.get("externalAccountBinding").is_none());
+        }
+
+        #[test]
+        #[wasm_bindgen_test]
+        fn should_fail_when_directory_requires_binding() {
+            let kp = Pem::from(Ed25519KeyPair::generate().to_pem());
+            let mut directory = directory(None, None, None);
+            directory.meta = Some(AcmeDirectoryMeta {
+                external_account_required: true,
+                ..Default::default()
+            });
+            let req = RustyAcme::new_account_request(&directory, JwsAlgorithm::Ed25519, &kp, None, "nonce".to_string());
+            assert!(matches!(
+                req.unwrap_err(),
+                RustyAcmeError::AccountError(AcmeAccountError::ExternalAccountRequired)
+            ));
+        }
+
+        #[test]
+        #[wasm_bindgen_test]
+        fn should_fail_when_external_account_required() {
+            let problem = json!({"type": "urn:ietf:params:acme:error:externalAccountRequired"});
+            assert!(matches!(
+                RustyAcme::new_account_response(problem).unwrap_err(),
+                RustyAcmeError::AccountError(AcmeAccountError::ExternalAccountRequired)
+            ));
+        }
+    }
+
+    mod key_change {
+        use jwt_simple::prelude::*;
+        use rusty_jwt_tools::jwk::{RustyJwk, TryFromJwk};
+
+        use crate::directory::tests::directory;
+
+        use super::*;
+
+        #[test]
+        #[wasm_bindgen_test]
+        fn should_nest_new_key_signature() {
+            let (old, new) = (Ed25519KeyPair::generate(), ES256KeyPair::generate());
+            let old_kp = Pem::from(old.to_pem());
+            let new_kp = Pem::from(new.to_pem().unwrap());
+            let account = AcmeAccount::default();
+            let url = "https://example.com/acme/key-change";
+            let req = RustyAcme::key_change_request(
+                &directory(None, Some(url), None),
+                &account,
+                JwsAlgorithm::Ed25519,
+                &old_kp,
+                JwsAlgorithm::P256,
+                &new_kp,
+                "nonce".to_string(),
+            )
+            .unwrap();
+
+            // outer JWS signed by the account key
+            let outer = req.decoded_protected().unwrap();
+            assert_eq!(outer["kid"], json!(account.acct_url().unwrap()));
+            assert_eq!(outer["url"], json!(url));
+            assert_eq!(outer["nonce"], json!("nonce"));
+
+            // inner JWS signed by the new key, without nonce
+            let inner = serde_json::from_value::<AcmeJws>(req.decoded_payload().unwrap().unwrap()).unwrap();
+            let header = inner.decoded_protected().unwrap();
+            assert_eq!(header["alg"], json!("ES256"));
+            assert_eq!(header["url"], json!(url));
+            assert!(header.get("nonce").is_none());
+            assert!(header.get("kid").is_none());
+            let new_jwk = serde_json::from_value::<Jwk>(header["jwk"].clone()).unwrap();
+            let new_pk = ES256PublicKey::try_from_jwk(&new_jwk).unwrap();
+            assert_eq!(new_pk.to_bytes(), new.public_key().to_bytes());
+
+            let payload = inner.decoded_payload().unwrap().unwrap();
+            let payload = serde_json::from_value::<AcmeKeyChangeRequest>(payload).unwrap();
+            assert_eq!(payload.account, account.acct_url().unwrap());
+            let old_jwk = RustyJwk::private_pem_to_jwk(JwsAlgorithm::Ed25519, &old_kp)
+                .unwrap()
+                .public;
+            assert_eq!(
+                serde_json::to_value(payload.old_key).unwrap(),
+                serde_json::to_value(old_jwk).unwrap()
+            );
+        }
+
+        #[test]
+        #[wasm_bindgen_test]
+        fn should_fail_when_not_supported() {
+            let kp = Pem::from(Ed25519KeyPair::generate().to_pem());
+            let result = RustyAcme::key_change_request(
+                &directory(None, None, None),
+                &AcmeAccount::default(),
+                JwsAlgorithm::Ed25519,
+                &kp,
+                JwsAlgorithm::Ed25519,
+                &kp,
+                "nonce".to_string(),
+            );
+            assert!(matches!(
+                result.unwrap_err(),
+                RustyAcmeError::AccountError(AcmeAccountError::KeyChangeNotSupported)
+            ));
+        }
+
+        #[test]
+        #[wasm_bindgen_test]
+        fn should_parse_response() {
+            let account = json!({
+                "status": "valid",
+                "orders": "https://example.com/acme/acct/evOfKhNU60wg/orders"
+            });
+            assert!(RustyAcme::key_change_response(account).is_ok());
+
+            let problem = json!({"type": "urn:ietf:params:acme:error:malformed", "detail": "key in use"});
+            assert!(matches!(
+                RustyAcme::key_change_response(problem).unwrap_err(),
+                RustyAcmeError::AccountError(AcmeAccountError::KeyChangeRejected(reason)) if reason == "key in use"
+            ));
+        }
+    }
+
+    mod deactivate {
+        use jwt_simple::prelude::*;
+
+        use super::*;
+
+        #[test]
+        #[wasm_bindgen_test]
+        fn should_request_deactivated_status() {
+            let kp = Pem::from(Ed25519KeyPair::generate().to_pem());
+            let account = AcmeAccount::default();
+            let req = RustyAcme::deactivate_account_request(&account, JwsAlgorithm::Ed25519, &kp, "nonce".to_string())
+                .unwrap();
+            let acct_url = json!(account.acct_url().unwrap());
+            let header = req.decoded_protected().unwrap();
+            assert_eq!(header["kid"], acct_url);
+            assert_eq!(header["url"], acct_url);
+            assert_eq!(req.decoded_payload().unwrap(), Some(json!({"status": "deactivated"})));
+        }
+
+        #[test]
+        #[wasm_bindgen_test]
+        fn should_succeed_when_deactivated() {
+            // the account might not list its orders anymore
+            let account = RustyAcme::deactivate_account_response(json!({"status": "deactivated"})).unwrap();
+            assert_eq!(account.status, AcmeAccountStatus::Deactivated);
+        }
+
+        #[test]
+        #[wasm_bindgen_test]
+        fn should_fail_when_not_deactivated() {
+            assert!(matches!(
+                RustyAcme::deactivate_account_response(json!({"status": "valid"})).unwrap_err(),
+                RustyAcmeError::AccountError(AcmeAccountError::NotDeactivated)
+            ));
+            assert!(matches!(
+                RustyAcme::deactivate_account_response(json!({"status": "revoked"})).unwrap_err(),
+                RustyAcmeError::AccountError(AcmeAccountError::Revoked)
+            ));
+            let problem = json!({"type": "urn:ietf:params:acme:error:unauthorized"});
+            assert!(matches!(
+                RustyAcme::deactivate_account_response(problem).unwrap_err(),
+                RustyAcmeError::AccountError(AcmeAccountError::DeactivationRejected(reason))
+                    if reason == "urn:ietf:params:acme:error:unauthorized"
+            ));
+        }
+    }
+
     mod acct_url {
         use super::*;
 
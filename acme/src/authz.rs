@@ -11,22 +11,17 @@ impl RustyAcme {
     pub fn new_authz_request(
         url: &url::Url,
         account: &AcmeAccount,
-        alg: JwsAlgorithm,
+        alg: impl Into<AcmeJwsAlgorithm>,
         kp: &Pem,
         previous_nonce: String,
     ) -> RustyAcmeResult<AcmeJws> {
-        // Extract the account URL from previous response which created a new account
-        let acct_url = account.acct_url()?;
-
-        // No payload required for authz
-        let payload = None::<serde_json::Value>;
-        let req = AcmeJws::new(alg, previous_nonce, url, Some(&acct_url), payload, kp)?;
-        Ok(req)
+        Self::post_as_get(url, account, alg, kp, previous_nonce)
     }
 
     /// parse the response from `POST /acme/authz/{authz_id}`
     /// [RFC 8555 Section 7.5](https://www.rfc-editor.org/rfc/rfc8555.html#section-7.5)
     pub fn new_authz_response(response: serde_json::Value) -> RustyAcmeResult<AcmeAuthz> {
+        AcmeProblem::check(&response)?;
         let authz = serde_json::from_value::<AcmeAuthz>(response)?;
 
         authz.verify()?;
@@ -46,6 +41,46 @@ impl RustyAcme {
         }
         Ok(authz)
     }
+
+    /// pre-authorize an identifier so that its challenge can be completed before creating the
+    /// order e.g. the device one, built with [AcmeIdentifier::try_new_device]
+    /// see [RFC 8555 Section 7.4.1](https://www.rfc-editor.org/rfc/rfc8555.html#section-7.4.1)
+    pub fn new_preauthz_request(
+        identifier: AcmeIdentifier,
+        directory: &AcmeDirectory,
+        account: &AcmeAccount,
+        alg: impl Into<AcmeJwsAlgorithm>,
+        kp: &Pem,
+        previous_nonce: String,
+    ) -> RustyAcmeResult<AcmeJws> {
+        let url = directory
+            .new_authz
+            .as_ref()
+            .ok_or(AcmeAuthzError::PreAuthorizationNotSupported)?;
+        let acct_url = account.acct_url()?;
+
+        let payload = AcmePreAuthzRequest { identifier };
+        let req = AcmeJws::new(alg, previous_nonce, url, Some(&acct_url), Some(payload), kp)?;
+        Ok(req)
+    }
+
+    /// parse the response from `POST /acme/new-authz`. Unlike an authorization created along with
+    /// an order, it might already be valid when the server reuses a previous one
+    /// see [RFC 8555 Section 7.4.1](https://www.rfc-editor.org/rfc/rfc8555.html#section-7.4.1)
+    pub fn new_preauthz_response(response: serde_json::Value) -> RustyAcmeResult<AcmeAuthz> {
+        AcmeProblem::check(&response)?;
+        let authz = serde_json::from_value::<AcmeAuthz>(response)?;
+
+        authz.verify()?;
+
+        match authz.status {
+            AuthzStatus::Pending | AuthzStatus::Valid => Ok(authz),
+            AuthzStatus::Invalid => Err(AcmeAuthzError::Invalid)?,
+            AuthzStatus::Revoked => Err(AcmeAuthzError::Revoked)?,
+            AuthzStatus::Deactivated => Err(AcmeAuthzError::Deactivated)?,
+            AuthzStatus::Expired => Err(AcmeAuthzError::Expired)?,
+        }
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -71,16 +106,36 @@ pub enum AcmeAuthzError {
     /// The Challenge type must match the identifier type
     #[error("The Challenge type must match the identifier type")]
     InvalidChallengeType,
+    /// The identifier does not designate the expected client
+    #[error("The authorization identifier has an unexpected '{0}'")]
+    IdentifierMismatch(&'static str),
+    /// The ACME server does not advertise a 'newAuthz' URL
+    #[error("The ACME server does not support pre-authorization")]
+    PreAuthorizationNotSupported,
+}
+
+/// For pre-authorizing an identifier
+/// see [RFC 8555 Section 7.4.1](https://www.rfc-editor.org/rfc/rfc8555.html#section-7.4.1)
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct AcmePreAuthzRequest {
+    /// identifier to authorize
+    pub identifier: AcmeIdentifier,
 }
 
 /// Result of an authorization creation
 /// see [RFC 8555 Section 7.5](https://www.rfc-editor.org/rfc/rfc8555.html#section-7.5)
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct AcmeAuthz {
     /// Should be pending for a newly created authorization
     pub status: AuthzStatus,
-    #[serde(skip_serializing_if = "Option::is_none", with = "time::serde::rfc3339::option")]
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "time::serde::rfc3339::option"
+    )]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<String>"))]
     /// Expiration time as [RFC 3339](https://www.rfc-editor.org/rfc/rfc3339)
     pub expires: Option<time::OffsetDateTime>,
     /// Challenges to complete later
@@ -127,6 +182,32 @@ impl AcmeAuthz {
 
         Ok(())
     }
+
+    /// The http-01 challenge of this authorization, if offered
+    pub fn http_challenge(&self) -> Option<&AcmeChallenge> {
+        self.challenges.iter().find(|c| c.typ == AcmeChallengeType::Http01)
+    }
+
+    /// The dns-01 challenge of this authorization, if offered
+    pub fn dns_challenge(&self) -> Option<&AcmeChallenge> {
+        self.challenges.iter().find(|c| c.typ == AcmeChallengeType::Dns01)
+    }
+
+    /// Parses the identifier value
+    pub fn wire_identifier(&self) -> RustyAcmeResult<WireIdentifier> {
+        self.identifier.to_wire_identifier()
+    }
+
+    /// Lets a client ensure this authorization is for its own identity before attempting its
+    /// challenge, see [WireIdentifier::verify]
+    pub fn verify_identity(
+        &self,
+        client_id: &ClientId,
+        handle: &QualifiedHandle,
+        display_name: &str,
+    ) -> RustyAcmeResult<()> {
+        self.wire_identifier()?.verify(client_id, handle, display_name)
+    }
 }
 
 #[cfg(test)]
@@ -144,6 +225,7 @@ impl Default for AcmeAuthz {
 /// see [RFC 8555 Section 7.1.6](https://www.rfc-editor.org/rfc/rfc8555.html#section-7.1.6)
 #[derive(Debug, Copy, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "lowercase")]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum AuthzStatus {
     Pending,
     Invalid,
@@ -188,9 +270,90 @@ pub mod tests {
         }
     }
 
+    mod preauthz {
+        use jwt_simple::prelude::*;
+
+        use crate::directory::tests::directory;
+
+        use super::*;
+
+        fn request(directory: &AcmeDirectory) -> RustyAcmeResult<AcmeJws> {
+            let kp = Pem::from(Ed25519KeyPair::generate().to_pem());
+            let account = AcmeAccount::default();
+            let identifier = AcmeIdentifier::new_device();
+            RustyAcme::new_preauthz_request(
+                identifier,
+                directory,
+                &account,
+                JwsAlgorithm::Ed25519,
+                &kp,
+                "nonce".to_string(),
+            )
+        }
+
+        #[test]
+        #[wasm_bindgen_test]
+        fn should_post_identifier_to_new_authz() {
+            let url = "https://example.com/acme/new-authz";
+            let req = request(&directory(Some(url), None, None)).unwrap();
+            let header = req.decoded_protected().unwrap();
+            assert_eq!(header["url"], json!(url));
+            assert_eq!(header["kid"], json!(AcmeAccount::default().acct_url().unwrap()));
+            let payload = req.decoded_payload().unwrap().unwrap();
+            let payload = serde_json::from_value::<AcmePreAuthzRequest>(payload).unwrap();
+            assert_eq!(payload.identifier, AcmeIdentifier::new_device());
+        }
+
+        #[test]
+        #[wasm_bindgen_test]
+        fn should_fail_when_not_supported() {
+            assert!(matches!(
+                request(&directory(None, None, None)).unwrap_err(),
+                RustyAcmeError::AuthzError(AcmeAuthzError::PreAuthorizationNotSupported)
+            ));
+        }
+
+        #[test]
+        #[wasm_bindgen_test]
+        fn should_accept_pending_or_valid_authz() {
+            let tomorrow = time::OffsetDateTime::now_utc() + time::Duration::days(1);
+            let authz = |status| {
+                serde_json::to_value(AcmeAuthz {
+                    status,
+                    expires: Some(tomorrow),
+                    ..Default::default()
+                })
+                .unwrap()
+            };
+            assert!(RustyAcme::new_preauthz_response(authz(AuthzStatus::Pending)).is_ok());
+            assert!(RustyAcme::new_preauthz_response(authz(AuthzStatus::Valid)).is_ok());
+            assert!(matches!(
+                RustyAcme::new_preauthz_response(authz(AuthzStatus::Invalid)).unwrap_err(),
+                RustyAcmeError::AuthzError(AcmeAuthzError::Invalid)
+            ));
+            assert!(matches!(
+                RustyAcme::new_preauthz_response(authz(AuthzStatus::Revoked)).unwrap_err(),
+                RustyAcmeError::AuthzError(AcmeAuthzError::Revoked)
+            ));
+        }
+    }
+
     mod verify {
         use super::*;
 
+        #[test]
+        #[wasm_bindgen_test]
+        fn should_verify_identity() {
+            let authz = AcmeAuthz::default();
+            let (handle, alice) = (QualifiedHandle::default(), "Alice Smith");
+            let client_id = AcmeIdentifier::default_client_id();
+            assert!(authz.verify_identity(&client_id, &handle, alice).is_ok());
+            assert!(matches!(
+                authz.verify_identity(&ClientId::alice(), &handle, alice).unwrap_err(),
+                RustyAcmeError::AuthzError(AcmeAuthzError::IdentifierMismatch("client-id"))
+            ));
+        }
+
         #[test]
         #[wasm_bindgen_test]
         fn should_succeed_when_valid() {
@@ -0,0 +1,195 @@
+use x509_cert::der::Decode as _;
+
+use crate::{error::CertificateError, prelude::*};
+
+/// Parsed view of a certificate returned by [RustyAcme::certificate_response], for displaying
+/// and validating it without another x509 dependency
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct AcmeCertificateInfo {
+    /// Distinguished name of the subject as a [RFC 4514](https://www.rfc-editor.org/rfc/rfc4514) string
+    pub subject: String,
+    /// Distinguished name of the issuer as a [RFC 4514](https://www.rfc-editor.org/rfc/rfc4514) string
+    pub issuer: String,
+    /// Serial number, lowercase hex encoded without the leading zero of its DER encoding
+    pub serial_number: String,
+    pub not_before: time::OffsetDateTime,
+    pub not_after: time::OffsetDateTime,
+    /// URIs of the Subject Alternative Name e.g. the Wire client id and handle
+    pub san_uris: Vec<String>,
+    /// DNS names of the Subject Alternative Name
+    pub san_dns_names: Vec<String>,
+    pub subject_key_identifier: Option<Vec<u8>>,
+    pub authority_key_identifier: Option<Vec<u8>>,
+    /// URIs the CRLs covering this certificate can be downloaded from
+    pub crl_distribution_points: Vec<String>,
+    /// Certificate Transparency timestamps embedded by the CA
+    pub signed_certificate_timestamps: Vec<AcmeSignedCertificateTimestamp>,
+    /// e.g. 'Ed25519' or 'ecdsa-with-SHA256'. The dotted object identifier when unknown
+    pub signature_algorithm: String,
+}
+
+impl AcmeCertificateInfo {
+    /// Parses a DER encoded certificate
+    pub fn try_from_der(certificate: &[u8]) -> RustyAcmeResult<Self> {
+        x509_cert::Certificate::from_der(certificate)?.try_into()
+    }
+}
+
+impl TryFrom<x509_cert::Certificate> for AcmeCertificateInfo {
+    type Error = RustyAcmeError;
+
+    fn try_from(certificate: x509_cert::Certificate) -> RustyAcmeResult<Self> {
+        let tbs = &certificate.tbs_certificate;
+        let to_datetime = |t: x509_cert::time::Time| -> RustyAcmeResult<time::OffsetDateTime> {
+            let secs = t.to_unix_duration().as_secs() as i64;
+            Ok(time::OffsetDateTime::from_unix_timestamp(secs).map_err(|_| CertificateError::InvalidFormat)?)
+        };
+
+        let (mut san_uris, mut san_dns_names) = (vec![], vec![]);
+        if let Some(san) =
+            find_extension::<x509_cert::ext::pkix::SubjectAltName>(tbs, oid_registry::OID_X509_EXT_SUBJECT_ALT_NAME)?
+        {
+            for name in san.0 {
+                match name {
+                    x509_cert::ext::pkix::name::GeneralName::UniformResourceIdentifier(uri) => {
+                        san_uris.push(uri.to_string())
+                    }
+                    x509_cert::ext::pkix::name::GeneralName::DnsName(dns) => san_dns_names.push(dns.to_string()),
+                    _ => {}
+                }
+            }
+        }
+
+        let subject_key_identifier = find_extension::<x509_cert::ext::pkix::SubjectKeyIdentifier>(
+            tbs,
+            oid_registry::OID_X509_EXT_SUBJECT_KEY_IDENTIFIER,
+        )?
+        .map(|ski| ski.0.as_bytes().to_vec());
+        let authority_key_identifier = find_extension::<x509_cert::ext::pkix::AuthorityKeyIdentifier>(
+            tbs,
+            oid_registry::OID_X509_EXT_AUTHORITY_KEY_IDENTIFIER,
+        )?
+        .and_then(|aki| aki.key_identifier)
+        .map(|kid| kid.as_bytes().to_vec());
+
+        let crl_distribution_points = find_extension::<x509_cert::ext::pkix::CrlDistributionPoints>(
+            tbs,
+            oid_registry::OID_X509_EXT_CRL_DISTRIBUTION_POINTS,
+        )?
+        .map(|dps| {
+            dps.0
+                .into_iter()
+                .filter_map(|dp| match dp.distribution_point {
+                    Some(x509_cert::ext::pkix::name::DistributionPointName::FullName(names)) => Some(names),
+                    _ => None,
+                })
+                .flatten()
+                .filter_map(|name| match name {
+                    x509_cert::ext::pkix::name::GeneralName::UniformResourceIdentifier(uri) => Some(uri.to_string()),
+                    _ => None,
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+        Ok(Self {
+            subject: tbs.subject.to_string(),
+            issuer: tbs.issuer.to_string(),
+            serial_number: serial_number_hex(&tbs.serial_number),
+            not_before: to_datetime(tbs.validity.not_before)?,
+            not_after: to_datetime(tbs.validity.not_after)?,
+            san_uris,
+            san_dns_names,
+            subject_key_identifier,
+            authority_key_identifier,
+            crl_distribution_points,
+            signed_certificate_timestamps: AcmeSignedCertificateTimestamp::find_all(tbs)?,
+            signature_algorithm: signature_algorithm_name(&certificate.signature_algorithm.oid),
+        })
+    }
+}
+
+/// Decodes the value of the extension identified by [oid], if any
+pub(crate) fn find_extension<T: x509_cert::der::DecodeOwned>(
+    tbs: &x509_cert::TbsCertificate,
+    oid: oid_registry::Oid,
+) -> RustyAcmeResult<Option<T>> {
+    let Some(extensions) = tbs.extensions.as_ref() else {
+        return Ok(None);
+    };
+    Ok(extensions
+        .iter()
+        .find(|e| e.extn_id.as_bytes() == oid.as_bytes())
+        .map(|e| T::from_der(e.extn_value.as_bytes()))
+        .transpose()?)
+}
+
+fn serial_number_hex(serial_number: &x509_cert::serial_number::SerialNumber) -> String {
+    let bytes = serial_number.as_bytes();
+    // a positive integer whose most significant bit is set is prefixed by a zero in DER
+    let bytes = match bytes {
+        [0, rest @ ..] if !rest.is_empty() => rest,
+        _ => bytes,
+    };
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn signature_algorithm_name(oid: &x509_cert::der::oid::ObjectIdentifier) -> String {
+    let known = [
+        (oid_registry::OID_SIG_ED25519, "Ed25519"),
+        (oid_registry::OID_SIG_ECDSA_WITH_SHA256, "ecdsa-with-SHA256"),
+        (oid_registry::OID_SIG_ECDSA_WITH_SHA384, "ecdsa-with-SHA384"),
+    ];
+    known
+        .iter()
+        .find(|(known, _)| known.as_bytes() == oid.as_bytes())
+        .map(|(_, name)| name.to_string())
+        .unwrap_or_else(|| oid.to_string())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use wasm_bindgen_test::*;
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_expose_certificate_fields() {
+        let der = pem::parse(crate::identity::tests::CERT).unwrap();
+        let info = AcmeCertificateInfo::try_from_der(der.contents()).unwrap();
+
+        assert_eq!(info.subject, "CN=Alice Smith,O=wire.com");
+        assert_eq!(info.issuer, "CN=wire Intermediate CA,O=wire");
+        assert_eq!(info.serial_number, "9699765fa164397da924a3bb992658d0");
+        assert_eq!(info.not_before.unix_timestamp(), 1704466382);
+        assert_eq!(info.not_after.unix_timestamp(), 2019826382);
+        assert_eq!(
+            info.san_uris,
+            vec![
+                "wireapp://%40alice_wire@wire.com",
+                "wireapp://obakjPOHQ2CkNb0rOrNM3A%21ba54e8ace8b4c90d@wire.com"
+            ]
+        );
+        assert!(info.san_dns_names.is_empty());
+        assert!(info.crl_distribution_points.is_empty());
+        assert_eq!(
+            hex::encode(info.subject_key_identifier.unwrap()),
+            "e339c91280ddd1d29589e313372e5a888c5a6f93"
+        );
+        assert_eq!(
+            hex::encode(info.authority_key_identifier.unwrap()),
+            "13fb1ed904dd675bab313a2e5e1a201da314cd8b"
+        );
+        assert_eq!(info.signature_algorithm, "ecdsa-with-SHA256");
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_fail_when_not_a_certificate() {
+        let result = AcmeCertificateInfo::try_from_der(b"not a certificate");
+        assert!(matches!(result.unwrap_err(), RustyAcmeError::DerError(_)));
+    }
+}
@@ -2,7 +2,7 @@ use x509_cert::Certificate;
 
 use rusty_jwt_tools::prelude::*;
 
-use crate::{error::CertificateError, identifier::CanonicalIdentifier, prelude::*};
+use crate::{error::CertificateError, identifier::CanonicalIdentifier, link::link_targets, prelude::*};
 
 impl RustyAcme {
     /// For fetching the generated certificate
@@ -10,21 +10,18 @@ impl RustyAcme {
     pub fn certificate_req(
         finalize: AcmeFinalize,
         account: AcmeAccount,
-        alg: JwsAlgorithm,
+        alg: impl Into<AcmeJwsAlgorithm>,
         kp: &Pem,
         previous_nonce: String,
     ) -> RustyAcmeResult<AcmeJws> {
-        // Extract the account URL from previous response which created a new account
-        let acct_url = account.acct_url()?;
-
-        // No payload required for getting a certificate
-        let payload = None::<serde_json::Value>;
-        let req = AcmeJws::new(alg, previous_nonce, &finalize.certificate, Some(&acct_url), payload, kp)?;
-        Ok(req)
+        Self::post_as_get(&finalize.certificate, &account, alg, kp, previous_nonce)
     }
 
     /// see [RFC 8555 Section 7.4.2](https://www.rfc-editor.org/rfc/rfc8555.html#section-7.4.2)
     pub fn certificate_response(response: String, order: AcmeOrder) -> RustyAcmeResult<Vec<Vec<u8>>> {
+        if let Ok(problem) = serde_json::from_str::<serde_json::Value>(&response) {
+            AcmeProblem::check(&problem)?;
+        }
         order.verify()?;
         let pems: Vec<pem::Pem> = pem::parse_many(response)?;
         pems.into_iter()
@@ -43,6 +40,7 @@ impl RustyAcme {
 
                 // only verify that leaf has the right identity fields
                 if i == 0 {
+                    Self::verify_leaf_validity(&cert, &order)?;
                     Self::verify_leaf_certificate(cert, &order.try_get_coalesce_identifier()?)?;
                 }
                 acc.push(cert_pem.contents().to_vec());
@@ -50,32 +48,336 @@ impl RustyAcme {
             })
     }
 
+    /// Same as [RustyAcme::certificate_response] but also verifies that the leaf certificate
+    /// certifies the key pair the CSR was signed with
+    /// see [RFC 8555 Section 7.4.2](https://www.rfc-editor.org/rfc/rfc8555.html#section-7.4.2)
+    ///
+    /// # Arguments
+    /// * `signing_alg` - algorithm of [signing_kp]
+    /// * `signing_kp` - key pair given to [RustyAcme::finalize_req]
+    pub fn certificate_response_for_key(
+        response: String,
+        order: AcmeOrder,
+        signing_alg: JwsAlgorithm,
+        signing_kp: &Pem,
+    ) -> RustyAcmeResult<Vec<Vec<u8>>> {
+        use x509_cert::der::Decode as _;
+
+        let certificates = Self::certificate_response(response, order)?;
+        let leaf = certificates
+            .first()
+            .ok_or(RustyAcmeError::SmallstepImplementationError(
+                "No certificate was returned",
+            ))?;
+        Self::verify_leaf_public_key(&Certificate::from_der(leaf)?, signing_alg, signing_kp)?;
+        Ok(certificates)
+    }
+
+    /// Alternate certificate chains the ACME server advertises along with the certificate e.g.
+    /// cross-signed by another root. Download them with [RustyAcme::alternate_certificate_req]
+    /// see [RFC 8555 Section 7.4.2](https://www.rfc-editor.org/rfc/rfc8555.html#section-7.4.2)
+    ///
+    /// # Arguments
+    /// * `links` - values of every 'Link' header of the certificate response
+    pub fn certificate_alternates(links: &[&str]) -> RustyAcmeResult<Vec<url::Url>> {
+        link_targets(links, "alternate")
+            .map(|url| {
+                url.parse()
+                    .map_err(|_| RustyAcmeError::SmallstepImplementationError("Invalid 'alternate' certificate link"))
+            })
+            .collect()
+    }
+
+    /// For fetching an alternate certificate chain, see [RustyAcme::certificate_alternates]. Parse
+    /// the response with [RustyAcme::certificate_response] as well.
+    pub fn alternate_certificate_req(
+        url: &url::Url,
+        account: &AcmeAccount,
+        alg: impl Into<AcmeJwsAlgorithm>,
+        kp: &Pem,
+        previous_nonce: String,
+    ) -> RustyAcmeResult<AcmeJws> {
+        Self::post_as_get(url, account, alg, kp, previous_nonce)
+    }
+
+    /// Picks, among the default chain and the alternate ones, the first one anchored in
+    /// [preferred_root] i.e. whose last certificate is either the root or issued by it. Falls back
+    /// to the first chain, which should be the default one, when none is.
+    ///
+    /// # Arguments
+    /// * `chains` - DER certificates of each chain, leaf first
+    /// * `preferred_root` - DER certificate of the root the caller trusts the most
+    pub fn select_certificate_chain(chains: Vec<Vec<Vec<u8>>>, preferred_root: &[u8]) -> RustyAcmeResult<Vec<Vec<u8>>> {
+        use x509_cert::der::Decode as _;
+
+        let root = Certificate::from_der(preferred_root)?;
+        let mut anchored = None;
+        for (i, chain) in chains.iter().enumerate() {
+            let Some(last) = chain.last() else {
+                continue;
+            };
+            let last_cert = Certificate::from_der(last)?;
+            if last.as_slice() == preferred_root || last_cert.tbs_certificate.issuer == root.tbs_certificate.subject {
+                anchored = Some(i);
+                break;
+            }
+        }
+        chains
+            .into_iter()
+            .nth(anchored.unwrap_or_default())
+            .ok_or(RustyAcmeError::ClientImplementationError(
+                "there is no certificate chain to pick from",
+            ))
+    }
+
+    /// Ensure that the certificate validity is within the one of the order, give or take the
+    /// ACME server backdating the certificate
+    fn verify_leaf_validity(cert: &Certificate, order: &AcmeOrder) -> RustyAcmeResult<()> {
+        const BACKDATE_TOLERANCE: time::Duration = time::Duration::minutes(5);
+
+        let validity = &cert.tbs_certificate.validity;
+        let to_unix = |t: x509_cert::time::Time| t.to_unix_duration().as_secs() as i64;
+        let (cert_nbf, cert_naf) = (to_unix(validity.not_before), to_unix(validity.not_after));
+
+        let starts_too_early = order
+            .not_before
+            .map(|nbf| cert_nbf < (nbf - BACKDATE_TOLERANCE).unix_timestamp())
+            .unwrap_or_default();
+        let ends_too_late = order
+            .not_after
+            .map(|naf| cert_naf > naf.unix_timestamp())
+            .unwrap_or_default();
+        if starts_too_early || ends_too_late {
+            return Err(CertificateError::ValidityMismatch)?;
+        }
+        Ok(())
+    }
+
     /// Ensure that the generated certificate matches our expectations (i.e. that the acme server is configured the right way)
     /// We verify that the fields in the certificate match the ones in the ACME order
     fn verify_leaf_certificate(cert: Certificate, identifier: &CanonicalIdentifier) -> RustyAcmeResult<()> {
-        // TODO: verify that cert is signed by enrollment.sign_kp
         let cert_identity = cert.extract_identity()?;
+        Self::verify_leaf_san(cert, identifier)?;
 
-        let invalid_client_id =
-            ClientId::try_from_qualified(&cert_identity.client_id)? != ClientId::try_from_uri(&identifier.client_id)?;
-        if invalid_client_id {
-            return Err(CertificateError::ClientIdMismatch)?;
-        }
-
-        let invalid_display_name = cert_identity.display_name != identifier.display_name;
+        let invalid_display_name = DisplayName::from(cert_identity.display_name.as_str())
+            != DisplayName::from(identifier.display_name.as_str());
         if invalid_display_name {
             return Err(CertificateError::DisplayNameMismatch)?;
         }
 
-        let invalid_handle = cert_identity.handle != identifier.handle;
-        if invalid_handle {
-            return Err(CertificateError::HandleMismatch)?;
-        }
-
         let invalid_domain = cert_identity.domain != identifier.domain;
         if invalid_domain {
             return Err(CertificateError::DomainMismatch)?;
         }
         Ok(())
     }
+
+    /// Ensure that, among the URIs of the Subject Alternative Name, there are the expected
+    /// ClientId and handle
+    fn verify_leaf_san(cert: Certificate, identifier: &CanonicalIdentifier) -> RustyAcmeResult<()> {
+        let san_uris = AcmeCertificateInfo::try_from(cert)?.san_uris;
+
+        let has_client_id = san_uris
+            .iter()
+            .any(|uri| matches!(ClientId::try_from_uri(uri), Ok(client_id) if client_id == identifier.client_id));
+        if !has_client_id {
+            return Err(CertificateError::ClientIdMismatch)?;
+        }
+
+        let has_handle = san_uris
+            .iter()
+            .any(|uri| matches!(uri.parse::<QualifiedHandle>(), Ok(handle) if handle == identifier.handle));
+        if !has_handle {
+            return Err(CertificateError::HandleMismatch)?;
+        }
+        Ok(())
+    }
+
+    /// Ensure that the certificate certifies the key pair the CSR was signed with
+    fn verify_leaf_public_key(cert: &Certificate, alg: JwsAlgorithm, kp: &Pem) -> RustyAcmeResult<()> {
+        let expected = Self::csr_spki(alg, kp)?;
+        let spki = &cert.tbs_certificate.subject_public_key_info;
+        if spki.subject_public_key.raw_bytes() != expected.subject_public_key.raw_bytes() {
+            return Err(CertificateError::PublicKeyMismatch)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use jwt_simple::prelude::*;
+    use rusty_jwt_tools::jwk::RustyJwk;
+    use wasm_bindgen_test::*;
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    pub const ROOT: &str = r#"-----BEGIN CERTIFICATE-----
+MIIBuzCCAWGgAwIBAgIUEdfpej7DDqFIgs2yILQlqd9Hg6cwCgYIKoZIzj0EAwIw
+KjERMA8GA1UECgwId2lyZS5jb20xFTATBgNVBAMMDFdpcmUgUm9vdCBDQTAgFw0y
+NjEwMTYxNDE2MTJaGA8yMTI2MDkyMjE0MTYxMlowKjERMA8GA1UECgwId2lyZS5j
+b20xFTATBgNVBAMMDFdpcmUgUm9vdCBDQTBZMBMGByqGSM49AgEGCCqGSM49AwEH
+A0IABLdh0WuVRJgfnFcpnaOcZp6oAFHT4lkewEX2wiGogrJyFEDxgPI5GpGyUlX2
+nM5cH6t7PHa+qFpaMB1r93IsBWqjYzBhMB8GA1UdIwQYMBaAFL4p7jITFa6COV5c
+2krpdaY14FJwMA8GA1UdEwEB/wQFMAMBAf8wDgYDVR0PAQH/BAQDAgEGMB0GA1Ud
+DgQWBBS+Ke4yExWugjleXNpK6XWmNeBScDAKBggqhkjOPQQDAgNIADBFAiAihxsJ
+tJYzYu339heZYOMKIhnxQjJtn1oFnSyzTHLVWQIhALsHK/n7SwNYcNfQ+tjjS989
+E+kzHTxJnbQO8Did/nOE
+-----END CERTIFICATE-----"#;
+
+    pub const OTHER_ROOT: &str = r#"-----BEGIN CERTIFICATE-----
+MIIBvDCCAWOgAwIBAgIUU34+RVp/rZVYvKrDBBtBt062xmswCgYIKoZIzj0EAwIw
+KzERMA8GA1UECgwId2lyZS5jb20xFjAUBgNVBAMMDU90aGVyIFJvb3QgQ0EwIBcN
+MjYxMDE2MTQxNzIyWhgPMjEyNjA5MjIxNDE3MjJaMCsxETAPBgNVBAoMCHdpcmUu
+Y29tMRYwFAYDVQQDDA1PdGhlciBSb290IENBMFkwEwYHKoZIzj0CAQYIKoZIzj0D
+AQcDQgAE82jz+3/r79neyMPChFm77Xo8DJ0vd1cAawHJLFqCePemcTchKw9svNVr
+4ZZVKCYl7cqjp78w8zZ27LMwa6DQYaNjMGEwHQYDVR0OBBYEFJMkGER/m1cPY7wA
+D8EaieEoQCncMB8GA1UdIwQYMBaAFJMkGER/m1cPY7wAD8EaieEoQCncMA8GA1Ud
+EwEB/wQFMAMBAf8wDgYDVR0PAQH/BAQDAgEGMAoGCCqGSM49BAMCA0cAMEQCIHqY
+VFJKldgzr0jvGiKOVd7+ODC+2grAFpvr38qhhCU1AiBkkQuhzJYWD3Li8hxyr7Sc
+4wSKtlL1BGgaFnHNPkTDbQ==
+-----END CERTIFICATE-----"#;
+
+    /// Issued by [ROOT]
+    pub const LEAF: &str = r#"-----BEGIN CERTIFICATE-----
+MIIBtjCCAV2gAwIBAgIUPQDN2DHpMprY8FEZXgtwUIotWUswCgYIKoZIzj0EAwIw
+KjERMA8GA1UECgwId2lyZS5jb20xFTATBgNVBAMMDFdpcmUgUm9vdCBDQTAgFw0y
+NjEwMTYxNDE2MTJaGA8yMTI2MDkyMjE0MTYxMlowKTERMA8GA1UECgwId2lyZS5j
+b20xFDASBgNVBAMMC0FsaWNlIFNtaXRoMFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcD
+QgAETmcvdIlhK7GOWPQD7sB1UH41FhChWxf3+SZZFGLfcJO8qhkauHkDQUazPmRr
+u7AljH1VoJJpY7FtFXel/vtvcaNgMF4wDAYDVR0TAQH/BAIwADAOBgNVHQ8BAf8E
+BAMCB4AwHQYDVR0OBBYEFEDLzXJwtqrhJhoYkcGrkXiVb+OMMB8GA1UdIwQYMBaA
+FL4p7jITFa6COV5c2krpdaY14FJwMAoGCCqGSM49BAMCA0cAMEQCICxwDjmX9LUn
+2e1WKYTypkGEC7gm9yGKBlzC5wvtqUFDAiAyg5/2Va7CaPhhYGSwpnig10k2A3TK
+uSrMooyf1cVEMw==
+-----END CERTIFICATE-----"#;
+
+    fn der(cert: &str) -> Vec<u8> {
+        pem::parse(cert).unwrap().contents().to_vec()
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_verify_validity_against_order() {
+        use x509_cert::der::Decode as _;
+
+        // valid from 2024-01-05T14:53:02Z until 2034-01-02T14:53:02Z
+        let cert = Certificate::from_der(&der(crate::identity::tests::CERT)).unwrap();
+        let date = |d: &str| time::OffsetDateTime::parse(d, &time::format_description::well_known::Rfc3339).unwrap();
+        let order = |nbf: Option<&str>, naf: Option<&str>| AcmeOrder {
+            not_before: nbf.map(date),
+            not_after: naf.map(date),
+            ..Default::default()
+        };
+
+        let requested = order(Some("2024-01-05T14:54:00Z"), Some("2034-01-02T14:53:02Z"));
+        assert!(RustyAcme::verify_leaf_validity(&cert, &requested).is_ok());
+        assert!(RustyAcme::verify_leaf_validity(&cert, &order(None, None)).is_ok());
+
+        for (nbf, naf) in [
+            (Some("2024-01-05T15:00:00Z"), None),
+            (None, Some("2034-01-01T00:00:00Z")),
+        ] {
+            assert!(matches!(
+                RustyAcme::verify_leaf_validity(&cert, &order(nbf, naf)).unwrap_err(),
+                RustyAcmeError::InvalidCertificate(CertificateError::ValidityMismatch)
+            ));
+        }
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_verify_san_contains_identity() {
+        use x509_cert::der::Decode as _;
+
+        let cert = || Certificate::from_der(&der(crate::identity::tests::CERT)).unwrap();
+        let identifier = CanonicalIdentifier {
+            client_id: ClientId::try_from_qualified("obakjPOHQ2CkNb0rOrNM3A:ba54e8ace8b4c90d@wire.com").unwrap(),
+            handle: "wireapp://%40alice_wire@wire.com".parse().unwrap(),
+            display_name: "Alice Smith".to_string(),
+            domain: "wire.com".to_string(),
+        };
+        assert!(RustyAcme::verify_leaf_san(cert(), &identifier).is_ok());
+
+        let other_client_id = CanonicalIdentifier {
+            client_id: AcmeIdentifier::default_client_id(),
+            ..identifier.clone()
+        };
+        assert!(matches!(
+            RustyAcme::verify_leaf_san(cert(), &other_client_id).unwrap_err(),
+            RustyAcmeError::InvalidCertificate(CertificateError::ClientIdMismatch)
+        ));
+
+        let other_handle = CanonicalIdentifier {
+            handle: "wireapp://%40bob_wire@wire.com".parse().unwrap(),
+            ..identifier
+        };
+        assert!(matches!(
+            RustyAcme::verify_leaf_san(cert(), &other_handle).unwrap_err(),
+            RustyAcmeError::InvalidCertificate(CertificateError::HandleMismatch)
+        ));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_verify_public_key_is_the_csr_one() {
+        use x509_cert::der::Decode as _;
+
+        let kp = Pem::from(Ed25519KeyPair::generate().to_pem());
+        let account_jwk = RustyJwk::public_pem_to_jwk(JwsAlgorithm::Ed25519, &kp).unwrap();
+        let chall = AcmeChallenge {
+            typ: AcmeChallengeType::TlsAlpn01,
+            ..AcmeChallenge::new_device()
+        };
+        let cert = chall
+            .tls_alpn01_certificate("example.org", &account_jwk, JwsAlgorithm::Ed25519, &kp)
+            .unwrap();
+        let cert = Certificate::from_der(&cert).unwrap();
+        assert!(RustyAcme::verify_leaf_public_key(&cert, JwsAlgorithm::Ed25519, &kp).is_ok());
+
+        let other_kp = Pem::from(Ed25519KeyPair::generate().to_pem());
+        assert!(matches!(
+            RustyAcme::verify_leaf_public_key(&cert, JwsAlgorithm::Ed25519, &other_kp).unwrap_err(),
+            RustyAcmeError::InvalidCertificate(CertificateError::PublicKeyMismatch)
+        ));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_parse_alternate_links() {
+        let links = [
+            "<https://example.com/acme/directory>;rel=\"index\"",
+            "<https://example.com/acme/cert/mAt3xBGaobw/1>;rel=\"alternate\"",
+        ];
+        let alternates = RustyAcme::certificate_alternates(&links).unwrap();
+        assert_eq!(alternates.len(), 1);
+        assert_eq!(alternates[0].as_str(), "https://example.com/acme/cert/mAt3xBGaobw/1");
+        assert!(RustyAcme::certificate_alternates(&links[..1]).unwrap().is_empty());
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_select_chain_anchored_in_preferred_root() {
+        // default chain issued by the root, alternate one ending with the other root
+        let default = vec![der(LEAF)];
+        let alternate = vec![der(LEAF), der(OTHER_ROOT)];
+        let chains = || vec![default.clone(), alternate.clone()];
+
+        let chain = RustyAcme::select_certificate_chain(chains(), &der(OTHER_ROOT)).unwrap();
+        assert_eq!(chain, alternate);
+        let chain = RustyAcme::select_certificate_chain(chains(), &der(ROOT)).unwrap();
+        assert_eq!(chain, default);
+
+        // not necessarily the first chain
+        let chain = RustyAcme::select_certificate_chain(vec![alternate.clone(), default.clone()], &der(ROOT)).unwrap();
+        assert_eq!(chain, default);
+
+        // falls back to the first chain
+        let chain = RustyAcme::select_certificate_chain(vec![alternate.clone()], &der(ROOT)).unwrap();
+        assert_eq!(chain, alternate);
+
+        assert!(RustyAcme::select_certificate_chain(vec![], &der(ROOT)).is_err());
+    }
 }
@@ -1,4 +1,7 @@
+use base64::Engine;
+use jwt_simple::prelude::*;
 use rusty_jwt_tools::prelude::*;
+use sha2::Digest;
 
 use crate::prelude::*;
 
@@ -9,7 +12,7 @@ impl RustyAcme {
         access_token: String,
         dpop_chall: AcmeChallenge,
         account: &AcmeAccount,
-        alg: JwsAlgorithm,
+        alg: impl Into<AcmeJwsAlgorithm>,
         kp: &Pem,
         previous_nonce: String,
     ) -> RustyAcmeResult<AcmeJws> {
@@ -31,7 +34,7 @@ impl RustyAcme {
         id_token: String,
         oidc_chall: AcmeChallenge,
         account: &AcmeAccount,
-        alg: JwsAlgorithm,
+        alg: impl Into<AcmeJwsAlgorithm>,
         kp: &Pem,
         previous_nonce: String,
     ) -> RustyAcmeResult<AcmeJws> {
@@ -44,9 +47,68 @@ impl RustyAcme {
         Ok(req)
     }
 
+    /// http-01 challenge request to `POST /acme/challenge/{token}`, to call once
+    /// [AcmeChallenge::http01_response_body] is served
+    /// see [RFC 8555 Section 8.3](https://www.rfc-editor.org/rfc/rfc8555.html#section-8.3)
+    pub fn http01_chall_request(
+        http_chall: AcmeChallenge,
+        account: &AcmeAccount,
+        alg: impl Into<AcmeJwsAlgorithm>,
+        kp: &Pem,
+        previous_nonce: String,
+    ) -> RustyAcmeResult<AcmeJws> {
+        http_chall.expect_type(AcmeChallengeType::Http01)?;
+        Self::empty_chall_request(http_chall, account, alg, kp, previous_nonce)
+    }
+
+    /// dns-01 challenge request to `POST /acme/challenge/{token}`, to call once the TXT record
+    /// holding [AcmeChallenge::dns01_txt_value] is provisioned
+    /// see [RFC 8555 Section 8.4](https://www.rfc-editor.org/rfc/rfc8555.html#section-8.4)
+    pub fn dns01_chall_request(
+        dns_chall: AcmeChallenge,
+        account: &AcmeAccount,
+        alg: impl Into<AcmeJwsAlgorithm>,
+        kp: &Pem,
+        previous_nonce: String,
+    ) -> RustyAcmeResult<AcmeJws> {
+        dns_chall.expect_type(AcmeChallengeType::Dns01)?;
+        Self::empty_chall_request(dns_chall, account, alg, kp, previous_nonce)
+    }
+
+    /// tls-alpn-01 challenge request to `POST /acme/challenge/{token}`, to call once
+    /// [AcmeChallenge::tls_alpn01_certificate] is presented
+    /// see [RFC 8737 Section 3](https://www.rfc-editor.org/rfc/rfc8737.html#section-3)
+    pub fn tls_alpn01_chall_request(
+        tls_alpn_chall: AcmeChallenge,
+        account: &AcmeAccount,
+        alg: impl Into<AcmeJwsAlgorithm>,
+        kp: &Pem,
+        previous_nonce: String,
+    ) -> RustyAcmeResult<AcmeJws> {
+        tls_alpn_chall.expect_type(AcmeChallengeType::TlsAlpn01)?;
+        Self::empty_chall_request(tls_alpn_chall, account, alg, kp, previous_nonce)
+    }
+
+    /// Standard challenges are triggered with an empty JSON object
+    /// see [RFC 8555 Section 7.5.1](https://www.rfc-editor.org/rfc/rfc8555.html#section-7.5.1)
+    fn empty_chall_request(
+        chall: AcmeChallenge,
+        account: &AcmeAccount,
+        alg: impl Into<AcmeJwsAlgorithm>,
+        kp: &Pem,
+        previous_nonce: String,
+    ) -> RustyAcmeResult<AcmeJws> {
+        // Extract the account URL from previous response which created a new account
+        let acct_url = account.acct_url()?;
+        let payload = Some(serde_json::json!({}));
+        let req = AcmeJws::new(alg, previous_nonce, &chall.url, Some(&acct_url), payload, kp)?;
+        Ok(req)
+    }
+
     /// 18. parse the response from `POST /acme/challenge/{token}`
     /// [RFC 8555 Section 7.5.1](https://www.rfc-editor.org/rfc/rfc8555.html#section-7.5.1)
     pub fn new_chall_response(response: serde_json::Value) -> RustyAcmeResult<AcmeChallenge> {
+        AcmeProblem::check(&response)?;
         let chall = serde_json::from_value::<AcmeChallenge>(response)?;
         match chall.status {
             Some(AcmeChallengeStatus::Valid) => {}
@@ -76,12 +138,16 @@ pub enum AcmeChallError {
     /// This challenge is being processed, retry later
     #[error("This challenge is being processed, retry later")]
     Processing,
+    /// This challenge is not of the type expected by the helper it was used with
+    #[error("Expected a '{0:?}' challenge")]
+    UnexpectedType(AcmeChallengeType),
 }
 
 /// For creating a challenge
 /// see [RFC 8555 Section 7.5.1](https://www.rfc-editor.org/rfc/rfc8555.html#section-7.5.1)
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct AcmeChallenge {
     #[serde(rename = "type")]
     /// Should be `wire-http-01` or `wire-oidc-01`
@@ -99,6 +165,64 @@ pub struct AcmeChallenge {
     pub target: url::Url,
 }
 
+impl AcmeChallenge {
+    /// Path under which the http-01 challenge response is served
+    pub const HTTP01_PATH: &'static str = "/.well-known/acme-challenge";
+
+    /// Label prepended to the domain name to get the name of the dns-01 TXT record
+    pub const DNS01_LABEL: &'static str = "_acme-challenge";
+
+    /// Token concatenated with the SHA-256 thumbprint of the account key
+    /// see [RFC 8555 Section 8.1](https://www.rfc-editor.org/rfc/rfc8555.html#section-8.1)
+    pub fn key_authorization(&self, account_jwk: &Jwk) -> RustyAcmeResult<String> {
+        self.key_authorization_with_hash_alg(account_jwk, HashAlgorithm::SHA256)
+    }
+
+    /// Same as [Self::key_authorization] but computes the thumbprint with [hash_alg] e.g. to match
+    /// the hash strength of a P-384 account key in the Wire challenges
+    pub fn key_authorization_with_hash_alg(
+        &self,
+        account_jwk: &Jwk,
+        hash_alg: HashAlgorithm,
+    ) -> RustyAcmeResult<String> {
+        let thumbprint = JwkThumbprint::generate(account_jwk, hash_alg)?.kid;
+        Ok(format!("{}.{thumbprint}", self.token))
+    }
+
+    /// Body to serve, as 'application/octet-stream', at [Self::http01_path] of the domain
+    /// see [RFC 8555 Section 8.3](https://www.rfc-editor.org/rfc/rfc8555.html#section-8.3)
+    pub fn http01_response_body(&self, account_jwk: &Jwk) -> RustyAcmeResult<String> {
+        self.expect_type(AcmeChallengeType::Http01)?;
+        self.key_authorization(account_jwk)
+    }
+
+    /// Path of the http-01 challenge response e.g. `/.well-known/acme-challenge/{token}`
+    pub fn http01_path(&self) -> String {
+        format!("{}/{}", Self::HTTP01_PATH, self.token)
+    }
+
+    /// Value of the TXT record to provision under [Self::dns01_record_name]: the base64url
+    /// encoded SHA-256 digest of the key authorization
+    /// see [RFC 8555 Section 8.4](https://www.rfc-editor.org/rfc/rfc8555.html#section-8.4)
+    pub fn dns01_txt_value(&self, account_jwk: &Jwk) -> RustyAcmeResult<String> {
+        self.expect_type(AcmeChallengeType::Dns01)?;
+        let digest = sha2::Sha256::digest(self.key_authorization(account_jwk)?);
+        Ok(base64::prelude::BASE64_URL_SAFE_NO_PAD.encode(digest))
+    }
+
+    /// Name of the dns-01 TXT record for [domain] e.g. `_acme-challenge.example.org`
+    pub fn dns01_record_name(domain: &str) -> String {
+        format!("{}.{}", Self::DNS01_LABEL, domain.trim_end_matches('.'))
+    }
+
+    pub(crate) fn expect_type(&self, typ: AcmeChallengeType) -> RustyAcmeResult<()> {
+        if self.typ != typ {
+            return Err(AcmeChallError::UnexpectedType(typ))?;
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 impl AcmeChallenge {
     pub fn new_device() -> Self {
@@ -131,6 +255,7 @@ impl AcmeChallenge {
 /// see [RFC 8555 Section 7.1.6](https://www.rfc-editor.org/rfc/rfc8555.html#section-7.1.6)
 #[derive(Debug, Copy, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "lowercase")]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum AcmeChallengeStatus {
     Pending,
     Processing,
@@ -139,6 +264,7 @@ pub enum AcmeChallengeStatus {
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum AcmeChallengeType {
     #[serde(rename = "http-01")]
     Http01,
@@ -189,6 +315,102 @@ pub mod tests {
         assert!(serde_json::from_value::<AcmeChallenge>(rfc_sample).is_ok());
     }
 
+    mod domain_validation {
+        use rusty_jwt_tools::jwk::TryIntoJwk;
+
+        use super::*;
+
+        fn chall(typ: AcmeChallengeType) -> AcmeChallenge {
+            AcmeChallenge {
+                typ,
+                token: "LoqXcYV8q5ONbJQxbmR7SCTNo3tiAXDfowyjxAjEuX0".to_string(),
+                ..AcmeChallenge::new_device()
+            }
+        }
+
+        fn account_jwk() -> Jwk {
+            Ed25519KeyPair::generate().public_key().try_into_jwk().unwrap()
+        }
+
+        #[test]
+        #[wasm_bindgen_test]
+        fn should_compute_http01_response_body() {
+            let jwk = account_jwk();
+            let chall = chall(AcmeChallengeType::Http01);
+            let thumbprint = JwkThumbprint::generate(&jwk, HashAlgorithm::SHA256).unwrap().kid;
+            let body = chall.http01_response_body(&jwk).unwrap();
+            assert_eq!(body, format!("{}.{thumbprint}", chall.token));
+            assert_eq!(
+                chall.http01_path(),
+                "/.well-known/acme-challenge/LoqXcYV8q5ONbJQxbmR7SCTNo3tiAXDfowyjxAjEuX0"
+            );
+        }
+
+        #[test]
+        #[wasm_bindgen_test]
+        fn should_compute_key_authorization_with_hash_alg() {
+            let jwk = ES384KeyPair::generate().public_key().try_into_jwk().unwrap();
+            let chall = chall(AcmeChallengeType::WireOidc01);
+            for hash_alg in [HashAlgorithm::SHA256, HashAlgorithm::SHA384, HashAlgorithm::SHA512] {
+                let thumbprint = JwkThumbprint::generate(&jwk, hash_alg).unwrap().kid;
+                let keyauth = chall.key_authorization_with_hash_alg(&jwk, hash_alg).unwrap();
+                assert_eq!(keyauth, format!("{}.{thumbprint}", chall.token));
+            }
+            assert_eq!(
+                chall.key_authorization(&jwk).unwrap(),
+                chall
+                    .key_authorization_with_hash_alg(&jwk, HashAlgorithm::SHA256)
+                    .unwrap()
+            );
+        }
+
+        #[test]
+        #[wasm_bindgen_test]
+        fn should_compute_dns01_txt_value() {
+            let jwk = account_jwk();
+            let chall = chall(AcmeChallengeType::Dns01);
+            let key_authz = chall.key_authorization(&jwk).unwrap();
+            let expected = base64::prelude::BASE64_URL_SAFE_NO_PAD.encode(sha2::Sha256::digest(key_authz));
+            assert_eq!(chall.dns01_txt_value(&jwk).unwrap(), expected);
+            assert_eq!(
+                AcmeChallenge::dns01_record_name("example.org."),
+                "_acme-challenge.example.org"
+            );
+        }
+
+        #[test]
+        #[wasm_bindgen_test]
+        fn should_fail_when_wrong_type() {
+            let jwk = account_jwk();
+            assert!(matches!(
+                chall(AcmeChallengeType::Dns01).http01_response_body(&jwk).unwrap_err(),
+                RustyAcmeError::ChallengeError(AcmeChallError::UnexpectedType(AcmeChallengeType::Http01))
+            ));
+            assert!(matches!(
+                chall(AcmeChallengeType::WireDpop01).dns01_txt_value(&jwk).unwrap_err(),
+                RustyAcmeError::ChallengeError(AcmeChallError::UnexpectedType(AcmeChallengeType::Dns01))
+            ));
+        }
+
+        #[test]
+        #[wasm_bindgen_test]
+        fn should_trigger_with_empty_object() {
+            let kp = Pem::from(Ed25519KeyPair::generate().to_pem());
+            let account = AcmeAccount::default();
+            let chall = chall(AcmeChallengeType::Http01);
+            let req =
+                RustyAcme::http01_chall_request(chall.clone(), &account, JwsAlgorithm::Ed25519, &kp, "nonce".into())
+                    .unwrap();
+            assert_eq!(req.decoded_payload().unwrap(), Some(json!({})));
+
+            let result = RustyAcme::dns01_chall_request(chall, &account, JwsAlgorithm::Ed25519, &kp, "nonce".into());
+            assert!(matches!(
+                result.unwrap_err(),
+                RustyAcmeError::ChallengeError(AcmeChallError::UnexpectedType(AcmeChallengeType::Dns01))
+            ));
+        }
+    }
+
     #[test]
     #[wasm_bindgen_test]
     fn chall_type_should_deserialize_as_expected() {
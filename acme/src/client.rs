@@ -0,0 +1,481 @@
+use rusty_jwt_tools::prelude::*;
+
+use crate::prelude::*;
+
+/// Performs the HTTP calls of the ACME protocol with a [HttpTransport], building the requests
+/// and parsing the responses with [RustyAcme]. It keeps track of the nonces and signs every request
+/// with the account key.
+/// see [RFC 8555 Section 7](https://www.rfc-editor.org/rfc/rfc8555.html#section-7)
+#[derive(Clone)]
+pub struct AcmeClient<T: HttpTransport> {
+    transport: T,
+    directory: AcmeDirectory,
+    alg: AcmeJwsAlgorithm,
+    kp: Pem,
+    /// from the 'Replay-Nonce' header of the last response, fetched from 'newNonce' when none
+    nonce: Option<String>,
+    observer: Option<std::sync::Arc<dyn AcmeObserver>>,
+}
+
+/// [AcmeResponse] along with the headers some steps need
+#[derive(Debug)]
+struct AcmeClientResponse {
+    body: serde_json::Value,
+    /// URL of the created resource e.g. the order
+    location: Option<url::Url>,
+}
+
+#[cfg(feature = "client")]
+impl AcmeClient<reqwest::Client> {
+    /// Fetches the directory with a default 'reqwest' HTTP client
+    ///
+    /// # Arguments
+    /// * `directory_url` - e.g. 'https://acme.wire.com/acme/{provisioner_name}/directory'
+    /// * `alg`/`kp` - account key, signing every request
+    pub async fn try_new(directory_url: &url::Url, alg: impl Into<AcmeJwsAlgorithm>, kp: Pem) -> RustyAcmeResult<Self> {
+        Self::try_new_with_transport(reqwest::Client::new(), directory_url, alg, kp).await
+    }
+
+    /// Same as [AcmeClient::try_new] with a proxy and additional trusted root certificates
+    #[cfg(not(target_family = "wasm"))]
+    pub async fn try_new_with_config(
+        directory_url: &url::Url,
+        alg: impl Into<AcmeJwsAlgorithm>,
+        kp: Pem,
+        config: &HttpClientConfig,
+    ) -> RustyAcmeResult<Self> {
+        Self::try_new_with_transport(config.try_build()?, directory_url, alg, kp).await
+    }
+}
+
+impl<T: HttpTransport> AcmeClient<T> {
+    const CONTENT_TYPE_JOSE: &'static str = "application/jose+json";
+
+    /// Same as [AcmeClient::try_new] with any [HttpTransport] e.g. a configured 'reqwest' client
+    /// with custom root certificates
+    pub async fn try_new_with_transport(
+        transport: T,
+        directory_url: &url::Url,
+        alg: impl Into<AcmeJwsAlgorithm>,
+        kp: Pem,
+    ) -> RustyAcmeResult<Self> {
+        let request = HttpRequest {
+            method: HttpMethod::Get,
+            url: directory_url.clone(),
+            headers: vec![],
+            body: vec![],
+        };
+        let response = Self::send(&transport, request).await?;
+        let directory = RustyAcme::acme_directory_response(Self::parse_body(&response.body))?;
+        Ok(Self {
+            transport,
+            directory,
+            alg: alg.into(),
+            kp,
+            nonce: None,
+            observer: None,
+        })
+    }
+
+    /// Notifies [observer] of every signed request and of its response
+    pub fn with_observer(mut self, observer: impl AcmeObserver + 'static) -> Self {
+        self.observer = Some(std::sync::Arc::new(observer));
+        self
+    }
+
+    pub fn directory(&self) -> &AcmeDirectory {
+        &self.directory
+    }
+
+    /// Resumes a session from a [SessionState] without fetching the directory again
+    ///
+    /// # Arguments
+    /// * `alg`/`kp` - account key the session was started with
+    pub fn resume_with_transport(
+        transport: T,
+        state: &SessionState,
+        alg: impl Into<AcmeJwsAlgorithm>,
+        kp: Pem,
+    ) -> Self {
+        Self {
+            transport,
+            directory: state.directory.clone(),
+            alg: alg.into(),
+            kp,
+            nonce: state.nonce.clone(),
+            observer: None,
+        }
+    }
+
+    /// Snapshot of the directory and of the current nonce. The caller completes it with the
+    /// resources obtained so far before persisting it
+    pub fn session_state(&self) -> SessionState {
+        SessionState {
+            nonce: self.nonce.clone(),
+            ..SessionState::new(self.directory.clone())
+        }
+    }
+
+    /// see [RustyAcme::new_account_request]
+    pub async fn new_account(&mut self, eab: Option<&ExternalAccountBinding>) -> RustyAcmeResult<AcmeAccount> {
+        let url = self.directory.new_account.clone();
+        let directory = self.directory.clone();
+        let response = self
+            .post(&url, |alg, kp, nonce| {
+                RustyAcme::new_account_request(&directory, alg, kp, eab, nonce)
+            })
+            .await?;
+        RustyAcme::new_account_response(response.body)
+    }
+
+    /// see [RustyAcme::new_order_request_with_identifiers]. Also returns the URL of the order, to
+    /// check it with [Self::check_order]
+    pub async fn new_order(
+        &mut self,
+        identifiers: Vec<AcmeIdentifier>,
+        validity: impl Into<AcmeOrderValidity>,
+        extensions: AcmeOrderExtensions,
+        account: &AcmeAccount,
+    ) -> RustyAcmeResult<(AcmeOrder, url::Url)> {
+        let url = self.directory.new_order.clone();
+        let directory = self.directory.clone();
+        let validity = validity.into();
+        let response = self
+            .post(&url, |alg, kp, nonce| {
+                RustyAcme::new_order_request_with_identifiers(
+                    identifiers.clone(),
+                    validity,
+                    extensions.clone(),
+                    &directory,
+                    account,
+                    alg,
+                    kp,
+                    nonce,
+                )
+            })
+            .await?;
+        let order_url = response.location.ok_or(RustyAcmeError::SmallstepImplementationError(
+            "Order created without a 'Location' header",
+        ))?;
+        Ok((RustyAcme::new_order_response(response.body)?, order_url))
+    }
+
+    /// see [RustyAcme::new_authz_request]
+    pub async fn new_authz(&mut self, authz_url: &url::Url, account: &AcmeAccount) -> RustyAcmeResult<AcmeAuthz> {
+        let response = self
+            .post(authz_url, |alg, kp, nonce| {
+                RustyAcme::new_authz_request(authz_url, account, alg, kp, nonce)
+            })
+            .await?;
+        RustyAcme::new_authz_response(response.body)
+    }
+
+    /// see [RustyAcme::dpop_chall_request]
+    pub async fn dpop_chall(
+        &mut self,
+        access_token: String,
+        dpop_chall: AcmeChallenge,
+        account: &AcmeAccount,
+    ) -> RustyAcmeResult<AcmeChallenge> {
+        let url = dpop_chall.url.clone();
+        let response = self
+            .post(&url, |alg, kp, nonce| {
+                RustyAcme::dpop_chall_request(access_token.clone(), dpop_chall.clone(), account, alg, kp, nonce)
+            })
+            .await?;
+        RustyAcme::new_chall_response(response.body)
+    }
+
+    /// see [RustyAcme::oidc_chall_request]
+    pub async fn oidc_chall(
+        &mut self,
+        id_token: String,
+        oidc_chall: AcmeChallenge,
+        account: &AcmeAccount,
+    ) -> RustyAcmeResult<AcmeChallenge> {
+        let url = oidc_chall.url.clone();
+        let response = self
+            .post(&url, |alg, kp, nonce| {
+                RustyAcme::oidc_chall_request(id_token.clone(), oidc_chall.clone(), account, alg, kp, nonce)
+            })
+            .await?;
+        RustyAcme::new_chall_response(response.body)
+    }
+
+    /// see [RustyAcme::check_order_request]
+    pub async fn check_order(&mut self, order_url: &url::Url, account: &AcmeAccount) -> RustyAcmeResult<AcmeOrder> {
+        let response = self
+            .post(order_url, |alg, kp, nonce| {
+                RustyAcme::check_order_request(order_url.clone(), account, alg, kp, nonce)
+            })
+            .await?;
+        RustyAcme::check_order_response(response.body)
+    }
+
+    /// see [RustyAcme::finalize_req]
+    ///
+    /// # Arguments
+    /// * `signing_kp` - client key, signing the CSR
+    pub async fn finalize(
+        &mut self,
+        order: &AcmeOrder,
+        account: &AcmeAccount,
+        signing_kp: &Pem,
+    ) -> RustyAcmeResult<AcmeFinalize> {
+        let response = self
+            .post(&order.finalize, |alg, kp, nonce| {
+                RustyAcme::finalize_req(order, account, alg, kp, signing_kp, nonce)
+            })
+            .await?;
+        RustyAcme::finalize_response(response.body)
+    }
+
+    /// see [RustyAcme::certificate_req] and [RustyAcme::certificate_chain_response]
+    pub async fn certificate(
+        &mut self,
+        finalize: AcmeFinalize,
+        order: AcmeOrder,
+        account: &AcmeAccount,
+    ) -> RustyAcmeResult<CertificateChain> {
+        let response = self
+            .post(&finalize.certificate, |alg, kp, nonce| {
+                RustyAcme::post_as_get(&finalize.certificate, account, alg, kp, nonce)
+            })
+            .await?;
+        let bundle = match response.body {
+            serde_json::Value::String(bundle) => bundle,
+            problem => problem.to_string(),
+        };
+        RustyAcme::certificate_chain_response(bundle, order)
+    }
+
+    /// A nonce from the last response, otherwise a fresh one
+    /// see [RFC 8555 Section 7.2](https://www.rfc-editor.org/rfc/rfc8555.html#section-7.2)
+    async fn nonce(&mut self) -> RustyAcmeResult<String> {
+        if let Some(nonce) = self.nonce.take() {
+            return Ok(nonce);
+        }
+        let request = HttpRequest {
+            method: HttpMethod::Head,
+            url: self.directory.new_nonce.clone(),
+            headers: vec![],
+            body: vec![],
+        };
+        let response = Self::send(&self.transport, request).await?;
+        Self::replay_nonce(&response).ok_or(RustyAcmeError::SmallstepImplementationError(
+            "'newNonce' responded without a 'Replay-Nonce' header",
+        ))
+    }
+
+    /// Signs the request with [sign] and sends it, retrying when the nonce is rejected
+    async fn post(
+        &mut self,
+        url: &url::Url,
+        mut sign: impl FnMut(AcmeJwsAlgorithm, &Pem, String) -> RustyAcmeResult<AcmeJws>,
+    ) -> RustyAcmeResult<AcmeClientResponse> {
+        let nonce = self.nonce().await?;
+        let (alg, kp, transport, observer) = (self.alg, &self.kp, &self.transport, self.observer.as_deref());
+        // 'Mutex' rather than 'RefCell' to keep the futures 'Send'
+        let location = std::sync::Mutex::new(None);
+        let sign_with_key = |nonce: String| sign(alg, kp, nonce);
+        let send = |jws: AcmeJws| {
+            let location = &location;
+            async move {
+                if let Some(observer) = observer {
+                    observer.on_request(url, &jws.decoded_protected()?, jws.decoded_payload()?.as_ref());
+                }
+                let request = HttpRequest {
+                    method: HttpMethod::Post,
+                    url: url.clone(),
+                    headers: vec![("content-type".to_string(), Self::CONTENT_TYPE_JOSE.to_string())],
+                    body: serde_json::to_vec(&jws)?,
+                };
+                let response = Self::send(transport, request).await?;
+                *location.lock().map_err(|_| RustyAcmeError::ImplementationError)? =
+                    response.header("location").and_then(|l| url::Url::parse(l).ok());
+                let replay_nonce = Self::replay_nonce(&response);
+                let body = Self::parse_body(&response.body);
+                if let Some(observer) = observer {
+                    observer.on_response(url, response.status, &body);
+                }
+                RustyAcme::check_rate_limit(response.status, &body, response.header("retry-after"))?;
+                Ok::<_, RustyAcmeError>(AcmeResponse { body, replay_nonce })
+            }
+        };
+        let response = RustyAcme::send_with_nonce_retry_async(nonce, sign_with_key, send).await?;
+        self.nonce = response.replay_nonce;
+        Ok(AcmeClientResponse {
+            body: response.body,
+            location: location.into_inner().map_err(|_| RustyAcmeError::ImplementationError)?,
+        })
+    }
+
+    async fn send(transport: &T, request: HttpRequest) -> RustyAcmeResult<HttpResponse> {
+        transport
+            .send(request)
+            .await
+            .map_err(|e| RustyAcmeError::TransportError(Box::new(e)))
+    }
+
+    fn replay_nonce(response: &HttpResponse) -> Option<String> {
+        response.header("replay-nonce").map(str::to_string)
+    }
+
+    /// JSON body, or a string for the PEM bundle of the certificate
+    fn parse_body(body: &[u8]) -> serde_json::Value {
+        serde_json::from_slice(body)
+            .unwrap_or_else(|_| serde_json::Value::String(String::from_utf8_lossy(body).into_owned()))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use serde_json::json;
+    use wasm_bindgen_test::*;
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    /// For consumers without network access
+    struct Offline;
+
+    impl HttpTransport for Offline {
+        type Error = std::io::Error;
+
+        async fn send(&self, _: HttpRequest) -> Result<HttpResponse, Self::Error> {
+            Err(std::io::Error::other("offline"))
+        }
+    }
+
+    /// Answers with canned responses, in order
+    struct Replay(std::sync::Mutex<Vec<HttpResponse>>);
+
+    impl Replay {
+        fn new(mut responses: Vec<HttpResponse>) -> Self {
+            responses.reverse();
+            Self(std::sync::Mutex::new(responses))
+        }
+    }
+
+    impl HttpTransport for Replay {
+        type Error = std::io::Error;
+
+        async fn send(&self, _: HttpRequest) -> Result<HttpResponse, Self::Error> {
+            self.0
+                .lock()
+                .unwrap()
+                .pop()
+                .ok_or_else(|| std::io::Error::other("no more responses"))
+        }
+    }
+
+    #[derive(Default)]
+    struct Recorder(std::sync::Mutex<Vec<(String, serde_json::Value)>>);
+
+    impl AcmeObserver for std::sync::Arc<Recorder> {
+        fn on_request(&self, url: &url::Url, protected: &serde_json::Value, payload: Option<&serde_json::Value>) {
+            let record = json!({"protected": protected, "payload": payload});
+            self.0.lock().unwrap().push((url.to_string(), record));
+        }
+
+        fn on_response(&self, url: &url::Url, status: u16, body: &serde_json::Value) {
+            let record = json!({"status": status, "body": body});
+            self.0.lock().unwrap().push((url.to_string(), record));
+        }
+    }
+
+    /// The canned transports answer right away, no need for an executor
+    pub(crate) fn block_on<F: core::future::Future>(future: F) -> F::Output {
+        use core::future::Future as _;
+
+        let mut cx = core::task::Context::from_waker(core::task::Waker::noop());
+        match core::pin::pin!(future).poll(&mut cx) {
+            core::task::Poll::Ready(output) => output,
+            core::task::Poll::Pending => panic!("expected the future to complete right away"),
+        }
+    }
+
+    fn response(status: u16, headers: &[(&str, &str)], body: serde_json::Value) -> HttpResponse {
+        HttpResponse {
+            status,
+            headers: headers.iter().map(|(n, v)| (n.to_string(), v.to_string())).collect(),
+            body: serde_json::to_vec(&body).unwrap(),
+        }
+    }
+
+    fn directory() -> HttpResponse {
+        response(
+            200,
+            &[],
+            json!({
+                "newNonce": "https://acme.wire.com/acme/wire/new-nonce",
+                "newAccount": "https://acme.wire.com/acme/wire/new-account",
+                "newOrder": "https://acme.wire.com/acme/wire/new-order",
+                "revokeCert": "https://acme.wire.com/acme/wire/revoke-cert"
+            }),
+        )
+    }
+
+    fn kp() -> Pem {
+        Pem::from(jwt_simple::prelude::Ed25519KeyPair::generate().to_pem())
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_parse_json_and_pem_bodies() {
+        type Client = AcmeClient<Offline>;
+        assert_eq!(Client::parse_body(br#"{"status":"valid"}"#), json!({"status": "valid"}));
+        let pem = "-----BEGIN CERTIFICATE-----\nAAAA\n-----END CERTIFICATE-----\n";
+        assert_eq!(Client::parse_body(pem.as_bytes()), json!(pem));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_surface_transport_errors() {
+        let url = "https://acme.wire.com/acme/wire/directory".parse().unwrap();
+        let client = AcmeClient::try_new_with_transport(Offline, &url, JwsAlgorithm::Ed25519, kp());
+        assert!(matches!(
+            block_on(client).unwrap_err(),
+            RustyAcmeError::TransportError(_)
+        ));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_notify_observer() {
+        let account = json!({"status": "valid", "orders": "https://acme.wire.com/acme/wire/account/abc/orders"});
+        let transport = Replay::new(vec![
+            directory(),
+            response(200, &[("Replay-Nonce", "first")], json!(null)),
+            response(201, &[("Replay-Nonce", "second")], account.clone()),
+        ]);
+        let recorder = std::sync::Arc::new(Recorder::default());
+        let url = "https://acme.wire.com/acme/wire/directory".parse().unwrap();
+        let client = AcmeClient::try_new_with_transport(transport, &url, JwsAlgorithm::Ed25519, kp());
+        let mut client = block_on(client).unwrap().with_observer(recorder.clone());
+        block_on(client.new_account(None)).unwrap();
+
+        let records = recorder.0.lock().unwrap();
+        let [(request_url, request), (response_url, response)] = &records[..] else {
+            panic!("expected a request and its response")
+        };
+        assert_eq!(request_url, "https://acme.wire.com/acme/wire/new-account");
+        assert_eq!(request["protected"]["nonce"], "first");
+        assert_eq!(request["payload"]["termsOfServiceAgreed"], true);
+        assert_eq!(response_url, request_url);
+        assert_eq!(response["status"], 201);
+        assert_eq!(response["body"], account);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_resume_session() {
+        let state = crate::session::tests::state();
+        let client = AcmeClient::resume_with_transport(Offline, &state, JwsAlgorithm::Ed25519, kp());
+        let resumed = client.session_state();
+        assert_eq!(resumed.nonce, state.nonce);
+        assert_eq!(resumed.directory.new_order, state.directory.new_order);
+        assert!(resumed.account.is_none());
+    }
+}
@@ -0,0 +1,215 @@
+use x509_cert::der::{Decode as _, Encode as _};
+
+use crate::prelude::*;
+
+/// Clock skew tolerated on the 'thisUpdate' of a CRL
+const CRL_MAX_SKEW: time::Duration = time::Duration::minutes(5);
+
+impl CertificateChain {
+    /// Enforces revocation without an OCSP responder, against a CRL downloaded from one of the
+    /// [AcmeCertificateInfo::crl_distribution_points] of the leaf certificate. Fails when the CRL
+    /// is not issued by the issuer of the leaf, is not yet valid, is outdated or lists the leaf as
+    /// revoked.
+    /// see [RFC 5280 Section 6.3](https://www.rfc-editor.org/rfc/rfc5280#section-6.3)
+    ///
+    /// # Arguments
+    /// * `crl_der` - DER encoded 'CertificateList'
+    pub fn verify_against_crl(&self, crl_der: &[u8]) -> RustyAcmeResult<()> {
+        let (leaf, issuer) = self.leaf_and_issuer()?.ok_or(AcmeCrlError::MissingIssuer)?;
+
+        let crl = x509_cert::crl::CertificateList::from_der(crl_der)?;
+        let tbs = &crl.tbs_cert_list;
+        if tbs.issuer != leaf.tbs_certificate.issuer {
+            return Err(AcmeCrlError::IssuerMismatch)?;
+        }
+        if !Self::verify_issuer_signature(&issuer, &tbs.to_der()?, crl.signature.raw_bytes())? {
+            return Err(AcmeCrlError::InvalidSignature)?;
+        }
+
+        Self::verify_crl_freshness(tbs, time::OffsetDateTime::now_utc())?;
+
+        let revoked = tbs
+            .revoked_certificates
+            .iter()
+            .flatten()
+            .any(|r| r.serial_number == leaf.tbs_certificate.serial_number);
+        if revoked {
+            return Err(AcmeCrlError::Revoked)?;
+        }
+        Ok(())
+    }
+
+    /// A CRL issued in the future is rejected, tolerating some clock skew, as is one past its
+    /// 'nextUpdate'
+    fn verify_crl_freshness(tbs: &x509_cert::crl::TbsCertList, now: time::OffsetDateTime) -> RustyAcmeResult<()> {
+        let at = |t: &x509_cert::time::Time| time::OffsetDateTime::UNIX_EPOCH + t.to_unix_duration();
+        if at(&tbs.this_update) > now + CRL_MAX_SKEW {
+            return Err(AcmeCrlError::NotYetValid)?;
+        }
+        if let Some(next_update) = &tbs.next_update {
+            if at(next_update) < now {
+                return Err(AcmeCrlError::Outdated)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Error while checking the revocation status of a certificate with a CRL
+#[derive(Debug, thiserror::Error)]
+pub enum AcmeCrlError {
+    /// The certificate chain does not contain the issuer of the leaf certificate
+    #[error("The certificate chain does not contain the issuer of the leaf certificate")]
+    MissingIssuer,
+    /// The CRL is not issued by the issuer of the leaf certificate
+    #[error("The CRL is not issued by the issuer of the leaf certificate")]
+    IssuerMismatch,
+    /// The CRL is not signed by the issuer of the leaf certificate
+    #[error("The CRL is not signed by the issuer of the leaf certificate")]
+    InvalidSignature,
+    /// The CRL is past its next update, a fresh one has to be downloaded
+    #[error("The CRL is past its next update")]
+    Outdated,
+    /// The CRL claims to be issued in the future
+    #[error("The CRL is not yet valid")]
+    NotYetValid,
+    /// The leaf certificate is revoked
+    #[error("The leaf certificate is revoked")]
+    Revoked,
+}
+
+#[cfg(test)]
+pub mod tests {
+    use base64::Engine as _;
+    use wasm_bindgen_test::*;
+
+    use crate::ocsp::tests::ISSUER;
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    const LEAF: &str = r#"-----BEGIN CERTIFICATE-----
+MIIByzCCAXKgAwIBAgICEjQwCgYIKoZIzj0EAwIwLzERMA8GA1UECgwId2lyZS5j
+b20xGjAYBgNVBAMMEVdpcmUgT0NTUCBSb290IENBMCAXDTI2MTAxNjE1MTMwOFoY
+DzIxMjYwOTIyMTUxMzA4WjApMREwDwYDVQQKDAh3aXJlLmNvbTEUMBIGA1UEAwwL
+QWxpY2UgU21pdGgwWTATBgcqhkjOPQIBBggqhkjOPQMBBwNCAAQyrL3a3MktevCV
+7Xq3kUMs2O4bwGJl3TdFbMjIMc8XQplDG1QkYW4bSMbuYudCVqLF9Yk0fGhOTv5p
+78qQH8ejo4GBMH8wLQYDVR0fBCYwJDAioCCgHoYcaHR0cDovL2NybC53aXJlLmNv
+bS9yb290LmNybDAOBgNVHQ8BAf8EBAMCB4AwHQYDVR0OBBYEFKzFNY9Or9gJ9eZ4
+7kRqhZsgeMqqMB8GA1UdIwQYMBaAFGzQ6HmGbUXjY5b2J6X6B2rxwdH/MAoGCCqG
+SM49BAMCA0cAMEQCIGHjjZczxr1Zk4oJ3gFienssNKNRRRfeavpbfJ9OQMVBAiBK
+4lAV/X8ZxvXFj8THWl/tTXUAs5iwrjH8rw0mf1kPKw==
+-----END CERTIFICATE-----"#;
+
+    const REVOKED_LEAF: &str = r#"-----BEGIN CERTIFICATE-----
+MIIByzCCAXCgAwIBAgICVngwCgYIKoZIzj0EAwIwLzERMA8GA1UECgwId2lyZS5j
+b20xGjAYBgNVBAMMEVdpcmUgT0NTUCBSb290IENBMCAXDTI2MTAxNjE1MTMwOFoY
+DzIxMjYwOTIyMTUxMzA4WjAnMREwDwYDVQQKDAh3aXJlLmNvbTESMBAGA1UEAwwJ
+Qm9iIFNtaXRoMFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAEMqy92tzJLXrwle16
+t5FDLNjuG8BiZd03RWzIyDHPF0KZQxtUJGFuG0jG7mLnQlaixfWJNHxoTk7+ae/K
+kB/Ho6OBgTB/MC0GA1UdHwQmMCQwIqAgoB6GHGh0dHA6Ly9jcmwud2lyZS5jb20v
+cm9vdC5jcmwwDgYDVR0PAQH/BAQDAgeAMB0GA1UdDgQWBBSsxTWPTq/YCfXmeO5E
+aoWbIHjKqjAfBgNVHSMEGDAWgBRs0Oh5hm1F42OW9iel+gdq8cHR/zAKBggqhkjO
+PQQDAgNJADBGAiEAwkL9UXn9+VIwWxzFWWJwjTdr38JS0yVegZSopWEdBegCIQDT
+MJg/5UfxQ+gRoknkh8Hrw4hvIFneHx70k1ZG/c7bwQ==
+-----END CERTIFICATE-----"#;
+
+    /// Issued by [ISSUER], revokes [REVOKED_LEAF]
+    const CRL: &str = r#"MIHfMIGHAgEBMAoGCCqGSM49BAMCMC8xETAPBgNVBAoMCHdpcmUuY29tMRowGAYD
+VQQDDBFXaXJlIE9DU1AgUm9vdCBDQRcNMjYxMDE2MTUxMzA4WhgPMjEyNjA5MjIx
+NTEzMDhaMBUwEwICVngXDTI2MTAxNjAwMDAwMFqgDjAMMAoGA1UdFAQDAgEBMAoG
+CCqGSM49BAMCA0cAMEQCIBbhN3xHqzKT0rOn2BwWiqxg7gWGHm3BYku+DSkFpB7g
+AiBQxc1XP2xl4hhNUkEcXqRmimeq++6OlJCCAY7udFHgWw=="#;
+
+    fn chain(leaf: &str) -> CertificateChain {
+        CertificateChain::try_from_pem(&format!("{leaf}\n{ISSUER}")).unwrap()
+    }
+
+    fn crl() -> Vec<u8> {
+        let b64 = CRL.split_whitespace().collect::<String>();
+        base64::prelude::BASE64_STANDARD.decode(b64).unwrap()
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_parse_distribution_points() {
+        let leaf = pem::parse(LEAF).unwrap();
+        let info = AcmeCertificateInfo::try_from_der(leaf.contents()).unwrap();
+        assert_eq!(info.crl_distribution_points, vec!["http://crl.wire.com/root.crl"]);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_accept_unrevoked_certificate() {
+        assert!(chain(LEAF).verify_against_crl(&crl()).is_ok());
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_fail_when_revoked() {
+        assert!(matches!(
+            chain(REVOKED_LEAF).verify_against_crl(&crl()).unwrap_err(),
+            RustyAcmeError::CrlError(AcmeCrlError::Revoked)
+        ));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_fail_when_issued_by_another_ca() {
+        use crate::certificate::tests::{LEAF as OTHER_LEAF, ROOT};
+
+        let chain = CertificateChain::try_from_pem(&format!("{OTHER_LEAF}\n{ROOT}")).unwrap();
+        assert!(matches!(
+            chain.verify_against_crl(&crl()).unwrap_err(),
+            RustyAcmeError::CrlError(AcmeCrlError::IssuerMismatch)
+        ));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_fail_when_not_signed_by_issuer() {
+        let mut crl = crl();
+        let signature = x509_cert::crl::CertificateList::from_der(&crl).unwrap().signature;
+        let signature = signature.raw_bytes();
+        // alter the last byte of the signature, the CRL stays well-formed
+        let position = crl.windows(signature.len()).position(|w| w == signature).unwrap();
+        crl[position + signature.len() - 1] ^= 1;
+
+        assert!(matches!(
+            chain(LEAF).verify_against_crl(&crl).unwrap_err(),
+            RustyAcmeError::CrlError(AcmeCrlError::InvalidSignature)
+        ));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_fail_when_not_fresh() {
+        let crl = x509_cert::crl::CertificateList::from_der(&crl()).unwrap();
+        let tbs = &crl.tbs_cert_list;
+        let at = |t: &x509_cert::time::Time| time::OffsetDateTime::UNIX_EPOCH + t.to_unix_duration();
+        let this_update = at(&tbs.this_update);
+
+        // tolerates some clock skew
+        assert!(CertificateChain::verify_crl_freshness(tbs, this_update - time::Duration::minutes(1)).is_ok());
+        assert!(matches!(
+            CertificateChain::verify_crl_freshness(tbs, this_update - time::Duration::hours(1)).unwrap_err(),
+            RustyAcmeError::CrlError(AcmeCrlError::NotYetValid)
+        ));
+        let next_update = at(tbs.next_update.as_ref().unwrap());
+        assert!(matches!(
+            CertificateChain::verify_crl_freshness(tbs, next_update + time::Duration::seconds(1)).unwrap_err(),
+            RustyAcmeError::CrlError(AcmeCrlError::Outdated)
+        ));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_fail_without_issuer() {
+        let chain = CertificateChain::try_from_pem(LEAF).unwrap();
+        assert!(matches!(
+            chain.verify_against_crl(&crl()).unwrap_err(),
+            RustyAcmeError::CrlError(AcmeCrlError::MissingIssuer)
+        ));
+    }
+}
@@ -5,6 +5,7 @@ impl RustyAcme {
     /// Then pass the response to this method to deserialize it
     /// see [RFC 8555 Section 7.1.1](https://www.rfc-editor.org/rfc/rfc8555.html#section-7.1.1)
     pub fn acme_directory_response(response: serde_json::Value) -> RustyAcmeResult<AcmeDirectory> {
+        AcmeProblem::check(&response)?;
         let directory = serde_json::from_value::<AcmeDirectory>(response)
             .map_err(|_| RustyAcmeError::SmallstepImplementationError("Invalid directory response"))?;
         Ok(directory)
@@ -21,8 +22,50 @@ pub struct AcmeDirectory {
     pub new_account: url::Url,
     /// URL for creating an order
     pub new_order: url::Url,
+    /// URL for pre-authorizing an identifier, absent when the server does not support it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_authz: Option<url::Url>,
     /// URL for revoking a certificate
     pub revoke_cert: url::Url,
+    /// URL for changing the account key, absent when the server does not support it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_change: Option<url::Url>,
+    /// URL for fetching the renewal information of a certificate, absent when the server does not
+    /// support it
+    /// see [draft-ietf-acme-ari](https://datatracker.ietf.org/doc/html/draft-ietf-acme-ari)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub renewal_info: Option<url::Url>,
+    /// Information about the ACME server
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<AcmeDirectoryMeta>,
+}
+
+impl AcmeDirectory {
+    /// Whether accounts have to be bound to an external one, see [ExternalAccountBinding]
+    pub fn external_account_required(&self) -> bool {
+        self.meta
+            .as_ref()
+            .map(|meta| meta.external_account_required)
+            .unwrap_or_default()
+    }
+}
+
+/// See [RFC 8555 Section 7.1.1](https://www.rfc-editor.org/rfc/rfc8555.html#section-7.1.1)
+#[derive(Debug, Clone, Default, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AcmeDirectoryMeta {
+    /// URL of the current terms of service
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub terms_of_service: Option<url::Url>,
+    /// Web page with information about the ACME server
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub website: Option<url::Url>,
+    /// Hostnames the ACME server recognizes as referring to itself in CAA records
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub caa_identities: Vec<String>,
+    /// Whether new accounts have to be bound to an external one
+    #[serde(default)]
+    pub external_account_required: bool,
 }
 
 #[cfg(test)]
@@ -32,6 +75,20 @@ pub mod tests {
 
     wasm_bindgen_test_configure!(run_in_browser);
 
+    /// Directory of the RFC sample, with the optional endpoints only when given
+    pub fn directory(new_authz: Option<&str>, key_change: Option<&str>, renewal_info: Option<&str>) -> AcmeDirectory {
+        serde_json::from_value(serde_json::json!({
+            "newNonce": "https://example.com/acme/new-nonce",
+            "newAccount": "https://example.com/acme/new-account",
+            "newOrder": "https://example.com/acme/new-order",
+            "newAuthz": new_authz,
+            "revokeCert": "https://example.com/acme/revoke-cert",
+            "keyChange": key_change,
+            "renewalInfo": renewal_info,
+        }))
+        .unwrap()
+    }
+
     #[test]
     #[wasm_bindgen_test]
     fn can_deserialize_rfc_sample() {
@@ -49,6 +106,33 @@ pub mod tests {
                 "externalAccountRequired": false
             }
         });
-        assert!(serde_json::from_value::<AcmeDirectory>(rfc_sample).is_ok());
+        let directory = serde_json::from_value::<AcmeDirectory>(rfc_sample).unwrap();
+        let meta = directory.meta.as_ref().unwrap();
+        assert_eq!(
+            meta.terms_of_service.as_ref().unwrap().as_str(),
+            "https://example.com/acme/terms/2017-5-30"
+        );
+        assert_eq!(meta.website.as_ref().unwrap().as_str(), "https://www.example.com/");
+        assert_eq!(meta.caa_identities, vec!["example.com".to_string()]);
+        assert!(!directory.external_account_required());
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_surface_external_account_required() {
+        let mut directory = serde_json::json!({
+            "newNonce": "https://example.com/acme/new-nonce",
+            "newAccount": "https://example.com/acme/new-account",
+            "newOrder": "https://example.com/acme/new-order",
+            "revokeCert": "https://example.com/acme/revoke-cert",
+        });
+        let parsed = RustyAcme::acme_directory_response(directory.clone()).unwrap();
+        assert!(parsed.meta.is_none());
+        assert!(!parsed.external_account_required());
+
+        directory["meta"] = serde_json::json!({"externalAccountRequired": true});
+        let parsed = RustyAcme::acme_directory_response(directory).unwrap();
+        assert!(parsed.external_account_required());
+        assert!(parsed.meta.unwrap().caa_identities.is_empty());
     }
 }
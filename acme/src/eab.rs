@@ -0,0 +1,115 @@
+use base64::Engine;
+use hmac::Mac;
+use jwt_simple::prelude::Jwk;
+
+use rusty_jwt_tools::prelude::*;
+
+use crate::prelude::*;
+
+/// Credentials handed out of band by the ACME server operator to bind a new account to an
+/// account the client has with it
+/// see [RFC 8555 Section 7.3.4](https://www.rfc-editor.org/rfc/rfc8555.html#section-7.3.4)
+#[derive(Clone)]
+pub struct ExternalAccountBinding {
+    /// key identifier
+    pub kid: String,
+    /// base64url encoded HMAC key
+    pub hmac_key: String,
+}
+
+impl std::fmt::Debug for ExternalAccountBinding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExternalAccountBinding")
+            .field("kid", &self.kid)
+            .field("hmac_key", &"***")
+            .finish()
+    }
+}
+
+impl ExternalAccountBinding {
+    /// HMAC algorithm, the only one step-ca supports
+    const ALG: &'static str = "HS256";
+
+    /// JWS over the public account key [kp], MACed with the HMAC key
+    pub(crate) fn sign(&self, url: &url::Url, alg: impl Into<AcmeJwsAlgorithm>, kp: &Pem) -> RustyAcmeResult<AcmeJws> {
+        let jwk: Jwk = alg.into().public_jwk(kp)?;
+        let header = serde_json::json!({
+            "alg": Self::ALG,
+            "kid": self.kid,
+            "url": url,
+        });
+        let b64 = base64::prelude::BASE64_URL_SAFE_NO_PAD;
+        let protected = b64.encode(serde_json::to_vec(&header)?);
+        let payload = b64.encode(serde_json::to_vec(&jwk)?);
+
+        let key = b64
+            .decode(&self.hmac_key)
+            .map_err(|_| RustyAcmeError::ClientImplementationError("the EAB HMAC key is not base64url encoded"))?;
+        let mut mac =
+            hmac::Hmac::<sha2::Sha256>::new_from_slice(&key).map_err(|_| RustyAcmeError::ImplementationError)?;
+        mac.update(format!("{protected}.{payload}").as_bytes());
+        let signature = b64.encode(mac.finalize().into_bytes());
+
+        Ok(AcmeJws {
+            protected,
+            payload,
+            signature,
+        })
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use jwt_simple::prelude::*;
+    use rusty_jwt_tools::jwk::RustyJwk;
+    use serde_json::json;
+    use wasm_bindgen_test::*;
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_mac_account_key() {
+        let hmac_key = [7u8; 32];
+        let eab = ExternalAccountBinding {
+            kid: "eab-kid".to_string(),
+            hmac_key: base64::prelude::BASE64_URL_SAFE_NO_PAD.encode(hmac_key),
+        };
+        let kp = Pem::from(Ed25519KeyPair::generate().to_pem());
+        let url = "https://example.com/acme/new-account".parse().unwrap();
+        let jws = eab.sign(&url, JwsAlgorithm::Ed25519, &kp).unwrap();
+
+        assert_eq!(
+            jws.decoded_protected().unwrap(),
+            json!({"alg": "HS256", "kid": "eab-kid", "url": "https://example.com/acme/new-account"})
+        );
+        let jwk = RustyJwk::private_pem_to_jwk(JwsAlgorithm::Ed25519, &kp).unwrap().public;
+        assert_eq!(jws.decoded_payload().unwrap(), Some(serde_json::to_value(jwk).unwrap()));
+
+        // verifies with the same HMAC key
+        let token = format!("{}.{}.{}", jws.protected, jws.payload, jws.signature);
+        let key = HS256Key::from_bytes(&hmac_key);
+        assert!(key.verify_token::<serde_json::Value>(&token, None).is_ok());
+        assert!(HS256Key::generate()
+            .verify_token::<serde_json::Value>(&token, None)
+            .is_err());
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_fail_when_hmac_key_not_base64() {
+        let eab = ExternalAccountBinding {
+            kid: "eab-kid".to_string(),
+            hmac_key: "not base64!".to_string(),
+        };
+        let kp = Pem::from(Ed25519KeyPair::generate().to_pem());
+        let url = "https://example.com/acme/new-account".parse().unwrap();
+        assert!(matches!(
+            eab.sign(&url, JwsAlgorithm::Ed25519, &kp).unwrap_err(),
+            RustyAcmeError::ClientImplementationError(_)
+        ));
+        assert!(!format!("{eab:?}").contains("not base64"));
+    }
+}
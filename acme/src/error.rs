@@ -34,6 +34,9 @@ pub enum RustyAcmeError {
     /// Error with hand-rolled signature
     #[error(transparent)]
     SignatureError(#[from] signature::Error),
+    /// Error of the [crate::prelude::HttpTransport] of [crate::prelude::AcmeClient]
+    #[error(transparent)]
+    TransportError(Box<dyn std::error::Error + Send + Sync>),
     /// We have done something terribly wrong
     #[error("We have done something terribly wrong and it needs to be fixed")]
     ImplementationError,
@@ -46,6 +49,19 @@ pub enum RustyAcmeError {
     /// Smallstep ACME server is not correctly implemented
     #[error("Incorrect response from ACME server because {0}")]
     SmallstepImplementationError(&'static str),
+    /// The ACME server refused the request
+    #[error("The ACME server refused the request because {0}")]
+    Problem(crate::problem::AcmeProblem),
+    /// The ACME server refused the request because of too many recent ones, either with a
+    /// 'rateLimited' problem or a '429 Too Many Requests' status
+    #[error("The ACME server is rate limiting requests")]
+    RateLimited {
+        /// from the 'Retry-After' header, when known
+        retry_after: Option<core::time::Duration>,
+    },
+    /// The ACME server kept rejecting the nonce of a request
+    #[error("The ACME server kept rejecting the nonce of the request")]
+    BadNonce,
     /// Error while processing an account
     #[error(transparent)]
     AccountError(#[from] crate::account::AcmeAccountError),
@@ -61,6 +77,21 @@ pub enum RustyAcmeError {
     /// Error while finalizing an order
     #[error(transparent)]
     FinalizeError(#[from] crate::finalize::AcmeFinalizeError),
+    /// Error while revoking a certificate
+    #[error(transparent)]
+    RevocationError(#[from] crate::revocation::AcmeRevocationError),
+    /// Error while fetching the renewal information of a certificate
+    #[error(transparent)]
+    RenewalInfoError(#[from] crate::renewal_info::AcmeRenewalInfoError),
+    /// Error while polling a resource
+    #[error(transparent)]
+    PollError(#[from] crate::retry::AcmePollError),
+    /// Error while checking the revocation status of a certificate with OCSP
+    #[error(transparent)]
+    OcspError(#[from] crate::ocsp::AcmeOcspError),
+    /// Error while checking the revocation status of a certificate with a CRL
+    #[error(transparent)]
+    CrlError(#[from] crate::crl::AcmeCrlError),
     /// UTF-8 parsing error
     #[error(transparent)]
     Utf8(#[from] std::str::Utf8Error),
@@ -102,4 +133,19 @@ pub enum CertificateError {
     /// Advertised public key does not match algorithm
     #[error("Advertised public key does not match algorithm")]
     InvalidPublicKey,
+    /// Public key is not the one the CSR was signed with
+    #[error("Public key is not the one the CSR was signed with")]
+    PublicKeyMismatch,
+    /// Certificate chain has no certificate
+    #[error("Certificate chain has no certificate")]
+    EmptyChain,
+    /// A certificate of the chain is not issued by the following one
+    #[error("A certificate of the chain is not issued by the following one")]
+    UnorderedChain,
+    /// Certificate chain does not lead to a trust anchor
+    #[error("Certificate chain does not lead to a trust anchor")]
+    UntrustedChain,
+    /// Validity period is not the one requested in the order
+    #[error("Validity period is not the one requested in the order")]
+    ValidityMismatch,
 }
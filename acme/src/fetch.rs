@@ -0,0 +1,76 @@
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+
+use crate::prelude::*;
+
+/// [HttpTransport] over the Fetch API, for web clients and service workers. The ACME server has to
+/// expose the 'Replay-Nonce', 'Location' and 'Retry-After' headers with
+/// 'Access-Control-Expose-Headers' for them to be readable cross-origin.
+/// see [Fetch API](https://developer.mozilla.org/en-US/docs/Web/API/Fetch_API)
+#[derive(Debug, Copy, Clone, Default)]
+pub struct FetchTransport;
+
+/// Error thrown by the Fetch API e.g. a network or a CORS error, as reported by the browser
+#[derive(Debug, thiserror::Error)]
+#[error("Fetch request failed: {0}")]
+pub struct FetchError(String);
+
+impl From<wasm_bindgen::JsValue> for FetchError {
+    fn from(value: wasm_bindgen::JsValue) -> Self {
+        // 'JsValue' is neither 'Send' nor 'Sync' hence only its description is kept
+        Self(value.as_string().unwrap_or_else(|| format!("{value:?}")))
+    }
+}
+
+impl HttpTransport for FetchTransport {
+    type Error = FetchError;
+
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, Self::Error> {
+        let init = web_sys::RequestInit::new();
+        init.set_method(match request.method {
+            HttpMethod::Get => "GET",
+            HttpMethod::Head => "HEAD",
+            HttpMethod::Post => "POST",
+        });
+        let headers = web_sys::Headers::new()?;
+        for (name, value) in &request.headers {
+            headers.append(name, value)?;
+        }
+        init.set_headers(&headers);
+        if !request.body.is_empty() {
+            init.set_body(&js_sys::Uint8Array::from(&request.body[..]));
+        }
+        let fetch_request = web_sys::Request::new_with_str_and_init(request.url.as_str(), &init)?;
+
+        let response = JsFuture::from(Self::fetch(&fetch_request)?).await?;
+        let response = response.dyn_into::<web_sys::Response>()?;
+
+        let status = response.status();
+        let mut headers = vec![];
+        if let Some(entries) = js_sys::try_iter(&response.headers())? {
+            for entry in entries {
+                let entry = js_sys::Array::from(&entry?);
+                if let (Some(name), Some(value)) = (entry.get(0).as_string(), entry.get(1).as_string()) {
+                    headers.push((name, value));
+                }
+            }
+        }
+        let body = JsFuture::from(response.array_buffer()?).await?;
+        let body = js_sys::Uint8Array::new(&body).to_vec();
+        Ok(HttpResponse { status, headers, body })
+    }
+}
+
+impl FetchTransport {
+    /// 'fetch' of the current global scope, either a window or a worker
+    fn fetch(request: &web_sys::Request) -> Result<js_sys::Promise, FetchError> {
+        let global = js_sys::global();
+        if let Some(window) = global.dyn_ref::<web_sys::Window>() {
+            Ok(window.fetch_with_request(request))
+        } else if let Some(worker) = global.dyn_ref::<web_sys::WorkerGlobalScope>() {
+            Ok(worker.fetch_with_request(request))
+        } else {
+            Err(FetchError("Fetch API is not available in this environment".to_string()))
+        }
+    }
+}
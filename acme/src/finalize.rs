@@ -13,21 +13,48 @@ use crate::{
 
 impl RustyAcme {
     /// see [RFC 8555 Section 7.4](https://www.rfc-editor.org/rfc/rfc8555.html#section-7.4)
+    ///
+    /// # Arguments
+    /// * `acme_alg`/`acme_kp` - account key, signing the request
+    /// * `signing_kp` - client key, signing the CSR with the algorithm of its key type
     pub fn finalize_req(
         order: &AcmeOrder,
         account: &AcmeAccount,
-        alg: JwsAlgorithm,
+        acme_alg: impl Into<AcmeJwsAlgorithm>,
+        acme_kp: &Pem,
+        signing_kp: &Pem,
+        previous_nonce: String,
+    ) -> RustyAcmeResult<AcmeJws> {
+        Self::finalize_req_with_options(
+            order,
+            account,
+            acme_alg,
+            acme_kp,
+            signing_kp,
+            &CsrOptions::default(),
+            previous_nonce,
+        )
+    }
+
+    /// Same as [RustyAcme::finalize_req] but requests, in the CSR, the extensions of [options]
+    /// see [RFC 8555 Section 7.4](https://www.rfc-editor.org/rfc/rfc8555.html#section-7.4)
+    pub fn finalize_req_with_options(
+        order: &AcmeOrder,
+        account: &AcmeAccount,
+        acme_alg: impl Into<AcmeJwsAlgorithm>,
         acme_kp: &Pem,
         signing_kp: &Pem,
+        options: &CsrOptions,
         previous_nonce: String,
     ) -> RustyAcmeResult<AcmeJws> {
         // Extract the account URL from previous response which created a new account
         let acct_url = account.acct_url()?;
         order.verify()?;
-        let csr = Self::generate_csr(alg, order.try_get_coalesce_identifier()?, signing_kp)?;
+        let signing_alg = Self::client_key_alg(signing_kp)?;
+        let csr = Self::generate_csr(signing_alg, order.try_get_coalesce_identifier()?, signing_kp, options)?;
         let payload = AcmeFinalizeRequest { csr };
         let req = AcmeJws::new(
-            alg,
+            acme_alg,
             previous_nonce,
             &order.finalize,
             Some(&acct_url),
@@ -37,13 +64,33 @@ impl RustyAcme {
         Ok(req)
     }
 
-    fn generate_csr(alg: JwsAlgorithm, identifier: CanonicalIdentifier, kp: &Pem) -> RustyAcmeResult<String> {
+    /// Algorithm of the client key pair [kp], from its key type
+    fn client_key_alg(kp: &Pem) -> RustyAcmeResult<JwsAlgorithm> {
+        if Ed25519KeyPair::from_pem(kp.as_str()).is_ok() {
+            Ok(JwsAlgorithm::Ed25519)
+        } else if ES256KeyPair::from_pem(kp.as_str()).is_ok() {
+            Ok(JwsAlgorithm::P256)
+        } else if ES384KeyPair::from_pem(kp.as_str()).is_ok() {
+            Ok(JwsAlgorithm::P384)
+        } else {
+            Err(RustyAcmeError::ClientImplementationError(
+                "the client key is not an Ed25519, P-256 or P-384 key pair",
+            ))
+        }
+    }
+
+    fn generate_csr(
+        alg: JwsAlgorithm,
+        identifier: CanonicalIdentifier,
+        kp: &Pem,
+        options: &CsrOptions,
+    ) -> RustyAcmeResult<String> {
         let algorithm = Self::csr_alg(alg)?;
         let cert_info = x509_cert::request::CertReqInfo {
             version: x509_cert::request::Version::V1,
             subject: Self::csr_subject(&identifier)?,
             public_key: Self::csr_spki(alg, kp)?,
-            attributes: Self::csr_attributes(identifier)?,
+            attributes: Self::csr_attributes(identifier, options)?,
         };
         let signature = Self::csr_signature(alg, kp, &cert_info)?;
 
@@ -57,7 +104,7 @@ impl RustyAcme {
         Ok(csr)
     }
 
-    fn csr_alg(alg: JwsAlgorithm) -> RustyAcmeResult<x509_cert::spki::AlgorithmIdentifierOwned> {
+    pub(crate) fn csr_alg(alg: JwsAlgorithm) -> RustyAcmeResult<x509_cert::spki::AlgorithmIdentifierOwned> {
         let oid = match alg {
             JwsAlgorithm::Ed25519 => oid_registry::OID_SIG_ED25519,
             JwsAlgorithm::P256 => oid_registry::OID_SIG_ECDSA_WITH_SHA256,
@@ -91,7 +138,7 @@ impl RustyAcme {
         Ok(subject)
     }
 
-    fn csr_spki(alg: JwsAlgorithm, kp: &Pem) -> RustyAcmeResult<x509_cert::spki::SubjectPublicKeyInfoOwned> {
+    pub(crate) fn csr_spki(alg: JwsAlgorithm, kp: &Pem) -> RustyAcmeResult<x509_cert::spki::SubjectPublicKeyInfoOwned> {
         let (pk, algorithm) = match alg {
             JwsAlgorithm::Ed25519 => {
                 let pk = Ed25519KeyPair::from_pem(kp.as_str())?.public_key().to_bytes();
@@ -129,34 +176,61 @@ impl RustyAcme {
         })
     }
 
-    // TODO: find a cleaner way to encode this reusing more x509-cert structs
-    fn csr_attributes(identifier: CanonicalIdentifier) -> RustyAcmeResult<x509_cert::attr::Attributes> {
-        fn gn(n: impl AsRef<str>) -> RustyAcmeResult<x509_cert::ext::pkix::name::GeneralName> {
-            let ia5_str = x509_cert::der::asn1::Ia5String::new(n.as_ref())?;
-            Ok(x509_cert::ext::pkix::name::GeneralName::UniformResourceIdentifier(
-                ia5_str,
-            ))
+    /// Extension request attribute, see [RFC 2985 Section 5.4.2](https://www.rfc-editor.org/rfc/rfc2985#section-5.4.2)
+    fn csr_attributes(
+        identifier: CanonicalIdentifier,
+        options: &CsrOptions,
+    ) -> RustyAcmeResult<x509_cert::attr::Attributes> {
+        let extension = |oid: oid_registry::Oid, value: Vec<u8>| -> RustyAcmeResult<x509_cert::ext::Extension> {
+            Ok(x509_cert::ext::Extension {
+                extn_id: oid.as_bytes().try_into()?,
+                critical: false,
+                extn_value: x509_cert::der::asn1::OctetString::new(value)?,
+            })
+        };
+
+        let mut san = vec![
+            CsrSubjectAltName::Uri(identifier.client_id.to_uri()),
+            CsrSubjectAltName::Uri(identifier.handle.to_string()),
+        ];
+        san.extend(options.additional_sans.iter().cloned());
+        let san = san
+            .into_iter()
+            .map(x509_cert::ext::pkix::name::GeneralName::try_from)
+            .collect::<RustyAcmeResult<Vec<_>>>()?;
+        let san = x509_cert::ext::pkix::SubjectAltName(san).to_der()?;
+        let mut extensions = vec![extension(oid_registry::OID_X509_EXT_SUBJECT_ALT_NAME, san)?];
+
+        if !options.key_usages.is_empty() {
+            let key_usage = CsrKeyUsage::to_bit_string(&options.key_usages)?.to_der()?;
+            extensions.push(extension(oid_registry::OID_X509_EXT_KEY_USAGE, key_usage)?);
         }
-        let san =
-            x509_cert::ext::pkix::SubjectAltName(vec![gn(identifier.client_id)?, gn(identifier.handle.as_str())?]);
-        let san = x509_cert::attr::AttributeValue::new(x509_cert::der::Tag::OctetString, san.to_der()?)?;
 
-        let san_oid = oid_registry::OID_X509_EXT_SUBJECT_ALT_NAME.to_der_vec()?;
-        let san = [san_oid, san.to_der()?].concat();
-        let san = x509_cert::attr::AttributeValue::new(x509_cert::der::Tag::Sequence, san)?;
-        let san = x509_cert::attr::AttributeValue::new(x509_cert::der::Tag::Sequence, san.to_der()?)?;
+        if !options.extended_key_usages.is_empty() {
+            let usages = options
+                .extended_key_usages
+                .iter()
+                .map(CsrExtendedKeyUsage::oid)
+                .collect();
+            let extended_key_usage = x509_cert::ext::pkix::ExtendedKeyUsage(usages).to_der()?;
+            extensions.push(extension(
+                oid_registry::OID_X509_EXT_EXTENDED_KEY_USAGE,
+                extended_key_usage,
+            )?);
+        }
 
         let attributes = vec![x509_cert::attr::Attribute {
             oid: oid_registry::OID_PKCS9_EXTENSION_REQUEST.as_bytes().try_into()?,
-            values: vec![san].try_into()?,
+            values: vec![x509_cert::der::Any::encode_from(&extensions)?].try_into()?,
         }];
         Ok(attributes.try_into()?)
     }
 
-    fn csr_signature(
+    /// Signs the DER encoding of [cert_info], a CSR or a certificate
+    pub(crate) fn csr_signature(
         alg: JwsAlgorithm,
         kp: &Pem,
-        cert_info: &x509_cert::request::CertReqInfo,
+        cert_info: &impl Encode,
     ) -> RustyAcmeResult<x509_cert::der::asn1::BitString> {
         use signature::Signer as _;
         let cert_data = cert_info.to_der()?;
@@ -198,11 +272,91 @@ impl RustyAcme {
 
     /// see [RFC 8555 Section 7.4](https://www.rfc-editor.org/rfc/rfc8555.html#section-7.4)
     pub fn finalize_response(response: serde_json::Value) -> RustyAcmeResult<AcmeFinalize> {
+        AcmeProblem::check(&response)?;
         let finalize = serde_json::from_value::<AcmeFinalize>(response)?;
         Ok(finalize)
     }
 }
 
+/// Extensions to request in the CSR built by [RustyAcme::finalize_req_with_options], on top of
+/// the Subject Alternative Name holding the ClientId and the handle. The ACME server might not
+/// honor them.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct CsrOptions {
+    /// Key Usage extension, omitted when empty
+    pub key_usages: Vec<CsrKeyUsage>,
+    /// Extended Key Usage extension, omitted when empty
+    pub extended_key_usages: Vec<CsrExtendedKeyUsage>,
+    /// Appended to the Subject Alternative Name
+    pub additional_sans: Vec<CsrSubjectAltName>,
+}
+
+/// see [RFC 5280 Section 4.2.1.3](https://www.rfc-editor.org/rfc/rfc5280#section-4.2.1.3)
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum CsrKeyUsage {
+    DigitalSignature,
+    NonRepudiation,
+    KeyEncipherment,
+    DataEncipherment,
+    KeyAgreement,
+}
+
+impl CsrKeyUsage {
+    /// Position in the 'KeyUsage' bit string
+    fn bit(&self) -> u8 {
+        match self {
+            Self::DigitalSignature => 0,
+            Self::NonRepudiation => 1,
+            Self::KeyEncipherment => 2,
+            Self::DataEncipherment => 3,
+            Self::KeyAgreement => 4,
+        }
+    }
+
+    /// DER requires trailing zero bits of a named bit list to be removed
+    fn to_bit_string(usages: &[Self]) -> RustyAcmeResult<x509_cert::der::asn1::BitString> {
+        let bits = usages.iter().fold(0u8, |acc, u| acc | (0x80 >> u.bit()));
+        let unused_bits = bits.trailing_zeros() as u8;
+        Ok(x509_cert::der::asn1::BitString::new(unused_bits, [bits])?)
+    }
+}
+
+/// see [RFC 5280 Section 4.2.1.12](https://www.rfc-editor.org/rfc/rfc5280#section-4.2.1.12)
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum CsrExtendedKeyUsage {
+    /// TLS client authentication
+    ClientAuth,
+    /// TLS server authentication
+    ServerAuth,
+}
+
+impl CsrExtendedKeyUsage {
+    fn oid(&self) -> x509_cert::der::oid::ObjectIdentifier {
+        match self {
+            Self::ClientAuth => x509_cert::der::oid::ObjectIdentifier::new_unwrap("1.3.6.1.5.5.7.3.2"),
+            Self::ServerAuth => x509_cert::der::oid::ObjectIdentifier::new_unwrap("1.3.6.1.5.5.7.3.1"),
+        }
+    }
+}
+
+/// Entry of the Subject Alternative Name
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum CsrSubjectAltName {
+    Uri(String),
+    DnsName(String),
+}
+
+impl TryFrom<CsrSubjectAltName> for x509_cert::ext::pkix::name::GeneralName {
+    type Error = RustyAcmeError;
+
+    fn try_from(name: CsrSubjectAltName) -> RustyAcmeResult<Self> {
+        Ok(match name {
+            CsrSubjectAltName::Uri(uri) => Self::UniformResourceIdentifier(x509_cert::der::asn1::Ia5String::new(&uri)?),
+            CsrSubjectAltName::DnsName(dns) => Self::DnsName(x509_cert::der::asn1::Ia5String::new(&dns)?),
+        })
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 #[error(transparent)]
 pub struct AcmeFinalizeError(#[from] AcmeOrderError);
@@ -215,8 +369,7 @@ pub struct AcmeFinalizeRequest {
     csr: String,
 }
 
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
-#[cfg_attr(test, derive(Clone))]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AcmeFinalize {
     pub certificate: url::Url,
@@ -294,6 +447,95 @@ pub mod tests {
         }
     }
 
+    mod csr {
+        use x509_cert::der::Decode as _;
+
+        use super::*;
+
+        fn csr_extensions(options: &CsrOptions) -> Vec<x509_cert::ext::Extension> {
+            let kp = Pem::from(Ed25519KeyPair::generate().to_pem());
+            let identifier = AcmeOrder::default().try_get_coalesce_identifier().unwrap();
+            let csr = RustyAcme::generate_csr(JwsAlgorithm::Ed25519, identifier, &kp, options).unwrap();
+            let csr = base64::prelude::BASE64_URL_SAFE_NO_PAD.decode(csr).unwrap();
+            let csr = x509_cert::request::CertReq::from_der(&csr).unwrap();
+            let attribute = csr.info.attributes.iter().next().unwrap();
+            assert_eq!(
+                attribute.oid.as_bytes(),
+                oid_registry::OID_PKCS9_EXTENSION_REQUEST.as_bytes()
+            );
+            attribute.values.iter().next().unwrap().decode_as().unwrap()
+        }
+
+        fn find<'a>(extensions: &'a [x509_cert::ext::Extension], oid: oid_registry::Oid) -> Option<&'a [u8]> {
+            extensions
+                .iter()
+                .find(|e| e.extn_id.as_bytes() == oid.as_bytes())
+                .map(|e| e.extn_value.as_bytes())
+        }
+
+        #[test]
+        #[wasm_bindgen_test]
+        fn should_derive_csr_algorithm_from_client_key() {
+            for (kp, alg) in [
+                (Ed25519KeyPair::generate().to_pem(), JwsAlgorithm::Ed25519),
+                (ES256KeyPair::generate().to_pem().unwrap(), JwsAlgorithm::P256),
+                (ES384KeyPair::generate().to_pem().unwrap(), JwsAlgorithm::P384),
+            ] {
+                assert_eq!(RustyAcme::client_key_alg(&kp.into()).unwrap(), alg);
+            }
+            let result = RustyAcme::client_key_alg(&Pem::from("not a key"));
+            assert!(matches!(
+                result.unwrap_err(),
+                RustyAcmeError::ClientImplementationError(_)
+            ));
+        }
+
+        #[test]
+        #[wasm_bindgen_test]
+        fn should_only_request_identity_by_default() {
+            let extensions = csr_extensions(&CsrOptions::default());
+            assert_eq!(extensions.len(), 1);
+            let san = find(&extensions, oid_registry::OID_X509_EXT_SUBJECT_ALT_NAME).unwrap();
+            let san = x509_cert::ext::pkix::SubjectAltName::from_der(san).unwrap();
+            assert_eq!(san.0.len(), 2);
+        }
+
+        #[test]
+        #[wasm_bindgen_test]
+        fn should_request_options() {
+            let options = CsrOptions {
+                key_usages: vec![CsrKeyUsage::DigitalSignature, CsrKeyUsage::KeyAgreement],
+                extended_key_usages: vec![CsrExtendedKeyUsage::ClientAuth],
+                additional_sans: vec![
+                    CsrSubjectAltName::Uri("wireapp://extra@wire.com".to_string()),
+                    CsrSubjectAltName::DnsName("wire.com".to_string()),
+                ],
+            };
+            let extensions = csr_extensions(&options);
+            assert_eq!(extensions.len(), 3);
+
+            let san = find(&extensions, oid_registry::OID_X509_EXT_SUBJECT_ALT_NAME).unwrap();
+            let san = x509_cert::ext::pkix::SubjectAltName::from_der(san).unwrap();
+            assert!(matches!(
+                &san.0[..],
+                [
+                    _,
+                    _,
+                    x509_cert::ext::pkix::name::GeneralName::UniformResourceIdentifier(uri),
+                    x509_cert::ext::pkix::name::GeneralName::DnsName(dns),
+                ] if uri.as_str() == "wireapp://extra@wire.com" && dns.as_str() == "wire.com"
+            ));
+
+            // digitalSignature (bit 0) and keyAgreement (bit 4), without the 3 trailing zero bits
+            let key_usage = find(&extensions, oid_registry::OID_X509_EXT_KEY_USAGE).unwrap();
+            assert_eq!(key_usage, [0x03, 0x02, 0x03, 0x88]);
+
+            let eku = find(&extensions, oid_registry::OID_X509_EXT_EXTENDED_KEY_USAGE).unwrap();
+            let eku = x509_cert::ext::pkix::ExtendedKeyUsage::from_der(eku).unwrap();
+            assert_eq!(eku.0, vec![CsrExtendedKeyUsage::ClientAuth.oid()]);
+        }
+    }
+
     mod verify {
         use super::*;
 
@@ -1,9 +1,10 @@
-use crate::prelude::*;
+use crate::{authz::AcmeAuthzError, prelude::*};
 use rusty_jwt_tools::prelude::*;
 
 /// Represent an identifier in an ACME Order
 #[derive(Debug, Clone, Eq, PartialEq, Hash, serde::Serialize, serde::Deserialize)]
 #[serde(tag = "type", content = "value", rename_all = "kebab-case")]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum AcmeIdentifier {
     WireappUser(String),
     WireappDevice(String),
@@ -16,26 +17,23 @@ impl AcmeIdentifier {
         display_name: String,
         domain: String,
     ) -> RustyAcmeResult<Self> {
-        let client_id = client_id.to_uri();
-        let identifier = WireIdentifier {
+        WireIdentifier {
             display_name,
             handle,
             domain,
             client_id: Some(client_id),
-        };
-        let identifier = serde_json::to_string(&identifier)?;
-        Ok(Self::WireappDevice(identifier))
+        }
+        .try_into()
     }
 
     pub fn try_new_user(handle: QualifiedHandle, display_name: String, domain: String) -> RustyAcmeResult<Self> {
-        let identifier = WireIdentifier {
+        WireIdentifier {
             display_name,
             handle,
             domain,
             client_id: None,
-        };
-        let identifier = serde_json::to_string(&identifier)?;
-        Ok(Self::WireappUser(identifier))
+        }
+        .try_into()
     }
 
     pub fn to_wire_identifier(&self) -> RustyAcmeResult<WireIdentifier> {
@@ -56,7 +54,7 @@ impl AcmeIdentifier {
 impl AcmeIdentifier {
     pub fn new_device() -> Self {
         Self::try_new_device(
-            ClientId::default(),
+            Self::default_client_id(),
             QualifiedHandle::default(),
             "Alice Smith".to_string(),
             "wire.com".to_string(),
@@ -72,14 +70,24 @@ impl AcmeIdentifier {
         )
         .unwrap()
     }
+
+    /// The default ClientId on the same domain as the identifiers
+    pub fn default_client_id() -> ClientId {
+        ClientId::try_new(ClientId::DEFAULT_USER.to_string(), 1223, "wire.com").unwrap()
+    }
 }
 
-/// Structure of the ACME identifier
+/// Structure of the ACME identifier, serialized as JSON in its value
 #[derive(Clone, Debug, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct WireIdentifier {
-    /// Wire ClientId. Absent on [AcmeIdentifier::WireappUser]
-    #[serde(rename = "client-id", skip_serializing_if = "Option::is_none")]
-    pub client_id: Option<String>,
+    /// Wire ClientId, as a URI in the value. Absent on [AcmeIdentifier::WireappUser]
+    #[serde(
+        rename = "client-id",
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "client_id_uri"
+    )]
+    pub client_id: Option<ClientId>,
     /// Wire client handle
     #[serde(rename = "handle")]
     pub handle: QualifiedHandle,
@@ -91,10 +99,74 @@ pub struct WireIdentifier {
     pub domain: String,
 }
 
+impl WireIdentifier {
+    /// Fails when this identifier does not designate the client [client_id] of the user with the
+    /// given [handle] and [display_name]. The ClientId is only verified on a device identifier.
+    /// Display names are compared as [DisplayName]s.
+    pub fn verify(&self, client_id: &ClientId, handle: &QualifiedHandle, display_name: &str) -> RustyAcmeResult<()> {
+        if matches!(&self.client_id, Some(c) if c != client_id) {
+            return Err(AcmeAuthzError::IdentifierMismatch("client-id"))?;
+        }
+        if &self.handle != handle {
+            return Err(AcmeAuthzError::IdentifierMismatch("handle"))?;
+        }
+        if DisplayName::from(self.display_name.as_str()) != DisplayName::from(display_name) {
+            return Err(AcmeAuthzError::IdentifierMismatch("name"))?;
+        }
+        if !domain::eq(&self.domain, &client_id.domain) {
+            return Err(AcmeAuthzError::IdentifierMismatch("domain"))?;
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<&AcmeIdentifier> for WireIdentifier {
+    type Error = RustyAcmeError;
+
+    fn try_from(identifier: &AcmeIdentifier) -> RustyAcmeResult<Self> {
+        identifier.to_wire_identifier()
+    }
+}
+
+/// A device identifier when it has a ClientId, a user one otherwise
+impl TryFrom<WireIdentifier> for AcmeIdentifier {
+    type Error = RustyAcmeError;
+
+    fn try_from(identifier: WireIdentifier) -> RustyAcmeResult<Self> {
+        let is_device = identifier.client_id.is_some();
+        let value = serde_json::to_string(&identifier)?;
+        Ok(if is_device {
+            Self::WireappDevice(value)
+        } else {
+            Self::WireappUser(value)
+        })
+    }
+}
+
+/// The ClientId is serialized as a URI in the identifier value but parsed from any of its formats
+mod client_id_uri {
+    use rusty_jwt_tools::prelude::ClientId;
+    use serde::{de::Error as _, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(client_id: &Option<ClientId>, serializer: S) -> Result<S::Ok, S::Error> {
+        match client_id {
+            Some(client_id) => serializer.serialize_some(&client_id.to_uri()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<ClientId>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|client_id| ClientId::parse_any(&client_id).map(|(client_id, _)| client_id))
+            .transpose()
+            .map_err(D::Error::custom)
+    }
+}
+
 /// Internal view of 2 merged [WireIdentifier], one of type [AcmeIdentifier::WireappUser] and one of [AcmeIdentifier::WireappDevice]
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct CanonicalIdentifier {
-    pub client_id: String,
+    pub client_id: ClientId,
     pub handle: QualifiedHandle,
     pub display_name: String,
     pub domain: String,
@@ -112,3 +184,91 @@ impl TryFrom<WireIdentifier> for CanonicalIdentifier {
         })
     }
 }
+
+#[cfg(test)]
+pub mod tests {
+    use serde_json::json;
+    use wasm_bindgen_test::*;
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_roundtrip_value() {
+        let device = AcmeIdentifier::new_device();
+        let identifier = device.to_wire_identifier().unwrap();
+        assert_eq!(identifier.client_id, Some(AcmeIdentifier::default_client_id()));
+        assert_eq!(AcmeIdentifier::try_from(identifier).unwrap(), device);
+
+        let user = AcmeIdentifier::new_user();
+        let identifier = WireIdentifier::try_from(&user).unwrap();
+        assert!(identifier.client_id.is_none());
+        assert_eq!(AcmeIdentifier::try_from(identifier).unwrap(), user);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_parse_value() {
+        let client_id = ClientId::default();
+        let value = json!({
+            "client-id": client_id.to_uri(),
+            "handle": QualifiedHandle::default(),
+            "name": "Alice Smith",
+            "domain": "wire.com",
+        });
+        let identifier = json!({"type": "wireapp-device", "value": value.to_string()});
+        let identifier = serde_json::from_value::<AcmeIdentifier>(identifier).unwrap();
+        let identifier = identifier.to_wire_identifier().unwrap();
+        assert_eq!(identifier.client_id, Some(client_id));
+        assert_eq!(identifier.display_name, "Alice Smith");
+
+        let value =
+            json!({"client-id": "not-a-client-id", "handle": QualifiedHandle::default(), "name": "", "domain": ""});
+        assert!(serde_json::from_value::<WireIdentifier>(value).is_err());
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_verify_identity() {
+        let (client_id, handle) = (AcmeIdentifier::default_client_id(), QualifiedHandle::default());
+        let (alice, wire) = ("Alice Smith".to_string(), "wire.com".to_string());
+        let device =
+            AcmeIdentifier::try_new_device(client_id.clone(), handle.clone(), alice.clone(), wire.clone()).unwrap();
+        let device = device.to_wire_identifier().unwrap();
+        assert!(device.verify(&client_id, &handle, &alice).is_ok());
+
+        let user = AcmeIdentifier::try_new_user(handle.clone(), alice.clone(), wire.clone()).unwrap();
+        let user = user.to_wire_identifier().unwrap();
+        assert!(user.verify(&client_id, &handle, &alice).is_ok());
+
+        let mismatch = |result: RustyAcmeResult<()>| match result.unwrap_err() {
+            RustyAcmeError::AuthzError(AcmeAuthzError::IdentifierMismatch(field)) => field,
+            e => panic!("unexpected error {e:?}"),
+        };
+        let other = ClientId {
+            device_id: client_id.device_id + 1,
+            ..client_id.clone()
+        };
+        assert_eq!(mismatch(device.verify(&other, &handle, &alice)), "client-id");
+        // a user identifier is not bound to a device
+        assert!(user.verify(&other, &handle, &alice).is_ok());
+        assert_eq!(mismatch(device.verify(&client_id, &handle, "Bob")), "name");
+        // visually identical names
+        assert!(device.verify(&client_id, &handle, "Alice  Smith").is_ok());
+        let other_domain = ClientId {
+            domain: "other.com".to_string(),
+            ..client_id.clone()
+        };
+        assert_eq!(mismatch(user.verify(&other_domain, &handle, &alice)), "domain");
+        // same domain in its ASCII form
+        let idn = AcmeIdentifier::try_new_user(handle.clone(), alice.clone(), "bücher.example".to_string()).unwrap();
+        let idn = idn.to_wire_identifier().unwrap();
+        let ascii_domain = ClientId {
+            domain: "xn--bcher-kva.example".to_string(),
+            ..client_id.clone()
+        };
+        assert!(idn.verify(&ascii_domain, &handle, &alice).is_ok());
+    }
+}
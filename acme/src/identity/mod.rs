@@ -6,7 +6,7 @@ use rusty_x509_check::IdentityStatus;
 use crate::error::CertificateError;
 use crate::prelude::*;
 
-mod thumbprint;
+pub(crate) mod thumbprint;
 
 #[derive(Debug, Clone)]
 pub struct WireIdentity {
@@ -20,8 +20,15 @@ pub struct WireIdentity {
 
 pub trait WireIdentityReader {
     /// Verifies a proof of identity, may it be a x509 certificate (or a Verifiable Presentation (later)).
-    /// We do not verify anything else e.g. expiry, it is left to MLS implementation
-    fn extract_identity(&self) -> RustyAcmeResult<WireIdentity>;
+    /// We do not verify anything else e.g. expiry, it is left to MLS implementation.
+    /// The public key thumbprint is computed with the hash matching the key e.g. SHA-384 for P-384
+    fn extract_identity(&self) -> RustyAcmeResult<WireIdentity> {
+        self.extract_identity_with_hash_alg(HashAlgorithm::SHA256)
+    }
+
+    /// Same as [WireIdentityReader::extract_identity] but computes the public key thumbprint with
+    /// [hash_alg] e.g. to match the hash strength of a P-384 key
+    fn extract_identity_with_hash_alg(&self, hash_alg: HashAlgorithm) -> RustyAcmeResult<WireIdentity>;
 
     /// returns the 'Not Before' claim which usually matches the creation timestamp
     fn extract_created_at(&self) -> RustyAcmeResult<u64>;
@@ -32,10 +39,15 @@ pub trait WireIdentityReader {
 
 impl WireIdentityReader for x509_cert::Certificate {
     fn extract_identity(&self) -> RustyAcmeResult<WireIdentity> {
+        let alg = thumbprint::try_spki_alg(&self.tbs_certificate.subject_public_key_info)?;
+        self.extract_identity_with_hash_alg(HashAlgorithm::from(alg))
+    }
+
+    fn extract_identity_with_hash_alg(&self, hash_alg: HashAlgorithm) -> RustyAcmeResult<WireIdentity> {
         let (client_id, handle) = try_extract_san(&self.tbs_certificate)?;
         let (display_name, domain) = try_extract_subject(&self.tbs_certificate)?;
         let status = IdentityStatus::from_cert(self);
-        let thumbprint = thumbprint::try_compute_jwk_canonicalized_thumbprint(&self.tbs_certificate)?;
+        let thumbprint = thumbprint::try_compute_jwk_canonicalized_thumbprint(&self.tbs_certificate, hash_alg)?;
 
         Ok(WireIdentity {
             client_id,
@@ -66,6 +78,10 @@ impl WireIdentityReader for &[u8] {
         x509_cert::Certificate::from_der(self)?.extract_identity()
     }
 
+    fn extract_identity_with_hash_alg(&self, hash_alg: HashAlgorithm) -> RustyAcmeResult<WireIdentity> {
+        x509_cert::Certificate::from_der(self)?.extract_identity_with_hash_alg(hash_alg)
+    }
+
     fn extract_created_at(&self) -> RustyAcmeResult<u64> {
         x509_cert::Certificate::from_der(self)?.extract_created_at()
     }
@@ -80,6 +96,10 @@ impl WireIdentityReader for Vec<u8> {
         self.as_slice().extract_identity()
     }
 
+    fn extract_identity_with_hash_alg(&self, hash_alg: HashAlgorithm) -> RustyAcmeResult<WireIdentity> {
+        self.as_slice().extract_identity_with_hash_alg(hash_alg)
+    }
+
     fn extract_created_at(&self) -> RustyAcmeResult<u64> {
         self.as_slice().extract_created_at()
     }
@@ -153,7 +173,7 @@ pub mod tests {
 
     wasm_bindgen_test_configure!(run_in_browser);
 
-    const CERT: &str = r#"-----BEGIN CERTIFICATE-----
+    pub const CERT: &str = r#"-----BEGIN CERTIFICATE-----
 MIICGjCCAcCgAwIBAgIRAJaZdl+hZDl9qSSju5kmWNAwCgYIKoZIzj0EAwIwLjEN
 MAsGA1UEChMEd2lyZTEdMBsGA1UEAxMUd2lyZSBJbnRlcm1lZGlhdGUgQ0EwHhcN
 MjQwMTA1MTQ1MzAyWhcNMzQwMTAyMTQ1MzAyWjApMREwDwYDVQQKEwh3aXJlLmNv
@@ -181,6 +201,23 @@ Ni1Dd2tmQWljUnpnJTIxNWNkNGViYjFmNzU0ODA5ZUB3aXJlLmNvbTAdBgwrBgEE
 AYKkZMYoQAEEDTALAgEGBAR3aXJlBAAwCgYIKoZIzj0EAwIDSAAwRQIgfwfd5vXm
 EoOKgYLyKNa24aewZZObydD+k0hFs4iKddICIQDf70uv+h0tHw/WNf15mZ8NGkJm
 OfqfZA1YMtN5NLz/AA==
+-----END CERTIFICATE-----"#;
+
+    /// Self-signed, with a P-384 key
+    const CERT_P384: &str = r#"-----BEGIN CERTIFICATE-----
+MIICRzCCAcygAwIBAgIURGpT93qT33bakzv0BfKzw+n5lyowCgYIKoZIzj0EAwMw
+KTERMA8GA1UECgwId2lyZS5jb20xFDASBgNVBAMMC0FsaWNlIFNtaXRoMCAXDTI2
+MTAxNjE1MDcyMVoYDzIxMjYwOTIyMTUwNzIxWjApMREwDwYDVQQKDAh3aXJlLmNv
+bTEUMBIGA1UEAwwLQWxpY2UgU21pdGgwdjAQBgcqhkjOPQIBBgUrgQQAIgNiAAQM
+lgFiG4Gt+2x8Y7d6od89Ksc7Q3T+fxff4AGW3buBMmzFM4yOGCfBnHEyvAOc/Ftf
+PL0EumIMuP7pQHM6wNd8AnCuwh6QrFCXFyOQ3Swf5QgzPo+3FGY7bxDW451PvlCj
+gbIwga8wDgYDVR0PAQH/BAQDAgeAMBMGA1UdJQQMMAoGCCsGAQUFBwMCMGkGA1Ud
+EQRiMGCGIHdpcmVhcHA6Ly8lNDBhbGljZV93aXJlQHdpcmUuY29thjx3aXJlYXBw
+Oi8vb2Jha2pQT0hRMkNrTmIwck9yTk0zQSUyMWJhNTRlOGFjZThiNGM5MGRAd2ly
+ZS5jb20wHQYDVR0OBBYEFPrdVo+/gPyA/WgWr/DgtBsWdikRMAoGCCqGSM49BAMD
+A2kAMGYCMQCYnwhfcXm9bzhwscegUA2yxrcFFEqQCqxErV67Jr7I0ZpmMJO7VFGl
+0PY4ukoHQzwCMQDj9TdtIn8uyQMMvNnYdPcCGWIbL2zVgGb7iQTCJ7wS+WfCkEJz
+5srH9ihY+OjjynE=
 -----END CERTIFICATE-----"#;
 
     #[test]
@@ -233,4 +270,36 @@ OfqfZA1YMtN5NLz/AA==
         let identity = cert_der.contents().extract_identity().unwrap();
         assert!(!identity.thumbprint.is_empty());
     }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn thumbprint_should_use_hash_alg() {
+        let cert_der = pem::parse(CERT).unwrap();
+        let sha256 = cert_der.contents().extract_identity().unwrap().thumbprint;
+        let with_hash = |hash_alg| {
+            cert_der
+                .contents()
+                .extract_identity_with_hash_alg(hash_alg)
+                .unwrap()
+                .thumbprint
+        };
+        assert_eq!(with_hash(HashAlgorithm::SHA256), sha256);
+        // base64url without padding of 48 & 64 bytes hashes
+        assert_eq!(with_hash(HashAlgorithm::SHA384).len(), 64);
+        assert_eq!(with_hash(HashAlgorithm::SHA512).len(), 86);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn thumbprint_should_match_key_hash_strength() {
+        let cert_der = pem::parse(CERT_P384).unwrap();
+        let identity = cert_der.contents().extract_identity().unwrap();
+        assert_eq!(&identity.client_id, "obakjPOHQ2CkNb0rOrNM3A:ba54e8ace8b4c90d@wire.com");
+        let sha384 = cert_der
+            .contents()
+            .extract_identity_with_hash_alg(HashAlgorithm::SHA384)
+            .unwrap()
+            .thumbprint;
+        assert_eq!(identity.thumbprint, sha384);
+    }
 }
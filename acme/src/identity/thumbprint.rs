@@ -5,29 +5,50 @@ use crate::{
 use jwt_simple::prelude::*;
 use rusty_jwt_tools::{
     jwk::TryIntoJwk,
-    prelude::{HashAlgorithm, JwkThumbprint},
+    prelude::{HashAlgorithm, JwkThumbprint, JwsAlgorithm},
 };
 use x509_cert::spki::SubjectPublicKeyInfoOwned;
 
 /// See: https://datatracker.ietf.org/doc/html/rfc8037#appendix-A.3
-pub(crate) fn try_compute_jwk_canonicalized_thumbprint(cert: &x509_cert::TbsCertificate) -> RustyAcmeResult<String> {
+pub(crate) fn try_compute_jwk_canonicalized_thumbprint(
+    cert: &x509_cert::TbsCertificate,
+    hash_alg: HashAlgorithm,
+) -> RustyAcmeResult<String> {
     let jwk = try_into_jwk(&cert.subject_public_key_info)?;
-    // Hash is always SHA-256
-    let thumbprint = JwkThumbprint::generate(&jwk, HashAlgorithm::SHA256)?;
+    let thumbprint = JwkThumbprint::generate(&jwk, hash_alg)?;
     Ok(thumbprint.kid)
 }
 
 fn try_into_jwk(spki: &SubjectPublicKeyInfoOwned) -> RustyAcmeResult<Jwk> {
+    let pk = spki.subject_public_key.raw_bytes();
+    Ok(match try_spki_alg(spki)? {
+        JwsAlgorithm::Ed25519 => Ed25519PublicKey::from_bytes(pk)?.try_into_jwk()?,
+        JwsAlgorithm::P256 => ES256PublicKey::from_bytes(pk)?.try_into_jwk()?,
+        JwsAlgorithm::P384 => ES384PublicKey::from_bytes(pk)?.try_into_jwk()?,
+    })
+}
+
+/// Algorithm of the certified public key. EC keys are identified by their curve
+/// see [RFC 5480 Section 2.1.1](https://www.rfc-editor.org/rfc/rfc5480#section-2.1.1)
+pub(crate) fn try_spki_alg(spki: &SubjectPublicKeyInfoOwned) -> RustyAcmeResult<JwsAlgorithm> {
     let oid = oid_registry::Oid::new(std::borrow::Cow::Borrowed(spki.algorithm.oid.as_bytes()));
 
     // cannot pattern match oid_registry::Oid because it contains a Cow<'_>
     if oid == oid_registry::OID_SIG_ED25519 {
-        Ok(Ed25519PublicKey::from_bytes(spki.subject_public_key.raw_bytes())?.try_into_jwk()?)
-    } else if oid == oid_registry::OID_SIG_ECDSA_WITH_SHA256 {
-        Ok(ES256PublicKey::from_bytes(spki.subject_public_key.raw_bytes())?.try_into_jwk()?)
-    } else if oid == oid_registry::OID_SIG_ECDSA_WITH_SHA384 {
-        Ok(ES384PublicKey::from_bytes(spki.subject_public_key.raw_bytes())?.try_into_jwk()?)
-    } else {
-        Err(RustyAcmeError::InvalidCertificate(CertificateError::InvalidPublicKey))
+        return Ok(JwsAlgorithm::Ed25519);
+    }
+    if oid == oid_registry::OID_KEY_TYPE_EC_PUBLIC_KEY {
+        let curve = spki
+            .algorithm
+            .parameters
+            .as_ref()
+            .and_then(|p| p.decode_as::<x509_cert::der::oid::ObjectIdentifier>().ok());
+        let curve = curve.as_ref().map(|c| c.as_bytes());
+        if curve == Some(oid_registry::OID_EC_P256.as_bytes()) {
+            return Ok(JwsAlgorithm::P256);
+        } else if curve == Some(oid_registry::OID_NIST_EC_P384.as_bytes()) {
+            return Ok(JwsAlgorithm::P384);
+        }
     }
+    Err(RustyAcmeError::InvalidCertificate(CertificateError::InvalidPublicKey))
 }
@@ -1,8 +1,119 @@
 use crate::prelude::*;
 use jwt_simple::prelude::*;
-use rusty_jwt_tools::prelude::*;
+use rusty_jwt_tools::{jwk::RustyJwk, prelude::*};
 
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+/// Algorithm of the ACME account key. Unlike the client keys, it never signs anything outside
+/// of the ACME protocol, hence it can also be an RSA key e.g. on devices whose keystore only
+/// supports RSA.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum AcmeJwsAlgorithm {
+    /// Any algorithm of the client keys
+    Jws(JwsAlgorithm),
+    /// RSASSA-PKCS1-v1_5 using SHA-256
+    ///
+    /// Specified in [RFC 7518 Section 3.3][1]
+    ///
+    /// [1]: https://www.rfc-editor.org/rfc/rfc7518#section-3.3
+    #[cfg(feature = "rsa")]
+    RS256,
+    /// RSASSA-PSS using SHA-256 and MGF1 with SHA-256
+    ///
+    /// Specified in [RFC 7518 Section 3.5][1]
+    ///
+    /// [1]: https://www.rfc-editor.org/rfc/rfc7518#section-3.5
+    #[cfg(feature = "rsa")]
+    PS256,
+}
+
+impl From<JwsAlgorithm> for AcmeJwsAlgorithm {
+    fn from(alg: JwsAlgorithm) -> Self {
+        Self::Jws(alg)
+    }
+}
+
+impl std::fmt::Display for AcmeJwsAlgorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Jws(alg) => f.write_str(&alg.to_string()),
+            #[cfg(feature = "rsa")]
+            Self::RS256 => f.write_str("RS256"),
+            #[cfg(feature = "rsa")]
+            Self::PS256 => f.write_str("PS256"),
+        }
+    }
+}
+
+impl AcmeJwsAlgorithm {
+    /// Public JWK of the PEM encoded (PKCS#8) key pair [kp]
+    pub fn public_jwk(&self, kp: &Pem) -> RustyAcmeResult<Jwk> {
+        #[cfg(feature = "rsa")]
+        use rusty_jwt_tools::jwk::TryIntoJwk as _;
+        Ok(match self {
+            Self::Jws(alg) => RustyJwk::private_pem_to_jwk(*alg, kp)?.public,
+            #[cfg(feature = "rsa")]
+            Self::RS256 => RS256KeyPair::from_pem(kp.as_str())?.public_key().try_into_jwk()?,
+            #[cfg(feature = "rsa")]
+            Self::PS256 => PS256KeyPair::from_pem(kp.as_str())?.public_key().try_into_jwk()?,
+        })
+    }
+
+    /// Signs a compact JWS, advertising the public key in the 'jwk' header when [with_jwk]
+    fn sign<T>(
+        &self,
+        header: JWTHeader,
+        claims: Option<JWTClaims<T>>,
+        kp: &Pem,
+        with_jwk: bool,
+    ) -> RustyAcmeResult<String>
+    where
+        T: serde::Serialize,
+        for<'de> T: serde::Deserialize<'de>,
+    {
+        #[cfg(feature = "rsa")]
+        let metadata = |jwk: Jwk| {
+            if with_jwk {
+                KeyMetadata::default().with_public_key(jwk)
+            } else {
+                KeyMetadata::default()
+            }
+        };
+        Ok(match self {
+            Self::Jws(alg) => RustyJwtTools::generate_jwt(*alg, header, claims, kp, with_jwk)?,
+            #[cfg(feature = "rsa")]
+            Self::RS256 => {
+                let mut rsa_kp = RS256KeyPair::from_pem(kp.as_str())?;
+                rsa_kp.attach_metadata(metadata(self.public_jwk(kp)?))?;
+                rsa_kp.sign_with_header(claims, header)?
+            }
+            #[cfg(feature = "rsa")]
+            Self::PS256 => {
+                let mut rsa_kp = PS256KeyPair::from_pem(kp.as_str())?;
+                rsa_kp.attach_metadata(metadata(self.public_jwk(kp)?))?;
+                rsa_kp.sign_with_header(claims, header)?
+            }
+        })
+    }
+}
+
+impl RustyAcme {
+    /// POST-as-GET request to any resource of the account e.g. to re-fetch an authorization, an
+    /// order or a certificate. Signed with the account key, its payload is empty.
+    /// see [RFC 8555 Section 6.3](https://www.rfc-editor.org/rfc/rfc8555.html#section-6.3)
+    pub fn post_as_get(
+        url: &url::Url,
+        account: &AcmeAccount,
+        alg: impl Into<AcmeJwsAlgorithm>,
+        kp: &Pem,
+        previous_nonce: String,
+    ) -> RustyAcmeResult<AcmeJws> {
+        // Extract the account URL from previous response which created a new account
+        let acct_url = account.acct_url()?;
+        let payload = None::<serde_json::Value>;
+        AcmeJws::new(alg, previous_nonce, url, Some(&acct_url), payload, kp)
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
 #[cfg_attr(test, derive(Clone))]
 #[serde(rename_all = "camelCase")]
 pub struct AcmeJws {
@@ -13,7 +124,7 @@ pub struct AcmeJws {
 
 impl AcmeJws {
     pub fn new<T>(
-        alg: JwsAlgorithm,
+        alg: impl Into<AcmeJwsAlgorithm>,
         nonce: String,
         url: &url::Url,
         kid: Option<&url::Url>,
@@ -24,12 +135,70 @@ impl AcmeJws {
         T: serde::Serialize,
         for<'de> T: serde::Deserialize<'de>,
     {
+        let alg = alg.into();
         let with_jwk = kid.is_none();
         let header = Self::header(alg, nonce, url, kid);
+        Self::sign(alg, header, payload, kp, with_jwk)
+    }
+
+    /// The decoded protected header e.g. to log the 'url', 'nonce' and 'kid' of a request
+    pub fn decoded_protected(&self) -> RustyAcmeResult<serde_json::Value> {
+        Self::decode_part(&self.protected)
+    }
+
+    /// The decoded payload, `None` for a POST-as-GET request
+    pub fn decoded_payload(&self) -> RustyAcmeResult<Option<serde_json::Value>> {
+        if self.payload.is_empty() {
+            return Ok(None);
+        }
+        Self::decode_part(&self.payload).map(Some)
+    }
+
+    fn decode_part(part: &str) -> RustyAcmeResult<serde_json::Value> {
+        use base64::Engine as _;
+
+        let part = base64::prelude::BASE64_URL_SAFE_NO_PAD
+            .decode(part)
+            .map_err(|_| RustyAcmeError::ImplementationError)?;
+        Ok(serde_json::from_slice(&part)?)
+    }
+
+    /// Inner JWS of an account key change, signed by the new key [kp] which it advertises in the
+    /// 'jwk' header. Unlike every other request it has no nonce.
+    /// see [RFC 8555 Section 7.3.5](https://www.rfc-editor.org/rfc/rfc8555.html#section-7.3.5)
+    pub(crate) fn new_inner<T>(
+        alg: impl Into<AcmeJwsAlgorithm>,
+        url: &url::Url,
+        payload: T,
+        kp: &Pem,
+    ) -> RustyAcmeResult<Self>
+    where
+        T: serde::Serialize,
+        for<'de> T: serde::Deserialize<'de>,
+    {
+        let alg = alg.into();
+        let header = JWTHeader {
+            algorithm: alg.to_string(),
+            custom: Some(serde_json::json!({ "url": url })),
+            ..Default::default()
+        };
+        Self::sign(alg, header, Some(payload), kp, true)
+    }
 
+    fn sign<T>(
+        alg: AcmeJwsAlgorithm,
+        header: JWTHeader,
+        payload: Option<T>,
+        kp: &Pem,
+        with_jwk: bool,
+    ) -> RustyAcmeResult<Self>
+    where
+        T: serde::Serialize,
+        for<'de> T: serde::Deserialize<'de>,
+    {
         let is_empty_payload = payload.is_none();
         let claims = payload.map(Self::claims);
-        let jwt = RustyJwtTools::generate_jwt(alg, header, claims, kp, with_jwk)?;
+        let jwt = alg.sign(header, claims, kp, with_jwk)?;
         let (protected, jwt) = jwt.split_once('.').ok_or(RustyAcmeError::ImplementationError)?;
         let (payload, signature) = jwt.split_once('.').ok_or(RustyAcmeError::ImplementationError)?;
         if signature.contains('.') {
@@ -46,6 +215,45 @@ impl AcmeJws {
         })
     }
 
+    /// Same as [AcmeJws::new] but the request is also signed by [cosigners] e.g. the MLS client
+    /// key, in JWS JSON General Serialization. The account key signature comes first and cosigners
+    /// always advertise their public key in the 'jwk' header.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_cosigned<T>(
+        alg: JwsAlgorithm,
+        nonce: String,
+        url: &url::Url,
+        kid: Option<&url::Url>,
+        payload: Option<T>,
+        kp: &Pem,
+        cosigners: &[(JwsAlgorithm, &Pem)],
+    ) -> RustyAcmeResult<GeneralJws>
+    where
+        T: serde::Serialize + Clone,
+        for<'de> T: serde::Deserialize<'de>,
+    {
+        let account = JwsSigner {
+            alg,
+            header: Self::header(alg.into(), nonce.clone(), url, kid),
+            kp,
+            with_jwk: kid.is_none(),
+        };
+        let cosigners = cosigners.iter().map(|&(alg, kp)| JwsSigner {
+            alg,
+            header: Self::header(alg.into(), nonce.clone(), url, None),
+            kp,
+            with_jwk: true,
+        });
+        let signers = std::iter::once(account).chain(cosigners).collect::<Vec<_>>();
+
+        let is_empty_payload = payload.is_none();
+        let mut jws = RustyJwtTools::generate_general_jws(payload.map(Self::claims), &signers)?;
+        if is_empty_payload {
+            jws.payload = String::new();
+        }
+        Ok(jws)
+    }
+
     fn claims<T>(custom: T) -> JWTClaims<T> {
         JWTClaims {
             custom,
@@ -60,7 +268,7 @@ impl AcmeJws {
         }
     }
 
-    fn header(alg: JwsAlgorithm, nonce: String, url: &url::Url, kid: Option<&url::Url>) -> JWTHeader {
+    fn header(alg: AcmeJwsAlgorithm, nonce: String, url: &url::Url, kid: Option<&url::Url>) -> JWTHeader {
         JWTHeader {
             algorithm: alg.to_string(),
             custom: Some(serde_json::json!({
@@ -72,3 +280,140 @@ impl AcmeJws {
         }
     }
 }
+
+#[cfg(test)]
+pub mod tests {
+    use wasm_bindgen_test::*;
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_post_as_get() {
+        let kp = Pem::from(Ed25519KeyPair::generate().to_pem());
+        let account = AcmeAccount::default();
+        let url: url::Url = "https://acme-server/acme/wire/authz/0DpEeMVjTpOk615lIRvihqEyZLW8CsMH"
+            .parse()
+            .unwrap();
+        let jws = RustyAcme::post_as_get(&url, &account, JwsAlgorithm::Ed25519, &kp, "nonce".to_string()).unwrap();
+        let header = jws.decoded_protected().unwrap();
+        assert_eq!(header["url"], url.as_str());
+        assert_eq!(header["kid"], account.acct_url().unwrap().as_str());
+        assert_eq!(header["nonce"], "nonce");
+        assert!(jws.payload.is_empty());
+        assert_eq!(jws.decoded_protected().unwrap(), header);
+        assert!(jws.decoded_payload().unwrap().is_none());
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_decode_payload() {
+        let kp = Pem::from(Ed25519KeyPair::generate().to_pem());
+        let url: url::Url = "https://acme-server/acme/new-account".parse().unwrap();
+        let payload = serde_json::json!({"termsOfServiceAgreed": true});
+        let jws = AcmeJws::new(
+            JwsAlgorithm::Ed25519,
+            "nonce".to_string(),
+            &url,
+            None,
+            Some(payload),
+            &kp,
+        )
+        .unwrap();
+        assert_eq!(jws.decoded_protected().unwrap()["url"], url.as_str());
+        assert_eq!(jws.decoded_payload().unwrap().unwrap()["termsOfServiceAgreed"], true);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_verify_every_cosigner_signature() {
+        let (account, client) = (Ed25519KeyPair::generate(), ES256KeyPair::generate());
+        let (account_kp, client_kp) = (Pem::from(account.to_pem()), Pem::from(client.to_pem().unwrap()));
+        let (account_pk, client_pk) = (
+            Pem::from(account.public_key().to_pem()),
+            Pem::from(client.public_key().to_pem().unwrap()),
+        );
+        let url: url::Url = "https://acme-server/acme/wire/order/0DpEeMVjTpOk615lIRvihqEyZLW8CsMH"
+            .parse()
+            .unwrap();
+        let jws = AcmeJws::new_cosigned(
+            JwsAlgorithm::Ed25519,
+            "nonce".to_string(),
+            &url,
+            Some(&url),
+            Some(serde_json::json!({"csr": "abcd"})),
+            &account_kp,
+            &[(JwsAlgorithm::P256, &client_kp)],
+        )
+        .unwrap();
+        let pks = [
+            AnyPublicKey::from((JwsAlgorithm::Ed25519, &account_pk)),
+            AnyPublicKey::from((JwsAlgorithm::P256, &client_pk)),
+        ];
+        let claims = RustyJwtTools::verify_general_jws::<serde_json::Value>(&jws, &pks).unwrap();
+        assert_eq!(claims.custom["csr"], "abcd");
+
+        // the cosigner signature alone being tampered with is enough to fail
+        let mut tampered = jws.clone();
+        let signature = &mut tampered.signatures[1].signature;
+        let first = if signature.starts_with('A') { "B" } else { "A" };
+        signature.replace_range(..1, first);
+        assert!(RustyJwtTools::verify_general_jws::<serde_json::Value>(&tampered, &pks).is_err());
+
+        // so is the account one
+        let mut tampered = jws;
+        let signature = &mut tampered.signatures[0].signature;
+        let first = if signature.starts_with('A') { "B" } else { "A" };
+        signature.replace_range(..1, first);
+        assert!(RustyJwtTools::verify_general_jws::<serde_json::Value>(&tampered, &pks).is_err());
+    }
+
+    #[cfg(feature = "rsa")]
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_sign_with_rsa_account_key() {
+        let kp = RS256KeyPair::generate(2048).unwrap();
+        let pk = kp.public_key();
+        let pem = Pem::from(kp.to_pem().unwrap());
+        let url: url::Url = "https://acme-server/acme/new-account".parse().unwrap();
+
+        for alg in [AcmeJwsAlgorithm::RS256, AcmeJwsAlgorithm::PS256] {
+            // jwk mode, e.g. for creating the account
+            let jws = AcmeJws::new(alg, "nonce".to_string(), &url, None, Some(serde_json::json!({})), &pem).unwrap();
+            let header = jws.decoded_protected().unwrap();
+            assert_eq!(header["alg"], alg.to_string());
+            assert_eq!(header["jwk"]["kty"], "RSA");
+            assert!(header.get("kid").is_none());
+
+            // kid mode, once the account exists
+            let jws = AcmeJws::new(
+                alg,
+                "nonce".to_string(),
+                &url,
+                Some(&url),
+                None::<serde_json::Value>,
+                &pem,
+            )
+            .unwrap();
+            let header = jws.decoded_protected().unwrap();
+            assert_eq!(header["kid"], url.as_str());
+            assert!(header.get("jwk").is_none());
+            assert!(jws.payload.is_empty());
+        }
+
+        // the account key signs both the protected header and the payload
+        let jws = AcmeJws::new(
+            AcmeJwsAlgorithm::RS256,
+            "nonce".to_string(),
+            &url,
+            Some(&url),
+            Some(serde_json::json!({})),
+            &pem,
+        )
+        .unwrap();
+        let token = format!("{}.{}.{}", jws.protected, jws.payload, jws.signature);
+        assert!(pk.verify_token::<serde_json::Value>(&token, None).is_ok());
+    }
+}
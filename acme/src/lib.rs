@@ -1,31 +1,75 @@
 mod account;
 mod authz;
+mod cert_info;
 mod certificate;
+mod chain;
 mod chall;
+mod client;
+mod crl;
 mod directory;
+mod eab;
 mod error;
+#[cfg(target_family = "wasm")]
+mod fetch;
 mod finalize;
 mod identifier;
 mod identity;
 mod jws;
+mod link;
+#[cfg(feature = "mock")]
+mod mock;
+mod nonce;
+mod observer;
+mod ocsp;
 mod order;
+mod problem;
+mod renewal_info;
+mod retry;
+mod revocation;
+#[cfg(feature = "schema")]
+mod schema;
+mod sct;
+mod session;
+mod tls_alpn;
+mod transport;
 
 /// Prelude
 pub mod prelude {
     pub use super::RustyAcme;
     use super::*;
-    pub use account::AcmeAccount;
-    pub use authz::AcmeAuthz;
+    pub use account::{AcmeAccount, AcmeAccountError, AcmeKeyChangeRequest};
+    pub use authz::{AcmeAuthz, AcmeAuthzError, AcmePreAuthzRequest};
+    pub use cert_info::AcmeCertificateInfo;
+    pub use chain::CertificateChain;
     pub use chall::{AcmeChallError, AcmeChallenge, AcmeChallengeType};
-    pub use error::{RustyAcmeError, RustyAcmeResult};
-    pub use finalize::AcmeFinalize;
+    pub use client::AcmeClient;
+    pub use crl::AcmeCrlError;
+    pub use eab::ExternalAccountBinding;
+    pub use error::{CertificateError, RustyAcmeError, RustyAcmeResult};
+    #[cfg(target_family = "wasm")]
+    pub use fetch::{FetchError, FetchTransport};
+    pub use finalize::{AcmeFinalize, CsrExtendedKeyUsage, CsrKeyUsage, CsrOptions, CsrSubjectAltName};
     pub use identifier::{AcmeIdentifier, WireIdentifier};
     pub use identity::{WireIdentity, WireIdentityReader};
-    pub use jws::AcmeJws;
-    pub use order::AcmeOrder;
+    pub use jws::{AcmeJws, AcmeJwsAlgorithm};
+    #[cfg(feature = "mock")]
+    pub use mock::MockAcmeServer;
+    pub use nonce::AcmeResponse;
+    pub use observer::AcmeObserver;
+    pub use ocsp::{AcmeOcspError, AcmeOcspRequest, OcspStatus};
+    pub use order::{AcmeOrder, AcmeOrderError, AcmeOrderExtensions, AcmeOrderList, AcmeOrderValidity};
+    pub use problem::{AcmeProblem, AcmeProblemIdentifier, AcmeProblemType};
+    pub use renewal_info::{AcmeRenewalInfo, AcmeRenewalInfoError, AcmeRenewalWindow};
+    pub use retry::{AcmePoll, AcmePollError, PollAction, PollCancellation, PollState, RetryPolicy};
+    pub use revocation::{AcmeRevocationError, AcmeRevocationRequest, RevocationReason, RevocationSigner};
     pub use rusty_x509_check as x509;
+    pub use sct::AcmeSignedCertificateTimestamp;
+    pub use session::SessionState;
+    #[cfg(all(feature = "client", not(target_family = "wasm")))]
+    pub use transport::HttpClientConfig;
+    pub use transport::{HttpMethod, HttpRequest, HttpResponse, HttpTransport};
 
-    pub use directory::AcmeDirectory;
+    pub use directory::{AcmeDirectory, AcmeDirectoryMeta};
 
     #[cfg(all(feature = "docker", not(target_family = "wasm")))]
     pub use docker::*;
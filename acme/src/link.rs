@@ -0,0 +1,52 @@
+/// Targets of the `Link` header values like `<https://...>;rel="next"` with the relation [rel].
+/// A header value might hold several comma separated links.
+/// see [RFC 8288 Section 3](https://www.rfc-editor.org/rfc/rfc8288.html#section-3)
+pub(crate) fn link_targets<'a>(links: &'a [&'a str], rel: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+    links
+        .iter()
+        .flat_map(|header| header.split(','))
+        .filter_map(move |link| link_target(link, rel))
+}
+
+fn link_target<'a>(link: &'a str, rel: &str) -> Option<&'a str> {
+    let mut parts = link.split(';').map(str::trim);
+    let target = parts.next()?.strip_prefix('<')?.strip_suffix('>')?;
+    parts
+        .filter_map(|param| param.split_once('='))
+        .filter(|(name, _)| name.trim().eq_ignore_ascii_case("rel"))
+        .any(|(_, rels)| rels.trim().trim_matches('"').split_whitespace().any(|r| r == rel))
+        .then_some(target)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use wasm_bindgen_test::*;
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_parse_link_values() {
+        assert_eq!(link_target("<https://a.b/c>;rel=next", "next"), Some("https://a.b/c"));
+        assert_eq!(
+            link_target(" <https://a.b/c> ; REL=\"prev next\"", "next"),
+            Some("https://a.b/c")
+        );
+        assert_eq!(link_target("<https://a.b/c>;rel=\"nextpage\"", "next"), None);
+        assert_eq!(link_target("https://a.b/c;rel=next", "next"), None);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_find_every_target() {
+        let links = [
+            "<https://a.b/1>;rel=\"alternate\", <https://a.b/index>;rel=\"index\"",
+            "<https://a.b/2>;rel=\"alternate\"",
+        ];
+        let targets = link_targets(&links, "alternate").collect::<Vec<_>>();
+        assert_eq!(targets, vec!["https://a.b/1", "https://a.b/2"]);
+        assert_eq!(link_targets(&links, "next").count(), 0);
+    }
+}
@@ -0,0 +1,962 @@
+use std::collections::{HashMap, HashSet};
+
+use base64::Engine as _;
+use jwt_simple::prelude::*;
+use x509_cert::der::{asn1::OctetString, Decode as _, Encode};
+
+use rusty_jwt_tools::prelude::*;
+
+use crate::{
+    authz::AuthzStatus,
+    chall::AcmeChallengeStatus,
+    order::{AcmeOrderRequest, AcmeOrderStatus},
+    prelude::*,
+};
+
+/// Certificates are valid this long when the order does not request a validity
+const MOCK_CERTIFICATE_VALIDITY: time::Duration = time::Duration::days(90);
+
+/// Orders and authorizations expire after this long
+const MOCK_RESOURCE_VALIDITY: time::Duration = time::Duration::days(1);
+
+/// In-memory ACME server implementing the Wire challenges, for running enrollment tests without
+/// network nor Docker. It is an [HttpTransport] hence plugs directly into an [AcmeClient]. Clones
+/// share the same state.
+///
+/// It validates:
+/// * 'wire-dpop-01' when the access token answers the challenge ('chal') for the ClientId of the
+///   device identifier ('client_id')
+/// * 'wire-oidc-01' when the ID token holds the key authorization ('keyauth'), the challenge URL
+///   ('acme_aud') and the display name of the user identifier ('name')
+///
+/// then issues the certificate with a self-signed Ed25519 root, see [Self::root_certificate]. An
+/// order renewing one of these certificates (see [AcmeOrderExtensions::replaces]) reuses its user
+/// authorization, hence has no 'wire-oidc-01' challenge to complete.
+/// Nonces are enforced but neither the signatures of the requests nor the ones of the tokens are
+/// verified.
+#[derive(Debug, Clone)]
+pub struct MockAcmeServer {
+    base_url: url::Url,
+    dpop_target: url::Url,
+    oidc_target: url::Url,
+    ca_kp: Pem,
+    root: Vec<u8>,
+    state: std::sync::Arc<std::sync::Mutex<MockAcmeState>>,
+}
+
+#[derive(Debug, Default)]
+struct MockAcmeState {
+    next_id: u64,
+    nonces: HashSet<String>,
+    /// public key, as a JWK, of each account
+    accounts: HashMap<u64, serde_json::Value>,
+    orders: HashMap<u64, MockOrder>,
+    authzs: HashMap<u64, MockAuthz>,
+    /// PEM bundle of each certificate, which has the id of its order
+    certificates: HashMap<u64, String>,
+}
+
+#[derive(Debug)]
+struct MockOrder {
+    account: u64,
+    order: AcmeOrder,
+    authzs: Vec<u64>,
+    certificate: Option<url::Url>,
+}
+
+#[derive(Debug)]
+struct MockAuthz {
+    account: u64,
+    order: u64,
+    authz: AcmeAuthz,
+}
+
+/// Problem document along with its status code
+type MockResult<T> = Result<T, AcmeProblem>;
+
+impl MockAcmeServer {
+    /// Generates the root of a new server
+    ///
+    /// # Arguments
+    /// * `base_url` - prefix of every URL e.g. 'https://acme.wire.com/acme/wire/', the directory
+    ///   being under it, see [Self::directory_url]
+    pub fn try_new(mut base_url: url::Url) -> RustyAcmeResult<Self> {
+        if !base_url.path().ends_with('/') {
+            base_url.set_path(&format!("{}/", base_url.path()));
+        }
+        let ca_kp = Pem::from(Ed25519KeyPair::generate().to_pem());
+        let root = Self::root_certificate_der(&ca_kp)?;
+        Ok(Self {
+            dpop_target: base_url.join("wire/access-token")?,
+            oidc_target: base_url.join("idp")?,
+            base_url,
+            ca_kp,
+            root,
+            state: Default::default(),
+        })
+    }
+
+    /// 'target' of the 'wire-dpop-01' challenges, the wire-server endpoint delivering access tokens
+    pub fn with_dpop_target(mut self, target: url::Url) -> Self {
+        self.dpop_target = target;
+        self
+    }
+
+    /// 'target' of the 'wire-oidc-01' challenges, the identity provider delivering ID tokens
+    pub fn with_oidc_target(mut self, target: url::Url) -> Self {
+        self.oidc_target = target;
+        self
+    }
+
+    pub fn directory_url(&self) -> url::Url {
+        self.url("directory")
+    }
+
+    /// Root CA every certificate is issued by, DER encoded e.g. to use as trust anchor
+    pub fn root_certificate(&self) -> &[u8] {
+        &self.root
+    }
+
+    fn url(&self, path: &str) -> url::Url {
+        let mut url = self.base_url.clone();
+        url.set_path(&format!("{}{path}", self.base_url.path()));
+        url
+    }
+
+    fn handle(&self, request: HttpRequest) -> HttpResponse {
+        let Ok(mut state) = self.state.lock() else {
+            return Self::problem_response(Self::problem(AcmeProblemType::ServerInternal, "poisoned state"));
+        };
+        let mut response = self.route(&mut state, &request).unwrap_or_else(Self::problem_response);
+        response.headers.push(("Replay-Nonce".to_string(), state.new_nonce()));
+        response
+    }
+
+    fn route(&self, state: &mut MockAcmeState, request: &HttpRequest) -> MockResult<HttpResponse> {
+        let path = request
+            .url
+            .as_str()
+            .strip_prefix(self.base_url.as_str())
+            .ok_or_else(Self::not_found)?;
+        let segments = path.split('/').collect::<Vec<_>>();
+        match (request.method, &segments[..]) {
+            (HttpMethod::Get, ["directory"]) => Self::json(200, &self.directory()),
+            (HttpMethod::Get | HttpMethod::Head, ["new-nonce"]) => Ok(HttpResponse {
+                status: 200,
+                ..Default::default()
+            }),
+            (HttpMethod::Post, segments) => {
+                let (protected, payload) = Self::verify_jws(state, request)?;
+                if let ["new-account"] = segments {
+                    return self.new_account(state, &protected);
+                }
+                let account = self.account_id(state, &protected)?;
+                let id = |id: &str| id.parse::<u64>().map_err(|_| Self::not_found());
+                match segments {
+                    ["account", owner, "orders"] if owner.parse::<u64>().ok() == Some(account) => {
+                        self.orders(state, account)
+                    }
+                    ["new-order"] => self.new_order(state, account, payload),
+                    ["authz", authz] => self.authz(state, account, id(authz)?),
+                    ["challenge", authz] => self.challenge(state, account, id(authz)?, payload),
+                    ["order", order] => self.order(state, account, id(order)?),
+                    ["order", order, "finalize"] => self.finalize(state, account, id(order)?, payload),
+                    ["certificate", order] => self.certificate(state, account, id(order)?),
+                    _ => Err(Self::not_found()),
+                }
+            }
+            _ => Err(Self::not_found()),
+        }
+    }
+
+    fn directory(&self) -> AcmeDirectory {
+        AcmeDirectory {
+            new_nonce: self.url("new-nonce"),
+            new_account: self.url("new-account"),
+            new_order: self.url("new-order"),
+            new_authz: None,
+            revoke_cert: self.url("revoke-cert"),
+            key_change: None,
+            renewal_info: None,
+            meta: None,
+        }
+    }
+
+    /// Consumes the nonce of the request and returns its decoded protected header and payload
+    fn verify_jws(
+        state: &mut MockAcmeState,
+        request: &HttpRequest,
+    ) -> MockResult<(serde_json::Value, Option<serde_json::Value>)> {
+        let malformed = || Self::problem(AcmeProblemType::Malformed, "invalid JWS");
+        let jws = serde_json::from_slice::<AcmeJws>(&request.body).map_err(|_| malformed())?;
+        let protected = jws.decoded_protected().map_err(|_| malformed())?;
+        let payload = jws.decoded_payload().map_err(|_| malformed())?;
+
+        let nonce = protected["nonce"].as_str().unwrap_or_default();
+        if !state.nonces.remove(nonce) {
+            return Err(Self::problem(
+                AcmeProblemType::BadNonce,
+                "unknown or already used nonce",
+            ));
+        }
+        if protected["url"] != request.url.as_str() {
+            return Err(Self::problem(
+                AcmeProblemType::Unauthorized,
+                "'url' is not the request one",
+            ));
+        }
+        Ok((protected, payload))
+    }
+
+    /// Account whose URL is the 'kid' of the request
+    fn account_id(&self, state: &MockAcmeState, protected: &serde_json::Value) -> MockResult<u64> {
+        protected["kid"]
+            .as_str()
+            .and_then(|kid| kid.strip_prefix(self.url("account/").as_str()))
+            .and_then(|id| id.parse::<u64>().ok())
+            .filter(|id| state.accounts.contains_key(id))
+            .ok_or_else(|| Self::problem(AcmeProblemType::AccountDoesNotExist, "unknown 'kid'"))
+    }
+
+    fn new_account(&self, state: &mut MockAcmeState, protected: &serde_json::Value) -> MockResult<HttpResponse> {
+        let jwk = protected
+            .get("jwk")
+            .ok_or_else(|| Self::problem(AcmeProblemType::Malformed, "missing 'jwk'"))?;
+        let existing = state.accounts.iter().find_map(|(id, key)| (key == jwk).then_some(*id));
+        let (id, status) = match existing {
+            Some(id) => (id, 200),
+            None => {
+                let id = state.new_id();
+                state.accounts.insert(id, jwk.clone());
+                (id, 201)
+            }
+        };
+        let account = serde_json::json!({
+            "status": "valid",
+            "orders": self.url(&format!("account/{id}/orders")),
+        });
+        Self::created(Self::json(status, &account)?, self.url(&format!("account/{id}")))
+    }
+
+    fn orders(&self, state: &MockAcmeState, account: u64) -> MockResult<HttpResponse> {
+        let orders = state
+            .orders
+            .iter()
+            .filter(|(_, order)| order.account == account)
+            .map(|(id, _)| self.url(&format!("order/{id}")))
+            .collect::<Vec<_>>();
+        Self::json(200, &serde_json::json!({ "orders": orders }))
+    }
+
+    fn new_order(
+        &self,
+        state: &mut MockAcmeState,
+        account: u64,
+        payload: Option<serde_json::Value>,
+    ) -> MockResult<HttpResponse> {
+        let request = serde_json::from_value::<AcmeOrderRequest>(payload.unwrap_or_default())
+            .map_err(|_| Self::problem(AcmeProblemType::Malformed, "invalid order"))?;
+        AcmeOrder::verify_identifiers(&request.identifiers)
+            .map_err(|e| Self::problem(AcmeProblemType::RejectedIdentifier, e.to_string()))?;
+
+        let renewed = match &request.extensions.replaces {
+            Some(cert_id) => Self::replaced_identifiers(state, account, cert_id)?,
+            None => vec![],
+        };
+
+        let expires = time::OffsetDateTime::now_utc() + MOCK_RESOURCE_VALIDITY;
+        let order_id = state.new_id();
+        let (mut authzs, mut all_valid) = (vec![], true);
+        for identifier in &request.identifiers {
+            let (typ, target) = match identifier {
+                AcmeIdentifier::WireappUser(_) => (AcmeChallengeType::WireOidc01, &self.oidc_target),
+                AcmeIdentifier::WireappDevice(_) => (AcmeChallengeType::WireDpop01, &self.dpop_target),
+            };
+            let reused = typ == AcmeChallengeType::WireOidc01 && renewed.contains(identifier);
+            all_valid &= reused;
+            let id = state.new_id();
+            let challenge = AcmeChallenge {
+                typ,
+                url: self.url(&format!("challenge/{id}")),
+                status: Some(if reused {
+                    AcmeChallengeStatus::Valid
+                } else {
+                    AcmeChallengeStatus::Pending
+                }),
+                token: Self::random::<32>()?,
+                target: target.clone(),
+            };
+            let authz = AcmeAuthz {
+                status: if reused {
+                    AuthzStatus::Valid
+                } else {
+                    AuthzStatus::Pending
+                },
+                expires: Some(expires),
+                challenges: [challenge],
+                identifier: identifier.clone(),
+            };
+            state.authzs.insert(
+                id,
+                MockAuthz {
+                    account,
+                    order: order_id,
+                    authz,
+                },
+            );
+            authzs.push(id);
+        }
+        let order = AcmeOrder {
+            status: if all_valid {
+                AcmeOrderStatus::Ready
+            } else {
+                AcmeOrderStatus::Pending
+            },
+            finalize: self.url(&format!("order/{order_id}/finalize")),
+            identifiers: request.identifiers,
+            authorizations: authzs.iter().map(|id| self.url(&format!("authz/{id}"))).collect(),
+            expires: Some(expires),
+            not_before: request.not_before,
+            not_after: request.not_after,
+            extensions: request.extensions,
+        };
+        let order = MockOrder {
+            account,
+            order,
+            authzs,
+            certificate: None,
+        };
+        let response = Self::json(201, &order.to_json()?)?;
+        state.orders.insert(order_id, order);
+        Self::created(response, self.url(&format!("order/{order_id}")))
+    }
+
+    /// Identifiers of the order whose certificate an order renews, when it belongs to [account]
+    fn replaced_identifiers(state: &MockAcmeState, account: u64, cert_id: &str) -> MockResult<Vec<AcmeIdentifier>> {
+        state
+            .certificates
+            .iter()
+            .filter_map(|(id, bundle)| Some((state.order(account, *id).ok()?, pem::parse_many(bundle).ok()?)))
+            .find(|(_, chain)| {
+                chain
+                    .first()
+                    .and_then(|leaf| RustyAcme::renewal_info_cert_id(leaf.contents()).ok())
+                    .is_some_and(|id| id == cert_id)
+            })
+            .map(|(order, _)| order.order.identifiers.clone())
+            .ok_or_else(|| Self::problem(AcmeProblemType::Malformed, "unknown certificate to replace"))
+    }
+
+    fn authz(&self, state: &MockAcmeState, account: u64, id: u64) -> MockResult<HttpResponse> {
+        let authz = state.authz(account, id)?;
+        Self::json(200, &authz.authz)
+    }
+
+    fn challenge(
+        &self,
+        state: &mut MockAcmeState,
+        account: u64,
+        id: u64,
+        payload: Option<serde_json::Value>,
+    ) -> MockResult<HttpResponse> {
+        let jwk = state.accounts.get(&account).cloned().unwrap_or_default();
+        let authz = state.authz(account, id)?;
+        if authz.authz.status == AuthzStatus::Pending {
+            Self::validate_challenge(&authz.authz, &jwk, &payload.unwrap_or_default())?;
+        }
+
+        let authz = state.authzs.get_mut(&id).ok_or_else(Self::not_found)?;
+        authz.authz.status = AuthzStatus::Valid;
+        authz.authz.challenges[0].status = Some(AcmeChallengeStatus::Valid);
+        let (order_id, challenge) = (authz.order, authz.authz.challenges[0].clone());
+
+        let all_valid = state
+            .orders
+            .get(&order_id)
+            .map(|order| &order.authzs)
+            .into_iter()
+            .flatten()
+            .all(|id| matches!(state.authzs.get(id), Some(a) if a.authz.status == AuthzStatus::Valid));
+        if let Some(order) = state.orders.get_mut(&order_id) {
+            if all_valid && order.order.status == AcmeOrderStatus::Pending {
+                order.order.status = AcmeOrderStatus::Ready;
+            }
+        }
+
+        let mut response = Self::json(200, &challenge)?;
+        let up = format!("<{}>;rel=\"up\"", self.url(&format!("authz/{id}")));
+        response.headers.push(("Link".to_string(), up));
+        Ok(response)
+    }
+
+    /// Verifies the token of a Wire challenge, see [MockAcmeServer]
+    fn validate_challenge(authz: &AcmeAuthz, jwk: &serde_json::Value, payload: &serde_json::Value) -> MockResult<()> {
+        let [challenge] = &authz.challenges;
+        let identifier = authz
+            .wire_identifier()
+            .map_err(|e| Self::problem(AcmeProblemType::ServerInternal, e.to_string()))?;
+        let incorrect = |detail: &str| Err(Self::problem(AcmeProblemType::IncorrectResponse, detail));
+        match challenge.typ {
+            AcmeChallengeType::WireDpop01 => {
+                let claims = Self::token_claims(payload, "access_token")?;
+                if claims["chal"] != challenge.token.as_str() {
+                    return incorrect("the access token does not answer this challenge");
+                }
+                let client_id = identifier.client_id.map(|c| c.to_uri()).unwrap_or_default();
+                if claims["client_id"] != client_id.as_str() {
+                    return incorrect("the access token was delivered to another client");
+                }
+            }
+            AcmeChallengeType::WireOidc01 => {
+                let claims = Self::token_claims(payload, "id_token")?;
+                let jwk = serde_json::from_value::<Jwk>(jwk.clone())
+                    .map_err(|_| Self::problem(AcmeProblemType::BadPublicKey, "invalid account key"))?;
+                let keyauth_matches = [HashAlgorithm::SHA256, HashAlgorithm::SHA384, HashAlgorithm::SHA512]
+                    .into_iter()
+                    .filter_map(|hash_alg| challenge.key_authorization_with_hash_alg(&jwk, hash_alg).ok())
+                    .any(|keyauth| claims["keyauth"] == keyauth.as_str());
+                if !keyauth_matches {
+                    return incorrect("the ID token does not hold the key authorization");
+                }
+                if claims["acme_aud"] != challenge.url.as_str() {
+                    return incorrect("the ID token is meant for another challenge");
+                }
+                let name = DisplayName::from(claims["name"].as_str().unwrap_or_default());
+                if name != DisplayName::from(identifier.display_name.as_str()) {
+                    return incorrect("the ID token is for another user");
+                }
+            }
+            _ => return Err(Self::problem(AcmeProblemType::Malformed, "unsupported challenge")),
+        }
+        Ok(())
+    }
+
+    /// Claims of the JWT in the [field] of the payload, without verifying its signature
+    fn token_claims(payload: &serde_json::Value, field: &str) -> MockResult<serde_json::Value> {
+        payload[field]
+            .as_str()
+            .and_then(|token| token.split('.').nth(1))
+            .and_then(|claims| base64::prelude::BASE64_URL_SAFE_NO_PAD.decode(claims).ok())
+            .and_then(|claims| serde_json::from_slice(&claims).ok())
+            .ok_or_else(|| Self::problem(AcmeProblemType::Malformed, format!("invalid '{field}'")))
+    }
+
+    fn order(&self, state: &MockAcmeState, account: u64, id: u64) -> MockResult<HttpResponse> {
+        Self::json(200, &state.order(account, id)?.to_json()?)
+    }
+
+    fn finalize(
+        &self,
+        state: &mut MockAcmeState,
+        account: u64,
+        id: u64,
+        payload: Option<serde_json::Value>,
+    ) -> MockResult<HttpResponse> {
+        let order = state.order(account, id)?;
+        if order.order.status != AcmeOrderStatus::Ready {
+            return Err(Self::problem(AcmeProblemType::OrderNotReady, "the order is not ready"));
+        }
+        let csr = payload
+            .as_ref()
+            .and_then(|p| p["csr"].as_str())
+            .and_then(|csr| base64::prelude::BASE64_URL_SAFE_NO_PAD.decode(csr).ok())
+            .and_then(|csr| x509_cert::request::CertReq::from_der(&csr).ok())
+            .ok_or_else(|| Self::problem(AcmeProblemType::BadCsr, "invalid CSR"))?;
+        let bundle = self
+            .issue(&order.order, &csr)
+            .map_err(|e| Self::problem(AcmeProblemType::BadCsr, e.to_string()))?;
+
+        state.certificates.insert(id, bundle);
+        let certificate = self.url(&format!("certificate/{id}"));
+        let order = state.orders.get_mut(&id).ok_or_else(Self::not_found)?;
+        order.order.status = AcmeOrderStatus::Valid;
+        order.certificate = Some(certificate);
+        Self::json(200, &order.to_json()?)
+    }
+
+    fn certificate(&self, state: &MockAcmeState, account: u64, id: u64) -> MockResult<HttpResponse> {
+        state.order(account, id)?;
+        let bundle = state.certificates.get(&id).ok_or_else(Self::not_found)?;
+        Ok(HttpResponse {
+            status: 200,
+            headers: vec![(
+                "Content-Type".to_string(),
+                "application/pem-certificate-chain".to_string(),
+            )],
+            body: bundle.as_bytes().to_vec(),
+        })
+    }
+
+    /// PEM bundle of the leaf certificate, with the identity of the order and the key of the
+    /// CSR, followed by the root
+    fn issue(&self, order: &AcmeOrder, csr: &x509_cert::request::CertReq) -> RustyAcmeResult<String> {
+        let identifier = order.try_get_coalesce_identifier()?;
+        let now = time::OffsetDateTime::now_utc();
+        let validity = (
+            order.not_before.unwrap_or(now),
+            order.not_after.unwrap_or(now + MOCK_CERTIFICATE_VALIDITY),
+        );
+        let san = [
+            CsrSubjectAltName::Uri(identifier.client_id.to_uri()),
+            CsrSubjectAltName::Uri(identifier.handle.to_string()),
+        ]
+        .into_iter()
+        .map(x509_cert::ext::pkix::name::GeneralName::try_from)
+        .collect::<RustyAcmeResult<Vec<_>>>()?;
+        let extensions = vec![
+            Self::extension(
+                oid_registry::OID_X509_EXT_SUBJECT_ALT_NAME,
+                false,
+                x509_cert::ext::pkix::SubjectAltName(san),
+            )?,
+            Self::extension(
+                oid_registry::OID_X509_EXT_BASIC_CONSTRAINTS,
+                true,
+                x509_cert::ext::pkix::BasicConstraints {
+                    ca: false,
+                    path_len_constraint: None,
+                },
+            )?,
+            Self::extension(
+                oid_registry::OID_X509_EXT_KEY_USAGE,
+                true,
+                x509_cert::ext::pkix::KeyUsage(x509_cert::ext::pkix::KeyUsages::DigitalSignature.into()),
+            )?,
+            Self::subject_key_identifier(&csr.info.public_key)?,
+            Self::authority_key_identifier(&RustyAcme::csr_spki(JwsAlgorithm::Ed25519, &self.ca_kp)?)?,
+        ];
+        let subject = Self::name(&[
+            (oid_registry::OID_X509_ORGANIZATION_NAME, identifier.domain.as_str()),
+            (oid_registry::OID_X509_COMMON_NAME, identifier.display_name.as_str()),
+        ])?;
+        let leaf = Self::sign(
+            &self.ca_kp,
+            Self::root_name()?,
+            subject,
+            csr.info.public_key.clone(),
+            validity,
+            extensions,
+        )?;
+        Ok(pem::encode_many(&[
+            pem::Pem::new("CERTIFICATE", leaf),
+            pem::Pem::new("CERTIFICATE", self.root.clone()),
+        ]))
+    }
+
+    fn root_certificate_der(ca_kp: &Pem) -> RustyAcmeResult<Vec<u8>> {
+        let spki = RustyAcme::csr_spki(JwsAlgorithm::Ed25519, ca_kp)?;
+        let now = time::OffsetDateTime::now_utc();
+        let validity = (now - time::Duration::hours(1), now + time::Duration::days(365));
+        let extensions = vec![
+            Self::extension(
+                oid_registry::OID_X509_EXT_BASIC_CONSTRAINTS,
+                true,
+                x509_cert::ext::pkix::BasicConstraints {
+                    ca: true,
+                    path_len_constraint: None,
+                },
+            )?,
+            Self::extension(
+                oid_registry::OID_X509_EXT_KEY_USAGE,
+                true,
+                x509_cert::ext::pkix::KeyUsage(
+                    x509_cert::ext::pkix::KeyUsages::KeyCertSign | x509_cert::ext::pkix::KeyUsages::CRLSign,
+                ),
+            )?,
+            Self::subject_key_identifier(&spki)?,
+            Self::authority_key_identifier(&spki)?,
+        ];
+        Self::sign(
+            ca_kp,
+            Self::root_name()?,
+            Self::root_name()?,
+            spki,
+            validity,
+            extensions,
+        )
+    }
+
+    fn root_name() -> RustyAcmeResult<x509_cert::name::Name> {
+        Self::name(&[
+            (oid_registry::OID_X509_ORGANIZATION_NAME, "wire.com"),
+            (oid_registry::OID_X509_COMMON_NAME, "Mock ACME Root CA"),
+        ])
+    }
+
+    fn sign(
+        ca_kp: &Pem,
+        issuer: x509_cert::name::Name,
+        subject: x509_cert::name::Name,
+        subject_public_key_info: x509_cert::spki::SubjectPublicKeyInfoOwned,
+        (not_before, not_after): (time::OffsetDateTime, time::OffsetDateTime),
+        extensions: Vec<x509_cert::ext::Extension>,
+    ) -> RustyAcmeResult<Vec<u8>> {
+        let to_time = |at: time::OffsetDateTime| -> RustyAcmeResult<x509_cert::time::Time> {
+            let since_epoch = core::time::Duration::from_secs(at.unix_timestamp().try_into().unwrap_or_default());
+            Ok(x509_cert::der::asn1::UtcTime::from_unix_duration(since_epoch)?.into())
+        };
+        let mut serial = Self::random_bytes::<16>()?;
+        // positive and without leading zero
+        serial[0] = (serial[0] & 0x7F) | 0x40;
+        let algorithm = RustyAcme::csr_alg(JwsAlgorithm::Ed25519)?;
+        let tbs_certificate = x509_cert::TbsCertificate {
+            version: x509_cert::Version::V3,
+            serial_number: x509_cert::serial_number::SerialNumber::new(&serial)?,
+            signature: algorithm.clone(),
+            issuer,
+            validity: x509_cert::time::Validity {
+                not_before: to_time(not_before)?,
+                not_after: to_time(not_after)?,
+            },
+            subject,
+            subject_public_key_info,
+            issuer_unique_id: None,
+            subject_unique_id: None,
+            extensions: Some(extensions),
+        };
+        let signature = RustyAcme::csr_signature(JwsAlgorithm::Ed25519, ca_kp, &tbs_certificate)?;
+        let certificate = x509_cert::Certificate {
+            tbs_certificate,
+            signature_algorithm: algorithm,
+            signature,
+        };
+        Ok(certificate.to_der()?)
+    }
+
+    fn name(attributes: &[(oid_registry::Oid, &str)]) -> RustyAcmeResult<x509_cert::name::Name> {
+        let rdns = attributes
+            .iter()
+            .map(
+                |(oid, value)| -> RustyAcmeResult<x509_cert::name::RelativeDistinguishedName> {
+                    let attribute = x509_cert::attr::AttributeTypeAndValue {
+                        oid: oid.as_bytes().try_into()?,
+                        value: x509_cert::attr::AttributeValue::new(x509_cert::der::Tag::Utf8String, value.as_bytes())?,
+                    };
+                    Ok(x509_cert::name::RelativeDistinguishedName(vec![attribute].try_into()?))
+                },
+            )
+            .collect::<RustyAcmeResult<Vec<_>>>()?;
+        Ok(x509_cert::name::Name::from(rdns))
+    }
+
+    fn extension(
+        oid: oid_registry::Oid,
+        critical: bool,
+        value: impl Encode,
+    ) -> RustyAcmeResult<x509_cert::ext::Extension> {
+        Ok(x509_cert::ext::Extension {
+            extn_id: oid.as_bytes().try_into()?,
+            critical,
+            extn_value: OctetString::new(value.to_der()?)?,
+        })
+    }
+
+    /// SHA-1 of the public key
+    /// see [RFC 5280 Section 4.2.1.2](https://www.rfc-editor.org/rfc/rfc5280#section-4.2.1.2)
+    fn key_identifier(spki: &x509_cert::spki::SubjectPublicKeyInfoOwned) -> RustyAcmeResult<OctetString> {
+        use sha1::Digest as _;
+        Ok(OctetString::new(
+            sha1::Sha1::digest(spki.subject_public_key.raw_bytes()).to_vec(),
+        )?)
+    }
+
+    fn subject_key_identifier(
+        spki: &x509_cert::spki::SubjectPublicKeyInfoOwned,
+    ) -> RustyAcmeResult<x509_cert::ext::Extension> {
+        let ski = x509_cert::ext::pkix::SubjectKeyIdentifier(Self::key_identifier(spki)?);
+        Self::extension(oid_registry::OID_X509_EXT_SUBJECT_KEY_IDENTIFIER, false, ski)
+    }
+
+    fn authority_key_identifier(
+        issuer_spki: &x509_cert::spki::SubjectPublicKeyInfoOwned,
+    ) -> RustyAcmeResult<x509_cert::ext::Extension> {
+        let aki = x509_cert::ext::pkix::AuthorityKeyIdentifier {
+            key_identifier: Some(Self::key_identifier(issuer_spki)?),
+            authority_cert_issuer: None,
+            authority_cert_serial_number: None,
+        };
+        Self::extension(oid_registry::OID_X509_EXT_AUTHORITY_KEY_IDENTIFIER, false, aki)
+    }
+
+    fn random_bytes<const N: usize>() -> RustyAcmeResult<[u8; N]> {
+        let mut bytes = [0u8; N];
+        getrandom::getrandom(&mut bytes).map_err(|_| RustyAcmeError::ImplementationError)?;
+        Ok(bytes)
+    }
+
+    /// base64 URL encoded random bytes e.g. for a challenge token
+    fn random<const N: usize>() -> MockResult<String> {
+        let bytes =
+            Self::random_bytes::<N>().map_err(|e| Self::problem(AcmeProblemType::ServerInternal, e.to_string()))?;
+        Ok(base64::prelude::BASE64_URL_SAFE_NO_PAD.encode(bytes))
+    }
+
+    fn json(status: u16, body: &impl serde::Serialize) -> MockResult<HttpResponse> {
+        let body =
+            serde_json::to_vec(body).map_err(|e| Self::problem(AcmeProblemType::ServerInternal, e.to_string()))?;
+        Ok(HttpResponse {
+            status,
+            headers: vec![("Content-Type".to_string(), "application/json".to_string())],
+            body,
+        })
+    }
+
+    fn created(mut response: HttpResponse, location: url::Url) -> MockResult<HttpResponse> {
+        response.headers.push(("Location".to_string(), location.to_string()));
+        Ok(response)
+    }
+
+    fn problem(typ: AcmeProblemType, detail: impl Into<String>) -> AcmeProblem {
+        let status = match typ {
+            AcmeProblemType::Unauthorized | AcmeProblemType::OrderNotReady => 403,
+            AcmeProblemType::ServerInternal => 500,
+            _ => 400,
+        };
+        AcmeProblem {
+            typ,
+            detail: Some(detail.into()),
+            status: Some(status),
+            identifier: None,
+            subproblems: vec![],
+        }
+    }
+
+    fn not_found() -> AcmeProblem {
+        AcmeProblem {
+            status: Some(404),
+            ..Self::problem(AcmeProblemType::Malformed, "no such resource")
+        }
+    }
+
+    fn problem_response(problem: AcmeProblem) -> HttpResponse {
+        HttpResponse {
+            status: problem.status.unwrap_or(400),
+            headers: vec![("Content-Type".to_string(), "application/problem+json".to_string())],
+            body: serde_json::to_vec(&problem).unwrap_or_default(),
+        }
+    }
+}
+
+impl MockAcmeState {
+    fn new_id(&mut self) -> u64 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    fn new_nonce(&mut self) -> String {
+        let nonce = MockAcmeServer::random::<16>().unwrap_or_else(|_| self.next_id.to_string());
+        self.nonces.insert(nonce.clone());
+        nonce
+    }
+
+    /// Authorization [id] when it belongs to [account]
+    fn authz(&self, account: u64, id: u64) -> MockResult<&MockAuthz> {
+        let authz = self.authzs.get(&id).ok_or_else(MockAcmeServer::not_found)?;
+        if authz.account != account {
+            return Err(MockAcmeServer::problem(
+                AcmeProblemType::Unauthorized,
+                "not your authorization",
+            ));
+        }
+        Ok(authz)
+    }
+
+    /// Order [id] when it belongs to [account]
+    fn order(&self, account: u64, id: u64) -> MockResult<&MockOrder> {
+        let order = self.orders.get(&id).ok_or_else(MockAcmeServer::not_found)?;
+        if order.account != account {
+            return Err(MockAcmeServer::problem(AcmeProblemType::Unauthorized, "not your order"));
+        }
+        Ok(order)
+    }
+}
+
+impl MockOrder {
+    /// The order, with its certificate URL once finalized
+    fn to_json(&self) -> MockResult<serde_json::Value> {
+        let mut order = serde_json::to_value(&self.order)
+            .map_err(|e| MockAcmeServer::problem(AcmeProblemType::ServerInternal, e.to_string()))?;
+        if let Some(certificate) = &self.certificate {
+            order["certificate"] = serde_json::json!(certificate);
+        }
+        Ok(order)
+    }
+}
+
+impl HttpTransport for MockAcmeServer {
+    type Error = core::convert::Infallible;
+
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, Self::Error> {
+        Ok(self.handle(request))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use rusty_jwt_tools::jwk::TryIntoJwk;
+    use serde_json::json;
+    use wasm_bindgen_test::*;
+
+    use super::*;
+    use crate::client::tests::block_on;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    /// The server does not verify the signature of the tokens
+    fn unsigned_token(claims: serde_json::Value) -> String {
+        let encode = |part: serde_json::Value| base64::prelude::BASE64_URL_SAFE_NO_PAD.encode(part.to_string());
+        format!("{}.{}.c2lnbmF0dXJl", encode(json!({"alg": "EdDSA"})), encode(claims))
+    }
+
+    struct Enrollment {
+        server: MockAcmeServer,
+        client: AcmeClient<MockAcmeServer>,
+        account_jwk: Jwk,
+        account: AcmeAccount,
+        order: AcmeOrder,
+        order_url: url::Url,
+        authzs: Vec<AcmeAuthz>,
+    }
+
+    fn enrollment() -> Enrollment {
+        let server = MockAcmeServer::try_new("https://acme.wire.com/acme/wire".parse().unwrap()).unwrap();
+        let kp = Ed25519KeyPair::generate();
+        let account_jwk = kp.public_key().try_into_jwk().unwrap();
+        let client = AcmeClient::try_new_with_transport(
+            server.clone(),
+            &server.directory_url(),
+            JwsAlgorithm::Ed25519,
+            Pem::from(kp.to_pem()),
+        );
+        let mut client = block_on(client).unwrap();
+        let account = block_on(client.new_account(None)).unwrap();
+        let identifiers = vec![AcmeIdentifier::new_device(), AcmeIdentifier::new_user()];
+        let validity = core::time::Duration::from_secs(3600);
+        let new_order = client.new_order(identifiers, validity, AcmeOrderExtensions::default(), &account);
+        let (order, order_url) = block_on(new_order).unwrap();
+        let authzs = order
+            .authorizations
+            .iter()
+            .map(|url| block_on(client.new_authz(url, &account)).unwrap())
+            .collect();
+        Enrollment {
+            server,
+            client,
+            account_jwk,
+            account,
+            order,
+            order_url,
+            authzs,
+        }
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_enroll() {
+        let Enrollment {
+            server,
+            mut client,
+            account_jwk,
+            account,
+            order,
+            order_url,
+            authzs,
+        } = enrollment();
+
+        let [dpop_chall] = order.try_get_device_authorization(&authzs).unwrap().challenges.clone();
+        assert_eq!(dpop_chall.typ, AcmeChallengeType::WireDpop01);
+        let access_token = unsigned_token(json!({
+            "chal": dpop_chall.token,
+            "client_id": AcmeIdentifier::default_client_id().to_uri(),
+        }));
+        block_on(client.dpop_chall(access_token, dpop_chall, &account)).unwrap();
+
+        let [oidc_chall] = order.try_get_user_authorization(&authzs).unwrap().challenges.clone();
+        assert_eq!(oidc_chall.typ, AcmeChallengeType::WireOidc01);
+        let id_token = unsigned_token(json!({
+            "keyauth": oidc_chall.key_authorization(&account_jwk).unwrap(),
+            "acme_aud": oidc_chall.url,
+            "name": "Alice Smith",
+        }));
+        block_on(client.oidc_chall(id_token, oidc_chall, &account)).unwrap();
+
+        let order = block_on(client.check_order(&order_url, &account)).unwrap();
+        let signing_kp = Pem::from(Ed25519KeyPair::generate().to_pem());
+        let finalize = client.finalize(&order, &account, &signing_kp);
+        let finalize = block_on(finalize).unwrap();
+        let chain = block_on(client.certificate(finalize, order, &account)).unwrap();
+
+        let identity = chain.leaf().extract_identity().unwrap();
+        assert_eq!(identity.display_name, "Alice Smith");
+        assert_eq!(identity.client_id, AcmeIdentifier::default_client_id().to_uri());
+        assert_eq!(chain.root(), Some(server.root_certificate()));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_reject_token_for_another_challenge() {
+        let Enrollment {
+            mut client,
+            account,
+            order,
+            authzs,
+            ..
+        } = enrollment();
+
+        let [dpop_chall] = order.try_get_device_authorization(&authzs).unwrap().challenges.clone();
+        let access_token = unsigned_token(json!({
+            "chal": "another-challenge",
+            "client_id": AcmeIdentifier::default_client_id().to_uri(),
+        }));
+        assert!(matches!(
+            block_on(client.dpop_chall(access_token, dpop_chall, &account)).unwrap_err(),
+            RustyAcmeError::Problem(AcmeProblem {
+                typ: AcmeProblemType::IncorrectResponse,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_not_finalize_pending_order() {
+        let Enrollment {
+            mut client,
+            account,
+            order,
+            ..
+        } = enrollment();
+
+        let signing_kp = Pem::from(Ed25519KeyPair::generate().to_pem());
+        let finalize = client.finalize(&order, &account, &signing_kp);
+        assert!(matches!(
+            block_on(finalize).unwrap_err(),
+            RustyAcmeError::Problem(AcmeProblem {
+                typ: AcmeProblemType::OrderNotReady,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_reject_unknown_certificate_to_replace() {
+        let Enrollment {
+            mut client, account, ..
+        } = enrollment();
+
+        let identifiers = vec![AcmeIdentifier::new_device(), AcmeIdentifier::new_user()];
+        let extensions = AcmeOrderExtensions {
+            replaces: Some("aYhba4dGQEHhs3uEe6CuLN4ByNQ.AIdlQyE".to_string()),
+            ..Default::default()
+        };
+        let validity = core::time::Duration::from_secs(3600);
+        assert!(matches!(
+            block_on(client.new_order(identifiers, validity, extensions, &account)).unwrap_err(),
+            RustyAcmeError::Problem(AcmeProblem {
+                typ: AcmeProblemType::Malformed,
+                ..
+            })
+        ));
+    }
+}
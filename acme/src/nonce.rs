@@ -0,0 +1,187 @@
+use core::future::Future;
+
+use crate::prelude::*;
+
+/// Response of the ACME server to a signed request
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct AcmeResponse {
+    /// body of the response
+    pub body: serde_json::Value,
+    /// 'Replay-Nonce' header, the nonce to sign the next request with
+    pub replay_nonce: Option<String>,
+}
+
+impl RustyAcme {
+    /// Times a request is signed again after the ACME server rejected its nonce, before giving up
+    pub const BAD_NONCE_RETRIES: u32 = 3;
+
+    /// Whether the ACME server rejected the nonce of a request
+    /// see [RFC 8555 Section 6.5](https://www.rfc-editor.org/rfc/rfc8555.html#section-6.5)
+    pub fn is_bad_nonce(response: &serde_json::Value) -> bool {
+        AcmeProblem::try_from_response(response)
+            .map(|problem| problem.typ == AcmeProblemType::BadNonce)
+            .unwrap_or_default()
+    }
+
+    /// Signs a request with [sign] then sends it with [send]. When the ACME server rejects its
+    /// nonce, signs it again with the nonce of the error response and sends it again, up to
+    /// [Self::BAD_NONCE_RETRIES] times.
+    /// see [RFC 8555 Section 6.5](https://www.rfc-editor.org/rfc/rfc8555.html#section-6.5)
+    ///
+    /// # Arguments
+    /// * `nonce` - to sign the first attempt with
+    /// * `sign` - builds the request with the given nonce e.g. [RustyAcme::new_order_request]
+    /// * `send` - performs the HTTP call
+    pub fn send_with_nonce_retry<E>(
+        mut nonce: String,
+        mut sign: impl FnMut(String) -> RustyAcmeResult<AcmeJws>,
+        mut send: impl FnMut(AcmeJws) -> Result<AcmeResponse, E>,
+    ) -> Result<AcmeResponse, E>
+    where
+        E: From<RustyAcmeError>,
+    {
+        for _ in 0..Self::BAD_NONCE_RETRIES {
+            let response = send(sign(nonce)?)?;
+            nonce = match Self::next_nonce(response)? {
+                Ok(response) => return Ok(response),
+                Err(nonce) => nonce,
+            };
+        }
+        let response = send(sign(nonce)?)?;
+        Self::last_attempt(response).map_err(E::from)
+    }
+
+    /// Same as [Self::send_with_nonce_retry] with an asynchronous HTTP call
+    pub async fn send_with_nonce_retry_async<E, F>(
+        mut nonce: String,
+        mut sign: impl FnMut(String) -> RustyAcmeResult<AcmeJws>,
+        mut send: impl FnMut(AcmeJws) -> F,
+    ) -> Result<AcmeResponse, E>
+    where
+        E: From<RustyAcmeError>,
+        F: Future<Output = Result<AcmeResponse, E>>,
+    {
+        for _ in 0..Self::BAD_NONCE_RETRIES {
+            let response = send(sign(nonce)?).await?;
+            nonce = match Self::next_nonce(response)? {
+                Ok(response) => return Ok(response),
+                Err(nonce) => nonce,
+            };
+        }
+        let response = send(sign(nonce)?).await?;
+        Self::last_attempt(response).map_err(E::from)
+    }
+
+    /// The response when the nonce was accepted, otherwise the nonce to retry with
+    fn next_nonce(response: AcmeResponse) -> RustyAcmeResult<Result<AcmeResponse, String>> {
+        if !Self::is_bad_nonce(&response.body) {
+            return Ok(Ok(response));
+        }
+        // the server has to provide a fresh nonce along with the error
+        let nonce = response
+            .replay_nonce
+            .ok_or(RustyAcmeError::SmallstepImplementationError(
+                "'badNonce' error without a 'Replay-Nonce' header",
+            ))?;
+        Ok(Err(nonce))
+    }
+
+    fn last_attempt(response: AcmeResponse) -> RustyAcmeResult<AcmeResponse> {
+        if Self::is_bad_nonce(&response.body) {
+            return Err(RustyAcmeError::BadNonce);
+        }
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use jwt_simple::prelude::*;
+    use serde_json::json;
+    use wasm_bindgen_test::*;
+
+    use rusty_jwt_tools::prelude::*;
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    fn bad_nonce(nonce: Option<&str>) -> AcmeResponse {
+        AcmeResponse {
+            body: json!({"type": "urn:ietf:params:acme:error:badNonce", "detail": "JWS has an invalid anti-replay nonce"}),
+            replay_nonce: nonce.map(str::to_string),
+        }
+    }
+
+    fn ok() -> AcmeResponse {
+        AcmeResponse {
+            body: json!({"status": "valid"}),
+            replay_nonce: Some("next".to_string()),
+        }
+    }
+
+    fn sign(nonce: String) -> RustyAcmeResult<AcmeJws> {
+        let kp = Pem::from(Ed25519KeyPair::generate().to_pem());
+        let url = "https://example.com/acme/new-order".parse().unwrap();
+        AcmeJws::new(JwsAlgorithm::Ed25519, nonce, &url, None, None::<serde_json::Value>, &kp)
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_resign_with_fresh_nonce() {
+        let mut nonces = vec![];
+        let mut responses = vec![ok(), bad_nonce(Some("n2")), bad_nonce(Some("n1"))];
+        let response = RustyAcme::send_with_nonce_retry::<RustyAcmeError>(
+            "n0".to_string(),
+            |nonce| {
+                nonces.push(nonce.clone());
+                sign(nonce)
+            },
+            |_| Ok(responses.pop().unwrap()),
+        )
+        .unwrap();
+        assert_eq!(response, ok());
+        assert_eq!(nonces, vec!["n0", "n1", "n2"]);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_give_up_after_retries() {
+        let mut attempts = 0;
+        let result = RustyAcme::send_with_nonce_retry::<RustyAcmeError>("n0".to_string(), sign, |_| {
+            attempts += 1;
+            Ok(bad_nonce(Some("n")))
+        });
+        assert!(matches!(result.unwrap_err(), RustyAcmeError::BadNonce));
+        assert_eq!(attempts, RustyAcme::BAD_NONCE_RETRIES + 1);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_fail_when_no_fresh_nonce() {
+        let result =
+            RustyAcme::send_with_nonce_retry::<RustyAcmeError>("n0".to_string(), sign, |_| Ok(bad_nonce(None)));
+        assert!(matches!(
+            result.unwrap_err(),
+            RustyAcmeError::SmallstepImplementationError(_)
+        ));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_not_retry_other_problems() {
+        let unauthorized = AcmeResponse {
+            body: json!({"type": "urn:ietf:params:acme:error:unauthorized"}),
+            replay_nonce: Some("n1".to_string()),
+        };
+        let mut attempts = 0;
+        let response = RustyAcme::send_with_nonce_retry::<RustyAcmeError>("n0".to_string(), sign, |_| {
+            attempts += 1;
+            Ok(unauthorized.clone())
+        })
+        .unwrap();
+        assert_eq!(response, unauthorized);
+        assert_eq!(attempts, 1);
+        assert!(!RustyAcme::is_bad_nonce(&json!(null)));
+    }
+}
@@ -0,0 +1,23 @@
+/// Notified of the exchange of an [crate::prelude::AcmeClient] with the ACME server e.g. to log it
+/// or to record the enrollment for a support ticket. Every method does nothing by default.
+///
+/// Beware that payloads carry the access and id tokens of the challenges.
+pub trait AcmeObserver: Send + Sync {
+    /// Before sending a signed request, including each retry after a rejected nonce
+    ///
+    /// # Arguments
+    /// * `protected` - decoded protected header, with the 'url', 'nonce' and either 'kid' or 'jwk'
+    /// * `payload` - decoded payload, `None` for a POST-as-GET request
+    fn on_request(&self, url: &url::Url, protected: &serde_json::Value, payload: Option<&serde_json::Value>) {
+        let _ = (url, protected, payload);
+    }
+
+    /// After receiving the response to a signed request, before it is checked
+    ///
+    /// # Arguments
+    /// * `status` - HTTP status code
+    /// * `body` - JSON body, or a string for the PEM bundle of the certificate
+    fn on_response(&self, url: &url::Url, status: u16, body: &serde_json::Value) {
+        let _ = (url, status, body);
+    }
+}
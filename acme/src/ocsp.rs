@@ -0,0 +1,422 @@
+use sha1::Digest as _;
+use x509_cert::der::{Decode as _, Encode as _};
+
+use crate::{cert_info::find_extension, prelude::*};
+
+/// 'id-ad-ocsp' access method of the 'Authority Information Access' extension
+/// see [RFC 5280 Section 4.2.2.1](https://www.rfc-editor.org/rfc/rfc5280#section-4.2.2.1)
+const ID_AD_OCSP: x509_cert::der::oid::ObjectIdentifier =
+    x509_cert::der::oid::ObjectIdentifier::new_unwrap("1.3.6.1.5.5.7.48.1");
+
+/// 'id-sha1', the hash algorithm of the certificate identifiers
+const ID_SHA1: x509_cert::der::oid::ObjectIdentifier =
+    x509_cert::der::oid::ObjectIdentifier::new_unwrap("1.3.14.3.2.26");
+
+/// see [RFC 6960 Section 4.2.1](https://www.rfc-editor.org/rfc/rfc6960#section-4.2.1)
+const ID_PKIX_OCSP_BASIC: x509_cert::der::oid::ObjectIdentifier =
+    x509_cert::der::oid::ObjectIdentifier::new_unwrap("1.3.6.1.5.5.7.48.1.1");
+
+/// Clock skew tolerated on the 'thisUpdate' of a response
+const OCSP_MAX_SKEW: time::Duration = time::Duration::minutes(5);
+
+/// Age past which a response without 'nextUpdate' is no longer trusted
+const OCSP_MAX_AGE: time::Duration = time::Duration::days(7);
+
+/// OCSP request for the leaf certificate of a [CertificateChain]
+/// see [RFC 6960 Appendix A.1](https://www.rfc-editor.org/rfc/rfc6960#appendix-A.1)
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct AcmeOcspRequest {
+    /// OCSP responder, from the 'Authority Information Access' extension of the leaf certificate
+    pub url: url::Url,
+    /// DER encoded, to POST with the 'application/ocsp-request' content type
+    pub body: Vec<u8>,
+}
+
+/// Revocation status of a certificate according to its OCSP responder
+/// see [RFC 6960 Section 2.2](https://www.rfc-editor.org/rfc/rfc6960#section-2.2)
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum OcspStatus {
+    Good,
+    Revoked,
+    /// The responder does not know about the certificate
+    Unknown,
+}
+
+impl CertificateChain {
+    /// Builds an OCSP request for the leaf certificate, to send to the responder listed in its
+    /// 'Authority Information Access' extension. Requires the issuer of the leaf in the chain.
+    pub fn ocsp_request(&self) -> RustyAcmeResult<AcmeOcspRequest> {
+        let (leaf, issuer) = self.leaf_and_issuer()?.ok_or(AcmeOcspError::MissingIssuer)?;
+        let url = Self::ocsp_responder(&leaf)?;
+
+        let request = x509_ocsp::OcspRequest {
+            tbs_request: x509_ocsp::TbsRequest {
+                version: x509_ocsp::Version::V1,
+                requestor_name: None,
+                request_list: vec![x509_ocsp::Request {
+                    req_cert: Self::ocsp_cert_id(&leaf, &issuer)?,
+                    single_request_extensions: None,
+                }],
+                request_extensions: None,
+            },
+            optional_signature: None,
+        };
+        Ok(AcmeOcspRequest {
+            url,
+            body: request.to_der()?,
+        })
+    }
+
+    /// Parses the DER encoded response of the OCSP responder to [Self::ocsp_request]. The response
+    /// has to be signed by the issuer of the leaf certificate, delegated responders are not
+    /// supported. It also has to be fresh: not past its 'nextUpdate' or, without one, issued less
+    /// than a week ago, and not issued in the future.
+    pub fn ocsp_response(&self, response: &[u8]) -> RustyAcmeResult<OcspStatus> {
+        let (leaf, issuer) = self.leaf_and_issuer()?.ok_or(AcmeOcspError::MissingIssuer)?;
+
+        let response = x509_ocsp::OcspResponse::from_der(response)?;
+        if !matches!(response.response_status, x509_ocsp::OcspResponseStatus::Successful) {
+            return Err(AcmeOcspError::Unsuccessful)?;
+        }
+        let response = response
+            .response_bytes
+            .filter(|r| r.response_type == ID_PKIX_OCSP_BASIC)
+            .ok_or(AcmeOcspError::Unsuccessful)?;
+        let response = x509_ocsp::BasicOcspResponse::from_der(response.response.as_bytes())?;
+
+        let signed = response.tbs_response_data.to_der()?;
+        if !Self::verify_issuer_signature(&issuer, &signed, response.signature.raw_bytes())? {
+            return Err(AcmeOcspError::InvalidSignature)?;
+        }
+
+        let cert_id = Self::ocsp_cert_id(&leaf, &issuer)?;
+        let single = response
+            .tbs_response_data
+            .responses
+            .iter()
+            .find(|r| {
+                r.cert_id.serial_number == cert_id.serial_number
+                    && r.cert_id.issuer_name_hash == cert_id.issuer_name_hash
+                    && r.cert_id.issuer_key_hash == cert_id.issuer_key_hash
+            })
+            .ok_or(AcmeOcspError::UnexpectedCertificate)?;
+        Self::verify_ocsp_freshness(single, time::OffsetDateTime::now_utc())?;
+        Ok(match single.cert_status {
+            x509_ocsp::CertStatus::Good(_) => OcspStatus::Good,
+            x509_ocsp::CertStatus::Revoked(_) => OcspStatus::Revoked,
+            x509_ocsp::CertStatus::Unknown(_) => OcspStatus::Unknown,
+        })
+    }
+
+    /// see [RFC 6960 Section 4.2.2.1](https://www.rfc-editor.org/rfc/rfc6960#section-4.2.2.1)
+    fn verify_ocsp_freshness(single: &x509_ocsp::SingleResponse, now: time::OffsetDateTime) -> RustyAcmeResult<()> {
+        let at = |t: &x509_ocsp::OcspGeneralizedTime| time::OffsetDateTime::UNIX_EPOCH + t.0.to_unix_duration();
+        let this_update = at(&single.this_update);
+        if this_update > now + OCSP_MAX_SKEW {
+            return Err(AcmeOcspError::NotYetValid)?;
+        }
+        let outdated = match &single.next_update {
+            Some(next_update) => at(next_update) < now,
+            None => this_update + OCSP_MAX_AGE < now,
+        };
+        if outdated {
+            return Err(AcmeOcspError::Outdated)?;
+        }
+        Ok(())
+    }
+
+    fn ocsp_responder(leaf: &x509_cert::Certificate) -> RustyAcmeResult<url::Url> {
+        let aia = find_extension::<x509_cert::ext::pkix::AuthorityInfoAccessSyntax>(
+            &leaf.tbs_certificate,
+            oid_registry::OID_PKIX_AUTHORITY_INFO_ACCESS,
+        )?
+        .ok_or(AcmeOcspError::MissingResponder)?;
+        let url = aia
+            .0
+            .iter()
+            .filter(|d| d.access_method == ID_AD_OCSP)
+            .find_map(|d| match &d.access_location {
+                x509_cert::ext::pkix::name::GeneralName::UniformResourceIdentifier(uri) => Some(uri.as_str()),
+                _ => None,
+            })
+            .ok_or(AcmeOcspError::MissingResponder)?;
+        Ok(url.parse()?)
+    }
+
+    /// Identifies [leaf] with SHA-1 hashes, which every responder supports
+    /// see [RFC 6960 Section 4.1.1](https://www.rfc-editor.org/rfc/rfc6960#section-4.1.1)
+    fn ocsp_cert_id(
+        leaf: &x509_cert::Certificate,
+        issuer: &x509_cert::Certificate,
+    ) -> RustyAcmeResult<x509_ocsp::CertId> {
+        let issuer_name = issuer.tbs_certificate.subject.to_der()?;
+        let issuer_key = issuer
+            .tbs_certificate
+            .subject_public_key_info
+            .subject_public_key
+            .raw_bytes();
+        let hash_algorithm = x509_cert::spki::AlgorithmIdentifierOwned {
+            oid: ID_SHA1,
+            parameters: Some(x509_cert::der::asn1::Null.into()),
+        };
+        Ok(x509_ocsp::CertId {
+            hash_algorithm,
+            issuer_name_hash: x509_cert::der::asn1::OctetString::new(sha1::Sha1::digest(issuer_name).as_slice())?,
+            issuer_key_hash: x509_cert::der::asn1::OctetString::new(sha1::Sha1::digest(issuer_key).as_slice())?,
+            serial_number: leaf.tbs_certificate.serial_number.clone(),
+        })
+    }
+}
+
+/// Error while checking the revocation status of a certificate with OCSP
+#[derive(Debug, thiserror::Error)]
+pub enum AcmeOcspError {
+    /// The leaf certificate does not list an OCSP responder
+    #[error("The leaf certificate does not list an OCSP responder")]
+    MissingResponder,
+    /// The certificate chain does not contain the issuer of the leaf certificate
+    #[error("The certificate chain does not contain the issuer of the leaf certificate")]
+    MissingIssuer,
+    /// The OCSP responder could not process the request
+    #[error("The OCSP responder could not process the request")]
+    Unsuccessful,
+    /// The OCSP response is not about the leaf certificate
+    #[error("The OCSP response is not about the leaf certificate")]
+    UnexpectedCertificate,
+    /// The OCSP response is not signed by the issuer of the leaf certificate
+    #[error("The OCSP response is not signed by the issuer of the leaf certificate")]
+    InvalidSignature,
+    /// The OCSP response is past its next update or, without one, too old. A fresh one has to be
+    /// requested
+    #[error("The OCSP response is outdated")]
+    Outdated,
+    /// The OCSP response claims to be issued in the future
+    #[error("The OCSP response is not yet valid")]
+    NotYetValid,
+}
+
+#[cfg(test)]
+pub mod tests {
+    use base64::Engine as _;
+    use wasm_bindgen_test::*;
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    pub const ISSUER: &str = r#"-----BEGIN CERTIFICATE-----
+MIIBxTCCAWugAwIBAgIUZe/9vflsxFIC0dcWUh0/g8GaAfMwCgYIKoZIzj0EAwIw
+LzERMA8GA1UECgwId2lyZS5jb20xGjAYBgNVBAMMEVdpcmUgT0NTUCBSb290IENB
+MCAXDTI2MTAxNjE1MTA0MVoYDzIxMjYwOTIyMTUxMDQxWjAvMREwDwYDVQQKDAh3
+aXJlLmNvbTEaMBgGA1UEAwwRV2lyZSBPQ1NQIFJvb3QgQ0EwWTATBgcqhkjOPQIB
+BggqhkjOPQMBBwNCAAS5fgf/XO4oi4veKO/7NGQdFiOizGlg3EPLZzoSHZj9em9J
+ahTm92IYs7hCVdCiI7a0xsFgVQXuNySB/ol5ynuzo2MwYTAdBgNVHQ4EFgQUbNDo
+eYZtReNjlvYnpfoHavHB0f8wHwYDVR0jBBgwFoAUbNDoeYZtReNjlvYnpfoHavHB
+0f8wDwYDVR0TAQH/BAUwAwEB/zAOBgNVHQ8BAf8EBAMCAYYwCgYIKoZIzj0EAwID
+SAAwRQIhANu9MyBmwq5gw8IV861hjZEMS+OmRR0S3/yrYuSM0DOaAiAlS2mOheee
+1vstyIx985+rGVikhbVBF1+c4mbz/29RKA==
+-----END CERTIFICATE-----"#;
+
+    /// Issued by [ISSUER], its serial number is 0x1234
+    const LEAF: &str = r#"-----BEGIN CERTIFICATE-----
+MIIB0DCCAXagAwIBAgICEjQwCgYIKoZIzj0EAwIwLzERMA8GA1UECgwId2lyZS5j
+b20xGjAYBgNVBAMMEVdpcmUgT0NTUCBSb290IENBMCAXDTI2MTAxNjE1MTA0MVoY
+DzIxMjYwOTIyMTUxMDQxWjApMREwDwYDVQQKDAh3aXJlLmNvbTEUMBIGA1UEAwwL
+QWxpY2UgU21pdGgwWTATBgcqhkjOPQIBBggqhkjOPQMBBwNCAAQyrL3a3MktevCV
+7Xq3kUMs2O4bwGJl3TdFbMjIMc8XQplDG1QkYW4bSMbuYudCVqLF9Yk0fGhOTv5p
+78qQH8ejo4GFMIGCMDAGCCsGAQUFBwEBBCQwIjAgBggrBgEFBQcwAYYUaHR0cDov
+L29jc3Aud2lyZS5jb20wDgYDVR0PAQH/BAQDAgeAMB0GA1UdDgQWBBSsxTWPTq/Y
+CfXmeO5EaoWbIHjKqjAfBgNVHSMEGDAWgBRs0Oh5hm1F42OW9iel+gdq8cHR/zAK
+BggqhkjOPQQDAgNIADBFAiEA21QnosOdClQ3xLl18Z4113npezelp1JFrBMz9gex
+B5MCIDxsDfBfJRbZ5poMnWQqa3lmxhoD93PmJts6g5jGxI+i
+-----END CERTIFICATE-----"#;
+
+    /// Issued by [ISSUER], its serial number is 0x5678
+    const REVOKED_LEAF: &str = r#"-----BEGIN CERTIFICATE-----
+MIIBzjCCAXSgAwIBAgICVngwCgYIKoZIzj0EAwIwLzERMA8GA1UECgwId2lyZS5j
+b20xGjAYBgNVBAMMEVdpcmUgT0NTUCBSb290IENBMCAXDTI2MTAxNjE1MTA0MVoY
+DzIxMjYwOTIyMTUxMDQxWjAnMREwDwYDVQQKDAh3aXJlLmNvbTESMBAGA1UEAwwJ
+Qm9iIFNtaXRoMFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAEMqy92tzJLXrwle16
+t5FDLNjuG8BiZd03RWzIyDHPF0KZQxtUJGFuG0jG7mLnQlaixfWJNHxoTk7+ae/K
+kB/Ho6OBhTCBgjAwBggrBgEFBQcBAQQkMCIwIAYIKwYBBQUHMAGGFGh0dHA6Ly9v
+Y3NwLndpcmUuY29tMA4GA1UdDwEB/wQEAwIHgDAdBgNVHQ4EFgQUrMU1j06v2An1
+5njuRGqFmyB4yqowHwYDVR0jBBgwFoAUbNDoeYZtReNjlvYnpfoHavHB0f8wCgYI
+KoZIzj0EAwIDSAAwRQIhAJojqFZUk+jHD09vDvmEJph/La+eWjZqGCYPI1aFFVhW
+AiA2ihBuORjBYpXsCNvznXkIRe1eHBYvgQmUIldWCOyJJg==
+-----END CERTIFICATE-----"#;
+
+    /// Base64 encoded response of [ISSUER] for [LEAF]
+    const GOOD_RESPONSE: &str = r#"MIIC8goBAKCCAuswggLnBgkrBgEFBQcwAQEEggLYMIIC1DCBq6ExMC8xETAPBgNV
+BAoMCHdpcmUuY29tMRowGAYDVQQDDBFXaXJlIE9DU1AgUm9vdCBDQRgPMjAyNjEw
+MTYxNTEwNDFaMGUwYzA7MAkGBSsOAwIaBQAEFL9/++D9Rrr754tyl8XMwGo+bqZC
+BBRs0Oh5hm1F42OW9iel+gdq8cHR/wICEjSAABgPMjAyNjEwMTYxNTEwNDFaoBEY
+DzIxMjYwOTIyMTUxMDQxWjAKBggqhkjOPQQDAgNHADBEAiBDVxtVRblYcqh5FB/b
+/YRp9d6OAabKk6gYlf51kQliuQIgMM364d1TGdJcDe10GQ7TXb00pU7ZZI5B5egr
+w4sCPXmgggHNMIIByTCCAcUwggFroAMCAQICFGXv/b35bMRSAtHXFlIdP4PBmgHz
+MAoGCCqGSM49BAMCMC8xETAPBgNVBAoMCHdpcmUuY29tMRowGAYDVQQDDBFXaXJl
+IE9DU1AgUm9vdCBDQTAgFw0yNjEwMTYxNTEwNDFaGA8yMTI2MDkyMjE1MTA0MVow
+LzERMA8GA1UECgwId2lyZS5jb20xGjAYBgNVBAMMEVdpcmUgT0NTUCBSb290IENB
+MFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAEuX4H/1zuKIuL3ijv+zRkHRYjosxp
+YNxDy2c6Eh2Y/XpvSWoU5vdiGLO4QlXQoiO2tMbBYFUF7jckgf6Jecp7s6NjMGEw
+HQYDVR0OBBYEFGzQ6HmGbUXjY5b2J6X6B2rxwdH/MB8GA1UdIwQYMBaAFGzQ6HmG
+bUXjY5b2J6X6B2rxwdH/MA8GA1UdEwEB/wQFMAMBAf8wDgYDVR0PAQH/BAQDAgGG
+MAoGCCqGSM49BAMCA0gAMEUCIQDbvTMgZsKuYMPCFfOtYY2RDEvjpkUdEt/8q2Lk
+jNAzmgIgJUtpjoXnntb7LciMffOfqxlYpIW1QRdfnOJm8/9vUSg="#;
+
+    /// Base64 encoded response of [ISSUER] for [REVOKED_LEAF]
+    const REVOKED_RESPONSE: &str = r#"MIIDBQoBAKCCAv4wggL6BgkrBgEFBQcwAQEEggLrMIIC5zCBvKExMC8xETAPBgNV
+BAoMCHdpcmUuY29tMRowGAYDVQQDDBFXaXJlIE9DU1AgUm9vdCBDQRgPMjAyNjEw
+MTYxNTEwNDFaMHYwdDA7MAkGBSsOAwIaBQAEFL9/++D9Rrr754tyl8XMwGo+bqZC
+BBRs0Oh5hm1F42OW9iel+gdq8cHR/wICVnihERgPMjAyNjEwMTYwMDAwMDBaGA8y
+MDI2MTAxNjE1MTA0MVqgERgPMjEyNjA5MjIxNTEwNDFaMAoGCCqGSM49BAMCA0kA
+MEYCIQCUJGPw7pGvTJqyQI6jqPEPHeyKgeIQWMxYLEkIyjUnrgIhAPbbLzOm5tQc
+XAwR4MvTgDSXyQTelh9trcF7zu2laucgoIIBzTCCAckwggHFMIIBa6ADAgECAhRl
+7/29+WzEUgLR1xZSHT+DwZoB8zAKBggqhkjOPQQDAjAvMREwDwYDVQQKDAh3aXJl
+LmNvbTEaMBgGA1UEAwwRV2lyZSBPQ1NQIFJvb3QgQ0EwIBcNMjYxMDE2MTUxMDQx
+WhgPMjEyNjA5MjIxNTEwNDFaMC8xETAPBgNVBAoMCHdpcmUuY29tMRowGAYDVQQD
+DBFXaXJlIE9DU1AgUm9vdCBDQTBZMBMGByqGSM49AgEGCCqGSM49AwEHA0IABLl+
+B/9c7iiLi94o7/s0ZB0WI6LMaWDcQ8tnOhIdmP16b0lqFOb3YhizuEJV0KIjtrTG
+wWBVBe43JIH+iXnKe7OjYzBhMB0GA1UdDgQWBBRs0Oh5hm1F42OW9iel+gdq8cHR
+/zAfBgNVHSMEGDAWgBRs0Oh5hm1F42OW9iel+gdq8cHR/zAPBgNVHRMBAf8EBTAD
+AQH/MA4GA1UdDwEB/wQEAwIBhjAKBggqhkjOPQQDAgNIADBFAiEA270zIGbCrmDD
+whXzrWGNkQxL46ZFHRLf/Kti5IzQM5oCICVLaY6F557W+y3IjH3zn6sZWKSFtUEX
+X5ziZvP/b1Eo"#;
+
+    /// Issued by another CA, whose responses are not fresh
+    const STALE_ISSUER: &str = r#"-----BEGIN CERTIFICATE-----
+MIIB0jCCAXegAwIBAgIUPWwnTVX282ydHbdk97/fq4/H4lgwCgYIKoZIzj0EAwIw
+NTERMA8GA1UECgwId2lyZS5jb20xIDAeBgNVBAMMF1dpcmUgU3RhbGUgT0NTUCBS
+b290IENBMCAXDTI2MTAxNjE1MDAwMFoYDzIxMjYwOTIyMTUwMDAwWjA1MREwDwYD
+VQQKDAh3aXJlLmNvbTEgMB4GA1UEAwwXV2lyZSBTdGFsZSBPQ1NQIFJvb3QgQ0Ew
+WTATBgcqhkjOPQIBBggqhkjOPQMBBwNCAARcs1tZ11kbskoDK7MowUaSBe4jlEqk
+23YcqEy1pk1qhiumBnUcbEc7lrzTSHyROIw9ER9CbQ2JuWD6rg+EmQIqo2MwYTAd
+BgNVHQ4EFgQULd7HFY14gaivqAh3nYE4PRfLv/EwHwYDVR0jBBgwFoAULd7HFY14
+gaivqAh3nYE4PRfLv/EwDwYDVR0TAQH/BAUwAwEB/zAOBgNVHQ8BAf8EBAMCAYYw
+CgYIKoZIzj0EAwIDSQAwRgIhAJ5gF2CZr39UmFQH6EZxe0T8FJemh/3kvubNNn1I
+txJAAiEAufB5qVUDMdg9dE7rAQlRhHQzvmLxZFbmefP/+BgWXXk=
+-----END CERTIFICATE-----"#;
+
+    /// Issued by [STALE_ISSUER], its serial number is 0x9abc
+    const STALE_LEAF: &str = r#"-----BEGIN CERTIFICATE-----
+MIIB2DCCAX2gAwIBAgIDAJq8MAoGCCqGSM49BAMCMDUxETAPBgNVBAoMCHdpcmUu
+Y29tMSAwHgYDVQQDDBdXaXJlIFN0YWxlIE9DU1AgUm9vdCBDQTAgFw0yNjEwMTYx
+NTAwMDBaGA8yMTI2MDkyMjE1MDAwMFowKTERMA8GA1UECgwId2lyZS5jb20xFDAS
+BgNVBAMMC0FsaWNlIFNtaXRoMFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAEd3O7
+WoE+5B4ExHzUXo48F5MyQO4WRR9j84fYFapIICccEP+hAadzyg8eHnAFsvSxTrLd
+smQ6maescd7ozatACaOBhTCBgjAwBggrBgEFBQcBAQQkMCIwIAYIKwYBBQUHMAGG
+FGh0dHA6Ly9vY3NwLndpcmUuY29tMA4GA1UdDwEB/wQEAwIHgDAdBgNVHQ4EFgQU
+yGM3TyArjBKrMZGr3ChszVLawIcwHwYDVR0jBBgwFoAULd7HFY14gaivqAh3nYE4
+PRfLv/EwCgYIKoZIzj0EAwIDSQAwRgIhAJ3UmrSrbBzCikLrEH17vtVsop6qn9Tq
+pDZkN2+Rl17qAiEA51bKckPtGG45VEERY378n96RCg6cA3ay9Q7iCJ4eW3g=
+-----END CERTIFICATE-----"#;
+
+    /// Base64 encoded response of [STALE_ISSUER] for [STALE_LEAF], past its 'nextUpdate'
+    const EXPIRED_RESPONSE: &str = r#"MIIBKQoBAKCCASIwggEeBgkrBgEFBQcwAQEEggEPMIIBCzCBsqE3MDUxETAPBgNVBAoMCHdpcmUu
+Y29tMSAwHgYDVQQDDBdXaXJlIFN0YWxlIE9DU1AgUm9vdCBDQRgPMjAyNjEwMTYxNjQ3MjhaMGYw
+ZDA8MAkGBSsOAwIaBQAEFKAkmL+GrRlncLB83/zyDWY+z59lBBQt3scVjXiBqK+oCHedgTg9F8u/
+8QIDAJq8gAAYDzIwMjYxMDAxMDAwMDAwWqARGA8yMDI2MTAwODAwMDAwMFowCgYIKoZIzj0EAwID
+SAAwRQIgE6mq2DhesuDtZZ97RJAUhaVacBrQx8XJIEDbJf7mQpUCIQDvlQ3R9lFVJxwHk55mK38Z
+bupaVpgeper5bbMvEGMpAg=="#;
+
+    /// Base64 encoded response of [STALE_ISSUER] for [STALE_LEAF], without 'nextUpdate' and
+    /// issued more than a week ago
+    const OLD_RESPONSE: &str = r#"MIIBFQoBAKCCAQ4wggEKBgkrBgEFBQcwAQEEgfwwgfkwgZ+hNzA1MREwDwYDVQQKDAh3aXJlLmNv
+bTEgMB4GA1UEAwwXV2lyZSBTdGFsZSBPQ1NQIFJvb3QgQ0EYDzIwMjYxMDE2MTY0NzI4WjBTMFEw
+PDAJBgUrDgMCGgUABBSgJJi/hq0ZZ3CwfN/88g1mPs+fZQQULd7HFY14gaivqAh3nYE4PRfLv/EC
+AwCavIAAGA8yMDI2MTAwMTAwMDAwMFowCgYIKoZIzj0EAwIDSQAwRgIhAMSzUQMi88TiRLKA2W06
+bnShHDsGLHxbPm0UWS6gPhRYAiEAnJOL9b2rAK/L7nYBD7WGzrM38FchmvdefswusahEEbE="#;
+
+    /// Base64 encoded response of [STALE_ISSUER] for [STALE_LEAF], issued in 2126
+    const FUTURE_RESPONSE: &str = r#"MIIBKQoBAKCCASIwggEeBgkrBgEFBQcwAQEEggEPMIIBCzCBsqE3MDUxETAPBgNVBAoMCHdpcmUu
+Y29tMSAwHgYDVQQDDBdXaXJlIFN0YWxlIE9DU1AgUm9vdCBDQRgPMjAyNjEwMTYxNjQ3MjhaMGYw
+ZDA8MAkGBSsOAwIaBQAEFKAkmL+GrRlncLB83/zyDWY+z59lBBQt3scVjXiBqK+oCHedgTg9F8u/
+8QIDAJq8gAAYDzIxMjYwMTAxMDAwMDAwWqARGA8yMTI2MDEwODAwMDAwMFowCgYIKoZIzj0EAwID
+SAAwRQIhALuCz7fiyg1VKZSz6biFaemm2GDVB+0BMZePB5P0S6isAiA6ijW9ppRPbQHf985uTRap
+1bWnA2RI8SobeL7LhWKPgQ=="#;
+
+    /// Request for [LEAF], without nonce
+    const REQUEST: &str = "30433041303f303d303b300906052b0e03021a05000414bf7ffbe0fd46bafbe78b7297c5ccc06a3e6ea64204146cd0e879866d45e36396f627a5fa076af1c1d1ff02021234";
+
+    fn chain(leaf: &str) -> CertificateChain {
+        CertificateChain::try_from_pem(&format!("{leaf}\n{ISSUER}")).unwrap()
+    }
+
+    fn response(b64: &str) -> Vec<u8> {
+        let b64 = b64.split_whitespace().collect::<String>();
+        base64::prelude::BASE64_STANDARD.decode(b64).unwrap()
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_build_request() {
+        let request = chain(LEAF).ocsp_request().unwrap();
+        assert_eq!(request.url.as_str(), "http://ocsp.wire.com/");
+        assert_eq!(hex::encode(request.body), REQUEST);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_parse_status() {
+        let status = chain(LEAF).ocsp_response(&response(GOOD_RESPONSE)).unwrap();
+        assert_eq!(status, OcspStatus::Good);
+        let status = chain(REVOKED_LEAF).ocsp_response(&response(REVOKED_RESPONSE)).unwrap();
+        assert_eq!(status, OcspStatus::Revoked);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_fail_when_response_about_another_certificate() {
+        let result = chain(REVOKED_LEAF).ocsp_response(&response(GOOD_RESPONSE));
+        assert!(matches!(
+            result.unwrap_err(),
+            RustyAcmeError::OcspError(AcmeOcspError::UnexpectedCertificate)
+        ));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_fail_when_not_signed_by_issuer() {
+        let mut response = response(GOOD_RESPONSE);
+        let parsed = x509_ocsp::OcspResponse::from_der(&response).unwrap();
+        let basic = parsed.response_bytes.unwrap().response;
+        let signature = x509_ocsp::BasicOcspResponse::from_der(basic.as_bytes())
+            .unwrap()
+            .signature;
+        let signature = signature.raw_bytes();
+        // alter the last byte of the signature, the response stays well-formed
+        let position = response.windows(signature.len()).position(|w| w == signature).unwrap();
+        response[position + signature.len() - 1] ^= 1;
+
+        let result = chain(LEAF).ocsp_response(&response);
+        assert!(matches!(
+            result.unwrap_err(),
+            RustyAcmeError::OcspError(AcmeOcspError::InvalidSignature)
+        ));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_fail_when_response_not_fresh() {
+        let chain = CertificateChain::try_from_pem(&format!("{STALE_LEAF}\n{STALE_ISSUER}")).unwrap();
+        for stale in [EXPIRED_RESPONSE, OLD_RESPONSE] {
+            assert!(matches!(
+                chain.ocsp_response(&response(stale)).unwrap_err(),
+                RustyAcmeError::OcspError(AcmeOcspError::Outdated)
+            ));
+        }
+        assert!(matches!(
+            chain.ocsp_response(&response(FUTURE_RESPONSE)).unwrap_err(),
+            RustyAcmeError::OcspError(AcmeOcspError::NotYetValid)
+        ));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_fail_without_issuer() {
+        let chain = CertificateChain::try_from_pem(LEAF).unwrap();
+        assert!(matches!(
+            chain.ocsp_request().unwrap_err(),
+            RustyAcmeError::OcspError(AcmeOcspError::MissingIssuer)
+        ));
+    }
+}
@@ -1,4 +1,5 @@
 use crate::identifier::CanonicalIdentifier;
+use crate::link::link_targets;
 use rusty_jwt_tools::prelude::*;
 use std::collections::HashSet;
 
@@ -8,33 +9,69 @@ use crate::prelude::*;
 impl RustyAcme {
     /// create a new order
     /// see [RFC 8555 Section 7.4](https://www.rfc-editor.org/rfc/rfc8555.html#section-7.4).
+    ///
+    /// # Arguments
+    /// * `validity` - of the certificate, either a period or how long from now it expires
     #[allow(clippy::too_many_arguments)]
     pub fn new_order_request(
         display_name: &str,
         client_id: ClientId,
         handle: &Handle,
-        expiry: core::time::Duration,
+        validity: impl Into<AcmeOrderValidity>,
+        extensions: AcmeOrderExtensions,
         directory: &AcmeDirectory,
         account: &AcmeAccount,
-        alg: JwsAlgorithm,
+        alg: impl Into<AcmeJwsAlgorithm>,
         kp: &Pem,
         previous_nonce: String,
     ) -> RustyAcmeResult<AcmeJws> {
-        // Extract the account URL from previous response which created a new account
-        let acct_url = account.acct_url()?;
-
         let domain = client_id.domain.clone();
         let handle = handle.try_to_qualified(&domain)?;
         let device_identifier =
             AcmeIdentifier::try_new_device(client_id, handle.clone(), display_name.to_string(), domain.clone())?;
         let user_identifier = AcmeIdentifier::try_new_user(handle, display_name.to_string(), domain)?;
 
-        let not_before = time::OffsetDateTime::now_utc();
-        let not_after = not_before + expiry;
+        Self::new_order_request_with_identifiers(
+            vec![device_identifier, user_identifier],
+            validity,
+            extensions,
+            directory,
+            account,
+            alg,
+            kp,
+            previous_nonce,
+        )
+    }
+
+    /// create a new order for any number of identifiers, which all have to belong to the same
+    /// Wire user
+    /// see [RFC 8555 Section 7.4](https://www.rfc-editor.org/rfc/rfc8555.html#section-7.4).
+    ///
+    /// # Arguments
+    /// * `validity` - of the certificate, either a period or how long from now it expires
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_order_request_with_identifiers(
+        identifiers: Vec<AcmeIdentifier>,
+        validity: impl Into<AcmeOrderValidity>,
+        extensions: AcmeOrderExtensions,
+        directory: &AcmeDirectory,
+        account: &AcmeAccount,
+        alg: impl Into<AcmeJwsAlgorithm>,
+        kp: &Pem,
+        previous_nonce: String,
+    ) -> RustyAcmeResult<AcmeJws> {
+        // Extract the account URL from previous response which created a new account
+        let acct_url = account.acct_url()?;
+
+        AcmeOrder::verify_identifiers(&identifiers)?;
+
+        let validity = validity.into();
+        validity.verify()?;
         let payload = AcmeOrderRequest {
-            identifiers: vec![device_identifier, user_identifier],
-            not_before: Some(not_before),
-            not_after: Some(not_after),
+            identifiers,
+            not_before: validity.not_before,
+            not_after: validity.not_after,
+            extensions,
         };
         let req = AcmeJws::new(
             alg,
@@ -50,6 +87,7 @@ impl RustyAcme {
     /// parse response from order creation
     /// [RFC 8555 Section 7.4](https://www.rfc-editor.org/rfc/rfc8555.html#section-7.4)
     pub fn new_order_response(response: serde_json::Value) -> RustyAcmeResult<AcmeOrder> {
+        AcmeProblem::check(&response)?;
         let order = serde_json::from_value::<AcmeOrder>(response)?;
         match order.status {
             AcmeOrderStatus::Pending => {}
@@ -73,22 +111,17 @@ impl RustyAcme {
     pub fn check_order_request(
         order_url: url::Url,
         account: &AcmeAccount,
-        alg: JwsAlgorithm,
+        alg: impl Into<AcmeJwsAlgorithm>,
         kp: &Pem,
         previous_nonce: String,
     ) -> RustyAcmeResult<AcmeJws> {
-        // Extract the account URL from previous response which created a new account
-        let acct_url = account.acct_url()?;
-
-        // No payload required for authz
-        let payload = None::<serde_json::Value>;
-        let req = AcmeJws::new(alg, previous_nonce, &order_url, Some(&acct_url), payload, kp)?;
-        Ok(req)
+        Self::post_as_get(&order_url, account, alg, kp, previous_nonce)
     }
 
     /// parse response from order check
     /// see [RFC 8555 Section 7.4](https://www.rfc-editor.org/rfc/rfc8555.html#section-7.4)
     pub fn check_order_response(response: serde_json::Value) -> RustyAcmeResult<AcmeOrder> {
+        AcmeProblem::check(&response)?;
         let order = serde_json::from_value::<AcmeOrder>(response)?;
         match order.status {
             AcmeOrderStatus::Ready => {}
@@ -119,6 +152,49 @@ impl RustyAcme {
     }
 }
 
+// List the account orders e.g. to find the ones left dangling by a crash
+impl RustyAcme {
+    /// list the orders of an account, one page at a time. Starts with the account 'orders' URL
+    /// then follows [AcmeOrderList::next] until there is no next page. Each order can then be
+    /// resumed with [RustyAcme::check_order_request].
+    /// see [RFC 8555 Section 7.1.2.1](https://www.rfc-editor.org/rfc/rfc8555.html#section-7.1.2.1)
+    ///
+    /// # Arguments
+    /// * `page` - next page URL, `None` for the first one
+    pub fn list_orders_request(
+        page: Option<&url::Url>,
+        account: &AcmeAccount,
+        alg: impl Into<AcmeJwsAlgorithm>,
+        kp: &Pem,
+        previous_nonce: String,
+    ) -> RustyAcmeResult<AcmeJws> {
+        let url = page
+            .or(account.orders.as_ref())
+            .ok_or(RustyAcmeError::SmallstepImplementationError(
+                "Account should have 'orders' url",
+            ))?;
+        Self::post_as_get(url, account, alg, kp, previous_nonce)
+    }
+
+    /// parse the response from `POST /acme/account/{account-id}/orders`
+    ///
+    /// # Arguments
+    /// * `links` - values of every 'Link' header of the response, which points to the next page
+    pub fn list_orders_response(response: serde_json::Value, links: &[&str]) -> RustyAcmeResult<AcmeOrderList> {
+        AcmeProblem::check(&response)?;
+        let mut list = serde_json::from_value::<AcmeOrderList>(response)
+            .map_err(|_| RustyAcmeError::SmallstepImplementationError("Invalid orders list response"))?;
+        list.next = link_targets(links, "next")
+            .next()
+            .map(|url| {
+                url.parse()
+                    .map_err(|_| RustyAcmeError::SmallstepImplementationError("Invalid 'next' link in orders list"))
+            })
+            .transpose()?;
+        Ok(list)
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum AcmeOrderError {
     /// step-ca flagged this order as invalid
@@ -130,9 +206,58 @@ pub enum AcmeOrderError {
     /// This order is expired
     #[error("This order is expired")]
     Expired,
-    /// This order should only have the 2 Wire identifiers
-    #[error("This order should only have the 2 Wire identifiers")]
+    /// This order should only have distinct Wire identifiers of a single user
+    #[error("This order should only have distinct Wire identifiers of a single user")]
     WrongIdentifiers,
+    /// The requested certificate validity ends before it starts or is already over
+    #[error("The requested certificate validity is invalid")]
+    InvalidValidity,
+}
+
+/// Validity period requested for the certificate. Either bound can be left to the ACME server.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct AcmeOrderValidity {
+    /// Start of the validity period
+    pub not_before: Option<time::OffsetDateTime>,
+    /// End of the validity period
+    pub not_after: Option<time::OffsetDateTime>,
+}
+
+impl AcmeOrderValidity {
+    /// Parses [RFC 3339](https://www.rfc-editor.org/rfc/rfc3339) dates
+    pub fn try_from_rfc3339(not_before: Option<&str>, not_after: Option<&str>) -> RustyAcmeResult<Self> {
+        let parse = |date: &str| {
+            time::OffsetDateTime::parse(date, &time::format_description::well_known::Rfc3339)
+                .map_err(|_| RustyAcmeError::ClientImplementationError("validity dates have to be in RFC 3339"))
+        };
+        Ok(Self {
+            not_before: not_before.map(parse).transpose()?,
+            not_after: not_after.map(parse).transpose()?,
+        })
+    }
+
+    fn verify(&self) -> RustyAcmeResult<()> {
+        let ends_before_start = matches!((self.not_before, self.not_after), (Some(nbf), Some(naf)) if naf <= nbf);
+        let is_over = self
+            .not_after
+            .map(|naf| naf <= time::OffsetDateTime::now_utc())
+            .unwrap_or_default();
+        if ends_before_start || is_over {
+            return Err(AcmeOrderError::InvalidValidity)?;
+        }
+        Ok(())
+    }
+}
+
+/// From now until [expiry] from now
+impl From<core::time::Duration> for AcmeOrderValidity {
+    fn from(expiry: core::time::Duration) -> Self {
+        let not_before = time::OffsetDateTime::now_utc();
+        Self {
+            not_before: Some(not_before),
+            not_after: Some(not_before + expiry),
+        }
+    }
 }
 
 /// For creating an order
@@ -151,44 +276,72 @@ pub struct AcmeOrderRequest {
     /// [RFC3339](https://www.rfc-editor.org/rfc/rfc3339)
     #[serde(skip_serializing_if = "Option::is_none", with = "time::serde::rfc3339::option")]
     pub not_after: Option<time::OffsetDateTime>,
+    /// Non-standard fields
+    #[serde(flatten)]
+    pub extensions: AcmeOrderExtensions,
+}
+
+/// Order fields beyond RFC 8555, with which the smallstep ACME server selects how to issue the
+/// certificate. Sent in the order request and echoed back in the order.
+#[derive(Debug, Clone, Default, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct AcmeOrderExtensions {
+    /// Name of the certificate profile to issue the certificate with
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    /// Data passed to the certificate template
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template_data: Option<serde_json::Value>,
+    /// Identifier of the certificate this order renews, see [RustyAcme::renewal_info_cert_id]. The
+    /// server might then reuse the authorizations of that certificate
+    /// see [draft-ietf-acme-ari Section 5](https://datatracker.ietf.org/doc/html/draft-ietf-acme-ari#section-5)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replaces: Option<String>,
+    /// Any other field, as is
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, serde_json::Value>,
 }
 
 /// Result of an order creation
 /// see [RFC 8555 Section 7.4](https://www.rfc-editor.org/rfc/rfc8555.html#section-7.4)
 #[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct AcmeOrder {
     pub status: AcmeOrderStatus,
     pub finalize: url::Url,
-    pub identifiers: [AcmeIdentifier; 2],
-    pub authorizations: [url::Url; 2],
-    #[serde(skip_serializing_if = "Option::is_none", with = "time::serde::rfc3339::option")]
+    pub identifiers: Vec<AcmeIdentifier>,
+    pub authorizations: Vec<url::Url>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "time::serde::rfc3339::option"
+    )]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<String>"))]
     pub expires: Option<time::OffsetDateTime>,
-    #[serde(skip_serializing_if = "Option::is_none", with = "time::serde::rfc3339::option")]
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "time::serde::rfc3339::option"
+    )]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<String>"))]
     pub not_before: Option<time::OffsetDateTime>,
-    #[serde(skip_serializing_if = "Option::is_none", with = "time::serde::rfc3339::option")]
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "time::serde::rfc3339::option"
+    )]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<String>"))]
     pub not_after: Option<time::OffsetDateTime>,
+    /// Non-standard fields, as sent in the order request
+    #[serde(flatten)]
+    pub extensions: AcmeOrderExtensions,
 }
 
 impl AcmeOrder {
     pub fn verify(&self) -> RustyAcmeResult<()> {
-        let [ref a, ref b] = self
-            .identifiers
-            .iter()
-            .collect::<HashSet<_>>() // ensures uniqueness
-            .iter()
-            .map(|i| i.to_wire_identifier())
-            .collect::<RustyAcmeResult<Vec<_>>>()?[..]
-        else {
-            return Err(AcmeOrderError::WrongIdentifiers)?;
-        };
-
-        let same_handle = a.handle == b.handle;
-        let same_display_name = a.display_name == b.display_name;
-        let same_domain = a.domain == b.domain;
-        if !(same_handle && same_display_name && same_domain) {
-            return Err(AcmeOrderError::WrongIdentifiers)?;
-        }
+        Self::verify_identifiers(&self.identifiers)?;
 
         let now = time::OffsetDateTime::now_utc().unix_timestamp();
 
@@ -222,6 +375,33 @@ impl AcmeOrder {
         Ok(())
     }
 
+    /// Identifiers have to be distinct and to all belong to the same user
+    pub(crate) fn verify_identifiers(identifiers: &[AcmeIdentifier]) -> RustyAcmeResult<()> {
+        let unique = identifiers.iter().collect::<HashSet<_>>(); // ensures uniqueness
+        if unique.len() != identifiers.len() {
+            return Err(AcmeOrderError::WrongIdentifiers)?;
+        }
+
+        let identifiers = identifiers
+            .iter()
+            .map(AcmeIdentifier::to_wire_identifier)
+            .collect::<RustyAcmeResult<Vec<_>>>()?;
+        let [first, others @ ..] = &identifiers[..] else {
+            return Err(AcmeOrderError::WrongIdentifiers)?;
+        };
+        let same_user = others.iter().all(|other| {
+            let same_handle = first.handle == other.handle;
+            let same_display_name =
+                DisplayName::from(first.display_name.as_str()) == DisplayName::from(other.display_name.as_str());
+            let same_domain = domain::eq(&first.domain, &other.domain);
+            same_handle && same_display_name && same_domain
+        });
+        if !same_user {
+            return Err(AcmeOrderError::WrongIdentifiers)?;
+        }
+        Ok(())
+    }
+
     /// A Wire Order has 2 identifiers. For simplification purposes, since they share most of their fields together we
     /// merge them to access the fields
     pub fn try_get_coalesce_identifier(&self) -> RustyAcmeResult<CanonicalIdentifier> {
@@ -236,8 +416,43 @@ impl AcmeOrder {
             .try_into()
     }
 
-    pub fn try_get_user_authorization(&self) -> RustyAcmeResult<AcmeAuthz> {
-        todo!()
+    /// Among the fetched [authzs], the authorization of one of this order's identifiers.
+    /// Authorizations are not necessarily listed in the same order as the identifiers, hence
+    /// they are matched by identifier.
+    pub fn try_get_authorization<'a>(
+        &self,
+        identifier: &AcmeIdentifier,
+        authzs: &'a [AcmeAuthz],
+    ) -> RustyAcmeResult<&'a AcmeAuthz> {
+        if !self.identifiers.contains(identifier) {
+            return Err(AcmeOrderError::WrongIdentifiers)?;
+        }
+        authzs
+            .iter()
+            .find(|authz| &authz.identifier == identifier)
+            .ok_or(RustyAcmeError::ClientImplementationError(
+                "every authorization of the order has to be fetched first",
+            ))
+    }
+
+    /// Among the fetched [authzs], the authorization of this order's user identifier
+    pub fn try_get_user_authorization<'a>(&self, authzs: &'a [AcmeAuthz]) -> RustyAcmeResult<&'a AcmeAuthz> {
+        let identifier = self
+            .identifiers
+            .iter()
+            .find(|i| matches!(i, AcmeIdentifier::WireappUser(_)))
+            .ok_or(RustyAcmeError::OrderError(AcmeOrderError::WrongIdentifiers))?;
+        self.try_get_authorization(identifier, authzs)
+    }
+
+    /// Among the fetched [authzs], the authorization of this order's device identifier
+    pub fn try_get_device_authorization<'a>(&self, authzs: &'a [AcmeAuthz]) -> RustyAcmeResult<&'a AcmeAuthz> {
+        let identifier = self
+            .identifiers
+            .iter()
+            .find(|i| matches!(i, AcmeIdentifier::WireappDevice(_)))
+            .ok_or(RustyAcmeError::OrderError(AcmeOrderError::WrongIdentifiers))?;
+        self.try_get_authorization(identifier, authzs)
     }
 }
 
@@ -251,8 +466,8 @@ impl Default for AcmeOrder {
             finalize: "https://acme-server/acme/order/n8LovurSfUFeeGSzD8nuGQwOUeIfSjhs/finalize"
                 .parse()
                 .unwrap(),
-            identifiers: [AcmeIdentifier::new_user(), AcmeIdentifier::new_device()],
-            authorizations: [
+            identifiers: vec![AcmeIdentifier::new_user(), AcmeIdentifier::new_device()],
+            authorizations: vec![
                 "https://acme-server/acme/wire/authz/0DpEeMVjTpOk615lIRvihqEyZLW8CsMH"
                     .parse()
                     .unwrap(),
@@ -263,13 +478,26 @@ impl Default for AcmeOrder {
             expires: Some(tomorrow),
             not_before: Some(now),
             not_after: Some(tomorrow),
+            extensions: AcmeOrderExtensions::default(),
         }
     }
 }
 
+/// A page of the orders of an account
+/// see [RFC 8555 Section 7.1.2.1](https://www.rfc-editor.org/rfc/rfc8555.html#section-7.1.2.1)
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct AcmeOrderList {
+    /// URLs of the orders, to fetch each one of them
+    pub orders: Vec<url::Url>,
+    /// URL of the next page, from the 'Link' header of the response
+    #[serde(skip)]
+    pub next: Option<url::Url>,
+}
+
 /// see [RFC 8555 Section 7.1.6](https://www.rfc-editor.org/rfc/rfc8555.html#section-7.1.6)
 #[derive(Debug, Copy, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "lowercase")]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum AcmeOrderStatus {
     Pending,
     Ready,
@@ -326,6 +554,96 @@ pub mod tests {
         }
     }
 
+    mod extensions {
+        use jwt_simple::prelude::*;
+
+        use crate::directory::tests::directory;
+
+        use super::*;
+
+        #[test]
+        #[wasm_bindgen_test]
+        fn should_send_extensions() {
+            let kp = Pem::from(Ed25519KeyPair::generate().to_pem());
+            let directory = directory(None, None, None);
+            let extensions = AcmeOrderExtensions {
+                profile: Some("wire-client".to_string()),
+                template_data: Some(json!({"team": "wire"})),
+                ..Default::default()
+            };
+            let req = RustyAcme::new_order_request(
+                "Alice Smith",
+                AcmeIdentifier::default_client_id(),
+                &Handle::from("alice_wire"),
+                core::time::Duration::from_secs(3600),
+                extensions,
+                &directory,
+                &AcmeAccount::default(),
+                JwsAlgorithm::Ed25519,
+                &kp,
+                "nonce".to_string(),
+            )
+            .unwrap();
+            let payload = req.decoded_payload().unwrap().unwrap();
+            assert_eq!(payload["profile"], json!("wire-client"));
+            assert_eq!(payload["templateData"], json!({"team": "wire"}));
+        }
+
+        #[test]
+        #[wasm_bindgen_test]
+        fn should_roundtrip_extensions_in_order() {
+            let mut order = serde_json::to_value(AcmeOrder::default()).unwrap();
+            assert!(order.get("profile").is_none());
+            order["profile"] = json!("wire-client");
+            order["x-custom"] = json!(42);
+
+            let parsed = serde_json::from_value::<AcmeOrder>(order.clone()).unwrap();
+            assert_eq!(parsed.extensions.profile.as_deref(), Some("wire-client"));
+            assert_eq!(parsed.extensions.other.get("x-custom"), Some(&json!(42)));
+            assert_eq!(serde_json::to_value(parsed).unwrap(), order);
+        }
+    }
+
+    mod validity {
+        use super::*;
+
+        #[test]
+        #[wasm_bindgen_test]
+        fn should_request_from_now_until_expiry() {
+            let validity = AcmeOrderValidity::from(core::time::Duration::from_secs(3600));
+            let (nbf, naf) = (validity.not_before.unwrap(), validity.not_after.unwrap());
+            assert_eq!(naf - nbf, time::Duration::hours(1));
+            assert!(validity.verify().is_ok());
+        }
+
+        #[test]
+        #[wasm_bindgen_test]
+        fn should_parse_rfc3339() {
+            let validity = AcmeOrderValidity::try_from_rfc3339(None, Some("2100-01-08T00:04:00+04:00")).unwrap();
+            assert!(validity.not_before.is_none());
+            assert_eq!(validity.not_after.unwrap().year(), 2100);
+            assert!(validity.verify().is_ok());
+            assert!(AcmeOrderValidity::try_from_rfc3339(Some("tomorrow"), None).is_err());
+        }
+
+        #[test]
+        #[wasm_bindgen_test]
+        fn should_fail_when_invalid() {
+            let validity =
+                AcmeOrderValidity::try_from_rfc3339(Some("2100-01-08T00:00:00Z"), Some("2100-01-01T00:00:00Z"))
+                    .unwrap();
+            assert!(matches!(
+                validity.verify().unwrap_err(),
+                RustyAcmeError::OrderError(AcmeOrderError::InvalidValidity)
+            ));
+            let validity = AcmeOrderValidity::try_from_rfc3339(None, Some("2000-01-01T00:00:00Z")).unwrap();
+            assert!(matches!(
+                validity.verify().unwrap_err(),
+                RustyAcmeError::OrderError(AcmeOrderError::InvalidValidity)
+            ));
+        }
+    }
+
     mod verify {
         use super::*;
 
@@ -399,7 +717,7 @@ pub mod tests {
 
             // homogeneous identifiers
             let order = AcmeOrder {
-                identifiers: [AcmeIdentifier::new_user(), AcmeIdentifier::new_user()],
+                identifiers: vec![AcmeIdentifier::new_user(), AcmeIdentifier::new_user()],
                 ..default_order.clone()
             };
             assert!(matches!(
@@ -409,7 +727,7 @@ pub mod tests {
 
             // homogeneous identifiers
             let order = AcmeOrder {
-                identifiers: [AcmeIdentifier::new_device(), AcmeIdentifier::new_device()],
+                identifiers: vec![AcmeIdentifier::new_device(), AcmeIdentifier::new_device()],
                 ..default_order.clone()
             };
             assert!(matches!(
@@ -417,6 +735,128 @@ pub mod tests {
                 RustyAcmeError::OrderError(AcmeOrderError::WrongIdentifiers)
             ));
         }
+
+        #[test]
+        #[wasm_bindgen_test]
+        fn should_succeed_with_any_number_of_identifiers() {
+            let order = AcmeOrder {
+                identifiers: vec![AcmeIdentifier::new_user()],
+                ..Default::default()
+            };
+            assert!(order.verify().is_ok());
+
+            // e.g. another device of the same user
+            let client_id = ClientId::try_new(ClientId::DEFAULT_USER.to_string(), 1224, "wire.com").unwrap();
+            let other_device = AcmeIdentifier::try_new_device(
+                client_id,
+                QualifiedHandle::default(),
+                "Alice Smith".to_string(),
+                "wire.com".to_string(),
+            )
+            .unwrap();
+            let order = AcmeOrder {
+                identifiers: vec![AcmeIdentifier::new_user(), AcmeIdentifier::new_device(), other_device],
+                ..Default::default()
+            };
+            assert!(order.verify().is_ok());
+        }
+
+        #[test]
+        #[wasm_bindgen_test]
+        fn should_succeed_when_domains_differ_only_in_form() {
+            let identifier = |domain: &str| {
+                AcmeIdentifier::try_new_user(
+                    QualifiedHandle::default(),
+                    "Alice Smith".to_string(),
+                    domain.to_string(),
+                )
+                .unwrap()
+            };
+            let order = AcmeOrder {
+                identifiers: vec![identifier("bücher.example"), identifier("xn--bcher-kva.example")],
+                ..Default::default()
+            };
+            assert!(order.verify().is_ok());
+        }
+
+        #[test]
+        #[wasm_bindgen_test]
+        fn should_fail_when_no_identifier() {
+            let order = AcmeOrder {
+                identifiers: vec![],
+                ..Default::default()
+            };
+            assert!(matches!(
+                order.verify().unwrap_err(),
+                RustyAcmeError::OrderError(AcmeOrderError::WrongIdentifiers)
+            ));
+        }
+
+        #[test]
+        #[wasm_bindgen_test]
+        fn should_fail_when_identifiers_of_different_users() {
+            let handle = Handle::from("bob_wire").try_to_qualified("wire.com").unwrap();
+            let other_user = AcmeIdentifier::try_new_user(handle, "Bob".to_string(), "wire.com".to_string()).unwrap();
+            let order = AcmeOrder {
+                identifiers: vec![AcmeIdentifier::new_device(), AcmeIdentifier::new_user(), other_user],
+                ..Default::default()
+            };
+            assert!(matches!(
+                order.verify().unwrap_err(),
+                RustyAcmeError::OrderError(AcmeOrderError::WrongIdentifiers)
+            ));
+        }
+    }
+
+    mod authorizations {
+        use super::*;
+
+        #[test]
+        #[wasm_bindgen_test]
+        fn should_match_authorizations_by_identifier() {
+            let order = AcmeOrder::default();
+            let user_authz = AcmeAuthz {
+                identifier: AcmeIdentifier::new_user(),
+                ..Default::default()
+            };
+            // not in the same order as the identifiers
+            let authzs = [AcmeAuthz::default(), user_authz];
+            let authz = order.try_get_user_authorization(&authzs).unwrap();
+            assert_eq!(authz.identifier, AcmeIdentifier::new_user());
+            let authz = order.try_get_device_authorization(&authzs).unwrap();
+            assert_eq!(authz.identifier, AcmeIdentifier::new_device());
+        }
+
+        #[test]
+        #[wasm_bindgen_test]
+        fn should_fail_when_authorization_missing() {
+            let order = AcmeOrder::default();
+            let authzs = [AcmeAuthz::default()];
+            assert!(matches!(
+                order.try_get_user_authorization(&authzs).unwrap_err(),
+                RustyAcmeError::ClientImplementationError(_)
+            ));
+        }
+
+        #[test]
+        #[wasm_bindgen_test]
+        fn should_fail_when_identifier_not_in_order() {
+            let order = AcmeOrder {
+                identifiers: vec![AcmeIdentifier::new_user()],
+                ..Default::default()
+            };
+            let authzs = [AcmeAuthz::default()];
+            assert!(matches!(
+                order.try_get_device_authorization(&authzs).unwrap_err(),
+                RustyAcmeError::OrderError(AcmeOrderError::WrongIdentifiers)
+            ));
+            assert!(matches!(
+                order
+                    .try_get_authorization(&AcmeIdentifier::new_device(), &authzs)
+                    .unwrap_err(),
+                RustyAcmeError::OrderError(AcmeOrderError::WrongIdentifiers)
+            ));
+        }
     }
 
     mod creation {
@@ -530,4 +970,64 @@ pub mod tests {
             ));
         }
     }
+
+    mod list {
+        use jwt_simple::prelude::*;
+
+        use super::*;
+
+        #[test]
+        #[wasm_bindgen_test]
+        fn should_start_with_account_orders_url() {
+            let kp = Pem::from(Ed25519KeyPair::generate().to_pem());
+            let account = AcmeAccount::default();
+            let url = |page: Option<&url::Url>| {
+                let req = RustyAcme::list_orders_request(page, &account, JwsAlgorithm::Ed25519, &kp, "n".to_string())
+                    .unwrap();
+                let header = req.decoded_protected().unwrap();
+                assert_eq!(header["kid"], json!(account.acct_url().unwrap()));
+                header["url"].clone()
+            };
+            assert_eq!(url(None), json!(account.orders.as_ref().unwrap()));
+            let next = "https://example.com/acme/orders/rzGoeA?cursor=2".parse().unwrap();
+            assert_eq!(url(Some(&next)), json!(next));
+        }
+
+        #[test]
+        #[wasm_bindgen_test]
+        fn should_follow_next_link() {
+            let response = json!({
+                "orders": [
+                    "https://example.com/acme/order/TOlocE8rfgo",
+                    "https://example.com/acme/order/4E16bbL5iSw"
+                ]
+            });
+            let links = [
+                "<https://example.com/acme/directory>;rel=\"index\"",
+                "<https://example.com/acme/terms>; rel=\"terms-of-service\", \
+                 <https://example.com/acme/orders/rzGoeA?cursor=2>; rel=\"next\"",
+            ];
+            let list = RustyAcme::list_orders_response(response.clone(), &links).unwrap();
+            assert_eq!(list.orders.len(), 2);
+            assert_eq!(
+                list.next.unwrap().as_str(),
+                "https://example.com/acme/orders/rzGoeA?cursor=2"
+            );
+
+            // last page
+            let list = RustyAcme::list_orders_response(response, &links[..1]).unwrap();
+            assert!(list.next.is_none());
+        }
+
+        #[test]
+        #[wasm_bindgen_test]
+        fn should_fail_when_not_a_list() {
+            assert!(matches!(
+                RustyAcme::list_orders_response(json!({"orders": "nope"}), &[]).unwrap_err(),
+                RustyAcmeError::SmallstepImplementationError("Invalid orders list response")
+            ));
+            let links = ["<not a url>;rel=next"];
+            assert!(RustyAcme::list_orders_response(json!({"orders": []}), &links).is_err());
+        }
+    }
 }
@@ -0,0 +1,304 @@
+use crate::prelude::*;
+
+/// Error document returned by the ACME server when it refuses a request
+/// see [RFC 8555 Section 6.7](https://www.rfc-editor.org/rfc/rfc8555.html#section-6.7)
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct AcmeProblem {
+    /// kind of error
+    #[serde(rename = "type")]
+    pub typ: AcmeProblemType,
+    /// human readable explanation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    /// HTTP status code of the response
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    /// identifier the error relates to, only in subproblems
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub identifier: Option<AcmeProblemIdentifier>,
+    /// errors for each identifier of a request, when they failed for different reasons
+    /// see [RFC 8555 Section 6.7.1](https://www.rfc-editor.org/rfc/rfc8555.html#section-6.7.1)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub subproblems: Vec<AcmeProblem>,
+}
+
+/// Identifier of a subproblem. Not an [AcmeIdentifier] since it can be of any type
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct AcmeProblemIdentifier {
+    /// type of the identifier e.g. `dns` or `wireapp-device`
+    #[serde(rename = "type")]
+    pub typ: String,
+    /// the identifier itself, as found in the order
+    pub value: String,
+}
+
+impl AcmeProblem {
+    /// Parses the response when it is a problem document. Unlike a challenge, which also has a
+    /// 'type', it has an ACME error type, a 'detail' or a numeric 'status'.
+    pub fn try_from_response(response: &serde_json::Value) -> Option<Self> {
+        let typ = response.get("type")?.as_str()?;
+        let is_problem = typ.starts_with(AcmeProblemType::URN_PREFIX)
+            || response.get("detail").is_some()
+            || response
+                .get("status")
+                .map(serde_json::Value::is_u64)
+                .unwrap_or_default();
+        is_problem
+            .then(|| serde_json::from_value(response.clone()).ok())
+            .flatten()
+    }
+
+    /// Fails with the problem when the response is one
+    pub(crate) fn check(response: &serde_json::Value) -> RustyAcmeResult<()> {
+        match Self::try_from_response(response) {
+            Some(problem) => Err(problem.into()),
+            None => Ok(()),
+        }
+    }
+
+    /// Human readable reason, the type when the server did not detail it
+    pub fn reason(self) -> String {
+        self.detail.unwrap_or_else(|| self.typ.into())
+    }
+}
+
+impl From<AcmeProblem> for RustyAcmeError {
+    /// Rate limits get a dedicated error, the delay of the 'Retry-After' header is not known yet,
+    /// see [RustyAcme::check_rate_limit]
+    fn from(problem: AcmeProblem) -> Self {
+        match problem.typ {
+            AcmeProblemType::RateLimited => Self::RateLimited { retry_after: None },
+            _ => Self::Problem(problem),
+        }
+    }
+}
+
+impl std::fmt::Display for AcmeProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.detail {
+            Some(detail) => write!(f, "{detail}")?,
+            None => write!(f, "{}", String::from(self.typ.clone()))?,
+        }
+        for subproblem in &self.subproblems {
+            match &subproblem.identifier {
+                Some(identifier) => write!(f, "; {}: {subproblem}", identifier.value)?,
+                None => write!(f, "; {subproblem}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Error types registered for ACME
+/// see [RFC 8555 Section 6.7](https://www.rfc-editor.org/rfc/rfc8555.html#section-6.7)
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum AcmeProblemType {
+    /// The request specified an account that does not exist
+    AccountDoesNotExist,
+    /// The request specified a certificate to be revoked that has already been revoked
+    AlreadyRevoked,
+    /// The CSR is unacceptable (e.g., due to a short key)
+    BadCsr,
+    /// The client sent an unacceptable anti-replay nonce
+    BadNonce,
+    /// The JWS was signed by a public key the server does not support
+    BadPublicKey,
+    /// The revocation reason provided is not allowed by the server
+    BadRevocationReason,
+    /// The JWS was signed with an algorithm the server does not support
+    BadSignatureAlgorithm,
+    /// Certification Authority Authorization (CAA) records forbid the CA from issuing a certificate
+    Caa,
+    /// Specific error conditions are indicated in the 'subproblems' array
+    Compound,
+    /// The server could not connect to validation target
+    Connection,
+    /// There was a problem with a DNS query during identifier validation
+    Dns,
+    /// The request must include a value for the 'externalAccountBinding' field
+    ExternalAccountRequired,
+    /// Response received didn't match the challenge's requirements
+    IncorrectResponse,
+    /// A contact URL for an account was invalid
+    InvalidContact,
+    /// The request message was malformed
+    Malformed,
+    /// The request attempted to finalize an order that is not ready to be finalized
+    OrderNotReady,
+    /// The request exceeds a rate limit
+    RateLimited,
+    /// The server will not issue certificates for the identifier
+    RejectedIdentifier,
+    /// The server experienced an internal error
+    ServerInternal,
+    /// The server received a TLS error during validation
+    Tls,
+    /// The client lacks sufficient authorization
+    Unauthorized,
+    /// A contact URL for an account used an unsupported protocol scheme
+    UnsupportedContact,
+    /// An identifier is of an unsupported type
+    UnsupportedIdentifier,
+    /// Visit the 'instance' URL and take actions specified there
+    UserActionRequired,
+    /// Any other type, as is
+    Other(String),
+}
+
+impl AcmeProblemType {
+    /// Namespace of the error types registered for ACME
+    pub const URN_PREFIX: &'static str = "urn:ietf:params:acme:error:";
+
+    const NAMES: [(&'static str, Self); 24] = [
+        ("accountDoesNotExist", Self::AccountDoesNotExist),
+        ("alreadyRevoked", Self::AlreadyRevoked),
+        ("badCSR", Self::BadCsr),
+        ("badNonce", Self::BadNonce),
+        ("badPublicKey", Self::BadPublicKey),
+        ("badRevocationReason", Self::BadRevocationReason),
+        ("badSignatureAlgorithm", Self::BadSignatureAlgorithm),
+        ("caa", Self::Caa),
+        ("compound", Self::Compound),
+        ("connection", Self::Connection),
+        ("dns", Self::Dns),
+        ("externalAccountRequired", Self::ExternalAccountRequired),
+        ("incorrectResponse", Self::IncorrectResponse),
+        ("invalidContact", Self::InvalidContact),
+        ("malformed", Self::Malformed),
+        ("orderNotReady", Self::OrderNotReady),
+        ("rateLimited", Self::RateLimited),
+        ("rejectedIdentifier", Self::RejectedIdentifier),
+        ("serverInternal", Self::ServerInternal),
+        ("tls", Self::Tls),
+        ("unauthorized", Self::Unauthorized),
+        ("unsupportedContact", Self::UnsupportedContact),
+        ("unsupportedIdentifier", Self::UnsupportedIdentifier),
+        ("userActionRequired", Self::UserActionRequired),
+    ];
+}
+
+impl From<String> for AcmeProblemType {
+    fn from(typ: String) -> Self {
+        typ.strip_prefix(Self::URN_PREFIX)
+            .and_then(|name| Self::NAMES.into_iter().find(|(n, _)| *n == name))
+            .map(|(_, t)| t)
+            .unwrap_or(Self::Other(typ))
+    }
+}
+
+impl From<AcmeProblemType> for String {
+    fn from(typ: AcmeProblemType) -> Self {
+        match typ {
+            AcmeProblemType::Other(typ) => typ,
+            typ => AcmeProblemType::NAMES
+                .into_iter()
+                .find(|(_, t)| *t == typ)
+                .map(|(name, _)| format!("{}{name}", AcmeProblemType::URN_PREFIX))
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use serde_json::json;
+    use wasm_bindgen_test::*;
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn can_deserialize_rfc_sample() {
+        let rfc_sample = json!({
+            "type": "urn:ietf:params:acme:error:malformed",
+            "detail": "Some of the identifiers requested were rejected",
+            "subproblems": [
+                {
+                    "type": "urn:ietf:params:acme:error:malformed",
+                    "detail": "Invalid underscore in DNS name \"_example.org\"",
+                    "identifier": {
+                        "type": "dns",
+                        "value": "_example.org"
+                    }
+                },
+                {
+                    "type": "urn:ietf:params:acme:error:rejectedIdentifier",
+                    "detail": "This CA will not issue for \"example.net\"",
+                    "identifier": {
+                        "type": "dns",
+                        "value": "example.net"
+                    }
+                }
+            ]
+        });
+        let problem = AcmeProblem::try_from_response(&rfc_sample).unwrap();
+        assert_eq!(problem.typ, AcmeProblemType::Malformed);
+        assert_eq!(problem.subproblems.len(), 2);
+        assert_eq!(problem.subproblems[1].typ, AcmeProblemType::RejectedIdentifier);
+        assert_eq!(problem.subproblems[1].identifier.as_ref().unwrap().value, "example.net");
+        assert!(problem.to_string().contains("example.net: This CA will not issue"));
+        assert_eq!(serde_json::to_value(problem).unwrap(), rfc_sample);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_roundtrip_types() {
+        for (name, typ) in AcmeProblemType::NAMES {
+            let urn = format!("urn:ietf:params:acme:error:{name}");
+            assert_eq!(AcmeProblemType::from(urn.clone()), typ);
+            assert_eq!(String::from(typ), urn);
+        }
+        let other = "https://example.com/problems/nope".to_string();
+        assert_eq!(
+            AcmeProblemType::from(other.clone()),
+            AcmeProblemType::Other(other.clone())
+        );
+        assert_eq!(String::from(AcmeProblemType::Other(other.clone())), other);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_not_mistake_challenge_for_problem() {
+        let chall = serde_json::to_value(AcmeChallenge::new_device()).unwrap();
+        assert!(AcmeProblem::try_from_response(&chall).is_none());
+        assert!(AcmeProblem::check(&chall).is_ok());
+
+        let problem = json!({"type": "about:blank", "status": 503});
+        assert!(matches!(
+            AcmeProblem::check(&problem).unwrap_err(),
+            RustyAcmeError::Problem(AcmeProblem { status: Some(503), .. })
+        ));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_expose_problem_from_responses() {
+        let problem = json!({
+            "type": "urn:ietf:params:acme:error:rateLimited",
+            "detail": "too many new orders recently",
+            "status": 429
+        });
+        let is_rate_limited = |e: RustyAcmeError| matches!(e, RustyAcmeError::RateLimited { retry_after: None });
+        assert!(is_rate_limited(
+            RustyAcme::new_order_response(problem.clone()).unwrap_err()
+        ));
+        assert!(is_rate_limited(
+            RustyAcme::new_authz_response(problem.clone()).unwrap_err()
+        ));
+        assert!(is_rate_limited(
+            RustyAcme::new_chall_response(problem.clone()).unwrap_err()
+        ));
+        assert!(is_rate_limited(
+            RustyAcme::finalize_response(problem.clone()).unwrap_err()
+        ));
+        assert!(is_rate_limited(
+            RustyAcme::new_account_response(problem.clone()).unwrap_err()
+        ));
+        let order = AcmeOrder::default();
+        let certificate = RustyAcme::certificate_response(problem.to_string(), order);
+        assert!(is_rate_limited(certificate.unwrap_err()));
+    }
+}
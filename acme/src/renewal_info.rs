@@ -0,0 +1,214 @@
+use base64::Engine;
+use sha2::Digest;
+use x509_cert::der::Decode as _;
+
+use crate::prelude::*;
+
+impl RustyAcme {
+    /// Unique identifier of a certificate for the ACME server: its authority key identifier and
+    /// serial number
+    /// see [draft-ietf-acme-ari Section 4.1](https://datatracker.ietf.org/doc/html/draft-ietf-acme-ari#section-4.1)
+    pub fn renewal_info_cert_id(certificate_der: &[u8]) -> RustyAcmeResult<String> {
+        let cert = x509_cert::Certificate::from_der(certificate_der)?;
+        let tbs = &cert.tbs_certificate;
+
+        let aki = tbs
+            .extensions
+            .iter()
+            .flatten()
+            .find_map(|e| {
+                (e.extn_id.as_bytes() == oid_registry::OID_X509_EXT_AUTHORITY_KEY_IDENTIFIER.as_bytes())
+                    .then(|| x509_cert::ext::pkix::AuthorityKeyIdentifier::from_der(e.extn_value.as_bytes()))
+            })
+            .transpose()?
+            .and_then(|aki| aki.key_identifier)
+            .ok_or(AcmeRenewalInfoError::MissingAuthorityKeyIdentifier)?;
+
+        let b64 = base64::prelude::BASE64_URL_SAFE_NO_PAD;
+        // DER encoded serial number, without tag and length but with its leading zero if any
+        let serial = tbs.serial_number.as_bytes();
+        Ok(format!("{}.{}", b64.encode(aki.as_bytes()), b64.encode(serial)))
+    }
+
+    /// URL to fetch the renewal information of a certificate from, with an unauthenticated `GET`
+    /// see [draft-ietf-acme-ari Section 4.1](https://datatracker.ietf.org/doc/html/draft-ietf-acme-ari#section-4.1)
+    pub fn renewal_info_request(directory: &AcmeDirectory, certificate_der: &[u8]) -> RustyAcmeResult<url::Url> {
+        let mut url = directory
+            .renewal_info
+            .clone()
+            .ok_or(AcmeRenewalInfoError::NotSupported)?;
+        let cert_id = Self::renewal_info_cert_id(certificate_der)?;
+        url.path_segments_mut()
+            .map_err(|_| RustyAcmeError::SmallstepImplementationError("Invalid 'renewalInfo' URL in directory"))?
+            .pop_if_empty()
+            .push(&cert_id);
+        Ok(url)
+    }
+
+    /// parse the response from `GET /acme/renewal-info/{cert-id}`
+    /// see [draft-ietf-acme-ari Section 4.2](https://datatracker.ietf.org/doc/html/draft-ietf-acme-ari#section-4.2)
+    pub fn renewal_info_response(response: serde_json::Value) -> RustyAcmeResult<AcmeRenewalInfo> {
+        AcmeProblem::check(&response)?;
+        let info = serde_json::from_value::<AcmeRenewalInfo>(response)
+            .map_err(|_| RustyAcmeError::SmallstepImplementationError("Invalid renewal info response"))?;
+        let window = &info.suggested_window;
+        if window.end < window.start {
+            return Err(AcmeRenewalInfoError::InvalidWindow)?;
+        }
+        Ok(info)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum AcmeRenewalInfoError {
+    /// The ACME server does not advertise a 'renewalInfo' URL
+    #[error("The ACME server does not support renewal information")]
+    NotSupported,
+    /// The certificate has no authority key identifier to identify it with
+    #[error("The certificate has no authority key identifier")]
+    MissingAuthorityKeyIdentifier,
+    /// The suggested window ends before it starts
+    #[error("The suggested renewal window ends before it starts")]
+    InvalidWindow,
+}
+
+/// When the ACME server would like a certificate to be renewed
+/// see [draft-ietf-acme-ari Section 4.2](https://datatracker.ietf.org/doc/html/draft-ietf-acme-ari#section-4.2)
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AcmeRenewalInfo {
+    /// Window within which to renew
+    pub suggested_window: AcmeRenewalWindow,
+    /// Page explaining why the window is what it is e.g. after a mass revocation
+    #[serde(rename = "explanationURL", skip_serializing_if = "Option::is_none")]
+    pub explanation_url: Option<url::Url>,
+}
+
+/// see [draft-ietf-acme-ari Section 4.2](https://datatracker.ietf.org/doc/html/draft-ietf-acme-ari#section-4.2)
+#[derive(Debug, Copy, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct AcmeRenewalWindow {
+    #[serde(with = "time::serde::rfc3339")]
+    pub start: time::OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub end: time::OffsetDateTime,
+}
+
+impl AcmeRenewalWindow {
+    /// Instant at [fraction], within `[0, 1]`, of the window
+    pub fn at(&self, fraction: f64) -> time::OffsetDateTime {
+        self.start + (self.end - self.start) * fraction.clamp(0.0, 1.0)
+    }
+}
+
+impl AcmeRenewalInfo {
+    /// Fraction within `[0, 1]` derived from a certificate, so that clients spread their renewals
+    /// while a given client always finds the same one e.g. after a restart
+    pub fn jitter(certificate_der: &[u8]) -> f64 {
+        let digest = sha2::Sha256::digest(certificate_der);
+        let seed = u64::from_le_bytes(digest[..8].try_into().unwrap_or_default());
+        seed as f64 / u64::MAX as f64
+    }
+
+    /// Instant within the suggested window at which to renew [certificate_der], see [Self::jitter]
+    pub fn renewal_time(&self, certificate_der: &[u8]) -> time::OffsetDateTime {
+        self.suggested_window.at(Self::jitter(certificate_der))
+    }
+
+    /// Whether [certificate_der] should be renewed now i.e. past its [Self::renewal_time]
+    pub fn should_renew_now(&self, certificate_der: &[u8]) -> bool {
+        time::OffsetDateTime::now_utc() >= self.renewal_time(certificate_der)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use serde_json::json;
+    use wasm_bindgen_test::*;
+
+    use crate::{directory::tests::directory, identity::tests::CERT};
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    fn cert_der() -> Vec<u8> {
+        pem::parse(CERT).unwrap().contents().to_vec()
+    }
+
+    fn info(start: time::Duration, end: time::Duration) -> AcmeRenewalInfo {
+        let now = time::OffsetDateTime::now_utc();
+        AcmeRenewalInfo {
+            suggested_window: AcmeRenewalWindow {
+                start: now + start,
+                end: now + end,
+            },
+            explanation_url: None,
+        }
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_compute_cert_id() {
+        // authority key identifier then serial number, which has a leading zero
+        let cert_id = RustyAcme::renewal_info_cert_id(&cert_der()).unwrap();
+        assert_eq!(cert_id, "E_se2QTdZ1urMTouXhogHaMUzYs.AJaZdl-hZDl9qSSju5kmWNA");
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_append_cert_id_to_url() {
+        let expected = "https://example.com/acme/renewal-info/E_se2QTdZ1urMTouXhogHaMUzYs.AJaZdl-hZDl9qSSju5kmWNA";
+        for url in [
+            "https://example.com/acme/renewal-info",
+            "https://example.com/acme/renewal-info/",
+        ] {
+            let url = RustyAcme::renewal_info_request(&directory(None, None, Some(url)), &cert_der()).unwrap();
+            assert_eq!(url.as_str(), expected);
+        }
+        assert!(matches!(
+            RustyAcme::renewal_info_request(&directory(None, None, None), &cert_der()).unwrap_err(),
+            RustyAcmeError::RenewalInfoError(AcmeRenewalInfoError::NotSupported)
+        ));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_parse_suggested_window() {
+        let response = json!({
+            "suggestedWindow": {
+                "start": "2021-01-03T00:00:00Z",
+                "end": "2021-01-07T00:00:00Z"
+            },
+            "explanationURL": "https://example.com/docs/ari"
+        });
+        let info = RustyAcme::renewal_info_response(response).unwrap();
+        assert_eq!(info.suggested_window.start.day(), 3);
+        assert_eq!(info.explanation_url.unwrap().as_str(), "https://example.com/docs/ari");
+
+        let response = json!({
+            "suggestedWindow": {
+                "start": "2021-01-07T00:00:00Z",
+                "end": "2021-01-03T00:00:00Z"
+            }
+        });
+        assert!(matches!(
+            RustyAcme::renewal_info_response(response).unwrap_err(),
+            RustyAcmeError::RenewalInfoError(AcmeRenewalInfoError::InvalidWindow)
+        ));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_renew_within_window() {
+        let day = time::Duration::days(1);
+        let upcoming = info(day, day * 2);
+        let renewal_time = upcoming.renewal_time(&cert_der());
+        let AcmeRenewalWindow { start, end } = upcoming.suggested_window;
+        assert!(start <= renewal_time && renewal_time <= end);
+        // the same every time for a given certificate
+        assert_eq!(upcoming.renewal_time(&cert_der()), renewal_time);
+
+        assert!(info(-day * 2, -day).should_renew_now(&cert_der()));
+        assert!(!info(day, day * 2).should_renew_now(&cert_der()));
+    }
+}
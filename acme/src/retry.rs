@@ -0,0 +1,642 @@
+use core::time::Duration;
+
+use crate::{
+    authz::{AcmeAuthzError, AuthzStatus},
+    chall::AcmeChallError,
+    order::AcmeOrderStatus,
+    prelude::*,
+};
+
+/// Outcome of polling a resource the ACME server processes asynchronously
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum AcmePoll<T> {
+    /// The resource reached the expected state
+    Ready(T),
+    /// The resource is still being processed. Poll it again after the delay the server suggested
+    /// in the 'Retry-After' header if any, see [RetryPolicy::delay]
+    Retry(Option<Duration>),
+}
+
+impl RustyAcme {
+    /// parse the response from `POST /acme/challenge/{token}`, like [RustyAcme::new_chall_response]
+    /// but a challenge still being processed is not an error
+    ///
+    /// # Arguments
+    /// * `retry_after` - 'Retry-After' header of the response, if any
+    pub fn poll_chall_response(
+        response: serde_json::Value,
+        retry_after: Option<&str>,
+    ) -> RustyAcmeResult<AcmePoll<AcmeChallenge>> {
+        Self::check_rate_limited_problem(&response, retry_after)?;
+        match Self::new_chall_response(response) {
+            Ok(chall) => Ok(AcmePoll::Ready(chall)),
+            Err(RustyAcmeError::ChallengeError(AcmeChallError::Processing)) => {
+                Ok(AcmePoll::Retry(retry_after.and_then(RetryPolicy::parse_retry_after)))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// parse the response from `POST /acme/authz/{authz_id}` once its challenge is completed,
+    /// until it turns 'valid'
+    ///
+    /// # Arguments
+    /// * `retry_after` - 'Retry-After' header of the response, if any
+    pub fn poll_authz_response(
+        response: serde_json::Value,
+        retry_after: Option<&str>,
+    ) -> RustyAcmeResult<AcmePoll<AcmeAuthz>> {
+        Self::check_rate_limited_problem(&response, retry_after)?;
+        let authz = serde_json::from_value::<AcmeAuthz>(response)?;
+        authz.verify()?;
+        match authz.status {
+            AuthzStatus::Valid => Ok(AcmePoll::Ready(authz)),
+            AuthzStatus::Pending => Ok(AcmePoll::Retry(retry_after.and_then(RetryPolicy::parse_retry_after))),
+            AuthzStatus::Invalid => Err(AcmeAuthzError::Invalid)?,
+            AuthzStatus::Revoked => Err(AcmeAuthzError::Revoked)?,
+            AuthzStatus::Deactivated => Err(AcmeAuthzError::Deactivated)?,
+            AuthzStatus::Expired => Err(AcmeAuthzError::Expired)?,
+        }
+    }
+
+    /// parse the response from `POST /acme/order/{order_id}` once challenges are completed, like
+    /// [RustyAcme::check_order_response] but an order still pending validation is not an error
+    ///
+    /// # Arguments
+    /// * `retry_after` - 'Retry-After' header of the response, if any
+    pub fn poll_order_response(
+        response: serde_json::Value,
+        retry_after: Option<&str>,
+    ) -> RustyAcmeResult<AcmePoll<AcmeOrder>> {
+        Self::check_rate_limited_problem(&response, retry_after)?;
+        if Self::order_status(&response)? == AcmeOrderStatus::Pending {
+            return Ok(AcmePoll::Retry(retry_after.and_then(RetryPolicy::parse_retry_after)));
+        }
+        Self::check_order_response(response).map(AcmePoll::Ready)
+    }
+
+    /// parse the response from `POST /acme/order/{order_id}/finalize` or from the order once
+    /// finalized, like [RustyAcme::finalize_response] but an order still being processed is not
+    /// an error
+    ///
+    /// # Arguments
+    /// * `retry_after` - 'Retry-After' header of the response, if any
+    pub fn poll_finalize_response(
+        response: serde_json::Value,
+        retry_after: Option<&str>,
+    ) -> RustyAcmeResult<AcmePoll<AcmeFinalize>> {
+        Self::check_rate_limited_problem(&response, retry_after)?;
+        // the 'certificate' URL is absent until the order is valid
+        if Self::order_status(&response)? == AcmeOrderStatus::Processing {
+            return Ok(AcmePoll::Retry(retry_after.and_then(RetryPolicy::parse_retry_after)));
+        }
+        let finalize = Self::finalize_response(response)?;
+        finalize.verify()?;
+        Ok(AcmePoll::Ready(finalize))
+    }
+
+    /// Fails with [RustyAcmeError::RateLimited] when the ACME server answered with a '429 Too Many
+    /// Requests' status or a 'rateLimited' problem, along with the delay of its 'Retry-After'
+    /// header. Other problems are left to the parsing of the response.
+    /// see [RFC 8555 Section 6.6](https://www.rfc-editor.org/rfc/rfc8555.html#section-6.6)
+    ///
+    /// # Arguments
+    /// * `status` - HTTP status code of the response
+    /// * `retry_after` - 'Retry-After' header of the response, if any
+    pub fn check_rate_limit(
+        status: u16,
+        response: &serde_json::Value,
+        retry_after: Option<&str>,
+    ) -> RustyAcmeResult<()> {
+        const TOO_MANY_REQUESTS: u16 = 429;
+        if status == TOO_MANY_REQUESTS {
+            return Err(RustyAcmeError::RateLimited {
+                retry_after: retry_after.and_then(RetryPolicy::parse_retry_after),
+            });
+        }
+        Self::check_rate_limited_problem(response, retry_after)
+    }
+
+    fn check_rate_limited_problem(response: &serde_json::Value, retry_after: Option<&str>) -> RustyAcmeResult<()> {
+        match AcmeProblem::try_from_response(response) {
+            Some(problem) if problem.typ == AcmeProblemType::RateLimited => Err(RustyAcmeError::RateLimited {
+                retry_after: retry_after.and_then(RetryPolicy::parse_retry_after),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    fn order_status(response: &serde_json::Value) -> RustyAcmeResult<AcmeOrderStatus> {
+        AcmeProblem::check(response)?;
+        let status = response.get("status").cloned().unwrap_or_default();
+        Ok(serde_json::from_value(status)?)
+    }
+}
+
+/// How long to wait between polls of a resource, honoring the server hints
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct RetryPolicy {
+    /// Delay before the first retry when the server gave no hint. Doubles on every retry
+    pub initial_delay: Duration,
+    /// Upper bound of any delay, including the ones suggested by the server
+    pub max_delay: Duration,
+    /// Number of retries before giving up
+    pub max_attempts: u32,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+            max_attempts: 10,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before the retry number [attempt], starting at 0: the one suggested by the server
+    /// when it gave one, an exponential backoff otherwise. `None` once every attempt is spent.
+    pub fn delay(&self, attempt: u32, hint: Option<Duration>) -> Option<Duration> {
+        if attempt >= self.max_attempts {
+            return None;
+        }
+        let delay = hint.unwrap_or_else(|| {
+            self.initial_delay
+                .checked_mul(2u32.saturating_pow(attempt))
+                .unwrap_or(self.max_delay)
+        });
+        Some(delay.min(self.max_delay))
+    }
+
+    /// Parses a 'Retry-After' header, either a number of seconds or an HTTP date. A date in the
+    /// past means retrying right away.
+    /// see [RFC 9110 Section 10.2.3](https://www.rfc-editor.org/rfc/rfc9110.html#section-10.2.3)
+    pub fn parse_retry_after(header: &str) -> Option<Duration> {
+        let header = header.trim();
+        if let Ok(seconds) = header.parse::<u64>() {
+            return Some(Duration::from_secs(seconds));
+        }
+        // HTTP dates are always in GMT, which is an obsolete zone in RFC 2822
+        let header = header.strip_suffix("GMT").map(|d| format!("{d}+0000"));
+        let date = time::OffsetDateTime::parse(&header?, &time::format_description::well_known::Rfc2822).ok()?;
+        let delay = date - time::OffsetDateTime::now_utc();
+        Some(delay.try_into().unwrap_or_default())
+    }
+}
+
+/// Why the polling of a resource was given up, see [PollState]
+#[derive(Debug, thiserror::Error)]
+pub enum AcmePollError {
+    /// The resource is still not ready after every attempt of the [RetryPolicy]
+    #[error("Gave up polling after {0} attempts")]
+    AttemptsExhausted(u32),
+    /// The resource is still not ready at the deadline of the [PollState]
+    #[error("Gave up polling at the deadline")]
+    Timeout,
+    /// The polling was cancelled with [PollCancellation::cancel]
+    #[error("Polling was cancelled")]
+    Cancelled,
+}
+
+/// What to do next after polling a resource, see [PollState]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum PollAction<T> {
+    /// The resource is still being processed, poll it again after this delay
+    Wait(Duration),
+    /// The resource is ready, move on to the next step e.g. finalize a ready order
+    Proceed(T),
+}
+
+/// Drives the polling of a resource: fed with each response, it tells whether to keep polling
+/// and for how long to wait, or to proceed. Aborting is an error, either the terminal state of the
+/// resource e.g. [AcmeChallError::Invalid], [AcmePollError::AttemptsExhausted],
+/// [AcmePollError::Timeout] or [AcmePollError::Cancelled]. Being rate limited counts as an
+/// attempt, waiting at least the delay the server asked for.
+///
+/// It performs no I/O: callers own the requests and the waits, hence can drop them at any time.
+/// Use one per resource since it counts the attempts.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct PollState {
+    policy: RetryPolicy,
+    attempt: u32,
+    deadline: Option<time::OffsetDateTime>,
+    cancellation: Option<PollCancellation>,
+}
+
+/// Cancels the polling of every [PollState] it was given to, from anywhere e.g. when the user
+/// leaves the enrollment screen. Clones share the same state.
+#[derive(Debug, Clone, Default)]
+pub struct PollCancellation(std::sync::Arc<std::sync::atomic::AtomicBool>);
+
+impl PollCancellation {
+    /// Cancels the polling, for good
+    pub fn cancel(&self) {
+        self.0.store(true, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    /// Whether [Self::cancel] was called on this or any of its clones
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(std::sync::atomic::Ordering::Relaxed)
+    }
+}
+
+impl PartialEq for PollCancellation {
+    fn eq(&self, other: &Self) -> bool {
+        std::sync::Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for PollCancellation {}
+
+impl PollState {
+    /// Waits between polls as [policy] says and gives up after its [RetryPolicy::max_attempts].
+    /// Without deadline nor cancellation, see [Self::with_deadline] and [Self::with_cancellation]
+    pub fn new(policy: RetryPolicy) -> Self {
+        Self {
+            policy,
+            ..Default::default()
+        }
+    }
+
+    /// Gives up with [AcmePollError::Timeout] when the resource is still not ready at [deadline].
+    /// Waits are shortened so that the last poll happens right at the deadline.
+    pub fn with_deadline(mut self, deadline: time::OffsetDateTime) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Gives up with [AcmePollError::Cancelled] on the next response once [cancellation] is
+    /// cancelled, even if the resource is ready
+    pub fn with_cancellation(mut self, cancellation: PollCancellation) -> Self {
+        self.cancellation = Some(cancellation);
+        self
+    }
+
+    /// Number of times the resource was found not ready so far
+    pub fn attempts(&self) -> u32 {
+        self.attempt
+    }
+
+    /// Feeds the response from `POST /acme/challenge/{token}`, see [RustyAcme::poll_chall_response]
+    pub fn on_chall_response(
+        &mut self,
+        response: serde_json::Value,
+        retry_after: Option<&str>,
+    ) -> RustyAcmeResult<PollAction<AcmeChallenge>> {
+        self.next(RustyAcme::poll_chall_response(response, retry_after))
+    }
+
+    /// Feeds the response from `POST /acme/authz/{authz_id}`, see [RustyAcme::poll_authz_response]
+    pub fn on_authz_response(
+        &mut self,
+        response: serde_json::Value,
+        retry_after: Option<&str>,
+    ) -> RustyAcmeResult<PollAction<AcmeAuthz>> {
+        self.next(RustyAcme::poll_authz_response(response, retry_after))
+    }
+
+    /// Feeds the response from `POST /acme/order/{order_id}`, see [RustyAcme::poll_order_response].
+    /// Proceeding means finalizing the order.
+    pub fn on_order_response(
+        &mut self,
+        response: serde_json::Value,
+        retry_after: Option<&str>,
+    ) -> RustyAcmeResult<PollAction<AcmeOrder>> {
+        self.next(RustyAcme::poll_order_response(response, retry_after))
+    }
+
+    /// Feeds the response from `POST /acme/order/{order_id}/finalize` or from the order once
+    /// finalized, see [RustyAcme::poll_finalize_response]. Proceeding means fetching the certificate.
+    pub fn on_finalize_response(
+        &mut self,
+        response: serde_json::Value,
+        retry_after: Option<&str>,
+    ) -> RustyAcmeResult<PollAction<AcmeFinalize>> {
+        self.next(RustyAcme::poll_finalize_response(response, retry_after))
+    }
+
+    fn next<T>(&mut self, poll: RustyAcmeResult<AcmePoll<T>>) -> RustyAcmeResult<PollAction<T>> {
+        if self.cancellation.as_ref().is_some_and(PollCancellation::is_cancelled) {
+            return Err(AcmePollError::Cancelled)?;
+        }
+        let (hint, rate_limit) = match poll {
+            Ok(AcmePoll::Ready(resource)) => return Ok(PollAction::Proceed(resource)),
+            Ok(AcmePoll::Retry(hint)) => (hint, None),
+            Err(RustyAcmeError::RateLimited { retry_after }) => (None, retry_after),
+            Err(e) => return Err(e),
+        };
+        let delay = self
+            .policy
+            .delay(self.attempt, hint)
+            .ok_or(AcmePollError::AttemptsExhausted(self.attempt))?;
+        // unlike a polling hint, a rate limit is not capped by the policy
+        let delay = rate_limit.map_or(delay, |r| r.max(delay));
+        let delay = match self.deadline {
+            Some(deadline) => {
+                let remaining = deadline - time::OffsetDateTime::now_utc();
+                if !remaining.is_positive() {
+                    return Err(AcmePollError::Timeout)?;
+                }
+                delay.min(remaining.try_into().unwrap_or_default())
+            }
+            None => delay,
+        };
+        self.attempt += 1;
+        Ok(PollAction::Wait(delay))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use serde_json::json;
+    use wasm_bindgen_test::*;
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_parse_retry_after() {
+        assert_eq!(RetryPolicy::parse_retry_after("120"), Some(Duration::from_secs(120)));
+        assert_eq!(RetryPolicy::parse_retry_after(" 0 "), Some(Duration::ZERO));
+        assert_eq!(
+            RetryPolicy::parse_retry_after("Fri, 31 Dec 1999 23:59:59 GMT"),
+            Some(Duration::ZERO)
+        );
+        let in_a_minute = time::OffsetDateTime::now_utc() + time::Duration::minutes(1);
+        let header = in_a_minute
+            .format(&time::format_description::well_known::Rfc2822)
+            .unwrap()
+            .replace("+0000", "GMT");
+        let delay = RetryPolicy::parse_retry_after(&header).unwrap();
+        assert!(Duration::from_secs(50) < delay && delay <= Duration::from_secs(60));
+        assert_eq!(RetryPolicy::parse_retry_after("-1"), None);
+        assert_eq!(RetryPolicy::parse_retry_after("soon"), None);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_back_off_exponentially() {
+        let policy = RetryPolicy {
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(5),
+            max_attempts: 4,
+        };
+        let delays = (0..5).map(|attempt| policy.delay(attempt, None)).collect::<Vec<_>>();
+        let secs = |s| Some(Duration::from_secs(s));
+        assert_eq!(delays, vec![secs(1), secs(2), secs(4), secs(5), None]);
+
+        // the server knows better but within bounds
+        assert_eq!(policy.delay(0, Some(Duration::from_secs(3))), secs(3));
+        assert_eq!(policy.delay(0, Some(Duration::from_secs(60))), secs(5));
+        assert_eq!(policy.delay(4, Some(Duration::from_secs(3))), None);
+        assert_eq!(policy.delay(u32::MAX - 1, None), None);
+        let unbounded = RetryPolicy {
+            max_attempts: u32::MAX,
+            ..policy
+        };
+        assert_eq!(unbounded.delay(100, None), secs(5));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_retry_processing_challenge() {
+        let mut chall = serde_json::to_value(AcmeChallenge::new_device()).unwrap();
+        chall["status"] = json!("processing");
+        let delay = Some(Duration::from_secs(5));
+        assert!(matches!(
+            RustyAcme::poll_chall_response(chall.clone(), Some("5")).unwrap(),
+            AcmePoll::Retry(d) if d == delay
+        ));
+        assert!(matches!(
+            RustyAcme::poll_chall_response(chall.clone(), None).unwrap(),
+            AcmePoll::Retry(None)
+        ));
+
+        chall["status"] = json!("valid");
+        let poll = RustyAcme::poll_chall_response(chall.clone(), Some("5")).unwrap();
+        assert!(matches!(poll, AcmePoll::Ready(_)));
+
+        chall["status"] = json!("invalid");
+        assert!(matches!(
+            RustyAcme::poll_chall_response(chall, None).unwrap_err(),
+            RustyAcmeError::ChallengeError(AcmeChallError::Invalid)
+        ));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_retry_pending_order() {
+        let order = |status| {
+            serde_json::to_value(AcmeOrder {
+                status,
+                ..Default::default()
+            })
+            .unwrap()
+        };
+        assert_eq!(
+            RustyAcme::poll_order_response(order(AcmeOrderStatus::Pending), Some("2")).unwrap(),
+            AcmePoll::Retry(Some(Duration::from_secs(2)))
+        );
+        let poll = RustyAcme::poll_order_response(order(AcmeOrderStatus::Ready), None).unwrap();
+        assert!(matches!(poll, AcmePoll::Ready(_)));
+        assert!(RustyAcme::poll_order_response(order(AcmeOrderStatus::Invalid), None).is_err());
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_retry_processing_finalize() {
+        let order = |status| {
+            serde_json::to_value(AcmeOrder {
+                status,
+                ..Default::default()
+            })
+            .unwrap()
+        };
+        assert!(matches!(
+            RustyAcme::poll_finalize_response(order(AcmeOrderStatus::Processing), None).unwrap(),
+            AcmePoll::Retry(None)
+        ));
+
+        let mut valid = order(AcmeOrderStatus::Valid);
+        valid["certificate"] = json!("https://acme-server/acme/certificate/ZPYxKtONfCo0e5ttVlqr6DaZDjULtymC");
+        let poll = RustyAcme::poll_finalize_response(valid, None).unwrap();
+        assert!(matches!(poll, AcmePoll::Ready(_)));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_map_rate_limits() {
+        let problem = json!({
+            "type": "urn:ietf:params:acme:error:rateLimited",
+            "detail": "too many new orders recently",
+            "status": 429
+        });
+        let secs = |s| Some(Duration::from_secs(s));
+        assert!(matches!(
+            RustyAcme::check_rate_limit(429, &json!({}), Some("60")).unwrap_err(),
+            RustyAcmeError::RateLimited { retry_after } if retry_after == secs(60)
+        ));
+        assert!(matches!(
+            RustyAcme::check_rate_limit(400, &problem, None).unwrap_err(),
+            RustyAcmeError::RateLimited { retry_after: None }
+        ));
+        assert!(matches!(
+            RustyAcme::poll_order_response(problem, Some("10")).unwrap_err(),
+            RustyAcmeError::RateLimited { retry_after } if retry_after == secs(10)
+        ));
+        let malformed = json!({"type": "urn:ietf:params:acme:error:malformed", "status": 400});
+        assert!(RustyAcme::check_rate_limit(400, &malformed, None).is_ok());
+        assert!(RustyAcme::check_rate_limit(200, &json!({"status": "valid"}), None).is_ok());
+    }
+
+    mod state {
+        use super::*;
+
+        fn order(status: AcmeOrderStatus) -> serde_json::Value {
+            serde_json::to_value(AcmeOrder {
+                status,
+                ..Default::default()
+            })
+            .unwrap()
+        }
+
+        #[test]
+        #[wasm_bindgen_test]
+        fn should_wait_then_proceed() {
+            let mut state = PollState::default();
+            let action = state.on_order_response(order(AcmeOrderStatus::Pending), None).unwrap();
+            assert_eq!(action, PollAction::Wait(Duration::from_secs(1)));
+            let action = state.on_order_response(order(AcmeOrderStatus::Pending), None).unwrap();
+            assert_eq!(action, PollAction::Wait(Duration::from_secs(2)));
+            // the server hint wins
+            let action = state
+                .on_order_response(order(AcmeOrderStatus::Pending), Some("3"))
+                .unwrap();
+            assert_eq!(action, PollAction::Wait(Duration::from_secs(3)));
+            assert_eq!(state.attempts(), 3);
+
+            let action = state.on_order_response(order(AcmeOrderStatus::Ready), None).unwrap();
+            assert!(matches!(action, PollAction::Proceed(o) if o.status == AcmeOrderStatus::Ready));
+        }
+
+        #[test]
+        #[wasm_bindgen_test]
+        fn should_give_up_after_max_attempts() {
+            let mut state = PollState::new(RetryPolicy {
+                max_attempts: 2,
+                ..Default::default()
+            });
+            assert!(state.on_order_response(order(AcmeOrderStatus::Pending), None).is_ok());
+            assert!(state.on_order_response(order(AcmeOrderStatus::Pending), None).is_ok());
+            assert!(matches!(
+                state
+                    .on_order_response(order(AcmeOrderStatus::Pending), None)
+                    .unwrap_err(),
+                RustyAcmeError::PollError(AcmePollError::AttemptsExhausted(2))
+            ));
+        }
+
+        #[test]
+        #[wasm_bindgen_test]
+        fn should_abort_when_terminal() {
+            let mut state = PollState::default();
+            assert!(matches!(
+                state
+                    .on_order_response(order(AcmeOrderStatus::Invalid), None)
+                    .unwrap_err(),
+                RustyAcmeError::OrderError(_)
+            ));
+
+            let mut chall = serde_json::to_value(AcmeChallenge::new_device()).unwrap();
+            chall["status"] = json!("invalid");
+            assert!(matches!(
+                state.on_chall_response(chall, None).unwrap_err(),
+                RustyAcmeError::ChallengeError(AcmeChallError::Invalid)
+            ));
+        }
+
+        #[test]
+        #[wasm_bindgen_test]
+        fn should_wait_when_rate_limited() {
+            let problem = json!({"type": "urn:ietf:params:acme:error:rateLimited", "status": 429});
+            let mut state = PollState::default();
+            // honored even beyond the max delay of the policy
+            let action = state.on_order_response(problem.clone(), Some("3600")).unwrap();
+            assert_eq!(action, PollAction::Wait(Duration::from_secs(3600)));
+            // backs off without hint
+            let action = state.on_order_response(problem, None).unwrap();
+            assert_eq!(action, PollAction::Wait(Duration::from_secs(2)));
+            assert_eq!(state.attempts(), 2);
+        }
+
+        #[test]
+        #[wasm_bindgen_test]
+        fn should_time_out_at_deadline() {
+            let now = time::OffsetDateTime::now_utc();
+            let mut state = PollState::default().with_deadline(now + time::Duration::seconds(10));
+            // the wait ends at the deadline at the latest
+            let action = state
+                .on_order_response(order(AcmeOrderStatus::Pending), Some("60"))
+                .unwrap();
+            assert!(matches!(action, PollAction::Wait(d) if d <= Duration::from_secs(10)));
+            // a ready resource is never late
+            let mut state = PollState::default().with_deadline(now - time::Duration::seconds(1));
+            let action = state.on_order_response(order(AcmeOrderStatus::Ready), None).unwrap();
+            assert!(matches!(action, PollAction::Proceed(_)));
+            assert!(matches!(
+                state
+                    .on_order_response(order(AcmeOrderStatus::Pending), None)
+                    .unwrap_err(),
+                RustyAcmeError::PollError(AcmePollError::Timeout)
+            ));
+            assert_eq!(state.attempts(), 0);
+        }
+
+        #[test]
+        #[wasm_bindgen_test]
+        fn should_stop_when_cancelled() {
+            let cancellation = PollCancellation::default();
+            let mut state = PollState::default().with_cancellation(cancellation.clone());
+            let action = state.on_order_response(order(AcmeOrderStatus::Pending), None).unwrap();
+            assert!(matches!(action, PollAction::Wait(_)));
+            cancellation.cancel();
+            assert!(matches!(
+                state
+                    .on_order_response(order(AcmeOrderStatus::Ready), None)
+                    .unwrap_err(),
+                RustyAcmeError::PollError(AcmePollError::Cancelled)
+            ));
+        }
+
+        #[test]
+        #[wasm_bindgen_test]
+        fn should_poll_authz_until_valid() {
+            let authz = |status| {
+                serde_json::to_value(AcmeAuthz {
+                    status,
+                    expires: None,
+                    ..Default::default()
+                })
+                .unwrap()
+            };
+            let mut state = PollState::default();
+            assert!(matches!(
+                state.on_authz_response(authz(AuthzStatus::Pending), None).unwrap(),
+                PollAction::Wait(_)
+            ));
+            assert!(matches!(
+                state.on_authz_response(authz(AuthzStatus::Valid), None).unwrap(),
+                PollAction::Proceed(_)
+            ));
+            assert!(matches!(
+                state
+                    .on_authz_response(authz(AuthzStatus::Deactivated), None)
+                    .unwrap_err(),
+                RustyAcmeError::AuthzError(AcmeAuthzError::Deactivated)
+            ));
+        }
+    }
+}
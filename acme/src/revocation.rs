@@ -0,0 +1,267 @@
+use base64::Engine;
+
+use rusty_jwt_tools::prelude::*;
+
+use crate::prelude::*;
+
+impl RustyAcme {
+    /// For revoking a certificate e.g. a compromised device credential, to send to the
+    /// [AcmeDirectory::revoke_cert] URL
+    /// see [RFC 8555 Section 7.6](https://www.rfc-editor.org/rfc/rfc8555.html#section-7.6)
+    ///
+    /// # Arguments
+    /// * `certificate` - DER encoded certificate to revoke
+    /// * `reason` - revocation reason, omitted when none
+    /// * `signer` - whether the request is signed by the account key which requested the
+    /// certificate or by the certificate key itself. [alg] and [kp] must be the ones of that key.
+    #[allow(clippy::too_many_arguments)]
+    pub fn revoke_cert_request(
+        directory: &AcmeDirectory,
+        certificate: &[u8],
+        reason: Option<RevocationReason>,
+        signer: RevocationSigner,
+        alg: impl Into<AcmeJwsAlgorithm>,
+        kp: &Pem,
+        previous_nonce: String,
+    ) -> RustyAcmeResult<AcmeJws> {
+        // the certificate key is advertised in the 'jwk' header when there is no account URL
+        let acct_url = match signer {
+            RevocationSigner::Account(account) => Some(account.acct_url()?),
+            RevocationSigner::CertificateKey => None,
+        };
+        let payload = AcmeRevocationRequest {
+            certificate: base64::prelude::BASE64_URL_SAFE_NO_PAD.encode(certificate),
+            reason,
+        };
+        let req = AcmeJws::new(
+            alg,
+            previous_nonce,
+            &directory.revoke_cert,
+            acct_url.as_ref(),
+            Some(payload),
+            kp,
+        )?;
+        Ok(req)
+    }
+
+    /// parse the response from `POST /acme/revoke-cert`. The server answers with an empty body on
+    /// success, pass [serde_json::Value::Null] then. Otherwise, it answers with a problem document.
+    /// see [RFC 8555 Section 7.6](https://www.rfc-editor.org/rfc/rfc8555.html#section-7.6)
+    pub fn revoke_cert_response(response: serde_json::Value) -> RustyAcmeResult<()> {
+        let problem = match response {
+            serde_json::Value::Null => return Ok(()),
+            serde_json::Value::Object(ref o) if o.is_empty() => return Ok(()),
+            response => serde_json::from_value::<AcmeProblem>(response)?,
+        };
+        let error = match problem.typ {
+            AcmeProblemType::AlreadyRevoked => AcmeRevocationError::AlreadyRevoked,
+            AcmeProblemType::BadRevocationReason => AcmeRevocationError::BadReason,
+            AcmeProblemType::Unauthorized => AcmeRevocationError::Unauthorized,
+            _ => AcmeRevocationError::Rejected(problem.reason()),
+        };
+        Err(error.into())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum AcmeRevocationError {
+    /// The certificate has already been revoked
+    #[error("The certificate has already been revoked")]
+    AlreadyRevoked,
+    /// The revocation reason is not allowed by the server
+    #[error("The revocation reason is not allowed by the server")]
+    BadReason,
+    /// The signing key is not authorized to revoke this certificate
+    #[error("The signing key is not authorized to revoke this certificate")]
+    Unauthorized,
+    /// The server refused to revoke the certificate
+    #[error("The server refused to revoke the certificate because {0}")]
+    Rejected(String),
+}
+
+/// Key signing a revocation request
+/// see [RFC 8555 Section 7.6](https://www.rfc-editor.org/rfc/rfc8555.html#section-7.6)
+#[derive(Debug, Clone, Copy)]
+pub enum RevocationSigner<'a> {
+    /// The account key which requested the certificate, identified by its account URL
+    Account(&'a AcmeAccount),
+    /// The key of the certificate itself, advertised in the 'jwk' header. For when the account
+    /// key is lost.
+    CertificateKey,
+}
+
+/// Revocation reason codes
+/// see [RFC 5280 Section 5.3.1](https://www.rfc-editor.org/rfc/rfc5280.html#section-5.3.1)
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(into = "u8", try_from = "u8")]
+pub enum RevocationReason {
+    Unspecified,
+    KeyCompromise,
+    CaCompromise,
+    AffiliationChanged,
+    Superseded,
+    CessationOfOperation,
+    CertificateHold,
+    RemoveFromCrl,
+    PrivilegeWithdrawn,
+    AaCompromise,
+}
+
+impl From<RevocationReason> for u8 {
+    fn from(reason: RevocationReason) -> Self {
+        match reason {
+            RevocationReason::Unspecified => 0,
+            RevocationReason::KeyCompromise => 1,
+            RevocationReason::CaCompromise => 2,
+            RevocationReason::AffiliationChanged => 3,
+            RevocationReason::Superseded => 4,
+            RevocationReason::CessationOfOperation => 5,
+            RevocationReason::CertificateHold => 6,
+            // 7 is not used
+            RevocationReason::RemoveFromCrl => 8,
+            RevocationReason::PrivilegeWithdrawn => 9,
+            RevocationReason::AaCompromise => 10,
+        }
+    }
+}
+
+impl TryFrom<u8> for RevocationReason {
+    type Error = RustyAcmeError;
+
+    fn try_from(code: u8) -> RustyAcmeResult<Self> {
+        Ok(match code {
+            0 => Self::Unspecified,
+            1 => Self::KeyCompromise,
+            2 => Self::CaCompromise,
+            3 => Self::AffiliationChanged,
+            4 => Self::Superseded,
+            5 => Self::CessationOfOperation,
+            6 => Self::CertificateHold,
+            8 => Self::RemoveFromCrl,
+            9 => Self::PrivilegeWithdrawn,
+            10 => Self::AaCompromise,
+            _ => return Err(AcmeRevocationError::BadReason)?,
+        })
+    }
+}
+
+/// For revoking a certificate
+/// see [RFC 8555 Section 7.6](https://www.rfc-editor.org/rfc/rfc8555.html#section-7.6)
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AcmeRevocationRequest {
+    /// base64url encoded DER certificate
+    pub certificate: String,
+    /// revocation reason code
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<RevocationReason>,
+}
+
+#[cfg(test)]
+pub mod tests {
+    use jwt_simple::prelude::*;
+    use serde_json::json;
+    use wasm_bindgen_test::*;
+
+    use crate::directory::tests::directory;
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    mod request {
+        use super::*;
+
+        #[test]
+        #[wasm_bindgen_test]
+        fn should_sign_with_account_key() {
+            let kp = Pem::from(Ed25519KeyPair::generate().to_pem());
+            let account = AcmeAccount::default();
+            let req = RustyAcme::revoke_cert_request(
+                &directory(None, None, None),
+                b"certificate",
+                Some(RevocationReason::KeyCompromise),
+                RevocationSigner::Account(&account),
+                JwsAlgorithm::Ed25519,
+                &kp,
+                "nonce".to_string(),
+            )
+            .unwrap();
+
+            let header = req.decoded_protected().unwrap();
+            assert_eq!(header["kid"], json!(account.acct_url().unwrap()));
+            assert_eq!(header["url"], json!("https://example.com/acme/revoke-cert"));
+            assert!(header.get("jwk").is_none());
+
+            let payload = req.decoded_payload().unwrap().unwrap();
+            let certificate = base64::prelude::BASE64_URL_SAFE_NO_PAD.encode(b"certificate");
+            assert_eq!(payload["certificate"], json!(certificate));
+            assert_eq!(payload["reason"], json!(1));
+        }
+
+        #[test]
+        #[wasm_bindgen_test]
+        fn should_sign_with_certificate_key() {
+            let kp = Pem::from(Ed25519KeyPair::generate().to_pem());
+            let req = RustyAcme::revoke_cert_request(
+                &directory(None, None, None),
+                b"certificate",
+                None,
+                RevocationSigner::CertificateKey,
+                JwsAlgorithm::Ed25519,
+                &kp,
+                "nonce".to_string(),
+            )
+            .unwrap();
+
+            let header = req.decoded_protected().unwrap();
+            assert!(header.get("kid").is_none());
+            assert!(header.get("jwk").is_some());
+            assert!(req.decoded_payload().unwrap().unwrap().get("reason").is_none());
+        }
+    }
+
+    mod response {
+        use super::*;
+
+        #[test]
+        #[wasm_bindgen_test]
+        fn should_succeed_when_empty() {
+            assert!(RustyAcme::revoke_cert_response(serde_json::Value::Null).is_ok());
+            assert!(RustyAcme::revoke_cert_response(json!({})).is_ok());
+        }
+
+        #[test]
+        #[wasm_bindgen_test]
+        fn should_fail_when_problem() {
+            let problem = |typ: &str| json!({"type": format!("urn:ietf:params:acme:error:{typ}"), "detail": "nope"});
+            assert!(matches!(
+                RustyAcme::revoke_cert_response(problem("alreadyRevoked")).unwrap_err(),
+                RustyAcmeError::RevocationError(AcmeRevocationError::AlreadyRevoked)
+            ));
+            assert!(matches!(
+                RustyAcme::revoke_cert_response(problem("badRevocationReason")).unwrap_err(),
+                RustyAcmeError::RevocationError(AcmeRevocationError::BadReason)
+            ));
+            assert!(matches!(
+                RustyAcme::revoke_cert_response(problem("unauthorized")).unwrap_err(),
+                RustyAcmeError::RevocationError(AcmeRevocationError::Unauthorized)
+            ));
+            assert!(matches!(
+                RustyAcme::revoke_cert_response(problem("serverInternal")).unwrap_err(),
+                RustyAcmeError::RevocationError(AcmeRevocationError::Rejected(detail)) if detail == "nope"
+            ));
+        }
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_roundtrip_reason_codes() {
+        for code in (0..=10).filter(|c| *c != 7) {
+            let reason = RevocationReason::try_from(code).unwrap();
+            assert_eq!(u8::from(reason), code);
+        }
+        assert!(RevocationReason::try_from(7).is_err());
+        assert!(serde_json::from_value::<RevocationReason>(json!(11)).is_err());
+    }
+}
@@ -0,0 +1,60 @@
+//! JSON Schemas of the ACME resources exchanged with the ACME server, so that the server and other
+//! components not written in Rust validate them against the same definitions as this library
+
+use schemars::{gen::SchemaSettings, schema::RootSchema, JsonSchema};
+
+use crate::prelude::*;
+
+impl RustyAcme {
+    /// JSON Schema of an [AcmeOrder]
+    pub fn order_schema() -> RootSchema {
+        Self::schema::<AcmeOrder>()
+    }
+
+    /// JSON Schema of an [AcmeAuthz]
+    pub fn authz_schema() -> RootSchema {
+        Self::schema::<AcmeAuthz>()
+    }
+
+    /// JSON Schema of an [AcmeChallenge]
+    pub fn challenge_schema() -> RootSchema {
+        Self::schema::<AcmeChallenge>()
+    }
+
+    fn schema<T: JsonSchema>() -> RootSchema {
+        SchemaSettings::draft07().into_generator().into_root_schema_for::<T>()
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use serde_json::json;
+    use wasm_bindgen_test::*;
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn order_schema_should_describe_order() {
+        let schema = serde_json::to_value(RustyAcme::order_schema()).unwrap();
+        assert_eq!(
+            schema["required"],
+            json!(["authorizations", "finalize", "identifiers", "status"])
+        );
+        assert_eq!(schema["properties"]["notBefore"]["type"], json!(["string", "null"]));
+        assert!(schema["definitions"]["AcmeIdentifier"].is_object());
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn challenge_schema_should_describe_wire_challenges() {
+        let schema = serde_json::to_value(RustyAcme::challenge_schema()).unwrap();
+        // documented variants end up in a 'oneOf', undocumented ones in an 'enum'
+        let types = schema["definitions"]["AcmeChallengeType"].to_string();
+        assert!(types.contains("\"wire-dpop-01\""));
+        assert!(types.contains("\"wire-oidc-01\""));
+        assert!(schema["properties"].get("type").is_some());
+    }
+}
@@ -0,0 +1,180 @@
+use crate::{cert_info::find_extension, error::CertificateError, prelude::*};
+
+/// 'Signed Certificate Timestamp List' extension embedded by CAs logging to Certificate Transparency
+/// see [RFC 6962 Section 3.3](https://www.rfc-editor.org/rfc/rfc6962#section-3.3)
+const SCT_LIST_OID: x509_cert::der::oid::ObjectIdentifier =
+    x509_cert::der::oid::ObjectIdentifier::new_unwrap("1.3.6.1.4.1.11129.2.4.2");
+
+/// Promise of a Certificate Transparency log to include the certificate, embedded in it by the CA
+/// see [RFC 6962 Section 3.2](https://www.rfc-editor.org/rfc/rfc6962#section-3.2)
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct AcmeSignedCertificateTimestamp {
+    /// 0 for v1
+    pub version: u8,
+    /// SHA-256 digest of the public key of the log
+    pub log_id: [u8; 32],
+    /// When the log accepted the certificate, with a millisecond precision
+    pub timestamp: time::OffsetDateTime,
+    /// TLS encoded, unparsed
+    pub extensions: Vec<u8>,
+    /// TLS 'HashAlgorithm' of the signature e.g. 4 for SHA-256
+    pub hash_algorithm: u8,
+    /// TLS 'SignatureAlgorithm' of the signature e.g. 3 for ECDSA
+    pub signature_algorithm: u8,
+    /// Signature of the log
+    pub signature: Vec<u8>,
+}
+
+impl AcmeSignedCertificateTimestamp {
+    /// Every timestamp embedded in the certificate, none when the CA does not log to CT
+    pub(crate) fn find_all(tbs: &x509_cert::TbsCertificate) -> RustyAcmeResult<Vec<Self>> {
+        let oid = oid_registry::Oid::new(std::borrow::Cow::Owned(SCT_LIST_OID.as_bytes().to_vec()));
+        let Some(list) = find_extension::<x509_cert::der::asn1::OctetString>(tbs, oid)? else {
+            return Ok(vec![]);
+        };
+        Self::try_from_list(list.as_bytes())
+    }
+
+    /// Parses a TLS encoded 'SignedCertificateTimestampList'
+    fn try_from_list(list: &[u8]) -> RustyAcmeResult<Vec<Self>> {
+        let mut list = TlsReader(list);
+        let mut scts = TlsReader(list.vec16()?);
+        list.finish()?;
+
+        let mut timestamps = vec![];
+        while !scts.0.is_empty() {
+            let mut sct = TlsReader(scts.vec16()?);
+            let version = sct.u8()?;
+            let log_id = sct.take(32)?.try_into().map_err(|_| CertificateError::InvalidFormat)?;
+            let millis = u64::from_be_bytes(sct.take(8)?.try_into().map_err(|_| CertificateError::InvalidFormat)?);
+            let timestamp = time::OffsetDateTime::from_unix_timestamp_nanos(millis as i128 * 1_000_000)
+                .map_err(|_| CertificateError::InvalidFormat)?;
+            let extensions = sct.vec16()?.to_vec();
+            let hash_algorithm = sct.u8()?;
+            let signature_algorithm = sct.u8()?;
+            let signature = sct.vec16()?.to_vec();
+            sct.finish()?;
+            timestamps.push(Self {
+                version,
+                log_id,
+                timestamp,
+                extensions,
+                hash_algorithm,
+                signature_algorithm,
+                signature,
+            });
+        }
+        Ok(timestamps)
+    }
+}
+
+/// Reads the TLS presentation language, see [RFC 8446 Section 3](https://www.rfc-editor.org/rfc/rfc8446#section-3)
+struct TlsReader<'a>(&'a [u8]);
+
+impl<'a> TlsReader<'a> {
+    fn take(&mut self, len: usize) -> RustyAcmeResult<&'a [u8]> {
+        if self.0.len() < len {
+            return Err(CertificateError::InvalidFormat)?;
+        }
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> RustyAcmeResult<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    /// Variable-length vector with a 2 bytes length prefix
+    fn vec16(&mut self) -> RustyAcmeResult<&'a [u8]> {
+        let len = self.take(2)?;
+        self.take(u16::from_be_bytes([len[0], len[1]]) as usize)
+    }
+
+    fn finish(&self) -> RustyAcmeResult<()> {
+        if !self.0.is_empty() {
+            return Err(CertificateError::InvalidFormat)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use wasm_bindgen_test::*;
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    /// Embeds 2 timestamps from fake logs
+    const LEAF: &str = r#"-----BEGIN CERTIFICATE-----
+MIICJTCCAcugAwIBAgICEjQwCgYIKoZIzj0EAwIwLzERMA8GA1UECgwId2lyZS5j
+b20xGjAYBgNVBAMMEVdpcmUgT0NTUCBSb290IENBMCAXDTI2MTAxNjE1MTQxNloY
+DzIxMjYwOTIyMTUxNDE2WjApMREwDwYDVQQKDAh3aXJlLmNvbTEUMBIGA1UEAwwL
+QWxpY2UgU21pdGgwWTATBgcqhkjOPQIBBggqhkjOPQMBBwNCAAQyrL3a3MktevCV
+7Xq3kUMs2O4bwGJl3TdFbMjIMc8XQplDG1QkYW4bSMbuYudCVqLF9Yk0fGhOTv5p
+78qQH8ejo4HaMIHXMIGEBgorBgEEAdZ5AgQCBHYEdAByADcAAAECAwQFBgcICQoL
+DA0ODxAREhMUFRYXGBkaGxwdHh8AAAGZ7ZT8GwAABAMACDAGAgEBAgECADcA////
+//////////////////////////////////////8AAAGZ7ZUBUAAABAMACDAGAgED
+AgEEMA4GA1UdDwEB/wQEAwIHgDAdBgNVHQ4EFgQUrMU1j06v2An15njuRGqFmyB4
+yqowHwYDVR0jBBgwFoAUbNDoeYZtReNjlvYnpfoHavHB0f8wCgYIKoZIzj0EAwID
+SAAwRQIhAKUgUYxSJmpLpg9+x4+f42eGMoutKe9GT3K2l7NWzpo2AiA/GCPxRCwJ
+q2J5D+8uDN6bC3Nw8KcJt3e6fPqomZLdXA==
+-----END CERTIFICATE-----"#;
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_extract_timestamps() {
+        let leaf = pem::parse(LEAF).unwrap();
+        let info = AcmeCertificateInfo::try_from_der(leaf.contents()).unwrap();
+        let [first, second] = &info.signed_certificate_timestamps[..] else {
+            panic!("expected 2 timestamps")
+        };
+
+        assert_eq!(first.version, 0);
+        assert_eq!(first.log_id, core::array::from_fn::<u8, 32, _>(|i| i as u8));
+        assert_eq!(first.timestamp.unix_timestamp_nanos(), 1760627588123 * 1_000_000);
+        assert!(first.extensions.is_empty());
+        assert_eq!((first.hash_algorithm, first.signature_algorithm), (4, 3));
+        assert_eq!(hex::encode(&first.signature), "3006020101020102");
+
+        assert_eq!(second.log_id, [0xFF; 32]);
+        assert_eq!(second.timestamp.unix_timestamp_nanos(), 1760627589456 * 1_000_000);
+        assert_eq!(hex::encode(&second.signature), "3006020103020104");
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_have_no_timestamp_when_not_logged() {
+        let der = pem::parse(crate::identity::tests::CERT).unwrap();
+        let info = AcmeCertificateInfo::try_from_der(der.contents()).unwrap();
+        assert!(info.signed_certificate_timestamps.is_empty());
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_fail_when_malformed() {
+        // list length larger than the list
+        let result = AcmeSignedCertificateTimestamp::try_from_list(&[0, 4, 0, 1]);
+        assert!(matches!(
+            result.unwrap_err(),
+            RustyAcmeError::InvalidCertificate(CertificateError::InvalidFormat)
+        ));
+        // trailing bytes in a timestamp
+        let mut sct = vec![0u8; 1 + 32 + 8 + 2 + 2 + 2];
+        sct.push(0);
+        let mut list = (sct.len() as u16).to_be_bytes().to_vec();
+        list.extend(sct);
+        let mut encoded = (list.len() as u16).to_be_bytes().to_vec();
+        encoded.extend(list);
+        let result = AcmeSignedCertificateTimestamp::try_from_list(&encoded);
+        assert!(matches!(
+            result.unwrap_err(),
+            RustyAcmeError::InvalidCertificate(CertificateError::InvalidFormat)
+        ));
+
+        let result = AcmeSignedCertificateTimestamp::try_from_list(&[0, 0]);
+        assert!(result.unwrap().is_empty());
+    }
+}
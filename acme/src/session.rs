@@ -0,0 +1,116 @@
+use crate::prelude::*;
+
+/// Snapshot of an enrollment in progress, to persist it and resume exactly where it stopped e.g.
+/// when the app is killed during the redirection to the identity provider. Its serialized form is
+/// stable: it is tagged with a version and deserializing an unknown one fails.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionState {
+    version: SessionStateVersion,
+    pub directory: AcmeDirectory,
+    /// Unused nonce from the last response. Might have expired when resuming, in which case the
+    /// request is retried with a fresh one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub account: Option<AcmeAccount>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order: Option<AcmeOrder>,
+    /// To check the order with [AcmeClient::check_order]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order_url: Option<url::Url>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub authorizations: Vec<AcmeAuthz>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finalize: Option<AcmeFinalize>,
+}
+
+/// Bumped on every breaking change of the serialized [SessionState]
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum SessionStateVersion {
+    #[default]
+    V1,
+}
+
+impl SessionState {
+    /// Snapshot of a session which has only fetched the directory
+    pub fn new(directory: AcmeDirectory) -> Self {
+        Self {
+            version: SessionStateVersion::default(),
+            directory,
+            nonce: None,
+            account: None,
+            order: None,
+            order_url: None,
+            authorizations: vec![],
+            finalize: None,
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use serde_json::json;
+    use wasm_bindgen_test::*;
+
+    use crate::directory::tests::directory;
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    pub fn state() -> SessionState {
+        let order = json!({
+            "status": "pending",
+            "finalize": "https://acme.wire.com/acme/wire/order/abc/finalize",
+            "identifiers": [],
+            "authorizations": ["https://acme.wire.com/acme/wire/authz/def"],
+            "profile": "device"
+        });
+        let authz = json!({
+            "status": "pending",
+            "challenges": [{
+                "type": "wire-oidc-01",
+                "url": "https://acme.wire.com/acme/wire/challenge/def/ghi",
+                "token": "abc",
+                "target": "https://idp.example.com/realms/wire"
+            }],
+            "identifier": {"type": "wireapp-user", "value": "{}"}
+        });
+        SessionState {
+            nonce: Some("nonce".to_string()),
+            account: Some(
+                serde_json::from_value(
+                    json!({"status": "valid", "orders": "https://acme.wire.com/acme/wire/account/abc/orders"}),
+                )
+                .unwrap(),
+            ),
+            order: Some(serde_json::from_value(order).unwrap()),
+            order_url: Some("https://acme.wire.com/acme/wire/order/abc".parse().unwrap()),
+            authorizations: vec![serde_json::from_value(authz).unwrap()],
+            ..SessionState::new(directory(None, None, None))
+        }
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_roundtrip() {
+        let state = state();
+        let serialized = serde_json::to_value(&state).unwrap();
+        assert_eq!(serialized["version"], "v1");
+        let deserialized = serde_json::from_value::<SessionState>(serialized.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&deserialized).unwrap(), serialized);
+        assert_eq!(deserialized.order, state.order);
+        assert_eq!(deserialized.account, state.account);
+        assert_eq!(deserialized.nonce, state.nonce);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_fail_when_unknown_version() {
+        let mut serialized = serde_json::to_value(state()).unwrap();
+        serialized["version"] = json!("v2");
+        assert!(serde_json::from_value::<SessionState>(serialized).is_err());
+    }
+}
@@ -0,0 +1,172 @@
+use jwt_simple::prelude::Jwk;
+use sha2::Digest;
+use x509_cert::der::{asn1::OctetString, Encode};
+
+use rusty_jwt_tools::prelude::*;
+
+use crate::prelude::*;
+
+/// 'id-pe-acmeIdentifier' extension, holding the SHA-256 digest of the key authorization
+/// see [RFC 8737 Section 6.1](https://www.rfc-editor.org/rfc/rfc8737.html#section-6.1)
+const ACME_IDENTIFIER_OID: x509_cert::der::oid::ObjectIdentifier =
+    x509_cert::der::oid::ObjectIdentifier::new_unwrap("1.3.6.1.5.5.7.1.31");
+
+/// The challenge certificate is only presented for the time of the validation
+const TLS_ALPN01_CERTIFICATE_VALIDITY: core::time::Duration = core::time::Duration::from_secs(7 * 24 * 3600);
+
+impl AcmeChallenge {
+    /// ALPN protocol the ACME server negotiates when validating a tls-alpn-01 challenge
+    /// see [RFC 8737 Section 6.2](https://www.rfc-editor.org/rfc/rfc8737.html#section-6.2)
+    pub const TLS_ALPN01_PROTOCOL: &'static [u8] = b"acme-tls/1";
+
+    /// Self-signed certificate to present, DER encoded, when the ACME server connects to [domain]
+    /// negotiating [Self::TLS_ALPN01_PROTOCOL]. It has [domain] as only subject alternative name
+    /// and the digest of the key authorization in a critical 'acmeIdentifier' extension.
+    /// see [RFC 8737 Section 3](https://www.rfc-editor.org/rfc/rfc8737.html#section-3)
+    ///
+    /// # Arguments
+    /// * `account_jwk` - public key of the ACME account
+    /// * `alg` - algorithm of [kp]
+    /// * `kp` - key pair of the certificate, it does not have to be the account one
+    pub fn tls_alpn01_certificate(
+        &self,
+        domain: &str,
+        account_jwk: &Jwk,
+        alg: JwsAlgorithm,
+        kp: &Pem,
+    ) -> RustyAcmeResult<Vec<u8>> {
+        self.expect_type(AcmeChallengeType::TlsAlpn01)?;
+
+        let digest = sha2::Sha256::digest(self.key_authorization(account_jwk)?);
+        let acme_identifier = x509_cert::ext::Extension {
+            extn_id: ACME_IDENTIFIER_OID,
+            critical: true,
+            extn_value: OctetString::new(OctetString::new(digest.as_slice())?.to_der()?)?,
+        };
+
+        let dns_name = x509_cert::der::asn1::Ia5String::new(domain.trim_end_matches('.'))?;
+        let san =
+            x509_cert::ext::pkix::SubjectAltName(vec![x509_cert::ext::pkix::name::GeneralName::DnsName(dns_name)]);
+        let san = x509_cert::ext::Extension {
+            extn_id: oid_registry::OID_X509_EXT_SUBJECT_ALT_NAME.as_bytes().try_into()?,
+            critical: false,
+            extn_value: OctetString::new(san.to_der()?)?,
+        };
+
+        let algorithm = RustyAcme::csr_alg(alg)?;
+        let tbs_certificate = x509_cert::TbsCertificate {
+            version: x509_cert::Version::V3,
+            serial_number: Self::tls_alpn01_serial_number()?,
+            signature: algorithm.clone(),
+            // self-signed, only the subject alternative name matters
+            issuer: x509_cert::name::Name::default(),
+            validity: Self::tls_alpn01_validity()?,
+            subject: x509_cert::name::Name::default(),
+            subject_public_key_info: RustyAcme::csr_spki(alg, kp)?,
+            issuer_unique_id: None,
+            subject_unique_id: None,
+            extensions: Some(vec![san, acme_identifier]),
+        };
+        let signature = RustyAcme::csr_signature(alg, kp, &tbs_certificate)?;
+
+        let certificate = x509_cert::Certificate {
+            tbs_certificate,
+            signature_algorithm: algorithm,
+            signature,
+        };
+        Ok(certificate.to_der()?)
+    }
+
+    fn tls_alpn01_serial_number() -> RustyAcmeResult<x509_cert::serial_number::SerialNumber> {
+        let mut serial = [0u8; 16];
+        getrandom::getrandom(&mut serial).map_err(|_| RustyAcmeError::ImplementationError)?;
+        // positive and without leading zero
+        serial[0] = (serial[0] & 0x7F) | 0x40;
+        Ok(x509_cert::serial_number::SerialNumber::new(&serial)?)
+    }
+
+    fn tls_alpn01_validity() -> RustyAcmeResult<x509_cert::time::Validity> {
+        let to_time = |at: time::OffsetDateTime| -> RustyAcmeResult<x509_cert::time::Time> {
+            let since_epoch = core::time::Duration::from_secs(at.unix_timestamp().try_into().unwrap_or_default());
+            Ok(x509_cert::der::asn1::UtcTime::from_unix_duration(since_epoch)?.into())
+        };
+        let now = time::OffsetDateTime::now_utc();
+        Ok(x509_cert::time::Validity {
+            not_before: to_time(now)?,
+            not_after: to_time(now + TLS_ALPN01_CERTIFICATE_VALIDITY)?,
+        })
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use jwt_simple::prelude::*;
+    use rusty_jwt_tools::jwk::TryIntoJwk;
+    use wasm_bindgen_test::*;
+    use x509_cert::der::Decode;
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    fn chall() -> AcmeChallenge {
+        AcmeChallenge {
+            typ: AcmeChallengeType::TlsAlpn01,
+            token: "evaGxfADs6pSRb2LAv9IZf17Dt3juxGJ-PCt92wr-oA".to_string(),
+            ..AcmeChallenge::new_device()
+        }
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_build_challenge_certificate() {
+        let account_jwk = Ed25519KeyPair::generate().public_key().try_into_jwk().unwrap();
+        let kp = Ed25519KeyPair::generate();
+        let pem = Pem::from(kp.to_pem());
+        let chall = chall();
+
+        let der = chall
+            .tls_alpn01_certificate("example.org", &account_jwk, JwsAlgorithm::Ed25519, &pem)
+            .unwrap();
+        let cert = x509_cert::Certificate::from_der(&der).unwrap();
+        let extensions = cert.tbs_certificate.extensions.as_ref().unwrap();
+
+        let acme_identifier = extensions.iter().find(|e| e.extn_id == ACME_IDENTIFIER_OID).unwrap();
+        assert!(acme_identifier.critical);
+        let digest = OctetString::from_der(acme_identifier.extn_value.as_bytes()).unwrap();
+        let expected = sha2::Sha256::digest(chall.key_authorization(&account_jwk).unwrap());
+        assert_eq!(digest.as_bytes(), expected.as_slice());
+
+        let san = extensions
+            .iter()
+            .find(|e| e.extn_id.as_bytes() == oid_registry::OID_X509_EXT_SUBJECT_ALT_NAME.as_bytes())
+            .unwrap();
+        let san = x509_cert::ext::pkix::SubjectAltName::from_der(san.extn_value.as_bytes()).unwrap();
+        assert!(matches!(
+            &san.0[..],
+            [x509_cert::ext::pkix::name::GeneralName::DnsName(name)] if name.as_str() == "example.org"
+        ));
+
+        // self-signed
+        let tbs = cert.tbs_certificate.to_der().unwrap();
+        let signature = ed25519_compact::Signature::from_slice(cert.signature.raw_bytes()).unwrap();
+        let pk = ed25519_compact::PublicKey::from_slice(&kp.public_key().to_bytes()).unwrap();
+        assert!(pk.verify(tbs, &signature).is_ok());
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_fail_when_not_tls_alpn01() {
+        let account_jwk = Ed25519KeyPair::generate().public_key().try_into_jwk().unwrap();
+        let pem = Pem::from(Ed25519KeyPair::generate().to_pem());
+        let chall = AcmeChallenge {
+            typ: AcmeChallengeType::Http01,
+            ..chall()
+        };
+        let result = chall.tls_alpn01_certificate("example.org", &account_jwk, JwsAlgorithm::Ed25519, &pem);
+        assert!(matches!(
+            result.unwrap_err(),
+            RustyAcmeError::ChallengeError(AcmeChallError::UnexpectedType(AcmeChallengeType::TlsAlpn01))
+        ));
+    }
+}
@@ -0,0 +1,27 @@
+use crate::utils::*;
+use clap::Parser;
+use console::style;
+use rusty_jwt_tools::prelude::*;
+use std::path::PathBuf;
+
+#[derive(Debug, Parser)]
+pub struct AccessDecode {
+    /// access token to decode
+    pub access_token: Option<PathBuf>,
+}
+
+impl AccessDecode {
+    pub fn execute(self) -> anyhow::Result<()> {
+        let access_token = read_file(self.access_token.as_ref())
+            .unwrap_or_else(read_stdin)
+            .trim()
+            .to_string();
+
+        let introspected = RustyJwtTools::introspect_access_token(&access_token).expect("Invalid access token");
+
+        println!("{}", style("⚠️ decoded WITHOUT verifying the signature").yellow());
+        println!("{}", serde_json::to_string_pretty(&introspected)?);
+
+        Ok(())
+    }
+}
@@ -49,6 +49,11 @@ pub struct AccessGenerate {
     /// e.g. '5' (current default)
     #[arg(long, default_value = "5")]
     api_version: u32,
+    /// space-delimited scope tokens the access token grants
+    ///
+    /// e.g. 'wire_client_id' (current default)
+    #[arg(long, default_value = "wire_client_id")]
+    scope: String,
 }
 
 impl AccessGenerate {
@@ -89,22 +94,23 @@ impl AccessGenerate {
         let max_expiration: u64 = 2136351646; // somewhere in 2037
         let hash_alg = HashAlgorithm::from(alg);
 
-        let access_token = RustyJwtTools::generate_access_token(
-            &client_dpop_token,
-            &client_id,
-            handle,
-            self.team.into(),
-            nonce,
-            htu,
-            htm,
-            leeway,
-            max_expiration,
-            backend_pk,
-            hash_alg,
-            self.api_version,
-            core::time::Duration::from_secs(360),
-        )
-        .unwrap();
+        let access_token = AccessTokenBuilder::default()
+            .dpop_proof(&client_dpop_token)
+            .client_id(&client_id)
+            .handle(handle)
+            .team(self.team.into())
+            .backend_nonce(nonce)
+            .htu(htu)
+            .htm(htm)
+            .max_skew_secs(leeway)
+            .max_expiration(max_expiration)
+            .backend_keys(backend_pk)
+            .hash_algorithm(hash_alg)
+            .api_version(self.api_version)
+            .scope(self.scope.as_str())
+            .expiry(core::time::Duration::from_secs(360))
+            .build()
+            .unwrap();
 
         println!("{access_token}");
 
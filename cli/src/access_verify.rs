@@ -40,7 +40,13 @@ pub struct AccessVerify {
     /// e.g. 'https://wire.com/clients/123abef456/access-token'
     #[arg(long)]
     pub issuer: String,
-    /// hash algorithm used to compute the JWK thumbprint. Supported values: ['SHA-256', 'SHA-384']
+    /// audience the access token has to be meant for. Can be repeated to accept any of several audiences.
+    /// Not verified when absent
+    ///
+    /// e.g. 'https://stepca/acme/wire/challenge/aaa/bbb'
+    #[arg(long)]
+    pub audience: Vec<String>,
+    /// hash algorithm used to compute the JWK thumbprint. Supported values: ['SHA-256', 'SHA-384', 'SHA-512']
     ///
     /// e.g. 'SHA-256'
     #[arg(long)]
@@ -48,14 +54,25 @@ pub struct AccessVerify {
     /// Thumbprint of the dpop proof JWK
     #[arg(long)]
     pub kid: String,
-    /// path to file with wire-server's signature public key in PEM format
-    #[arg(long)]
-    pub key: PathBuf,
+    /// path to file with wire-server's signature public key in PEM format.
+    /// Can be repeated to trust several keys e.g. during a key rotation
+    #[arg(long, required = true)]
+    pub key: Vec<PathBuf>,
     /// version of wire-server http API
     ///
     /// e.g. '5' (current default)
     #[arg(long, default_value = "5")]
     pub api_version: u32,
+    /// space-delimited scope tokens the access token must all grant
+    ///
+    /// e.g. 'wire_client_id' (current default)
+    #[arg(long, default_value = "wire_client_id")]
+    pub scope: String,
+    /// 'jti' of an access token revoked before its expiry. Can be repeated
+    ///
+    /// e.g. '046e05f9-92b8-43f6-93ae-faf5c3640b6d'
+    #[arg(long)]
+    pub revoked: Vec<String>,
 }
 
 impl AccessVerify {
@@ -67,23 +84,43 @@ impl AccessVerify {
 
         let client_id = ClientId::try_from_uri(&self.client_id).expect("Invalid 'client_id'");
         let challenge: AcmeNonce = self.challenge.into();
-        let (_, backend_pk) = parse_public_key_pem(read_file(Some(&self.key)).unwrap());
+        let backend_pks = self
+            .key
+            .iter()
+            .map(|key| parse_public_key_pem(read_file(Some(key)).unwrap()).1)
+            .collect::<Vec<_>>();
         let issuer = self.issuer.as_str().try_into().expect("Invalid 'issuer'");
         let handle = self.handle.parse::<QualifiedHandle>().expect("Invalid handle");
+        let audience = self
+            .audience
+            .iter()
+            .map(|aud| aud.parse().expect("Invalid 'audience'"))
+            .collect::<Vec<_>>();
+        let audience = if audience.is_empty() {
+            AudiencePolicy::Ignore
+        } else {
+            AudiencePolicy::Required(audience)
+        };
 
-        let verification = RustyJwtTools::verify_access_token(
-            &access_token,
-            &client_id,
-            &handle,
-            challenge,
-            self.leeway,
-            self.max_expiry,
-            issuer,
-            backend_pk,
-            self.kid,
-            self.hash_algorithm,
-            self.api_version,
-        );
+        let token = AccessTokenToVerify {
+            access_token: &access_token,
+            client_id: &client_id,
+            handle: &handle,
+            challenge: &challenge,
+            client_kid: &self.kid,
+        };
+        let revocation = InMemoryRevocationList::from_iter(self.revoked);
+        let options = VerifyAccessTokenOptions::default()
+            .max_skew_secs(self.leeway)
+            .max_expiration(self.max_expiry)
+            .issuers([issuer])
+            .audience(audience)
+            .backend_pks(backend_pks)
+            .hash_algorithm(self.hash_algorithm)
+            .api_versions(self.api_version)
+            .scope(self.scope.as_str())
+            .revocation(&revocation);
+        let verification = RustyJwtTools::verify_access_token(&token, &options);
 
         if verification.is_ok() {
             println!("✅ access token is valid");
@@ -0,0 +1,10 @@
+use clap::{CommandFactory, FromArgMatches};
+use rusty_jwt_cli::*;
+
+fn main() -> anyhow::Result<()> {
+    let matches = RustyCli::command()
+        .name("rusty-jwt")
+        .bin_name("rusty-jwt")
+        .get_matches();
+    RustyCli::from_arg_matches(&matches)?.execute()
+}
@@ -0,0 +1,83 @@
+use crate::{pem::*, utils::*};
+use clap::Parser;
+use rusty_jwt_tools::prelude::*;
+use std::path::PathBuf;
+
+#[derive(Debug, Parser)]
+pub struct DpopGenerate {
+    /// path to file with the client's key pair in PEM format, see `key-generate`
+    #[arg(short = 'k', long)]
+    key: PathBuf,
+    /// base64Url encoded challenge (nonce) generated by acme server
+    ///
+    /// e.g. 'okAJ33Ym/XS2qmmhhh7aWSbBlYy4Ttm1EysqW8I/9ng'
+    #[arg(short = 'c', long)]
+    challenge: String,
+    /// base64Url encoded nonce generated by wire-server
+    ///
+    /// e.g. 'WE88EvOBzbqGerznM+2P/AadVf7374y0cH19sDSZA2A'
+    #[arg(long)]
+    nonce: String,
+    /// wire-server uri this token will be sent to
+    ///
+    /// e.g. 'https://wire.example.com/clients/token'
+    #[arg(long)]
+    htu: String,
+    /// HTTP method of the request this token will be sent with
+    #[arg(long, default_value = "POST")]
+    htm: String,
+    /// ACME challenge url the token is meant for
+    ///
+    /// e.g. 'https://stepca/acme/wire/challenge/aaa/bbb'
+    #[arg(long)]
+    audience: String,
+    /// qualified wire client id
+    ///
+    /// e.g. 'wireapp://lJGYPz0ZRq2kvc_XpdaDlA!7b52de7af952ba14@wire.com'
+    #[arg(short = 'i', long)]
+    client_id: String,
+    /// Wire handle
+    ///
+    /// e.g. 'beltram_wire'
+    #[arg(long)]
+    handle: String,
+    /// Wire team the user belongs to
+    ///
+    /// e.g. 'wire'
+    #[arg(short = 't', long)]
+    team: Option<String>,
+    /// dpop token expiration in seconds
+    ///
+    /// e.g. '300' for 5 minutes
+    #[arg(short = 'e', long, default_value = "300")]
+    expiry: u64,
+}
+
+impl DpopGenerate {
+    pub fn execute(self) -> anyhow::Result<()> {
+        let (alg, client_kp) = parse_key_pair_pem(read_file(Some(&self.key)).unwrap());
+
+        let client_id = ClientId::try_from_uri(&self.client_id).expect("Invalid 'client_id'");
+        let handle = Handle::from(self.handle)
+            .try_to_qualified(&client_id.domain)
+            .expect("Invalid 'handle'");
+        let dpop = Dpop {
+            challenge: self.challenge.into(),
+            htm: self.htm.as_str().try_into().expect("Invalid 'htm'"),
+            htu: self.htu.as_str().try_into().expect("Invalid 'htu'"),
+            handle,
+            team: self.team.into(),
+            extra_claims: None,
+        };
+        let audience = self.audience.parse().expect("Invalid 'audience'");
+        let expiry = core::time::Duration::from_secs(self.expiry);
+
+        let dpop_token =
+            RustyJwtTools::generate_dpop_token(dpop, &client_id, self.nonce.into(), audience, expiry, alg, &client_kp)
+                .expect("Failed generating client Dpop token");
+
+        println!("{dpop_token}");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,95 @@
+use crate::utils::*;
+use clap::Parser;
+use console::style;
+use rusty_jwt_tools::prelude::*;
+use std::path::PathBuf;
+
+#[derive(Debug, Parser)]
+pub struct DpopVerify {
+    /// dpop token to verify
+    pub dpop_token: Option<PathBuf>,
+    /// qualified wire client id
+    ///
+    /// e.g. 'wireapp://lJGYPz0ZRq2kvc_XpdaDlA!7b52de7af952ba14@wire.com'
+    #[arg(long)]
+    pub client_id: String,
+    /// Wire handle
+    ///
+    /// e.g. 'beltram_wire'
+    #[arg(long)]
+    pub handle: String,
+    /// Wire team the user belongs to
+    ///
+    /// e.g. 'wire'
+    #[arg(short = 't', long)]
+    pub team: Option<String>,
+    /// base64Url encoded nonce generated by wire-server
+    ///
+    /// e.g. 'WE88EvOBzbqGerznM+2P/AadVf7374y0cH19sDSZA2A'
+    #[arg(long)]
+    pub nonce: String,
+    /// wire-server uri the token has been sent to
+    ///
+    /// e.g. 'https://wire.example.com/clients/token'
+    #[arg(long)]
+    pub htu: String,
+    /// HTTP method of the request the token has been sent with
+    #[arg(long, default_value = "POST")]
+    pub htm: String,
+    /// maximum of clock skew in seconds allowed. Defaults to 360.
+    ///
+    /// e.g. '360' (5 min)
+    #[arg(long, default_value = "360")]
+    pub leeway: u16,
+    /// dpop token maximum allowed expiration expressed as unix timestamp
+    ///
+    /// e.g. '1701507459'
+    #[arg(long)]
+    pub max_expiry: u64,
+}
+
+impl DpopVerify {
+    pub fn execute(self) -> anyhow::Result<()> {
+        let dpop_token = read_file(self.dpop_token.as_ref())
+            .unwrap_or_else(read_stdin)
+            .trim()
+            .to_string();
+
+        let client_id = ClientId::try_from_uri(&self.client_id).expect("Invalid 'client_id'");
+        let handle = Handle::from(self.handle)
+            .try_to_qualified(&client_id.domain)
+            .expect("Invalid 'handle'");
+        let htu = self.htu.as_str().try_into().expect("Invalid 'htu'");
+        let htm = self.htm.as_str().try_into().expect("Invalid 'htm'");
+
+        let report = RustyJwtTools::verify_dpop_proof_with_report(
+            &dpop_token,
+            &client_id,
+            &handle,
+            &self.team.into(),
+            TeamPolicy::default(),
+            &self.nonce.into(),
+            &htu,
+            htm,
+            self.leeway,
+            self.max_expiry,
+            &ClaimsVersion::ALL,
+            &DomainPolicy::Any,
+        );
+
+        for check in &report.checks {
+            let line = format!("{}.{}", check.token, check.check);
+            match &check.error {
+                None => println!("- {}", style(line).green()),
+                Some(error) => println!("- {}: {}", style(line).red(), error),
+            }
+        }
+        if report.is_valid() {
+            println!("✅ dpop token is valid");
+        } else {
+            panic!("❌ dpop token is not valid");
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,132 @@
+use crate::pem::*;
+use crate::utils::read_file;
+use clap::Parser;
+use console::style;
+use jwt_simple::prelude::*;
+use rusty_acme::prelude::{CertificateChain, HttpClientConfig};
+use rusty_jwt_tools::prelude::*;
+use std::path::PathBuf;
+use wire_e2e_identity::prelude::{E2eiEnrollment, E2eiEnrollmentFlow, E2eiEnrollmentStep, RustyE2eIdentity};
+
+#[derive(Debug, Parser)]
+pub struct Enroll {
+    /// directory url of the ACME server
+    ///
+    /// e.g. 'https://acme.wire.example.com/acme/wire/directory'
+    #[arg(short = 'd', long)]
+    directory: String,
+    /// path to file with the client's signature key pair in PEM format, see `key-generate`
+    #[arg(short = 'k', long)]
+    key: PathBuf,
+    /// qualified wire client id
+    ///
+    /// e.g. 'lJGYPz0ZRq2kvc_XpdaDlA:7b52de7af952ba14@wire.com'
+    #[arg(short = 'i', long)]
+    client_id: String,
+    /// user display name
+    ///
+    /// e.g. 'Alice Smith'
+    #[arg(long)]
+    display_name: String,
+    /// Wire handle
+    ///
+    /// e.g. 'beltram_wire'
+    #[arg(long)]
+    handle: String,
+    /// Wire team the user belongs to
+    ///
+    /// e.g. 'wire'
+    #[arg(short = 't', long)]
+    team: Option<String>,
+    /// certificate validity in seconds
+    ///
+    /// e.g. '7776000' for 90 days (current default)
+    #[arg(short = 'e', long, default_value = "7776000")]
+    expiry: u64,
+    /// file to write the certificate chain to in PEM format. Printed to stdout when absent
+    #[arg(short = 'o', long)]
+    out: Option<PathBuf>,
+}
+
+impl Enroll {
+    pub fn execute(self) -> anyhow::Result<()> {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?
+            .block_on(self.enroll())
+    }
+
+    /// Drives the enrollment, prompting for the access token from wire-server and for the ID
+    /// token from the identity provider since both require to be logged in
+    async fn enroll(self) -> anyhow::Result<()> {
+        let (alg, kp) = parse_key_pair_pem(read_file(Some(&self.key)).unwrap());
+        let raw_sign_key = match alg {
+            JwsAlgorithm::Ed25519 => Ed25519KeyPair::from_pem(kp.as_str())?.to_bytes(),
+            JwsAlgorithm::P256 => ES256KeyPair::from_pem(kp.as_str())?.to_bytes(),
+            JwsAlgorithm::P384 => ES384KeyPair::from_pem(kp.as_str())?.to_bytes(),
+        };
+        let delegate = RustyE2eIdentity::try_new(alg, raw_sign_key)?;
+        let expiry = core::time::Duration::from_secs(self.expiry);
+        let mut enrollment = E2eiEnrollment::try_new(
+            delegate,
+            &self.client_id,
+            &self.display_name,
+            &self.handle,
+            self.team,
+            expiry,
+        )?;
+        let directory_url = self.directory.parse().expect("Invalid 'directory'");
+        let mut flow = E2eiEnrollmentFlow::new(HttpClientConfig::default().try_build()?);
+
+        flow.new_account(&mut enrollment, &directory_url).await?;
+        println!("{}", style("✅ account created").green());
+        flow.new_order(&mut enrollment).await?;
+        println!("{}", style("✅ order created").green());
+
+        if enrollment.step() == E2eiEnrollmentStep::DpopChallenge {
+            let nonce = prompt("wire-server nonce ('GET /clients/{client}/nonce')")?;
+            let dpop_token = enrollment.new_dpop_token(nonce, core::time::Duration::from_secs(300))?;
+            println!(
+                "- DPoP token to exchange for an access token:\n{}",
+                style(dpop_token).cyan()
+            );
+            let access_token = prompt("access token ('POST /clients/{client}/access-token')")?;
+            flow.dpop_challenge(&mut enrollment, access_token).await?;
+            println!("{}", style("✅ DPoP challenge valid").green());
+        }
+
+        if enrollment.step() == E2eiEnrollmentStep::OidcChallenge {
+            let keyauth = enrollment.keyauth().unwrap_or_default();
+            let audience = enrollment
+                .oidc_challenge()
+                .map(|c| c.url.to_string())
+                .unwrap_or_default();
+            println!("- claims to request in the ID token:");
+            println!("  keyauth: {}", style(keyauth).cyan());
+            println!("  acme_aud: {}", style(audience).cyan());
+            let id_token = prompt("ID token")?;
+            flow.oidc_challenge(&mut enrollment, id_token).await?;
+            println!("{}", style("✅ OIDC challenge valid").green());
+        }
+
+        let chain = flow.certificate(&mut enrollment).await?;
+        let chain = CertificateChain::try_from(chain)?.to_pem();
+        println!("{}", style("✅ certificate issued").green());
+        match self.out {
+            Some(out) => std::fs::write(out, chain)?,
+            None => println!("{chain}"),
+        }
+
+        Ok(())
+    }
+}
+
+fn prompt(what: &str) -> anyhow::Result<String> {
+    use std::io::Write as _;
+
+    print!("{} ", style(format!("? {what}:")).yellow());
+    std::io::stdout().flush()?;
+    let mut line = String::new();
+    std::io::stdin().read_line(&mut line)?;
+    Ok(line.trim().to_string())
+}
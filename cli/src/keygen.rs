@@ -0,0 +1,50 @@
+use clap::{Parser, ValueEnum};
+use jwt_simple::prelude::*;
+use std::{io::Write, path::PathBuf};
+
+#[derive(Debug, Parser)]
+pub struct KeyGenerate {
+    /// signature algorithm of the key pair
+    #[arg(short = 'a', long, value_enum, default_value = "ed25519")]
+    alg: KeyAlgorithm,
+    /// file to write the key pair to in PEM format, readable by its owner only. Printed to stdout when absent
+    ///
+    /// e.g. 'kp-ed25519.pem'
+    #[arg(short = 'o', long)]
+    out: Option<PathBuf>,
+}
+
+#[derive(Debug, Copy, Clone, ValueEnum)]
+pub enum KeyAlgorithm {
+    Ed25519,
+    P256,
+    P384,
+}
+
+impl KeyGenerate {
+    pub fn execute(self) -> anyhow::Result<()> {
+        let pem = match self.alg {
+            KeyAlgorithm::Ed25519 => Ed25519KeyPair::generate().to_pem(),
+            KeyAlgorithm::P256 => ES256KeyPair::generate().to_pem()?,
+            KeyAlgorithm::P384 => ES384KeyPair::generate().to_pem()?,
+        };
+        match self.out {
+            Some(out) => Self::write_private(&out, &pem)?,
+            None => println!("{pem}"),
+        }
+        Ok(())
+    }
+
+    /// Creates [path] with no permission for anyone but its owner, before the key gets in. An
+    /// existing file is restricted as well since the mode only applies on creation.
+    fn write_private(path: &std::path::Path, pem: &str) -> std::io::Result<()> {
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(path)?;
+        #[cfg(unix)]
+        file.set_permissions(std::os::unix::fs::PermissionsExt::from_mode(0o600))?;
+        file.write_all(pem.as_bytes())
+    }
+}
@@ -1,9 +1,15 @@
 use clap::{Parser, Subcommand};
 
+pub mod access_decode;
 pub mod access_generate;
 pub mod access_verify;
 pub mod build;
+pub mod dpop_generate;
+pub mod dpop_verify;
+#[cfg(feature = "enroll")]
+pub mod enroll;
 pub mod jwk;
+pub mod keygen;
 pub mod parse;
 pub mod pem;
 pub mod utils;
@@ -50,4 +56,49 @@ pub enum Commands {
         #[command(flatten)]
         delegate: access_generate::AccessGenerate,
     },
+    /// Decode an access token WITHOUT verifying it
+    DecodeAccess {
+        #[command(flatten)]
+        delegate: access_decode::AccessDecode,
+    },
+    /// Generates a client Dpop token
+    GenerateDpop {
+        #[command(flatten)]
+        delegate: dpop_generate::DpopGenerate,
+    },
+    /// Verify a client Dpop token, reporting every check
+    VerifyDpop {
+        #[command(flatten)]
+        delegate: dpop_verify::DpopVerify,
+    },
+    /// Generates a key pair in PEM format
+    KeyGenerate {
+        #[command(flatten)]
+        delegate: keygen::KeyGenerate,
+    },
+    /// Enrolls a client against an ACME server, prompting for the tokens requiring to be logged in
+    #[cfg(feature = "enroll")]
+    Enroll {
+        #[command(flatten)]
+        delegate: enroll::Enroll,
+    },
+}
+
+impl RustyCli {
+    pub fn execute(self) -> anyhow::Result<()> {
+        match self.cmd {
+            Commands::JwtBuild { delegate } => delegate.execute()?,
+            Commands::JwtParse { delegate } => delegate.execute()?,
+            Commands::JwkParse { delegate } => delegate.execute()?,
+            Commands::VerifyAccess { delegate } => delegate.execute()?,
+            Commands::GenerateAccess { delegate } => delegate.execute()?,
+            Commands::DecodeAccess { delegate } => delegate.execute()?,
+            Commands::GenerateDpop { delegate } => delegate.execute()?,
+            Commands::VerifyDpop { delegate } => delegate.execute()?,
+            Commands::KeyGenerate { delegate } => delegate.execute()?,
+            #[cfg(feature = "enroll")]
+            Commands::Enroll { delegate } => delegate.execute()?,
+        };
+        Ok(())
+    }
 }
@@ -0,0 +1,248 @@
+use core::future::Future;
+use std::sync::Arc;
+
+use futures_util::StreamExt;
+use rusty_acme::prelude::HttpTransport;
+
+use crate::{
+    flow::{saver, AcmeServerState, Saver},
+    prelude::*,
+};
+
+/// Enrolls many clients at once against the same ACME server e.g. from a provisioning tool. Each
+/// enrollment is driven by its own [E2eiEnrollmentFlow] over a clone of the transport and up to
+/// [Self::with_max_concurrency] of them progress concurrently. They share the nonces handed out
+/// by the ACME server and all of them hold off while it rate limits any of them.
+pub struct E2eiBulkEnrollment<T: HttpTransport + Clone> {
+    transport: T,
+    directory_url: url::Url,
+    max_concurrency: usize,
+    retry_policy: E2eiRetryPolicy,
+    observer: Option<Arc<dyn E2eiObserver>>,
+    saver: Option<Saver>,
+    server: Arc<AcmeServerState>,
+}
+
+/// Fetches the tokens vouching for the device and the user of each enrollment of an
+/// [E2eiBulkEnrollment]
+pub trait E2eiTokenProvider {
+    /// Error of wire-server or of the identity provider
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    /// Access token delivered by wire-server in exchange for [E2eiEnrollment::new_dpop_token]
+    fn access_token(&self, enrollment: &E2eiEnrollment) -> impl Future<Output = Result<String, Self::Error>>;
+
+    /// ID token delivered by the identity provider, holding [E2eiEnrollment::keyauth]
+    fn id_token(&self, enrollment: &E2eiEnrollment) -> impl Future<Output = Result<String, Self::Error>>;
+}
+
+impl<T: HttpTransport + Clone> E2eiBulkEnrollment<T> {
+    /// Enrollments progressing concurrently by default
+    pub const DEFAULT_MAX_CONCURRENCY: usize = 8;
+
+    /// # Parameters
+    /// * `directory_url` - e.g. `https://acme.wire.com/acme/wire/directory`
+    pub fn new(transport: T, directory_url: url::Url) -> Self {
+        Self {
+            transport,
+            directory_url,
+            max_concurrency: Self::DEFAULT_MAX_CONCURRENCY,
+            retry_policy: E2eiRetryPolicy::NONE,
+            observer: None,
+            saver: None,
+            server: Default::default(),
+        }
+    }
+
+    /// Lets at most [max_concurrency] enrollments progress at the same time, at least one
+    pub fn with_max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.max_concurrency = max_concurrency.max(1);
+        self
+    }
+
+    /// Retries every step of every enrollment according to [policy], see [E2eiEnrollmentFlow::with_retry_policy]
+    pub fn with_retry_policy(mut self, policy: E2eiRetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// Notifies [observer] each time any enrollment progresses, see [E2eiObserver::on_progress]
+    /// for telling them apart
+    pub fn with_observer(mut self, observer: impl E2eiObserver + 'static) -> Self {
+        self.observer = Some(Arc::new(observer));
+        self
+    }
+
+    /// Saves every enrollment in [store] after each step, see [E2eiEnrollmentFlow::with_secret_store]
+    pub fn with_secret_store(mut self, store: impl SecretStore + 'static) -> Self {
+        self.saver = Some(saver(store));
+        self
+    }
+
+    /// Drives every enrollment up to [E2eiEnrollmentStep::Done], or resumes it from where it
+    /// stopped. A failing enrollment does not affect the others. Returns the result of each
+    /// enrollment, in the same order: the DER certificates, leaf first, or the error it stopped on.
+    pub async fn enroll<P: E2eiTokenProvider>(
+        &self,
+        enrollments: &mut [E2eiEnrollment],
+        tokens: &P,
+    ) -> Vec<E2eIdentityResult<Vec<Vec<u8>>>> {
+        futures_util::stream::iter(enrollments.iter_mut())
+            .map(|enrollment| self.enroll_one(enrollment, tokens))
+            .buffered(self.max_concurrency)
+            .collect()
+            .await
+    }
+
+    async fn enroll_one<P: E2eiTokenProvider>(
+        &self,
+        enrollment: &mut E2eiEnrollment,
+        tokens: &P,
+    ) -> E2eIdentityResult<Vec<Vec<u8>>> {
+        let mut flow = E2eiEnrollmentFlow::new(self.transport.clone())
+            .with_server_state(self.server.clone())
+            .with_retry_policy(self.retry_policy);
+        if let Some(observer) = &self.observer {
+            flow = flow.with_observer(observer.clone());
+        }
+        if let Some(saver) = &self.saver {
+            flow = flow.with_saver(saver.clone());
+        }
+        let token_error = |e: P::Error| E2eIdentityError::TokenProviderError(Box::new(e));
+        loop {
+            match enrollment.step() {
+                E2eiEnrollmentStep::Directory | E2eiEnrollmentStep::NewAccount => {
+                    flow.new_account(enrollment, &self.directory_url).await?;
+                }
+                E2eiEnrollmentStep::NewOrder | E2eiEnrollmentStep::Authorizations => flow.new_order(enrollment).await?,
+                E2eiEnrollmentStep::DpopChallenge => {
+                    let access_token = tokens.access_token(enrollment).await.map_err(token_error)?;
+                    flow.dpop_challenge(enrollment, access_token).await?;
+                }
+                E2eiEnrollmentStep::OidcChallenge => {
+                    let id_token = tokens.id_token(enrollment).await.map_err(token_error)?;
+                    flow.oidc_challenge(enrollment, id_token).await?;
+                }
+                E2eiEnrollmentStep::CheckOrder | E2eiEnrollmentStep::Finalize | E2eiEnrollmentStep::Certificate => {
+                    return flow.certificate(enrollment).await
+                }
+                step => return Err(E2eIdentityError::OutOfOrderEnrollment(step)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use wasm_bindgen_test::*;
+
+    use super::*;
+    use crate::{
+        enrollment::tests::{block_on, enrollment, server},
+        flow::tests::{access_token, enroll, enroll_until, id_token},
+    };
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    struct Tokens;
+
+    impl E2eiTokenProvider for Tokens {
+        type Error = std::io::Error;
+
+        async fn access_token(&self, enrollment: &E2eiEnrollment) -> Result<String, Self::Error> {
+            Ok(access_token(enrollment))
+        }
+
+        async fn id_token(&self, enrollment: &E2eiEnrollment) -> Result<String, Self::Error> {
+            Ok(id_token(enrollment))
+        }
+    }
+
+    /// wire-server is down
+    struct NoTokens;
+
+    impl E2eiTokenProvider for NoTokens {
+        type Error = std::io::Error;
+
+        async fn access_token(&self, _: &E2eiEnrollment) -> Result<String, Self::Error> {
+            Err(std::io::ErrorKind::ConnectionRefused.into())
+        }
+
+        async fn id_token(&self, _: &E2eiEnrollment) -> Result<String, Self::Error> {
+            Err(std::io::ErrorKind::ConnectionRefused.into())
+        }
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_enroll_many_clients() {
+        let server = server();
+        let bulk = E2eiBulkEnrollment::new(server.clone(), server.directory_url()).with_max_concurrency(2);
+        let mut enrollments = (0..5).map(|_| enrollment()).collect::<Vec<_>>();
+
+        let results = block_on(bulk.enroll(&mut enrollments, &Tokens));
+        assert_eq!(results.len(), 5);
+        assert!(results.iter().all(|r| r.as_ref().unwrap().len() == 2));
+        assert!(enrollments.iter().all(|e| e.step() == E2eiEnrollmentStep::Done));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_report_per_client_results() {
+        let server = server();
+        let mut done = enrollment();
+        enroll(&mut E2eiEnrollmentFlow::new(server.clone()), &mut done);
+        let bulk = E2eiBulkEnrollment::new(server.clone(), server.directory_url());
+        let mut enrollments = vec![enrollment(), done, enrollment()];
+
+        let results = block_on(bulk.enroll(&mut enrollments, &Tokens));
+        assert!(results[0].is_ok());
+        assert!(matches!(
+            results[1],
+            Err(E2eIdentityError::OutOfOrderEnrollment(E2eiEnrollmentStep::Done))
+        ));
+        assert!(results[2].is_ok());
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_resume_from_any_step() {
+        let server = server();
+        let mut flow = E2eiEnrollmentFlow::new(server.clone());
+        let steps = [
+            E2eiEnrollmentStep::NewAccount,
+            E2eiEnrollmentStep::Authorizations,
+            E2eiEnrollmentStep::Finalize,
+            E2eiEnrollmentStep::Certificate,
+        ];
+        let mut enrollments = steps
+            .iter()
+            .map(|step| {
+                let mut enrollment = enrollment();
+                enroll_until(&mut flow, &mut enrollment, *step);
+                enrollment
+            })
+            .collect::<Vec<_>>();
+
+        let bulk = E2eiBulkEnrollment::new(server.clone(), server.directory_url());
+        let results = block_on(bulk.enroll(&mut enrollments, &Tokens));
+        assert!(results.iter().all(|r| r.as_ref().unwrap().len() == 2));
+        assert!(enrollments.iter().all(|e| e.step() == E2eiEnrollmentStep::Done));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_stop_when_tokens_are_unavailable() {
+        let server = server();
+        let bulk = E2eiBulkEnrollment::new(server.clone(), server.directory_url());
+        let mut enrollments = vec![enrollment(), enrollment()];
+
+        let results = block_on(bulk.enroll(&mut enrollments, &NoTokens));
+        assert!(results
+            .iter()
+            .all(|r| matches!(r, Err(E2eIdentityError::TokenProviderError(_)))));
+        // resumes where it stopped
+        let results = block_on(bulk.enroll(&mut enrollments, &Tokens));
+        assert!(results.iter().all(Result::is_ok));
+    }
+}
@@ -0,0 +1,1172 @@
+use rusty_acme::prelude::AcmeOrderExtensions;
+use rusty_jwt_tools::prelude::ClientId;
+
+use crate::{oidc::OidcTokenResponse, prelude::*, Json};
+
+/// A whole enrollment, from the ACME directory to the x509 certificate, driven one step at a time.
+/// It does not perform any I/O: each `*_request` method returns an [E2eiAcmeRequest] to send and
+/// the matching `*_response` one parses the response body and moves to the next
+/// [E2eiEnrollmentStep]. Calling a method out of order fails with
+/// [E2eIdentityError::OutOfOrderEnrollment].
+///
+/// Nonces are up to the caller: each request takes the "replay-nonce" header of the previous
+/// response, or a fresh one from `HEAD {directory.new_nonce}`.
+///
+/// It can be serialized at any step e.g. before opening the system browser for the OIDC login and
+/// deserialized once the app has been restarted. Deserializing the state of an unknown version
+/// fails. It holds the signature and ACME private keys: store it encrypted.
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct E2eiEnrollment {
+    version: E2eiEnrollmentVersion,
+    delegate: RustyE2eIdentity,
+    client_id: String,
+    display_name: String,
+    handle: String,
+    team: Option<String>,
+    expiry: core::time::Duration,
+    step: E2eiEnrollmentStep,
+    directory: Option<AcmeDirectory>,
+    account: Option<E2eiAcmeAccount>,
+    order_url: Option<url::Url>,
+    pending_authorizations: Vec<url::Url>,
+    dpop_challenge: Option<E2eiAcmeChallenge>,
+    oidc_challenge: Option<E2eiAcmeChallenge>,
+    keyauth: Option<String>,
+    order: Option<E2eiAcmeOrder>,
+    finalize: Option<E2eiAcmeFinalize>,
+    /// DER leaf of the delivered certificate, to renew
+    #[serde(default)]
+    certificate: Option<Vec<u8>>,
+    /// Identifier of the certificate a renewal replaces
+    #[serde(default)]
+    replaces: Option<String>,
+    /// Binds the OIDC authorization request to the token exchange, which might happen after a restart
+    #[serde(default)]
+    pkce: Option<Pkce>,
+    /// Signed by the signature key a renewal replaces, see [Self::cross_signature]
+    #[serde(default)]
+    cross_signature: Option<String>,
+    /// Whether to embed [Self::cross_signature] in the DPoP token
+    #[serde(default)]
+    cross_signature_claim: bool,
+}
+
+impl std::fmt::Debug for E2eiEnrollment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // the delegate holds the private keys and the PKCE the code verifier, both left out
+        f.debug_struct("E2eiEnrollment")
+            .field("version", &self.version)
+            .field("client_id", &self.client_id)
+            .field("display_name", &self.display_name)
+            .field("handle", &self.handle)
+            .field("team", &self.team)
+            .field("expiry", &self.expiry)
+            .field("step", &self.step)
+            .field("directory", &self.directory)
+            .field("account", &self.account)
+            .field("order_url", &self.order_url)
+            .field("pending_authorizations", &self.pending_authorizations)
+            .field("dpop_challenge", &self.dpop_challenge)
+            .field("oidc_challenge", &self.oidc_challenge)
+            .field("keyauth", &self.keyauth)
+            .field("order", &self.order)
+            .field("finalize", &self.finalize)
+            .field("certificate", &self.certificate.as_ref().map(Vec::len))
+            .field("replaces", &self.replaces)
+            .field("cross_signature", &self.cross_signature)
+            .field("cross_signature_claim", &self.cross_signature_claim)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Bumped on every breaking change of the serialized [E2eiEnrollment]
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum E2eiEnrollmentVersion {
+    #[default]
+    V1,
+}
+
+/// Where an [E2eiEnrollment] stands i.e. what it expects next
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum E2eiEnrollmentStep {
+    /// Expects the body of `GET {directory_url}`, see [E2eiEnrollment::directory_response]
+    Directory,
+    /// see [E2eiEnrollment::new_account_request]
+    NewAccount,
+    /// see [E2eiEnrollment::new_order_request]
+    NewOrder,
+    /// Fetches the authorizations of the order one after the other, see [E2eiEnrollment::new_authz_request]
+    Authorizations,
+    /// see [E2eiEnrollment::new_dpop_token] and [E2eiEnrollment::new_dpop_challenge_request]
+    DpopChallenge,
+    /// see [E2eiEnrollment::new_oidc_authorization_url] and [E2eiEnrollment::new_oidc_challenge_request]
+    OidcChallenge,
+    /// see [E2eiEnrollment::check_order_request]
+    CheckOrder,
+    /// see [E2eiEnrollment::finalize_request]
+    Finalize,
+    /// see [E2eiEnrollment::certificate_request]
+    Certificate,
+    /// The certificate has been delivered
+    Done,
+}
+
+impl E2eiEnrollment {
+    /// Starts an enrollment. The [RustyE2eIdentity] holds the key material and is dropped along
+    /// with this enrollment.
+    ///
+    /// # Parameters
+    /// * `delegate` - holds the signature key to certify
+    /// * `client_id` - client identifier with user b64Url encoded & clientId hex encoded e.g. `NDUyMGUyMmY2YjA3NGU3NjkyZjE1NjJjZTAwMmQ2NTQ:6add501bacd1d90e@example.com`
+    /// * `display_name` - human readable name displayed in the application e.g. `Smith, Alice M (QA)`
+    /// * `handle` - user handle e.g. `alice.smith.qa`
+    /// * `team` - team the user belongs to, if any
+    /// * `expiry` - x509 generated certificate expiry
+    pub fn try_new(
+        delegate: RustyE2eIdentity,
+        client_id: &str,
+        display_name: &str,
+        handle: &str,
+        team: Option<String>,
+        expiry: core::time::Duration,
+    ) -> E2eIdentityResult<Self> {
+        ClientId::try_from_qualified(client_id)?;
+        Ok(Self {
+            version: E2eiEnrollmentVersion::default(),
+            delegate,
+            client_id: client_id.to_string(),
+            display_name: display_name.to_string(),
+            handle: handle.to_string(),
+            team,
+            expiry,
+            step: E2eiEnrollmentStep::Directory,
+            directory: None,
+            account: None,
+            order_url: None,
+            pending_authorizations: vec![],
+            dpop_challenge: None,
+            oidc_challenge: None,
+            keyauth: None,
+            order: None,
+            finalize: None,
+            certificate: None,
+            replaces: None,
+            pkce: None,
+            cross_signature: None,
+            cross_signature_claim: false,
+        })
+    }
+
+    /// Renews the certificate delivered by this enrollment, before it expires, with the same ACME
+    /// account and a new signature key. The new order replaces the current certificate so the CA
+    /// might reuse the user authorization, in which case the OIDC challenge is skipped: the
+    /// enrollment goes from [E2eiEnrollmentStep::DpopChallenge] straight to
+    /// [E2eiEnrollmentStep::CheckOrder]. The renewal starts at [E2eiEnrollmentStep::NewOrder].
+    ///
+    /// Before the current signature key is dropped, it signs the new one, see [Self::cross_signature].
+    ///
+    /// # Parameters
+    /// * `raw_sign_key` - Raw signature key as bytes, of the same algorithm as the current one
+    /// * `expiry` - x509 generated certificate expiry
+    pub fn try_renew(self, raw_sign_key: Vec<u8>, expiry: core::time::Duration) -> E2eIdentityResult<Self> {
+        self.expect(E2eiEnrollmentStep::Done)?;
+        let certificate = self.get(&self.certificate)?;
+        let replaces = RustyAcme::renewal_info_cert_id(certificate)?;
+        let delegate = self.delegate.try_new_with_same_acme_key(raw_sign_key)?;
+        let cross_signature = self.delegate.cross_sign(&delegate, &self.client_id, certificate)?;
+        Ok(Self {
+            version: E2eiEnrollmentVersion::default(),
+            delegate,
+            expiry,
+            step: E2eiEnrollmentStep::NewOrder,
+            order_url: None,
+            pending_authorizations: vec![],
+            dpop_challenge: None,
+            oidc_challenge: None,
+            keyauth: None,
+            order: None,
+            finalize: None,
+            certificate: None,
+            replaces: Some(replaces),
+            pkce: None,
+            cross_signature: Some(cross_signature),
+            cross_signature_claim: false,
+            ..self
+        })
+    }
+
+    /// When to renew the delivered certificate, see [E2eiRenewalSchedule::try_new]
+    ///
+    /// # Parameters
+    /// * `renewal_info` - suggested by the ACME server, if it supports renewal information
+    pub fn renewal_schedule(&self, renewal_info: Option<&AcmeRenewalInfo>) -> E2eIdentityResult<E2eiRenewalSchedule> {
+        self.expect(E2eiEnrollmentStep::Done)?;
+        E2eiRenewalSchedule::try_new(self.get(&self.certificate)?, renewal_info)
+    }
+
+    /// JWT signed by the signature key this renewal replaces, over the new signature public key,
+    /// so that wire-server can link the new credential to the expiring one. Its claims are an
+    /// [E2eiCrossSignature] and its 'x5c' header holds the expiring certificate. `None` when not
+    /// renewing.
+    pub fn cross_signature(&self) -> Option<&str> {
+        self.cross_signature.as_deref()
+    }
+
+    /// Embeds the [Self::cross_signature] in the DPoP token, as the [E2eiCrossSignature::DPOP_CLAIM]
+    /// claim, for wire-server to link the credentials when delivering the access token. Does
+    /// nothing when not renewing.
+    pub fn with_cross_signature_claim(mut self) -> Self {
+        self.cross_signature_claim = true;
+        self
+    }
+
+    /// What this enrollment expects next
+    pub fn step(&self) -> E2eiEnrollmentStep {
+        self.step
+    }
+
+    /// Client identifier this enrollment certifies
+    pub fn client_id(&self) -> &str {
+        &self.client_id
+    }
+
+    /// Stores this enrollment as the [SecretKind::PendingEnrollment] of its client, to resume it
+    /// with [Self::restore] e.g. once the app has been restarted, along with its ACME and
+    /// signature private keys as PEM, as its [SecretKind::AcmeKey] and [SecretKind::ClientKey].
+    pub fn save<S: SecretStore + ?Sized>(&self, store: &S) -> E2eIdentityResult<()> {
+        let enrollment = zeroize::Zeroizing::new(serde_json::to_vec(self)?);
+        let secrets = [
+            (SecretKind::PendingEnrollment, enrollment.as_slice()),
+            (SecretKind::AcmeKey, self.delegate.acme_kp.as_bytes()),
+            (SecretKind::ClientKey, self.delegate.sign_kp.as_bytes()),
+        ];
+        for (kind, secret) in secrets {
+            store.put(&self.client_id, kind, secret).map_err(Self::store_error)?;
+        }
+        Ok(())
+    }
+
+    /// The enrollment of [client_id] last saved with [Self::save], if any
+    pub fn restore<S: SecretStore + ?Sized>(store: &S, client_id: &str) -> E2eIdentityResult<Option<Self>> {
+        let enrollment = store
+            .get(client_id, SecretKind::PendingEnrollment)
+            .map_err(Self::store_error)?;
+        Ok(enrollment.map(|e| serde_json::from_slice(&e)).transpose()?)
+    }
+
+    /// ACME directory, once fetched
+    pub fn directory(&self) -> Option<&AcmeDirectory> {
+        self.directory.as_ref()
+    }
+
+    /// ACME order, once created
+    pub fn order_url(&self) -> Option<&url::Url> {
+        self.order_url.as_ref()
+    }
+
+    /// Parses the response from `GET /acme/{provisioner-name}/directory`. Then fetch a nonce with
+    /// `HEAD {directory.new_nonce}`.
+    ///
+    /// # Parameters
+    /// * `directory` - http response body
+    pub fn directory_response(&mut self, directory: Json) -> E2eIdentityResult<AcmeDirectory> {
+        self.expect(E2eiEnrollmentStep::Directory)?;
+        let directory = self.delegate.acme_directory_response(directory)?;
+        self.directory = Some(directory.clone());
+        self.step = E2eiEnrollmentStep::NewAccount;
+        Ok(directory)
+    }
+
+    /// Creates a new acme account.
+    ///
+    /// # Parameters
+    /// * `previous_nonce` - you got from calling `HEAD {directory.new_nonce}`
+    pub fn new_account_request(&self, previous_nonce: String) -> E2eIdentityResult<E2eiAcmeRequest> {
+        self.expect(E2eiEnrollmentStep::NewAccount)?;
+        let directory = self.get(&self.directory)?;
+        let body = self.delegate.acme_new_account_request(directory, previous_nonce)?;
+        Ok(E2eiAcmeRequest::new(directory.new_account.clone(), body))
+    }
+
+    /// Parses the response from `POST /acme/{provisioner-name}/new-account`.
+    ///
+    /// # Parameters
+    /// * `account` - http response body
+    pub fn new_account_response(&mut self, account: Json) -> E2eIdentityResult<()> {
+        self.expect(E2eiEnrollmentStep::NewAccount)?;
+        self.account = Some(self.delegate.acme_new_account_response(account)?);
+        self.step = E2eiEnrollmentStep::NewOrder;
+        Ok(())
+    }
+
+    /// Creates a new acme order for the handle and the clientId of this enrollment.
+    ///
+    /// # Parameters
+    /// * `previous_nonce` - "replay-nonce" response header from `POST /acme/{provisioner-name}/new-account`
+    pub fn new_order_request(&self, previous_nonce: String) -> E2eIdentityResult<E2eiAcmeRequest> {
+        self.expect(E2eiEnrollmentStep::NewOrder)?;
+        let directory = self.get(&self.directory)?;
+        let extensions = AcmeOrderExtensions {
+            replaces: self.replaces.clone(),
+            ..Default::default()
+        };
+        let order_req = RustyAcme::new_order_request(
+            &self.display_name,
+            ClientId::try_from_qualified(&self.client_id)?,
+            &Handle::from(self.handle.as_str()),
+            self.expiry,
+            extensions,
+            directory,
+            &self.get(&self.account)?.clone().try_into()?,
+            self.delegate.sign_alg,
+            &self.delegate.acme_kp,
+            previous_nonce,
+        )?;
+        let body = serde_json::to_value(order_req)?;
+        Ok(E2eiAcmeRequest::new(directory.new_order.clone(), body))
+    }
+
+    /// Parses the response from `POST /acme/{provisioner-name}/new-order` and returns the URLs of
+    /// the authorizations to fetch.
+    ///
+    /// # Parameters
+    /// * `new_order` - http response body
+    /// * `order_url` - "location" response header
+    pub fn new_order_response(&mut self, new_order: Json, order_url: url::Url) -> E2eIdentityResult<Vec<url::Url>> {
+        self.expect(E2eiEnrollmentStep::NewOrder)?;
+        let new_order = self.delegate.acme_new_order_response(new_order)?;
+        if new_order.authorizations.is_empty() {
+            return Err(E2eIdentityError::IncompleteAuthorizations);
+        }
+        self.order_url = Some(order_url);
+        self.pending_authorizations = new_order.authorizations.clone();
+        self.step = E2eiEnrollmentStep::Authorizations;
+        Ok(new_order.authorizations)
+    }
+
+    /// Fetches the next authorization of the order which has not been fetched yet.
+    ///
+    /// # Parameters
+    /// * `previous_nonce` - "replay-nonce" response header from `POST /acme/{provisioner-name}/new-order`
+    /// (or from the previous authorization)
+    pub fn new_authz_request(&self, previous_nonce: String) -> E2eIdentityResult<E2eiAcmeRequest> {
+        self.expect(E2eiEnrollmentStep::Authorizations)?;
+        let url = self
+            .pending_authorizations
+            .first()
+            .ok_or(E2eIdentityError::OutOfOrderEnrollment(self.step))?;
+        let body = self
+            .delegate
+            .acme_new_authz_request(url, self.get(&self.account)?, previous_nonce)?;
+        Ok(E2eiAcmeRequest::new(url.clone(), body))
+    }
+
+    /// Parses the response from `POST /acme/{provisioner-name}/authz/{authz-id}`. Once all the
+    /// authorizations have been fetched, the enrollment moves to the DPoP challenge.
+    ///
+    /// # Parameters
+    /// * `new_authz` - http response body
+    pub fn new_authz_response(&mut self, new_authz: Json) -> E2eIdentityResult<E2eiAcmeAuthorization> {
+        self.expect(E2eiEnrollmentStep::Authorizations)?;
+        let authz = if self.replaces.is_some() {
+            // when renewing, the CA might reuse a valid authorization
+            self.delegate
+                .authorization(RustyAcme::new_preauthz_response(new_authz)?)?
+        } else {
+            self.delegate.acme_new_authz_response(new_authz)?
+        };
+        match &authz {
+            E2eiAcmeAuthorization::User { keyauth, challenge, .. } => {
+                self.oidc_challenge = Some(challenge.clone());
+                self.keyauth = Some(keyauth.clone());
+            }
+            E2eiAcmeAuthorization::Device { challenge, .. } => self.dpop_challenge = Some(challenge.clone()),
+        }
+        self.pending_authorizations.remove(0);
+        if self.pending_authorizations.is_empty() {
+            if self.dpop_challenge.is_none() || self.oidc_challenge.is_none() {
+                return Err(E2eIdentityError::IncompleteAuthorizations);
+            }
+            self.step = self.pending_challenge(false);
+        }
+        Ok(authz)
+    }
+
+    /// 'wire-dpop-01' challenge, whose target is the wire-server endpoint delivering access tokens
+    pub fn dpop_challenge(&self) -> Option<&E2eiAcmeChallenge> {
+        self.dpop_challenge.as_ref()
+    }
+
+    /// 'wire-oidc-01' challenge, whose target is the identity provider delivering ID tokens
+    pub fn oidc_challenge(&self) -> Option<&E2eiAcmeChallenge> {
+        self.oidc_challenge.as_ref()
+    }
+
+    /// Key authorization of the OIDC challenge, to have in the 'keyauth' claim of the ID token
+    pub fn keyauth(&self) -> Option<&str> {
+        self.keyauth.as_deref()
+    }
+
+    /// Generates the client DPoP token to send to the target of the [Self::dpop_challenge] in
+    /// exchange for an access token.
+    ///
+    /// # Parameters
+    /// * `backend_nonce` - you get by calling `GET /clients/token/nonce` on wire-server
+    /// * `expiry` - token expiry
+    pub fn new_dpop_token(&self, backend_nonce: String, expiry: core::time::Duration) -> E2eIdentityResult<String> {
+        self.expect(E2eiEnrollmentStep::DpopChallenge)?;
+        let extra_claims = self
+            .cross_signature
+            .as_ref()
+            .filter(|_| self.cross_signature_claim)
+            .map(|cross_signature| serde_json::json!({ E2eiCrossSignature::DPOP_CLAIM: cross_signature }));
+        self.delegate.new_dpop_token_with_extra_claims(
+            &self.client_id,
+            self.get(&self.dpop_challenge)?,
+            backend_nonce,
+            &self.handle,
+            self.team.clone(),
+            expiry,
+            extra_claims,
+        )
+    }
+
+    /// Creates the request answering the DPoP challenge.
+    ///
+    /// # Parameters
+    /// * `access_token` - returned by wire-server in exchange for [Self::new_dpop_token]
+    /// * `previous_nonce` - "replay-nonce" response header from the last authorization
+    pub fn new_dpop_challenge_request(
+        &self,
+        access_token: String,
+        previous_nonce: String,
+    ) -> E2eIdentityResult<E2eiAcmeRequest> {
+        self.expect(E2eiEnrollmentStep::DpopChallenge)?;
+        let challenge = self.get(&self.dpop_challenge)?;
+        let body = self.delegate.acme_dpop_challenge_request(
+            access_token,
+            challenge,
+            self.get(&self.account)?,
+            previous_nonce,
+        )?;
+        Ok(E2eiAcmeRequest::new(challenge.url.clone(), body))
+    }
+
+    /// Parses the response from `POST /acme/{provisioner-name}/challenge/{challenge-id}` for the
+    /// DPoP challenge.
+    ///
+    /// # Parameters
+    /// * `challenge` - http response body
+    pub fn new_dpop_challenge_response(&mut self, challenge: Json) -> E2eIdentityResult<()> {
+        self.expect(E2eiEnrollmentStep::DpopChallenge)?;
+        self.delegate.acme_new_challenge_response(challenge)?;
+        self.step = self.pending_challenge(true);
+        Ok(())
+    }
+
+    /// Authorization code request with PKCE to open in the system browser, requesting an ID token
+    /// holding the [Self::keyauth] from the target of the [Self::oidc_challenge]. A new PKCE code
+    /// verifier is generated on every call.
+    ///
+    /// # Parameters
+    /// * `provider` - identity provider, target of the [Self::oidc_challenge]
+    /// * `client_id` - OAuth client registered at the identity provider
+    /// * `redirect_uri` - where the identity provider sends the authorization code
+    /// * `state` - opaque value returned along the authorization code, preventing CSRF
+    /// * `nonce` - value to find in the 'nonce' claim of the ID token, preventing replays
+    pub fn new_oidc_authorization_url(
+        &mut self,
+        provider: &OidcProvider,
+        client_id: &str,
+        redirect_uri: &url::Url,
+        state: &str,
+        nonce: &str,
+    ) -> E2eIdentityResult<url::Url> {
+        self.expect(E2eiEnrollmentStep::OidcChallenge)?;
+        let pkce = Pkce::generate();
+        let url = provider.authorization_url(client_id, redirect_uri, state, nonce, &pkce, &self.oidc_claims()?)?;
+        self.pkce = Some(pkce);
+        Ok(url)
+    }
+
+    /// PKCE of the last [Self::new_oidc_authorization_url]
+    pub fn pkce(&self) -> Option<&Pkce> {
+        self.pkce.as_ref()
+    }
+
+    /// Exchange of the authorization code for the ID token to answer the OIDC challenge with.
+    ///
+    /// # Parameters
+    /// * `provider` - the one of the [Self::new_oidc_authorization_url]
+    /// * `client_id` - the one of the [Self::new_oidc_authorization_url]
+    /// * `code` - authorization code the identity provider redirected to `redirect_uri` with
+    /// * `redirect_uri` - the one of the [Self::new_oidc_authorization_url]
+    pub fn new_oidc_token_request(
+        &self,
+        provider: &OidcProvider,
+        client_id: &str,
+        code: &str,
+        redirect_uri: &url::Url,
+    ) -> E2eIdentityResult<OidcTokenRequest> {
+        self.expect(E2eiEnrollmentStep::OidcChallenge)?;
+        provider.token_request(client_id, code, redirect_uri, self.get(&self.pkce)?)
+    }
+
+    /// Exchange of the stored refresh token for an ID token holding the [Self::keyauth], sparing
+    /// the end user an interactive login. Returns `None` when there is no refresh token, then go
+    /// through [Self::new_oidc_authorization_url].
+    ///
+    /// # Parameters
+    /// * `provider` - identity provider, target of the [Self::oidc_challenge]
+    /// * `client_id` - OAuth client registered at the identity provider
+    /// * `store` - holds the refresh token of the previous enrollment
+    pub fn new_oidc_refresh_request<S: RefreshTokenStore>(
+        &self,
+        provider: &OidcProvider,
+        client_id: &str,
+        store: &S,
+    ) -> E2eIdentityResult<Option<OidcTokenRequest>> {
+        self.expect(E2eiEnrollmentStep::OidcChallenge)?;
+        let refresh_token = store.refresh_token().map_err(Self::store_error)?;
+        refresh_token
+            .map(|refresh_token| provider.refresh_token_request(client_id, &refresh_token, &self.oidc_claims()?))
+            .transpose()
+    }
+
+    /// Parses the response of [Self::new_oidc_token_request] or [Self::new_oidc_refresh_request]
+    /// and returns the ID token to answer the OIDC challenge with. The refresh token delivered
+    /// along is stored for the next renewal, replacing the previous one. An 'invalid_grant' error
+    /// e.g. an expired refresh token forgets the stored one.
+    ///
+    /// # Parameters
+    /// * `response` - http response body
+    /// * `store` - where to keep the refresh token
+    pub fn new_oidc_token_response<S: RefreshTokenStore>(
+        &mut self,
+        response: Json,
+        store: &mut S,
+    ) -> E2eIdentityResult<String> {
+        self.expect(E2eiEnrollmentStep::OidcChallenge)?;
+        if let Some(error) = response["error"].as_str() {
+            if error == "invalid_grant" {
+                store.set_refresh_token(None).map_err(Self::store_error)?;
+            }
+            return Err(E2eIdentityError::OidcTokenError(error.to_string()));
+        }
+        let response = serde_json::from_value::<OidcTokenResponse>(response)?;
+        if let Some(refresh_token) = response.refresh_token {
+            store
+                .set_refresh_token(Some(refresh_token))
+                .map_err(Self::store_error)?;
+        }
+        Ok(response.id_token)
+    }
+
+    /// Creates the request answering the OIDC challenge.
+    ///
+    /// # Parameters
+    /// * `id_token` - returned by the target of the [Self::oidc_challenge], holding the [Self::keyauth]
+    /// * `previous_nonce` - "replay-nonce" response header from the DPoP challenge
+    pub fn new_oidc_challenge_request(
+        &self,
+        id_token: String,
+        previous_nonce: String,
+    ) -> E2eIdentityResult<E2eiAcmeRequest> {
+        self.expect(E2eiEnrollmentStep::OidcChallenge)?;
+        let challenge = self.get(&self.oidc_challenge)?;
+        let body =
+            self.delegate
+                .acme_oidc_challenge_request(id_token, challenge, self.get(&self.account)?, previous_nonce)?;
+        Ok(E2eiAcmeRequest::new(challenge.url.clone(), body))
+    }
+
+    /// Halts the enrollment when the ID token is obtained by another component e.g. the system
+    /// account manager, returning what the ID token has to hold. Persist the enrollment meanwhile
+    /// then resume it with [Self::new_external_oidc_challenge_request].
+    pub fn deferred_oidc_challenge(&self) -> E2eIdentityResult<E2eiDeferredOidcChallenge> {
+        self.expect(E2eiEnrollmentStep::OidcChallenge)?;
+        let challenge = self.get(&self.oidc_challenge)?;
+        Ok(E2eiDeferredOidcChallenge {
+            issuer: challenge.target.clone(),
+            audience: challenge.url.clone(),
+            keyauth: self.get(&self.keyauth)?.clone(),
+            claims: self.oidc_claims()?,
+        })
+    }
+
+    /// Same as [Self::new_oidc_challenge_request] for an ID token obtained out of band, see
+    /// [Self::deferred_oidc_challenge]. Since this enrollment did not request it, its claims are
+    /// first checked to answer the OIDC challenge. Its signature is left to the ACME server.
+    ///
+    /// # Parameters
+    /// * `id_token` - obtained out of band, holding the [E2eiDeferredOidcChallenge::keyauth] and [E2eiDeferredOidcChallenge::audience]
+    /// * `previous_nonce` - "replay-nonce" response header from the DPoP challenge
+    pub fn new_external_oidc_challenge_request(
+        &self,
+        id_token: String,
+        previous_nonce: String,
+    ) -> E2eIdentityResult<E2eiAcmeRequest> {
+        let challenge = self.deferred_oidc_challenge()?;
+        let claims = Self::unverified_claims(&id_token)?;
+        let claim = |name: &'static str| {
+            claims
+                .get(name)
+                .and_then(Json::as_str)
+                .ok_or(E2eIdentityError::MissingIdTokenClaim(name))
+        };
+        if claim("keyauth")? != challenge.keyauth {
+            return Err(E2eIdentityError::IdTokenChallengeMismatch("keyauth"));
+        }
+        if claim("acme_aud")? != challenge.audience.as_str() {
+            return Err(E2eIdentityError::IdTokenChallengeMismatch("acme_aud"));
+        }
+        self.new_oidc_challenge_request(id_token, previous_nonce)
+    }
+
+    /// Parses the response from `POST /acme/{provisioner-name}/challenge/{challenge-id}` for the
+    /// OIDC challenge.
+    ///
+    /// # Parameters
+    /// * `challenge` - http response body
+    pub fn new_oidc_challenge_response(&mut self, challenge: Json) -> E2eIdentityResult<()> {
+        self.expect(E2eiEnrollmentStep::OidcChallenge)?;
+        self.delegate.acme_new_challenge_response(challenge)?;
+        self.step = E2eiEnrollmentStep::CheckOrder;
+        Ok(())
+    }
+
+    /// Verifies that both challenges have been completed.
+    ///
+    /// # Parameters
+    /// * `previous_nonce` - "replay-nonce" response header from the OIDC challenge
+    pub fn check_order_request(&self, previous_nonce: String) -> E2eIdentityResult<E2eiAcmeRequest> {
+        self.expect(E2eiEnrollmentStep::CheckOrder)?;
+        let order_url = self.get(&self.order_url)?;
+        let body =
+            self.delegate
+                .acme_check_order_request(order_url.clone(), self.get(&self.account)?, previous_nonce)?;
+        Ok(E2eiAcmeRequest::new(order_url.clone(), body))
+    }
+
+    /// Parses the response from `POST /acme/{provisioner-name}/order/{order-id}`, which has to be
+    /// ready for finalization.
+    ///
+    /// # Parameters
+    /// * `order` - http response body
+    pub fn check_order_response(&mut self, order: Json) -> E2eIdentityResult<()> {
+        self.expect(E2eiEnrollmentStep::CheckOrder)?;
+        self.order = Some(self.delegate.acme_check_order_response(order)?);
+        self.step = E2eiEnrollmentStep::Finalize;
+        Ok(())
+    }
+
+    /// Sends the CSR of the signature key.
+    ///
+    /// # Parameters
+    /// * `previous_nonce` - "replay-nonce" response header from `POST /acme/{provisioner-name}/order/{order-id}`
+    pub fn finalize_request(&self, previous_nonce: String) -> E2eIdentityResult<E2eiAcmeRequest> {
+        self.expect(E2eiEnrollmentStep::Finalize)?;
+        let order = self.get(&self.order)?;
+        let body = self
+            .delegate
+            .acme_finalize_request(order, self.get(&self.account)?, previous_nonce)?;
+        Ok(E2eiAcmeRequest::new(order.finalize_url.clone(), body))
+    }
+
+    /// Fetches the order again while the ACME server is still issuing the certificate i.e. when
+    /// the finalize response is 'processing'. Its response is parsed by [Self::finalize_response].
+    ///
+    /// # Parameters
+    /// * `previous_nonce` - "replay-nonce" response header from `POST /acme/{provisioner-name}/order/{order-id}/finalize`
+    pub fn poll_finalize_request(&self, previous_nonce: String) -> E2eIdentityResult<E2eiAcmeRequest> {
+        self.expect(E2eiEnrollmentStep::Finalize)?;
+        let order_url = self.get(&self.order_url)?;
+        let body =
+            self.delegate
+                .acme_check_order_request(order_url.clone(), self.get(&self.account)?, previous_nonce)?;
+        Ok(E2eiAcmeRequest::new(order_url.clone(), body))
+    }
+
+    /// Parses the response from `POST /acme/{provisioner-name}/order/{order-id}/finalize`, or from
+    /// [Self::poll_finalize_request].
+    ///
+    /// # Parameters
+    /// * `finalize` - http response body
+    pub fn finalize_response(&mut self, finalize: Json) -> E2eIdentityResult<()> {
+        self.expect(E2eiEnrollmentStep::Finalize)?;
+        self.finalize = Some(self.delegate.acme_finalize_response(finalize)?);
+        self.step = E2eiEnrollmentStep::Certificate;
+        Ok(())
+    }
+
+    /// Fetches the x509 certificate.
+    ///
+    /// # Parameters
+    /// * `previous_nonce` - "replay-nonce" response header from `POST /acme/{provisioner-name}/order/{order-id}/finalize`
+    pub fn certificate_request(&self, previous_nonce: String) -> E2eIdentityResult<E2eiAcmeRequest> {
+        self.expect(E2eiEnrollmentStep::Certificate)?;
+        let finalize = self.get(&self.finalize)?.clone();
+        let url = finalize.certificate_url.clone();
+        let body =
+            self.delegate
+                .acme_x509_certificate_request(finalize, self.get(&self.account)?.clone(), previous_nonce)?;
+        Ok(E2eiAcmeRequest::new(url, body))
+    }
+
+    /// Parses the response from `POST /acme/{provisioner-name}/certificate/{certificate-id}`,
+    /// verifies the leaf certifies the identity and the signature key of this enrollment then
+    /// returns the DER certificate chain, leaf first.
+    ///
+    /// # Parameters
+    /// * `response` - http string response body
+    pub fn certificate_response(&mut self, response: String) -> E2eIdentityResult<Vec<Vec<u8>>> {
+        self.expect(E2eiEnrollmentStep::Certificate)?;
+        let order = self.get(&self.order)?.clone();
+        let certificates = self.delegate.acme_x509_certificate_response(response, order)?;
+        self.certificate = certificates.first().cloned();
+        self.step = E2eiEnrollmentStep::Done;
+        Ok(certificates)
+    }
+
+    /// Step of the first challenge left to complete, skipping the ones the CA has already validated
+    fn pending_challenge(&self, dpop_completed: bool) -> E2eiEnrollmentStep {
+        let is_valid = |c: &Option<E2eiAcmeChallenge>| c.as_ref().is_some_and(|c| c.delegate["status"] == "valid");
+        if !dpop_completed && !is_valid(&self.dpop_challenge) {
+            E2eiEnrollmentStep::DpopChallenge
+        } else if !is_valid(&self.oidc_challenge) {
+            E2eiEnrollmentStep::OidcChallenge
+        } else {
+            E2eiEnrollmentStep::CheckOrder
+        }
+    }
+
+    fn expect(&self, step: E2eiEnrollmentStep) -> E2eIdentityResult<()> {
+        if self.step != step {
+            return Err(E2eIdentityError::OutOfOrderEnrollment(self.step));
+        }
+        Ok(())
+    }
+
+    /// Field set by a previous step, which is missing only when steps are called out of order
+    /// Claims the ID token has to hold to answer the [Self::oidc_challenge]
+    /// A variant of https://openid.net/specs/openid-connect-core-1_0.html#ClaimsParameter
+    fn oidc_claims(&self) -> E2eIdentityResult<Json> {
+        Ok(serde_json::json!({
+            "id_token": {
+                "keyauth": { "essential": true, "value": self.get(&self.keyauth)? },
+                "acme_aud": { "essential": true, "value": self.get(&self.oidc_challenge)?.url },
+            }
+        }))
+    }
+
+    /// Payload of a JWT, without verifying its signature
+    fn unverified_claims(token: &str) -> E2eIdentityResult<Json> {
+        use base64::Engine as _;
+
+        let payload = token
+            .split('.')
+            .nth(1)
+            .and_then(|p| base64::prelude::BASE64_URL_SAFE_NO_PAD.decode(p).ok())
+            .ok_or(RustyJwtError::InvalidToken("the ID token is not a JWT".to_string()))?;
+        Ok(serde_json::from_slice(&payload)?)
+    }
+
+    fn store_error<E: std::error::Error + Send + Sync + 'static>(error: E) -> E2eIdentityError {
+        E2eIdentityError::SecretStoreError(Box::new(error))
+    }
+
+    fn get<'a, T>(&self, field: &'a Option<T>) -> E2eIdentityResult<&'a T> {
+        field.as_ref().ok_or(E2eIdentityError::OutOfOrderEnrollment(self.step))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use base64::Engine;
+    use jwt_simple::prelude::*;
+    use serde_json::json;
+    use wasm_bindgen_test::*;
+
+    use rusty_acme::prelude::{HttpResponse, HttpTransport, MockAcmeServer};
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    pub const CLIENT_ID: &str = "obakjPOHQ2CkNb0rOrNM3A:ba54e8ace8b4c90d@wire.com";
+
+    /// The mock server answers right away
+    pub fn block_on<F: core::future::Future>(future: F) -> F::Output {
+        use core::future::Future as _;
+
+        let mut cx = core::task::Context::from_waker(core::task::Waker::noop());
+        match core::pin::pin!(future).poll(&mut cx) {
+            core::task::Poll::Ready(output) => output,
+            core::task::Poll::Pending => panic!("expected the future to complete right away"),
+        }
+    }
+
+    /// The mock server does not verify the signature of the tokens
+    pub fn unsigned_token(claims: Json) -> String {
+        let encode = |part: Json| base64::prelude::BASE64_URL_SAFE_NO_PAD.encode(part.to_string());
+        format!("{}.{}.c2lnbmF0dXJl", encode(json!({"alg": "EdDSA"})), encode(claims))
+    }
+
+    pub fn enrollment() -> E2eiEnrollment {
+        let kp = Ed25519KeyPair::generate();
+        let identity = RustyE2eIdentity::try_new(JwsAlgorithm::Ed25519, kp.to_bytes()).unwrap();
+        let expiry = core::time::Duration::from_secs(3600);
+        E2eiEnrollment::try_new(identity, CLIENT_ID, "Alice Smith", "alice_wire", None, expiry).unwrap()
+    }
+
+    pub fn send(server: &MockAcmeServer, request: E2eiAcmeRequest) -> HttpResponse {
+        block_on(server.send(request.into())).unwrap()
+    }
+
+    pub fn nonce(response: &HttpResponse) -> String {
+        response.header("replay-nonce").unwrap().to_string()
+    }
+
+    pub fn body(response: &HttpResponse) -> Json {
+        serde_json::from_slice(&response.body).unwrap()
+    }
+
+    /// Runs the enrollment up to the OIDC challenge and returns the nonce to answer it with
+    pub fn until_oidc_challenge(server: &MockAcmeServer, enrollment: &mut E2eiEnrollment) -> String {
+        let get = rusty_acme::prelude::HttpRequest {
+            method: rusty_acme::prelude::HttpMethod::Get,
+            url: server.directory_url(),
+            headers: vec![],
+            body: vec![],
+        };
+        let response = block_on(server.send(get)).unwrap();
+        enrollment.directory_response(body(&response)).unwrap();
+        let previous_nonce = nonce(&response);
+
+        let response = send(server, enrollment.new_account_request(previous_nonce).unwrap());
+        enrollment.new_account_response(body(&response)).unwrap();
+        let previous_nonce = new_order(server, enrollment, nonce(&response));
+        assert_eq!(enrollment.step(), E2eiEnrollmentStep::DpopChallenge);
+        dpop_challenge(server, enrollment, previous_nonce)
+    }
+
+    /// Creates the order then fetches its authorizations
+    pub fn new_order(server: &MockAcmeServer, enrollment: &mut E2eiEnrollment, mut previous_nonce: String) -> String {
+        let response = send(server, enrollment.new_order_request(previous_nonce).unwrap());
+        let order_url = response.header("location").unwrap().parse().unwrap();
+        let authorizations = enrollment.new_order_response(body(&response), order_url).unwrap();
+        assert_eq!(authorizations.len(), 2);
+        previous_nonce = nonce(&response);
+
+        for _ in authorizations {
+            let response = send(server, enrollment.new_authz_request(previous_nonce).unwrap());
+            enrollment.new_authz_response(body(&response)).unwrap();
+            previous_nonce = nonce(&response);
+        }
+        previous_nonce
+    }
+
+    pub fn dpop_challenge(server: &MockAcmeServer, enrollment: &mut E2eiEnrollment, previous_nonce: String) -> String {
+        let dpop_chall = enrollment.dpop_challenge().unwrap();
+        let dpop_token = rusty_acme::prelude::AcmeChallenge::try_from(dpop_chall.clone())
+            .unwrap()
+            .token;
+        let client_id = ClientId::try_from_qualified(CLIENT_ID).unwrap();
+        let access_token = unsigned_token(json!({"chal": dpop_token, "client_id": client_id.to_uri()}));
+        let request = enrollment
+            .new_dpop_challenge_request(access_token, previous_nonce)
+            .unwrap();
+        let response = send(server, request);
+        enrollment.new_dpop_challenge_response(body(&response)).unwrap();
+        nonce(&response)
+    }
+
+    /// Completes the enrollment from the OIDC challenge and returns the certificate chain
+    pub fn from_oidc_challenge(
+        server: &MockAcmeServer,
+        enrollment: &mut E2eiEnrollment,
+        previous_nonce: String,
+    ) -> Vec<Vec<u8>> {
+        let oidc_chall = enrollment.oidc_challenge().unwrap();
+        let id_token = unsigned_token(json!({
+            "keyauth": enrollment.keyauth().unwrap(),
+            "acme_aud": oidc_chall.url,
+            "name": "Alice Smith",
+        }));
+        let request = enrollment.new_oidc_challenge_request(id_token, previous_nonce).unwrap();
+        let response = send(server, request);
+        enrollment.new_oidc_challenge_response(body(&response)).unwrap();
+        certificate(server, enrollment, nonce(&response))
+    }
+
+    /// Checks the order, finalizes it then fetches the certificate chain
+    pub fn certificate(
+        server: &MockAcmeServer,
+        enrollment: &mut E2eiEnrollment,
+        previous_nonce: String,
+    ) -> Vec<Vec<u8>> {
+        let response = send(server, enrollment.check_order_request(previous_nonce).unwrap());
+        enrollment.check_order_response(body(&response)).unwrap();
+        let previous_nonce = nonce(&response);
+
+        let response = send(server, enrollment.finalize_request(previous_nonce).unwrap());
+        enrollment.finalize_response(body(&response)).unwrap();
+        let previous_nonce = nonce(&response);
+
+        let response = send(server, enrollment.certificate_request(previous_nonce).unwrap());
+        enrollment
+            .certificate_response(String::from_utf8(response.body).unwrap())
+            .unwrap()
+    }
+
+    pub fn server() -> MockAcmeServer {
+        MockAcmeServer::try_new("https://acme.wire.com/acme/wire".parse().unwrap()).unwrap()
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_enroll() {
+        let server = server();
+        let mut enrollment = enrollment();
+        let previous_nonce = until_oidc_challenge(&server, &mut enrollment);
+        let chain = from_oidc_challenge(&server, &mut enrollment, previous_nonce);
+        assert_eq!(chain.len(), 2);
+        assert_eq!(enrollment.step(), E2eiEnrollmentStep::Done);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_not_leak_private_keys_in_debug() {
+        let enrollment = enrollment();
+        assert!(serde_json::to_string(&enrollment).unwrap().contains("PRIVATE KEY"));
+        let debug = format!("{enrollment:?}");
+        assert!(!debug.contains("PRIVATE KEY"));
+        assert!(debug.contains(CLIENT_ID));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_resume_after_restart() {
+        let server = server();
+        let mut enrollment = enrollment();
+        let previous_nonce = until_oidc_challenge(&server, &mut enrollment);
+        let persisted = serde_json::to_string(&enrollment).unwrap();
+        drop(enrollment);
+
+        let mut enrollment = serde_json::from_str::<E2eiEnrollment>(&persisted).unwrap();
+        assert_eq!(enrollment.step(), E2eiEnrollmentStep::OidcChallenge);
+        let chain = from_oidc_challenge(&server, &mut enrollment, previous_nonce);
+        assert_eq!(chain.len(), 2);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_keep_pkce_across_restart() {
+        let server = server();
+        let mut enrollment = enrollment();
+        until_oidc_challenge(&server, &mut enrollment);
+        let keycloak = OidcProvider::Keycloak {
+            base_url: "https://keycloak.wire.com".parse().unwrap(),
+            realm: "wire".to_string(),
+        };
+        let redirect_uri = "https://wire.com/callback".parse::<url::Url>().unwrap();
+        let url = enrollment
+            .new_oidc_authorization_url(&keycloak, "wireapp", &redirect_uri, "state", "nonce")
+            .unwrap();
+        let code_challenge = enrollment.pkce().unwrap().code_challenge();
+        assert!(url
+            .query_pairs()
+            .any(|(k, v)| k == "code_challenge" && v == code_challenge));
+        let claims = url.query_pairs().find(|(k, _)| k == "claims").unwrap().1;
+        let claims = serde_json::from_str::<Json>(&claims).unwrap();
+        assert_eq!(claims["id_token"]["keyauth"]["value"], enrollment.keyauth().unwrap());
+
+        let persisted = serde_json::to_string(&enrollment).unwrap();
+        let enrollment = serde_json::from_str::<E2eiEnrollment>(&persisted).unwrap();
+        let request = enrollment
+            .new_oidc_token_request(&keycloak, "wireapp", "code", &redirect_uri)
+            .unwrap();
+        let code_verifier = enrollment.pkce().unwrap().code_verifier().to_string();
+        assert!(url::form_urlencoded::parse(request.body.as_bytes())
+            .any(|(k, v)| k == "code_verifier" && v == code_verifier));
+    }
+
+    #[derive(Default)]
+    pub struct TestStore(Option<String>);
+
+    impl RefreshTokenStore for TestStore {
+        type Error = std::convert::Infallible;
+
+        fn refresh_token(&self) -> Result<Option<String>, Self::Error> {
+            Ok(self.0.clone())
+        }
+
+        fn set_refresh_token(&mut self, refresh_token: Option<String>) -> Result<(), Self::Error> {
+            self.0 = refresh_token;
+            Ok(())
+        }
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_reuse_refresh_token() {
+        let server = server();
+        let mut enrollment = enrollment();
+        until_oidc_challenge(&server, &mut enrollment);
+        let entra_id = OidcProvider::EntraId {
+            tenant: "organizations".to_string(),
+        };
+        let mut store = TestStore::default();
+        assert!(enrollment
+            .new_oidc_refresh_request(&entra_id, "wireapp", &store)
+            .unwrap()
+            .is_none());
+
+        let response = json!({"id_token": "a.b.c", "refresh_token": "refresh-1", "token_type": "Bearer"});
+        let id_token = enrollment.new_oidc_token_response(response, &mut store).unwrap();
+        assert_eq!(id_token, "a.b.c");
+        assert_eq!(store.0.as_deref(), Some("refresh-1"));
+
+        let request = enrollment
+            .new_oidc_refresh_request(&entra_id, "wireapp", &store)
+            .unwrap()
+            .unwrap();
+        assert_eq!(request.url, entra_id.token_endpoint().unwrap());
+        let body = url::form_urlencoded::parse(request.body.as_bytes())
+            .into_owned()
+            .collect::<std::collections::HashMap<_, _>>();
+        assert_eq!(body["grant_type"], "refresh_token");
+        assert_eq!(body["refresh_token"], "refresh-1");
+        let claims = serde_json::from_str::<Json>(&body["claims"]).unwrap();
+        assert_eq!(claims["id_token"]["keyauth"]["value"], enrollment.keyauth().unwrap());
+
+        // refresh tokens are rotated
+        let response = json!({"id_token": "d.e.f", "refresh_token": "refresh-2"});
+        enrollment.new_oidc_token_response(response, &mut store).unwrap();
+        assert_eq!(store.0.as_deref(), Some("refresh-2"));
+
+        let response = json!({"error": "invalid_grant"});
+        assert!(matches!(
+            enrollment.new_oidc_token_response(response, &mut store).unwrap_err(),
+            E2eIdentityError::OidcTokenError(e) if e == "invalid_grant"
+        ));
+        assert!(store.0.is_none());
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_not_resume_unknown_version() {
+        let mut persisted = serde_json::to_value(enrollment()).unwrap();
+        assert_eq!(persisted["version"], "v1");
+        persisted["version"] = json!("v2");
+        assert!(serde_json::from_value::<E2eiEnrollment>(persisted).is_err());
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_renew_without_oidc_challenge() {
+        let server = server();
+        let mut enrollment = enrollment();
+        let previous_nonce = until_oidc_challenge(&server, &mut enrollment);
+        let chain = from_oidc_challenge(&server, &mut enrollment, previous_nonce);
+
+        let raw_sign_key = Ed25519KeyPair::generate().to_bytes();
+        let expiry = core::time::Duration::from_secs(3600);
+        let mut renewal = enrollment.try_renew(raw_sign_key, expiry).unwrap();
+        assert_eq!(renewal.step(), E2eiEnrollmentStep::NewOrder);
+
+        let head = rusty_acme::prelude::HttpRequest {
+            method: rusty_acme::prelude::HttpMethod::Head,
+            url: server.directory_url().join("new-nonce").unwrap(),
+            headers: vec![],
+            body: vec![],
+        };
+        let previous_nonce = nonce(&block_on(server.send(head)).unwrap());
+        let previous_nonce = new_order(&server, &mut renewal, previous_nonce);
+        assert_eq!(renewal.step(), E2eiEnrollmentStep::DpopChallenge);
+        let previous_nonce = dpop_challenge(&server, &mut renewal, previous_nonce);
+        assert_eq!(renewal.step(), E2eiEnrollmentStep::CheckOrder);
+
+        let renewed = certificate(&server, &mut renewal, previous_nonce);
+        assert_ne!(renewed[0], chain[0]);
+        assert_eq!(renewal.step(), E2eiEnrollmentStep::Done);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_cross_sign_when_renewing() {
+        use rusty_jwt_tools::jwk::TryIntoJwk as _;
+
+        let server = server();
+        let kp = Ed25519KeyPair::generate();
+        let identity = RustyE2eIdentity::try_new(JwsAlgorithm::Ed25519, kp.to_bytes()).unwrap();
+        let expiry = core::time::Duration::from_secs(3600);
+        let mut enrollment =
+            E2eiEnrollment::try_new(identity, CLIENT_ID, "Alice Smith", "alice_wire", None, expiry).unwrap();
+        let previous_nonce = until_oidc_challenge(&server, &mut enrollment);
+        let chain = from_oidc_challenge(&server, &mut enrollment, previous_nonce);
+        assert_eq!(enrollment.cross_signature(), None);
+
+        let new_kp = Ed25519KeyPair::generate();
+        let mut renewal = enrollment
+            .try_renew(new_kp.to_bytes(), expiry)
+            .unwrap()
+            .with_cross_signature_claim();
+        let cross_signature = renewal.cross_signature().unwrap().to_string();
+
+        // signed by the expiring key, over the new one
+        let claims = kp
+            .public_key()
+            .verify_token::<E2eiCrossSignature>(&cross_signature, None)
+            .unwrap();
+        let new_key = new_kp.public_key().try_into_jwk().unwrap();
+        assert_eq!(
+            serde_json::to_value(claims.custom.new_key).unwrap(),
+            serde_json::to_value(new_key).unwrap()
+        );
+        let client_id = E2eiClientId::try_from_qualified(CLIENT_ID).unwrap();
+        assert_eq!(claims.subject, Some(client_id.to_uri()));
+        let header = Token::decode_metadata(&cross_signature).unwrap();
+        assert_eq!(header.signature_type(), Some(E2eiCrossSignature::TYP));
+        let x5c = header.certificate_chain().unwrap();
+        assert_eq!(base64::prelude::BASE64_STANDARD.decode(&x5c[0]).unwrap(), chain[0]);
+
+        // then embedded in the DPoP token
+        let head = rusty_acme::prelude::HttpRequest {
+            method: rusty_acme::prelude::HttpMethod::Head,
+            url: server.directory_url().join("new-nonce").unwrap(),
+            headers: vec![],
+            body: vec![],
+        };
+        let previous_nonce = nonce(&block_on(server.send(head)).unwrap());
+        new_order(&server, &mut renewal, previous_nonce);
+        let dpop = renewal.new_dpop_token("backend-nonce".to_string(), expiry).unwrap();
+        let payload = dpop.split('.').nth(1).unwrap();
+        let payload = base64::prelude::BASE64_URL_SAFE_NO_PAD.decode(payload).unwrap();
+        let claims = serde_json::from_slice::<Json>(&payload).unwrap();
+        assert_eq!(claims[E2eiCrossSignature::DPOP_CLAIM], cross_signature);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_fail_when_out_of_order() {
+        let mut enrollment = enrollment();
+        assert!(matches!(
+            enrollment.new_account_request("nonce".to_string()).unwrap_err(),
+            E2eIdentityError::OutOfOrderEnrollment(E2eiEnrollmentStep::Directory)
+        ));
+        assert!(matches!(
+            enrollment.finalize_response(json!({})).unwrap_err(),
+            E2eIdentityError::OutOfOrderEnrollment(E2eiEnrollmentStep::Directory)
+        ));
+        assert_eq!(enrollment.step(), E2eiEnrollmentStep::Directory);
+        let expiry = core::time::Duration::from_secs(3600);
+        assert!(matches!(
+            enrollment.try_renew(vec![], expiry).unwrap_err(),
+            E2eIdentityError::OutOfOrderEnrollment(E2eiEnrollmentStep::Directory)
+        ));
+    }
+}
@@ -1,3 +1,9 @@
+use rusty_acme::prelude::{
+    AcmeAccountError, AcmeAuthzError, AcmeChallError, AcmeOrderError, AcmeProblem, AcmeProblemType, CertificateError,
+    RustyAcmeError,
+};
+use rusty_jwt_tools::prelude::RustyJwtError;
+
 /// Wrapper over a [Result] with a [RustyAcmeError] error
 pub type E2eIdentityResult<T> = Result<T, E2eIdentityError>;
 
@@ -7,6 +13,36 @@ pub enum E2eIdentityError {
     /// Invalid/incomplete certificate
     #[error("Given x509 certificate is invalid and does not follow Wire's format")]
     InvalidCertificate,
+    /// An enrollment step was called before the previous ones completed
+    #[error("This step cannot be performed now, the enrollment is at step {0:?}")]
+    OutOfOrderEnrollment(crate::prelude::E2eiEnrollmentStep),
+    /// The order does not authorize both the user and the device
+    #[error("The order is missing the user or the device authorization")]
+    IncompleteAuthorizations,
+    /// The ID token lacks a claim holding the identity
+    #[error("The ID token is missing the '{0}' claim")]
+    MissingIdTokenClaim(&'static str),
+    /// The identity in the ID token is not the one being enrolled
+    #[error("The '{0}' claim of the ID token does not match the identity being enrolled")]
+    IdTokenIdentityMismatch(&'static str),
+    /// An ID token obtained out of band answers another OIDC challenge
+    #[error("The '{0}' claim of the ID token does not answer the OIDC challenge of this enrollment")]
+    IdTokenChallengeMismatch(&'static str),
+    /// The Identity Provider does not vouch for the claim holding the identity
+    #[error("The '{0}' claim of the ID token is not verified by the Identity Provider")]
+    UnverifiedIdTokenClaim(&'static str),
+    /// The token endpoint of the Identity Provider returned an error e.g. 'invalid_grant'
+    #[error("The Identity Provider token endpoint returned the '{0}' error")]
+    OidcTokenError(String),
+    /// Error of the caller-provided [crate::prelude::SecretStore] or [crate::prelude::RefreshTokenStore]
+    #[error(transparent)]
+    SecretStoreError(Box<dyn std::error::Error + Send + Sync>),
+    /// Error of the caller-provided [crate::prelude::E2eiTokenProvider]
+    #[error(transparent)]
+    TokenProviderError(Box<dyn std::error::Error + Send + Sync>),
+    /// Invalid Identity Provider url
+    #[error(transparent)]
+    InvalidUrl(#[from] url::ParseError),
     /// Json error
     #[error(transparent)]
     JsonError(#[from] serde_json::Error),
@@ -20,3 +56,211 @@ pub enum E2eIdentityError {
     #[error(transparent)]
     JwtSimpleError(#[from] jwt_simple::Error),
 }
+
+/// What went wrong from the user's point of view, to show the right guidance, see
+/// [E2eIdentityError::category]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum E2eiErrorCategory {
+    /// The ACME server, wire-server or the Identity Provider could not be reached: check the
+    /// connectivity then try again
+    Network,
+    /// The Identity Provider session is over or the token it issued got rejected: log in again
+    LoginRequired,
+    /// The device clock is off, the tokens or the order are not valid yet: fix the date and time
+    ClockSkew,
+    /// The ACME server, or the Identity Provider it trusts, is not set up the way Wire expects:
+    /// contact the administrator
+    CaMisconfigured,
+    /// The identity vouched for by the Identity Provider or in the certificate is not the one of
+    /// this client: log in with the right account
+    IdentityMismatch,
+    /// A transient failure e.g. rate limiting or an ACME server error: try again later
+    Retryable,
+    /// A misuse of this library or an unexpected failure, to report
+    Other,
+}
+
+impl E2eIdentityError {
+    /// Sorts this error into an [E2eiErrorCategory]
+    pub fn category(&self) -> E2eiErrorCategory {
+        match self {
+            Self::IdTokenIdentityMismatch(_) | Self::IdTokenChallengeMismatch(_) | Self::InvalidCertificate => {
+                E2eiErrorCategory::IdentityMismatch
+            }
+            Self::MissingIdTokenClaim(_) | Self::UnverifiedIdTokenClaim(_) | Self::IncompleteAuthorizations => {
+                E2eiErrorCategory::CaMisconfigured
+            }
+            Self::OidcTokenError(error) => match error.as_str() {
+                "invalid_grant" | "login_required" | "interaction_required" | "consent_required" => {
+                    E2eiErrorCategory::LoginRequired
+                }
+                "temporarily_unavailable" | "server_error" | "slow_down" => E2eiErrorCategory::Retryable,
+                _ => E2eiErrorCategory::CaMisconfigured,
+            },
+            Self::TokenProviderError(_) => E2eiErrorCategory::Network,
+            Self::AcmeError(e) => Self::acme_category(e),
+            Self::JwtError(e) => Self::jwt_category(e),
+            Self::OutOfOrderEnrollment(_)
+            | Self::SecretStoreError(_)
+            | Self::InvalidUrl(_)
+            | Self::JsonError(_)
+            | Self::JwtSimpleError(_) => E2eiErrorCategory::Other,
+        }
+    }
+
+    /// Whether trying the same operation again later might succeed
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self.category(),
+            E2eiErrorCategory::Network | E2eiErrorCategory::Retryable
+        )
+    }
+
+    fn acme_category(error: &RustyAcmeError) -> E2eiErrorCategory {
+        match error {
+            RustyAcmeError::TransportError(_) => E2eiErrorCategory::Network,
+            RustyAcmeError::RateLimited { .. } | RustyAcmeError::BadNonce | RustyAcmeError::PollError(_) => {
+                E2eiErrorCategory::Retryable
+            }
+            RustyAcmeError::Problem(problem) => Self::problem_category(problem),
+            RustyAcmeError::JwtError(e) => Self::jwt_category(e),
+            RustyAcmeError::InvalidCertificate(
+                CertificateError::ClientIdMismatch
+                | CertificateError::DisplayNameMismatch
+                | CertificateError::HandleMismatch
+                | CertificateError::DomainMismatch,
+            )
+            | RustyAcmeError::OrderError(AcmeOrderError::WrongIdentifiers)
+            | RustyAcmeError::AuthzError(AcmeAuthzError::IdentifierMismatch(_))
+            | RustyAcmeError::ChallengeError(AcmeChallError::Invalid) => E2eiErrorCategory::IdentityMismatch,
+            RustyAcmeError::OrderError(AcmeOrderError::NotYetValid | AcmeOrderError::InvalidValidity) => {
+                E2eiErrorCategory::ClockSkew
+            }
+            // the enrollment took too long, starting over succeeds
+            RustyAcmeError::OrderError(AcmeOrderError::Expired)
+            | RustyAcmeError::AuthzError(AcmeAuthzError::Expired)
+            | RustyAcmeError::ChallengeError(AcmeChallError::Processing) => E2eiErrorCategory::Retryable,
+            RustyAcmeError::InvalidCertificate(_)
+            | RustyAcmeError::SmallstepImplementationError(_)
+            | RustyAcmeError::X509CheckError(_)
+            | RustyAcmeError::FinalizeError(_)
+            | RustyAcmeError::OrderError(AcmeOrderError::Invalid)
+            | RustyAcmeError::AuthzError(AcmeAuthzError::PreAuthorizationNotSupported)
+            | RustyAcmeError::AccountError(AcmeAccountError::ExternalAccountRequired) => {
+                E2eiErrorCategory::CaMisconfigured
+            }
+            _ => E2eiErrorCategory::Other,
+        }
+    }
+
+    fn problem_category(problem: &AcmeProblem) -> E2eiErrorCategory {
+        match problem.typ {
+            AcmeProblemType::ServerInternal
+            | AcmeProblemType::RateLimited
+            | AcmeProblemType::BadNonce
+            | AcmeProblemType::OrderNotReady => E2eiErrorCategory::Retryable,
+            AcmeProblemType::Unauthorized | AcmeProblemType::UserActionRequired => E2eiErrorCategory::LoginRequired,
+            AcmeProblemType::RejectedIdentifier | AcmeProblemType::UnsupportedIdentifier => {
+                E2eiErrorCategory::IdentityMismatch
+            }
+            _ if problem.status.is_some_and(|status| status >= 500) => E2eiErrorCategory::Retryable,
+            AcmeProblemType::BadCsr
+            | AcmeProblemType::BadPublicKey
+            | AcmeProblemType::BadSignatureAlgorithm
+            | AcmeProblemType::ExternalAccountRequired
+            | AcmeProblemType::Caa
+            | AcmeProblemType::Connection
+            | AcmeProblemType::Dns
+            | AcmeProblemType::Tls
+            | AcmeProblemType::IncorrectResponse => E2eiErrorCategory::CaMisconfigured,
+            _ => E2eiErrorCategory::Other,
+        }
+    }
+
+    fn jwt_category(error: &RustyJwtError) -> E2eiErrorCategory {
+        match error {
+            RustyJwtError::DpopNotYetValid | RustyJwtError::InvalidDpopIat => E2eiErrorCategory::ClockSkew,
+            RustyJwtError::TokenExpired | RustyJwtError::RevokedAccessToken => E2eiErrorCategory::LoginRequired,
+            RustyJwtError::TokenSubMismatch
+            | RustyJwtError::DpopHandleMismatch
+            | RustyJwtError::DpopTeamMismatch
+            | RustyJwtError::DomainNotAllowed(_) => E2eiErrorCategory::IdentityMismatch,
+            _ => E2eiErrorCategory::Other,
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use wasm_bindgen_test::*;
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    fn problem(typ: AcmeProblemType, status: u16) -> E2eIdentityError {
+        RustyAcmeError::Problem(AcmeProblem {
+            typ,
+            detail: None,
+            status: Some(status),
+            identifier: None,
+            subproblems: vec![],
+        })
+        .into()
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_categorize_acme_errors() {
+        let transport = RustyAcmeError::TransportError(Box::new(std::io::Error::from(std::io::ErrorKind::TimedOut)));
+        assert_eq!(E2eIdentityError::from(transport).category(), E2eiErrorCategory::Network);
+        let rate_limited = RustyAcmeError::RateLimited { retry_after: None };
+        assert_eq!(
+            E2eIdentityError::from(rate_limited).category(),
+            E2eiErrorCategory::Retryable
+        );
+        assert_eq!(
+            problem(AcmeProblemType::Unauthorized, 401).category(),
+            E2eiErrorCategory::LoginRequired
+        );
+        assert_eq!(
+            problem(AcmeProblemType::Malformed, 503).category(),
+            E2eiErrorCategory::Retryable
+        );
+        assert_eq!(
+            problem(AcmeProblemType::BadSignatureAlgorithm, 400).category(),
+            E2eiErrorCategory::CaMisconfigured
+        );
+        assert_eq!(
+            problem(AcmeProblemType::Malformed, 400).category(),
+            E2eiErrorCategory::Other
+        );
+        let not_yet_valid = RustyAcmeError::OrderError(AcmeOrderError::NotYetValid);
+        assert_eq!(
+            E2eIdentityError::from(not_yet_valid).category(),
+            E2eiErrorCategory::ClockSkew
+        );
+        let mismatch = RustyAcmeError::InvalidCertificate(CertificateError::HandleMismatch);
+        assert_eq!(
+            E2eIdentityError::from(mismatch).category(),
+            E2eiErrorCategory::IdentityMismatch
+        );
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_categorize_identity_errors() {
+        let login = E2eIdentityError::OidcTokenError("invalid_grant".to_string());
+        assert_eq!(login.category(), E2eiErrorCategory::LoginRequired);
+        assert!(!login.is_retryable());
+        let client = E2eIdentityError::OidcTokenError("invalid_client".to_string());
+        assert_eq!(client.category(), E2eiErrorCategory::CaMisconfigured);
+        let mismatch = E2eIdentityError::IdTokenIdentityMismatch("preferred_username");
+        assert_eq!(mismatch.category(), E2eiErrorCategory::IdentityMismatch);
+        let skew = E2eIdentityError::from(RustyJwtError::DpopNotYetValid);
+        assert_eq!(skew.category(), E2eiErrorCategory::ClockSkew);
+        let token_provider =
+            E2eIdentityError::TokenProviderError(Box::new(std::io::Error::from(std::io::ErrorKind::ConnectionRefused)));
+        assert!(token_provider.is_retryable());
+    }
+}
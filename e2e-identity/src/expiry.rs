@@ -0,0 +1,219 @@
+use rusty_acme::prelude::AcmeCertificateInfo;
+use time::{Duration, OffsetDateTime};
+
+use crate::prelude::*;
+
+/// When a client renews its certificate, so that every client follows the same policy while
+/// they do not all renew at once. Compute it once the certificate has been issued, and again
+/// whenever the ACME server provides new renewal information (ARI).
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct E2eiRenewalSchedule {
+    /// Start of the validity period of the certificate
+    pub not_before: OffsetDateTime,
+    /// End of the validity period of the certificate, see [E2eiRenewalStatus::Expired]
+    pub not_after: OffsetDateTime,
+    /// Window within which to renew: the one suggested by the ACME server, otherwise from 2/3 to
+    /// 5/6 of the validity period
+    pub window: AcmeRenewalWindow,
+    /// Recommended instant to renew at, within [Self::window]. Derived from the certificate so
+    /// that a client finds the same one after a restart while clients spread over the window.
+    pub renewal_time: OffsetDateTime,
+    /// End of [Self::window]: past it, renew before using the credential
+    pub deadline: OffsetDateTime,
+}
+
+/// Where a certificate stands in its [E2eiRenewalSchedule]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum E2eiRenewalStatus {
+    /// Nothing to do before [E2eiRenewalSchedule::renewal_time]
+    Valid,
+    /// Renew in the background
+    RenewalDue,
+    /// Renew before using the credential, it expires soon
+    PastDeadline,
+    /// The certificate is no longer valid
+    Expired,
+}
+
+impl E2eiRenewalSchedule {
+    /// Delay before retrying a failed renewal, doubling on every failure
+    pub const RETRY_BACKOFF: Duration = Duration::minutes(5);
+    /// Longest delay between two renewal attempts
+    pub const MAX_RETRY_BACKOFF: Duration = Duration::hours(6);
+
+    /// # Parameters
+    /// * `certificate` - DER leaf certificate, see [E2eiEnrollment::certificate_response]
+    /// * `renewal_info` - suggested by the ACME server, see [RustyAcme::renewal_info_response]
+    pub fn try_new(certificate: &[u8], renewal_info: Option<&AcmeRenewalInfo>) -> E2eIdentityResult<Self> {
+        let info = AcmeCertificateInfo::try_from_der(certificate)?;
+        Ok(Self::from_validity(
+            info.not_before,
+            info.not_after,
+            AcmeRenewalInfo::jitter(certificate),
+            renewal_info,
+        ))
+    }
+
+    /// # Parameters
+    /// * `jitter` - within `[0, 1]`, where [Self::renewal_time] falls within [Self::window], see
+    ///   [AcmeRenewalInfo::jitter]
+    fn from_validity(
+        not_before: OffsetDateTime,
+        not_after: OffsetDateTime,
+        jitter: f64,
+        renewal_info: Option<&AcmeRenewalInfo>,
+    ) -> Self {
+        let window = match renewal_info {
+            // an already elapsed window e.g. after a mass revocation asks to renew right away
+            Some(info) => AcmeRenewalWindow {
+                start: info.suggested_window.start.min(not_after),
+                end: info.suggested_window.end.min(not_after),
+            },
+            None => {
+                let validity = not_after - not_before;
+                AcmeRenewalWindow {
+                    start: not_before + validity * 2 / 3,
+                    end: not_before + validity * 5 / 6,
+                }
+            }
+        };
+        let renewal_time = window.at(jitter);
+        Self {
+            not_before,
+            not_after,
+            window,
+            renewal_time,
+            deadline: window.end,
+        }
+    }
+
+    /// Where the certificate stands at [now]
+    pub fn status(&self, now: OffsetDateTime) -> E2eiRenewalStatus {
+        if now >= self.not_after {
+            E2eiRenewalStatus::Expired
+        } else if now >= self.deadline {
+            E2eiRenewalStatus::PastDeadline
+        } else if now >= self.renewal_time {
+            E2eiRenewalStatus::RenewalDue
+        } else {
+            E2eiRenewalStatus::Valid
+        }
+    }
+
+    /// When to try renewing next: at [Self::renewal_time], then after each failure with a
+    /// randomized exponential backoff from [Self::RETRY_BACKOFF] up to
+    /// [Self::MAX_RETRY_BACKOFF], not later than [Self::deadline] while it has not passed.
+    ///
+    /// # Parameters
+    /// * `failures` - renewal attempts which failed so far
+    pub fn next_attempt(&self, now: OffsetDateTime, failures: u32) -> OffsetDateTime {
+        if failures == 0 {
+            return self.renewal_time.max(now);
+        }
+        let backoff = 2i32
+            .checked_pow(failures - 1)
+            .and_then(|factor| Self::RETRY_BACKOFF.checked_mul(factor))
+            .map_or(Self::MAX_RETRY_BACKOFF, |backoff| backoff.min(Self::MAX_RETRY_BACKOFF));
+        // between half and all of the backoff so that failing clients do not retry in lockstep
+        let next = now + backoff * (0.5 + rand::random::<f64>() / 2.0);
+        if now < self.deadline {
+            next.min(self.deadline)
+        } else {
+            next
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use wasm_bindgen_test::*;
+
+    use super::*;
+    use crate::{
+        enrollment::tests::{enrollment, server},
+        flow::tests::enroll,
+    };
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    fn schedule(jitter: f64, renewal_info: Option<&AcmeRenewalInfo>) -> E2eiRenewalSchedule {
+        let not_before = OffsetDateTime::UNIX_EPOCH;
+        E2eiRenewalSchedule::from_validity(not_before, not_before + Duration::days(90), jitter, renewal_info)
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_renew_in_last_third_by_default() {
+        let earliest = schedule(0.0, None);
+        let latest = schedule(1.0, None);
+        assert_eq!(earliest.renewal_time, OffsetDateTime::UNIX_EPOCH + Duration::days(60));
+        assert_eq!(latest.renewal_time, OffsetDateTime::UNIX_EPOCH + Duration::days(75));
+        assert_eq!(latest.deadline, latest.renewal_time);
+
+        let s = schedule(0.5, None);
+        let day = |d| OffsetDateTime::UNIX_EPOCH + Duration::days(d);
+        assert_eq!(s.status(day(10)), E2eiRenewalStatus::Valid);
+        assert_eq!(s.status(day(70)), E2eiRenewalStatus::RenewalDue);
+        assert_eq!(s.status(day(80)), E2eiRenewalStatus::PastDeadline);
+        assert_eq!(s.status(day(90)), E2eiRenewalStatus::Expired);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_follow_renewal_info() {
+        let day = |d| OffsetDateTime::UNIX_EPOCH + Duration::days(d);
+        let info = AcmeRenewalInfo {
+            suggested_window: AcmeRenewalWindow {
+                start: day(20),
+                end: day(22),
+            },
+            explanation_url: None,
+        };
+        let s = schedule(0.5, Some(&info));
+        assert_eq!(s.renewal_time, day(21));
+        assert_eq!(s.deadline, day(22));
+
+        // never past expiry
+        let info = AcmeRenewalInfo {
+            suggested_window: AcmeRenewalWindow {
+                start: day(100),
+                end: day(120),
+            },
+            explanation_url: None,
+        };
+        assert_eq!(schedule(0.5, Some(&info)).deadline, day(90));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_back_off_until_deadline() {
+        let s = schedule(0.0, None);
+        let now = OffsetDateTime::UNIX_EPOCH;
+        assert_eq!(s.next_attempt(now, 0), s.renewal_time);
+        assert_eq!(s.next_attempt(s.deadline, 0), s.deadline);
+
+        let now = s.renewal_time;
+        let first = s.next_attempt(now, 1) - now;
+        assert!(first >= Duration::seconds(150) && first <= Duration::minutes(5));
+        let capped = s.next_attempt(now, 30) - now;
+        assert!(capped >= Duration::hours(3) && capped <= Duration::hours(6));
+
+        let close = s.deadline - Duration::minutes(1);
+        assert_eq!(s.next_attempt(close, 10), s.deadline);
+        let late = s.deadline + Duration::days(1);
+        assert!(s.next_attempt(late, 10) > late);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_schedule_issued_certificate() {
+        let mut enrollment = enrollment();
+        let chain = enroll(&mut E2eiEnrollmentFlow::new(server()), &mut enrollment);
+        let s = E2eiRenewalSchedule::try_new(&chain[0], None).unwrap();
+        assert!(s.window.start <= s.renewal_time && s.renewal_time <= s.deadline);
+        assert!(s.deadline < s.not_after);
+        // the same after a restart
+        assert_eq!(E2eiRenewalSchedule::try_new(&chain[0], None).unwrap(), s);
+        assert_eq!(enrollment.renewal_schedule(None).unwrap(), s);
+    }
+}
@@ -0,0 +1,956 @@
+use core::time::Duration;
+use std::collections::HashMap;
+
+use rusty_acme::prelude::{
+    AcmeCertificateInfo, AcmePoll, AcmeProblem, AcmeProblemType, HttpMethod, HttpRequest, HttpResponse, HttpTransport,
+    RetryPolicy,
+};
+
+use crate::{prelude::*, Json};
+
+/// Drives an [E2eiEnrollment] over any [HttpTransport] e.g. the `FetchTransport` in the browser
+/// or the mock ACME server in tests. It sends the ACME requests of each step, keeps track of the
+/// nonces and signs a request again when the ACME server rejects its nonce. Calls to wire-server
+/// for the access token and to the identity provider for the ID token are left to the caller.
+///
+/// Each step fails on the first error unless given an [E2eiRetryPolicy] with [Self::with_retry_policy]
+/// or [Self::with_step_retry_policy].
+///
+/// It does not hold any state worth persisting: after a restart, resume the deserialized
+/// [E2eiEnrollment] with a new flow. Given a [SecretStore] with [Self::with_secret_store], it
+/// saves the enrollment after each step, to resume it with [E2eiEnrollment::restore].
+pub struct E2eiEnrollmentFlow<T: HttpTransport> {
+    transport: T,
+    server: std::sync::Arc<AcmeServerState>,
+    observer: Option<std::sync::Arc<dyn E2eiObserver>>,
+    retry_policy: E2eiRetryPolicy,
+    step_retry_policies: HashMap<E2eiEnrollmentStep, E2eiRetryPolicy>,
+    saver: Option<Saver>,
+}
+
+/// Saves an enrollment in a [SecretStore] whatever its error type
+pub(crate) type Saver = std::sync::Arc<dyn Fn(&E2eiEnrollment) -> E2eIdentityResult<()> + Send + Sync>;
+
+pub(crate) fn saver(store: impl SecretStore + 'static) -> Saver {
+    std::sync::Arc::new(move |enrollment: &E2eiEnrollment| enrollment.save(&store))
+}
+
+impl<T: HttpTransport> E2eiEnrollmentFlow<T> {
+    pub fn new(transport: T) -> Self {
+        Self {
+            transport,
+            server: Default::default(),
+            observer: None,
+            retry_policy: E2eiRetryPolicy::NONE,
+            step_retry_policies: HashMap::new(),
+            saver: None,
+        }
+    }
+
+    /// Retries every step according to [policy], unless overridden with [Self::with_step_retry_policy]
+    pub fn with_retry_policy(mut self, policy: E2eiRetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// Retries the requests sent while the enrollment is at [step] according to [policy] e.g.
+    /// [E2eiEnrollmentStep::CheckOrder] to wait for the authorizations to turn valid
+    pub fn with_step_retry_policy(mut self, step: E2eiEnrollmentStep, policy: E2eiRetryPolicy) -> Self {
+        self.step_retry_policies.insert(step, policy);
+        self
+    }
+
+    /// Shares the nonces and the rate limits with the other flows enrolling against the same ACME server
+    pub(crate) fn with_server_state(mut self, server: std::sync::Arc<AcmeServerState>) -> Self {
+        self.server = server;
+        self
+    }
+
+    /// Notifies [observer] each time the enrollment progresses
+    pub fn with_observer(mut self, observer: impl E2eiObserver + 'static) -> Self {
+        self.observer = Some(std::sync::Arc::new(observer));
+        self
+    }
+
+    /// Saves the enrollment in [store] after each step, see [E2eiEnrollment::save]. A step
+    /// fails when its enrollment cannot be saved.
+    pub fn with_secret_store(self, store: impl SecretStore + 'static) -> Self {
+        self.with_saver(saver(store))
+    }
+
+    pub(crate) fn with_saver(mut self, saver: Saver) -> Self {
+        self.saver = Some(saver);
+        self
+    }
+
+    /// Fetches the ACME directory then creates the ACME account, up to [E2eiEnrollmentStep::NewOrder].
+    /// Resumes an enrollment whose directory is already fetched.
+    ///
+    /// # Parameters
+    /// * `directory_url` - e.g. `https://acme.wire.com/acme/wire/directory`
+    pub async fn new_account(
+        &mut self,
+        enrollment: &mut E2eiEnrollment,
+        directory_url: &url::Url,
+    ) -> E2eIdentityResult<AcmeDirectory> {
+        let directory = match enrollment.directory() {
+            Some(directory) if enrollment.step() == E2eiEnrollmentStep::NewAccount => directory.clone(),
+            _ => self.directory(enrollment, directory_url).await?,
+        };
+
+        let response = self
+            .post(enrollment, |e, nonce| e.new_account_request(nonce), Pending::Never)
+            .await?;
+        enrollment.new_account_response(Self::body(&response)?)?;
+        let account_url = response.header("location").and_then(|l| l.parse().ok());
+        self.notify(enrollment, E2eiProgress::AccountCreated { account_url });
+        self.save(enrollment)?;
+        Ok(directory)
+    }
+
+    async fn directory(
+        &mut self,
+        enrollment: &mut E2eiEnrollment,
+        directory_url: &url::Url,
+    ) -> E2eIdentityResult<AcmeDirectory> {
+        let mut retry = Retry::new(self.retry_policy(enrollment.step()));
+        let response = loop {
+            let response = self.send(Self::request(HttpMethod::Get, directory_url.clone())).await;
+            match retry.next(&response, Pending::Never) {
+                Some(delay) => Self::sleep(delay).await,
+                None => break response?,
+            }
+        };
+        self.keep_nonce(&response);
+        let directory = enrollment.directory_response(Self::body(&response)?)?;
+        let directory_url = directory_url.clone();
+        self.notify(enrollment, E2eiProgress::DirectoryFetched { directory_url });
+        self.save(enrollment)?;
+        Ok(directory)
+    }
+
+    /// Creates the order then fetches its authorizations, up to [E2eiEnrollmentStep::DpopChallenge].
+    /// Resumes an enrollment whose order is already created.
+    pub async fn new_order(&mut self, enrollment: &mut E2eiEnrollment) -> E2eIdentityResult<()> {
+        if enrollment.step() != E2eiEnrollmentStep::Authorizations {
+            let response = self
+                .post_non_idempotent(enrollment, |e, nonce| e.new_order_request(nonce))
+                .await?;
+            let order_url = response
+                .header("location")
+                .ok_or(RustyAcmeError::SmallstepImplementationError(
+                    "'newOrder' responded without a 'Location' header",
+                ))?
+                .parse()?;
+            enrollment.new_order_response(Self::body(&response)?, order_url)?;
+            self.save(enrollment)?;
+        }
+        while enrollment.step() == E2eiEnrollmentStep::Authorizations {
+            let response = self
+                .post(enrollment, |e, nonce| e.new_authz_request(nonce), Pending::Never)
+                .await?;
+            enrollment.new_authz_response(Self::body(&response)?)?;
+        }
+        if let Some(order_url) = enrollment.order_url().cloned() {
+            self.notify(enrollment, E2eiProgress::OrderCreated { order_url });
+        }
+        self.save(enrollment)
+    }
+
+    /// Answers the DPoP challenge. When renewing, the CA might have reused the user authorization
+    /// in which case the enrollment skips [E2eiEnrollmentStep::OidcChallenge].
+    ///
+    /// # Parameters
+    /// * `access_token` - returned by wire-server in exchange for [E2eiEnrollment::new_dpop_token]
+    pub async fn dpop_challenge(
+        &mut self,
+        enrollment: &mut E2eiEnrollment,
+        access_token: String,
+    ) -> E2eIdentityResult<()> {
+        let response = self
+            .post(
+                enrollment,
+                |e, nonce| e.new_dpop_challenge_request(access_token.clone(), nonce),
+                Pending::Challenge,
+            )
+            .await?;
+        enrollment.new_dpop_challenge_response(Self::body(&response)?)?;
+        if let Some(challenge) = enrollment.dpop_challenge() {
+            let challenge_url = challenge.url.clone();
+            self.notify(enrollment, E2eiProgress::DpopChallengeValid { challenge_url });
+        }
+        self.save(enrollment)
+    }
+
+    /// Answers the OIDC challenge
+    ///
+    /// # Parameters
+    /// * `id_token` - returned by the identity provider, holding the [E2eiEnrollment::keyauth]
+    pub async fn oidc_challenge(&mut self, enrollment: &mut E2eiEnrollment, id_token: String) -> E2eIdentityResult<()> {
+        self.answer_oidc_challenge(enrollment, |e, nonce| {
+            e.new_oidc_challenge_request(id_token.clone(), nonce)
+        })
+        .await
+    }
+
+    /// Resumes an enrollment halted with [E2eiEnrollment::deferred_oidc_challenge] once another
+    /// component obtained the ID token, see [E2eiEnrollment::new_external_oidc_challenge_request]
+    ///
+    /// # Parameters
+    /// * `id_token` - obtained out of band
+    pub async fn external_oidc_challenge(
+        &mut self,
+        enrollment: &mut E2eiEnrollment,
+        id_token: String,
+    ) -> E2eIdentityResult<()> {
+        self.answer_oidc_challenge(enrollment, |e, nonce| {
+            e.new_external_oidc_challenge_request(id_token.clone(), nonce)
+        })
+        .await
+    }
+
+    async fn answer_oidc_challenge(
+        &mut self,
+        enrollment: &mut E2eiEnrollment,
+        sign: impl Fn(&E2eiEnrollment, String) -> E2eIdentityResult<E2eiAcmeRequest>,
+    ) -> E2eIdentityResult<()> {
+        let response = self.post(enrollment, sign, Pending::Challenge).await?;
+        enrollment.new_oidc_challenge_response(Self::body(&response)?)?;
+        if let Some(challenge) = enrollment.oidc_challenge() {
+            let challenge_url = challenge.url.clone();
+            self.notify(enrollment, E2eiProgress::OidcChallengeValid { challenge_url });
+        }
+        self.save(enrollment)
+    }
+
+    /// Checks the order, finalizes it then fetches the certificate chain, up to
+    /// [E2eiEnrollmentStep::Done]. Returns the DER certificates, leaf first. Resumes an
+    /// enrollment whose order is already checked or finalized.
+    pub async fn certificate(&mut self, enrollment: &mut E2eiEnrollment) -> E2eIdentityResult<Vec<Vec<u8>>> {
+        if enrollment.step() == E2eiEnrollmentStep::CheckOrder {
+            let response = self
+                .post(enrollment, |e, nonce| e.check_order_request(nonce), Pending::Order)
+                .await?;
+            enrollment.check_order_response(Self::body(&response)?)?;
+            self.save(enrollment)?;
+        }
+
+        if enrollment.step() == E2eiEnrollmentStep::Finalize {
+            let response = self
+                .post_non_idempotent(enrollment, |e, nonce| e.finalize_request(nonce))
+                .await?;
+            let mut finalize = Self::body(&response)?;
+            if Pending::Finalize.is_pending(&finalize) {
+                let response = self
+                    .post(enrollment, |e, nonce| e.poll_finalize_request(nonce), Pending::Finalize)
+                    .await?;
+                finalize = Self::body(&response)?;
+            }
+            enrollment.finalize_response(finalize)?;
+            self.save(enrollment)?;
+        }
+
+        let response = self
+            .post(enrollment, |e, nonce| e.certificate_request(nonce), Pending::Never)
+            .await?;
+        let chain = String::from_utf8(response.body)
+            .map_err(|_| RustyAcmeError::SmallstepImplementationError("the certificate chain is not a PEM bundle"))?;
+        let chain = enrollment.certificate_response(chain)?;
+        if let Some(leaf) = chain.first() {
+            let leaf = AcmeCertificateInfo::try_from_der(leaf)?;
+            let progress = E2eiProgress::CertificateIssued {
+                serial_number: leaf.serial_number,
+                not_after: leaf.not_after.unix_timestamp(),
+            };
+            self.notify(enrollment, progress);
+        }
+        self.save(enrollment)?;
+        Ok(chain)
+    }
+
+    fn notify(&self, enrollment: &E2eiEnrollment, progress: E2eiProgress) {
+        if let Some(observer) = &self.observer {
+            observer.on_progress(enrollment.client_id(), &progress);
+        }
+    }
+
+    fn save(&self, enrollment: &E2eiEnrollment) -> E2eIdentityResult<()> {
+        self.saver.as_ref().map_or(Ok(()), |save| save(enrollment))
+    }
+
+    fn retry_policy(&self, step: E2eiEnrollmentStep) -> E2eiRetryPolicy {
+        self.step_retry_policies
+            .get(&step)
+            .copied()
+            .unwrap_or(self.retry_policy)
+    }
+
+    /// Sends the request of the current step signed with [sign], again according to the
+    /// [E2eiRetryPolicy] of the step after a transient error or while the resource is [pending].
+    async fn post(
+        &mut self,
+        enrollment: &E2eiEnrollment,
+        sign: impl Fn(&E2eiEnrollment, String) -> E2eIdentityResult<E2eiAcmeRequest>,
+        pending: Pending,
+    ) -> E2eIdentityResult<HttpResponse> {
+        let retry = Retry::new(self.retry_policy(enrollment.step()));
+        self.post_with(enrollment, sign, pending, retry).await
+    }
+
+    /// Like [Self::post] for a request the ACME server must not process twice e.g. 'newOrder'. It
+    /// is not sent again after a transport error or a 5xx since it might have gone through.
+    async fn post_non_idempotent(
+        &mut self,
+        enrollment: &E2eiEnrollment,
+        sign: impl Fn(&E2eiEnrollment, String) -> E2eIdentityResult<E2eiAcmeRequest>,
+    ) -> E2eIdentityResult<HttpResponse> {
+        let retry = Retry::new(self.retry_policy(enrollment.step())).non_idempotent();
+        self.post_with(enrollment, sign, Pending::Never, retry).await
+    }
+
+    async fn post_with(
+        &mut self,
+        enrollment: &E2eiEnrollment,
+        sign: impl Fn(&E2eiEnrollment, String) -> E2eIdentityResult<E2eiAcmeRequest>,
+        pending: Pending,
+        mut retry: Retry,
+    ) -> E2eIdentityResult<HttpResponse> {
+        loop {
+            if let Some(pause) = self.server.remaining_pause() {
+                Self::sleep(pause).await;
+            }
+            let response = self.post_once(enrollment, &sign).await;
+            if let Err(E2eIdentityError::AcmeError(RustyAcmeError::RateLimited {
+                retry_after: Some(retry_after),
+            })) = &response
+            {
+                self.server.pause(*retry_after);
+            }
+            match retry.next(&response, pending) {
+                Some(delay) => Self::sleep(delay).await,
+                None => return response,
+            }
+        }
+    }
+
+    /// Signs the request of the current step with [sign] and sends it, signing it again when the
+    /// ACME server rejects its nonce.
+    /// see [RFC 8555 Section 6.5](https://www.rfc-editor.org/rfc/rfc8555.html#section-6.5)
+    async fn post_once(
+        &self,
+        enrollment: &E2eiEnrollment,
+        sign: &impl Fn(&E2eiEnrollment, String) -> E2eIdentityResult<E2eiAcmeRequest>,
+    ) -> E2eIdentityResult<HttpResponse> {
+        let mut nonce = match self.server.take_nonce() {
+            Some(nonce) => nonce,
+            None => self.new_nonce(enrollment).await?,
+        };
+        let mut retries = RustyAcme::BAD_NONCE_RETRIES;
+        loop {
+            let response = self.send(sign(enrollment, nonce)?.into()).await?;
+            let body = Self::body(&response).unwrap_or_default();
+            match (RustyAcme::is_bad_nonce(&body), Self::replay_nonce(&response)) {
+                (true, Some(replay_nonce)) if retries > 0 => {
+                    retries -= 1;
+                    nonce = replay_nonce;
+                }
+                (true, _) => return Err(RustyAcmeError::BadNonce.into()),
+                (false, _) => {
+                    self.keep_nonce(&response);
+                    RustyAcme::check_rate_limit(response.status, &body, response.header("retry-after"))?;
+                    if let Some(problem) = AcmeProblem::try_from_response(&body) {
+                        return Err(RustyAcmeError::from(problem).into());
+                    }
+                    return Ok(response);
+                }
+            }
+        }
+    }
+
+    /// Fresh nonce from `HEAD {directory.new_nonce}`
+    async fn new_nonce(&self, enrollment: &E2eiEnrollment) -> E2eIdentityResult<String> {
+        let directory = enrollment
+            .directory()
+            .ok_or(E2eIdentityError::OutOfOrderEnrollment(enrollment.step()))?;
+        let response = self
+            .send(Self::request(HttpMethod::Head, directory.new_nonce.clone()))
+            .await?;
+        Ok(
+            Self::replay_nonce(&response).ok_or(RustyAcmeError::SmallstepImplementationError(
+                "'newNonce' responded without a 'Replay-Nonce' header",
+            ))?,
+        )
+    }
+
+    async fn send(&self, request: HttpRequest) -> E2eIdentityResult<HttpResponse> {
+        if let Some(observer) = &self.observer {
+            observer.on_request(&request);
+        }
+        let response = self
+            .transport
+            .send(request.clone())
+            .await
+            .map_err(|e| RustyAcmeError::TransportError(Box::new(e)))?;
+        if let Some(observer) = &self.observer {
+            observer.on_response(&request, &response);
+        }
+        Ok(response)
+    }
+
+    async fn sleep(delay: Duration) {
+        if !delay.is_zero() {
+            // cannot fail, the timer is never dropped while the delay is pending
+            let _ = fluvio_wasm_timer::Delay::new(delay).await;
+        }
+    }
+
+    fn request(method: HttpMethod, url: url::Url) -> HttpRequest {
+        HttpRequest {
+            method,
+            url,
+            headers: vec![],
+            body: vec![],
+        }
+    }
+
+    fn keep_nonce(&self, response: &HttpResponse) {
+        if let Some(nonce) = Self::replay_nonce(response) {
+            self.server.put_nonce(nonce);
+        }
+    }
+
+    fn replay_nonce(response: &HttpResponse) -> Option<String> {
+        response.header("replay-nonce").map(str::to_string)
+    }
+
+    fn body(response: &HttpResponse) -> E2eIdentityResult<Json> {
+        Ok(serde_json::from_slice(&response.body)?)
+    }
+}
+
+/// What the flows enrolling against the same ACME server share, see [E2eiBulkEnrollment]
+#[derive(Debug, Default)]
+pub(crate) struct AcmeServerState {
+    /// unused nonces, the most recent last
+    nonces: std::sync::Mutex<Vec<String>>,
+    /// the ACME server rate limited a request, asking to hold off for this long from then
+    pause: std::sync::Mutex<Option<(fluvio_wasm_timer::Instant, Duration)>>,
+}
+
+impl AcmeServerState {
+    /// The ACME server forgets nonces after a while, older ones are dropped
+    const MAX_NONCES: usize = 16;
+
+    fn take_nonce(&self) -> Option<String> {
+        Self::lock(&self.nonces).pop()
+    }
+
+    fn put_nonce(&self, nonce: String) {
+        let mut nonces = Self::lock(&self.nonces);
+        nonces.push(nonce);
+        if nonces.len() > Self::MAX_NONCES {
+            nonces.remove(0);
+        }
+    }
+
+    fn pause(&self, delay: Duration) {
+        let mut pause = Self::lock(&self.pause);
+        if Self::remaining(&pause) < Some(delay) {
+            *pause = Some((fluvio_wasm_timer::Instant::now(), delay));
+        }
+    }
+
+    fn remaining_pause(&self) -> Option<Duration> {
+        Self::remaining(&Self::lock(&self.pause))
+    }
+
+    fn remaining(pause: &Option<(fluvio_wasm_timer::Instant, Duration)>) -> Option<Duration> {
+        let (start, delay) = pause.as_ref()?;
+        Some(delay.saturating_sub(start.elapsed())).filter(|d| !d.is_zero())
+    }
+
+    /// A panic while enrolling a client must not prevent enrolling the others
+    fn lock<V>(mutex: &std::sync::Mutex<V>) -> std::sync::MutexGuard<'_, V> {
+        mutex.lock().unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+/// Resource the ACME server may still be processing when it responds
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Pending {
+    Never,
+    /// 'processing' challenge
+    Challenge,
+    /// 'pending' order whose authorizations are not valid yet
+    Order,
+    /// 'processing' order whose certificate is not issued yet
+    Finalize,
+}
+
+impl Pending {
+    fn is_pending(self, body: &Json) -> bool {
+        let body = body.clone();
+        match self {
+            Self::Never => false,
+            Self::Challenge => matches!(RustyAcme::poll_chall_response(body, None), Ok(AcmePoll::Retry(_))),
+            Self::Order => matches!(RustyAcme::poll_order_response(body, None), Ok(AcmePoll::Retry(_))),
+            Self::Finalize => matches!(RustyAcme::poll_finalize_response(body, None), Ok(AcmePoll::Retry(_))),
+        }
+    }
+}
+
+/// Attempts of a step according to its [E2eiRetryPolicy]
+struct Retry {
+    policy: E2eiRetryPolicy,
+    attempt: u32,
+    start: fluvio_wasm_timer::Instant,
+    /// whether the request can be sent again when it is unknown if the ACME server processed it
+    idempotent: bool,
+}
+
+impl Retry {
+    fn new(policy: E2eiRetryPolicy) -> Self {
+        Self {
+            policy,
+            attempt: 0,
+            start: fluvio_wasm_timer::Instant::now(),
+            idempotent: true,
+        }
+    }
+
+    fn non_idempotent(mut self) -> Self {
+        self.idempotent = false;
+        self
+    }
+
+    /// Delay before attempting again, `None` when [response] is final
+    fn next(&mut self, response: &E2eIdentityResult<HttpResponse>, pending: Pending) -> Option<Duration> {
+        let retry_ambiguous = self.idempotent;
+        let retry_after = match response {
+            Ok(r) if r.status >= 500 && retry_ambiguous => {
+                r.header("retry-after").and_then(RetryPolicy::parse_retry_after)
+            }
+            Ok(r) if pending.is_pending(&serde_json::from_slice(&r.body).unwrap_or_default()) => {
+                r.header("retry-after").and_then(RetryPolicy::parse_retry_after)
+            }
+            Err(E2eIdentityError::AcmeError(RustyAcmeError::RateLimited { retry_after })) => *retry_after,
+            Err(E2eIdentityError::AcmeError(RustyAcmeError::BadNonce)) => None,
+            Err(E2eIdentityError::AcmeError(RustyAcmeError::TransportError(_))) if retry_ambiguous => None,
+            Err(E2eIdentityError::AcmeError(RustyAcmeError::Problem(problem)))
+                if retry_ambiguous
+                    && (problem.typ == AcmeProblemType::ServerInternal || problem.status.is_some_and(|s| s >= 500)) =>
+            {
+                None
+            }
+            _ => return None,
+        };
+        self.attempt += 1;
+        self.policy.delay(self.attempt, self.start.elapsed(), retry_after)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use serde_json::json;
+    use wasm_bindgen_test::*;
+
+    use rusty_acme::prelude::MockAcmeServer;
+
+    use super::*;
+    use crate::enrollment::tests::{block_on, body, enrollment, send, server, unsigned_token, CLIENT_ID};
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    pub fn access_token(enrollment: &E2eiEnrollment) -> String {
+        let dpop_chall = enrollment.dpop_challenge().unwrap().clone();
+        let token = rusty_acme::prelude::AcmeChallenge::try_from(dpop_chall).unwrap().token;
+        let client_id = E2eiClientId::try_from_qualified(CLIENT_ID).unwrap();
+        unsigned_token(json!({"chal": token, "client_id": client_id.to_uri()}))
+    }
+
+    pub fn id_token(enrollment: &E2eiEnrollment) -> String {
+        unsigned_token(json!({
+            "keyauth": enrollment.keyauth().unwrap(),
+            "acme_aud": enrollment.oidc_challenge().unwrap().url,
+            "name": "Alice Smith",
+        }))
+    }
+
+    pub fn enroll(flow: &mut E2eiEnrollmentFlow<MockAcmeServer>, enrollment: &mut E2eiEnrollment) -> Vec<Vec<u8>> {
+        let directory_url = flow.transport.directory_url();
+        block_on(flow.new_account(enrollment, &directory_url)).unwrap();
+        block_on(flow.new_order(enrollment)).unwrap();
+        block_on(flow.dpop_challenge(enrollment, access_token(enrollment))).unwrap();
+        block_on(flow.oidc_challenge(enrollment, id_token(enrollment))).unwrap();
+        block_on(flow.certificate(enrollment)).unwrap()
+    }
+
+    /// Runs the enrollment up to [step], sending the requests of the steps following
+    /// [E2eiEnrollmentStep::Directory], [E2eiEnrollmentStep::NewOrder],
+    /// [E2eiEnrollmentStep::CheckOrder] and [E2eiEnrollmentStep::Finalize] without [flow] as if
+    /// it had been interrupted right after them
+    pub fn enroll_until(
+        flow: &mut E2eiEnrollmentFlow<MockAcmeServer>,
+        enrollment: &mut E2eiEnrollment,
+        step: E2eiEnrollmentStep,
+    ) {
+        let server = flow.transport.clone();
+        while enrollment.step() != step {
+            let nonce = || block_on(flow.new_nonce(enrollment)).unwrap();
+            match enrollment.step() {
+                E2eiEnrollmentStep::Directory => {
+                    let get = E2eiEnrollmentFlow::<MockAcmeServer>::request(HttpMethod::Get, server.directory_url());
+                    let response = block_on(server.send(get)).unwrap();
+                    enrollment.directory_response(body(&response)).unwrap();
+                }
+                E2eiEnrollmentStep::NewAccount => {
+                    block_on(flow.new_account(enrollment, &server.directory_url())).unwrap();
+                }
+                E2eiEnrollmentStep::NewOrder => {
+                    let response = send(&server, enrollment.new_order_request(nonce()).unwrap());
+                    let order_url = response.header("location").unwrap().parse().unwrap();
+                    enrollment.new_order_response(body(&response), order_url).unwrap();
+                }
+                E2eiEnrollmentStep::Authorizations => block_on(flow.new_order(enrollment)).unwrap(),
+                E2eiEnrollmentStep::DpopChallenge => {
+                    block_on(flow.dpop_challenge(enrollment, access_token(enrollment))).unwrap();
+                }
+                E2eiEnrollmentStep::OidcChallenge => {
+                    block_on(flow.oidc_challenge(enrollment, id_token(enrollment))).unwrap();
+                }
+                E2eiEnrollmentStep::CheckOrder => {
+                    let response = send(&server, enrollment.check_order_request(nonce()).unwrap());
+                    enrollment.check_order_response(body(&response)).unwrap();
+                }
+                E2eiEnrollmentStep::Finalize => {
+                    let response = send(&server, enrollment.finalize_request(nonce()).unwrap());
+                    enrollment.finalize_response(body(&response)).unwrap();
+                }
+                E2eiEnrollmentStep::Certificate | E2eiEnrollmentStep::Done => {
+                    block_on(flow.certificate(enrollment)).unwrap();
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_enroll_over_transport() {
+        let mut flow = E2eiEnrollmentFlow::new(server());
+        let mut enrollment = enrollment();
+        let chain = enroll(&mut flow, &mut enrollment);
+        assert_eq!(chain.len(), 2);
+        assert_eq!(enrollment.step(), E2eiEnrollmentStep::Done);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_resume_with_new_flow() {
+        let server = server();
+        let mut flow = E2eiEnrollmentFlow::new(server.clone());
+        let mut enrollment = enrollment();
+        block_on(flow.new_account(&mut enrollment, &server.directory_url())).unwrap();
+        block_on(flow.new_order(&mut enrollment)).unwrap();
+        let persisted = serde_json::to_string(&enrollment).unwrap();
+
+        // no nonce left, a fresh one is fetched
+        let mut flow = E2eiEnrollmentFlow::new(server);
+        let mut enrollment = serde_json::from_str::<E2eiEnrollment>(&persisted).unwrap();
+        block_on(flow.dpop_challenge(&mut enrollment, access_token(&enrollment))).unwrap();
+        block_on(flow.oidc_challenge(&mut enrollment, id_token(&enrollment))).unwrap();
+        assert_eq!(block_on(flow.certificate(&mut enrollment)).unwrap().len(), 2);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_resume_from_any_step() {
+        let mut flow = E2eiEnrollmentFlow::new(server());
+        let directory_url = flow.transport.directory_url();
+        let mut enrollment = enrollment();
+
+        enroll_until(&mut flow, &mut enrollment, E2eiEnrollmentStep::NewAccount);
+        block_on(flow.new_account(&mut enrollment, &directory_url)).unwrap();
+        assert_eq!(enrollment.step(), E2eiEnrollmentStep::NewOrder);
+
+        enroll_until(&mut flow, &mut enrollment, E2eiEnrollmentStep::Authorizations);
+        block_on(flow.new_order(&mut enrollment)).unwrap();
+        assert_eq!(enrollment.step(), E2eiEnrollmentStep::DpopChallenge);
+
+        enroll_until(&mut flow, &mut enrollment, E2eiEnrollmentStep::Finalize);
+        assert_eq!(block_on(flow.certificate(&mut enrollment)).unwrap().len(), 2);
+
+        let mut enrollment = crate::enrollment::tests::enrollment();
+        enroll_until(&mut flow, &mut enrollment, E2eiEnrollmentStep::Certificate);
+        assert_eq!(block_on(flow.certificate(&mut enrollment)).unwrap().len(), 2);
+        assert_eq!(enrollment.step(), E2eiEnrollmentStep::Done);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_save_in_secret_store() {
+        let server = server();
+        let store = std::sync::Arc::new(InMemorySecretStore::new());
+        let mut flow = E2eiEnrollmentFlow::new(server.clone()).with_secret_store(store.clone());
+        let mut enrollment = enrollment();
+        block_on(flow.new_account(&mut enrollment, &server.directory_url())).unwrap();
+        block_on(flow.new_order(&mut enrollment)).unwrap();
+        assert!(store.get(CLIENT_ID, SecretKind::AcmeKey).unwrap().is_some());
+        assert!(store.get(CLIENT_ID, SecretKind::ClientKey).unwrap().is_some());
+        drop(enrollment);
+
+        let mut flow = E2eiEnrollmentFlow::new(server).with_secret_store(store.clone());
+        let mut enrollment = E2eiEnrollment::restore(&store, CLIENT_ID).unwrap().unwrap();
+        assert_eq!(enrollment.step(), E2eiEnrollmentStep::DpopChallenge);
+        block_on(flow.dpop_challenge(&mut enrollment, access_token(&enrollment))).unwrap();
+        block_on(flow.oidc_challenge(&mut enrollment, id_token(&enrollment))).unwrap();
+        block_on(flow.certificate(&mut enrollment)).unwrap();
+
+        let restored = E2eiEnrollment::restore(&store, CLIENT_ID).unwrap().unwrap();
+        assert_eq!(restored.step(), E2eiEnrollmentStep::Done);
+        assert!(E2eiEnrollment::restore(&store, "unknown").unwrap().is_none());
+    }
+
+    #[derive(Default)]
+    struct Recorder(std::sync::Mutex<Vec<(String, E2eiProgress)>>);
+
+    impl E2eiObserver for Recorder {
+        fn on_progress(&self, client_id: &str, progress: &E2eiProgress) {
+            self.0.lock().unwrap().push((client_id.to_string(), progress.clone()));
+        }
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_notify_observer() {
+        let recorder = std::sync::Arc::new(Recorder::default());
+        let mut flow = E2eiEnrollmentFlow::new(server()).with_observer(recorder.clone());
+        let mut enrollment = enrollment();
+        let chain = enroll(&mut flow, &mut enrollment);
+
+        let records = recorder.0.lock().unwrap();
+        assert!(records.iter().all(|(client_id, _)| client_id == CLIENT_ID));
+        let progress = records.iter().map(|(_, p)| p).collect::<Vec<_>>();
+        assert_eq!(progress.len(), 6);
+        let directory_url = flow.transport.directory_url();
+        assert_eq!(progress[0], &E2eiProgress::DirectoryFetched { directory_url });
+        assert!(matches!(
+            progress[1],
+            E2eiProgress::AccountCreated { account_url: Some(_) }
+        ));
+        assert!(matches!(progress[2], E2eiProgress::OrderCreated { .. }));
+        let challenge_url = enrollment.dpop_challenge().unwrap().url.clone();
+        assert_eq!(progress[3], &E2eiProgress::DpopChallengeValid { challenge_url });
+        let challenge_url = enrollment.oidc_challenge().unwrap().url.clone();
+        assert_eq!(progress[4], &E2eiProgress::OidcChallengeValid { challenge_url });
+        let leaf = AcmeCertificateInfo::try_from_der(&chain[0]).unwrap();
+        let not_after = leaf.not_after.unix_timestamp();
+        let serial_number = leaf.serial_number;
+        assert_eq!(
+            progress[5],
+            &E2eiProgress::CertificateIssued {
+                serial_number,
+                not_after
+            }
+        );
+    }
+
+    /// Fails the next [Self::failures] requests as if the ACME server was unreachable
+    #[derive(Clone)]
+    struct FlakyTransport {
+        server: MockAcmeServer,
+        failures: std::sync::Arc<std::sync::atomic::AtomicU32>,
+    }
+
+    impl FlakyTransport {
+        fn fail_next(&self, failures: u32) {
+            self.failures.store(failures, std::sync::atomic::Ordering::SeqCst);
+        }
+    }
+
+    impl HttpTransport for FlakyTransport {
+        type Error = std::io::Error;
+
+        async fn send(&self, request: HttpRequest) -> Result<HttpResponse, Self::Error> {
+            let failures = &self.failures;
+            if failures.load(std::sync::atomic::Ordering::SeqCst) > 0 {
+                failures.fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
+                return Err(std::io::ErrorKind::ConnectionReset.into());
+            }
+            Ok(self.server.send(request).await.unwrap())
+        }
+    }
+
+    fn flaky() -> FlakyTransport {
+        FlakyTransport {
+            server: server(),
+            failures: Default::default(),
+        }
+    }
+
+    const RETRY: E2eiRetryPolicy = E2eiRetryPolicy {
+        attempts: 3,
+        backoff: Duration::ZERO,
+        max_elapsed: Duration::from_secs(60),
+    };
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_retry_transport_errors() {
+        let transport = flaky();
+        let directory_url = transport.server.directory_url();
+        let mut flow = E2eiEnrollmentFlow::new(transport.clone()).with_retry_policy(RETRY);
+        let mut enrollment = enrollment();
+
+        transport.fail_next(2);
+        block_on(flow.new_account(&mut enrollment, &directory_url)).unwrap();
+        block_on(flow.new_order(&mut enrollment)).unwrap();
+        assert_eq!(enrollment.step(), E2eiEnrollmentStep::DpopChallenge);
+
+        // the nonce of the failed request is lost, a fresh one is fetched
+        transport.fail_next(2);
+        block_on(flow.dpop_challenge(&mut enrollment, access_token(&enrollment))).unwrap();
+        assert_eq!(enrollment.step(), E2eiEnrollmentStep::OidcChallenge);
+
+        transport.fail_next(3);
+        let result = block_on(flow.oidc_challenge(&mut enrollment, id_token(&enrollment)));
+        assert!(matches!(
+            result.unwrap_err(),
+            E2eIdentityError::AcmeError(RustyAcmeError::TransportError(_))
+        ));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_retry_only_configured_steps() {
+        let transport = flaky();
+        let directory_url = transport.server.directory_url();
+        let mut flow =
+            E2eiEnrollmentFlow::new(transport.clone()).with_step_retry_policy(E2eiEnrollmentStep::DpopChallenge, RETRY);
+        let mut enrollment = enrollment();
+
+        transport.fail_next(1);
+        let result = block_on(flow.new_account(&mut enrollment, &directory_url));
+        assert!(matches!(
+            result.unwrap_err(),
+            E2eIdentityError::AcmeError(RustyAcmeError::TransportError(_))
+        ));
+        block_on(flow.new_account(&mut enrollment, &directory_url)).unwrap();
+        block_on(flow.new_order(&mut enrollment)).unwrap();
+
+        transport.fail_next(1);
+        block_on(flow.dpop_challenge(&mut enrollment, access_token(&enrollment))).unwrap();
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_not_retry_non_idempotent_requests_after_transport_errors() {
+        let transport = flaky();
+        let directory_url = transport.server.directory_url();
+        let mut flow = E2eiEnrollmentFlow::new(transport.clone()).with_retry_policy(RETRY);
+        let mut enrollment = enrollment();
+        block_on(flow.new_account(&mut enrollment, &directory_url)).unwrap();
+
+        // the order might have been created, creating another one is up to the caller
+        transport.fail_next(1);
+        let result = block_on(flow.new_order(&mut enrollment));
+        assert!(matches!(
+            result.unwrap_err(),
+            E2eIdentityError::AcmeError(RustyAcmeError::TransportError(_))
+        ));
+        assert_eq!(enrollment.step(), E2eiEnrollmentStep::NewOrder);
+        block_on(flow.new_order(&mut enrollment)).unwrap();
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_not_retry_client_errors() {
+        let recorder = std::sync::Arc::new(crate::prelude::FlowRecorder::new());
+        let mut flow = E2eiEnrollmentFlow::new(server())
+            .with_retry_policy(RETRY)
+            .with_observer(recorder.clone());
+        let mut enrollment = enrollment();
+        let directory_url = flow.transport.directory_url();
+        block_on(flow.new_account(&mut enrollment, &directory_url)).unwrap();
+        block_on(flow.new_order(&mut enrollment)).unwrap();
+
+        let sent = recorder.events().len();
+        let result = block_on(flow.dpop_challenge(&mut enrollment, unsigned_token(json!({}))));
+        assert!(matches!(
+            result.unwrap_err(),
+            E2eIdentityError::AcmeError(RustyAcmeError::Problem(_))
+        ));
+        // a single request and its response
+        assert_eq!(recorder.events().len(), sent + 2);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_share_nonces_and_pauses() {
+        let server = AcmeServerState::default();
+        (0..=AcmeServerState::MAX_NONCES).for_each(|i| server.put_nonce(i.to_string()));
+        assert_eq!(server.take_nonce(), Some(AcmeServerState::MAX_NONCES.to_string()));
+        let nonces = AcmeServerState::lock(&server.nonces).clone();
+        assert_eq!(nonces.len(), AcmeServerState::MAX_NONCES - 1);
+        // the oldest is dropped
+        assert_eq!(nonces[0], "1");
+
+        assert_eq!(server.remaining_pause(), None);
+        server.pause(Duration::from_secs(60));
+        // a shorter pause does not cut it short
+        server.pause(Duration::from_secs(1));
+        assert!(server.remaining_pause().unwrap() > Duration::from_secs(59));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_resume_with_external_id_token() {
+        let server = server();
+        let mut flow = E2eiEnrollmentFlow::new(server.clone());
+        let mut enrollment = enrollment();
+        block_on(flow.new_account(&mut enrollment, &server.directory_url())).unwrap();
+        block_on(flow.new_order(&mut enrollment)).unwrap();
+        block_on(flow.dpop_challenge(&mut enrollment, access_token(&enrollment))).unwrap();
+        let deferred = enrollment.deferred_oidc_challenge().unwrap();
+        assert_eq!(deferred.audience, enrollment.oidc_challenge().unwrap().url);
+        assert_eq!(Some(deferred.keyauth.as_str()), enrollment.keyauth());
+        assert_eq!(deferred.claims["id_token"]["keyauth"]["value"], deferred.keyauth);
+        let persisted = serde_json::to_string(&enrollment).unwrap();
+
+        // the system account manager obtains the ID token
+        let id_token = |keyauth: &str| {
+            unsigned_token(json!({
+                "keyauth": keyauth,
+                "acme_aud": deferred.audience,
+                "name": "Alice Smith",
+            }))
+        };
+        let mut flow = E2eiEnrollmentFlow::new(server);
+        let mut enrollment = serde_json::from_str::<E2eiEnrollment>(&persisted).unwrap();
+        let result = block_on(flow.external_oidc_challenge(&mut enrollment, id_token("another")));
+        assert!(matches!(
+            result.unwrap_err(),
+            E2eIdentityError::IdTokenChallengeMismatch("keyauth")
+        ));
+        let result = block_on(flow.external_oidc_challenge(&mut enrollment, "not a jwt".to_string()));
+        assert!(matches!(result.unwrap_err(), E2eIdentityError::JwtError(_)));
+
+        block_on(flow.external_oidc_challenge(&mut enrollment, id_token(&deferred.keyauth))).unwrap();
+        assert_eq!(enrollment.step(), E2eiEnrollmentStep::CheckOrder);
+        assert_eq!(block_on(flow.certificate(&mut enrollment)).unwrap().len(), 2);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_surface_acme_problems() {
+        let mut flow = E2eiEnrollmentFlow::new(server());
+        let mut enrollment = enrollment();
+        let directory_url = flow.transport.directory_url();
+        block_on(flow.new_account(&mut enrollment, &directory_url)).unwrap();
+        block_on(flow.new_order(&mut enrollment)).unwrap();
+        let result = block_on(flow.dpop_challenge(&mut enrollment, unsigned_token(json!({}))));
+        assert!(matches!(
+            result.unwrap_err(),
+            E2eIdentityError::AcmeError(RustyAcmeError::Problem(_))
+        ));
+    }
+}
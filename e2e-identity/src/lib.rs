@@ -1,32 +1,53 @@
-use jwt_simple::prelude::{ES256KeyPair, ES384KeyPair, Ed25519KeyPair, Jwk};
+use jwt_simple::prelude::{Claims, Duration, ES256KeyPair, ES384KeyPair, Ed25519KeyPair, JWTHeader, Jwk};
 use zeroize::Zeroize;
 
 use error::*;
 use prelude::*;
-use rusty_acme::prelude::{AcmeChallenge, AcmeIdentifier};
+use rusty_acme::prelude::{AcmeAuthz, AcmeChallenge, AcmeIdentifier};
 use rusty_jwt_tools::{
     jwk::TryIntoJwk,
-    jwk_thumbprint::JwkThumbprint,
     prelude::{ClientId, Dpop, Handle, Htm, Pem, RustyJwtTools},
 };
 
 #[cfg(feature = "identity-builder")]
 mod builder;
+mod bulk;
+mod enrollment;
 mod error;
+mod expiry;
+mod flow;
+mod observer;
+mod oidc;
+mod pkce;
+mod recorder;
+mod retry;
+mod secret;
 mod types;
 
 pub mod prelude {
     pub use rusty_acme::prelude::x509;
     pub use rusty_acme::prelude::{
-        x509::IdentityStatus, AcmeDirectory, RustyAcme, RustyAcmeError, WireIdentity, WireIdentityReader,
+        x509::IdentityStatus, AcmeDirectory, AcmeRenewalInfo, AcmeRenewalWindow, ExternalAccountBinding, RustyAcme,
+        RustyAcmeError, WireIdentity, WireIdentityReader,
     };
     pub use rusty_jwt_tools::prelude::{ClientId as E2eiClientId, Handle, HashAlgorithm, JwsAlgorithm, RustyJwtError};
 
     #[cfg(feature = "identity-builder")]
     pub use super::builder::*;
-    pub use super::error::{E2eIdentityError, E2eIdentityResult};
+    pub use super::bulk::{E2eiBulkEnrollment, E2eiTokenProvider};
+    pub use super::enrollment::{E2eiEnrollment, E2eiEnrollmentStep};
+    pub use super::error::{E2eIdentityError, E2eIdentityResult, E2eiErrorCategory};
+    pub use super::expiry::{E2eiRenewalSchedule, E2eiRenewalStatus};
+    pub use super::flow::E2eiEnrollmentFlow;
+    pub use super::observer::{E2eiObserver, E2eiProgress};
+    pub use super::oidc::{OidcIdentity, OidcProvider, RefreshTokenStore};
+    pub use super::pkce::Pkce;
+    pub use super::recorder::{FlowActor, FlowEvent, FlowRecorder};
+    pub use super::retry::E2eiRetryPolicy;
+    pub use super::secret::{ClientRefreshTokenStore, InMemorySecretStore, SecretKind, SecretStore};
     pub use super::types::{
-        E2eiAcmeAccount, E2eiAcmeAuthorization, E2eiAcmeChallenge, E2eiAcmeFinalize, E2eiAcmeOrder, E2eiNewAcmeOrder,
+        E2eiAcmeAccount, E2eiAcmeAuthorization, E2eiAcmeChallenge, E2eiAcmeFinalize, E2eiAcmeOrder, E2eiAcmeRequest,
+        E2eiCrossSignature, E2eiDeferredOidcChallenge, E2eiNewAcmeOrder, OidcTokenRequest,
     };
     pub use super::RustyE2eIdentity;
 }
@@ -51,11 +72,7 @@ impl RustyE2eIdentity {
     /// * `sign_alg` - Signature algorithm (only Ed25519 for now)
     /// * `raw_sign_key` - Raw signature key as bytes
     pub fn try_new(sign_alg: JwsAlgorithm, mut raw_sign_key: Vec<u8>) -> E2eIdentityResult<Self> {
-        let sign_kp = match sign_alg {
-            JwsAlgorithm::Ed25519 => Ed25519KeyPair::from_bytes(&raw_sign_key[..])?.to_pem(),
-            JwsAlgorithm::P256 => ES256KeyPair::from_bytes(&raw_sign_key[..])?.to_pem()?,
-            JwsAlgorithm::P384 => ES384KeyPair::from_bytes(&raw_sign_key[..])?.to_pem()?,
-        };
+        let sign_kp = Self::sign_kp(sign_alg, &raw_sign_key)?;
         let (acme_kp, acme_jwk) = match sign_alg {
             JwsAlgorithm::Ed25519 => {
                 let kp = Ed25519KeyPair::generate();
@@ -74,13 +91,76 @@ impl RustyE2eIdentity {
         raw_sign_key.zeroize();
         Ok(Self {
             sign_alg,
-            sign_kp: sign_kp.into(),
+            sign_kp,
             hash_alg: HashAlgorithm::from(sign_alg),
             acme_kp,
             acme_jwk,
         })
     }
 
+    /// Same as [Self::try_new] but keeps the ACME account key of this instance, for renewing the
+    /// certificate with the same ACME account
+    pub(crate) fn try_new_with_same_acme_key(&self, mut raw_sign_key: Vec<u8>) -> E2eIdentityResult<Self> {
+        let sign_kp = Self::sign_kp(self.sign_alg, &raw_sign_key);
+        raw_sign_key.zeroize();
+        Ok(Self {
+            sign_alg: self.sign_alg,
+            sign_kp: sign_kp?,
+            hash_alg: self.hash_alg,
+            acme_kp: self.acme_kp.clone(),
+            acme_jwk: self.acme_jwk.clone(),
+        })
+    }
+
+    /// Signs the signature public key of [renewed] with the signature key of this instance, which
+    /// [certificate] certifies and is embedded in the 'x5c' header, see [E2eiCrossSignature]
+    pub(crate) fn cross_sign(&self, renewed: &Self, client_id: &str, certificate: &[u8]) -> E2eIdentityResult<String> {
+        let client_id = ClientId::try_from_qualified(client_id)?;
+        let cross_signature = E2eiCrossSignature {
+            new_key: renewed.sign_jwk()?,
+        };
+        // links two keys for good, it does not expire
+        let mut claims =
+            Claims::with_custom_claims(cross_signature, Duration::from_secs(0)).with_subject(client_id.to_uri());
+        claims.expires_at = None;
+        let header = JWTHeader {
+            signature_type: Some(E2eiCrossSignature::TYP.to_string()),
+            ..Default::default()
+        };
+        let chain = [certificate.to_vec()];
+        Ok(RustyJwtTools::generate_jwt_with_x5c(
+            self.sign_alg,
+            header,
+            Some(claims),
+            &self.sign_kp,
+            &chain,
+            false,
+        )?)
+    }
+
+    fn sign_jwk(&self) -> E2eIdentityResult<Jwk> {
+        Ok(match self.sign_alg {
+            JwsAlgorithm::Ed25519 => Ed25519KeyPair::from_pem(self.sign_kp.as_str())?
+                .public_key()
+                .try_into_jwk()?,
+            JwsAlgorithm::P256 => ES256KeyPair::from_pem(self.sign_kp.as_str())?
+                .public_key()
+                .try_into_jwk()?,
+            JwsAlgorithm::P384 => ES384KeyPair::from_pem(self.sign_kp.as_str())?
+                .public_key()
+                .try_into_jwk()?,
+        })
+    }
+
+    fn sign_kp(sign_alg: JwsAlgorithm, raw_sign_key: &[u8]) -> E2eIdentityResult<Pem> {
+        Ok(match sign_alg {
+            JwsAlgorithm::Ed25519 => Ed25519KeyPair::from_bytes(raw_sign_key)?.to_pem(),
+            JwsAlgorithm::P256 => ES256KeyPair::from_bytes(raw_sign_key)?.to_pem()?,
+            JwsAlgorithm::P384 => ES384KeyPair::from_bytes(raw_sign_key)?.to_pem()?,
+        }
+        .into())
+    }
+
     /// Parses the response from `GET /acme/{provisioner-name}/directory`.
     /// Use this [AcmeDirectory] in the next step to fetch the first nonce from the acme server. Use
     /// [AcmeDirectory::new_nonce].
@@ -107,7 +187,27 @@ impl RustyE2eIdentity {
         directory: &AcmeDirectory,
         previous_nonce: String,
     ) -> E2eIdentityResult<Json> {
-        let acct_req = RustyAcme::new_account_request(directory, self.sign_alg, &self.acme_kp, previous_nonce)?;
+        let acct_req = RustyAcme::new_account_request(directory, self.sign_alg, &self.acme_kp, None, previous_nonce)?;
+        Ok(serde_json::to_value(acct_req)?)
+    }
+
+    /// Same as [Self::acme_new_account_request] for ACME servers only creating accounts bound to
+    /// an external one.
+    ///
+    /// See [RFC 8555 Section 7.3.4](https://www.rfc-editor.org/rfc/rfc8555.html#section-7.3.4).
+    ///
+    /// # Parameters
+    /// * `directory` - you got from [Self::acme_directory_response]
+    /// * `eab` - key identifier and HMAC key handed out by the ACME server operator
+    /// * `previous_nonce` - you got from calling `HEAD {directory.new_nonce}`
+    pub fn acme_new_account_request_with_eab(
+        &self,
+        directory: &AcmeDirectory,
+        eab: &ExternalAccountBinding,
+        previous_nonce: String,
+    ) -> E2eIdentityResult<Json> {
+        let acct_req =
+            RustyAcme::new_account_request(directory, self.sign_alg, &self.acme_kp, Some(eab), previous_nonce)?;
         Ok(serde_json::to_value(acct_req)?)
     }
 
@@ -152,6 +252,7 @@ impl RustyE2eIdentity {
             client_id,
             &handle.into(),
             expiry,
+            Default::default(),
             directory,
             &account,
             self.sign_alg,
@@ -209,13 +310,15 @@ impl RustyE2eIdentity {
     pub fn acme_new_authz_response(&self, new_authz: Json) -> E2eIdentityResult<E2eiAcmeAuthorization> {
         let authz = serde_json::from_value(new_authz)?;
         let authz = RustyAcme::new_authz_response(authz)?;
+        self.authorization(authz)
+    }
 
+    /// Maps the challenge of an authorization, computing the key authorization of the user one
+    pub(crate) fn authorization(&self, authz: AcmeAuthz) -> E2eIdentityResult<E2eiAcmeAuthorization> {
         let [challenge] = authz.challenges;
         Ok(match authz.identifier {
             AcmeIdentifier::WireappUser(_) => {
-                let thumbprint = JwkThumbprint::generate(&self.acme_jwk, self.hash_alg)?.kid;
-                let oidc_chall_token = &challenge.token;
-                let keyauth = format!("{oidc_chall_token}.{thumbprint}");
+                let keyauth = challenge.key_authorization_with_hash_alg(&self.acme_jwk, self.hash_alg)?;
                 E2eiAcmeAuthorization::User {
                     identifier: authz.identifier.to_json()?,
                     challenge: challenge.try_into()?,
@@ -254,6 +357,21 @@ impl RustyE2eIdentity {
         handle: &str,
         team: Option<String>,
         expiry: core::time::Duration,
+    ) -> E2eIdentityResult<String> {
+        self.new_dpop_token_with_extra_claims(client_id, dpop_challenge, backend_nonce, handle, team, expiry, None)
+    }
+
+    /// Same as [Self::new_dpop_token] with additional claims e.g. the cross signature of a renewal
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new_dpop_token_with_extra_claims(
+        &self,
+        client_id: &str,
+        dpop_challenge: &E2eiAcmeChallenge,
+        backend_nonce: String,
+        handle: &str,
+        team: Option<String>,
+        expiry: core::time::Duration,
+        extra_claims: Option<Json>,
     ) -> E2eIdentityResult<String> {
         let dpop_chall: AcmeChallenge = dpop_challenge.clone().try_into()?;
         let audience = dpop_chall.url;
@@ -265,7 +383,7 @@ impl RustyE2eIdentity {
             challenge: dpop_chall.token.into(),
             handle,
             team: team.into(),
-            extra_claims: None,
+            extra_claims,
         };
         Ok(RustyJwtTools::generate_dpop_token(
             dpop,
@@ -438,7 +556,8 @@ impl RustyE2eIdentity {
         Ok(serde_json::to_value(certificate_req)?)
     }
 
-    /// Parses the response from `POST /acme/{provisioner-name}/certificate/{certificate-id}`.
+    /// Parses the response from `POST /acme/{provisioner-name}/certificate/{certificate-id}`
+    /// and verifies that the leaf certificate certifies the signature key of this enrollment.
     ///
     /// See [RFC 8555 Section 7.4.2](https://www.rfc-editor.org/rfc/rfc8555.html#section-7.4.2)
     ///
@@ -450,6 +569,11 @@ impl RustyE2eIdentity {
         order: E2eiAcmeOrder,
     ) -> E2eIdentityResult<Vec<Vec<u8>>> {
         let order = order.try_into()?;
-        Ok(RustyAcme::certificate_response(response, order)?)
+        Ok(RustyAcme::certificate_response_for_key(
+            response,
+            order,
+            self.sign_alg,
+            &self.sign_kp,
+        )?)
     }
 }
@@ -0,0 +1,65 @@
+use rusty_acme::prelude::{HttpRequest, HttpResponse};
+
+/// Notified by an [crate::prelude::E2eiEnrollmentFlow] each time the enrollment progresses e.g.
+/// to show it in the UI or to correlate support logs. Every method does nothing by default.
+///
+/// Beware that request bodies carry the access and id tokens of the challenges.
+pub trait E2eiObserver: Send + Sync {
+    /// Before sending a request to the ACME server, including each retry after a rejected nonce
+    fn on_request(&self, request: &HttpRequest) {
+        let _ = request;
+    }
+
+    /// After receiving the response to a request, before it is checked
+    fn on_response(&self, request: &HttpRequest, response: &HttpResponse) {
+        let _ = (request, response);
+    }
+
+    /// After a step completed
+    ///
+    /// # Arguments
+    /// * `client_id` - of the enrollment e.g. `NDUyMGUyMmY2YjA3NGU3NjkyZjE1NjJjZTAwMmQ2NTQ:6add501bacd1d90e@example.com`
+    /// * `progress` - what completed
+    fn on_progress(&self, client_id: &str, progress: &E2eiProgress) {
+        let _ = (client_id, progress);
+    }
+}
+
+impl<O: E2eiObserver + ?Sized> E2eiObserver for std::sync::Arc<O> {
+    fn on_request(&self, request: &HttpRequest) {
+        (**self).on_request(request)
+    }
+
+    fn on_response(&self, request: &HttpRequest, response: &HttpResponse) {
+        (**self).on_response(request, response)
+    }
+
+    fn on_progress(&self, client_id: &str, progress: &E2eiProgress) {
+        (**self).on_progress(client_id, progress)
+    }
+}
+
+/// Milestone of an enrollment, see [E2eiObserver]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum E2eiProgress {
+    /// The ACME directory has been fetched
+    DirectoryFetched { directory_url: url::Url },
+    /// The ACME account has been created, or retrieved when it already existed
+    AccountCreated {
+        /// from the 'Location' header, when the ACME server provided it
+        account_url: Option<url::Url>,
+    },
+    /// The order and its authorizations have been created
+    OrderCreated { order_url: url::Url },
+    /// wire-server vouched for the device
+    DpopChallengeValid { challenge_url: url::Url },
+    /// The identity provider vouched for the user
+    OidcChallengeValid { challenge_url: url::Url },
+    /// The x509 certificate has been delivered
+    CertificateIssued {
+        /// lowercase hex encoded
+        serial_number: String,
+        /// unix timestamp in seconds
+        not_after: i64,
+    },
+}
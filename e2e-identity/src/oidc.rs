@@ -0,0 +1,508 @@
+use rusty_jwt_tools::prelude::{DisplayName, QualifiedHandle};
+
+use crate::{prelude::*, Json};
+
+/// Identity provider delivering the ID token of the 'wire-oidc-01' challenge: its endpoints and
+/// the claims holding the Wire identity
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum OidcProvider {
+    /// Dex, whose issuer is e.g. 'https://dex.wire.com/dex'
+    Dex { issuer: url::Url },
+    /// Microsoft Entra ID (formerly Azure AD) v2.0 endpoints of a tenant, either its id e.g.
+    /// '9188040d-6c67-4c5b-b112-36a304b66dad', one of its domains e.g. 'wire.onmicrosoft.com' or
+    /// 'organizations' for any work account
+    EntraId { tenant: String },
+    /// Google accounts
+    Google,
+    /// A Keycloak realm e.g. 'wire' served from 'https://keycloak.wire.com'
+    #[serde(rename_all = "camelCase")]
+    Keycloak { base_url: url::Url, realm: String },
+}
+
+/// Wire identity held by the claims of an ID token, see [OidcProvider::identity]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct OidcIdentity {
+    pub handle: QualifiedHandle,
+    pub display_name: String,
+}
+
+/// Caller-provided storage of the identity provider refresh token, e.g. in the OS keychain, so
+/// that renewals get a fresh ID token without an interactive login. It is up to the caller to
+/// scope it to a user and an identity provider.
+pub trait RefreshTokenStore {
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    /// Last refresh token delivered by the identity provider, if any
+    fn refresh_token(&self) -> Result<Option<String>, Self::Error>;
+
+    /// Replaces the refresh token, `None` forgets it
+    fn set_refresh_token(&mut self, refresh_token: Option<String>) -> Result<(), Self::Error>;
+}
+
+/// Successful response of the token endpoint
+/// see [OpenID Connect Core Section 3.1.3.3](https://openid.net/specs/openid-connect-core-1_0.html#TokenResponse)
+#[derive(serde::Deserialize)]
+pub(crate) struct OidcTokenResponse {
+    pub(crate) id_token: String,
+    pub(crate) refresh_token: Option<String>,
+}
+
+impl OidcProvider {
+    const GOOGLE_ISSUER: &'static str = "https://accounts.google.com";
+    const ENTRA_ID_AUTHORITY: &'static str = "https://login.microsoftonline.com";
+    /// Tenants standing for several ones, tokens then carry the issuer of the user's tenant
+    const ENTRA_ID_MULTI_TENANTS: [&'static str; 3] = ["common", "organizations", "consumers"];
+
+    /// Claim holding the handle, either qualified e.g. 'wireapp://%40alice_wire@wire.com' or not
+    pub const HANDLE_CLAIM: &'static str = "preferred_username";
+
+    /// Claim holding the display name
+    pub const DISPLAY_NAME_CLAIM: &'static str = "name";
+
+    /// 'iss' claim of the ID tokens
+    pub fn issuer(&self) -> E2eIdentityResult<url::Url> {
+        Ok(match self {
+            Self::Dex { issuer } => issuer.clone(),
+            Self::EntraId { tenant } => format!("{}/{tenant}/v2.0", Self::ENTRA_ID_AUTHORITY).parse()?,
+            Self::Google => Self::GOOGLE_ISSUER.parse()?,
+            Self::Keycloak { base_url, realm } => Self::endpoint(base_url, &format!("realms/{realm}"))?,
+        })
+    }
+
+    /// OpenID Provider Configuration document
+    /// see [OpenID Connect Discovery Section 4](https://openid.net/specs/openid-connect-discovery-1_0.html#ProviderConfig)
+    pub fn discovery_url(&self) -> E2eIdentityResult<url::Url> {
+        Self::endpoint(&self.issuer()?, ".well-known/openid-configuration")
+    }
+
+    /// Where the end user authenticates then gets redirected from with an authorization code
+    pub fn authorization_endpoint(&self) -> E2eIdentityResult<url::Url> {
+        match self {
+            Self::Dex { issuer } => Self::endpoint(issuer, "auth"),
+            Self::EntraId { tenant } => Self::entra_id_endpoint(tenant, "oauth2/v2.0/authorize"),
+            Self::Google => Ok("https://accounts.google.com/o/oauth2/v2/auth".parse()?),
+            Self::Keycloak { .. } => Self::endpoint(&self.issuer()?, "protocol/openid-connect/auth"),
+        }
+    }
+
+    /// Where the authorization code is exchanged for the ID token
+    pub fn token_endpoint(&self) -> E2eIdentityResult<url::Url> {
+        match self {
+            Self::Dex { issuer } => Self::endpoint(issuer, "token"),
+            Self::EntraId { tenant } => Self::entra_id_endpoint(tenant, "oauth2/v2.0/token"),
+            Self::Google => Ok("https://oauth2.googleapis.com/token".parse()?),
+            Self::Keycloak { .. } => Self::endpoint(&self.issuer()?, "protocol/openid-connect/token"),
+        }
+    }
+
+    /// Public keys verifying the signature of the ID tokens
+    pub fn jwks_uri(&self) -> E2eIdentityResult<url::Url> {
+        match self {
+            Self::Dex { issuer } => Self::endpoint(issuer, "keys"),
+            Self::EntraId { tenant } => Self::entra_id_endpoint(tenant, "discovery/v2.0/keys"),
+            Self::Google => Ok("https://www.googleapis.com/oauth2/v3/certs".parse()?),
+            Self::Keycloak { .. } => Self::endpoint(&self.issuer()?, "protocol/openid-connect/certs"),
+        }
+    }
+
+    /// Authorization code request with PKCE, where the end user is redirected to
+    /// see [OpenID Connect Core Section 3.1.2.1](https://openid.net/specs/openid-connect-core-1_0.html#AuthRequest)
+    ///
+    /// # Parameters
+    /// * `client_id` - OAuth client registered at the identity provider
+    /// * `redirect_uri` - where the identity provider sends the authorization code
+    /// * `state` - opaque value returned along the authorization code, preventing CSRF
+    /// * `nonce` - value to find in the 'nonce' claim of the ID token, preventing replays
+    /// * `pkce` - its code challenge is sent, its code verifier goes in the [Self::token_request]
+    /// * `claims` - requested claims e.g. the 'keyauth' and 'acme_aud' ones of the OIDC challenge
+    pub fn authorization_url(
+        &self,
+        client_id: &str,
+        redirect_uri: &url::Url,
+        state: &str,
+        nonce: &str,
+        pkce: &Pkce,
+        claims: &Json,
+    ) -> E2eIdentityResult<url::Url> {
+        let mut url = self.authorization_endpoint()?;
+        if matches!(self, Self::Google) {
+            // Google only delivers a refresh token to offline clients
+            url.query_pairs_mut().append_pair("access_type", "offline");
+        }
+        url.query_pairs_mut()
+            .append_pair("response_type", "code")
+            .append_pair("client_id", client_id)
+            .append_pair("redirect_uri", redirect_uri.as_str())
+            .append_pair("scope", self.scope())
+            .append_pair("state", state)
+            .append_pair("nonce", nonce)
+            .append_pair("code_challenge", &pkce.code_challenge())
+            .append_pair("code_challenge_method", Pkce::METHOD)
+            .append_pair("claims", &claims.to_string());
+        Ok(url)
+    }
+
+    /// Exchange of the authorization code for the ID token, proving with the PKCE code verifier
+    /// that this client sent the [Self::authorization_url]
+    ///
+    /// # Parameters
+    /// * `client_id` - OAuth client registered at the identity provider
+    /// * `code` - authorization code the identity provider redirected to `redirect_uri` with
+    /// * `redirect_uri` - the one of the [Self::authorization_url]
+    /// * `pkce` - the one of the [Self::authorization_url]
+    pub fn token_request(
+        &self,
+        client_id: &str,
+        code: &str,
+        redirect_uri: &url::Url,
+        pkce: &Pkce,
+    ) -> E2eIdentityResult<OidcTokenRequest> {
+        let body = url::form_urlencoded::Serializer::new(String::new())
+            .append_pair("grant_type", "authorization_code")
+            .append_pair("client_id", client_id)
+            .append_pair("code", code)
+            .append_pair("redirect_uri", redirect_uri.as_str())
+            .append_pair("code_verifier", pkce.code_verifier())
+            .finish();
+        Ok(OidcTokenRequest {
+            url: self.token_endpoint()?,
+            body,
+        })
+    }
+
+    /// Exchange of a refresh token for a new ID token, without any interaction with the end user.
+    /// The identity provider has to honor the 'claims' parameter on refresh for the ID token to
+    /// hold the values of the current OIDC challenge.
+    ///
+    /// # Parameters
+    /// * `client_id` - OAuth client registered at the identity provider
+    /// * `refresh_token` - delivered along the previous ID token
+    /// * `claims` - requested claims e.g. the 'keyauth' and 'acme_aud' ones of the OIDC challenge
+    pub fn refresh_token_request(
+        &self,
+        client_id: &str,
+        refresh_token: &str,
+        claims: &Json,
+    ) -> E2eIdentityResult<OidcTokenRequest> {
+        let body = url::form_urlencoded::Serializer::new(String::new())
+            .append_pair("grant_type", "refresh_token")
+            .append_pair("client_id", client_id)
+            .append_pair("refresh_token", refresh_token)
+            .append_pair("scope", self.scope())
+            .append_pair("claims", &claims.to_string())
+            .finish();
+        Ok(OidcTokenRequest {
+            url: self.token_endpoint()?,
+            body,
+        })
+    }
+
+    /// Maps the claims of an ID token to the Wire identity. A handle which is not qualified is
+    /// qualified with [domain]. Keycloak omits the 'name' claim when the user has no first nor last
+    /// name, it is then built from the 'given_name' and 'family_name' ones.
+    ///
+    /// Entra ID holds the user principal name in 'preferred_username' e.g. 'alice_wire@wire.com',
+    /// its domain then qualifies the handle. Guest users have theirs rewritten by the host tenant
+    /// e.g. 'alice_wire_wire.com#EXT#@wire.onmicrosoft.com'. This claim is mutable by the user, so
+    /// it is refused when the 'xms_edov' optional claim says its domain owner is not verified, and
+    /// the token must come from the configured tenant.
+    ///
+    /// # Parameters
+    /// * `claims` - decoded payload of the ID token
+    /// * `domain` - DNS name of owning backend e.g. `example.com`
+    pub fn identity(&self, claims: &Json, domain: &str) -> E2eIdentityResult<OidcIdentity> {
+        let claim = |name: &'static str| {
+            claims[name]
+                .as_str()
+                .filter(|c| !c.is_empty())
+                .ok_or(E2eIdentityError::MissingIdTokenClaim(name))
+        };
+        let handle = claim(Self::HANDLE_CLAIM)?;
+        let handle = match (self, handle.parse::<QualifiedHandle>()) {
+            (_, Ok(handle)) => handle,
+            (Self::EntraId { tenant }, Err(_)) => Self::entra_id_handle(claims, tenant, handle, domain)?,
+            (_, Err(_)) => Handle::from(handle).try_to_qualified(domain)?,
+        };
+        let display_name = match (self, claim(Self::DISPLAY_NAME_CLAIM)) {
+            (Self::Keycloak { .. } | Self::EntraId { .. }, Err(_)) => {
+                let names = [claims["given_name"].as_str(), claims["family_name"].as_str()];
+                let names = names
+                    .into_iter()
+                    .flatten()
+                    .filter(|n| !n.is_empty())
+                    .collect::<Vec<_>>();
+                if names.is_empty() {
+                    return Err(E2eIdentityError::MissingIdTokenClaim(Self::DISPLAY_NAME_CLAIM));
+                }
+                names.join(" ")
+            }
+            (_, display_name) => display_name?.to_string(),
+        };
+        Ok(OidcIdentity { handle, display_name })
+    }
+
+    /// Verifies the claims of an ID token hold the identity being enrolled, before answering the
+    /// OIDC challenge with it
+    ///
+    /// # Parameters
+    /// * `claims` - decoded payload of the ID token
+    /// * `handle` - user handle e.g. `alice.smith.qa`
+    /// * `display_name` - human readable name displayed in the application e.g. `Smith, Alice M (QA)`
+    /// * `domain` - DNS name of owning backend e.g. `example.com`
+    pub fn verify_identity(
+        &self,
+        claims: &Json,
+        handle: &str,
+        display_name: &str,
+        domain: &str,
+    ) -> E2eIdentityResult<()> {
+        let identity = self.identity(claims, domain)?;
+        if identity.handle != Handle::from(handle).try_to_qualified(domain)? {
+            return Err(E2eIdentityError::IdTokenIdentityMismatch(Self::HANDLE_CLAIM));
+        }
+        if DisplayName::from(identity.display_name) != DisplayName::from(display_name) {
+            return Err(E2eIdentityError::IdTokenIdentityMismatch(Self::DISPLAY_NAME_CLAIM));
+        }
+        Ok(())
+    }
+
+    /// Entra ID only delivers a refresh token when 'offline_access' is requested
+    fn scope(&self) -> &'static str {
+        match self {
+            Self::EntraId { .. } => "openid profile offline_access",
+            _ => "openid profile",
+        }
+    }
+
+    /// Handle from an Entra ID user principal name
+    fn entra_id_handle(claims: &Json, tenant: &str, upn: &str, domain: &str) -> E2eIdentityResult<QualifiedHandle> {
+        let is_multi_tenant = Self::ENTRA_ID_MULTI_TENANTS.contains(&tenant.to_lowercase().as_str());
+        // tenant domains cannot be compared with the 'tid' claim
+        let is_tenant_id = !is_multi_tenant && !tenant.contains('.');
+        match claims["tid"].as_str() {
+            Some(tid) if is_tenant_id && !tid.eq_ignore_ascii_case(tenant) => {
+                return Err(E2eIdentityError::IdTokenIdentityMismatch("tid"));
+            }
+            _ => {}
+        }
+        if claims["xms_edov"].as_bool() == Some(false) {
+            return Err(E2eIdentityError::UnverifiedIdTokenClaim(Self::HANDLE_CLAIM));
+        }
+        // guest users: 'alice_wire_wire.com#EXT#@host.onmicrosoft.com' stands for 'alice_wire@wire.com'
+        let guest = upn.split_once("#EXT#@").and_then(|(guest, _)| guest.rsplit_once('_'));
+        match guest.or_else(|| upn.rsplit_once('@')) {
+            Some((handle, upn_domain)) => Ok(Handle::from(handle).try_to_qualified(upn_domain)?),
+            None => Ok(Handle::from(upn).try_to_qualified(domain)?),
+        }
+    }
+
+    fn entra_id_endpoint(tenant: &str, path: &str) -> E2eIdentityResult<url::Url> {
+        Ok(format!("{}/{tenant}/{path}", Self::ENTRA_ID_AUTHORITY).parse()?)
+    }
+
+    /// [path] under [base], whether the latter ends with a '/' or not
+    fn endpoint(base: &url::Url, path: &str) -> E2eIdentityResult<url::Url> {
+        Ok(format!("{}/{path}", base.as_str().trim_end_matches('/')).parse()?)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use serde_json::json;
+    use wasm_bindgen_test::*;
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    pub fn keycloak() -> OidcProvider {
+        OidcProvider::Keycloak {
+            base_url: "https://keycloak.wire.com".parse().unwrap(),
+            realm: "wire".to_string(),
+        }
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_have_keycloak_endpoints() {
+        let keycloak = keycloak();
+        let realm = "https://keycloak.wire.com/realms/wire";
+        assert_eq!(keycloak.issuer().unwrap().as_str(), realm);
+        assert_eq!(
+            keycloak.discovery_url().unwrap().as_str(),
+            format!("{realm}/.well-known/openid-configuration")
+        );
+        assert_eq!(
+            keycloak.authorization_endpoint().unwrap().as_str(),
+            format!("{realm}/protocol/openid-connect/auth")
+        );
+        assert_eq!(
+            keycloak.token_endpoint().unwrap().as_str(),
+            format!("{realm}/protocol/openid-connect/token")
+        );
+        assert_eq!(
+            keycloak.jwks_uri().unwrap().as_str(),
+            format!("{realm}/protocol/openid-connect/certs")
+        );
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_have_dex_endpoints() {
+        let dex = OidcProvider::Dex {
+            issuer: "https://dex.wire.com/dex/".parse().unwrap(),
+        };
+        assert_eq!(dex.token_endpoint().unwrap().as_str(), "https://dex.wire.com/dex/token");
+        assert_eq!(dex.jwks_uri().unwrap().as_str(), "https://dex.wire.com/dex/keys");
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_map_keycloak_claims() {
+        let expected = OidcIdentity {
+            handle: "wireapp://%40alice_wire@wire.com".parse().unwrap(),
+            display_name: "Alice Smith".to_string(),
+        };
+        let claims = json!({"preferred_username": "wireapp://%40alice_wire@wire.com", "name": "Alice Smith"});
+        assert_eq!(keycloak().identity(&claims, "wire.com").unwrap(), expected);
+
+        // unqualified username, no 'name' claim
+        let claims = json!({"preferred_username": "alice_wire", "given_name": "Alice", "family_name": "Smith"});
+        assert_eq!(keycloak().identity(&claims, "wire.com").unwrap(), expected);
+
+        let claims = json!({"name": "Alice Smith"});
+        assert!(matches!(
+            keycloak().identity(&claims, "wire.com").unwrap_err(),
+            E2eIdentityError::MissingIdTokenClaim("preferred_username")
+        ));
+    }
+
+    pub fn entra_id() -> OidcProvider {
+        OidcProvider::EntraId {
+            tenant: "9188040d-6c67-4c5b-b112-36a304b66dad".to_string(),
+        }
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_have_entra_id_v2_endpoints() {
+        let entra_id = entra_id();
+        let tenant = "https://login.microsoftonline.com/9188040d-6c67-4c5b-b112-36a304b66dad";
+        assert_eq!(entra_id.issuer().unwrap().as_str(), format!("{tenant}/v2.0"));
+        assert_eq!(
+            entra_id.discovery_url().unwrap().as_str(),
+            format!("{tenant}/v2.0/.well-known/openid-configuration")
+        );
+        assert_eq!(
+            entra_id.authorization_endpoint().unwrap().as_str(),
+            format!("{tenant}/oauth2/v2.0/authorize")
+        );
+        assert_eq!(
+            entra_id.token_endpoint().unwrap().as_str(),
+            format!("{tenant}/oauth2/v2.0/token")
+        );
+        assert_eq!(
+            entra_id.jwks_uri().unwrap().as_str(),
+            format!("{tenant}/discovery/v2.0/keys")
+        );
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_map_entra_id_claims() {
+        let expected = OidcIdentity {
+            handle: "wireapp://%40alice_wire@wire.com".parse().unwrap(),
+            display_name: "Alice Smith".to_string(),
+        };
+        let tid = "9188040d-6c67-4c5b-b112-36a304b66dad";
+        // member user
+        let claims = json!({"preferred_username": "alice_wire@wire.com", "name": "Alice Smith", "tid": tid});
+        assert_eq!(entra_id().identity(&claims, "wire.com").unwrap(), expected);
+
+        // guest user, no 'name' claim
+        let claims = json!({
+            "preferred_username": "alice_wire_wire.com#EXT#@wire.onmicrosoft.com",
+            "given_name": "Alice",
+            "family_name": "Smith",
+            "tid": tid,
+            "xms_edov": true,
+        });
+        assert_eq!(entra_id().identity(&claims, "wire.com").unwrap(), expected);
+
+        // unverified domain owner
+        let claims = json!({"preferred_username": "alice_wire@wire.com", "name": "Alice Smith", "xms_edov": false});
+        assert!(matches!(
+            entra_id().identity(&claims, "wire.com").unwrap_err(),
+            E2eIdentityError::UnverifiedIdTokenClaim("preferred_username")
+        ));
+
+        // token from another tenant
+        let claims = json!({"preferred_username": "alice_wire@wire.com", "name": "Alice Smith", "tid": "f8cdef31-a31e-4b4a-93e4-5f571e91255a"});
+        assert!(matches!(
+            entra_id().identity(&claims, "wire.com").unwrap_err(),
+            E2eIdentityError::IdTokenIdentityMismatch("tid")
+        ));
+        let organizations = OidcProvider::EntraId {
+            tenant: "organizations".to_string(),
+        };
+        assert_eq!(organizations.identity(&claims, "wire.com").unwrap(), expected);
+
+        // user principal name from another domain
+        let claims = json!({"preferred_username": "alice_wire@evil.com", "name": "Alice Smith"});
+        assert!(matches!(
+            entra_id()
+                .verify_identity(&claims, "alice_wire", "Alice Smith", "wire.com")
+                .unwrap_err(),
+            E2eIdentityError::IdTokenIdentityMismatch("preferred_username")
+        ));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_use_pkce() {
+        let keycloak = keycloak();
+        let pkce = Pkce::generate();
+        let redirect_uri = "https://wire.com/callback".parse::<url::Url>().unwrap();
+        let url = keycloak
+            .authorization_url("wireapp", &redirect_uri, "state", "nonce", &pkce, &json!({}))
+            .unwrap();
+        let query = url
+            .query_pairs()
+            .into_owned()
+            .collect::<std::collections::HashMap<_, _>>();
+        assert_eq!(query["code_challenge"], pkce.code_challenge());
+        assert_eq!(query["code_challenge_method"], "S256");
+        assert_eq!(query["redirect_uri"], redirect_uri.as_str());
+
+        let request = keycloak.token_request("wireapp", "code", &redirect_uri, &pkce).unwrap();
+        assert_eq!(request.url, keycloak.token_endpoint().unwrap());
+        let body = url::form_urlencoded::parse(request.body.as_bytes())
+            .into_owned()
+            .collect::<std::collections::HashMap<_, _>>();
+        assert_eq!(body["code_verifier"], pkce.code_verifier());
+        assert_eq!(body["grant_type"], "authorization_code");
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_verify_identity() {
+        let claims = json!({"preferred_username": "wireapp://%40alice_wire@wire.com", "name": "Alice  Smith"});
+        let keycloak = keycloak();
+        assert!(keycloak
+            .verify_identity(&claims, "alice_wire", "Alice Smith", "wire.com")
+            .is_ok());
+        assert!(matches!(
+            keycloak
+                .verify_identity(&claims, "bob_wire", "Alice Smith", "wire.com")
+                .unwrap_err(),
+            E2eIdentityError::IdTokenIdentityMismatch("preferred_username")
+        ));
+        assert!(matches!(
+            keycloak
+                .verify_identity(&claims, "alice_wire", "Bob Smith", "wire.com")
+                .unwrap_err(),
+            E2eIdentityError::IdTokenIdentityMismatch("name")
+        ));
+    }
+}
@@ -0,0 +1,84 @@
+use base64::Engine;
+use rand::RngCore;
+use sha2::Digest;
+use zeroize::Zeroize;
+
+/// Proof Key for Code Exchange binding the OAuth authorization request to the token exchange, so
+/// that an intercepted authorization code cannot be redeemed by another app
+/// see [RFC 7636](https://www.rfc-editor.org/rfc/rfc7636)
+#[derive(Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Pkce {
+    code_verifier: String,
+}
+
+impl Pkce {
+    /// Only code challenge method supported, 'plain' being discouraged
+    pub const METHOD: &'static str = "S256";
+
+    /// Bytes of entropy of the code verifier, encoded as the 43 characters recommended by
+    /// [RFC 7636 Section 4.1](https://www.rfc-editor.org/rfc/rfc7636#section-4.1)
+    const VERIFIER_ENTROPY: usize = 32;
+
+    /// Generates a random code verifier
+    pub fn generate() -> Self {
+        let mut entropy = [0u8; Self::VERIFIER_ENTROPY];
+        rand::thread_rng().fill_bytes(&mut entropy);
+        let code_verifier = base64::prelude::BASE64_URL_SAFE_NO_PAD.encode(entropy);
+        entropy.zeroize();
+        Self { code_verifier }
+    }
+
+    /// Secret sent in the token exchange
+    pub fn code_verifier(&self) -> &str {
+        &self.code_verifier
+    }
+
+    /// `BASE64URL(SHA256(code_verifier))` sent in the authorization request
+    pub fn code_challenge(&self) -> String {
+        base64::prelude::BASE64_URL_SAFE_NO_PAD.encode(sha2::Sha256::digest(self.code_verifier.as_bytes()))
+    }
+}
+
+impl std::fmt::Debug for Pkce {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Pkce")
+            .field("code_challenge", &self.code_challenge())
+            .finish()
+    }
+}
+
+impl Drop for Pkce {
+    fn drop(&mut self) {
+        self.code_verifier.zeroize();
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use wasm_bindgen_test::*;
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_derive_s256_code_challenge() {
+        // see https://www.rfc-editor.org/rfc/rfc7636#appendix-B
+        let pkce = Pkce {
+            code_verifier: "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk".to_string(),
+        };
+        assert_eq!(pkce.code_challenge(), "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM");
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_generate_random_code_verifier() {
+        let (a, b) = (Pkce::generate(), Pkce::generate());
+        assert_ne!(a, b);
+        assert_eq!(a.code_verifier().len(), 43);
+        let unreserved = |c: char| c.is_ascii_alphanumeric() || "-._~".contains(c);
+        assert!(a.code_verifier().chars().all(unreserved));
+    }
+}
@@ -0,0 +1,275 @@
+use rusty_acme::prelude::{AcmeJws, HttpMethod, HttpRequest, HttpResponse};
+
+use crate::{prelude::*, Json};
+
+/// Records the exchanges of an enrollment to render them as a mermaid sequence diagram or as a
+/// plain-text transcript e.g. to document the flow or to attach it to a bug report. It observes
+/// an [E2eiEnrollmentFlow] through [E2eiEnrollmentFlow::with_observer] with an `Arc<FlowRecorder>`
+/// and the calls performed outside of it, to wire-server and to the identity provider, are
+/// recorded with [Self::record_request] and [Self::record_response].
+///
+/// Beware that the transcript holds the access and id tokens of the challenges.
+#[derive(Debug, Default)]
+pub struct FlowRecorder {
+    events: std::sync::Mutex<Vec<FlowEvent>>,
+}
+
+/// Party of an enrollment
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum FlowActor {
+    WireClient,
+    WireServer,
+    AcmeServer,
+    IdentityProvider,
+}
+
+/// What a [FlowRecorder] recorded
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum FlowEvent {
+    Request {
+        from: FlowActor,
+        to: FlowActor,
+        method: HttpMethod,
+        url: url::Url,
+        /// JSON body with the protected header and the payload of a JWS decoded, if any
+        body: Option<Json>,
+    },
+    Response {
+        from: FlowActor,
+        to: FlowActor,
+        status: u16,
+        /// JSON body, or a string e.g. for the PEM bundle of the certificate, if any
+        body: Option<Json>,
+    },
+    Progress(E2eiProgress),
+}
+
+impl FlowRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a request performed outside of the [E2eiEnrollmentFlow] e.g. to wire-server
+    pub fn record_request(&self, from: FlowActor, to: FlowActor, request: &HttpRequest) {
+        self.push(FlowEvent::Request {
+            from,
+            to,
+            method: request.method,
+            url: request.url.clone(),
+            body: Self::decode_body(&request.body),
+        });
+    }
+
+    /// Records the response to a request recorded with [Self::record_request]
+    pub fn record_response(&self, from: FlowActor, to: FlowActor, response: &HttpResponse) {
+        self.push(FlowEvent::Response {
+            from,
+            to,
+            status: response.status,
+            body: Self::decode_body(&response.body),
+        });
+    }
+
+    /// Everything recorded so far
+    pub fn events(&self) -> Vec<FlowEvent> {
+        self.lock().clone()
+    }
+
+    /// Mermaid sequence diagram of the requests, responses and milestones recorded so far
+    pub fn mermaid(&self) -> String {
+        let lines = self.lock().iter().map(FlowEvent::mermaid).collect::<Vec<_>>();
+        format!("sequenceDiagram\n    autonumber\n{}", lines.join("\n"))
+    }
+
+    /// Plain-text transcript of everything recorded so far, bodies included
+    pub fn transcript(&self) -> String {
+        let events = self.lock();
+        let lines = events
+            .iter()
+            .enumerate()
+            .map(|(i, event)| format!("{}. {}", i + 1, event.transcript()))
+            .collect::<Vec<_>>();
+        lines.join("\n")
+    }
+
+    fn push(&self, event: FlowEvent) {
+        self.lock().push(event);
+    }
+
+    /// A panic while recording must not prevent rendering
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<FlowEvent>> {
+        self.events.lock().unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    fn decode_body(body: &[u8]) -> Option<Json> {
+        if body.is_empty() {
+            return None;
+        }
+        let Ok(body) = serde_json::from_slice::<Json>(body) else {
+            return Some(Json::String(String::from_utf8_lossy(body).into_owned()));
+        };
+        let decoded = serde_json::from_value::<AcmeJws>(body.clone())
+            .ok()
+            .and_then(|jws| Some((jws.decoded_protected().ok()?, jws.decoded_payload().ok()?)));
+        match decoded {
+            Some((protected, payload)) => Some(serde_json::json!({"protected": protected, "payload": payload})),
+            None => Some(body),
+        }
+    }
+}
+
+impl E2eiObserver for FlowRecorder {
+    fn on_request(&self, request: &HttpRequest) {
+        self.record_request(FlowActor::WireClient, FlowActor::AcmeServer, request);
+    }
+
+    fn on_response(&self, _request: &HttpRequest, response: &HttpResponse) {
+        self.record_response(FlowActor::AcmeServer, FlowActor::WireClient, response);
+    }
+
+    fn on_progress(&self, _client_id: &str, progress: &E2eiProgress) {
+        self.push(FlowEvent::Progress(progress.clone()));
+    }
+}
+
+impl FlowEvent {
+    fn mermaid(&self) -> String {
+        match self {
+            Self::Request {
+                from, to, method, url, ..
+            } => {
+                let lock = if url.scheme() == "https" { "🔒 " } else { "" };
+                format!("    {from}->>+{to}: {lock}{} {}", Self::method(*method), url.path())
+            }
+            Self::Response { from, to, status, .. } => format!("    {from}->>-{to}: {status}"),
+            Self::Progress(progress) => {
+                let actor = FlowActor::WireClient;
+                format!("    {actor}->>{actor}: {}", Self::milestone(progress))
+            }
+        }
+    }
+
+    fn transcript(&self) -> String {
+        let body = |body: &Option<Json>| match body {
+            Some(Json::String(s)) => format!("\n{s}"),
+            Some(body) => format!("\n{}", serde_json::to_string_pretty(body).unwrap_or_default()),
+            None => String::new(),
+        };
+        match self {
+            Self::Request {
+                from,
+                to,
+                method,
+                url,
+                body: b,
+            } => format!("{from} -> {to}: {} {url}{}", Self::method(*method), body(b)),
+            Self::Response {
+                from,
+                to,
+                status,
+                body: b,
+            } => format!("{from} -> {to}: {status}{}", body(b)),
+            Self::Progress(progress) => Self::milestone(progress),
+        }
+    }
+
+    fn method(method: HttpMethod) -> &'static str {
+        match method {
+            HttpMethod::Get => "GET",
+            HttpMethod::Head => "HEAD",
+            HttpMethod::Post => "POST",
+        }
+    }
+
+    fn milestone(progress: &E2eiProgress) -> String {
+        match progress {
+            E2eiProgress::DirectoryFetched { .. } => "directory fetched".to_string(),
+            E2eiProgress::AccountCreated { .. } => "account created".to_string(),
+            E2eiProgress::OrderCreated { .. } => "order created".to_string(),
+            E2eiProgress::DpopChallengeValid { .. } => "DPoP challenge valid".to_string(),
+            E2eiProgress::OidcChallengeValid { .. } => "OIDC challenge valid".to_string(),
+            E2eiProgress::CertificateIssued { serial_number, .. } => format!("certificate {serial_number} issued"),
+        }
+    }
+}
+
+impl std::fmt::Display for FlowActor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::WireClient => "wire-client",
+            Self::WireServer => "wire-server",
+            Self::AcmeServer => "acme-server",
+            Self::IdentityProvider => "IdP",
+        };
+        write!(f, "{name}")
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::sync::Arc;
+
+    use wasm_bindgen_test::*;
+
+    use super::*;
+    use crate::{
+        enrollment::tests::{enrollment, server},
+        flow::tests::enroll,
+    };
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_render_enrollment() {
+        let recorder = Arc::new(FlowRecorder::new());
+        let mut flow = E2eiEnrollmentFlow::new(server()).with_observer(recorder.clone());
+        enroll(&mut flow, &mut enrollment());
+
+        let mermaid = recorder.mermaid();
+        let mut lines = mermaid.lines();
+        assert_eq!(lines.next(), Some("sequenceDiagram"));
+        assert_eq!(lines.next(), Some("    autonumber"));
+        assert_eq!(
+            lines.next(),
+            Some("    wire-client->>+acme-server: 🔒 GET /acme/wire/directory")
+        );
+        assert_eq!(lines.next(), Some("    acme-server->>-wire-client: 200"));
+        assert!(mermaid.contains("    wire-client->>wire-client: OIDC challenge valid"));
+
+        let transcript = recorder.transcript();
+        assert!(transcript.starts_with("1. wire-client -> acme-server: GET https://acme.wire.com/acme/wire/directory"));
+        // JWS are decoded
+        assert!(transcript.contains("\"termsOfServiceAgreed\": true"));
+        assert!(transcript.contains("-----BEGIN CERTIFICATE-----"));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_record_calls_outside_the_flow() {
+        let recorder = FlowRecorder::new();
+        let request = HttpRequest {
+            method: HttpMethod::Get,
+            url: "https://wire.com/clients/token/nonce".parse().unwrap(),
+            headers: vec![],
+            body: vec![],
+        };
+        recorder.record_request(FlowActor::WireClient, FlowActor::WireServer, &request);
+        let response = HttpResponse {
+            status: 200,
+            body: b"\"nonce\"".to_vec(),
+            ..Default::default()
+        };
+        recorder.record_response(FlowActor::WireServer, FlowActor::WireClient, &response);
+
+        assert_eq!(recorder.events().len(), 2);
+        assert_eq!(
+            recorder.mermaid(),
+            "sequenceDiagram\n    autonumber\n    wire-client->>+wire-server: 🔒 GET /clients/token/nonce\n    wire-server->>-wire-client: 200"
+        );
+        assert_eq!(
+            recorder.transcript(),
+            "1. wire-client -> wire-server: GET https://wire.com/clients/token/nonce\n2. wire-server -> wire-client: 200\nnonce"
+        );
+    }
+}
@@ -0,0 +1,106 @@
+use core::time::Duration;
+
+/// How an [crate::prelude::E2eiEnrollmentFlow] retries a step when the ACME server is unreachable,
+/// rate limits it or fails with a 5xx, and how long it keeps polling while the ACME server
+/// validates a challenge, the order or issues the certificate. The delay suggested by the ACME
+/// server in a 'Retry-After' header takes precedence over the backoff.
+///
+/// Creating the order and finalizing it are not idempotent: they are retried when the ACME server
+/// rejected them e.g. rate limited them, never after a transport error or a 5xx since the ACME
+/// server might have processed them.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct E2eiRetryPolicy {
+    /// Number of attempts of the step, the first one included. `1` means no retry
+    pub attempts: u32,
+    /// Delay before the first retry when the ACME server gave no hint. Doubles on every retry
+    pub backoff: Duration,
+    /// Gives up when waiting before the next attempt would end later than this after the first one
+    pub max_elapsed: Duration,
+}
+
+impl E2eiRetryPolicy {
+    /// Fails on the first error, the default
+    pub const NONE: Self = Self {
+        attempts: 1,
+        backoff: Duration::ZERO,
+        max_elapsed: Duration::ZERO,
+    };
+
+    /// Delay before the next attempt, `None` when giving up
+    ///
+    /// # Arguments
+    /// * `attempt` - number of attempts performed so far, starting at 1
+    /// * `elapsed` - since the first attempt
+    /// * `retry_after` - delay from the 'Retry-After' header of the last response, if any
+    pub fn delay(&self, attempt: u32, elapsed: Duration, retry_after: Option<Duration>) -> Option<Duration> {
+        if attempt >= self.attempts {
+            return None;
+        }
+        let delay = retry_after.unwrap_or_else(|| {
+            self.backoff
+                .checked_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+                .unwrap_or(Duration::MAX)
+        });
+        (elapsed.saturating_add(delay) <= self.max_elapsed).then_some(delay)
+    }
+}
+
+impl Default for E2eiRetryPolicy {
+    fn default() -> Self {
+        Self::NONE
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use wasm_bindgen_test::*;
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    const POLICY: E2eiRetryPolicy = E2eiRetryPolicy {
+        attempts: 4,
+        backoff: Duration::from_secs(1),
+        max_elapsed: Duration::from_secs(60),
+    };
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_not_retry_by_default() {
+        assert_eq!(E2eiRetryPolicy::default().delay(1, Duration::ZERO, None), None);
+        assert_eq!(
+            E2eiRetryPolicy::default().delay(1, Duration::ZERO, Some(Duration::ZERO)),
+            None
+        );
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_back_off_exponentially() {
+        let delays = (1..=4)
+            .map(|attempt| POLICY.delay(attempt, Duration::ZERO, None))
+            .collect::<Vec<_>>();
+        let expected = [Some(1), Some(2), Some(4), None].map(|d| d.map(Duration::from_secs));
+        assert_eq!(delays, expected);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_honor_retry_after() {
+        let retry_after = Some(Duration::from_secs(10));
+        assert_eq!(POLICY.delay(1, Duration::ZERO, retry_after), retry_after);
+        assert_eq!(POLICY.delay(4, Duration::ZERO, retry_after), None);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_give_up_after_max_elapsed() {
+        assert_eq!(
+            POLICY.delay(1, Duration::from_secs(59), None),
+            Some(Duration::from_secs(1))
+        );
+        assert_eq!(POLICY.delay(1, Duration::from_secs(60), None), None);
+        assert_eq!(POLICY.delay(1, Duration::ZERO, Some(Duration::from_secs(61))), None);
+    }
+}
@@ -0,0 +1,189 @@
+use std::{collections::HashMap, sync::Arc};
+
+use zeroize::Zeroizing;
+
+use crate::prelude::RefreshTokenStore;
+
+/// Secret of a client kept in a [SecretStore]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum SecretKind {
+    /// PEM private key of the ACME account
+    AcmeKey,
+    /// PEM private signature key the certificate certifies e.g. the MLS one
+    ClientKey,
+    /// Refresh token of the identity provider, see [ClientRefreshTokenStore]
+    RefreshToken,
+    /// Serialized [crate::prelude::E2eiEnrollment] to resume or renew, holding both private keys
+    PendingEnrollment,
+}
+
+/// Caller-provided storage of the secrets of each client e.g. in the OS keychain, so that
+/// platforms do not have to fork the enrollment to keep them safe. See [InMemorySecretStore] for
+/// a volatile one.
+pub trait SecretStore: Send + Sync {
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    /// The secret of [client_id], if any
+    ///
+    /// # Arguments
+    /// * `client_id` - e.g. `NDUyMGUyMmY2YjA3NGU3NjkyZjE1NjJjZTAwMmQ2NTQ:6add501bacd1d90e@example.com`
+    fn get(&self, client_id: &str, kind: SecretKind) -> Result<Option<Zeroizing<Vec<u8>>>, Self::Error>;
+
+    /// Stores the secret of [client_id], replacing the previous one
+    fn put(&self, client_id: &str, kind: SecretKind, secret: &[u8]) -> Result<(), Self::Error>;
+
+    /// Forgets the secret of [client_id], doing nothing when there is none
+    fn delete(&self, client_id: &str, kind: SecretKind) -> Result<(), Self::Error>;
+}
+
+impl<S: SecretStore + ?Sized> SecretStore for Arc<S> {
+    type Error = S::Error;
+
+    fn get(&self, client_id: &str, kind: SecretKind) -> Result<Option<Zeroizing<Vec<u8>>>, Self::Error> {
+        (**self).get(client_id, kind)
+    }
+
+    fn put(&self, client_id: &str, kind: SecretKind, secret: &[u8]) -> Result<(), Self::Error> {
+        (**self).put(client_id, kind, secret)
+    }
+
+    fn delete(&self, client_id: &str, kind: SecretKind) -> Result<(), Self::Error> {
+        (**self).delete(client_id, kind)
+    }
+}
+
+/// Volatile [SecretStore] e.g. for tests or short-lived provisioning tools. Secrets are zeroized
+/// when replaced or dropped.
+#[derive(Default)]
+pub struct InMemorySecretStore {
+    secrets: std::sync::Mutex<HashMap<(String, SecretKind), Zeroizing<Vec<u8>>>>,
+}
+
+impl InMemorySecretStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<(String, SecretKind), Zeroizing<Vec<u8>>>> {
+        self.secrets.lock().unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+impl std::fmt::Debug for InMemorySecretStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // only which secrets are held, never their value
+        let secrets = self.lock();
+        f.debug_struct("InMemorySecretStore")
+            .field("secrets", &secrets.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl SecretStore for InMemorySecretStore {
+    type Error = std::convert::Infallible;
+
+    fn get(&self, client_id: &str, kind: SecretKind) -> Result<Option<Zeroizing<Vec<u8>>>, Self::Error> {
+        Ok(self.lock().get(&(client_id.to_string(), kind)).cloned())
+    }
+
+    fn put(&self, client_id: &str, kind: SecretKind, secret: &[u8]) -> Result<(), Self::Error> {
+        self.lock()
+            .insert((client_id.to_string(), kind), Zeroizing::new(secret.to_vec()));
+        Ok(())
+    }
+
+    fn delete(&self, client_id: &str, kind: SecretKind) -> Result<(), Self::Error> {
+        self.lock().remove(&(client_id.to_string(), kind));
+        Ok(())
+    }
+}
+
+/// [RefreshTokenStore] keeping the refresh token of a client as its [SecretKind::RefreshToken]
+pub struct ClientRefreshTokenStore<'a, S: SecretStore + ?Sized> {
+    store: &'a S,
+    client_id: &'a str,
+}
+
+impl<'a, S: SecretStore + ?Sized> ClientRefreshTokenStore<'a, S> {
+    pub fn new(store: &'a S, client_id: &'a str) -> Self {
+        Self { store, client_id }
+    }
+}
+
+impl<S: SecretStore + ?Sized> RefreshTokenStore for ClientRefreshTokenStore<'_, S> {
+    type Error = S::Error;
+
+    /// A refresh token which is not UTF-8 is ignored, requiring a new login
+    fn refresh_token(&self) -> Result<Option<String>, Self::Error> {
+        let refresh_token = self.store.get(self.client_id, SecretKind::RefreshToken)?;
+        Ok(refresh_token.and_then(|t| String::from_utf8(t.to_vec()).ok()))
+    }
+
+    fn set_refresh_token(&mut self, refresh_token: Option<String>) -> Result<(), Self::Error> {
+        match refresh_token.map(Zeroizing::new) {
+            Some(refresh_token) => self
+                .store
+                .put(self.client_id, SecretKind::RefreshToken, refresh_token.as_bytes()),
+            None => self.store.delete(self.client_id, SecretKind::RefreshToken),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use wasm_bindgen_test::*;
+
+    use super::*;
+    use crate::enrollment::tests::CLIENT_ID;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_keep_secrets_per_client_and_kind() {
+        let store = InMemorySecretStore::new();
+        store.put(CLIENT_ID, SecretKind::AcmeKey, b"acme").unwrap();
+        store.put(CLIENT_ID, SecretKind::ClientKey, b"client").unwrap();
+        store.put("other", SecretKind::AcmeKey, b"other").unwrap();
+        assert_eq!(
+            store.get(CLIENT_ID, SecretKind::AcmeKey).unwrap().as_deref(),
+            Some(&b"acme".to_vec())
+        );
+        assert_eq!(
+            store.get(CLIENT_ID, SecretKind::ClientKey).unwrap().as_deref(),
+            Some(&b"client".to_vec())
+        );
+
+        store.put(CLIENT_ID, SecretKind::AcmeKey, b"rotated").unwrap();
+        store.delete(CLIENT_ID, SecretKind::ClientKey).unwrap();
+        assert_eq!(
+            store.get(CLIENT_ID, SecretKind::AcmeKey).unwrap().as_deref(),
+            Some(&b"rotated".to_vec())
+        );
+        assert_eq!(store.get(CLIENT_ID, SecretKind::ClientKey).unwrap(), None);
+        assert!(store.get("other", SecretKind::AcmeKey).unwrap().is_some());
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_not_leak_secrets_in_debug() {
+        let store = InMemorySecretStore::new();
+        store.put(CLIENT_ID, SecretKind::AcmeKey, b"top secret").unwrap();
+        let debug = format!("{store:?}");
+        assert!(debug.contains(CLIENT_ID) && debug.contains("AcmeKey"));
+        assert!(!debug.contains("116, 111, 112") && !debug.contains("top secret"));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_keep_refresh_token_in_secret_store() {
+        let store = InMemorySecretStore::new();
+        let mut refresh_tokens = ClientRefreshTokenStore::new(&store, CLIENT_ID);
+        assert_eq!(refresh_tokens.refresh_token().unwrap(), None);
+        refresh_tokens.set_refresh_token(Some("refresh".to_string())).unwrap();
+        assert_eq!(refresh_tokens.refresh_token().unwrap().as_deref(), Some("refresh"));
+        assert!(store.get(CLIENT_ID, SecretKind::RefreshToken).unwrap().is_some());
+        refresh_tokens.set_refresh_token(None).unwrap();
+        assert_eq!(store.get(CLIENT_ID, SecretKind::RefreshToken).unwrap(), None);
+    }
+}
@@ -26,11 +26,93 @@ impl TryFrom<rusty_acme::prelude::AcmeAccount> for E2eiAcmeAccount {
     }
 }
 
+/// Signed request of an [crate::prelude::E2eiEnrollment] step, to POST to [Self::url] with the
+/// 'application/jose+json' content type
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct E2eiAcmeRequest {
+    pub url: url::Url,
+    pub body: Json,
+}
+
+impl E2eiAcmeRequest {
+    pub(crate) fn new(url: url::Url, body: Json) -> Self {
+        Self { url, body }
+    }
+}
+
+impl From<E2eiAcmeRequest> for rusty_acme::prelude::HttpRequest {
+    fn from(request: E2eiAcmeRequest) -> Self {
+        Self {
+            method: rusty_acme::prelude::HttpMethod::Post,
+            url: request.url,
+            headers: vec![("Content-Type".to_string(), "application/jose+json".to_string())],
+            body: request.body.to_string().into_bytes(),
+        }
+    }
+}
+
+/// Custom claims of the JWT the expiring signature key signs when renewing, linking it to the new
+/// signature key. Its 'sub' is the client id URI and its 'x5c' header holds the certificate of the
+/// expiring key, see [crate::prelude::E2eiEnrollment::cross_signature].
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct E2eiCrossSignature {
+    /// signature public key of the renewed credential
+    pub new_key: jwt_simple::prelude::Jwk,
+}
+
+impl E2eiCrossSignature {
+    /// JWT header 'typ'
+    pub const TYP: &'static str = "wire-cross-sig+jwt";
+    /// Claim of the DPoP token holding the cross signature, see
+    /// [crate::prelude::E2eiEnrollment::with_cross_signature_claim]
+    pub const DPOP_CLAIM: &'static str = "cross_sig";
+}
+
+/// Everything a component obtaining the ID token out of band e.g. the system account manager
+/// needs, see [crate::prelude::E2eiEnrollment::deferred_oidc_challenge]
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct E2eiDeferredOidcChallenge {
+    /// Identity provider expected to issue the ID token, the target of the OIDC challenge
+    pub issuer: url::Url,
+    /// To have in the 'acme_aud' claim of the ID token, the URL of the OIDC challenge
+    pub audience: url::Url,
+    /// To have in the 'keyauth' claim of the ID token
+    pub keyauth: String,
+    /// 'claims' parameter of the authorization request asking the identity provider for both, see
+    /// [OpenID Connect Core Section 5.5](https://openid.net/specs/openid-connect-core-1_0.html#ClaimsParameter)
+    pub claims: Json,
+}
+
+/// Exchange of an authorization code for the ID token, to POST to [Self::url] with the
+/// 'application/x-www-form-urlencoded' content type
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OidcTokenRequest {
+    pub url: url::Url,
+    pub body: String,
+}
+
+impl From<OidcTokenRequest> for rusty_acme::prelude::HttpRequest {
+    fn from(request: OidcTokenRequest) -> Self {
+        Self {
+            method: rusty_acme::prelude::HttpMethod::Post,
+            url: request.url,
+            headers: vec![(
+                "Content-Type".to_string(),
+                "application/x-www-form-urlencoded".to_string(),
+            )],
+            body: request.body.into_bytes(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct E2eiNewAcmeOrder {
     pub delegate: Json,
-    pub authorizations: [url::Url; 2],
+    pub authorizations: Vec<url::Url>,
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -102,7 +102,7 @@ fn e2e_api() {
               ],
               "expires": "2032-02-10T14:59:20Z",
               "notBefore": "2013-02-09T14:59:20.442908Z",
-              "notAfter": "2032-02-09T15:59:20.442908Z"
+              "notAfter": "2034-02-09T15:59:20.442908Z"
             });
 
             let order_url = "https://example.com/acme/wire-acme/order/C7uOXEgg5KPMPtbdE3aVMzv7cJjwUVth"
@@ -202,22 +202,22 @@ fn e2e_api() {
             let htm = Htm::Post;
             let htu: Htu = dpop_chall.target.clone().into();
             let alice = ClientId::try_from_qualified(&qualified_client_id).unwrap();
-            let access_token = RustyJwtTools::generate_access_token(
-                client_dpop_token.as_str(),
-                &alice,
-                qualified_handle.clone(),
-                team.into(),
-                backend_nonce,
-                htu,
-                htm,
-                leeway,
-                max_expiration,
-                backend_kp.clone(),
-                enrollment.hash_alg,
-                5,
-                core::time::Duration::from_secs(360),
-            )
-            .unwrap();
+            let access_token = AccessTokenBuilder::default()
+                .dpop_proof(client_dpop_token.as_str())
+                .client_id(&alice)
+                .handle(qualified_handle.clone())
+                .team(team.into())
+                .backend_nonce(backend_nonce)
+                .htu(htu)
+                .htm(htm)
+                .max_skew_secs(leeway)
+                .max_expiration(max_expiration)
+                .backend_keys(backend_kp.clone())
+                .hash_algorithm(enrollment.hash_alg)
+                .api_version(5)
+                .expiry(core::time::Duration::from_secs(360))
+                .build()
+                .unwrap();
             access_token
         };
 
@@ -255,7 +255,7 @@ fn e2e_api() {
                     issuer,
                     hash_algorithm,
                     kid,
-                    key: backend_pk_file,
+                    key: vec![backend_pk_file],
                     api_version: 5,
                 }
                 .execute()
@@ -317,7 +317,7 @@ fn e2e_api() {
               ],
               "expires": "2032-02-10T14:59:20Z",
               "notBefore": "2013-02-09T14:59:20.442908Z",
-              "notAfter": "2032-02-09T15:59:20.442908Z"
+              "notAfter": "2034-02-09T15:59:20.442908Z"
             });
             let order = enrollment.acme_check_order_response(resp).unwrap();
             // verify ready
@@ -350,7 +350,7 @@ fn e2e_api() {
               ],
               "expires": "2032-02-10T14:59:20Z",
               "notBefore": "2013-02-09T14:59:20.442908Z",
-              "notAfter": "2032-02-09T15:59:20.442908Z"
+              "notAfter": "2034-02-09T15:59:20.442908Z"
             });
             let finalize = enrollment.acme_finalize_response(resp).unwrap();
             (finalize, prev_nonce())
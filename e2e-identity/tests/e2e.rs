@@ -442,22 +442,22 @@ mod dpop_challenge {
                     .unwrap();
 
                     let backend_kp: Pem = test.backend_kp.clone();
-                    let access_token = RustyJwtTools::generate_access_token(
-                        &client_dpop_token,
-                        &client_id,
-                        handle,
-                        test.team.clone().into(),
-                        backend_nonce,
-                        htu,
-                        Htm::Post,
-                        360,
-                        2136351646,
-                        backend_kp,
-                        test.hash_alg,
-                        5,
-                        core::time::Duration::from_secs(360),
-                    )
-                    .unwrap();
+                    let access_token = AccessTokenBuilder::default()
+                        .dpop_proof(&client_dpop_token)
+                        .client_id(&client_id)
+                        .handle(handle)
+                        .team(test.team.clone().into())
+                        .backend_nonce(backend_nonce)
+                        .htu(htu)
+                        .htm(Htm::Post)
+                        .max_skew_secs(360)
+                        .max_expiration(2136351646)
+                        .backend_keys(backend_kp)
+                        .hash_algorithm(test.hash_alg)
+                        .api_version(5)
+                        .expiry(core::time::Duration::from_secs(360))
+                        .build()
+                        .unwrap();
                     Ok((test, access_token))
                 })
             }),
@@ -520,22 +520,22 @@ mod dpop_challenge {
                     .unwrap();
 
                     let backend_kp: Pem = test.backend_kp.clone();
-                    let access_token = RustyJwtTools::generate_access_token(
-                        &client_dpop_token,
-                        &client_id,
-                        handle,
-                        test.team.clone().into(),
-                        backend_nonce,
-                        htu,
-                        Htm::Post,
-                        360,
-                        2136351646,
-                        backend_kp,
-                        test.hash_alg,
-                        5,
-                        core::time::Duration::from_secs(360),
-                    )
-                    .unwrap();
+                    let access_token = AccessTokenBuilder::default()
+                        .dpop_proof(&client_dpop_token)
+                        .client_id(&client_id)
+                        .handle(handle)
+                        .team(test.team.clone().into())
+                        .backend_nonce(backend_nonce)
+                        .htu(htu)
+                        .htm(Htm::Post)
+                        .max_skew_secs(360)
+                        .max_expiration(2136351646)
+                        .backend_keys(backend_kp)
+                        .hash_algorithm(test.hash_alg)
+                        .api_version(5)
+                        .expiry(core::time::Duration::from_secs(360))
+                        .build()
+                        .unwrap();
                     Ok((test, access_token))
                 })
             }),
@@ -626,22 +626,22 @@ mod dpop_challenge {
                     .unwrap();
 
                     let backend_kp: Pem = test.backend_kp.clone();
-                    let access_token = RustyJwtTools::generate_access_token(
-                        &client_dpop_token,
-                        &client_id,
-                        handle,
-                        test.team.clone().into(),
-                        backend_nonce,
-                        htu,
-                        Htm::Post,
-                        360,
-                        2136351646,
-                        backend_kp,
-                        test.hash_alg,
-                        5,
-                        core::time::Duration::from_secs(360),
-                    )
-                    .unwrap();
+                    let access_token = AccessTokenBuilder::default()
+                        .dpop_proof(&client_dpop_token)
+                        .client_id(&client_id)
+                        .handle(handle)
+                        .team(test.team.clone().into())
+                        .backend_nonce(backend_nonce)
+                        .htu(htu)
+                        .htm(Htm::Post)
+                        .max_skew_secs(360)
+                        .max_expiration(2136351646)
+                        .backend_keys(backend_kp)
+                        .hash_algorithm(test.hash_alg)
+                        .api_version(5)
+                        .expiry(core::time::Duration::from_secs(360))
+                        .build()
+                        .unwrap();
                     Ok((test, access_token))
                 })
             }),
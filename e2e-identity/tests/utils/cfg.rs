@@ -9,6 +9,7 @@ use testcontainers::clients::Cli;
 
 use rusty_acme::prelude::{AcmeAccount, AcmeAuthz, AcmeChallenge, AcmeDirectory, AcmeFinalize, AcmeOrder};
 use rusty_jwt_tools::{jwk::TryIntoJwk, prelude::*};
+use wire_e2e_identity::prelude::OidcProvider as IdpProvider;
 
 use crate::utils::{
     ctx::ctx_store_http_client,
@@ -127,12 +128,15 @@ impl<'a> E2eTest<'a> {
                 )
             }
             OidcProvider::Keycloak => {
-                let realm = KeycloakImage::REALM;
+                let keycloak = |port: u16| IdpProvider::Keycloak {
+                    base_url: format!("{idp_base}:{port}").parse().unwrap(),
+                    realm: KeycloakImage::REALM.to_string(),
+                };
                 // this will be called from Docker network so we don't want to use the host port
                 let docker_port = KeycloakImage::HTTP_PORT;
                 (
-                    format!("{idp_base}:{idp_host_port}/realms/{realm}"),
-                    format!("{idp_base}:{docker_port}/realms/{realm}/protocol/openid-connect/certs",),
+                    keycloak(idp_host_port).issuer().unwrap().to_string(),
+                    keycloak(docker_port).jwks_uri().unwrap().to_string(),
                 )
             }
             OidcProvider::Google => (
@@ -343,12 +347,13 @@ impl<'a> E2eTest<'a> {
         };
         let uri = match self.oidc_provider {
             OidcProvider::Dex => format!("{authz_server_uri}/dex/.well-known/openid-configuration"),
-            OidcProvider::Keycloak => {
-                format!(
-                    "{authz_server_uri}/realms/{}/.well-known/openid-configuration",
-                    KeycloakImage::REALM
-                )
+            OidcProvider::Keycloak => IdpProvider::Keycloak {
+                base_url: authz_server_uri.parse().unwrap(),
+                realm: KeycloakImage::REALM.to_string(),
             }
+            .discovery_url()
+            .unwrap()
+            .to_string(),
             OidcProvider::Google => "https://accounts.google.com/.well-known/openid-configuration".to_string(),
         };
         let resp = self.client.get(&uri).send().await.unwrap();
@@ -135,7 +135,7 @@ impl<'a> E2eTest<'a> {
     ) -> TestResult<(AcmeAccount, String)> {
         // see https://www.rfc-editor.org/rfc/rfc8555.html#section-7.3
         self.display_step("create a new account");
-        let account_req = RustyAcme::new_account_request(directory, self.alg, &self.acme_kp, previous_nonce)?;
+        let account_req = RustyAcme::new_account_request(directory, self.alg, &self.acme_kp, None, previous_nonce)?;
         let req = self.client.acme_req(&directory.new_account, &account_req)?;
         self.display_req(
             Actor::WireClient,
@@ -179,6 +179,7 @@ impl<'a> E2eTest<'a> {
             self.sub.clone(),
             &self.handle.clone().into(),
             expiry,
+            Default::default(),
             directory,
             account,
             self.alg,
@@ -66,22 +66,22 @@ fn generate_access_token(dpop: &str, client_id: ClientId, nonce: BackendNonce) -
 
     let leeway = 2;
     let max_expiry = 2082008461;
-    let access_token = RustyJwtTools::generate_access_token(
-        dpop,
-        &client_id,
-        handle,
-        team,
-        nonce,
-        htu,
-        Htm::Post,
-        leeway,
-        max_expiry,
-        backend_kp,
-        hash_alg,
-        5,
-        core::time::Duration::from_secs(360),
-    )
-    .unwrap();
+    let access_token = AccessTokenBuilder::default()
+        .dpop_proof(dpop)
+        .client_id(&client_id)
+        .handle(handle)
+        .team(team)
+        .backend_nonce(nonce)
+        .htu(htu)
+        .htm(Htm::Post)
+        .max_skew_secs(leeway)
+        .max_expiration(max_expiry)
+        .backend_keys(backend_kp)
+        .hash_algorithm(hash_alg)
+        .api_version(5)
+        .expiry(core::time::Duration::from_secs(360))
+        .build()
+        .unwrap();
     serde_json::json!({
         "expires_in": 2082008461,
         "token": access_token,
@@ -0,0 +1,427 @@
+//! C-FFI over the ACME request builders and response parsers of [rusty_acme], for wire-server to
+//! drive enrollment test flows. Arguments and results are JSON, as exchanged with the ACME server,
+//! except for plain strings e.g. nonces and PEM keys.
+//!
+//! Ownership:
+//! * arguments are borrowed for the duration of the call only, they remain owned by the caller
+//! * every function returns a heap allocated [AcmeFfiResult] owned by the caller, which has to
+//! release it exactly once with [RustyAcmeFfi::acme_free_result]. The strings it points to are
+//! released along with it: copy them before
+//! * a null argument or one which is not UTF-8 fails with [AcmeFfiError::FfiError]
+
+use std::{
+    ffi::{CStr, CString},
+    os::raw::c_char,
+};
+
+use base64::Engine;
+use rusty_acme::prelude::*;
+use rusty_jwt_tools::prelude::*;
+
+pub struct RustyAcmeFfi;
+
+/// Outcome of any function of [RustyAcmeFfi]. Its layout is stable.
+#[repr(C)]
+#[derive(Debug)]
+pub struct AcmeFfiResult {
+    /// `0` on success, an [AcmeFfiError] otherwise
+    pub error: u8,
+    /// NUL-terminated JSON on success, null otherwise
+    pub value: *mut c_char,
+    /// NUL-terminated description of the error, null on success
+    pub message: *mut c_char,
+}
+
+/// Signature algorithm of a key passed across the FFI
+#[derive(Debug, Copy, Clone)]
+#[repr(u8)]
+pub enum AcmeFfiAlgorithm {
+    P256 = 1,
+    P384 = 2,
+    Ed25519 = 3,
+}
+
+#[derive(Debug, Copy, Clone)]
+#[repr(u8)]
+pub enum AcmeFfiError {
+    /// Unmapped error
+    UnknownError = 1,
+    /// Error at FFI boundary: a null pointer, a string which is not UTF-8 or an unknown algorithm
+    FfiError = 2,
+    /// An argument is not the expected JSON or is invalid
+    InvalidArgument = 3,
+    /// The ACME server refused the request, the message holds its problem document
+    Problem = 4,
+    /// The ACME server is rate limiting requests
+    RateLimited = 5,
+    /// The ACME server rejected the nonce of the request
+    BadNonce = 6,
+    /// The ACME server responded something which is not a valid ACME resource
+    InvalidResponse = 7,
+    /// The ACME resource is in an unexpected state e.g. an invalid challenge or a pending order
+    InvalidState = 8,
+    /// The certificate is invalid or does not match the order
+    InvalidCertificate = 9,
+    /// Error while signing with the given key
+    JwtError = 10,
+}
+
+/// Error code along with its description
+struct Failure(AcmeFfiError, String);
+
+type FfiResult<T> = Result<T, Failure>;
+
+impl From<RustyAcmeError> for Failure {
+    fn from(e: RustyAcmeError) -> Self {
+        let code = match &e {
+            RustyAcmeError::Problem(_) => AcmeFfiError::Problem,
+            RustyAcmeError::RateLimited { .. } => AcmeFfiError::RateLimited,
+            RustyAcmeError::BadNonce => AcmeFfiError::BadNonce,
+            RustyAcmeError::JsonError(_) | RustyAcmeError::SmallstepImplementationError(_) => {
+                AcmeFfiError::InvalidResponse
+            }
+            RustyAcmeError::ClientImplementationError(_)
+            | RustyAcmeError::AccountError(_)
+            | RustyAcmeError::OrderError(_)
+            | RustyAcmeError::AuthzError(_)
+            | RustyAcmeError::ChallengeError(_)
+            | RustyAcmeError::FinalizeError(_) => AcmeFfiError::InvalidState,
+            RustyAcmeError::InvalidCertificate(_)
+            | RustyAcmeError::X509CheckError(_)
+            | RustyAcmeError::PemError(_)
+            | RustyAcmeError::DerError(_) => AcmeFfiError::InvalidCertificate,
+            RustyAcmeError::JwtError(_) | RustyAcmeError::RawJwtError(_) | RustyAcmeError::SignatureError(_) => {
+                AcmeFfiError::JwtError
+            }
+            RustyAcmeError::UrlError(_) => AcmeFfiError::InvalidArgument,
+            _ => AcmeFfiError::UnknownError,
+        };
+        Self(code, e.to_string())
+    }
+}
+
+impl From<RustyJwtError> for Failure {
+    fn from(e: RustyJwtError) -> Self {
+        Self(AcmeFfiError::InvalidArgument, e.to_string())
+    }
+}
+
+impl RustyAcmeFfi {
+    /// see [RustyAcme::acme_directory_response]
+    #[no_mangle]
+    pub extern "C" fn acme_directory_response(response: *const c_char) -> *mut AcmeFfiResult {
+        Self::call(|| Self::json(RustyAcme::acme_directory_response(Self::json_arg(response)?)?))
+    }
+
+    /// see [RustyAcme::new_account_request]
+    #[no_mangle]
+    pub extern "C" fn acme_new_account_request(
+        directory: *const c_char,
+        alg: u8,
+        kp: *const c_char,
+        previous_nonce: *const c_char,
+    ) -> *mut AcmeFfiResult {
+        Self::call(|| {
+            let directory = Self::json_arg::<AcmeDirectory>(directory)?;
+            let (alg, kp) = (Self::alg_arg(alg)?, Self::pem_arg(kp)?);
+            let nonce = Self::str_arg(previous_nonce)?.to_string();
+            Self::json(RustyAcme::new_account_request(&directory, alg, &kp, None, nonce)?)
+        })
+    }
+
+    /// see [RustyAcme::new_account_response]
+    #[no_mangle]
+    pub extern "C" fn acme_new_account_response(response: *const c_char) -> *mut AcmeFfiResult {
+        Self::call(|| Self::json(RustyAcme::new_account_response(Self::json_arg(response)?)?))
+    }
+
+    /// see [RustyAcme::new_order_request]
+    ///
+    /// # Arguments
+    /// * `client_id` - e.g. `NDUyMGUyMmY2YjA3NGU3NjkyZjE1NjJjZTAwMmQ2NTQ:6add501bacd1d90e@example.com`
+    /// * `handle` - without the domain e.g. `alice_wire`
+    /// * `expiry_secs` - validity of the certificate from now
+    #[no_mangle]
+    #[allow(clippy::too_many_arguments)]
+    pub extern "C" fn acme_new_order_request(
+        display_name: *const c_char,
+        client_id: *const c_char,
+        handle: *const c_char,
+        expiry_secs: u64,
+        directory: *const c_char,
+        account: *const c_char,
+        alg: u8,
+        kp: *const c_char,
+        previous_nonce: *const c_char,
+    ) -> *mut AcmeFfiResult {
+        Self::call(|| {
+            let display_name = Self::str_arg(display_name)?;
+            let client_id = ClientId::try_from_qualified(Self::str_arg(client_id)?)?;
+            let handle = Handle::from(Self::str_arg(handle)?);
+            let expiry = core::time::Duration::from_secs(expiry_secs);
+            let directory = Self::json_arg::<AcmeDirectory>(directory)?;
+            let account = Self::json_arg::<AcmeAccount>(account)?;
+            let (alg, kp) = (Self::alg_arg(alg)?, Self::pem_arg(kp)?);
+            let nonce = Self::str_arg(previous_nonce)?.to_string();
+            Self::json(RustyAcme::new_order_request(
+                display_name,
+                client_id,
+                &handle,
+                expiry,
+                AcmeOrderExtensions::default(),
+                &directory,
+                &account,
+                alg,
+                &kp,
+                nonce,
+            )?)
+        })
+    }
+
+    /// see [RustyAcme::new_order_response]
+    #[no_mangle]
+    pub extern "C" fn acme_new_order_response(response: *const c_char) -> *mut AcmeFfiResult {
+        Self::call(|| Self::json(RustyAcme::new_order_response(Self::json_arg(response)?)?))
+    }
+
+    /// see [RustyAcme::new_authz_request]
+    ///
+    /// # Arguments
+    /// * `url` - one of the authorizations of the order
+    #[no_mangle]
+    pub extern "C" fn acme_new_authz_request(
+        url: *const c_char,
+        account: *const c_char,
+        alg: u8,
+        kp: *const c_char,
+        previous_nonce: *const c_char,
+    ) -> *mut AcmeFfiResult {
+        Self::call(|| {
+            let url = Self::url_arg(url)?;
+            let account = Self::json_arg::<AcmeAccount>(account)?;
+            let (alg, kp) = (Self::alg_arg(alg)?, Self::pem_arg(kp)?);
+            let nonce = Self::str_arg(previous_nonce)?.to_string();
+            Self::json(RustyAcme::new_authz_request(&url, &account, alg, &kp, nonce)?)
+        })
+    }
+
+    /// see [RustyAcme::new_authz_response]
+    #[no_mangle]
+    pub extern "C" fn acme_new_authz_response(response: *const c_char) -> *mut AcmeFfiResult {
+        Self::call(|| Self::json(RustyAcme::new_authz_response(Self::json_arg(response)?)?))
+    }
+
+    /// see [RustyAcme::dpop_chall_request]
+    #[no_mangle]
+    pub extern "C" fn acme_dpop_chall_request(
+        access_token: *const c_char,
+        dpop_chall: *const c_char,
+        account: *const c_char,
+        alg: u8,
+        kp: *const c_char,
+        previous_nonce: *const c_char,
+    ) -> *mut AcmeFfiResult {
+        Self::call(|| {
+            let access_token = Self::str_arg(access_token)?.to_string();
+            let chall = Self::json_arg::<AcmeChallenge>(dpop_chall)?;
+            let account = Self::json_arg::<AcmeAccount>(account)?;
+            let (alg, kp) = (Self::alg_arg(alg)?, Self::pem_arg(kp)?);
+            let nonce = Self::str_arg(previous_nonce)?.to_string();
+            Self::json(RustyAcme::dpop_chall_request(
+                access_token,
+                chall,
+                &account,
+                alg,
+                &kp,
+                nonce,
+            )?)
+        })
+    }
+
+    /// see [RustyAcme::oidc_chall_request]
+    #[no_mangle]
+    pub extern "C" fn acme_oidc_chall_request(
+        id_token: *const c_char,
+        oidc_chall: *const c_char,
+        account: *const c_char,
+        alg: u8,
+        kp: *const c_char,
+        previous_nonce: *const c_char,
+    ) -> *mut AcmeFfiResult {
+        Self::call(|| {
+            let id_token = Self::str_arg(id_token)?.to_string();
+            let chall = Self::json_arg::<AcmeChallenge>(oidc_chall)?;
+            let account = Self::json_arg::<AcmeAccount>(account)?;
+            let (alg, kp) = (Self::alg_arg(alg)?, Self::pem_arg(kp)?);
+            let nonce = Self::str_arg(previous_nonce)?.to_string();
+            Self::json(RustyAcme::oidc_chall_request(
+                id_token, chall, &account, alg, &kp, nonce,
+            )?)
+        })
+    }
+
+    /// see [RustyAcme::new_chall_response]
+    #[no_mangle]
+    pub extern "C" fn acme_new_chall_response(response: *const c_char) -> *mut AcmeFfiResult {
+        Self::call(|| Self::json(RustyAcme::new_chall_response(Self::json_arg(response)?)?))
+    }
+
+    /// see [RustyAcme::check_order_request]
+    ///
+    /// # Arguments
+    /// * `order_url` - 'location' header of the new order response
+    #[no_mangle]
+    pub extern "C" fn acme_check_order_request(
+        order_url: *const c_char,
+        account: *const c_char,
+        alg: u8,
+        kp: *const c_char,
+        previous_nonce: *const c_char,
+    ) -> *mut AcmeFfiResult {
+        Self::call(|| {
+            let order_url = Self::url_arg(order_url)?;
+            let account = Self::json_arg::<AcmeAccount>(account)?;
+            let (alg, kp) = (Self::alg_arg(alg)?, Self::pem_arg(kp)?);
+            let nonce = Self::str_arg(previous_nonce)?.to_string();
+            Self::json(RustyAcme::check_order_request(order_url, &account, alg, &kp, nonce)?)
+        })
+    }
+
+    /// see [RustyAcme::check_order_response]
+    #[no_mangle]
+    pub extern "C" fn acme_check_order_response(response: *const c_char) -> *mut AcmeFfiResult {
+        Self::call(|| Self::json(RustyAcme::check_order_response(Self::json_arg(response)?)?))
+    }
+
+    /// see [RustyAcme::finalize_req]
+    ///
+    /// # Arguments
+    /// * `alg`/`kp` - account key, signing the request
+    /// * `signing_kp` - client key, signing the CSR
+    #[no_mangle]
+    pub extern "C" fn acme_finalize_request(
+        order: *const c_char,
+        account: *const c_char,
+        alg: u8,
+        kp: *const c_char,
+        signing_kp: *const c_char,
+        previous_nonce: *const c_char,
+    ) -> *mut AcmeFfiResult {
+        Self::call(|| {
+            let order = Self::json_arg::<AcmeOrder>(order)?;
+            let account = Self::json_arg::<AcmeAccount>(account)?;
+            let (alg, kp) = (Self::alg_arg(alg)?, Self::pem_arg(kp)?);
+            let signing_kp = Self::pem_arg(signing_kp)?;
+            let nonce = Self::str_arg(previous_nonce)?.to_string();
+            Self::json(RustyAcme::finalize_req(&order, &account, alg, &kp, &signing_kp, nonce)?)
+        })
+    }
+
+    /// see [RustyAcme::finalize_response]
+    #[no_mangle]
+    pub extern "C" fn acme_finalize_response(response: *const c_char) -> *mut AcmeFfiResult {
+        Self::call(|| Self::json(RustyAcme::finalize_response(Self::json_arg(response)?)?))
+    }
+
+    /// see [RustyAcme::certificate_req]
+    #[no_mangle]
+    pub extern "C" fn acme_certificate_request(
+        finalize: *const c_char,
+        account: *const c_char,
+        alg: u8,
+        kp: *const c_char,
+        previous_nonce: *const c_char,
+    ) -> *mut AcmeFfiResult {
+        Self::call(|| {
+            let finalize = Self::json_arg::<AcmeFinalize>(finalize)?;
+            let account = Self::json_arg::<AcmeAccount>(account)?;
+            let (alg, kp) = (Self::alg_arg(alg)?, Self::pem_arg(kp)?);
+            let nonce = Self::str_arg(previous_nonce)?.to_string();
+            Self::json(RustyAcme::certificate_req(finalize, account, alg, &kp, nonce)?)
+        })
+    }
+
+    /// see [RustyAcme::certificate_response]. Its value is a JSON array of the base64 encoded DER
+    /// certificates, leaf first.
+    ///
+    /// # Arguments
+    /// * `response` - PEM bundle
+    #[no_mangle]
+    pub extern "C" fn acme_certificate_response(response: *const c_char, order: *const c_char) -> *mut AcmeFfiResult {
+        Self::call(|| {
+            let response = Self::str_arg(response)?.to_string();
+            let order = Self::json_arg::<AcmeOrder>(order)?;
+            let chain = RustyAcme::certificate_response(response, order)?
+                .iter()
+                .map(|der| base64::prelude::BASE64_STANDARD.encode(der))
+                .collect::<Vec<_>>();
+            Self::json(chain)
+        })
+    }
+
+    /// Releases an [AcmeFfiResult] along with the strings it points to. Does nothing on a null
+    /// pointer.
+    #[no_mangle]
+    pub extern "C" fn acme_free_result(ptr: *mut AcmeFfiResult) {
+        if ptr.is_null() {
+            return;
+        }
+        let result = unsafe { Box::from_raw(ptr) };
+        for s in [result.value, result.message] {
+            if !s.is_null() {
+                let _ = unsafe { CString::from_raw(s) };
+            }
+        }
+    }
+
+    fn call(f: impl FnOnce() -> FfiResult<String>) -> *mut AcmeFfiResult {
+        let into_raw = |s: String| CString::new(s).map_or(std::ptr::null_mut(), CString::into_raw);
+        let result = match f() {
+            Ok(value) => AcmeFfiResult {
+                error: 0,
+                value: into_raw(value),
+                message: std::ptr::null_mut(),
+            },
+            Err(Failure(error, message)) => AcmeFfiResult {
+                error: error as u8,
+                value: std::ptr::null_mut(),
+                message: into_raw(message),
+            },
+        };
+        Box::into_raw(Box::new(result))
+    }
+
+    fn json(value: impl serde::Serialize) -> FfiResult<String> {
+        serde_json::to_string(&value).map_err(|e| Failure(AcmeFfiError::UnknownError, e.to_string()))
+    }
+
+    fn str_arg<'a>(ptr: *const c_char) -> FfiResult<&'a str> {
+        if ptr.is_null() {
+            return Err(Failure(AcmeFfiError::FfiError, "null argument".to_string()));
+        }
+        unsafe { CStr::from_ptr(ptr) }
+            .to_str()
+            .map_err(|e| Failure(AcmeFfiError::FfiError, e.to_string()))
+    }
+
+    fn json_arg<T: serde::de::DeserializeOwned>(ptr: *const c_char) -> FfiResult<T> {
+        serde_json::from_str(Self::str_arg(ptr)?).map_err(|e| Failure(AcmeFfiError::InvalidArgument, e.to_string()))
+    }
+
+    fn url_arg(ptr: *const c_char) -> FfiResult<url::Url> {
+        Ok(Self::str_arg(ptr)?.parse().map_err(RustyAcmeError::from)?)
+    }
+
+    fn pem_arg(ptr: *const c_char) -> FfiResult<Pem> {
+        Ok(Self::str_arg(ptr)?.into())
+    }
+
+    fn alg_arg(alg: u8) -> FfiResult<JwsAlgorithm> {
+        match alg {
+            a if a == AcmeFfiAlgorithm::P256 as u8 => Ok(JwsAlgorithm::P256),
+            a if a == AcmeFfiAlgorithm::P384 as u8 => Ok(JwsAlgorithm::P384),
+            a if a == AcmeFfiAlgorithm::Ed25519 as u8 => Ok(JwsAlgorithm::Ed25519),
+            _ => Err(Failure(AcmeFfiError::FfiError, format!("unknown algorithm {alg}"))),
+        }
+    }
+}
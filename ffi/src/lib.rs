@@ -1,16 +1,20 @@
-#![cfg(not(target_family = "wasm"))]
+#![cfg(any(not(target_family = "wasm"), feature = "wasm"))]
 #![warn(dead_code)]
 #![allow(clippy::not_unsafe_ptr_arg_deref)]
 
 //! We only declare here intermediate FFI representation with raw types. But we do not generate
 //! all the bindings and wrappers here.
 //! * Haskell: we expose a C-FFI and [wire-server](https://github.com/wireapp/wire-server) will
-//! maintain the Haskell wrapper
+//! maintain the Haskell wrapper. ACME requests and responses are exposed in [acme]
 //! * WASM: we handle bindings here but we let [core-crypto](https://github.com/wireapp/core-crypto)
-//! maintain the Typescript wrapper
+//! maintain the Typescript wrapper. Behind the `wasm` feature, [wasm] is published as an npm
+//! package for the web client
 //! * Android/iOS: we just expose raw types and let [core-crypto](https://github.com/wireapp/core-crypto)
-//! generate the bindings and wrappers
+//! generate the bindings and wrappers. Behind the `uniffi` feature, UniFFI generates Kotlin and
+//! Swift bindings out of [mobile] for the wire clients to consume directly
+//! * Python: behind the `python` feature, [python] is a PyO3 module for QA and backend tooling
 
+#[cfg(not(target_family = "wasm"))]
 use std::{
     ffi::{CStr, CString},
     os::raw::c_char,
@@ -19,8 +23,21 @@ use std::{
 
 use rusty_jwt_tools::prelude::*;
 
+#[cfg(not(target_family = "wasm"))]
+pub mod acme;
+#[cfg(feature = "uniffi")]
+pub mod mobile;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+#[cfg(feature = "uniffi")]
+uniffi::setup_scaffolding!();
+
 pub struct RustyJwtToolsFfi;
 
+#[cfg(not(target_family = "wasm"))]
 impl RustyJwtToolsFfi {
     /// see [RustyJwtTools::generate_dpop_access_token]
     #[no_mangle]
@@ -73,22 +90,22 @@ impl RustyJwtToolsFfi {
                 Ok(handle) => handle,
                 Err(e) => return Box::into_raw(Box::new(Err(e))),
             };
-            let res = RustyJwtTools::generate_access_token(
-                dpop,
-                &client_id,
-                handle,
-                team,
-                nonce,
-                uri,
-                method,
-                max_skew_secs,
-                max_expiration,
-                kp,
-                hash_algorithm,
-                api_version,
-                expiry,
-            )
-            .map_err(HsError::from);
+            let res = AccessTokenBuilder::default()
+                .dpop_proof(dpop)
+                .client_id(&client_id)
+                .handle(handle)
+                .team(team)
+                .backend_nonce(nonce)
+                .htu(uri)
+                .htm(method)
+                .max_skew_secs(max_skew_secs)
+                .max_expiration(max_expiration)
+                .backend_keys(kp)
+                .hash_algorithm(hash_algorithm)
+                .api_version(api_version)
+                .expiry(expiry)
+                .build()
+                .map_err(HsError::from);
             return Box::into_raw(Box::new(res));
         }
         Box::into_raw(Box::new(Err(HsError::ImplementationError)))
@@ -138,10 +155,155 @@ impl RustyJwtToolsFfi {
             let _ = Box::from_raw(ptr);
         }
     }
+
+    /// see [RustyJwtTools::verify_access_token]. The result has to be freed with
+    /// [Self::free_verified_access_token]
+    ///
+    /// # Arguments
+    /// * `issuer` - wire-server endpoint which delivered the access token
+    /// * `backend_keys` - PEM public keys of wire-server, concatenated
+    /// * `client_kid` - JWK thumbprint of the DPoP proof key
+    /// * `expected_audiences` - URLs the (aud) claim has to match any of, space separated. Null
+    ///   not to verify (aud)
+    /// * `allowed_domains` - domains the client may belong to, space separated. Null to accept any
+    /// * `hash_algorithm` - of `client_kid` e.g. `SHA-256`
+    /// * `is_revoked` - called with the NUL-terminated (jti) claim, returns whether the access
+    ///   token has been revoked. Null not to check revocation at all
+    #[no_mangle]
+    #[allow(clippy::too_many_arguments)]
+    pub extern "C" fn verify_access_token(
+        access_token: *const c_char,
+        user: *const c_char,
+        client_id: u64,
+        handle: *const c_char,
+        domain: *const c_char,
+        challenge: *const c_char,
+        max_skew_secs: u16,
+        max_expiration: u64,
+        issuer: *const c_char,
+        backend_keys: *const c_char,
+        client_kid: *const c_char,
+        api_version: u32,
+        expected_audiences: *const c_char,
+        allowed_domains: *const c_char,
+        hash_algorithm: *const c_char,
+        is_revoked: Option<extern "C" fn(jti: *const c_char) -> bool>,
+    ) -> *mut HsVerifiedAccessToken {
+        let str_arg = |ptr: *const c_char| {
+            if ptr.is_null() {
+                return Err(HsError::FfiError);
+            }
+            unsafe { CStr::from_ptr(ptr) }.to_str().map_err(|_| HsError::Utf8Error)
+        };
+        let verify = || -> HsResult<String> {
+            let user = uuid::Uuid::from_str(str_arg(user)?).map_err(|_| HsError::InvalidUserId)?;
+            let client_id = ClientId::try_new(user.to_string(), client_id, str_arg(domain)?)?;
+            let handle = Handle::from(str_arg(handle)?).try_to_qualified(&client_id.domain)?;
+            let issuer = Htu::try_from(str_arg(issuer)?)?;
+            let backend_keys = pem::parse_many(str_arg(backend_keys)?)
+                .map_err(|_| HsError::InvalidBackendKeys)?
+                .iter()
+                .map(|key| Pem::from(pem::encode(key)))
+                .collect::<Vec<_>>();
+            let challenge = AcmeNonce::from(str_arg(challenge)?.to_string());
+            let token = AccessTokenToVerify {
+                access_token: str_arg(access_token)?,
+                client_id: &client_id,
+                handle: &handle,
+                challenge: &challenge,
+                client_kid: str_arg(client_kid)?,
+            };
+            let audience = if expected_audiences.is_null() {
+                AudiencePolicy::Ignore
+            } else {
+                let audiences = str_arg(expected_audiences)?.split_whitespace().map(url::Url::parse);
+                AudiencePolicy::Required(
+                    audiences
+                        .collect::<Result<_, _>>()
+                        .map_err(|_| HsError::InvalidParameter)?,
+                )
+            };
+            let domains = if allowed_domains.is_null() {
+                DomainPolicy::Any
+            } else {
+                DomainPolicy::AllowList(
+                    str_arg(allowed_domains)?
+                        .split_whitespace()
+                        .map(str::to_string)
+                        .collect(),
+                )
+            };
+            let hash_algorithm =
+                HashAlgorithm::from_str(str_arg(hash_algorithm)?).map_err(|_| HsError::InvalidParameter)?;
+            // a 'jti' which cannot cross the FFI is deemed revoked
+            let revocation = move |jti: &str| match (is_revoked, CString::new(jti)) {
+                (None, _) => false,
+                (Some(is_revoked), Ok(jti)) => is_revoked(jti.as_ptr()),
+                (Some(_), Err(_)) => true,
+            };
+            let options = VerifyAccessTokenOptions::default()
+                .max_skew_secs(max_skew_secs)
+                .max_expiration(max_expiration)
+                .issuers([issuer])
+                .audience(audience)
+                .backend_pks(backend_keys)
+                .hash_algorithm(hash_algorithm)
+                .api_versions(api_version)
+                .domains(domains)
+                .revocation(&revocation);
+            let claims = RustyJwtTools::verify_access_token(&token, &options)?;
+
+            let claims = serde_json::json!({
+                "client_id": claims.client_id().to_uri(),
+                "handle": claims.handle().to_string(),
+                "team": claims.team().to_vec(),
+                "api_version": claims.api_version(),
+                "jti": claims.jti(),
+                "issued_at": claims.issued_at(),
+                "expires_at": claims.expires_at(),
+            });
+            Ok(claims.to_string())
+        };
+        let result = match verify() {
+            Ok(claims) => HsVerifiedAccessToken {
+                error: 0,
+                claims: CString::new(claims).map_or(std::ptr::null_mut(), CString::into_raw),
+            },
+            Err(e) => HsVerifiedAccessToken {
+                error: e as u8,
+                claims: std::ptr::null_mut(),
+            },
+        };
+        Box::into_raw(Box::new(result))
+    }
+
+    /// Frees the [HsVerifiedAccessToken] returned by [Self::verify_access_token], along with its
+    /// claims. This has to be called from haskell
+    #[no_mangle]
+    pub extern "C" fn free_verified_access_token(ptr: *mut HsVerifiedAccessToken) {
+        if ptr.is_null() {
+            return;
+        }
+        let result = unsafe { Box::from_raw(ptr) };
+        if !result.claims.is_null() {
+            let _ = unsafe { CString::from_raw(result.claims) };
+        }
+    }
 }
 
 pub type HsResult<T> = Result<T, HsError>;
 
+/// Outcome of [RustyJwtToolsFfi::verify_access_token]. Its layout is stable.
+#[repr(C)]
+#[derive(Debug)]
+pub struct HsVerifiedAccessToken {
+    /// `0` on success, an [HsError] otherwise
+    pub error: u8,
+    /// NUL-terminated JSON of the trusted claims on success, null otherwise: `client_id`,
+    /// `handle`, `team`, `api_version`, `jti`, `issued_at` and `expires_at`
+    pub claims: *mut c_char,
+}
+
 #[derive(Debug, Copy, Clone)]
 #[repr(u8)]
 #[allow(dead_code)]
@@ -230,6 +392,36 @@ pub enum HsError {
     DpopHandleMismatch = 41,
     /// Client team does not match the supplied team
     DpopTeamMismatch = 42,
+    /// A claim other than the ones above is absent
+    MissingClaim = 43,
+    /// Access token signature does not verify with any of the backend keys
+    InvalidSignature = 44,
+    /// Access token has been revoked
+    RevokedAccessToken = 45,
+    /// Token exceeds the maximum length or JSON nesting depth
+    TokenTooLarge = 46,
+    /// Token 'crit' header is invalid or lists unsupported parameters
+    InvalidCriticalHeader = 47,
+    /// Token has a claim it must not have
+    ForbiddenClaim = 48,
+    /// (aud) claim is invalid or matches none of the expected audiences
+    AudienceMismatch = 49,
+    /// No backend key matches the (kid) header, or it cannot be used
+    NoMatchingKey = 50,
+    /// Claims are in an unsupported schema version
+    UnsupportedClaimsVersion = 51,
+    /// Token was issued for a domain which is not allowed
+    DomainNotAllowed = 52,
+    /// Handle is in the wrong format
+    InvalidHandle = 53,
+    /// Identifier scheme is not 'wireapp'
+    InvalidIdentifierScheme = 54,
+    /// Token is not a valid JWE, CWT, general or detached JWS
+    InvalidTokenFormat = 55,
+    /// 'x5c' certificate chain is invalid
+    X509Error = 56,
+    /// A required parameter is missing or invalid
+    InvalidParameter = 57,
 }
 
 impl From<RustyJwtError> for HsError {
@@ -272,8 +464,31 @@ impl From<RustyJwtError> for HsError {
             RustyJwtError::DpopHtmMismatch => Self::DpopHtmMismatch,
             RustyJwtError::InvalidBackendKeys(_) => Self::InvalidBackendKeys,
             RustyJwtError::InvalidClientId => Self::InvalidClientId,
-            RustyJwtError::UnsupportedApiVersion => Self::UnsupportedApiVersion,
+            RustyJwtError::UnsupportedApiVersion(_) | RustyJwtError::InvalidApiVersionRange(..) => {
+                Self::UnsupportedApiVersion
+            }
             RustyJwtError::UnsupportedScope => Self::UnsupportedScope,
+            RustyJwtError::MissingTokenClaim(_) => Self::MissingClaim,
+            RustyJwtError::InvalidSignature => Self::InvalidSignature,
+            RustyJwtError::RevokedAccessToken => Self::RevokedAccessToken,
+            RustyJwtError::TokenTooLong(_) | RustyJwtError::TokenTooDeep(_) => Self::TokenTooLarge,
+            RustyJwtError::InvalidCriticalHeader(_) | RustyJwtError::UnsupportedCriticalHeader(_) => {
+                Self::InvalidCriticalHeader
+            }
+            RustyJwtError::ForbiddenTokenClaim(_) => Self::ForbiddenClaim,
+            RustyJwtError::InvalidAudience | RustyJwtError::AudienceMismatch => Self::AudienceMismatch,
+            RustyJwtError::NoMatchingJwk(_) | RustyJwtError::InvalidJwkSetKey(_) => Self::NoMatchingKey,
+            RustyJwtError::UnsupportedClaimsVersion(_) => Self::UnsupportedClaimsVersion,
+            RustyJwtError::DomainNotAllowed(_) => Self::DomainNotAllowed,
+            RustyJwtError::InvalidHandle => Self::InvalidHandle,
+            RustyJwtError::InvalidIdentifierScheme(_) => Self::InvalidIdentifierScheme,
+            RustyJwtError::InvalidJwe(_)
+            | RustyJwtError::InvalidCwt(_)
+            | RustyJwtError::InvalidGeneralJws(_)
+            | RustyJwtError::InvalidDetachedJws(_) => Self::InvalidTokenFormat,
+            RustyJwtError::InvalidX5c(_) => Self::X509Error,
+            RustyJwtError::MissingBuilderField(_) | RustyJwtError::InvalidBuilderField(_, _) => Self::InvalidParameter,
+            // variants behind features of rusty-jwt-tools
             _ => Self::UnknownError,
         }
     }
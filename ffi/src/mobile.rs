@@ -0,0 +1,417 @@
+//! UniFFI interface consumed by the Kotlin and Swift wire clients. Generate the bindings out of
+//! the built library with e.g.
+//! `cargo run --features uniffi --bin uniffi-bindgen generate --library ../target/release/librusty_jwt_tools_ffi.so --language kotlin --out-dir bindings/kotlin`
+
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use rusty_jwt_tools::prelude::*;
+use wire_e2e_identity::prelude::{
+    E2eIdentityError, E2eiAcmeRequest, E2eiEnrollment, E2eiEnrollmentStep, E2eiErrorCategory, RustyE2eIdentity,
+};
+
+/// Error of any call, with the guidance to show
+#[derive(Debug, thiserror::Error, uniffi::Error)]
+pub enum MobileError {
+    #[error("{message}")]
+    Failed {
+        category: MobileErrorCategory,
+        message: String,
+    },
+}
+
+/// see [E2eiErrorCategory]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, uniffi::Enum)]
+pub enum MobileErrorCategory {
+    Network,
+    LoginRequired,
+    ClockSkew,
+    CaMisconfigured,
+    IdentityMismatch,
+    Retryable,
+    Other,
+}
+
+impl From<E2eIdentityError> for MobileError {
+    fn from(e: E2eIdentityError) -> Self {
+        Self::Failed {
+            category: e.category().into(),
+            message: e.to_string(),
+        }
+    }
+}
+
+impl From<RustyJwtError> for MobileError {
+    fn from(e: RustyJwtError) -> Self {
+        E2eIdentityError::from(e).into()
+    }
+}
+
+impl From<serde_json::Error> for MobileError {
+    fn from(e: serde_json::Error) -> Self {
+        E2eIdentityError::from(e).into()
+    }
+}
+
+impl From<E2eiErrorCategory> for MobileErrorCategory {
+    fn from(category: E2eiErrorCategory) -> Self {
+        match category {
+            E2eiErrorCategory::Network => Self::Network,
+            E2eiErrorCategory::LoginRequired => Self::LoginRequired,
+            E2eiErrorCategory::ClockSkew => Self::ClockSkew,
+            E2eiErrorCategory::CaMisconfigured => Self::CaMisconfigured,
+            E2eiErrorCategory::IdentityMismatch => Self::IdentityMismatch,
+            E2eiErrorCategory::Retryable => Self::Retryable,
+            E2eiErrorCategory::Other => Self::Other,
+        }
+    }
+}
+
+/// see [JwsAlgorithm]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, uniffi::Enum)]
+pub enum SignatureAlgorithm {
+    P256,
+    P384,
+    Ed25519,
+}
+
+impl From<SignatureAlgorithm> for JwsAlgorithm {
+    fn from(alg: SignatureAlgorithm) -> Self {
+        match alg {
+            SignatureAlgorithm::P256 => Self::P256,
+            SignatureAlgorithm::P384 => Self::P384,
+            SignatureAlgorithm::Ed25519 => Self::Ed25519,
+        }
+    }
+}
+
+/// DPoP token to send to wire-server along with the request it is bound to, see [RustyJwtTools::generate_dpop_token]
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct DpopTokenParams {
+    /// HTTP method of the request e.g. `POST`
+    pub htm: String,
+    /// HTTP uri of the request e.g. `https://wire.example.com/clients/8d4e2de0af1f4b9e/access-token`
+    pub htu: String,
+    /// ACME challenge token
+    pub challenge: String,
+    /// User handle, without the domain e.g. `alice_wire`
+    pub handle: String,
+    /// Team the user belongs to, if any
+    pub team: Option<String>,
+    /// e.g. `NDUyMGUyMmY2YjA3NGU3NjkyZjE1NjJjZTAwMmQ2NTQ:6add501bacd1d90e@example.com`
+    pub client_id: String,
+    /// Nonce delivered by wire-server
+    pub backend_nonce: String,
+    /// ACME challenge URL
+    pub audience: String,
+    pub expiry_secs: u64,
+    pub alg: SignatureAlgorithm,
+    /// PEM private key of the client
+    pub key_pem: String,
+}
+
+/// Generates a DPoP token, see [DpopTokenParams]
+#[uniffi::export]
+pub fn generate_dpop_token(params: DpopTokenParams) -> Result<String, MobileError> {
+    let client_id = ClientId::try_from_qualified(&params.client_id)?;
+    let dpop = Dpop {
+        htm: params.htm.as_str().try_into()?,
+        htu: params.htu.as_str().try_into()?,
+        challenge: params.challenge.into(),
+        handle: Handle::from(params.handle.as_str()).try_to_qualified(&client_id.domain)?,
+        team: params.team.into(),
+        extra_claims: None,
+    };
+    let audience = params.audience.parse().map_err(E2eIdentityError::from)?;
+    Ok(RustyJwtTools::generate_dpop_token(
+        dpop,
+        &client_id,
+        params.backend_nonce.into(),
+        audience,
+        core::time::Duration::from_secs(params.expiry_secs),
+        params.alg.into(),
+        &params.key_pem.into(),
+    )?)
+}
+
+/// Access token to verify, see [RustyJwtTools::verify_access_token]
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct AccessTokenParams {
+    pub access_token: String,
+    /// e.g. `NDUyMGUyMmY2YjA3NGU3NjkyZjE1NjJjZTAwMmQ2NTQ:6add501bacd1d90e@example.com`
+    pub client_id: String,
+    /// User handle, without the domain e.g. `alice_wire`
+    pub handle: String,
+    /// ACME challenge token
+    pub challenge: String,
+    /// Clock skew tolerated, in seconds
+    pub max_skew_secs: u16,
+    /// Latest expiry accepted, in seconds since epoch
+    pub max_expiration: u64,
+    /// wire-server endpoint which delivered the access token
+    pub issuer: String,
+    /// PEM public keys of wire-server, several during a key rotation
+    pub backend_public_keys: Vec<String>,
+    /// JWK thumbprint of the DPoP proof key
+    pub client_kid: String,
+    /// wire-server http API version
+    pub api_version: u32,
+}
+
+/// Trusted claims of a verified access token
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct VerifiedAccessToken {
+    /// e.g. `wireapp://NDUyMGUyMmY2YjA3NGU3NjkyZjE1NjJjZTAwMmQ2NTQ!6add501bacd1d90e@example.com`
+    pub client_id: String,
+    /// e.g. `wireapp://%40alice_wire@example.com`
+    pub handle: String,
+    pub team: Vec<String>,
+    pub api_version: u32,
+    pub jti: String,
+    /// in seconds since epoch
+    pub expires_at: u64,
+}
+
+/// Verifies an access token delivered by wire-server, see [AccessTokenParams]
+#[uniffi::export]
+pub fn verify_access_token(params: AccessTokenParams) -> Result<VerifiedAccessToken, MobileError> {
+    let client_id = ClientId::try_from_qualified(&params.client_id)?;
+    let handle = Handle::from(params.handle.as_str()).try_to_qualified(&client_id.domain)?;
+    let issuer = Htu::try_from(params.issuer.as_str())?;
+    let backend_pks = params
+        .backend_public_keys
+        .into_iter()
+        .map(Pem::from)
+        .collect::<Vec<_>>();
+    let challenge = AcmeNonce::from(params.challenge);
+    let token = AccessTokenToVerify {
+        access_token: &params.access_token,
+        client_id: &client_id,
+        handle: &handle,
+        challenge: &challenge,
+        client_kid: &params.client_kid,
+    };
+    let options = VerifyAccessTokenOptions::default()
+        .max_skew_secs(params.max_skew_secs)
+        .max_expiration(params.max_expiration)
+        .issuers([issuer])
+        .backend_pks(backend_pks)
+        .hash_algorithm(HashAlgorithm::SHA256)
+        .api_versions(params.api_version);
+    let claims = RustyJwtTools::verify_access_token(&token, &options)?;
+
+    Ok(VerifiedAccessToken {
+        client_id: claims.client_id().to_uri(),
+        handle: claims.handle().to_string(),
+        team: claims.team().to_vec(),
+        api_version: claims.api_version(),
+        jti: claims.jti().to_string(),
+        expires_at: claims.expires_at(),
+    })
+}
+
+/// see [E2eiEnrollmentStep]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, uniffi::Enum)]
+pub enum EnrollmentStep {
+    Directory,
+    NewAccount,
+    NewOrder,
+    Authorizations,
+    DpopChallenge,
+    OidcChallenge,
+    CheckOrder,
+    Finalize,
+    Certificate,
+    Done,
+}
+
+impl From<E2eiEnrollmentStep> for EnrollmentStep {
+    fn from(step: E2eiEnrollmentStep) -> Self {
+        match step {
+            E2eiEnrollmentStep::Directory => Self::Directory,
+            E2eiEnrollmentStep::NewAccount => Self::NewAccount,
+            E2eiEnrollmentStep::NewOrder => Self::NewOrder,
+            E2eiEnrollmentStep::Authorizations => Self::Authorizations,
+            E2eiEnrollmentStep::DpopChallenge => Self::DpopChallenge,
+            E2eiEnrollmentStep::OidcChallenge => Self::OidcChallenge,
+            E2eiEnrollmentStep::CheckOrder => Self::CheckOrder,
+            E2eiEnrollmentStep::Finalize => Self::Finalize,
+            E2eiEnrollmentStep::Certificate => Self::Certificate,
+            E2eiEnrollmentStep::Done => Self::Done,
+        }
+    }
+}
+
+/// Request to POST to the ACME server with the 'application/jose+json' content type
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct AcmeRequest {
+    pub url: String,
+    /// JSON JWS
+    pub body: Vec<u8>,
+}
+
+impl TryFrom<E2eiAcmeRequest> for AcmeRequest {
+    type Error = MobileError;
+
+    fn try_from(request: E2eiAcmeRequest) -> Result<Self, Self::Error> {
+        Ok(Self {
+            url: request.url.to_string(),
+            body: serde_json::to_vec(&request.body)?,
+        })
+    }
+}
+
+/// see [E2eiEnrollment]. Responses are the raw bodies returned by the ACME server and nonces the
+/// 'replay-nonce' header of the previous response.
+#[derive(Debug, uniffi::Object)]
+pub struct Enrollment(Mutex<E2eiEnrollment>);
+
+#[uniffi::export]
+impl Enrollment {
+    /// see [E2eiEnrollment::try_new]
+    #[uniffi::constructor]
+    pub fn new(
+        alg: SignatureAlgorithm,
+        raw_sign_key: Vec<u8>,
+        client_id: String,
+        display_name: String,
+        handle: String,
+        team: Option<String>,
+        expiry_secs: u64,
+    ) -> Result<Arc<Self>, MobileError> {
+        let delegate = RustyE2eIdentity::try_new(alg.into(), raw_sign_key)?;
+        let expiry = core::time::Duration::from_secs(expiry_secs);
+        let enrollment = E2eiEnrollment::try_new(delegate, &client_id, &display_name, &handle, team, expiry)?;
+        Ok(Arc::new(Self(Mutex::new(enrollment))))
+    }
+
+    /// Resumes an enrollment serialized with [Self::to_json]
+    #[uniffi::constructor]
+    pub fn from_json(json: String) -> Result<Arc<Self>, MobileError> {
+        Ok(Arc::new(Self(Mutex::new(serde_json::from_str(&json)?))))
+    }
+
+    /// Serializes the enrollment, holding private keys, to resume it after a restart
+    pub fn to_json(&self) -> Result<String, MobileError> {
+        Ok(serde_json::to_string(&*self.lock())?)
+    }
+
+    pub fn step(&self) -> EnrollmentStep {
+        self.lock().step().into()
+    }
+
+    pub fn directory_response(&self, directory: Vec<u8>) -> Result<(), MobileError> {
+        self.lock().directory_response(serde_json::from_slice(&directory)?)?;
+        Ok(())
+    }
+
+    pub fn new_account_request(&self, previous_nonce: String) -> Result<AcmeRequest, MobileError> {
+        self.lock().new_account_request(previous_nonce)?.try_into()
+    }
+
+    pub fn new_account_response(&self, account: Vec<u8>) -> Result<(), MobileError> {
+        Ok(self.lock().new_account_response(serde_json::from_slice(&account)?)?)
+    }
+
+    pub fn new_order_request(&self, previous_nonce: String) -> Result<AcmeRequest, MobileError> {
+        self.lock().new_order_request(previous_nonce)?.try_into()
+    }
+
+    /// Returns the URLs of the authorizations to fetch
+    ///
+    /// # Parameters
+    /// * `order_url` - 'location' header of the response
+    pub fn new_order_response(&self, new_order: Vec<u8>, order_url: String) -> Result<Vec<String>, MobileError> {
+        let order_url = order_url.parse().map_err(E2eIdentityError::from)?;
+        let authorizations = self
+            .lock()
+            .new_order_response(serde_json::from_slice(&new_order)?, order_url)?;
+        Ok(authorizations.iter().map(ToString::to_string).collect())
+    }
+
+    pub fn new_authz_request(&self, previous_nonce: String) -> Result<AcmeRequest, MobileError> {
+        self.lock().new_authz_request(previous_nonce)?.try_into()
+    }
+
+    pub fn new_authz_response(&self, new_authz: Vec<u8>) -> Result<(), MobileError> {
+        self.lock().new_authz_response(serde_json::from_slice(&new_authz)?)?;
+        Ok(())
+    }
+
+    /// DPoP token to exchange with wire-server for an access token
+    pub fn new_dpop_token(&self, backend_nonce: String, expiry_secs: u64) -> Result<String, MobileError> {
+        let expiry = core::time::Duration::from_secs(expiry_secs);
+        Ok(self.lock().new_dpop_token(backend_nonce, expiry)?)
+    }
+
+    pub fn new_dpop_challenge_request(
+        &self,
+        access_token: String,
+        previous_nonce: String,
+    ) -> Result<AcmeRequest, MobileError> {
+        self.lock()
+            .new_dpop_challenge_request(access_token, previous_nonce)?
+            .try_into()
+    }
+
+    pub fn new_dpop_challenge_response(&self, challenge: Vec<u8>) -> Result<(), MobileError> {
+        Ok(self
+            .lock()
+            .new_dpop_challenge_response(serde_json::from_slice(&challenge)?)?)
+    }
+
+    /// To request in the ID token, see [E2eiEnrollment::keyauth]
+    pub fn keyauth(&self) -> Option<String> {
+        self.lock().keyauth().map(ToString::to_string)
+    }
+
+    pub fn new_oidc_challenge_request(
+        &self,
+        id_token: String,
+        previous_nonce: String,
+    ) -> Result<AcmeRequest, MobileError> {
+        self.lock()
+            .new_oidc_challenge_request(id_token, previous_nonce)?
+            .try_into()
+    }
+
+    pub fn new_oidc_challenge_response(&self, challenge: Vec<u8>) -> Result<(), MobileError> {
+        Ok(self
+            .lock()
+            .new_oidc_challenge_response(serde_json::from_slice(&challenge)?)?)
+    }
+
+    pub fn check_order_request(&self, previous_nonce: String) -> Result<AcmeRequest, MobileError> {
+        self.lock().check_order_request(previous_nonce)?.try_into()
+    }
+
+    pub fn check_order_response(&self, order: Vec<u8>) -> Result<(), MobileError> {
+        Ok(self.lock().check_order_response(serde_json::from_slice(&order)?)?)
+    }
+
+    pub fn finalize_request(&self, previous_nonce: String) -> Result<AcmeRequest, MobileError> {
+        self.lock().finalize_request(previous_nonce)?.try_into()
+    }
+
+    pub fn finalize_response(&self, finalize: Vec<u8>) -> Result<(), MobileError> {
+        Ok(self.lock().finalize_response(serde_json::from_slice(&finalize)?)?)
+    }
+
+    pub fn certificate_request(&self, previous_nonce: String) -> Result<AcmeRequest, MobileError> {
+        self.lock().certificate_request(previous_nonce)?.try_into()
+    }
+
+    /// Returns the DER certificates, leaf first
+    ///
+    /// # Parameters
+    /// * `response` - PEM bundle
+    pub fn certificate_response(&self, response: String) -> Result<Vec<Vec<u8>>, MobileError> {
+        Ok(self.lock().certificate_response(response)?)
+    }
+}
+
+impl Enrollment {
+    /// A panic in a previous call must not brick the enrollment
+    fn lock(&self) -> MutexGuard<'_, E2eiEnrollment> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
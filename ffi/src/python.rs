@@ -0,0 +1,296 @@
+//! PyO3 module for wire QA and backend tooling, mirroring the prelude of [rusty_jwt_tools]. Build
+//! and install it in the current virtualenv with `maturin develop --release` then
+//! `import rusty_jwt_tools`.
+
+use pyo3::{create_exception, exceptions::PyException, prelude::*};
+
+use rusty_jwt_tools::prelude::*;
+
+create_exception!(
+    rusty_jwt_tools,
+    RustyJwtToolsError,
+    PyException,
+    "Raised by any function of this module"
+);
+
+fn py_err(e: impl ToString) -> PyErr {
+    RustyJwtToolsError::new_err(e.to_string())
+}
+
+/// see [JwsAlgorithm]
+#[pyclass(name = "SignatureAlgorithm", eq, eq_int, frozen)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum PySignatureAlgorithm {
+    P256,
+    P384,
+    Ed25519,
+}
+
+impl From<PySignatureAlgorithm> for JwsAlgorithm {
+    fn from(alg: PySignatureAlgorithm) -> Self {
+        match alg {
+            PySignatureAlgorithm::P256 => Self::P256,
+            PySignatureAlgorithm::P384 => Self::P384,
+            PySignatureAlgorithm::Ed25519 => Self::Ed25519,
+        }
+    }
+}
+
+/// see [ClientId]
+#[pyclass(name = "ClientId", eq, frozen)]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct PyClientId(ClientId);
+
+#[pymethods]
+impl PyClientId {
+    #[new]
+    fn new(user_id: &str, device_id: u64, domain: &str) -> PyResult<Self> {
+        Ok(Self(ClientId::try_new(user_id, device_id, domain).map_err(py_err)?))
+    }
+
+    /// e.g. `NDUyMGUyMmY2YjA3NGU3NjkyZjE1NjJjZTAwMmQ2NTQ:6add501bacd1d90e@example.com`
+    #[staticmethod]
+    fn from_qualified(client_id: &str) -> PyResult<Self> {
+        Ok(Self(ClientId::try_from_qualified(client_id).map_err(py_err)?))
+    }
+
+    /// e.g. `wireapp://NDUyMGUyMmY2YjA3NGU3NjkyZjE1NjJjZTAwMmQ2NTQ!6add501bacd1d90e@example.com`
+    #[staticmethod]
+    fn from_uri(client_id: &str) -> PyResult<Self> {
+        Ok(Self(ClientId::try_from_uri(client_id).map_err(py_err)?))
+    }
+
+    /// Either of the forms above, see [ClientId::parse_any]
+    #[staticmethod]
+    fn parse(client_id: &str) -> PyResult<Self> {
+        Ok(Self(ClientId::parse_any(client_id).map_err(py_err)?.0))
+    }
+
+    #[getter]
+    fn user_id(&self) -> String {
+        self.0.user_id.to_string()
+    }
+
+    #[getter]
+    fn device_id(&self) -> u64 {
+        self.0.device_id
+    }
+
+    #[getter]
+    fn domain(&self) -> &str {
+        &self.0.domain
+    }
+
+    fn to_uri(&self) -> String {
+        self.0.to_uri()
+    }
+
+    fn to_qualified(&self) -> String {
+        self.0.to_qualified()
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_qualified()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("ClientId('{}')", self.0.to_qualified())
+    }
+}
+
+/// see [Handle]
+#[pyclass(name = "Handle", eq, frozen)]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct PyHandle(Handle);
+
+#[pymethods]
+impl PyHandle {
+    /// Without the domain e.g. `alice_wire`
+    #[new]
+    fn new(handle: &str) -> Self {
+        Self(handle.into())
+    }
+
+    /// e.g. `wireapp://%40alice_wire@example.com`
+    #[staticmethod]
+    fn from_qualified(handle: &str) -> PyResult<Self> {
+        let qualified = handle.parse::<QualifiedHandle>().map_err(py_err)?;
+        Ok(Self(qualified.try_into().map_err(py_err)?))
+    }
+
+    /// see [Handle::try_to_qualified]
+    fn to_qualified(&self, domain: &str) -> PyResult<String> {
+        Ok(self.0.try_to_qualified(domain).map_err(py_err)?.to_string())
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Handle('{}')", *self.0)
+    }
+}
+
+/// Generates a DPoP token, see [RustyJwtTools::generate_dpop_token]
+///
+/// # Arguments
+/// * `handle` - without the domain e.g. `alice_wire`
+/// * `audience` - ACME challenge URL
+/// * `key_pem` - PEM private key of the client
+#[pyfunction]
+#[pyo3(signature = (htm, htu, challenge, handle, client_id, backend_nonce, audience, expiry_secs, alg, key_pem, team = None))]
+#[allow(clippy::too_many_arguments)]
+fn generate_dpop_token(
+    htm: &str,
+    htu: &str,
+    challenge: String,
+    handle: &PyHandle,
+    client_id: &PyClientId,
+    backend_nonce: String,
+    audience: &str,
+    expiry_secs: u64,
+    alg: PySignatureAlgorithm,
+    key_pem: String,
+    team: Option<String>,
+) -> PyResult<String> {
+    let client_id = &client_id.0;
+    let dpop = Dpop {
+        htm: htm.try_into().map_err(py_err)?,
+        htu: htu.try_into().map_err(py_err)?,
+        challenge: challenge.into(),
+        handle: handle.0.try_to_qualified(&client_id.domain).map_err(py_err)?,
+        team: team.into(),
+        extra_claims: None,
+    };
+    RustyJwtTools::generate_dpop_token(
+        dpop,
+        client_id,
+        backend_nonce.into(),
+        audience.parse().map_err(py_err)?,
+        core::time::Duration::from_secs(expiry_secs),
+        alg.into(),
+        &key_pem.into(),
+    )
+    .map_err(py_err)
+}
+
+/// Verifies a DPoP proof then generates an access token, as wire-server does, see
+/// [AccessTokenBuilder]
+///
+/// # Arguments
+/// * `htu`/`htm` - of the request the proof is bound to
+/// * `backend_keys` - PEM keypair of wire-server signing the access token
+#[pyfunction]
+#[pyo3(signature = (dpop_proof, client_id, handle, backend_nonce, htu, htm, max_skew_secs, max_expiration, backend_keys, api_version, expiry_secs, team = None))]
+#[allow(clippy::too_many_arguments)]
+fn generate_dpop_access_token(
+    dpop_proof: &str,
+    client_id: &PyClientId,
+    handle: &PyHandle,
+    backend_nonce: String,
+    htu: &str,
+    htm: &str,
+    max_skew_secs: u16,
+    max_expiration: u64,
+    backend_keys: String,
+    api_version: u32,
+    expiry_secs: u64,
+    team: Option<String>,
+) -> PyResult<String> {
+    let client_id = &client_id.0;
+    AccessTokenBuilder::default()
+        .dpop_proof(dpop_proof)
+        .client_id(client_id)
+        .handle(handle.0.try_to_qualified(&client_id.domain).map_err(py_err)?)
+        .team(team.into())
+        .backend_nonce(backend_nonce.into())
+        .htu(htu.try_into().map_err(py_err)?)
+        .htm(htm.try_into().map_err(py_err)?)
+        .max_skew_secs(max_skew_secs)
+        .max_expiration(max_expiration)
+        .backend_keys(backend_keys.into())
+        .hash_algorithm(HashAlgorithm::SHA256)
+        .api_version(api_version)
+        .expiry(core::time::Duration::from_secs(expiry_secs))
+        .build()
+        .map_err(py_err)
+}
+
+/// Trusted claims of a verified access token
+#[pyclass(name = "VerifiedAccessToken", get_all, frozen)]
+#[derive(Debug, Clone)]
+pub struct PyVerifiedAccessToken {
+    client_id: PyClientId,
+    /// e.g. `wireapp://%40alice_wire@example.com`
+    handle: String,
+    team: Vec<String>,
+    api_version: u32,
+    jti: String,
+    /// in seconds since epoch
+    expires_at: u64,
+}
+
+/// Verifies an access token delivered by wire-server, see [RustyJwtTools::verify_access_token]
+///
+/// # Arguments
+/// * `issuer` - wire-server endpoint which delivered the access token
+/// * `backend_public_keys` - PEM public keys of wire-server, several during a key rotation
+/// * `client_kid` - JWK thumbprint of the DPoP proof key
+#[pyfunction]
+#[allow(clippy::too_many_arguments)]
+fn verify_access_token(
+    access_token: &str,
+    client_id: &PyClientId,
+    handle: &PyHandle,
+    challenge: String,
+    max_skew_secs: u16,
+    max_expiration: u64,
+    issuer: &str,
+    backend_public_keys: Vec<String>,
+    client_kid: String,
+    api_version: u32,
+) -> PyResult<PyVerifiedAccessToken> {
+    let client_id = &client_id.0;
+    let handle = handle.0.try_to_qualified(&client_id.domain).map_err(py_err)?;
+    let issuer = Htu::try_from(issuer).map_err(py_err)?;
+    let backend_pks = backend_public_keys.into_iter().map(Pem::from).collect::<Vec<_>>();
+    let challenge = AcmeNonce::from(challenge);
+    let token = AccessTokenToVerify {
+        access_token,
+        client_id,
+        handle: &handle,
+        challenge: &challenge,
+        client_kid: &client_kid,
+    };
+    let options = VerifyAccessTokenOptions::default()
+        .max_skew_secs(max_skew_secs)
+        .max_expiration(max_expiration)
+        .issuers([issuer])
+        .backend_pks(backend_pks)
+        .hash_algorithm(HashAlgorithm::SHA256)
+        .api_versions(api_version);
+    let claims = RustyJwtTools::verify_access_token(&token, &options).map_err(py_err)?;
+    Ok(PyVerifiedAccessToken {
+        client_id: PyClientId(claims.client_id().clone()),
+        handle: claims.handle().to_string(),
+        team: claims.team().to_vec(),
+        api_version: claims.api_version(),
+        jti: claims.jti().to_string(),
+        expires_at: claims.expires_at(),
+    })
+}
+
+#[pymodule]
+#[pyo3(name = "rusty_jwt_tools")]
+fn rusty_jwt_tools_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("RustyJwtToolsError", m.py().get_type_bound::<RustyJwtToolsError>())?;
+    m.add_class::<PySignatureAlgorithm>()?;
+    m.add_class::<PyClientId>()?;
+    m.add_class::<PyHandle>()?;
+    m.add_class::<PyVerifiedAccessToken>()?;
+    m.add_function(wrap_pyfunction!(generate_dpop_token, m)?)?;
+    m.add_function(wrap_pyfunction!(generate_dpop_access_token, m)?)?;
+    m.add_function(wrap_pyfunction!(verify_access_token, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,500 @@
+//! wasm-bindgen interface consumed by the web client, published as an npm package with e.g.
+//! `wasm-pack build --target web --scope wireapp -- --features wasm`
+//!
+//! ACME responses are passed as the parsed JSON bodies returned by the ACME server and the
+//! resulting resources are to be passed back as is to the next steps. Requests are JWS to POST
+//! as JSON with the 'application/jose+json' content type.
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use rusty_acme::prelude::*;
+use rusty_jwt_tools::prelude::*;
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPES: &str = r#"
+/** DPoP token to send to wire-server along with the request it is bound to */
+export interface DpopTokenParams {
+    /** HTTP method of the request e.g. `POST` */
+    htm: string;
+    /** HTTP uri of the request e.g. `https://wire.example.com/clients/8d4e2de0af1f4b9e/access-token` */
+    htu: string;
+    /** ACME challenge token */
+    challenge: string;
+    /** User handle, without the domain e.g. `alice_wire` */
+    handle: string;
+    /** Team the user belongs to, if any */
+    team?: string;
+    /** e.g. `NDUyMGUyMmY2YjA3NGU3NjkyZjE1NjJjZTAwMmQ2NTQ:6add501bacd1d90e@example.com` */
+    clientId: string;
+    /** Nonce delivered by wire-server */
+    backendNonce: string;
+    /** ACME challenge URL */
+    audience: string;
+    expirySecs: number;
+    alg: SignatureAlgorithm;
+    /** PEM private key of the client */
+    keyPem: string;
+}
+
+/** Access token to verify */
+export interface AccessTokenParams {
+    accessToken: string;
+    /** e.g. `NDUyMGUyMmY2YjA3NGU3NjkyZjE1NjJjZTAwMmQ2NTQ:6add501bacd1d90e@example.com` */
+    clientId: string;
+    /** User handle, without the domain e.g. `alice_wire` */
+    handle: string;
+    /** ACME challenge token */
+    challenge: string;
+    /** Clock skew tolerated, in seconds */
+    maxSkewSecs: number;
+    /** Latest expiry accepted, in seconds since epoch */
+    maxExpiration: number;
+    /** wire-server endpoint which delivered the access token */
+    issuer: string;
+    /** PEM public keys of wire-server, several during a key rotation */
+    backendPublicKeys: string[];
+    /** JWK thumbprint of the DPoP proof key */
+    clientKid: string;
+    /** wire-server http API version */
+    apiVersion: number;
+}
+
+/** Trusted claims of a verified access token */
+export interface VerifiedAccessToken {
+    /** e.g. `wireapp://NDUyMGUyMmY2YjA3NGU3NjkyZjE1NjJjZTAwMmQ2NTQ!6add501bacd1d90e@example.com` */
+    clientId: string;
+    /** e.g. `wireapp://%40alice_wire@example.com` */
+    handle: string;
+    team: string[];
+    apiVersion: number;
+    jti: string;
+    /** in seconds since epoch */
+    expiresAt: number;
+}
+
+/** Request body to POST to the ACME server */
+export interface AcmeJws {
+    protected: string;
+    payload: string;
+    signature: string;
+}
+
+/** ACME resources, to pass back as is */
+export type AcmeDirectory = Record<string, unknown>;
+export type AcmeAccount = Record<string, unknown>;
+export type AcmeOrder = Record<string, unknown>;
+export type AcmeAuthz = Record<string, unknown>;
+export type AcmeChallenge = Record<string, unknown>;
+export type AcmeFinalize = Record<string, unknown>;
+"#;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(typescript_type = "DpopTokenParams")]
+    pub type JsDpopTokenParams;
+    #[wasm_bindgen(typescript_type = "AccessTokenParams")]
+    pub type JsAccessTokenParams;
+    #[wasm_bindgen(typescript_type = "VerifiedAccessToken")]
+    pub type JsVerifiedAccessToken;
+    #[wasm_bindgen(typescript_type = "AcmeJws")]
+    pub type JsAcmeJws;
+    #[wasm_bindgen(typescript_type = "AcmeDirectory")]
+    pub type JsAcmeDirectory;
+    #[wasm_bindgen(typescript_type = "AcmeAccount")]
+    pub type JsAcmeAccount;
+    #[wasm_bindgen(typescript_type = "AcmeOrder")]
+    pub type JsAcmeOrder;
+    #[wasm_bindgen(typescript_type = "AcmeAuthz")]
+    pub type JsAcmeAuthz;
+    #[wasm_bindgen(typescript_type = "AcmeChallenge")]
+    pub type JsAcmeChallenge;
+    #[wasm_bindgen(typescript_type = "AcmeFinalize")]
+    pub type JsAcmeFinalize;
+}
+
+/// see [JwsAlgorithm]
+#[wasm_bindgen]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SignatureAlgorithm {
+    P256 = 1,
+    P384 = 2,
+    Ed25519 = 3,
+}
+
+impl From<SignatureAlgorithm> for JwsAlgorithm {
+    fn from(alg: SignatureAlgorithm) -> Self {
+        match alg {
+            SignatureAlgorithm::P256 => Self::P256,
+            SignatureAlgorithm::P384 => Self::P384,
+            SignatureAlgorithm::Ed25519 => Self::Ed25519,
+        }
+    }
+}
+
+impl TryFrom<u8> for SignatureAlgorithm {
+    type Error = JsError;
+
+    /// Within a deserialized object, where the enum is represented by its discriminant
+    fn try_from(alg: u8) -> Result<Self, Self::Error> {
+        match alg {
+            1 => Ok(Self::P256),
+            2 => Ok(Self::P384),
+            3 => Ok(Self::Ed25519),
+            _ => Err(JsError::new(&format!("unknown signature algorithm {alg}"))),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DpopTokenParams {
+    htm: String,
+    htu: String,
+    challenge: String,
+    handle: String,
+    team: Option<String>,
+    client_id: String,
+    backend_nonce: String,
+    audience: String,
+    expiry_secs: u64,
+    alg: u8,
+    key_pem: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AccessTokenParams {
+    access_token: String,
+    client_id: String,
+    handle: String,
+    challenge: String,
+    max_skew_secs: u16,
+    max_expiration: u64,
+    issuer: String,
+    backend_public_keys: Vec<String>,
+    client_kid: String,
+    api_version: u32,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct VerifiedAccessToken {
+    client_id: String,
+    handle: String,
+    team: Vec<String>,
+    api_version: u32,
+    jti: String,
+    expires_at: u64,
+}
+
+/// Generates a DPoP token, see [RustyJwtTools::generate_dpop_token]
+#[wasm_bindgen(js_name = generateDpopToken)]
+pub fn generate_dpop_token(params: JsDpopTokenParams) -> Result<String, JsError> {
+    let params = from_js::<DpopTokenParams>(params.into())?;
+    let client_id = ClientId::try_from_qualified(&params.client_id)?;
+    let dpop = Dpop {
+        htm: params.htm.as_str().try_into()?,
+        htu: params.htu.as_str().try_into()?,
+        challenge: params.challenge.into(),
+        handle: Handle::from(params.handle.as_str()).try_to_qualified(&client_id.domain)?,
+        team: params.team.into(),
+        extra_claims: None,
+    };
+    Ok(RustyJwtTools::generate_dpop_token(
+        dpop,
+        &client_id,
+        params.backend_nonce.into(),
+        params.audience.parse()?,
+        core::time::Duration::from_secs(params.expiry_secs),
+        SignatureAlgorithm::try_from(params.alg)?.into(),
+        &params.key_pem.into(),
+    )?)
+}
+
+/// Verifies an access token delivered by wire-server, see [RustyJwtTools::verify_access_token]
+#[wasm_bindgen(js_name = verifyAccessToken)]
+pub fn verify_access_token(params: JsAccessTokenParams) -> Result<JsVerifiedAccessToken, JsError> {
+    let params = from_js::<AccessTokenParams>(params.into())?;
+    let client_id = ClientId::try_from_qualified(&params.client_id)?;
+    let handle = Handle::from(params.handle.as_str()).try_to_qualified(&client_id.domain)?;
+    let issuer = Htu::try_from(params.issuer.as_str())?;
+    let backend_pks = params
+        .backend_public_keys
+        .into_iter()
+        .map(Pem::from)
+        .collect::<Vec<_>>();
+    let challenge = AcmeNonce::from(params.challenge);
+    let token = AccessTokenToVerify {
+        access_token: &params.access_token,
+        client_id: &client_id,
+        handle: &handle,
+        challenge: &challenge,
+        client_kid: &params.client_kid,
+    };
+    let options = VerifyAccessTokenOptions::default()
+        .max_skew_secs(params.max_skew_secs)
+        .max_expiration(params.max_expiration)
+        .issuers([issuer])
+        .backend_pks(backend_pks)
+        .hash_algorithm(HashAlgorithm::SHA256)
+        .api_versions(params.api_version);
+    let claims = RustyJwtTools::verify_access_token(&token, &options)?;
+
+    to_js(VerifiedAccessToken {
+        client_id: claims.client_id().to_uri(),
+        handle: claims.handle().to_string(),
+        team: claims.team().to_vec(),
+        api_version: claims.api_version(),
+        jti: claims.jti().to_string(),
+        expires_at: claims.expires_at(),
+    })
+}
+
+/// see [RustyAcme::acme_directory_response]
+#[wasm_bindgen(js_name = acmeDirectoryResponse)]
+pub fn acme_directory_response(directory: JsValue) -> Result<JsAcmeDirectory, JsError> {
+    to_js(RustyAcme::acme_directory_response(from_js(directory)?)?)
+}
+
+/// see [RustyAcme::new_account_request]
+#[wasm_bindgen(js_name = newAccountRequest)]
+pub fn new_account_request(
+    directory: JsAcmeDirectory,
+    alg: SignatureAlgorithm,
+    key_pem: String,
+    previous_nonce: String,
+) -> Result<JsAcmeJws, JsError> {
+    let directory = from_js::<AcmeDirectory>(directory.into())?;
+    let jws = RustyAcme::new_account_request(
+        &directory,
+        JwsAlgorithm::from(alg),
+        &key_pem.into(),
+        None,
+        previous_nonce,
+    )?;
+    to_js(jws)
+}
+
+/// see [RustyAcme::new_account_response]
+#[wasm_bindgen(js_name = newAccountResponse)]
+pub fn new_account_response(account: JsValue) -> Result<JsAcmeAccount, JsError> {
+    to_js(RustyAcme::new_account_response(from_js(account)?)?)
+}
+
+/// see [RustyAcme::new_order_request]
+///
+/// # Arguments
+/// * `client_id` - e.g. `NDUyMGUyMmY2YjA3NGU3NjkyZjE1NjJjZTAwMmQ2NTQ:6add501bacd1d90e@example.com`
+/// * `handle` - without the domain e.g. `alice_wire`
+/// * `expiry_secs` - validity of the certificate from now
+#[wasm_bindgen(js_name = newOrderRequest)]
+#[allow(clippy::too_many_arguments)]
+pub fn new_order_request(
+    display_name: String,
+    client_id: String,
+    handle: String,
+    expiry_secs: u32,
+    directory: JsAcmeDirectory,
+    account: JsAcmeAccount,
+    alg: SignatureAlgorithm,
+    key_pem: String,
+    previous_nonce: String,
+) -> Result<JsAcmeJws, JsError> {
+    let jws = RustyAcme::new_order_request(
+        &display_name,
+        ClientId::try_from_qualified(&client_id)?,
+        &handle.as_str().into(),
+        core::time::Duration::from_secs(expiry_secs.into()),
+        AcmeOrderExtensions::default(),
+        &from_js(directory.into())?,
+        &from_js(account.into())?,
+        JwsAlgorithm::from(alg),
+        &key_pem.into(),
+        previous_nonce,
+    )?;
+    to_js(jws)
+}
+
+/// see [RustyAcme::new_order_response]
+#[wasm_bindgen(js_name = newOrderResponse)]
+pub fn new_order_response(order: JsValue) -> Result<JsAcmeOrder, JsError> {
+    to_js(RustyAcme::new_order_response(from_js(order)?)?)
+}
+
+/// see [RustyAcme::new_authz_request]
+///
+/// # Arguments
+/// * `url` - one of the authorizations of the order
+#[wasm_bindgen(js_name = newAuthzRequest)]
+pub fn new_authz_request(
+    url: String,
+    account: JsAcmeAccount,
+    alg: SignatureAlgorithm,
+    key_pem: String,
+    previous_nonce: String,
+) -> Result<JsAcmeJws, JsError> {
+    let jws = RustyAcme::new_authz_request(
+        &url.parse()?,
+        &from_js(account.into())?,
+        JwsAlgorithm::from(alg),
+        &key_pem.into(),
+        previous_nonce,
+    )?;
+    to_js(jws)
+}
+
+/// see [RustyAcme::new_authz_response]
+#[wasm_bindgen(js_name = newAuthzResponse)]
+pub fn new_authz_response(authz: JsValue) -> Result<JsAcmeAuthz, JsError> {
+    to_js(RustyAcme::new_authz_response(from_js(authz)?)?)
+}
+
+/// see [RustyAcme::dpop_chall_request]
+#[wasm_bindgen(js_name = dpopChallengeRequest)]
+pub fn dpop_chall_request(
+    access_token: String,
+    dpop_chall: JsAcmeChallenge,
+    account: JsAcmeAccount,
+    alg: SignatureAlgorithm,
+    key_pem: String,
+    previous_nonce: String,
+) -> Result<JsAcmeJws, JsError> {
+    let jws = RustyAcme::dpop_chall_request(
+        access_token,
+        from_js(dpop_chall.into())?,
+        &from_js(account.into())?,
+        JwsAlgorithm::from(alg),
+        &key_pem.into(),
+        previous_nonce,
+    )?;
+    to_js(jws)
+}
+
+/// see [RustyAcme::oidc_chall_request]
+#[wasm_bindgen(js_name = oidcChallengeRequest)]
+pub fn oidc_chall_request(
+    id_token: String,
+    oidc_chall: JsAcmeChallenge,
+    account: JsAcmeAccount,
+    alg: SignatureAlgorithm,
+    key_pem: String,
+    previous_nonce: String,
+) -> Result<JsAcmeJws, JsError> {
+    let jws = RustyAcme::oidc_chall_request(
+        id_token,
+        from_js(oidc_chall.into())?,
+        &from_js(account.into())?,
+        JwsAlgorithm::from(alg),
+        &key_pem.into(),
+        previous_nonce,
+    )?;
+    to_js(jws)
+}
+
+/// see [RustyAcme::new_chall_response]
+#[wasm_bindgen(js_name = newChallengeResponse)]
+pub fn new_chall_response(challenge: JsValue) -> Result<JsAcmeChallenge, JsError> {
+    to_js(RustyAcme::new_chall_response(from_js(challenge)?)?)
+}
+
+/// see [RustyAcme::check_order_request]
+///
+/// # Arguments
+/// * `order_url` - 'location' header of the new order response
+#[wasm_bindgen(js_name = checkOrderRequest)]
+pub fn check_order_request(
+    order_url: String,
+    account: JsAcmeAccount,
+    alg: SignatureAlgorithm,
+    key_pem: String,
+    previous_nonce: String,
+) -> Result<JsAcmeJws, JsError> {
+    let jws = RustyAcme::check_order_request(
+        order_url.parse()?,
+        &from_js(account.into())?,
+        JwsAlgorithm::from(alg),
+        &key_pem.into(),
+        previous_nonce,
+    )?;
+    to_js(jws)
+}
+
+/// see [RustyAcme::check_order_response]
+#[wasm_bindgen(js_name = checkOrderResponse)]
+pub fn check_order_response(order: JsValue) -> Result<JsAcmeOrder, JsError> {
+    to_js(RustyAcme::check_order_response(from_js(order)?)?)
+}
+
+/// see [RustyAcme::finalize_req]
+///
+/// # Arguments
+/// * `alg`/`key_pem` - account key, signing the request
+/// * `signing_key_pem` - client key, signing the CSR
+#[wasm_bindgen(js_name = finalizeRequest)]
+pub fn finalize_request(
+    order: JsAcmeOrder,
+    account: JsAcmeAccount,
+    alg: SignatureAlgorithm,
+    key_pem: String,
+    signing_key_pem: String,
+    previous_nonce: String,
+) -> Result<JsAcmeJws, JsError> {
+    let jws = RustyAcme::finalize_req(
+        &from_js(order.into())?,
+        &from_js(account.into())?,
+        JwsAlgorithm::from(alg),
+        &key_pem.into(),
+        &signing_key_pem.into(),
+        previous_nonce,
+    )?;
+    to_js(jws)
+}
+
+/// see [RustyAcme::finalize_response]
+#[wasm_bindgen(js_name = finalizeResponse)]
+pub fn finalize_response(finalize: JsValue) -> Result<JsAcmeFinalize, JsError> {
+    to_js(RustyAcme::finalize_response(from_js(finalize)?)?)
+}
+
+/// see [RustyAcme::certificate_req]
+#[wasm_bindgen(js_name = certificateRequest)]
+pub fn certificate_request(
+    finalize: JsAcmeFinalize,
+    account: JsAcmeAccount,
+    alg: SignatureAlgorithm,
+    key_pem: String,
+    previous_nonce: String,
+) -> Result<JsAcmeJws, JsError> {
+    let jws = RustyAcme::certificate_req(
+        from_js(finalize.into())?,
+        from_js(account.into())?,
+        JwsAlgorithm::from(alg),
+        &key_pem.into(),
+        previous_nonce,
+    )?;
+    to_js(jws)
+}
+
+/// see [RustyAcme::certificate_response]. Returns the DER certificates, leaf first.
+///
+/// # Arguments
+/// * `response` - PEM bundle
+#[wasm_bindgen(js_name = certificateResponse)]
+pub fn certificate_response(response: String, order: JsAcmeOrder) -> Result<Vec<js_sys::Uint8Array>, JsError> {
+    let chain = RustyAcme::certificate_response(response, from_js(order.into())?)?;
+    Ok(chain
+        .iter()
+        .map(|der| js_sys::Uint8Array::from(der.as_slice()))
+        .collect())
+}
+
+fn from_js<T: DeserializeOwned>(value: JsValue) -> Result<T, JsError> {
+    Ok(serde_wasm_bindgen::from_value(value)?)
+}
+
+/// JSON compatible so that resources can be passed to `JSON.stringify`
+fn to_js<T: Serialize, J: JsCast>(value: T) -> Result<J, JsError> {
+    let serializer = serde_wasm_bindgen::Serializer::json_compatible();
+    Ok(value.serialize(&serializer)?.unchecked_into())
+}
@@ -0,0 +1,136 @@
+use std::ops::{RangeFrom, RangeInclusive};
+
+use jwt_simple::prelude::*;
+
+use crate::prelude::*;
+
+/// wire-server API versions a verifier accepts in the access token 'api_version' claim, as an
+/// inclusive range so that a verifier keeps on accepting tokens while clients catch up on a new version.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize)]
+pub struct SupportedApiVersions {
+    min: u32,
+    max: u32,
+}
+
+impl SupportedApiVersions {
+    /// Accepts every version in `range`. Fails when `range` is empty i.e. its start is greater than its end
+    pub fn new(range: RangeInclusive<u32>) -> RustyJwtResult<Self> {
+        let (min, max) = range.into_inner();
+        if min > max {
+            return Err(RustyJwtError::InvalidApiVersionRange(min, max));
+        }
+        Ok(Self { min, max })
+    }
+
+    /// Oldest supported version
+    pub fn min(&self) -> u32 {
+        self.min
+    }
+
+    /// Latest supported version
+    pub fn max(&self) -> u32 {
+        self.max
+    }
+
+    /// Whether `api_version` is supported
+    pub fn contains(&self, api_version: u32) -> bool {
+        (self.min..=self.max).contains(&api_version)
+    }
+
+    /// Fails with the unsupported version when `api_version` is not supported
+    pub fn verify(&self, api_version: u32) -> RustyJwtResult<()> {
+        if !self.contains(api_version) {
+            return Err(RustyJwtError::UnsupportedApiVersion(api_version));
+        }
+        Ok(())
+    }
+}
+
+impl From<u32> for SupportedApiVersions {
+    fn from(api_version: u32) -> Self {
+        Self {
+            min: api_version,
+            max: api_version,
+        }
+    }
+}
+
+impl TryFrom<RangeInclusive<u32>> for SupportedApiVersions {
+    type Error = RustyJwtError;
+
+    fn try_from(range: RangeInclusive<u32>) -> RustyJwtResult<Self> {
+        Self::new(range)
+    }
+}
+
+impl From<RangeFrom<u32>> for SupportedApiVersions {
+    fn from(range: RangeFrom<u32>) -> Self {
+        Self {
+            min: range.start,
+            max: u32::MAX,
+        }
+    }
+}
+
+impl std::fmt::Display for SupportedApiVersions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.min == self.max {
+            write!(f, "{}", self.min)
+        } else {
+            write!(f, "{}..={}", self.min, self.max)
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use wasm_bindgen_test::*;
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_accept_versions_in_range() {
+        let versions = SupportedApiVersions::try_from(5..=7).unwrap();
+        assert!(versions.verify(5).is_ok());
+        assert!(versions.verify(7).is_ok());
+        assert!(matches!(
+            versions.verify(4).unwrap_err(),
+            RustyJwtError::UnsupportedApiVersion(4)
+        ));
+        assert!(matches!(
+            versions.verify(8).unwrap_err(),
+            RustyJwtError::UnsupportedApiVersion(8)
+        ));
+        assert_eq!(versions.to_string(), "5..=7");
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_accept_single_version() {
+        let versions = SupportedApiVersions::from(5);
+        assert!(versions.contains(5));
+        assert!(!versions.contains(6));
+        assert_eq!(versions.to_string(), "5");
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_accept_open_range() {
+        let versions = SupportedApiVersions::from(5..);
+        assert!(versions.contains(u32::MAX));
+        assert!(!versions.contains(4));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_fail_when_range_empty() {
+        assert!(matches!(
+            SupportedApiVersions::new(7..=5).unwrap_err(),
+            RustyJwtError::InvalidApiVersionRange(7, 5)
+        ));
+        assert!(SupportedApiVersions::try_from(5..=5).is_ok());
+    }
+}
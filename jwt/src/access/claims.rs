@@ -0,0 +1,133 @@
+use jwt_simple::prelude::*;
+
+use crate::{
+    access::{Access, AccessScope},
+    prelude::*,
+};
+
+/// Claims of an access token which passed [RustyJwtTools::verify_access_token], along with the ones
+/// of its embedded DPoP proof. Unlike [IntrospectedAccessToken] they can be trusted.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct AccessTokenClaims {
+    client_id: ClientId,
+    handle: QualifiedHandle,
+    team: Team,
+    challenge: AcmeNonce,
+    cnf: Cnf,
+    proof: String,
+    api_version: u32,
+    scope: AccessScope,
+    issuer: Htu,
+    audiences: HashSet<String>,
+    jti: String,
+    nonce: BackendNonce,
+    issued_at: Option<u64>,
+    not_before: Option<u64>,
+    expires_at: u64,
+    extra_claims: Option<serde_json::Value>,
+}
+
+impl AccessTokenClaims {
+    /// Builds them out of already verified access token and DPoP proof claims
+    pub(crate) fn try_new(access: JWTClaims<Access>, proof: JWTClaims<Dpop>) -> RustyJwtResult<Self> {
+        let missing = RustyJwtError::MissingTokenClaim;
+        Ok(Self {
+            client_id: ClientId::try_from_uri(&access.custom.client_id)?,
+            handle: proof.custom.handle,
+            team: proof.custom.team,
+            challenge: access.custom.challenge,
+            cnf: access.custom.cnf,
+            proof: access.custom.proof,
+            api_version: access.custom.api_version,
+            scope: access.custom.scope.as_str().into(),
+            issuer: access.issuer.as_deref().ok_or(missing("iss"))?.try_into()?,
+            audiences: access.audiences.map(Audiences::into_set).unwrap_or_default(),
+            jti: access.jwt_id.ok_or(missing("jti"))?,
+            nonce: access.nonce.ok_or(missing("nonce"))?.into(),
+            issued_at: access.issued_at.map(|t| t.as_secs()),
+            not_before: access.invalid_before.map(|t| t.as_secs()),
+            expires_at: access.expires_at.ok_or(missing("exp"))?.as_secs(),
+            extra_claims: access.custom.extra_claims,
+        })
+    }
+
+    /// Client the access token has been delivered to, from the 'client_id' claim
+    pub fn client_id(&self) -> &ClientId {
+        &self.client_id
+    }
+
+    /// Handle of the client, from the embedded proof 'handle' claim
+    pub fn handle(&self) -> &QualifiedHandle {
+        &self.handle
+    }
+
+    /// Team of the client, from the embedded proof 'team' claim
+    pub fn team(&self) -> &Team {
+        &self.team
+    }
+
+    /// ACME challenge the access token answers, from the 'chal' claim
+    pub fn challenge(&self) -> &AcmeNonce {
+        &self.challenge
+    }
+
+    /// Binding to the client's DPoP key, from the 'cnf' claim
+    pub fn cnf(&self) -> &Cnf {
+        &self.cnf
+    }
+
+    /// Embedded DPoP proof, in JWS Compact Serialization format
+    pub fn proof(&self) -> &str {
+        &self.proof
+    }
+
+    /// wire-server API version, from the 'api_version' claim
+    pub fn api_version(&self) -> u32 {
+        self.api_version
+    }
+
+    /// Scope tokens granted, from the 'scope' claim
+    pub fn scope(&self) -> &AccessScope {
+        &self.scope
+    }
+
+    /// wire-server endpoint which delivered the access token, from the 'iss' claim
+    pub fn issuer(&self) -> &Htu {
+        &self.issuer
+    }
+
+    /// Intended recipients of the access token, from the 'aud' claim. Empty when absent
+    pub fn audiences(&self) -> &HashSet<String> {
+        &self.audiences
+    }
+
+    /// Unique identifier of the access token, from the 'jti' claim
+    pub fn jti(&self) -> &str {
+        &self.jti
+    }
+
+    /// wire-server nonce, from the 'nonce' claim
+    pub fn nonce(&self) -> &BackendNonce {
+        &self.nonce
+    }
+
+    /// Issuance time in seconds since epoch, from the 'iat' claim
+    pub fn issued_at(&self) -> Option<u64> {
+        self.issued_at
+    }
+
+    /// Time before which the access token is invalid, in seconds since epoch, from the 'nbf' claim
+    pub fn not_before(&self) -> Option<u64> {
+        self.not_before
+    }
+
+    /// Expiration time in seconds since epoch, from the 'exp' claim
+    pub fn expires_at(&self) -> u64 {
+        self.expires_at
+    }
+
+    /// Claims not part of the access token claim set, if any
+    pub fn extra_claims(&self) -> Option<&serde_json::Value> {
+        self.extra_claims.as_ref()
+    }
+}
@@ -0,0 +1,102 @@
+use jwt_simple::prelude::*;
+
+use crate::{jwk_thumbprint::JwkThumbprint, prelude::*};
+
+/// Confirmation claim binding an access token to the client DPoP key, by its key identifier, by
+/// the key itself or both
+///
+/// Specified in [RFC 7800 Section 3.1][1]
+///
+/// [1]: https://www.rfc-editor.org/rfc/rfc7800#section-3.1
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Cnf {
+    /// Key identifier of the client key, see [KidStrategy]
+    #[serde(rename = "kid", skip_serializing_if = "Option::is_none")]
+    pub kid: Option<String>,
+    /// Client public key
+    #[serde(rename = "jwk", skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(
+        feature = "schema",
+        schemars(with = "Option<serde_json::Map<String, serde_json::Value>>")
+    )]
+    pub jwk: Option<Jwk>,
+}
+
+impl Cnf {
+    /// Whether it confirms the client key [jwk] whose key identifier is [kid]. Fails when it holds
+    /// neither a key identifier nor a key.
+    pub(crate) fn confirms(&self, alg: JwsAlgorithm, jwk: &Jwk, kid: &JwkThumbprint) -> RustyJwtResult<bool> {
+        if self.kid.is_none() && self.jwk.is_none() {
+            return Err(RustyJwtError::MissingTokenClaim("cnf"));
+        }
+        let kid_matches = self.kid.as_ref().map(|k| k == &kid.kid).unwrap_or(true);
+        let jwk_matches = self
+            .jwk
+            .as_ref()
+            .map(|cnf_jwk| AnyPublicKey::from((alg, cnf_jwk)) == AnyPublicKey::from((alg, jwk)))
+            .unwrap_or(true);
+        Ok(kid_matches && jwk_matches)
+    }
+}
+
+impl From<JwkThumbprint> for Cnf {
+    fn from(thumbprint: JwkThumbprint) -> Self {
+        Self {
+            kid: Some(thumbprint.kid),
+            jwk: None,
+        }
+    }
+}
+
+// compares the JSON representation of the JWK so that its field order does not matter
+impl PartialEq for Cnf {
+    fn eq(&self, other: &Self) -> bool {
+        let jwk = |cnf: &Self| cnf.jwk.as_ref().and_then(|jwk| serde_json::to_value(jwk).ok());
+        self.kid == other.kid && jwk(self) == jwk(other)
+    }
+}
+
+impl Eq for Cnf {}
+
+#[cfg(test)]
+pub mod tests {
+    use wasm_bindgen_test::*;
+
+    use crate::test_utils::*;
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[apply(all_ciphersuites)]
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_confirm_either_form(ciphersuite: Ciphersuite) {
+        let (alg, jwk) = (ciphersuite.key.alg, ciphersuite.key.to_jwk());
+        let kid = JwkThumbprint::generate(&jwk, ciphersuite.hash).unwrap();
+        let other = ciphersuite.key.create_another().to_jwk();
+        let other_kid = JwkThumbprint::generate(&other, ciphersuite.hash).unwrap();
+
+        let by_kid = Cnf::from(kid.clone());
+        assert!(by_kid.confirms(alg, &jwk, &kid).unwrap());
+        assert!(!by_kid.confirms(alg, &other, &other_kid).unwrap());
+
+        let by_jwk = Cnf {
+            kid: None,
+            jwk: Some(jwk.clone()),
+        };
+        assert!(by_jwk.confirms(alg, &jwk, &kid).unwrap());
+        assert!(!by_jwk.confirms(alg, &other, &other_kid).unwrap());
+
+        // both have to match
+        let both = Cnf {
+            kid: Some(kid.kid.clone()),
+            jwk: Some(other.clone()),
+        };
+        assert!(!both.confirms(alg, &jwk, &kid).unwrap());
+
+        let result = Cnf::default().confirms(alg, &jwk, &kid);
+        assert!(matches!(result.unwrap_err(), RustyJwtError::MissingTokenClaim("cnf")));
+    }
+}
@@ -1,10 +1,11 @@
 use jwt_simple::{prelude::*, token::Token};
 
 use crate::{
-    access::Access,
+    access::{Access, AccessScope},
     dpop::{VerifyDpop, VerifyDpopTokenHeader},
     jwk::TryIntoJwk,
-    jwk_thumbprint::JwkThumbprint,
+    jwt::{report::DPOP_PROOF, TokenLimits},
+    model::claims_version::Versioned,
     prelude::*,
 };
 
@@ -14,6 +15,7 @@ impl RustyJwtTools {
     ///
     /// Verifications:
     /// * [dpop_proof] has the correct syntax
+    /// * [dpop_proof] fits in the default [TokenLimits]
     /// * `typ` header field is "dpop+jwt"
     /// * signature algorithm (alg) in JWT header is a supported algorithm
     /// * signature corresponds to the public key (jwk) in the JWT header
@@ -41,7 +43,10 @@ impl RustyJwtTools {
     /// * `hash_algorithm` - to calculate JWK thumbprint
     /// * `api_version` - version of wire-server http API
     /// * `expiry` - access token 'exp' (expiry)
+    ///
+    /// Prefer [AccessTokenBuilder] which names every argument.
     #[allow(clippy::too_many_arguments)]
+    #[deprecated(since = "0.9.0", note = "use `AccessTokenBuilder` instead")]
     pub fn generate_access_token(
         dpop_proof: &str,
         client_id: &ClientId,
@@ -57,33 +62,79 @@ impl RustyJwtTools {
         api_version: u32,
         expiry: core::time::Duration,
     ) -> RustyJwtResult<String> {
-        let header = Token::decode_metadata(dpop_proof)?;
-        let (alg, jwk) = header.verify_dpop_header()?;
-        let proof_claims = dpop_proof.verify_client_dpop(
-            alg,
-            jwk,
-            client_id,
-            &handle,
-            &team,
-            &backend_nonce,
-            None,
-            Some(method),
-            &uri,
-            max_expiration,
-            max_skew_secs,
-        )?;
-        Self::access_token(
-            alg,
-            jwk,
-            dpop_proof,
-            proof_claims,
-            backend_keys,
-            client_id,
-            backend_nonce,
-            hash_algorithm,
-            api_version,
-            expiry,
-        )
+        let mut builder = AccessTokenBuilder::default()
+            .dpop_proof(dpop_proof)
+            .client_id(client_id)
+            .handle(handle)
+            .team(team)
+            .backend_nonce(backend_nonce)
+            .htu(uri)
+            .htm(method)
+            .max_skew_secs(max_skew_secs)
+            .max_expiration(max_expiration)
+            .backend_keys(backend_keys)
+            .hash_algorithm(hash_algorithm)
+            .api_version(api_version)
+            .expiry(expiry);
+        // kept lenient for backward compatibility, only the builder rejects a zero expiry
+        builder.allow_zero_expiry = true;
+        builder.build()
+    }
+
+    /// Performs the same checks on [dpop_proof] as [AccessTokenBuilder::build] but, instead
+    /// of stopping at the first error, performs every check possible and reports their outcome.
+    /// Meant for troubleshooting a failing enrollment.
+    ///
+    /// # Arguments
+    /// * see [AccessTokenBuilder]
+    #[allow(clippy::too_many_arguments)]
+    pub fn verify_dpop_proof_with_report(
+        dpop_proof: &str,
+        client_id: &ClientId,
+        handle: &QualifiedHandle,
+        team: &Team,
+        team_policy: TeamPolicy,
+        backend_nonce: &BackendNonce,
+        uri: &Htu,
+        method: Htm,
+        max_skew_secs: u16,
+        max_expiration: u64,
+        claims_versions: &[ClaimsVersion],
+        domains: &DomainPolicy,
+    ) -> VerificationReport {
+        let mut report = VerificationReport::default();
+        let mut verify = || -> RustyJwtResult<()> {
+            let limits = TokenLimits::default();
+            report.require(DPOP_PROOF, "limits", limits.verify(dpop_proof))?;
+            let header = report.require(
+                DPOP_PROOF,
+                "syntax",
+                Token::decode_metadata(dpop_proof).map_err(RustyJwtError::from),
+            )?;
+            let (alg, jwk) = report.require(DPOP_PROOF, "header", header.verify_dpop_header())?;
+            dpop_proof.verify_client_dpop_with_report(
+                alg,
+                jwk,
+                client_id,
+                handle,
+                team,
+                team_policy,
+                backend_nonce,
+                None,
+                Some(method),
+                uri,
+                max_expiration,
+                max_skew_secs,
+                limits,
+                claims_versions,
+                domains,
+                &mut report,
+            )?;
+            Ok(())
+        };
+        // the failing check, if any, is already part of the report
+        let _ = verify();
+        report
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -95,33 +146,45 @@ impl RustyJwtTools {
         backend_keys: Pem,
         client_id: &ClientId,
         nonce: BackendNonce,
-        hash: HashAlgorithm,
+        kid: &KidStrategy,
+        cnf_jwk: bool,
         api_version: u32,
+        scope: &AccessScope,
+        audiences: Option<Vec<url::Url>>,
         expiry: core::time::Duration,
     ) -> RustyJwtResult<String> {
-        let header = Self::new_access_header(alg);
-
-        let with_jwk = |jwk: Jwk| KeyMetadata::default().with_public_key(jwk);
         let claims = {
-            let audience = proof_claims
-                .audiences
-                .ok_or(RustyJwtError::MissingTokenClaim("aud"))?
-                .into_string()
-                .map_err(|_| RustyJwtError::InvalidAudience)?
-                .parse::<url::Url>()
-                .map_err(|_| RustyJwtError::InvalidAudience)?;
-            let cnf = JwkThumbprint::generate(client_jwk, hash)?;
+            let audiences = match audiences {
+                Some(audiences) => audiences,
+                None => Self::proof_audiences(proof_claims.audiences)?,
+            };
+            let cnf = Cnf {
+                kid: Some(kid.derive(alg, client_jwk)?.kid),
+                jwk: cnf_jwk.then(|| client_jwk.clone()),
+            };
             Access {
                 challenge: proof_claims.custom.challenge,
                 cnf,
                 proof: proof.to_string(),
                 client_id: client_id.to_uri(),
                 api_version,
-                scope: Access::DEFAULT_SCOPE.to_string(),
+                scope: scope.to_string(),
                 extra_claims: proof_claims.custom.extra_claims,
             }
-            .into_jwt_claims(client_id, nonce, proof_claims.custom.htu, audience, expiry)
+            .into_jwt_claims(client_id, nonce, proof_claims.custom.htu, audiences, expiry)
         };
+        Self::sign_access_token(alg, claims, &backend_keys)
+    }
+
+    /// Signs the access token claims with the backend key pair, advertising its public key in the 'jwk' header
+    pub(super) fn sign_access_token(
+        alg: JwsAlgorithm,
+        claims: JWTClaims<Access>,
+        backend_keys: &Pem,
+    ) -> RustyJwtResult<String> {
+        let header = Self::new_access_header(alg);
+        let claims = Versioned::current(claims);
+        let with_jwk = |jwk: Jwk| KeyMetadata::default().with_public_key(jwk);
         Ok(match alg {
             JwsAlgorithm::P256 => {
                 let mut kp = ES256KeyPair::from_pem(backend_keys.as_str())
@@ -147,6 +210,23 @@ impl RustyJwtTools {
         })
     }
 
+    /// The access token is by default meant for the same audiences as the DPoP proof
+    pub(super) fn proof_audiences(audiences: Option<Audiences>) -> RustyJwtResult<Vec<url::Url>> {
+        let audiences = match audiences.ok_or(RustyJwtError::MissingTokenClaim("aud"))? {
+            Audiences::AsString(audience) => vec![audience],
+            Audiences::AsSet(audiences) => audiences.into_iter().collect(),
+        };
+        if audiences.is_empty() {
+            return Err(RustyJwtError::InvalidAudience);
+        }
+        let mut audiences = audiences
+            .iter()
+            .map(|aud| aud.parse::<url::Url>().map_err(|_| RustyJwtError::InvalidAudience))
+            .collect::<RustyJwtResult<Vec<_>>>()?;
+        audiences.sort();
+        Ok(audiences)
+    }
+
     fn new_access_header(alg: JwsAlgorithm) -> JWTHeader {
         JWTHeader {
             algorithm: alg.to_string(),
@@ -156,6 +236,288 @@ impl RustyJwtTools {
     }
 }
 
+/// Builds an introspectable DPoP access token out of a client's DPoP proof, after verifying it.
+/// See [RustyJwtTools::generate_access_token] for the verifications performed.
+///
+/// `team`, `htm`, `scope`, `audiences` and `limits` have defaults, every other field is required.
+#[derive(Debug, Clone)]
+pub struct AccessTokenBuilder<'a> {
+    dpop_proof: Option<&'a str>,
+    client_id: Option<&'a ClientId>,
+    handle: Option<QualifiedHandle>,
+    team: Team,
+    team_policy: TeamPolicy,
+    claims_versions: Vec<ClaimsVersion>,
+    domains: DomainPolicy,
+    backend_nonce: Option<BackendNonce>,
+    htu: Option<Htu>,
+    htm: Htm,
+    max_skew_secs: Option<u16>,
+    max_expiration: Option<u64>,
+    backend_keys: Option<Pem>,
+    hash_algorithm: Option<HashAlgorithm>,
+    kid_strategy: Option<KidStrategy>,
+    cnf_jwk: bool,
+    api_version: Option<u32>,
+    scope: AccessScope,
+    audiences: Option<Vec<url::Url>>,
+    expiry: Option<core::time::Duration>,
+    allow_zero_expiry: bool,
+    limits: TokenLimits,
+    #[cfg(feature = "jwe")]
+    encrypt_for: Option<Pem>,
+}
+
+impl Default for AccessTokenBuilder<'_> {
+    fn default() -> Self {
+        Self {
+            dpop_proof: None,
+            client_id: None,
+            handle: None,
+            team: Team::none(),
+            team_policy: TeamPolicy::default(),
+            claims_versions: ClaimsVersion::ALL.to_vec(),
+            domains: DomainPolicy::default(),
+            backend_nonce: None,
+            htu: None,
+            htm: Htm::Post,
+            max_skew_secs: None,
+            max_expiration: None,
+            backend_keys: None,
+            hash_algorithm: None,
+            kid_strategy: None,
+            cnf_jwk: false,
+            api_version: None,
+            scope: AccessScope::default(),
+            audiences: None,
+            expiry: None,
+            allow_zero_expiry: false,
+            limits: TokenLimits::default(),
+            #[cfg(feature = "jwe")]
+            encrypt_for: None,
+        }
+    }
+}
+
+impl<'a> AccessTokenBuilder<'a> {
+    /// DPoP proof from the client in JWS Compact Serialization format
+    pub fn dpop_proof(mut self, dpop_proof: &'a str) -> Self {
+        self.dpop_proof = Some(dpop_proof);
+        self
+    }
+
+    /// see [ClientId]
+    pub fn client_id(mut self, client_id: &'a ClientId) -> Self {
+        self.client_id = Some(client_id);
+        self
+    }
+
+    /// see [QualifiedHandle]
+    pub fn handle(mut self, handle: QualifiedHandle) -> Self {
+        self.handle = Some(handle);
+        self
+    }
+
+    /// Team the client belongs to. Defaults to none i.e. a personal user
+    pub fn team(mut self, team: Team) -> Self {
+        self.team = team;
+        self
+    }
+
+    /// How the proof 'team' claim is verified against [AccessTokenBuilder::team]. Defaults to
+    /// [TeamPolicy::Lenient]
+    pub fn team_policy(mut self, team_policy: TeamPolicy) -> Self {
+        self.team_policy = team_policy;
+        self
+    }
+
+    /// Schema versions of the DPoP proof claims accepted. Defaults to [ClaimsVersion::ALL]
+    pub fn claims_versions(mut self, claims_versions: Vec<ClaimsVersion>) -> Self {
+        self.claims_versions = claims_versions;
+        self
+    }
+
+    /// Domains the client and its handle may belong to. Defaults to [DomainPolicy::Any]
+    pub fn domains(mut self, domains: DomainPolicy) -> Self {
+        self.domains = domains;
+        self
+    }
+
+    /// The most recent DPoP nonce provided by the backend to the client
+    pub fn backend_nonce(mut self, backend_nonce: BackendNonce) -> Self {
+        self.backend_nonce = Some(backend_nonce);
+        self
+    }
+
+    /// The HTTPS URI on the backend for the DPoP auth token endpoint
+    pub fn htu(mut self, htu: Htu) -> Self {
+        self.htu = Some(htu);
+        self
+    }
+
+    /// The HTTPS method used on the backend for the DPoP auth token endpoint. Defaults to [Htm::Post]
+    pub fn htm(mut self, htm: Htm) -> Self {
+        self.htm = htm;
+        self
+    }
+
+    /// The maximum number of seconds of clock skew allowed
+    pub fn max_skew_secs(mut self, max_skew_secs: u16) -> Self {
+        self.max_skew_secs = Some(max_skew_secs);
+        self
+    }
+
+    /// The maximal expiration date and time of the DPoP proof, in seconds since epoch
+    pub fn max_expiration(mut self, max_expiration: u64) -> Self {
+        self.max_expiration = Some(max_expiration);
+        self
+    }
+
+    /// PEM format concatenated private key and public key of the Wire backend
+    pub fn backend_keys(mut self, backend_keys: Pem) -> Self {
+        self.backend_keys = Some(backend_keys);
+        self
+    }
+
+    /// To calculate the JWK thumbprint
+    pub fn hash_algorithm(mut self, hash_algorithm: HashAlgorithm) -> Self {
+        self.hash_algorithm = Some(hash_algorithm);
+        self
+    }
+
+    /// How the access token 'cnf' claim is derived from the client JWK, a [KidStrategy::Thumbprint]
+    /// with [Self::hash_algorithm] by default. When set, [Self::hash_algorithm] is not required.
+    pub fn kid_strategy(mut self, kid_strategy: KidStrategy) -> Self {
+        self.kid_strategy = Some(kid_strategy);
+        self
+    }
+
+    /// Whether to also embed the client JWK in the 'cnf' claim, along with its key identifier, for
+    /// consumers only understanding the 'jwk' confirmation method of RFC 7800. False by default.
+    /// Always embedded with a [KidStrategy::Custom] which does not bind the key by itself.
+    pub fn cnf_jwk(mut self, cnf_jwk: bool) -> Self {
+        self.cnf_jwk = cnf_jwk;
+        self
+    }
+
+    /// Version of wire-server http API
+    pub fn api_version(mut self, api_version: u32) -> Self {
+        self.api_version = Some(api_version);
+        self
+    }
+
+    /// What the access token grants access to. Defaults to the wire enrollment scope
+    pub fn scope(mut self, scope: impl Into<AccessScope>) -> Self {
+        self.scope = scope.into();
+        self
+    }
+
+    /// Audiences the access token is meant for. Defaults to the DPoP proof 'aud' claim
+    pub fn audiences(mut self, audiences: impl IntoIterator<Item = url::Url>) -> Self {
+        self.audiences = Some(audiences.into_iter().collect());
+        self
+    }
+
+    /// Lifetime of the access token, its 'exp' claim being now plus this
+    pub fn expiry(mut self, expiry: core::time::Duration) -> Self {
+        self.expiry = Some(expiry);
+        self
+    }
+
+    /// Size limits enforced before parsing the DPoP proof. Defaults to [TokenLimits::default]
+    pub fn limits(mut self, limits: TokenLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Wraps the signed access token in a JWE only the holder of this P-256 key can decrypt, so
+    /// that intermediaries cannot read its claims. See [RustyJwtTools::decrypt_access_token]
+    #[cfg(feature = "jwe")]
+    pub fn encrypt_for(mut self, recipient_pk: Pem) -> Self {
+        self.encrypt_for = Some(recipient_pk);
+        self
+    }
+
+    /// Verifies the DPoP proof and, if valid, returns an access token in JWS Compact Serialization
+    /// format, nested in a JWE Compact Serialization one when encrypted
+    pub fn build(self) -> RustyJwtResult<String> {
+        let dpop_proof = self
+            .dpop_proof
+            .ok_or(RustyJwtError::MissingBuilderField("dpop_proof"))?;
+        let client_id = self.client_id.ok_or(RustyJwtError::MissingBuilderField("client_id"))?;
+        let handle = self.handle.ok_or(RustyJwtError::MissingBuilderField("handle"))?;
+        let backend_nonce = self
+            .backend_nonce
+            .ok_or(RustyJwtError::MissingBuilderField("backend_nonce"))?;
+        let htu = self.htu.ok_or(RustyJwtError::MissingBuilderField("htu"))?;
+        let max_skew_secs = self
+            .max_skew_secs
+            .ok_or(RustyJwtError::MissingBuilderField("max_skew_secs"))?;
+        let max_expiration = self
+            .max_expiration
+            .ok_or(RustyJwtError::MissingBuilderField("max_expiration"))?;
+        let backend_keys = self
+            .backend_keys
+            .ok_or(RustyJwtError::MissingBuilderField("backend_keys"))?;
+        let kid_strategy = match (self.kid_strategy, self.hash_algorithm) {
+            (Some(kid_strategy), _) => kid_strategy,
+            (None, Some(hash_algorithm)) => KidStrategy::Thumbprint(hash_algorithm),
+            (None, None) => return Err(RustyJwtError::MissingBuilderField("hash_algorithm")),
+        };
+        let api_version = self
+            .api_version
+            .ok_or(RustyJwtError::MissingBuilderField("api_version"))?;
+        let expiry = self.expiry.ok_or(RustyJwtError::MissingBuilderField("expiry"))?;
+        if expiry.is_zero() && !self.allow_zero_expiry {
+            return Err(RustyJwtError::InvalidBuilderField("expiry", "it cannot be zero"));
+        }
+        if matches!(&self.audiences, Some(audiences) if audiences.is_empty()) {
+            return Err(RustyJwtError::InvalidBuilderField("audiences", "it cannot be empty"));
+        }
+
+        self.limits.verify(dpop_proof)?;
+        let header = Token::decode_metadata(dpop_proof)?;
+        let (alg, jwk) = header.verify_dpop_header()?;
+        let proof_claims = dpop_proof.verify_client_dpop(
+            alg,
+            jwk,
+            client_id,
+            &handle,
+            &self.team,
+            self.team_policy,
+            &backend_nonce,
+            None,
+            Some(self.htm),
+            &htu,
+            max_expiration,
+            max_skew_secs,
+            self.limits,
+            &self.claims_versions,
+            &self.domains,
+        )?;
+        let access_token = RustyJwtTools::access_token(
+            alg,
+            jwk,
+            dpop_proof,
+            proof_claims,
+            backend_keys,
+            client_id,
+            backend_nonce,
+            &kid_strategy,
+            self.cnf_jwk || !kid_strategy.binds_key(),
+            api_version,
+            &self.scope,
+            self.audiences,
+            expiry,
+        )?;
+        #[cfg(feature = "jwe")]
+        if let Some(recipient_pk) = &self.encrypt_for {
+            return RustyJwtTools::jwe_encrypt_ecdh_es(recipient_pk, access_token.as_bytes(), Some("JWT"));
+        }
+        Ok(access_token)
+    }
+}
+
 #[cfg(test)]
 pub mod tests {
     use base64::Engine;
@@ -298,7 +660,50 @@ pub mod tests {
 
                 let backend_key = JwtKey::from((ciphersuite.key.alg, backend_key));
                 let claims = backend_key.claims::<Access>(&token);
-                assert_eq!(claims.custom.cnf, expected_cnf);
+                assert_eq!(claims.custom.cnf, Cnf::from(expected_cnf));
+            }
+
+            #[apply(all_ciphersuites)]
+            #[test]
+            fn should_derive_cnf_with_kid_strategy(ciphersuite: Ciphersuite) {
+                let dpop = DpopBuilder::from(ciphersuite.key.clone()).build();
+
+                let params = Params::from(ciphersuite.clone());
+                let backend_key = params.backend_keys.clone();
+                let kid = KidStrategy::Custom("client-key-1".to_string());
+                let token = access_token_with(&dpop, params, |b| b.kid_strategy(kid)).unwrap();
+
+                let backend_key = JwtKey::from((ciphersuite.key.alg, backend_key));
+                let claims = backend_key.claims::<Access>(&token);
+                assert_eq!(claims.custom.cnf.kid.as_deref(), Some("client-key-1"));
+                // an opaque key identifier does not bind the key, the key itself does
+                let client_jwk = Token::decode_metadata(&dpop).unwrap().public_key().unwrap().clone();
+                assert_eq!(
+                    claims.custom.cnf.jwk.map(|jwk| serde_json::to_value(jwk).unwrap()),
+                    Some(serde_json::to_value(client_jwk).unwrap())
+                );
+            }
+
+            #[apply(all_ciphersuites)]
+            #[test]
+            fn should_embed_jwk_in_cnf_when_asked(ciphersuite: Ciphersuite) {
+                let dpop = DpopBuilder::from(ciphersuite.key.clone()).build();
+                let client_jwk = Token::decode_metadata(&dpop).unwrap().public_key().unwrap().clone();
+
+                let params = Params::from(ciphersuite.clone());
+                let backend_key = JwtKey::from((ciphersuite.key.alg, params.backend_keys.clone()));
+                let token = access_token_with(&dpop, params.clone(), |b| b.cnf_jwk(true)).unwrap();
+                let claims = backend_key.claims::<Access>(&token);
+                let expected_kid = JwkThumbprint::generate(&client_jwk, ciphersuite.hash).unwrap();
+                assert_eq!(claims.custom.cnf.kid, Some(expected_kid.kid));
+                assert_eq!(
+                    claims.custom.cnf.jwk.map(|jwk| serde_json::to_value(jwk).unwrap()),
+                    Some(serde_json::to_value(client_jwk).unwrap())
+                );
+
+                // not embedded by default
+                let token = access_token_with_dpop(&dpop, params).unwrap();
+                assert!(backend_key.claims::<Access>(&token).custom.cnf.jwk.is_none());
             }
         }
 
@@ -423,6 +828,21 @@ pub mod tests {
                 assert_eq!(claims.custom.scope, Access::DEFAULT_SCOPE);
             }
 
+            #[apply(all_ciphersuites)]
+            #[test]
+            fn should_have_configured_scope(ciphersuite: Ciphersuite) {
+                let params = Params {
+                    scope: ["wire_client_id", "other"].as_slice().into(),
+                    ..ciphersuite.clone().into()
+                };
+                let backend_key = params.backend_keys.clone();
+                let token = access_token(params).unwrap();
+
+                let backend_key = JwtKey::from((ciphersuite.key.alg, backend_key));
+                let claims = backend_key.claims::<Access>(&token);
+                assert_eq!(claims.custom.scope, "wire_client_id other");
+            }
+
             #[apply(all_ciphersuites)]
             #[test]
             fn should_have_backend_nonce(ciphersuite: Ciphersuite) {
@@ -469,6 +889,48 @@ pub mod tests {
                 assert!(nbf <= (now - leeway) + test_leeway);
             }
 
+            #[apply(all_ciphersuites)]
+            #[test]
+            fn should_have_proof_audience(ciphersuite: Ciphersuite) {
+                let params = Params::from(ciphersuite.clone());
+                let (backend_key, audience) = (params.backend_keys.clone(), params.audience.clone());
+                let token = access_token(params).unwrap();
+
+                let backend_key = JwtKey::from((ciphersuite.key.alg, backend_key));
+                let claims = backend_key.claims::<Access>(&token);
+                assert_eq!(claims.audiences.unwrap().into_string().unwrap(), audience.to_string());
+            }
+
+            #[apply(all_ciphersuites)]
+            #[test]
+            fn should_have_configured_audiences(ciphersuite: Ciphersuite) {
+                let audiences: Vec<url::Url> = vec![
+                    "https://a.com/challenge".parse().unwrap(),
+                    "https://b.com/challenge".parse().unwrap(),
+                ];
+                let params = Params {
+                    access_audiences: Some(audiences.clone()),
+                    ..ciphersuite.clone().into()
+                };
+                let backend_key = params.backend_keys.clone();
+                let token = access_token(params).unwrap();
+
+                let backend_key = JwtKey::from((ciphersuite.key.alg, backend_key));
+                let claims = backend_key.claims::<Access>(&token);
+                let expected = audiences.iter().map(url::Url::to_string).collect::<HashSet<_>>();
+                assert_eq!(claims.audiences.unwrap().into_set(), expected);
+
+                // should fail when empty
+                let params = Params {
+                    access_audiences: Some(vec![]),
+                    ..ciphersuite.into()
+                };
+                assert!(matches!(
+                    access_token(params).unwrap_err(),
+                    RustyJwtError::InvalidBuilderField("audiences", _)
+                ));
+            }
+
             #[apply(all_ciphersuites)]
             #[test]
             fn should_have_dpop_extra_claims(ciphersuite: Ciphersuite) {
@@ -517,6 +979,149 @@ pub mod tests {
         }
     }
 
+    mod builder {
+        use super::*;
+
+        #[apply(all_ciphersuites)]
+        #[test]
+        fn should_fail_when_required_field_missing(ciphersuite: Ciphersuite) {
+            let params = Params::from(ciphersuite);
+            let result = AccessTokenBuilder::default()
+                .client_id(&params.client_id)
+                .handle(params.handle)
+                .build();
+            assert!(matches!(
+                result.unwrap_err(),
+                RustyJwtError::MissingBuilderField("dpop_proof")
+            ));
+        }
+
+        #[apply(all_ciphersuites)]
+        #[test]
+        fn should_fail_when_expiry_zero(ciphersuite: Ciphersuite) {
+            let params = Params {
+                expiry: core::time::Duration::ZERO,
+                ..ciphersuite.into()
+            };
+            assert!(matches!(
+                access_token(params).unwrap_err(),
+                RustyJwtError::InvalidBuilderField("expiry", _)
+            ));
+        }
+
+        #[cfg(feature = "jwe")]
+        #[apply(all_ciphersuites)]
+        #[test]
+        fn should_encrypt_access_token(ciphersuite: Ciphersuite) {
+            let params = Params::from(ciphersuite);
+            let recipient = JwtKey::new_key(JwsAlgorithm::P256);
+            let dpop = RustyJwtTools::generate_dpop_token(
+                params.dpop.clone(),
+                &params.client_id,
+                params.backend_nonce.clone(),
+                params.audience.clone(),
+                Duration::from_days(1).into(),
+                params.dpop_alg,
+                &params.key.kp,
+            )
+            .unwrap();
+            let encrypted = AccessTokenBuilder::default()
+                .dpop_proof(&dpop)
+                .client_id(&params.client_id)
+                .handle(params.handle)
+                .team(params.team)
+                .backend_nonce(params.backend_nonce)
+                .htu(params.uri)
+                .max_skew_secs(params.leeway)
+                .max_expiration(params.max_expiration)
+                .backend_keys(params.backend_keys)
+                .hash_algorithm(params.hash_alg)
+                .api_version(params.api_version)
+                .expiry(params.expiry)
+                .encrypt_for(recipient.pk.clone())
+                .build()
+                .unwrap();
+
+            // claims cannot be read without decrypting first
+            assert_eq!(encrypted.split('.').count(), 5);
+            assert!(RustyJwtTools::introspect_access_token(&encrypted).is_err());
+
+            let access_token = RustyJwtTools::decrypt_access_token(&encrypted, &recipient.kp).unwrap();
+            let introspected = RustyJwtTools::introspect_access_token(&access_token).unwrap();
+            assert_eq!(introspected.claims.subject, Some(params.client_id.to_uri()));
+
+            // only the recipient can decrypt it
+            let other = recipient.create_another();
+            assert!(RustyJwtTools::decrypt_access_token(&encrypted, &other.kp).is_err());
+        }
+
+        #[apply(all_ciphersuites)]
+        #[test]
+        #[allow(deprecated)]
+        fn deprecated_wrapper_should_still_generate(ciphersuite: Ciphersuite) {
+            let params = Params::from(ciphersuite);
+            let dpop = RustyJwtTools::generate_dpop_token(
+                params.dpop.clone(),
+                &params.client_id,
+                params.backend_nonce.clone(),
+                params.audience.clone(),
+                Duration::from_days(1).into(),
+                params.dpop_alg,
+                &params.key.kp,
+            )
+            .unwrap();
+            let result = RustyJwtTools::generate_access_token(
+                &dpop,
+                &params.client_id,
+                params.handle,
+                params.team,
+                params.backend_nonce,
+                params.uri,
+                params.method,
+                params.leeway,
+                params.max_expiration,
+                params.backend_keys,
+                params.hash_alg,
+                params.api_version,
+                params.expiry,
+            );
+            assert!(result.is_ok());
+        }
+
+        #[apply(all_ciphersuites)]
+        #[test]
+        #[allow(deprecated)]
+        fn deprecated_wrapper_should_accept_zero_expiry(ciphersuite: Ciphersuite) {
+            let params = Params::from(ciphersuite);
+            let dpop = RustyJwtTools::generate_dpop_token(
+                params.dpop.clone(),
+                &params.client_id,
+                params.backend_nonce.clone(),
+                params.audience.clone(),
+                Duration::from_days(1).into(),
+                params.dpop_alg,
+                &params.key.kp,
+            )
+            .unwrap();
+            let result = RustyJwtTools::generate_access_token(
+                &dpop,
+                &params.client_id,
+                params.handle,
+                params.team,
+                params.backend_nonce,
+                params.uri,
+                params.method,
+                params.leeway,
+                params.max_expiration,
+                params.backend_keys,
+                params.hash_alg,
+                params.api_version,
+                core::time::Duration::ZERO,
+            );
+            assert!(result.is_ok());
+        }
+    }
+
     mod backend_keys {
         use super::*;
 
@@ -981,6 +1586,61 @@ pub mod tests {
             assert!(matches!(result.unwrap_err(), RustyJwtError::DpopTeamMismatch));
         }
 
+        #[apply(all_ciphersuites)]
+        #[test]
+        fn team_policy(ciphersuite: Ciphersuite) {
+            let dpop = |team: Option<&str>| {
+                DpopBuilder {
+                    dpop: TestDpop {
+                        team: team.map(str::to_string),
+                        ..Default::default()
+                    },
+                    ..ciphersuite.key.clone().into()
+                }
+                .build()
+            };
+            let build = |dpop: &str, team: Team, policy: TeamPolicy| {
+                let params = Params {
+                    team,
+                    ..ciphersuite.clone().into()
+                };
+                access_token_with(dpop, params, |b| b.team_policy(policy))
+            };
+
+            // personal user
+            assert!(build(&dpop(None), Team::none(), TeamPolicy::Exact).is_ok());
+
+            // should fail when 'team' is absent from the proof of a team user
+            let result = build(&dpop(None), "wire".into(), TeamPolicy::Exact);
+            assert!(matches!(result.unwrap_err(), RustyJwtError::DpopTeamMismatch));
+
+            // should fail when a personal user sends a 'team'
+            let result = build(&dpop(Some("wire")), Team::none(), TeamPolicy::Exact);
+            assert!(matches!(result.unwrap_err(), RustyJwtError::DpopTeamMismatch));
+
+            assert!(build(&dpop(Some("AAA")), "BBB".into(), TeamPolicy::Ignore).is_ok());
+        }
+
+        #[apply(all_ciphersuites)]
+        #[test]
+        fn domains(ciphersuite: Ciphersuite) {
+            let dpop = DpopBuilder::from(ciphersuite.key.clone()).build();
+            let build = |domains: &[&str]| {
+                let domains = DomainPolicy::from_iter(domains.iter().copied());
+                access_token_with(&dpop, ciphersuite.clone().into(), |b| b.domains(domains))
+            };
+            // client on 'example.com', handle on 'wire.com'
+            assert!(build(&["example.com", "wire.com"]).is_ok());
+
+            // should fail when the client belongs to a foreign domain
+            let result = build(&["wire.com"]);
+            assert!(matches!(result.unwrap_err(), RustyJwtError::DomainNotAllowed(d) if d == "example.com"));
+
+            // should fail when the handle belongs to a foreign domain
+            let result = build(&["example.com"]);
+            assert!(matches!(result.unwrap_err(), RustyJwtError::DomainNotAllowed(d) if d == "wire.com"));
+        }
+
         #[apply(all_ciphersuites)]
         #[test]
         fn challenge(ciphersuite: Ciphersuite) {
@@ -1210,6 +1870,8 @@ pub mod tests {
         pub backend_keys: Pem,
         pub hash_alg: HashAlgorithm,
         pub api_version: u32,
+        pub scope: AccessScope,
+        pub access_audiences: Option<Vec<url::Url>>,
         pub expiry: core::time::Duration,
         pub audience: url::Url,
     }
@@ -1232,6 +1894,8 @@ pub mod tests {
                 backend_keys,
                 hash_alg: ciphersuite.hash,
                 api_version: Access::DEFAULT_WIRE_SERVER_API_VERSION,
+                scope: AccessScope::default(),
+                access_audiences: None,
                 expiry: core::time::Duration::from_secs(Access::DEFAULT_EXPIRY),
                 audience: "https://stepca:32902/acme/wire/challenge/I16phsvAPGbruDHr5Bh6akQVPKP6OO5v/dF2LHNmGI20R8rzzcgnrCSv789XcFEyL".parse().unwrap(),
             }
@@ -1256,6 +1920,14 @@ pub mod tests {
     }
 
     fn access_token_with_dpop(dpop: &str, params: Params) -> RustyJwtResult<String> {
+        access_token_with(dpop, params, |builder| builder)
+    }
+
+    fn access_token_with(
+        dpop: &str,
+        params: Params,
+        customize: impl FnOnce(AccessTokenBuilder) -> AccessTokenBuilder,
+    ) -> RustyJwtResult<String> {
         let Params {
             client_id,
             handle,
@@ -1268,23 +1940,31 @@ pub mod tests {
             backend_keys,
             hash_alg,
             api_version,
+            scope,
+            access_audiences,
             expiry,
             ..
         } = params;
-        RustyJwtTools::generate_access_token(
-            dpop,
-            &client_id,
-            handle,
-            team,
-            backend_nonce,
-            uri,
-            method,
-            leeway,
-            max_expiration,
-            backend_keys,
-            hash_alg,
-            api_version,
-            expiry,
-        )
+        let builder = AccessTokenBuilder::default()
+            .dpop_proof(dpop)
+            .client_id(&client_id)
+            .handle(handle)
+            .team(team)
+            .backend_nonce(backend_nonce)
+            .htu(uri)
+            .htm(method)
+            .max_skew_secs(leeway)
+            .max_expiration(max_expiration)
+            .backend_keys(backend_keys)
+            .hash_algorithm(hash_alg)
+            .api_version(api_version)
+            .scope(scope)
+            .expiry(expiry);
+        let builder = customize(builder);
+        match access_audiences {
+            Some(audiences) => builder.audiences(audiences),
+            None => builder,
+        }
+        .build()
     }
 }
@@ -0,0 +1,107 @@
+use jwt_simple::prelude::*;
+
+use crate::{
+    access::Access,
+    jwt::{report::decode_unverified_claims, TokenLimits},
+    model::claims_version::Versioned,
+    prelude::*,
+};
+
+/// Outcome of [RustyJwtTools::introspect_access_token]
+pub type IntrospectionResult = RustyJwtResult<IntrospectedAccessToken>;
+
+/// Header and claims of an access token decoded WITHOUT verifying its signature. None of it can be
+/// trusted: only use it for logging, debugging or routing, never for authorizing a client.
+#[derive(Debug, Clone, Serialize)]
+pub struct IntrospectedAccessToken {
+    /// Signature algorithm from the 'alg' header
+    pub alg: String,
+    /// Token type from the 'typ' header
+    pub typ: Option<String>,
+    /// Public key from the 'jwk' header, the one of the backend which supposedly signed the token
+    pub jwk: Option<Jwk>,
+    /// Key identifier from the 'kid' header
+    pub kid: Option<String>,
+    /// Schema version of the access token claims, see [ClaimsVersion]
+    pub claims_version: u64,
+    /// Claims of the access token
+    pub claims: JWTClaims<Access>,
+    /// Claims of the embedded DPoP proof, when they can be decoded
+    pub proof: Option<JWTClaims<Dpop>>,
+}
+
+impl RustyJwtTools {
+    /// Decodes an access token without verifying it, for intermediaries which do not hold the
+    /// backend public key. Fails when the token does not fit in the default [TokenLimits] or cannot
+    /// be decoded.
+    ///
+    /// # Arguments
+    /// * `access_token` - JWS Compact Serialization format
+    pub fn introspect_access_token(access_token: &str) -> IntrospectionResult {
+        let limits = TokenLimits::default();
+        limits.verify(access_token)?;
+        let header = Token::decode_metadata(access_token)?;
+        let claims = decode_unverified_claims::<Versioned<Access>>(access_token)?;
+        let claims_version = claims.custom.version;
+        let claims = Versioned::unversioned(claims);
+        let proof = Some(claims.custom.proof.as_str())
+            .filter(|proof| limits.verify(proof).is_ok())
+            .and_then(|proof| decode_unverified_claims::<Versioned<Dpop>>(proof).ok())
+            .map(Versioned::unversioned);
+        Ok(IntrospectedAccessToken {
+            alg: header.algorithm().to_string(),
+            typ: header.signature_type().map(str::to_string),
+            jwk: header.public_key().cloned(),
+            kid: header.key_id().map(str::to_string),
+            claims_version,
+            claims,
+            proof,
+        })
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::test_utils::*;
+
+    use super::*;
+
+    #[apply(all_ciphersuites)]
+    #[test]
+    fn should_decode_without_key(ciphersuite: Ciphersuite) {
+        let access = AccessBuilder::from(ciphersuite.clone()).build();
+        let introspected = RustyJwtTools::introspect_access_token(&access).unwrap();
+        assert_eq!(introspected.alg, ciphersuite.key.alg.to_string());
+        assert_eq!(introspected.typ.as_deref(), Some(Access::TYP));
+        assert_eq!(introspected.claims.subject, Some(ClientId::default().to_uri()));
+        assert_eq!(introspected.claims.custom.challenge, AcmeNonce::default());
+        let proof = introspected.proof.unwrap();
+        assert_eq!(proof.custom.htu, TestDpop::default().htu.unwrap());
+    }
+
+    #[apply(all_ciphersuites)]
+    #[test]
+    fn should_decode_even_when_signed_by_untrusted_key(ciphersuite: Ciphersuite) {
+        let other = Ciphersuite {
+            key: ciphersuite.key.create_another(),
+            ..ciphersuite.clone()
+        };
+        let access = AccessBuilder {
+            jwk: Some(ciphersuite.key.to_jwk()),
+            ..other.into()
+        }
+        .build();
+        assert!(RustyJwtTools::introspect_access_token(&access).is_ok());
+    }
+
+    #[test]
+    fn should_fail_when_malformed() {
+        assert!(RustyJwtTools::introspect_access_token("not.a.token").is_err());
+        assert!(RustyJwtTools::introspect_access_token("").is_err());
+        let too_long = "a".repeat(TokenLimits::DEFAULT_MAX_LENGTH + 1);
+        assert!(matches!(
+            RustyJwtTools::introspect_access_token(&too_long).unwrap_err(),
+            RustyJwtError::TokenTooLong(_)
+        ));
+    }
+}
@@ -4,23 +4,42 @@ use crate::jwk_thumbprint::JwkThumbprint;
 use crate::jwt::new_jti;
 use crate::prelude::*;
 
+mod api_version;
+mod claims;
+mod cnf;
 pub mod generate;
+mod introspect;
+mod options;
+mod renew;
+mod revocation;
+mod scope;
 mod verify;
 
+pub use api_version::SupportedApiVersions;
+pub use claims::AccessTokenClaims;
+pub use cnf::Cnf;
+pub use generate::AccessTokenBuilder;
+pub use introspect::{IntrospectedAccessToken, IntrospectionResult};
+pub use options::VerifyAccessTokenOptions;
+pub use revocation::{InMemoryRevocationList, NoRevocation, RevocationCheck};
+pub use scope::AccessScope;
+pub use verify::AccessTokenToVerify;
+
 /// Claims in an access token
 ///
 /// Specified in [OAuth 2.0 Demonstrating Proof-of-Possession at the Application Layer (DPoP)][1]
 ///
 /// [1]: https://www.ietf.org/archive/id/draft-ietf-oauth-dpop-11.html
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[cfg_attr(test, derive(Default))]
 pub struct Access {
     /// ACME server nonce
     #[serde(rename = "chal")]
     pub challenge: AcmeNonce,
-    /// Hash of the JWK, see [JwkThumbprint]
+    /// Binds the access token to the client key, see [Cnf]
     #[serde(rename = "cnf")]
-    pub cnf: JwkThumbprint,
+    pub cnf: Cnf,
     /// Proof of possession in form of a Dpop JWT token generated by [RustyJwtTools::generate_dpop_token]
     #[serde(rename = "proof")]
     pub proof: String,
@@ -35,6 +54,7 @@ pub struct Access {
     pub scope: String,
     /// Allows passing extra arbitrary data which will end up in access token claims
     #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "schema", schemars(skip))]
     pub extra_claims: Option<serde_json::Value>,
 }
 
@@ -54,28 +74,32 @@ impl Access {
     #[cfg(test)]
     pub const DEFAULT_WIRE_SERVER_API_VERSION: u32 = 5;
 
-    /// Current wire-server API version
+    /// Scope of the access tokens used for wire enrollment
     pub const DEFAULT_SCOPE: &'static str = "wire_client_id";
 
     /// we want "nbf" & "iat" slightly in the past to prevent clock drifts or problems non-monotonic hosts
     pub(crate) const NOW_LEEWAY_SECONDS: u64 = 3600;
 
-    pub fn into_jwt_claims(
+    pub(crate) fn into_jwt_claims(
         self,
         client_id: &ClientId,
         nonce: BackendNonce,
         issuer: Htu,
-        audience: url::Url,
+        audiences: Vec<url::Url>,
         expiry: core::time::Duration,
     ) -> JWTClaims<Self> {
         let now = coarsetime::Clock::now_since_epoch() - Duration::from_secs(Self::NOW_LEEWAY_SECONDS);
-        let mut claims = Claims::with_custom_claims(self, expiry.into())
+        let claims = Claims::with_custom_claims(self, expiry.into())
             .invalid_before(now)
             .with_jwt_id(new_jti())
             .with_subject(client_id.to_uri())
             .with_nonce(nonce.to_string())
-            .with_issuer(issuer)
-            .with_audience(audience);
+            .with_issuer(issuer);
+        // a single audience remains a string for verifiers not supporting lists
+        let mut claims = match audiences.as_slice() {
+            [audience] => claims.with_audience(audience),
+            _ => claims.with_audiences(audiences.iter().map(url::Url::to_string).collect::<HashSet<_>>()),
+        };
         claims.issued_at = Some(now);
         claims
     }
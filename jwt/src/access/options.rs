@@ -0,0 +1,195 @@
+use crate::{
+    access::{AccessScope, NoRevocation, RevocationCheck, SupportedApiVersions},
+    jwt::{AudiencePolicy, TokenLimits},
+    prelude::*,
+};
+
+/// Expectations an access token is verified against by [RustyJwtTools::verify_access_token], shared
+/// by every access token verified with them. Those specific to a client are in [AccessTokenToVerify].
+///
+/// `audience`, `claims_versions`, `domains`, `scope`, `limits` and `revocation` have defaults,
+/// every other field is required.
+#[derive(Clone)]
+pub struct VerifyAccessTokenOptions<'a> {
+    pub(super) max_skew_secs: Option<u16>,
+    pub(super) max_expiration: Option<u64>,
+    pub(super) issuers: Vec<Htu>,
+    pub(super) audience: AudiencePolicy,
+    pub(super) backend_pks: Vec<Pem>,
+    pub(super) hash_algorithm: Option<HashAlgorithm>,
+    pub(super) kid_strategy: Option<KidStrategy>,
+    pub(super) api_versions: Option<SupportedApiVersions>,
+    pub(super) claims_versions: Vec<ClaimsVersion>,
+    pub(super) domains: DomainPolicy,
+    pub(super) scope: AccessScope,
+    pub(super) limits: TokenLimits,
+    pub(super) revocation: &'a dyn RevocationCheck,
+}
+
+impl Default for VerifyAccessTokenOptions<'_> {
+    fn default() -> Self {
+        Self {
+            max_skew_secs: None,
+            max_expiration: None,
+            issuers: vec![],
+            audience: AudiencePolicy::default(),
+            backend_pks: vec![],
+            hash_algorithm: None,
+            kid_strategy: None,
+            api_versions: None,
+            claims_versions: ClaimsVersion::ALL.to_vec(),
+            domains: DomainPolicy::default(),
+            scope: AccessScope::default(),
+            limits: TokenLimits::default(),
+            revocation: &NoRevocation,
+        }
+    }
+}
+
+impl std::fmt::Debug for VerifyAccessTokenOptions<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VerifyAccessTokenOptions")
+            .field("max_skew_secs", &self.max_skew_secs)
+            .field("max_expiration", &self.max_expiration)
+            .field("issuers", &self.issuers)
+            .field("audience", &self.audience)
+            .field("backend_pks", &self.backend_pks)
+            .field("hash_algorithm", &self.hash_algorithm)
+            .field("kid_strategy", &self.kid_strategy)
+            .field("api_versions", &self.api_versions)
+            .field("claims_versions", &self.claims_versions)
+            .field("domains", &self.domains)
+            .field("scope", &self.scope)
+            .field("limits", &self.limits)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'a> VerifyAccessTokenOptions<'a> {
+    /// The maximum number of seconds of clock skew allowed ex: 360 (5 min)
+    pub fn max_skew_secs(mut self, max_skew_secs: u16) -> Self {
+        self.max_skew_secs = Some(max_skew_secs);
+        self
+    }
+
+    /// The maximal expiration date and time, in seconds since epoch ex: 1668987368
+    pub fn max_expiration(mut self, max_expiration: u64) -> Self {
+        self.max_expiration = Some(max_expiration);
+        self
+    }
+
+    /// wire-server endpoints the access token can be delivered by, any of them is valid e.g. when
+    /// reachable behind several hostnames
+    pub fn issuers(mut self, issuers: impl IntoIterator<Item = Htu>) -> Self {
+        self.issuers = issuers.into_iter().collect();
+        self
+    }
+
+    /// How the access token 'aud' claim is verified. Defaults to [AudiencePolicy::Ignore]
+    pub fn audience(mut self, audience: AudiencePolicy) -> Self {
+        self.audience = audience;
+        self
+    }
+
+    /// PEM format public keys of the Wire backend. Several can be trusted at once e.g. during a key rotation
+    pub fn backend_pks(mut self, backend_pks: impl IntoIterator<Item = Pem>) -> Self {
+        self.backend_pks = backend_pks.into_iter().collect();
+        self
+    }
+
+    /// To calculate the JWK thumbprint of the DPoP proof JWK
+    pub fn hash_algorithm(mut self, hash_algorithm: HashAlgorithm) -> Self {
+        self.hash_algorithm = Some(hash_algorithm);
+        self
+    }
+
+    /// How the client key identifier and the 'cnf' claim are derived from the DPoP proof JWK, a
+    /// [KidStrategy::Thumbprint] with [Self::hash_algorithm] by default. When set,
+    /// [Self::hash_algorithm] is not required.
+    pub fn kid_strategy(mut self, kid_strategy: KidStrategy) -> Self {
+        self.kid_strategy = Some(kid_strategy);
+        self
+    }
+
+    /// Versions of wire-server http API accepted, a single one e.g. `5`, an open range e.g. `5..` or
+    /// a [SupportedApiVersions::new] one
+    pub fn api_versions(mut self, api_versions: impl Into<SupportedApiVersions>) -> Self {
+        self.api_versions = Some(api_versions.into());
+        self
+    }
+
+    /// Accepted schema versions of the access token and proof claims. Defaults to [ClaimsVersion::ALL]
+    pub fn claims_versions(mut self, claims_versions: impl Into<Vec<ClaimsVersion>>) -> Self {
+        self.claims_versions = claims_versions.into();
+        self
+    }
+
+    /// Domains the client and its handle may belong to. Defaults to [DomainPolicy::Any]
+    pub fn domains(mut self, domains: DomainPolicy) -> Self {
+        self.domains = domains;
+        self
+    }
+
+    /// Scope tokens the access token must all grant. Defaults to the wire enrollment scope. It
+    /// cannot be empty, otherwise any access token would be accepted regardless of its scope
+    pub fn scope(mut self, scope: impl Into<AccessScope>) -> Self {
+        self.scope = scope.into();
+        self
+    }
+
+    /// Size limits enforced before parsing the access token and its embedded proof. Defaults to
+    /// [TokenLimits::default]
+    pub fn limits(mut self, limits: TokenLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Denylist of revoked access tokens. Defaults to [NoRevocation]
+    pub fn revocation(mut self, revocation: &'a dyn RevocationCheck) -> Self {
+        self.revocation = revocation;
+        self
+    }
+
+    /// Fails when a required option is missing
+    pub(super) fn resolve(&self) -> RustyJwtResult<ResolvedOptions<'_, 'a>> {
+        let max_skew_secs = self
+            .max_skew_secs
+            .ok_or(RustyJwtError::MissingBuilderField("max_skew_secs"))?;
+        let max_expiration = self
+            .max_expiration
+            .ok_or(RustyJwtError::MissingBuilderField("max_expiration"))?;
+        if self.issuers.is_empty() {
+            return Err(RustyJwtError::MissingBuilderField("issuers"));
+        }
+        if self.backend_pks.is_empty() {
+            return Err(RustyJwtError::MissingBuilderField("backend_pks"));
+        }
+        let kid = match (&self.kid_strategy, self.hash_algorithm) {
+            (Some(kid_strategy), _) => kid_strategy.clone(),
+            (None, Some(hash_algorithm)) => KidStrategy::Thumbprint(hash_algorithm),
+            (None, None) => return Err(RustyJwtError::MissingBuilderField("hash_algorithm")),
+        };
+        let api_versions = self
+            .api_versions
+            .ok_or(RustyJwtError::MissingBuilderField("api_versions"))?;
+        if self.scope.is_empty() {
+            return Err(RustyJwtError::InvalidBuilderField("scope", "it cannot be empty"));
+        }
+        Ok(ResolvedOptions {
+            max_skew_secs,
+            max_expiration,
+            kid,
+            api_versions,
+            options: self,
+        })
+    }
+}
+
+/// [VerifyAccessTokenOptions] once its required fields have been checked
+pub(super) struct ResolvedOptions<'o, 'a> {
+    pub max_skew_secs: u16,
+    pub max_expiration: u64,
+    pub kid: KidStrategy,
+    pub api_versions: SupportedApiVersions,
+    pub options: &'o VerifyAccessTokenOptions<'a>,
+}
@@ -0,0 +1,366 @@
+use jwt_simple::prelude::*;
+
+use crate::{
+    access::Access,
+    dpop::{VerifyDpop, VerifyDpopTokenHeader},
+    jwt::{report::decode_unverified_claims, verify::jwt_error_mapping, TokenLimits},
+    model::claims_version::Versioned,
+    prelude::*,
+};
+
+impl RustyJwtTools {
+    /// Reissues an access token, possibly expired, with a new 'exp' and 'jti' so that a client does
+    /// not have to go through a whole new DPoP challenge while its proof is still fresh.
+    ///
+    /// Verifications:
+    /// * [access_token] fits in the default [TokenLimits]
+    /// * `typ` header field is "at+jwt" and the signature algorithm (alg) is a supported one
+    /// * signature corresponds to the public key of [backend_keys]
+    /// * `exp` claim is no older than [renewal_grace_secs]
+    /// * [client_id] corresponds to the (sub) claim expressed as URI
+    /// * `jti` claim is present and has not been revoked according to [revocation]
+    /// * the embedded proof is still valid, see [RustyJwtTools::verify_access_token]
+    /// * `cnf` claim is the key identifier of the embedded proof JWK derived with [kid_strategy], and
+    /// also embeds this JWK when the key identifier alone does not bind it
+    ///
+    /// Every other claim, including the `cnf` binding to the client key, is kept as is.
+    ///
+    /// # Arguments
+    /// * `access_token` - access token to renew, in JWS Compact Serialization format
+    /// * `expiry` - renewed access token 'exp' (expiry)
+    /// * `backend_keys` - PEM format concatenated private key and public key of the Wire backend which issued [access_token]
+    /// * `client_id` - see [ClientId]
+    /// * `handle` - see [QualifiedHandle]
+    /// * `team` - see [Team]
+    /// * `team_policy` - see [TeamPolicy]
+    /// * `kid_strategy` - how the access token 'cnf' key identifier got derived, see [KidStrategy]
+    /// * `max_skew_secs` - The maximum number of seconds of clock skew the implementation will allow ex: 360 (5 min)
+    /// * `max_expiration` - The maximal expiration date and time of the embedded proof, in seconds since epoch ex: 1668987368
+    /// * `renewal_grace_secs` - how long after its expiry an access token can still be renewed
+    /// * `revocation` - denylist of revoked access tokens, [NoRevocation] when not supported
+    #[allow(clippy::too_many_arguments)]
+    pub fn renew_access_token(
+        access_token: &str,
+        expiry: core::time::Duration,
+        backend_keys: Pem,
+        client_id: &ClientId,
+        handle: &QualifiedHandle,
+        team: &Team,
+        team_policy: TeamPolicy,
+        kid_strategy: &KidStrategy,
+        max_skew_secs: u16,
+        max_expiration: u64,
+        renewal_grace_secs: u64,
+        revocation: &dyn RevocationCheck,
+    ) -> RustyJwtResult<String> {
+        let limits = TokenLimits::default();
+        limits.verify(access_token)?;
+        let header = Token::decode_metadata(access_token)?;
+        let (alg, _) = Self::verify_access_token_header(&header)?;
+        let backend_pk = Self::backend_public_key(alg, &backend_keys)?;
+
+        // 'exp' is the only claim allowed to be stale, by [renewal_grace_secs], and it is verified
+        // by hand since jwt_simple cannot skip it. Its clock is set right before the expiry, never
+        // later than now, so that 'iat' and 'nbf' are still verified with [max_skew_secs] only.
+        // The unverified 'exp' only sets this clock, the signature is verified in the same call.
+        let now = Clock::now_since_epoch();
+        let expires_at = decode_unverified_claims::<NoCustomClaims>(access_token)?
+            .expires_at
+            .ok_or(RustyJwtError::MissingTokenClaim("exp"))?;
+        let options = VerificationOptions {
+            required_subject: Some(client_id.to_uri()),
+            time_tolerance: Some(Duration::from_secs(max_skew_secs as u64)),
+            artificial_time: Some(now.min(Duration::from_secs(expires_at.as_secs().saturating_sub(1)))),
+            ..Default::default()
+        };
+        let claims = AnyPublicKey::from(&backend_pk)
+            .verify_token::<Versioned<Access>>(access_token, Some(options))
+            .map_err(jwt_error_mapping)?;
+        if expires_at + Duration::from_secs(renewal_grace_secs) < now {
+            return Err(RustyJwtError::TokenExpired);
+        }
+        let claims = Versioned::verify(claims, &ClaimsVersion::ALL)?;
+        // a revoked access token must not be brought back to life
+        let jti = claims
+            .jwt_id
+            .as_deref()
+            .ok_or(RustyJwtError::MissingTokenClaim("jti"))?;
+        if revocation.is_revoked(jti) {
+            return Err(RustyJwtError::RevokedAccessToken);
+        }
+        let nonce: BackendNonce = claims
+            .nonce
+            .clone()
+            .ok_or(RustyJwtError::MissingTokenClaim("nonce"))?
+            .into();
+        let issuer = Htu::try_from(
+            claims
+                .issuer
+                .as_deref()
+                .ok_or(RustyJwtError::MissingTokenClaim("iss"))?,
+        )?;
+        let audiences = Self::proof_audiences(claims.audiences)?;
+
+        let proof = claims.custom.proof.as_str();
+        let proof_header = Token::decode_metadata(proof)?;
+        let (proof_alg, proof_jwk) = proof_header.verify_dpop_header()?;
+        proof.verify_client_dpop(
+            proof_alg,
+            proof_jwk,
+            client_id,
+            handle,
+            team,
+            team_policy,
+            &nonce,
+            Some(&claims.custom.challenge),
+            None,
+            &issuer,
+            max_expiration,
+            max_skew_secs,
+            limits,
+            &ClaimsVersion::ALL,
+            // the domains got verified when the access token was issued
+            &DomainPolicy::Any,
+        )?;
+        let proof_thumbprint = kid_strategy.derive(proof_alg, proof_jwk)?;
+        let cnf = &claims.custom.cnf;
+        if !kid_strategy.binds_key() && cnf.jwk.is_none() {
+            return Err(RustyJwtError::MissingTokenClaim("cnf.jwk"));
+        }
+        if !cnf.confirms(proof_alg, proof_jwk, &proof_thumbprint)? {
+            return Err(RustyJwtError::InvalidJwkThumbprint);
+        }
+
+        let claims = claims
+            .custom
+            .into_jwt_claims(client_id, nonce, issuer, audiences, expiry);
+        Self::sign_access_token(alg, claims, &backend_keys)
+    }
+
+    /// Public key of the backend key pair which is expected to have signed the access token
+    fn backend_public_key(alg: JwsAlgorithm, backend_keys: &Pem) -> RustyJwtResult<ParsedPublicKey> {
+        Ok(match alg {
+            JwsAlgorithm::P256 => ParsedPublicKey::P256(
+                ES256KeyPair::from_pem(backend_keys.as_str())
+                    .map_err(|_| RustyJwtError::InvalidBackendKeys("Invalid ES256 key pair"))?
+                    .public_key(),
+            ),
+            JwsAlgorithm::P384 => ParsedPublicKey::P384(
+                ES384KeyPair::from_pem(backend_keys.as_str())
+                    .map_err(|_| RustyJwtError::InvalidBackendKeys("Invalid ES384 key pair"))?
+                    .public_key(),
+            ),
+            JwsAlgorithm::Ed25519 => ParsedPublicKey::Ed25519(
+                Ed25519KeyPair::from_pem(backend_keys.as_str())
+                    .map_err(|_| RustyJwtError::InvalidBackendKeys("Invalid ED25519 key pair"))?
+                    .public_key(),
+            ),
+        })
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::test_utils::*;
+
+    use super::*;
+
+    #[apply(all_ciphersuites)]
+    #[test]
+    fn should_renew_expired_access_token(ciphersuite: Ciphersuite) {
+        let access = expired_access(&ciphersuite, Duration::from_hours(1));
+        let renewed = renew(&access, &ciphersuite, ciphersuite.key.kp.clone(), 2 * 3600).unwrap();
+
+        let (before, after) = (claims(&access), claims(&renewed));
+        assert_ne!(before.jwt_id, after.jwt_id);
+        assert!(after.expires_at.unwrap() > now());
+        assert_eq!(before.custom.cnf, after.custom.cnf);
+        assert_eq!(before.custom.proof, after.custom.proof);
+        assert_eq!(before.custom.challenge, after.custom.challenge);
+        assert_eq!(before.issuer, after.issuer);
+
+        // the renewed access token is as good as a freshly generated one
+        let kid = after.custom.cnf.kid.clone().unwrap();
+        let token = AccessTokenToVerify {
+            access_token: &renewed,
+            client_id: &ClientId::default(),
+            handle: &QualifiedHandle::default(),
+            challenge: &AcmeNonce::default(),
+            client_kid: &kid,
+        };
+        let options = VerifyAccessTokenOptions::default()
+            .max_skew_secs(5)
+            .max_expiration(2136351646)
+            .issuers([after.issuer.as_deref().unwrap().try_into().unwrap()])
+            .backend_pks([ciphersuite.key.pk.clone()])
+            .hash_algorithm(ciphersuite.hash)
+            .api_versions(Access::DEFAULT_WIRE_SERVER_API_VERSION)
+            .claims_versions([ClaimsVersion::CURRENT]);
+        let result = RustyJwtTools::verify_access_token(&token, &options);
+        assert!(result.is_ok());
+    }
+
+    #[apply(all_ciphersuites)]
+    #[test]
+    fn should_fail_when_expired_for_too_long(ciphersuite: Ciphersuite) {
+        let access = expired_access(&ciphersuite, Duration::from_days(1));
+        let result = renew(&access, &ciphersuite, ciphersuite.key.kp.clone(), 3600);
+        assert!(matches!(result.unwrap_err(), RustyJwtError::TokenExpired));
+    }
+
+    #[apply(all_ciphersuites)]
+    #[test]
+    fn should_fail_when_issued_in_the_future(ciphersuite: Ciphersuite) {
+        // the renewal grace only applies to 'exp', not to 'iat'
+        let access = AccessBuilder {
+            iat: Some(now() + Duration::from_hours(1)),
+            exp: Some(now() + Duration::from_hours(2)),
+            ..builder(&ciphersuite, Duration::from_hours(1))
+        };
+        let result = renew(&access.build(), &ciphersuite, ciphersuite.key.kp.clone(), 3 * 3600);
+        assert!(matches!(result.unwrap_err(), RustyJwtError::InvalidDpopIat));
+    }
+
+    #[apply(all_ciphersuites)]
+    #[test]
+    fn should_fail_when_not_issued_by_backend(ciphersuite: Ciphersuite) {
+        let access = expired_access(&ciphersuite, Duration::from_hours(1));
+        let other_backend = ciphersuite.key.create_another().kp;
+        assert!(renew(&access, &ciphersuite, other_backend, 2 * 3600).is_err());
+    }
+
+    #[apply(all_ciphersuites)]
+    #[test]
+    fn should_fail_when_revoked(ciphersuite: Ciphersuite) {
+        let access = expired_access(&ciphersuite, Duration::from_hours(1));
+        let jti = claims(&access).jwt_id.unwrap();
+        let revocation = InMemoryRevocationList::from_iter([jti]);
+        let result = renew_unless_revoked(&access, &ciphersuite, ciphersuite.key.kp.clone(), 2 * 3600, &revocation);
+        assert!(matches!(result.unwrap_err(), RustyJwtError::RevokedAccessToken));
+
+        // other access tokens can still be renewed
+        let other = expired_access(&ciphersuite, Duration::from_hours(1));
+        let result = renew_unless_revoked(&other, &ciphersuite, ciphersuite.key.kp.clone(), 2 * 3600, &revocation);
+        assert!(result.is_ok());
+    }
+
+    #[apply(all_ciphersuites)]
+    #[test]
+    fn should_fail_when_cnf_mismatches_proof(ciphersuite: Ciphersuite) {
+        let other = ciphersuite.key.create_another();
+        let cnf = JwkThumbprint::generate(&other.to_jwk(), ciphersuite.hash).unwrap();
+        let mut access = builder(&ciphersuite, Duration::from_hours(1));
+        access.access.cnf = Some(cnf.into());
+        let result = renew(&access.build(), &ciphersuite, ciphersuite.key.kp.clone(), 2 * 3600);
+        assert!(matches!(result.unwrap_err(), RustyJwtError::InvalidJwkThumbprint));
+    }
+
+    #[apply(all_ciphersuites)]
+    #[test]
+    fn should_fail_when_proof_mismatches_client(ciphersuite: Ciphersuite) {
+        let access = expired_access(&ciphersuite, Duration::from_hours(1));
+        let result = RustyJwtTools::renew_access_token(
+            &access,
+            core::time::Duration::from_secs(Access::DEFAULT_EXPIRY),
+            ciphersuite.key.kp.clone(),
+            &ClientId::default(),
+            &QualifiedHandle::default(),
+            &Team::from("another-team"),
+            TeamPolicy::default(),
+            &KidStrategy::Thumbprint(ciphersuite.hash),
+            5,
+            2136351646,
+            2 * 3600,
+            &NoRevocation,
+        );
+        assert!(result.is_err());
+    }
+
+    #[apply(all_ciphersuites)]
+    #[test]
+    fn should_renew_with_custom_kid_strategy(ciphersuite: Ciphersuite) {
+        let kid = "client-key-1".to_string();
+        let access = |cnf_jwk: Option<Jwk>| {
+            let mut access = builder(&ciphersuite, Duration::from_hours(1));
+            access.access.cnf = Some(Cnf {
+                kid: Some(kid.clone()),
+                jwk: cnf_jwk,
+            });
+            access.build()
+        };
+        let renew = |access: &str| {
+            RustyJwtTools::renew_access_token(
+                access,
+                core::time::Duration::from_secs(Access::DEFAULT_EXPIRY),
+                ciphersuite.key.kp.clone(),
+                &ClientId::default(),
+                &QualifiedHandle::default(),
+                &Team::default(),
+                TeamPolicy::default(),
+                &KidStrategy::Custom(kid.clone()),
+                5,
+                2136351646,
+                2 * 3600,
+                &NoRevocation,
+            )
+        };
+
+        // the key identifier is not recomputed as a thumbprint
+        let renewed = renew(&access(Some(ciphersuite.key.to_jwk()))).unwrap();
+        assert_eq!(claims(&renewed).custom.cnf.kid, Some(kid.clone()));
+
+        // an opaque key identifier alone does not bind the proof key
+        let result = renew(&access(None));
+        assert!(matches!(
+            result.unwrap_err(),
+            RustyJwtError::MissingTokenClaim("cnf.jwk")
+        ));
+    }
+
+    fn builder(ciphersuite: &Ciphersuite, expired_since: Duration) -> AccessBuilder {
+        let exp = now() - expired_since;
+        AccessBuilder {
+            iat: Some(exp - Duration::from_hours(1)),
+            exp: Some(exp),
+            audiences: Some(Audiences::AsString(
+                "https://stepca/acme/wire/challenge/aa/bb".to_string(),
+            )),
+            ..ciphersuite.clone().into()
+        }
+    }
+
+    fn expired_access(ciphersuite: &Ciphersuite, expired_since: Duration) -> String {
+        builder(ciphersuite, expired_since).build()
+    }
+
+    fn renew(access: &str, ciphersuite: &Ciphersuite, backend_keys: Pem, grace: u64) -> RustyJwtResult<String> {
+        renew_unless_revoked(access, ciphersuite, backend_keys, grace, &NoRevocation)
+    }
+
+    fn renew_unless_revoked(
+        access: &str,
+        ciphersuite: &Ciphersuite,
+        backend_keys: Pem,
+        grace: u64,
+        revocation: &dyn RevocationCheck,
+    ) -> RustyJwtResult<String> {
+        RustyJwtTools::renew_access_token(
+            access,
+            core::time::Duration::from_secs(Access::DEFAULT_EXPIRY),
+            backend_keys,
+            &ClientId::default(),
+            &QualifiedHandle::default(),
+            &Team::default(),
+            TeamPolicy::default(),
+            &KidStrategy::Thumbprint(ciphersuite.hash),
+            5,
+            2136351646, // somewhere in 2037
+            grace,
+            revocation,
+        )
+    }
+
+    fn claims(access: &str) -> JWTClaims<Access> {
+        decode_unverified_claims(access).unwrap()
+    }
+}
@@ -0,0 +1,86 @@
+use std::{collections::HashSet, sync::RwLock};
+
+/// Denylist of access tokens revoked before their expiry, consulted while verifying them e.g. to
+/// immediately lock out a client whose device got stolen.
+///
+/// Any closure `Fn(&str) -> bool` taking the access token 'jti' is a [RevocationCheck]
+pub trait RevocationCheck: Send + Sync {
+    /// Whether the access token identified by its 'jti' claim has been revoked
+    fn is_revoked(&self, jti: &str) -> bool;
+}
+
+impl<F> RevocationCheck for F
+where
+    F: Fn(&str) -> bool + Send + Sync,
+{
+    fn is_revoked(&self, jti: &str) -> bool {
+        self(jti)
+    }
+}
+
+/// Never revokes any access token, for verifiers which do not support revocation
+#[derive(Debug, Copy, Clone, Default)]
+pub struct NoRevocation;
+
+impl RevocationCheck for NoRevocation {
+    fn is_revoked(&self, _jti: &str) -> bool {
+        false
+    }
+}
+
+/// Revoked access tokens held in memory. Meant for tests or a single verifier instance since the
+/// denylist is neither persisted nor shared.
+#[derive(Debug, Default)]
+pub struct InMemoryRevocationList(RwLock<HashSet<String>>);
+
+impl InMemoryRevocationList {
+    /// Revokes the access token identified by its 'jti' claim
+    pub fn revoke(&self, jti: impl Into<String>) {
+        self.0.write().unwrap_or_else(|e| e.into_inner()).insert(jti.into());
+    }
+}
+
+impl RevocationCheck for InMemoryRevocationList {
+    fn is_revoked(&self, jti: &str) -> bool {
+        self.0.read().unwrap_or_else(|e| e.into_inner()).contains(jti)
+    }
+}
+
+impl<S: Into<String>> FromIterator<S> for InMemoryRevocationList {
+    fn from_iter<I: IntoIterator<Item = S>>(jtis: I) -> Self {
+        Self(RwLock::new(jtis.into_iter().map(Into::into).collect()))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use wasm_bindgen_test::*;
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_revoke_in_memory() {
+        let list = InMemoryRevocationList::from_iter(["a"]);
+        assert!(list.is_revoked("a"));
+        assert!(!list.is_revoked("b"));
+        list.revoke("b");
+        assert!(list.is_revoked("b"));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_never_revoke() {
+        assert!(!NoRevocation.is_revoked("a"));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn closure_should_be_revocation_check() {
+        let check = |jti: &str| jti == "a";
+        assert!(check.is_revoked("a"));
+        assert!(!check.is_revoked("b"));
+    }
+}
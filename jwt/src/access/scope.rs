@@ -0,0 +1,128 @@
+use jwt_simple::prelude::*;
+
+use crate::access::Access;
+
+/// Scope of an access token i.e. what it grants access to on wire-server, one or many scope tokens.
+///
+/// Serialized as a space-delimited list as specified in [RFC 8693 Section 4.2][1]
+///
+/// [1]: https://datatracker.ietf.org/doc/html/rfc8693#section-4.2
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct AccessScope(Vec<String>);
+
+impl AccessScope {
+    /// Scope tokens, in order
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(String::as_str)
+    }
+
+    /// Whether there is no scope token at all
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Whether every scope token of `required` is granted by this scope. Trivially true when
+    /// `required` is empty, which is why verification rejects an empty expected scope
+    pub fn contains_all(&self, required: &Self) -> bool {
+        required.iter().all(|s| self.iter().any(|t| t == s))
+    }
+}
+
+/// The wire enrollment scope
+impl Default for AccessScope {
+    fn default() -> Self {
+        Access::DEFAULT_SCOPE.into()
+    }
+}
+
+impl From<&str> for AccessScope {
+    fn from(scope: &str) -> Self {
+        Self(scope.split_whitespace().map(str::to_string).collect())
+    }
+}
+
+impl From<String> for AccessScope {
+    fn from(scope: String) -> Self {
+        scope.as_str().into()
+    }
+}
+
+impl From<Vec<String>> for AccessScope {
+    fn from(scopes: Vec<String>) -> Self {
+        scopes.iter().flat_map(|s| s.split_whitespace()).collect()
+    }
+}
+
+impl From<&[&str]> for AccessScope {
+    fn from(scopes: &[&str]) -> Self {
+        scopes.iter().flat_map(|s| s.split_whitespace()).collect()
+    }
+}
+
+impl<'a> FromIterator<&'a str> for AccessScope {
+    fn from_iter<I: IntoIterator<Item = &'a str>>(iter: I) -> Self {
+        Self(iter.into_iter().map(str::to_string).collect())
+    }
+}
+
+impl std::fmt::Display for AccessScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0.join(" "))
+    }
+}
+
+impl Serialize for AccessScope {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for AccessScope {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(String::deserialize(deserializer)?.into())
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use wasm_bindgen_test::*;
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_parse_space_delimited_list() {
+        let scope = AccessScope::from(" a  b c ");
+        assert_eq!(scope.iter().collect::<Vec<_>>(), vec!["a", "b", "c"]);
+        assert_eq!(scope.to_string(), "a b c");
+        assert_eq!(scope, AccessScope::from(["a", "b c"].as_slice()));
+        assert_eq!(
+            scope,
+            AccessScope::from(vec!["a".to_string(), "b".to_string(), "c".to_string()])
+        );
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_contain_all_required_scopes() {
+        let scope = AccessScope::from("a b c");
+        assert!(scope.contains_all(&"c a".into()));
+        assert!(scope.contains_all(&"".into()));
+        assert!(!scope.contains_all(&"a d".into()));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_be_empty_without_scope_token() {
+        assert!(AccessScope::from("  ").is_empty());
+        assert!(!AccessScope::from("a").is_empty());
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_default_to_wire_enrollment_scope() {
+        assert_eq!(AccessScope::default().to_string(), Access::DEFAULT_SCOPE);
+    }
+}
@@ -0,0 +1,392 @@
+//! CBOR Web Tokens, a compact alternative to JWTs for constrained devices
+//!
+//! Specified in [RFC 8392][1] and signed as a COSE_Sign1 structure specified in [RFC 9052][2].
+//! The claim sets are the same as the JWT ones, only their encoding differs.
+//!
+//! [1]: https://www.rfc-editor.org/rfc/rfc8392
+//! [2]: https://www.rfc-editor.org/rfc/rfc9052#section-4.2
+
+use ciborium::value::{Integer, Value};
+use jwt_simple::prelude::*;
+use serde::de::DeserializeOwned;
+
+use crate::{
+    jwt::signature::{sign_raw, verify_raw},
+    prelude::*,
+};
+
+/// CBOR tag of a COSE_Sign1 structure
+const COSE_SIGN1_TAG: u64 = 18;
+
+/// Label of the 'alg' COSE header parameter
+const ALG_LABEL: i64 = 1;
+
+/// Label of the 'crit' COSE header parameter
+const CRIT_LABEL: i64 = 2;
+
+/// Context of the COSE_Sign1 signature structure
+const SIGNATURE1_CONTEXT: &str = "Signature1";
+
+/// Integer labels of the registered claims, see [RFC 8392 Section 4][1]. Other claims keep their name as label
+///
+/// [1]: https://www.rfc-editor.org/rfc/rfc8392#section-4
+const REGISTERED_CLAIMS: [(&str, i64); 7] = [
+    ("iss", 1),
+    ("sub", 2),
+    ("aud", 3),
+    ("exp", 4),
+    ("nbf", 5),
+    ("iat", 6),
+    ("jti", 7),
+];
+
+impl RustyJwtTools {
+    /// Same as [RustyJwtTools::generate_dpop_token] but serializes the DPoP proof as a CWT
+    ///
+    /// # Arguments
+    /// * see [RustyJwtTools::generate_dpop_token]
+    pub fn generate_dpop_cwt(
+        dpop: Dpop,
+        client_id: &ClientId,
+        nonce: BackendNonce,
+        audience: url::Url,
+        expiry: core::time::Duration,
+        alg: JwsAlgorithm,
+        kp: &Pem,
+    ) -> RustyJwtResult<Vec<u8>> {
+        let claims = dpop.into_jwt_claims(nonce, client_id, expiry, audience);
+        Self::sign_cwt(&claims, alg, kp)
+    }
+
+    /// Signs a claim set, for example the one of a [Dpop] proof or of an [Access] token, as a CWT
+    ///
+    /// # Arguments
+    /// * `claims` - claim set to sign
+    /// * `alg` - signature algorithm
+    /// * `kp` - PEM format key pair of the signer
+    pub fn sign_cwt<T: Serialize>(claims: &JWTClaims<T>, alg: JwsAlgorithm, kp: &Pem) -> RustyJwtResult<Vec<u8>> {
+        let payload = encode(&claims_to_cbor(serde_json::to_value(claims)?)?)?;
+        let protected = encode(&Value::Map(vec![(ALG_LABEL.into(), cose_alg(alg).into())]))?;
+        let signature = sign_raw(alg, kp, &signature1(&protected, &payload)?)?;
+        let cose_sign1 = Value::Array(vec![
+            Value::Bytes(protected),
+            Value::Map(vec![]),
+            Value::Bytes(payload),
+            Value::Bytes(signature),
+        ]);
+        encode(&Value::Tag(COSE_SIGN1_TAG, Box::new(cose_sign1)))
+    }
+
+    /// Verifies the signature of a CWT and returns its claim set. Also verifies that it has not
+    /// expired and is not used before its 'nbf'. Other claims are left to the caller.
+    ///
+    /// # Arguments
+    /// * `cwt` - CWT to verify, a COSE_Sign1 structure
+    /// * `alg` - expected signature algorithm
+    /// * `pk` - PEM format public key of the signer
+    /// * `leeway` - The maximum number of seconds of clock skew the implementation will allow
+    pub fn verify_cwt<T: DeserializeOwned>(
+        cwt: &[u8],
+        alg: JwsAlgorithm,
+        pk: &Pem,
+        leeway: u16,
+    ) -> RustyJwtResult<JWTClaims<T>> {
+        let cose_sign1 = match decode(cwt)? {
+            Value::Tag(COSE_SIGN1_TAG, value) => *value,
+            value => value,
+        };
+        let Value::Array(parts) = cose_sign1 else {
+            return Err(RustyJwtError::InvalidCwt("not a COSE_Sign1 structure"));
+        };
+        let [Value::Bytes(protected), _, Value::Bytes(payload), Value::Bytes(signature)] = parts.as_slice() else {
+            return Err(RustyJwtError::InvalidCwt("not a COSE_Sign1 structure"));
+        };
+        let header = match decode(protected)? {
+            Value::Map(header) => header,
+            _ => vec![],
+        };
+        let header_alg = header
+            .iter()
+            .find(|(label, _)| label == &Value::from(ALG_LABEL))
+            .and_then(|(_, alg)| alg.as_integer())
+            .and_then(|alg| i64::try_from(alg).ok());
+        if header_alg != Some(cose_alg(alg)) {
+            return Err(RustyJwtError::UnsupportedAlgorithm);
+        }
+        verify_critical_labels(&header)?;
+        verify_raw(alg, pk, &signature1(protected, payload)?, signature).map_err(|e| match e {
+            RustyJwtError::InvalidSignature => RustyJwtError::InvalidCwt("signature is invalid"),
+            e => e,
+        })?;
+
+        let claims: JWTClaims<T> = serde_json::from_value(cbor_to_claims(decode(payload)?)?)?;
+        let now = coarsetime::Clock::now_since_epoch();
+        let leeway = Duration::from_secs(leeway as u64);
+        let expires_at = claims.expires_at.ok_or(RustyJwtError::MissingTokenClaim("exp"))?;
+        if now > expires_at + leeway {
+            return Err(RustyJwtError::TokenExpired);
+        }
+        if matches!(claims.invalid_before, Some(nbf) if nbf > now + leeway) {
+            return Err(RustyJwtError::DpopNotYetValid);
+        }
+        Ok(claims)
+    }
+}
+
+/// No extension header parameter is understood, so a 'crit' header listing any is rejected.
+///
+/// Specified in [RFC 9052 Section 3.1][1]
+///
+/// [1]: https://www.rfc-editor.org/rfc/rfc9052#section-3.1
+fn verify_critical_labels(header: &[(Value, Value)]) -> RustyJwtResult<()> {
+    let Some((_, crit)) = header.iter().find(|(label, _)| label == &Value::from(CRIT_LABEL)) else {
+        return Ok(());
+    };
+    let crit = crit
+        .as_array()
+        .filter(|crit| !crit.is_empty())
+        .ok_or(RustyJwtError::InvalidCriticalHeader("it is not a non-empty array"))?;
+    let label = match &crit[0] {
+        Value::Integer(label) => i128::from(*label).to_string(),
+        Value::Text(label) => label.clone(),
+        _ => {
+            return Err(RustyJwtError::InvalidCriticalHeader(
+                "it lists something else than a label",
+            ))
+        }
+    };
+    Err(RustyJwtError::UnsupportedCriticalHeader(label))
+}
+
+/// COSE algorithm identifiers, see [RFC 9053][1]
+///
+/// [1]: https://www.rfc-editor.org/rfc/rfc9053#section-2
+fn cose_alg(alg: JwsAlgorithm) -> i64 {
+    match alg {
+        JwsAlgorithm::P256 => -7,
+        JwsAlgorithm::P384 => -35,
+        JwsAlgorithm::Ed25519 => -8,
+    }
+}
+
+/// The 'Sig_structure' actually signed, see [RFC 9052 Section 4.4][1]
+///
+/// [1]: https://www.rfc-editor.org/rfc/rfc9052#section-4.4
+fn signature1(protected: &[u8], payload: &[u8]) -> RustyJwtResult<Vec<u8>> {
+    encode(&Value::Array(vec![
+        Value::Text(SIGNATURE1_CONTEXT.to_string()),
+        Value::Bytes(protected.to_vec()),
+        Value::Bytes(vec![]),
+        Value::Bytes(payload.to_vec()),
+    ]))
+}
+
+/// Maps the claim names to their CWT labels. 'jti' becomes the 'cti' byte string
+fn claims_to_cbor(claims: serde_json::Value) -> RustyJwtResult<Value> {
+    let serde_json::Value::Object(claims) = claims else {
+        return Err(RustyJwtError::ImplementationError);
+    };
+    let claims = claims
+        .into_iter()
+        .map(
+            |(name, value)| match REGISTERED_CLAIMS.iter().find(|(n, _)| n == &name) {
+                Some(("jti", label)) => match value {
+                    serde_json::Value::String(jti) => Ok((Value::from(*label), Value::Bytes(jti.into_bytes()))),
+                    _ => Err(RustyJwtError::InvalidCwt("'jti' is not a string")),
+                },
+                Some((_, label)) => Ok((Value::from(*label), json_to_cbor(value))),
+                None => Ok((Value::Text(name), json_to_cbor(value))),
+            },
+        )
+        .collect::<RustyJwtResult<Vec<_>>>()?;
+    Ok(Value::Map(claims))
+}
+
+/// Reverse of [claims_to_cbor]
+fn cbor_to_claims(claims: Value) -> RustyJwtResult<serde_json::Value> {
+    let Value::Map(claims) = claims else {
+        return Err(RustyJwtError::InvalidCwt("claims are not a map"));
+    };
+    let claims = claims
+        .into_iter()
+        .map(|(label, value)| match label {
+            Value::Text(name) => Ok((name, cbor_to_json(value)?)),
+            Value::Integer(label) => {
+                let (name, _) = REGISTERED_CLAIMS
+                    .iter()
+                    .find(|(_, l)| Integer::from(*l) == label)
+                    .ok_or(RustyJwtError::InvalidCwt("unknown claim label"))?;
+                let value = match (*name, value) {
+                    ("jti", Value::Bytes(cti)) => serde_json::Value::String(
+                        String::from_utf8(cti).map_err(|_| RustyJwtError::InvalidCwt("'cti' is not UTF-8"))?,
+                    ),
+                    (_, value) => cbor_to_json(value)?,
+                };
+                Ok((name.to_string(), value))
+            }
+            _ => Err(RustyJwtError::InvalidCwt("unsupported claim label")),
+        })
+        .collect::<RustyJwtResult<serde_json::Map<_, _>>>()?;
+    Ok(serde_json::Value::Object(claims))
+}
+
+fn json_to_cbor(value: serde_json::Value) -> Value {
+    match value {
+        serde_json::Value::Null => Value::Null,
+        serde_json::Value::Bool(b) => Value::Bool(b),
+        serde_json::Value::Number(n) => match (n.as_u64(), n.as_i64(), n.as_f64()) {
+            (Some(n), _, _) => n.into(),
+            (_, Some(n), _) => n.into(),
+            (_, _, n) => Value::Float(n.unwrap_or_default()),
+        },
+        serde_json::Value::String(s) => Value::Text(s),
+        serde_json::Value::Array(a) => Value::Array(a.into_iter().map(json_to_cbor).collect()),
+        serde_json::Value::Object(o) => {
+            Value::Map(o.into_iter().map(|(k, v)| (Value::Text(k), json_to_cbor(v))).collect())
+        }
+    }
+}
+
+fn cbor_to_json(value: Value) -> RustyJwtResult<serde_json::Value> {
+    Ok(match value {
+        Value::Null => serde_json::Value::Null,
+        Value::Bool(b) => serde_json::Value::Bool(b),
+        Value::Integer(i) => match (u64::try_from(i), i64::try_from(i)) {
+            (Ok(n), _) => n.into(),
+            (_, Ok(n)) => n.into(),
+            _ => return Err(RustyJwtError::InvalidCwt("integer out of range")),
+        },
+        Value::Float(f) => serde_json::Number::from_f64(f)
+            .map(serde_json::Value::Number)
+            .ok_or(RustyJwtError::InvalidCwt("float is not finite"))?,
+        Value::Text(s) => serde_json::Value::String(s),
+        Value::Array(a) => serde_json::Value::Array(a.into_iter().map(cbor_to_json).collect::<RustyJwtResult<_>>()?),
+        Value::Map(m) => serde_json::Value::Object(
+            m.into_iter()
+                .map(|(k, v)| match k {
+                    Value::Text(k) => Ok((k, cbor_to_json(v)?)),
+                    _ => Err(RustyJwtError::InvalidCwt("map keys should be text")),
+                })
+                .collect::<RustyJwtResult<_>>()?,
+        ),
+        _ => return Err(RustyJwtError::InvalidCwt("unsupported CBOR type")),
+    })
+}
+
+fn encode(value: &Value) -> RustyJwtResult<Vec<u8>> {
+    let mut buf = vec![];
+    ciborium::ser::into_writer(value, &mut buf).map_err(|_| RustyJwtError::ImplementationError)?;
+    Ok(buf)
+}
+
+fn decode(bytes: &[u8]) -> RustyJwtResult<Value> {
+    ciborium::de::from_reader(bytes).map_err(|_| RustyJwtError::InvalidCwt("not valid CBOR"))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use wasm_bindgen_test::*;
+
+    use crate::{access::Access, test_utils::*};
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[apply(all_keys)]
+    #[wasm_bindgen_test]
+    fn should_round_trip_dpop(key: JwtKey) {
+        let audience = "https://stepca/acme/wire/challenge/aa/bb".parse::<url::Url>().unwrap();
+        let cwt = RustyJwtTools::generate_dpop_cwt(
+            Dpop::default(),
+            &ClientId::default(),
+            BackendNonce::default(),
+            audience,
+            core::time::Duration::from_secs(3600),
+            key.alg,
+            &key.kp,
+        )
+        .unwrap();
+        let claims = RustyJwtTools::verify_cwt::<Dpop>(&cwt, key.alg, &key.pk, 5).unwrap();
+        assert_eq!(claims.custom, Dpop::default());
+        assert_eq!(claims.subject, Some(ClientId::default().to_uri()));
+        assert_eq!(claims.nonce, Some(BackendNonce::default().to_string()));
+        assert!(claims.jwt_id.is_some());
+    }
+
+    #[apply(all_keys)]
+    #[wasm_bindgen_test]
+    fn should_round_trip_access(key: JwtKey) {
+        let claims = Claims::with_custom_claims(Access::default(), Duration::from_hours(1))
+            .with_subject(ClientId::default().to_uri())
+            .with_audiences(HashSet::from(["a".to_string(), "b".to_string()]));
+        let cwt = RustyJwtTools::sign_cwt(&claims, key.alg, &key.kp).unwrap();
+        let verified = RustyJwtTools::verify_cwt::<Access>(&cwt, key.alg, &key.pk, 5).unwrap();
+        assert_eq!(verified.custom, claims.custom);
+        assert_eq!(verified.expires_at, claims.expires_at);
+        assert!(matches!(verified.audiences, Some(Audiences::AsSet(a)) if a.len() == 2));
+    }
+
+    #[apply(all_keys)]
+    #[wasm_bindgen_test]
+    fn should_fail_when_signed_by_another_key(key: JwtKey) {
+        let claims = Claims::with_custom_claims(Dpop::default(), Duration::from_hours(1));
+        let cwt = RustyJwtTools::sign_cwt(&claims, key.alg, &key.kp).unwrap();
+        let other = key.create_another();
+        let result = RustyJwtTools::verify_cwt::<Dpop>(&cwt, key.alg, &other.pk, 5);
+        assert!(matches!(result.unwrap_err(), RustyJwtError::InvalidCwt(_)));
+    }
+
+    #[apply(all_keys)]
+    #[wasm_bindgen_test]
+    fn should_fail_when_algorithm_differs(key: JwtKey) {
+        let claims = Claims::with_custom_claims(Dpop::default(), Duration::from_hours(1));
+        let cwt = RustyJwtTools::sign_cwt(&claims, key.alg, &key.kp).unwrap();
+        let [other_alg, _] = key.reverse_algorithms();
+        let result = RustyJwtTools::verify_cwt::<Dpop>(&cwt, other_alg, &key.pk, 5);
+        assert!(matches!(result.unwrap_err(), RustyJwtError::UnsupportedAlgorithm));
+    }
+
+    #[apply(all_keys)]
+    #[wasm_bindgen_test]
+    fn should_fail_when_expired(key: JwtKey) {
+        let mut claims = Claims::with_custom_claims(Dpop::default(), Duration::from_hours(1));
+        claims.expires_at = Some(now() - Duration::from_hours(1));
+        let cwt = RustyJwtTools::sign_cwt(&claims, key.alg, &key.kp).unwrap();
+        let result = RustyJwtTools::verify_cwt::<Dpop>(&cwt, key.alg, &key.pk, 5);
+        assert!(matches!(result.unwrap_err(), RustyJwtError::TokenExpired));
+    }
+
+    #[apply(all_keys)]
+    #[wasm_bindgen_test]
+    fn should_fail_when_tampered(key: JwtKey) {
+        let claims = Claims::with_custom_claims(Dpop::default(), Duration::from_hours(1));
+        let mut cwt = RustyJwtTools::sign_cwt(&claims, key.alg, &key.kp).unwrap();
+        let len = cwt.len();
+        cwt[len - 80] ^= 1;
+        assert!(RustyJwtTools::verify_cwt::<Dpop>(&cwt, key.alg, &key.pk, 5).is_err());
+    }
+
+    #[apply(all_keys)]
+    #[wasm_bindgen_test]
+    fn should_reject_unsupported_critical_header(key: JwtKey) {
+        let claims = Claims::with_custom_claims(Dpop::default(), Duration::from_hours(1));
+        let payload = encode(&claims_to_cbor(serde_json::to_value(&claims).unwrap()).unwrap()).unwrap();
+        let protected = encode(&Value::Map(vec![
+            (ALG_LABEL.into(), cose_alg(key.alg).into()),
+            (CRIT_LABEL.into(), Value::Array(vec![(-65537).into()])),
+            ((-65537).into(), true.into()),
+        ]))
+        .unwrap();
+        let signature = sign_raw(key.alg, &key.kp, &signature1(&protected, &payload).unwrap()).unwrap();
+        let cose_sign1 = Value::Array(vec![
+            Value::Bytes(protected),
+            Value::Map(vec![]),
+            Value::Bytes(payload),
+            Value::Bytes(signature),
+        ]);
+        let cwt = encode(&Value::Tag(COSE_SIGN1_TAG, Box::new(cose_sign1))).unwrap();
+        let result = RustyJwtTools::verify_cwt::<Dpop>(&cwt, key.alg, &key.pk, 5);
+        assert!(matches!(result.unwrap_err(), RustyJwtError::UnsupportedCriticalHeader(l) if l == "-65537"));
+    }
+}
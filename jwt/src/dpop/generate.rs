@@ -1,6 +1,6 @@
 use jwt_simple::prelude::*;
 
-use crate::{dpop::Dpop, prelude::*};
+use crate::{dpop::Dpop, model::claims_version::Versioned, prelude::*};
 
 impl RustyJwtTools {
     /// Generates a DPoP JWT. Generally used on the client side.
@@ -26,7 +26,60 @@ impl RustyJwtTools {
         // TODO: is it up to us to validate the 'client_id' format or is it opaque to us ?
         let header = Self::new_dpop_header(alg);
         let claims = dpop.into_jwt_claims(nonce, client_id, expiry, audience);
-        Self::generate_jwt(alg, header, Some(claims), kp, true)
+        Self::generate_jwt(alg, header, Some(Versioned::current(claims)), kp, true)
+    }
+
+    /// Same as [RustyJwtTools::generate_dpop_token] but also advertises the key identifier of [kp]
+    /// in the 'kid' header. It has to be derived the same way as the access token 'cnf' claim.
+    ///
+    /// # Arguments
+    /// * `kid` - how the key identifier is derived from the public key of [kp]
+    #[allow(clippy::too_many_arguments)]
+    pub fn generate_dpop_token_with_kid(
+        dpop: Dpop,
+        client_id: &ClientId,
+        nonce: BackendNonce,
+        audience: url::Url,
+        expiry: core::time::Duration,
+        alg: JwsAlgorithm,
+        kp: &Pem,
+        kid: &KidStrategy,
+    ) -> RustyJwtResult<String> {
+        use crate::jwk::TryIntoJwk as _;
+
+        let jwk = match alg {
+            JwsAlgorithm::P256 => ES256KeyPair::from_pem(kp)?.public_key().try_into_jwk()?,
+            JwsAlgorithm::P384 => ES384KeyPair::from_pem(kp)?.public_key().try_into_jwk()?,
+            JwsAlgorithm::Ed25519 => Ed25519KeyPair::from_pem(kp)?.public_key().try_into_jwk()?,
+        };
+        let header = JWTHeader {
+            key_id: Some(kid.derive(alg, &jwk)?.kid),
+            ..Self::new_dpop_header(alg)
+        };
+        let claims = dpop.into_jwt_claims(nonce, client_id, expiry, audience);
+        Self::generate_jwt(alg, header, Some(Versioned::current(claims)), kp, true)
+    }
+
+    /// Same as [RustyJwtTools::generate_dpop_token] but also embeds a certificate chain certifying
+    /// [kp] in the 'x5c' header
+    ///
+    /// # Arguments
+    /// * `chain` - DER encoded certificates, the leaf one certifying [kp] first
+    #[cfg(feature = "x5c")]
+    #[allow(clippy::too_many_arguments)]
+    pub fn generate_dpop_token_with_x5c(
+        dpop: Dpop,
+        client_id: &ClientId,
+        nonce: BackendNonce,
+        audience: url::Url,
+        expiry: core::time::Duration,
+        alg: JwsAlgorithm,
+        kp: &Pem,
+        chain: &[Vec<u8>],
+    ) -> RustyJwtResult<String> {
+        let header = Self::new_dpop_header(alg);
+        let claims = dpop.into_jwt_claims(nonce, client_id, expiry, audience);
+        Self::generate_jwt_with_x5c(alg, header, Some(Versioned::current(claims)), kp, chain, true)
     }
 
     fn new_dpop_header(alg: JwsAlgorithm) -> JWTHeader {
@@ -86,6 +139,26 @@ pub mod tests {
             assert_eq!(header.algorithm(), key.alg.to_string())
         }
 
+        #[apply(all_keys)]
+        #[wasm_bindgen_test]
+        fn should_have_derived_kid(key: JwtKey) {
+            let kid = KidStrategy::Thumbprint(HashAlgorithm::SHA256);
+            let token = RustyJwtTools::generate_dpop_token_with_kid(
+                Dpop::default(),
+                &ClientId::default(),
+                BackendNonce::default(),
+                "https://stepca/acme/wire/challenge/aaa/bbb".parse().unwrap(),
+                Duration::from_days(1).into(),
+                key.alg,
+                &key.kp,
+                &kid,
+            )
+            .unwrap();
+            let header = Token::decode_metadata(token.as_str()).unwrap();
+            let expected = kid.derive(key.alg, header.public_key().unwrap()).unwrap();
+            assert_eq!(header.key_id(), Some(expected.kid.as_str()))
+        }
+
         #[apply(all_keys)]
         #[wasm_bindgen_test]
         fn should_have_right_fields_naming(key: JwtKey) {
@@ -8,6 +8,7 @@ use crate::prelude::*;
 ///
 /// [1]: https://tools.ietf.org/html/rfc7231#section-4
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[cfg_attr(test, derive(Default))]
 #[serde(rename_all = "UPPERCASE")]
 pub enum Htm {
@@ -8,6 +8,7 @@ use crate::prelude::*;
 ///
 /// [1]: https://tools.ietf.org/html/rfc7230#section-5.5
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Htu(url::Url);
 
 #[cfg(test)]
@@ -20,6 +20,7 @@ mod verify;
 ///
 /// [1]: https://www.ietf.org/archive/id/draft-ietf-oauth-dpop-11.html
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[cfg_attr(test, derive(Default))]
 pub struct Dpop {
     /// The HTTP method of the request to which the JWT is attached
@@ -34,11 +35,12 @@ pub struct Dpop {
     /// Client's handle e.g. `beltram_wire`
     #[serde(rename = "handle")]
     pub handle: QualifiedHandle,
-    /// Team the client belongs to e.g. `wire`
+    /// Teams the client belongs to e.g. `wire`, none for a personal user
     #[serde(rename = "team")]
     pub team: Team,
     /// Allows passing extra arbitrary data which will end up in DPoP token claims
     #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "schema", schemars(skip))]
     pub extra_claims: Option<serde_json::Value>,
 }
 
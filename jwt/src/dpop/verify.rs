@@ -1,7 +1,10 @@
 use jwt_simple::prelude::*;
 
-use crate::jwt::{Verify, VerifyJwt, VerifyJwtHeader};
-use crate::prelude::*;
+use crate::jwt::{
+    report::{ensure, values, DPOP_PROOF},
+    AudiencePolicy, ClaimsPolicy, TokenLimits, Verify, VerifyJwt, VerifyJwtHeader,
+};
+use crate::{model::claims_version::Versioned, prelude::*};
 
 /// Verifies DPoP token specific header
 pub trait VerifyDpopTokenHeader {
@@ -28,6 +31,10 @@ pub trait VerifyDpop {
     /// # Arguments
     /// * `htm` - method
     /// * `uri` - uri
+    /// * `team_policy` - how the 'team' claim is verified against [team]
+    /// * `limits` - size limits enforced before parsing the token
+    /// * `claims_versions` - accepted schema versions of the claims, see [ClaimsVersion]
+    /// * `domains` - domains [client_id] and the 'handle' claim may belong to
     #[allow(clippy::too_many_arguments)]
     fn verify_client_dpop(
         &self,
@@ -36,58 +43,142 @@ pub trait VerifyDpop {
         client_id: &ClientId,
         handle: &QualifiedHandle,
         team: &Team,
+        team_policy: TeamPolicy,
         backend_nonce: &BackendNonce,
         challenge: Option<&AcmeNonce>,
         htm: Option<Htm>,
         htu: &Htu,
         max_expiration: u64,
         leeway: u16,
+        limits: TokenLimits,
+        claims_versions: &[ClaimsVersion],
+        domains: &DomainPolicy,
+    ) -> RustyJwtResult<JWTClaims<Dpop>> {
+        self.verify_client_dpop_with_report(
+            alg,
+            jwk,
+            client_id,
+            handle,
+            team,
+            team_policy,
+            backend_nonce,
+            challenge,
+            htm,
+            htu,
+            max_expiration,
+            leeway,
+            limits,
+            claims_versions,
+            domains,
+            &mut VerificationReport::fail_fast(),
+        )
+    }
+
+    /// Same as [VerifyDpop::verify_client_dpop] but records every check in [report]
+    #[allow(clippy::too_many_arguments)]
+    fn verify_client_dpop_with_report(
+        &self,
+        alg: JwsAlgorithm,
+        jwk: &Jwk,
+        client_id: &ClientId,
+        handle: &QualifiedHandle,
+        team: &Team,
+        team_policy: TeamPolicy,
+        backend_nonce: &BackendNonce,
+        challenge: Option<&AcmeNonce>,
+        htm: Option<Htm>,
+        htu: &Htu,
+        max_expiration: u64,
+        leeway: u16,
+        limits: TokenLimits,
+        claims_versions: &[ClaimsVersion],
+        domains: &DomainPolicy,
+        report: &mut VerificationReport,
     ) -> RustyJwtResult<JWTClaims<Dpop>>;
 }
 
 impl VerifyDpop for &str {
-    fn verify_client_dpop(
+    fn verify_client_dpop_with_report(
         &self,
         alg: JwsAlgorithm,
         jwk: &Jwk,
         client_id: &ClientId,
         handle: &QualifiedHandle,
         team: &Team,
+        team_policy: TeamPolicy,
         backend_nonce: &BackendNonce,
         challenge: Option<&AcmeNonce>,
         htm: Option<Htm>,
         htu: &Htu,
         max_expiration: u64,
         leeway: u16,
+        limits: TokenLimits,
+        claims_versions: &[ClaimsVersion],
+        domains: &DomainPolicy,
+        report: &mut VerificationReport,
     ) -> RustyJwtResult<JWTClaims<Dpop>> {
         let pk = AnyPublicKey::from((alg, jwk));
         let verify = Verify {
             client_id,
             backend_nonce: Some(backend_nonce),
             leeway,
-            issuer: None,
+            issuers: None,
+            audience: AudiencePolicy::Ignore,
+            claims_policy: ClaimsPolicy::default(),
+            limits,
+            validators: vec![],
+            critical_headers: vec![],
         };
 
-        let claims = (*self).verify_jwt::<Dpop>(&pk, max_expiration, verify)?;
+        let verified = (*self).verify_jwt::<Versioned<Dpop>>(&pk, max_expiration, verify);
+        let claims = report.signature(DPOP_PROOF, *self, verified)?;
+        let claims = report.require(DPOP_PROOF, "cv", Versioned::verify(claims, claims_versions))?;
+        report.check(DPOP_PROOF, "sub domain", domains.verify(&client_id.domain), || {
+            values(&domains.allowed(), &client_id.domain)
+        })?;
+        report.check(
+            DPOP_PROOF,
+            "handle domain",
+            domains.verify_handle(&claims.custom.handle),
+            || values(&domains.allowed(), &claims.custom.handle),
+        )?;
         if let Some(expected_htm) = htm {
-            if expected_htm != claims.custom.htm {
-                return Err(RustyJwtError::DpopHtmMismatch);
-            }
-        }
-        if htu != &claims.custom.htu {
-            return Err(RustyJwtError::DpopHtuMismatch);
+            report.check(
+                DPOP_PROOF,
+                "htm",
+                ensure(expected_htm == claims.custom.htm, RustyJwtError::DpopHtmMismatch),
+                || values(&expected_htm, &claims.custom.htm),
+            )?;
         }
+        report.check(
+            DPOP_PROOF,
+            "htu",
+            ensure(htu == &claims.custom.htu, RustyJwtError::DpopHtuMismatch),
+            || values(htu, &claims.custom.htu),
+        )?;
         if let Some(chal) = challenge {
-            if chal != &claims.custom.challenge {
-                return Err(RustyJwtError::DpopChallengeMismatch);
-            }
-        }
-        if &claims.custom.handle != handle {
-            return Err(RustyJwtError::DpopHandleMismatch);
-        }
-        if team != &claims.custom.team {
-            return Err(RustyJwtError::DpopTeamMismatch);
+            report.check(
+                DPOP_PROOF,
+                "chal",
+                ensure(chal == &claims.custom.challenge, RustyJwtError::DpopChallengeMismatch),
+                || values(chal, &claims.custom.challenge),
+            )?;
         }
+        report.check(
+            DPOP_PROOF,
+            "handle",
+            ensure(&claims.custom.handle == handle, RustyJwtError::DpopHandleMismatch),
+            || values(handle, &claims.custom.handle),
+        )?;
+        report.check(
+            DPOP_PROOF,
+            "team",
+            ensure(
+                team_policy.verify(team, &claims.custom.team),
+                RustyJwtError::DpopTeamMismatch,
+            ),
+            || values(team, &claims.custom.team),
+        )?;
         Ok(claims)
     }
 }
@@ -11,6 +11,28 @@ pub enum RustyJwtError {
     #[error(transparent)]
     #[cfg(feature = "jwe")]
     JweError(#[from] biscuit::errors::Error),
+    /// JWE does not have the expected format or algorithms
+    #[error("JWE is invalid because {0}")]
+    InvalidJwe(&'static str),
+    /// CWT cannot be decoded or its signature is invalid
+    #[error("CWT is invalid because {0}")]
+    InvalidCwt(&'static str),
+    /// JWS in JSON General Serialization does not have the expected signatures
+    #[error("JWS is invalid because {0}")]
+    InvalidGeneralJws(&'static str),
+    /// JWS with a detached payload does not have the expected format
+    #[error("JWS with detached payload is invalid because {0}")]
+    InvalidDetachedJws(&'static str),
+    /// Signature does not match the signed data or the public key
+    #[error("Invalid signature")]
+    InvalidSignature,
+    /// 'x5c' certificate chain is missing, malformed or does not certify the signing key
+    #[error("'x5c' certificate chain is invalid because {0}")]
+    InvalidX5c(&'static str),
+    /// Error validating a X.509 certificate chain
+    #[error(transparent)]
+    #[cfg(feature = "x5c")]
+    X509CheckError(#[from] rusty_x509_check::RustyX509CheckError),
     /// Error generating random numbers
     #[error(transparent)]
     RandError(#[from] rand::Error),
@@ -77,12 +99,33 @@ pub enum RustyJwtError {
     /// JWT token expiry is later than supplied threshold
     #[error("JWT token expiry is later than supplied threshold")]
     TokenLivesTooLong,
+    /// Access token has been revoked before its expiry, see [crate::prelude::RevocationCheck]
+    #[error("Access token has been revoked")]
+    RevokedAccessToken,
     /// JWT token token lacks a claim
     #[error("JWT token token lacks '{0}' claim")]
     MissingTokenClaim(&'static str),
+    /// JWT token exceeds the maximum allowed length
+    #[error("JWT token exceeds the maximum length of {0} bytes")]
+    TokenTooLong(usize),
+    /// JWT token header or claims exceed the maximum allowed JSON nesting depth
+    #[error("JWT token exceeds the maximum JSON nesting depth of {0}")]
+    TokenTooDeep(usize),
+    /// JWT token 'crit' header is malformed or a critical header parameter has an invalid value
+    #[error("JWT token 'crit' header is invalid because {0}")]
+    InvalidCriticalHeader(&'static str),
+    /// JWT token lists in its 'crit' header a header parameter which is not understood
+    #[error("JWT token critical header parameter '{0}' is not supported")]
+    UnsupportedCriticalHeader(String),
+    /// JWT token has a claim it is not allowed to have
+    #[error("JWT token must not have a '{0}' claim")]
+    ForbiddenTokenClaim(&'static str),
     /// JWT token has an invalid "aud" claim
     #[error("JWT token has an invalid 'aud' claim")]
     InvalidAudience,
+    /// JWT token 'aud' claim does not match any of the expected audiences
+    #[error("JWT token 'aud' claim does not match any of the expected audiences")]
+    AudienceMismatch,
     /// DPoP token 'nonce' claim mismatches with the expected [crate::prelude::BackendNonce]
     #[error("DPoP token 'nonce' claim mismatches with the expected backend_nonce")]
     DpopNonceMismatch,
@@ -107,12 +150,27 @@ pub enum RustyJwtError {
     /// Supplied backend keys have an invalid format
     #[error("Supplied backend keys have an invalid format because {0}")]
     InvalidBackendKeys(&'static str),
+    /// No key of the JWK Set matches the token 'kid' header
+    #[error("No key of the JWK Set matches the 'kid' '{0}'")]
+    NoMatchingJwk(String),
+    /// The key of the JWK Set matching the token 'kid' cannot be used to verify it
+    #[error("The JWK Set key matching the 'kid' cannot be used because {0}")]
+    InvalidJwkSetKey(&'static str),
     /// see [crate::client_id::QualifiedClientId]
     #[error("Supplied client identifier is invalid")]
     InvalidClientId,
-    /// Verified a token with an unsupported wire-server API version
-    #[error("Verified a token with an unsupported wire-server API version")]
-    UnsupportedApiVersion,
+    /// Verified a token with an unsupported wire-server API version, see [crate::prelude::SupportedApiVersions]
+    #[error("Verified a token with an unsupported wire-server API version '{0}'")]
+    UnsupportedApiVersion(u32),
+    /// Supported wire-server API versions range has its lower bound greater than its upper one
+    #[error("Supported wire-server API versions range '{0}..={1}' is empty")]
+    InvalidApiVersionRange(u32, u32),
+    /// Verified a token whose claims are in an unsupported schema version, see [crate::prelude::ClaimsVersion]
+    #[error("Verified a token whose claims are in the unsupported schema version '{0}'")]
+    UnsupportedClaimsVersion(u64),
+    /// Verified a token for a domain outside of the allowed ones, see [crate::prelude::DomainPolicy]
+    #[error("Verified a token for the domain '{0}' which is not allowed")]
+    DomainNotAllowed(String),
     /// Verified a token with an unsupported scope
     #[error("Verified a token with an unsupported scope")]
     UnsupportedScope,
@@ -122,6 +180,12 @@ pub enum RustyJwtError {
     /// Invalid identifier (client id or handle) scheme
     #[error("Invalid identifier scheme '{0}', should be 'wireapp'")]
     InvalidIdentifierScheme(String),
+    /// A required field of a builder has not been set
+    #[error("Builder field '{0}' is required")]
+    MissingBuilderField(&'static str),
+    /// A field of a builder has an invalid value
+    #[error("Builder field '{0}' is invalid because {1}")]
+    InvalidBuilderField(&'static str, &'static str),
     /// We have done something terribly wrong
     #[error("We have done something terribly wrong and it needs to be fixed")]
     ImplementationError,
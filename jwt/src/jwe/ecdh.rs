@@ -0,0 +1,232 @@
+use base64::{prelude::BASE64_URL_SAFE_NO_PAD, Engine};
+use biscuit::jwa::{ContentEncryptionAlgorithm, EncryptionOptions, EncryptionResult};
+use jwt_simple::prelude::*;
+use p256::elliptic_curve::{group::Curve as _, sec1::ToEncodedPoint as _};
+use sha2::Digest as _;
+
+use crate::{
+    jwe::alg::JweAlgorithm,
+    jwk::{TryFromJwk, TryIntoJwk},
+    prelude::*,
+};
+
+/// Key agreement of the content encryption key with Elliptic Curve Diffie-Hellman Ephemeral Static
+///
+/// Specified in [RFC 7518 Section 4.6][1]
+///
+/// [1]: https://www.rfc-editor.org/rfc/rfc7518#section-4.6
+const ECDH_ES: &str = "ECDH-ES";
+
+/// Content encryption algorithm used along with [ECDH_ES]
+const ENC: JweAlgorithm = JweAlgorithm::AES256GCM;
+
+/// JOSE header of a JWE whose content encryption key is agreed with ECDH-ES, either directly
+/// ([ECDH_ES]) or to wrap it, or of one encrypted with a shared symmetric key
+#[derive(Debug, Serialize, Deserialize)]
+pub(super) struct EcdhEsHeader {
+    pub(super) alg: String,
+    pub(super) enc: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) cty: Option<String>,
+    /// ephemeral public key of the sender, absent without key agreement
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) epk: Option<Jwk>,
+}
+
+impl EcdhEsHeader {
+    /// Splits a JWE Compact Serialization into its 5 parts and decodes its protected header
+    pub(super) fn parse_compact(jwe: &str) -> RustyJwtResult<(Self, [&str; 5])> {
+        let parts: [&str; 5] = jwe
+            .split('.')
+            .collect::<Vec<_>>()
+            .try_into()
+            .map_err(|_| RustyJwtError::InvalidJwe("not in JWE Compact Serialization format"))?;
+        let header = serde_json::from_slice::<Self>(&BASE64_URL_SAFE_NO_PAD.decode(parts[0])?)?;
+        Ok((header, parts))
+    }
+
+    /// Ephemeral public key of the sender, required for key agreement
+    pub(super) fn epk(&self) -> RustyJwtResult<ES256PublicKey> {
+        let epk = self
+            .epk
+            .as_ref()
+            .ok_or(RustyJwtError::InvalidJwe("'epk' header is missing"))?;
+        ES256PublicKey::try_from_jwk(epk)
+    }
+}
+
+impl RustyJwtTools {
+    /// Encrypts a [payload] in a JWE with Compact Serialization for the holder of a P-256 key pair.
+    /// The content encryption key is agreed with ECDH-ES and the payload encrypted with A256GCM.
+    ///
+    /// Specified in [RFC 7516][1]
+    ///
+    /// # Arguments
+    /// * `recipient_pk` - PEM format P-256 public key of the recipient
+    /// * `payload` - any data to encrypt. It does not necessarily have to be json.
+    /// * `cty` - content type of the payload e.g. `JWT` for a nested JWT
+    ///
+    /// [1]: https://www.rfc-editor.org/rfc/rfc7516.html#section-7.1
+    pub fn jwe_encrypt_ecdh_es(recipient_pk: &Pem, payload: &[u8], cty: Option<&str>) -> RustyJwtResult<String> {
+        let recipient = ES256PublicKey::from_pem(recipient_pk)?;
+        let ephemeral = ES256KeyPair::generate();
+        let header = EcdhEsHeader {
+            alg: ECDH_ES.to_string(),
+            enc: ENC.to_string(),
+            cty: cty.map(str::to_string),
+            epk: Some(ephemeral.public_key().try_into_jwk()?),
+        };
+        let header = BASE64_URL_SAFE_NO_PAD.encode(serde_json::to_vec(&header)?);
+
+        let cek = Self::ecdh_es_cek(&ephemeral.to_bytes(), &recipient.public_key().to_bytes_uncompressed())?;
+        let key = Self::build_jwe_key(ENC, cek);
+        let nonce = Self::generate_nonce(ENC, &mut None::<rand_chacha::ChaCha20Rng>)?;
+        let encrypted = ContentEncryptionAlgorithm::from(ENC).encrypt(
+            payload,
+            header.as_bytes(),
+            &key,
+            &EncryptionOptions::AES_GCM { nonce },
+        )?;
+
+        // with direct key agreement, there is no encrypted key
+        let b64 = |v: &[u8]| BASE64_URL_SAFE_NO_PAD.encode(v);
+        Ok(format!(
+            "{header}..{}.{}.{}",
+            b64(&encrypted.nonce),
+            b64(&encrypted.encrypted),
+            b64(&encrypted.tag)
+        ))
+    }
+
+    /// Decrypts a JWE produced by [RustyJwtTools::jwe_encrypt_ecdh_es]
+    ///
+    /// # Arguments
+    /// * `recipient_kp` - PEM format P-256 private key of the recipient
+    /// * `jwe` - JWE Compact Serialization format
+    pub fn jwe_decrypt_ecdh_es(recipient_kp: &Pem, jwe: &str) -> RustyJwtResult<Vec<u8>> {
+        let (header, [protected, encrypted_key, iv, ciphertext, tag]) = EcdhEsHeader::parse_compact(jwe)?;
+        if !encrypted_key.is_empty() {
+            return Err(RustyJwtError::InvalidJwe("it should not have an encrypted key"));
+        }
+        if header.alg != ECDH_ES {
+            return Err(RustyJwtError::InvalidJwe("'alg' header is not 'ECDH-ES'"));
+        }
+        if header.enc != ENC.to_string() {
+            return Err(RustyJwtError::InvalidJwe("'enc' header is not 'A256GCM'"));
+        }
+
+        let recipient = ES256KeyPair::from_pem(recipient_kp)
+            .map_err(|_| RustyJwtError::InvalidJwe("recipient key is not a P-256 key pair"))?;
+        let epk = header.epk()?;
+        let cek = Self::ecdh_es_cek(&recipient.to_bytes(), &epk.public_key().to_bytes_uncompressed())?;
+        let encrypted = EncryptionResult {
+            nonce: BASE64_URL_SAFE_NO_PAD.decode(iv)?,
+            encrypted: BASE64_URL_SAFE_NO_PAD.decode(ciphertext)?,
+            tag: BASE64_URL_SAFE_NO_PAD.decode(tag)?,
+            additional_data: protected.as_bytes().to_vec(),
+        };
+        Ok(ContentEncryptionAlgorithm::from(ENC).decrypt(&encrypted, &Self::build_jwe_key(ENC, cek))?)
+    }
+
+    /// Agrees on the content encryption key out of a private key and the other party public key
+    fn ecdh_es_cek(sk: &[u8], pk: &[u8]) -> RustyJwtResult<Vec<u8>> {
+        Self::ecdh_es_key(sk, pk, &ENC.to_string(), ENC.key_length())
+    }
+
+    /// Agrees on a key of [key_len] bytes out of a private key and the other party public key.
+    /// [algorithm_id] is the 'enc' header in Direct Key Agreement mode, the 'alg' one otherwise.
+    pub(super) fn ecdh_es_key(sk: &[u8], pk: &[u8], algorithm_id: &str, key_len: usize) -> RustyJwtResult<Vec<u8>> {
+        let sk = p256::SecretKey::from_slice(sk).map_err(|_| RustyJwtError::InvalidJwe("invalid P-256 private key"))?;
+        let pk =
+            p256::PublicKey::from_sec1_bytes(pk).map_err(|_| RustyJwtError::InvalidJwe("invalid P-256 public key"))?;
+        let shared = (pk.to_projective() * *sk.to_nonzero_scalar()).to_affine();
+        let shared = shared.to_encoded_point(false);
+        let z = shared.x().ok_or(RustyJwtError::ImplementationError)?;
+        Ok(Self::concat_kdf(z, algorithm_id, key_len))
+    }
+
+    /// Concat KDF with SHA-256 of [RFC 7518 Section 4.6.2][1]. 'apu' and 'apv' are not used.
+    ///
+    /// [1]: https://www.rfc-editor.org/rfc/rfc7518#section-4.6.2
+    fn concat_kdf(z: &[u8], algorithm_id: &str, key_len: usize) -> Vec<u8> {
+        let mut hasher = sha2::Sha256::new();
+        // a single round suffices for keys up to 256 bits
+        hasher.update(1u32.to_be_bytes());
+        hasher.update(z);
+        hasher.update((algorithm_id.len() as u32).to_be_bytes());
+        hasher.update(algorithm_id.as_bytes());
+        hasher.update(0u32.to_be_bytes()); // PartyUInfo
+        hasher.update(0u32.to_be_bytes()); // PartyVInfo
+        hasher.update(((key_len * 8) as u32).to_be_bytes());
+        hasher.finalize()[..key_len].to_vec()
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use wasm_bindgen_test::*;
+
+    use crate::test_utils::*;
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn can_round_trip() {
+        let recipient = JwtKey::new_key(JwsAlgorithm::P256);
+        let payload = b"The true sign of intelligence is not knowledge but imagination.";
+        let jwe = RustyJwtTools::jwe_encrypt_ecdh_es(&recipient.pk, payload, Some("JWT")).unwrap();
+        assert_eq!(jwe.split('.').nth(1), Some(""));
+        let decrypted = RustyJwtTools::jwe_decrypt_ecdh_es(&recipient.kp, &jwe).unwrap();
+        assert_eq!(decrypted, payload);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_fail_decrypting_with_wrong_key() {
+        let recipient = JwtKey::new_key(JwsAlgorithm::P256);
+        let jwe = RustyJwtTools::jwe_encrypt_ecdh_es(&recipient.pk, b"abcd", None).unwrap();
+        let other = recipient.create_another();
+        let result = RustyJwtTools::jwe_decrypt_ecdh_es(&other.kp, &jwe);
+        assert!(matches!(result.unwrap_err(), RustyJwtError::JweError(_)));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_fail_when_not_a_jwe() {
+        let recipient = JwtKey::new_key(JwsAlgorithm::P256);
+        let result = RustyJwtTools::jwe_decrypt_ecdh_es(&recipient.kp, "a.b.c");
+        assert!(matches!(result.unwrap_err(), RustyJwtError::InvalidJwe(_)));
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    mod interop {
+        use super::*;
+
+        #[test]
+        fn should_roundtrip_with_josekit_encrypt() {
+            let recipient = JwtKey::new_key(JwsAlgorithm::P256);
+            let mut header = josekit::jwe::JweHeader::new();
+            header.set_content_encryption("A256GCM");
+            let encrypter = josekit::jwe::ECDH_ES
+                .encrypter_from_pem(recipient.pk.as_bytes())
+                .unwrap();
+            let jwe = josekit::jwe::serialize_compact(b"abcd", &header, &encrypter).unwrap();
+            let decrypted = RustyJwtTools::jwe_decrypt_ecdh_es(&recipient.kp, &jwe).unwrap();
+            assert_eq!(decrypted, b"abcd");
+        }
+
+        #[test]
+        fn should_roundtrip_with_josekit_decrypt() {
+            let recipient = JwtKey::new_key(JwsAlgorithm::P256);
+            let jwe = RustyJwtTools::jwe_encrypt_ecdh_es(&recipient.pk, b"abcd", None).unwrap();
+            let decrypter = josekit::jwe::ECDH_ES
+                .decrypter_from_pem(recipient.kp.as_bytes())
+                .unwrap();
+            let (decrypted, _) = josekit::jwe::deserialize_compact(&jwe, &decrypter).unwrap();
+            assert_eq!(decrypted, b"abcd");
+        }
+    }
+}
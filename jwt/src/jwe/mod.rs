@@ -10,6 +10,10 @@ use alg::JweAlgorithm;
 use crate::prelude::*;
 
 pub mod alg;
+mod ecdh;
+mod recipient;
+
+pub use recipient::JweRecipientKey;
 
 pub trait Rng: rand::RngCore + rand::CryptoRng {}
 
@@ -0,0 +1,309 @@
+use base64::{prelude::BASE64_URL_SAFE_NO_PAD, Engine};
+use biscuit::jwa::{ContentEncryptionAlgorithm, EncryptionOptions, EncryptionResult};
+use jwt_simple::prelude::*;
+use serde::de::DeserializeOwned;
+
+use crate::{
+    jwe::{alg::JweAlgorithm, ecdh::EcdhEsHeader},
+    jwk::{TryFromJwk, TryIntoJwk},
+    prelude::*,
+};
+
+/// Content encryption key randomly generated and wrapped with AES Key Wrap using a key agreed with
+/// Elliptic Curve Diffie-Hellman Ephemeral Static
+///
+/// Specified in [RFC 7518 Section 4.6][1]
+///
+/// [1]: https://www.rfc-editor.org/rfc/rfc7518#section-4.6
+const ECDH_ES_A256KW: &str = "ECDH-ES+A256KW";
+
+/// The recipient symmetric key is directly the content encryption key
+///
+/// Specified in [RFC 7518 Section 4.5][1]
+///
+/// [1]: https://www.rfc-editor.org/rfc/rfc7518#section-4.5
+const DIR: &str = "dir";
+
+/// Content encryption algorithm used with both [ECDH_ES_A256KW] and [DIR]
+const ENC: JweAlgorithm = JweAlgorithm::AES256GCM;
+
+/// Key of the recipient of a JWE produced by [RustyJwtTools::jwe_encrypt_claims], to decrypt it
+#[derive(Debug, Clone, Copy)]
+pub enum JweRecipientKey<'a> {
+    /// PEM format P-256 key pair, for a JWE encrypted to its public key with 'ECDH-ES+A256KW'
+    P256(&'a Pem),
+    /// symmetric key JWK ('kty' "oct") of 256 bits, for a JWE encrypted with 'dir'
+    Oct(&'a Jwk),
+}
+
+impl RustyJwtTools {
+    /// Encrypts claims in a JWE with Compact Serialization for the holder of [recipient_jwk]. The
+    /// key management algorithm depends on the JWK:
+    /// * P-256 public key: 'ECDH-ES+A256KW'
+    /// * 256 bits symmetric key ('kty' "oct"): 'dir'
+    ///
+    /// Content is always encrypted with 'A256GCM'.
+    ///
+    /// # Arguments
+    /// * `claims` - anything serializable as json e.g. [JWTClaims]
+    /// * `recipient_jwk` - public key of the recipient, or the symmetric key shared with it
+    pub fn jwe_encrypt_claims(claims: &impl Serialize, recipient_jwk: &Jwk) -> RustyJwtResult<String> {
+        let (header, cek, encrypted_key) = match &recipient_jwk.algorithm {
+            AlgorithmParameters::EllipticCurve(_) => {
+                let recipient = ES256PublicKey::try_from_jwk(recipient_jwk)
+                    .map_err(|_| RustyJwtError::InvalidJwe("recipient key is not a P-256 public key"))?;
+                let ephemeral = ES256KeyPair::generate();
+                let kek = Self::ecdh_es_key(
+                    &ephemeral.to_bytes(),
+                    &recipient.public_key().to_bytes_uncompressed(),
+                    ECDH_ES_A256KW,
+                    ENC.key_length(),
+                )?;
+                let cek = Self::random_cek()?;
+                let encrypted_key = kek_aes256(&kek)?
+                    .wrap_vec(&cek)
+                    .map_err(|_| RustyJwtError::ImplementationError)?;
+                let header = EcdhEsHeader {
+                    alg: ECDH_ES_A256KW.to_string(),
+                    enc: ENC.to_string(),
+                    cty: None,
+                    epk: Some(ephemeral.public_key().try_into_jwk()?),
+                };
+                (header, cek, encrypted_key)
+            }
+            AlgorithmParameters::OctetKey(_) => {
+                let header = EcdhEsHeader {
+                    alg: DIR.to_string(),
+                    enc: ENC.to_string(),
+                    cty: None,
+                    epk: None,
+                };
+                (header, oct_key(recipient_jwk)?, vec![])
+            }
+            _ => {
+                return Err(RustyJwtError::InvalidJwe(
+                    "recipient key is neither P-256 nor symmetric",
+                ))
+            }
+        };
+        let header = BASE64_URL_SAFE_NO_PAD.encode(serde_json::to_vec(&header)?);
+
+        let nonce = Self::generate_nonce(ENC, &mut None::<rand_chacha::ChaCha20Rng>)?;
+        let encrypted = ContentEncryptionAlgorithm::from(ENC).encrypt(
+            &serde_json::to_vec(claims)?,
+            header.as_bytes(),
+            &Self::build_jwe_key(ENC, cek),
+            &EncryptionOptions::AES_GCM { nonce },
+        )?;
+
+        let b64 = |v: &[u8]| BASE64_URL_SAFE_NO_PAD.encode(v);
+        Ok(format!(
+            "{header}.{}.{}.{}.{}",
+            b64(&encrypted_key),
+            b64(&encrypted.nonce),
+            b64(&encrypted.encrypted),
+            b64(&encrypted.tag)
+        ))
+    }
+
+    /// Decrypts a JWE produced by [RustyJwtTools::jwe_encrypt_claims]
+    ///
+    /// # Arguments
+    /// * `jwe` - JWE Compact Serialization format
+    /// * `key` - key of the recipient, matching the 'alg' header of the JWE
+    pub fn jwe_decrypt_claims<T: DeserializeOwned>(jwe: &str, key: JweRecipientKey) -> RustyJwtResult<T> {
+        let (header, [protected, encrypted_key, iv, ciphertext, tag]) = EcdhEsHeader::parse_compact(jwe)?;
+        if header.enc != ENC.to_string() {
+            return Err(RustyJwtError::InvalidJwe("'enc' header is not 'A256GCM'"));
+        }
+        let encrypted_key = BASE64_URL_SAFE_NO_PAD.decode(encrypted_key)?;
+
+        let cek = match (header.alg.as_str(), key) {
+            (ECDH_ES_A256KW, JweRecipientKey::P256(recipient_kp)) => {
+                let recipient = ES256KeyPair::from_pem(recipient_kp)
+                    .map_err(|_| RustyJwtError::InvalidJwe("recipient key is not a P-256 key pair"))?;
+                let epk = header.epk()?;
+                let kek = Self::ecdh_es_key(
+                    &recipient.to_bytes(),
+                    &epk.public_key().to_bytes_uncompressed(),
+                    ECDH_ES_A256KW,
+                    ENC.key_length(),
+                )?;
+                kek_aes256(&kek)?
+                    .unwrap_vec(&encrypted_key)
+                    .map_err(|_| RustyJwtError::InvalidJwe("content encryption key cannot be unwrapped"))?
+            }
+            (DIR, JweRecipientKey::Oct(jwk)) => {
+                if !encrypted_key.is_empty() {
+                    return Err(RustyJwtError::InvalidJwe("it should not have an encrypted key"));
+                }
+                oct_key(jwk)?
+            }
+            (ECDH_ES_A256KW | DIR, _) => {
+                return Err(RustyJwtError::InvalidJwe(
+                    "recipient key does not match the 'alg' header",
+                ))
+            }
+            _ => return Err(RustyJwtError::InvalidJwe("'alg' header is not supported")),
+        };
+
+        let encrypted = EncryptionResult {
+            nonce: BASE64_URL_SAFE_NO_PAD.decode(iv)?,
+            encrypted: BASE64_URL_SAFE_NO_PAD.decode(ciphertext)?,
+            tag: BASE64_URL_SAFE_NO_PAD.decode(tag)?,
+            additional_data: protected.as_bytes().to_vec(),
+        };
+        let decrypted = ContentEncryptionAlgorithm::from(ENC).decrypt(&encrypted, &Self::build_jwe_key(ENC, cek))?;
+        Ok(serde_json::from_slice(&decrypted)?)
+    }
+
+    fn random_cek() -> RustyJwtResult<Vec<u8>> {
+        use rand::{RngCore as _, SeedableRng as _};
+        let mut cek = vec![0u8; ENC.key_length()];
+        rand_chacha::ChaCha20Rng::from_entropy().try_fill_bytes(&mut cek)?;
+        Ok(cek)
+    }
+}
+
+fn kek_aes256(kek: &[u8]) -> RustyJwtResult<aes_kw::KekAes256> {
+    aes_kw::KekAes256::try_from(kek).map_err(|_| RustyJwtError::ImplementationError)
+}
+
+/// Raw value of a symmetric key JWK, which has to be suitable for 'A256GCM'
+fn oct_key(jwk: &Jwk) -> RustyJwtResult<Vec<u8>> {
+    match &jwk.algorithm {
+        AlgorithmParameters::OctetKey(OctetKeyParameters { value, .. }) => {
+            let key = BASE64_URL_SAFE_NO_PAD.decode(value)?;
+            if key.len() != ENC.key_length() {
+                return Err(RustyJwtError::InvalidJwe("symmetric key is not 256 bits long"));
+            }
+            Ok(key)
+        }
+        _ => Err(RustyJwtError::InvalidJwe("recipient key is not a symmetric key")),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use serde_json::json;
+    use wasm_bindgen_test::*;
+
+    use crate::test_utils::*;
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn can_round_trip_ecdh_es_a256kw() {
+        let recipient = JwtKey::new_key(JwsAlgorithm::P256);
+        let claims = Claims::with_custom_claims(json!({"email": "alice@wire.com"}), Duration::from_hours(1));
+        let jwe = RustyJwtTools::jwe_encrypt_claims(&claims, &recipient.to_jwk()).unwrap();
+        assert_eq!(alg(&jwe), ECDH_ES_A256KW);
+        let decrypted: JWTClaims<serde_json::Value> =
+            RustyJwtTools::jwe_decrypt_claims(&jwe, JweRecipientKey::P256(&recipient.kp)).unwrap();
+        assert_eq!(decrypted.custom, claims.custom);
+        assert_eq!(decrypted.expires_at, claims.expires_at);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn can_round_trip_dir() {
+        let key = oct_jwk(&[7u8; 32]);
+        let claims = json!({"email": "alice@wire.com"});
+        let jwe = RustyJwtTools::jwe_encrypt_claims(&claims, &key).unwrap();
+        assert_eq!(alg(&jwe), DIR);
+        assert_eq!(jwe.split('.').nth(1), Some(""));
+        let decrypted: serde_json::Value = RustyJwtTools::jwe_decrypt_claims(&jwe, JweRecipientKey::Oct(&key)).unwrap();
+        assert_eq!(decrypted, claims);
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_fail_decrypting_with_wrong_key() {
+        let recipient = JwtKey::new_key(JwsAlgorithm::P256);
+        let jwe = RustyJwtTools::jwe_encrypt_claims(&json!({}), &recipient.to_jwk()).unwrap();
+        let other = recipient.create_another();
+        let result = RustyJwtTools::jwe_decrypt_claims::<serde_json::Value>(&jwe, JweRecipientKey::P256(&other.kp));
+        assert!(matches!(result.unwrap_err(), RustyJwtError::InvalidJwe(_)));
+
+        let jwe = RustyJwtTools::jwe_encrypt_claims(&json!({}), &oct_jwk(&[7u8; 32])).unwrap();
+        let other = oct_jwk(&[8u8; 32]);
+        let result = RustyJwtTools::jwe_decrypt_claims::<serde_json::Value>(&jwe, JweRecipientKey::Oct(&other));
+        assert!(matches!(result.unwrap_err(), RustyJwtError::JweError(_)));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_fail_when_key_does_not_match_alg() {
+        let recipient = JwtKey::new_key(JwsAlgorithm::P256);
+        let jwe = RustyJwtTools::jwe_encrypt_claims(&json!({}), &recipient.to_jwk()).unwrap();
+        let key = oct_jwk(&[7u8; 32]);
+        let result = RustyJwtTools::jwe_decrypt_claims::<serde_json::Value>(&jwe, JweRecipientKey::Oct(&key));
+        assert!(matches!(result.unwrap_err(), RustyJwtError::InvalidJwe(_)));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_fail_with_unsupported_recipient_key() {
+        let ed25519 = JwtKey::new_key(JwsAlgorithm::Ed25519).to_jwk();
+        let result = RustyJwtTools::jwe_encrypt_claims(&json!({}), &ed25519);
+        assert!(matches!(result.unwrap_err(), RustyJwtError::InvalidJwe(_)));
+
+        let p384 = JwtKey::new_key(JwsAlgorithm::P384).to_jwk();
+        let result = RustyJwtTools::jwe_encrypt_claims(&json!({}), &p384);
+        assert!(matches!(result.unwrap_err(), RustyJwtError::InvalidJwe(_)));
+
+        let too_short = oct_jwk(&[7u8; 16]);
+        let result = RustyJwtTools::jwe_encrypt_claims(&json!({}), &too_short);
+        assert!(matches!(result.unwrap_err(), RustyJwtError::InvalidJwe(_)));
+    }
+
+    fn oct_jwk(key: &[u8]) -> Jwk {
+        serde_json::from_value(json!({"kty": "oct", "k": BASE64_URL_SAFE_NO_PAD.encode(key)})).unwrap()
+    }
+
+    fn alg(jwe: &str) -> String {
+        EcdhEsHeader::parse_compact(jwe).unwrap().0.alg
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    mod interop {
+        use super::*;
+
+        #[test]
+        fn should_decrypt_josekit_ecdh_es_a256kw() {
+            let recipient = JwtKey::new_key(JwsAlgorithm::P256);
+            let mut header = josekit::jwe::JweHeader::new();
+            header.set_content_encryption("A256GCM");
+            let encrypter = josekit::jwe::ECDH_ES_A256KW
+                .encrypter_from_pem(recipient.pk.as_bytes())
+                .unwrap();
+            let jwe = josekit::jwe::serialize_compact(br#"{"a":"b"}"#, &header, &encrypter).unwrap();
+            let decrypted: serde_json::Value =
+                RustyJwtTools::jwe_decrypt_claims(&jwe, JweRecipientKey::P256(&recipient.kp)).unwrap();
+            assert_eq!(decrypted, json!({"a": "b"}));
+        }
+
+        #[test]
+        fn josekit_should_decrypt_ecdh_es_a256kw() {
+            let recipient = JwtKey::new_key(JwsAlgorithm::P256);
+            let jwe = RustyJwtTools::jwe_encrypt_claims(&json!({"a": "b"}), &recipient.to_jwk()).unwrap();
+            let decrypter = josekit::jwe::ECDH_ES_A256KW
+                .decrypter_from_pem(recipient.kp.as_bytes())
+                .unwrap();
+            let (decrypted, _) = josekit::jwe::deserialize_compact(&jwe, &decrypter).unwrap();
+            assert_eq!(decrypted, br#"{"a":"b"}"#);
+        }
+
+        #[test]
+        fn josekit_should_decrypt_dir() {
+            let key = [7u8; 32];
+            let jwe = RustyJwtTools::jwe_encrypt_claims(&json!({"a": "b"}), &oct_jwk(&key)).unwrap();
+            let decrypter = josekit::jwe::Dir.decrypter_from_bytes(key).unwrap();
+            let (decrypted, _) = josekit::jwe::deserialize_compact(&jwe, &decrypter).unwrap();
+            assert_eq!(decrypted, br#"{"a":"b"}"#);
+        }
+    }
+}
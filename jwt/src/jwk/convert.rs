@@ -0,0 +1,236 @@
+use jwt_simple::prelude::*;
+
+use super::*;
+
+/// A private key as a JWK i.e. its public JWK along with the private key 'd' parameter
+///
+/// Specified in [RFC 7518 Section 6.2.2][1] and [RFC 8037 Section 2][2]
+///
+/// [1]: https://www.rfc-editor.org/rfc/rfc7518#section-6.2.2
+/// [2]: https://www.rfc-editor.org/rfc/rfc8037#section-2
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PrivateJwk {
+    /// public part of the key
+    #[serde(flatten)]
+    pub public: Jwk,
+    /// base64url encoded private key: the scalar for ECDSA keys, the seed for EdDSA ones
+    #[serde(rename = "d")]
+    pub d: String,
+}
+
+impl std::fmt::Debug for PrivateJwk {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PrivateJwk")
+            .field("public", &self.public)
+            .field("d", &"***")
+            .finish()
+    }
+}
+
+impl RustyJwk {
+    /// Converts a PEM encoded public key into a JWK
+    pub fn public_pem_to_jwk(alg: JwsAlgorithm, pk: &Pem) -> RustyJwtResult<Jwk> {
+        match alg {
+            JwsAlgorithm::P256 => ES256PublicKey::from_pem(pk)?.try_into_jwk(),
+            JwsAlgorithm::P384 => ES384PublicKey::from_pem(pk)?.try_into_jwk(),
+            JwsAlgorithm::Ed25519 => Ed25519PublicKey::from_pem(pk)?.try_into_jwk(),
+        }
+    }
+
+    /// Converts a public key JWK into PEM
+    pub fn public_jwk_to_pem(alg: JwsAlgorithm, jwk: &Jwk) -> RustyJwtResult<Pem> {
+        Ok(match alg {
+            JwsAlgorithm::P256 => ES256PublicKey::try_from_jwk(jwk)?.to_pem()?.into(),
+            JwsAlgorithm::P384 => ES384PublicKey::try_from_jwk(jwk)?.to_pem()?.into(),
+            JwsAlgorithm::Ed25519 => Ed25519PublicKey::try_from_jwk(jwk)?.to_pem().into(),
+        })
+    }
+
+    /// Converts a DER encoded public key (SubjectPublicKeyInfo) into a JWK
+    pub fn public_der_to_jwk(alg: JwsAlgorithm, pk: &[u8]) -> RustyJwtResult<Jwk> {
+        match alg {
+            JwsAlgorithm::P256 => ES256PublicKey::from_der(pk)?.try_into_jwk(),
+            JwsAlgorithm::P384 => ES384PublicKey::from_der(pk)?.try_into_jwk(),
+            JwsAlgorithm::Ed25519 => Ed25519PublicKey::from_der(pk)?.try_into_jwk(),
+        }
+    }
+
+    /// Converts a public key JWK into DER (SubjectPublicKeyInfo)
+    pub fn public_jwk_to_der(alg: JwsAlgorithm, jwk: &Jwk) -> RustyJwtResult<Vec<u8>> {
+        Ok(match alg {
+            JwsAlgorithm::P256 => ES256PublicKey::try_from_jwk(jwk)?.to_der()?,
+            JwsAlgorithm::P384 => ES384PublicKey::try_from_jwk(jwk)?.to_der()?,
+            JwsAlgorithm::Ed25519 => Ed25519PublicKey::try_from_jwk(jwk)?.to_der(),
+        })
+    }
+
+    /// Converts a PEM encoded (PKCS#8) key pair into a private JWK
+    pub fn private_pem_to_jwk(alg: JwsAlgorithm, kp: &Pem) -> RustyJwtResult<PrivateJwk> {
+        match alg {
+            JwsAlgorithm::P256 => ES256KeyPair::from_pem(kp)?.try_into_private_jwk(),
+            JwsAlgorithm::P384 => ES384KeyPair::from_pem(kp)?.try_into_private_jwk(),
+            JwsAlgorithm::Ed25519 => Ed25519KeyPair::from_pem(kp)?.try_into_private_jwk(),
+        }
+    }
+
+    /// Converts a private JWK into a PEM encoded (PKCS#8) key pair
+    pub fn private_jwk_to_pem(alg: JwsAlgorithm, jwk: &PrivateJwk) -> RustyJwtResult<Pem> {
+        Ok(match alg {
+            JwsAlgorithm::P256 => ES256KeyPair::try_from_private_jwk(jwk)?.to_pem()?.into(),
+            JwsAlgorithm::P384 => ES384KeyPair::try_from_private_jwk(jwk)?.to_pem()?.into(),
+            JwsAlgorithm::Ed25519 => Ed25519KeyPair::try_from_private_jwk(jwk)?.to_pem().into(),
+        })
+    }
+
+    /// Converts a DER encoded (PKCS#8) key pair into a private JWK
+    pub fn private_der_to_jwk(alg: JwsAlgorithm, kp: &[u8]) -> RustyJwtResult<PrivateJwk> {
+        match alg {
+            JwsAlgorithm::P256 => ES256KeyPair::from_der(kp)?.try_into_private_jwk(),
+            JwsAlgorithm::P384 => ES384KeyPair::from_der(kp)?.try_into_private_jwk(),
+            JwsAlgorithm::Ed25519 => Ed25519KeyPair::from_der(kp)?.try_into_private_jwk(),
+        }
+    }
+
+    /// Converts a private JWK into a DER encoded (PKCS#8) key pair
+    pub fn private_jwk_to_der(alg: JwsAlgorithm, jwk: &PrivateJwk) -> RustyJwtResult<Vec<u8>> {
+        Ok(match alg {
+            JwsAlgorithm::P256 => ES256KeyPair::try_from_private_jwk(jwk)?.to_der()?,
+            JwsAlgorithm::P384 => ES384KeyPair::try_from_private_jwk(jwk)?.to_der()?,
+            JwsAlgorithm::Ed25519 => Ed25519KeyPair::try_from_private_jwk(jwk)?.to_der(),
+        })
+    }
+}
+
+/// Conversions of a key pair from/to a [PrivateJwk]
+trait PrivateJwkKeyPair: Sized {
+    fn try_into_private_jwk(self) -> RustyJwtResult<PrivateJwk>;
+
+    /// Fails when the public part of [jwk] is not the public key of its 'd' parameter
+    fn try_from_private_jwk(jwk: &PrivateJwk) -> RustyJwtResult<Self>;
+}
+
+/// Whether both JWKs represent the same public key, regardless of their optional parameters
+fn same_public_key(a: &Jwk, b: &Jwk) -> RustyJwtResult<bool> {
+    Ok(serde_json::to_value(&a.algorithm)? == serde_json::to_value(&b.algorithm)?)
+}
+
+impl PrivateJwkKeyPair for ES256KeyPair {
+    fn try_into_private_jwk(self) -> RustyJwtResult<PrivateJwk> {
+        Ok(PrivateJwk {
+            public: self.public_key().try_into_jwk()?,
+            d: RustyJwk::base64_url_encode(self.to_bytes()),
+        })
+    }
+
+    fn try_from_private_jwk(jwk: &PrivateJwk) -> RustyJwtResult<Self> {
+        let kp = Self::from_bytes(&RustyJwk::base64_url_decode(&jwk.d)?)?;
+        if !same_public_key(&kp.public_key().try_into_jwk()?, &jwk.public)? {
+            return Err(RustyJwtError::InvalidDpopJwk);
+        }
+        Ok(kp)
+    }
+}
+
+impl PrivateJwkKeyPair for ES384KeyPair {
+    fn try_into_private_jwk(self) -> RustyJwtResult<PrivateJwk> {
+        Ok(PrivateJwk {
+            public: self.public_key().try_into_jwk()?,
+            d: RustyJwk::base64_url_encode(self.to_bytes()),
+        })
+    }
+
+    fn try_from_private_jwk(jwk: &PrivateJwk) -> RustyJwtResult<Self> {
+        let kp = Self::from_bytes(&RustyJwk::base64_url_decode(&jwk.d)?)?;
+        if !same_public_key(&kp.public_key().try_into_jwk()?, &jwk.public)? {
+            return Err(RustyJwtError::InvalidDpopJwk);
+        }
+        Ok(kp)
+    }
+}
+
+impl PrivateJwkKeyPair for Ed25519KeyPair {
+    fn try_into_private_jwk(self) -> RustyJwtResult<PrivateJwk> {
+        // the key pair is the seed followed by the public key
+        let seed = &self.to_bytes()[..ed25519_compact::Seed::BYTES];
+        Ok(PrivateJwk {
+            public: self.public_key().try_into_jwk()?,
+            d: RustyJwk::base64_url_encode(seed),
+        })
+    }
+
+    fn try_from_private_jwk(jwk: &PrivateJwk) -> RustyJwtResult<Self> {
+        let seed = ed25519_compact::Seed::from_slice(&RustyJwk::base64_url_decode(&jwk.d)?)
+            .map_err(|_| RustyJwtError::InvalidDpopJwk)?;
+        let kp = Self::from_bytes(&*ed25519_compact::KeyPair::from_seed(seed))?;
+        if !same_public_key(&kp.public_key().try_into_jwk()?, &jwk.public)? {
+            return Err(RustyJwtError::InvalidDpopJwk);
+        }
+        Ok(kp)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use wasm_bindgen_test::*;
+
+    use crate::test_utils::*;
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[apply(all_keys)]
+    #[wasm_bindgen_test]
+    fn should_roundtrip_public_key(key: JwtKey) {
+        let jwk = RustyJwk::public_pem_to_jwk(key.alg, &key.pk).unwrap();
+        let pem = RustyJwk::public_jwk_to_pem(key.alg, &jwk).unwrap();
+        assert_eq!(pem.trim(), key.pk.trim());
+
+        let der = RustyJwk::public_jwk_to_der(key.alg, &jwk).unwrap();
+        let from_der = RustyJwk::public_der_to_jwk(key.alg, &der).unwrap();
+        assert!(same_public_key(&jwk, &from_der).unwrap());
+    }
+
+    #[apply(all_keys)]
+    #[wasm_bindgen_test]
+    fn should_roundtrip_private_key(key: JwtKey) {
+        let jwk = RustyJwk::private_pem_to_jwk(key.alg, &key.kp).unwrap();
+        assert!(same_public_key(&jwk.public, &key.to_jwk()).unwrap());
+
+        // survives JSON serialization
+        let json = serde_json::to_value(&jwk).unwrap();
+        assert!(json.get("d").is_some() && json.get("x").is_some());
+        let jwk = serde_json::from_value::<PrivateJwk>(json).unwrap();
+
+        let pem = RustyJwk::private_jwk_to_pem(key.alg, &jwk).unwrap();
+        let der = RustyJwk::private_jwk_to_der(key.alg, &jwk).unwrap();
+        let from_der = RustyJwk::private_der_to_jwk(key.alg, &der).unwrap();
+        assert_eq!(from_der.d, jwk.d);
+
+        // the converted key pair still signs for the original public key
+        let claims = Claims::create(Duration::from_hours(1));
+        let token =
+            RustyJwtTools::generate_jwt::<NoCustomClaims>(key.alg, JWTHeader::default(), Some(claims), &pem, false)
+                .unwrap();
+        let pk = AnyPublicKey::from((key.alg, &key.pk));
+        assert!(pk.verify_token::<NoCustomClaims>(&token, None).is_ok());
+    }
+
+    #[apply(all_keys)]
+    #[wasm_bindgen_test]
+    fn should_not_leak_private_key_in_debug(key: JwtKey) {
+        let jwk = RustyJwk::private_pem_to_jwk(key.alg, &key.kp).unwrap();
+        let debug = format!("{jwk:?}");
+        assert!(!debug.contains(&jwk.d));
+        assert!(debug.contains("***"));
+    }
+
+    #[apply(all_keys)]
+    #[wasm_bindgen_test]
+    fn should_fail_when_private_jwk_public_part_differs(key: JwtKey) {
+        let mut jwk = RustyJwk::private_pem_to_jwk(key.alg, &key.kp).unwrap();
+        jwk.public = key.create_another().to_jwk();
+        let result = RustyJwk::private_jwk_to_pem(key.alg, &jwk);
+        assert!(matches!(result.unwrap_err(), RustyJwtError::InvalidDpopJwk));
+    }
+}
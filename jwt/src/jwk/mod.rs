@@ -5,10 +5,15 @@ use jwt_simple::prelude::*;
 
 use crate::prelude::*;
 
+mod convert;
 mod ecdsa;
 mod eddsa;
-#[cfg(feature = "test-utils")]
+#[cfg(feature = "rsa")]
 mod rsa;
+mod set;
+
+pub use convert::PrivateJwk;
+pub use set::JwkSet;
 
 /// From json to JWK
 pub trait TryIntoJwk {
@@ -25,7 +30,7 @@ where
     fn try_from_jwk(jwk: &Jwk) -> RustyJwtResult<Self>;
 }
 
-/// JWK utilities
+/// JWK utilities, including conversions of public & private keys between PEM, DER and JWK
 pub struct RustyJwk;
 
 impl RustyJwk {
@@ -30,3 +30,19 @@ impl TryFromJwk for RS256PublicKey {
         })
     }
 }
+
+impl TryIntoJwk for PS256PublicKey {
+    fn try_into_jwk(self) -> RustyJwtResult<Jwk> {
+        let c = self.to_components();
+        let e = base64::prelude::BASE64_URL_SAFE_NO_PAD.encode(c.e);
+        let n = base64::prelude::BASE64_URL_SAFE_NO_PAD.encode(c.n);
+        Ok(Jwk {
+            common: CommonParameters::default(),
+            algorithm: AlgorithmParameters::RSA(RSAKeyParameters {
+                key_type: RSAKeyType::RSA,
+                e,
+                n,
+            }),
+        })
+    }
+}
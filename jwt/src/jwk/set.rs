@@ -0,0 +1,158 @@
+use jwt_simple::prelude::*;
+
+use crate::{jwt::VerifyJwtHeader, prelude::*};
+
+/// A set of public keys, for example the ones a server publishes to verify the tokens it issues.
+///
+/// Specified in [RFC 7517 Section 5: JWK Set Format][1]
+///
+/// [1]: https://www.rfc-editor.org/rfc/rfc7517#section-5
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct JwkSet {
+    /// The keys of the set
+    pub keys: Vec<Jwk>,
+}
+
+impl JwkSet {
+    /// Selects the key matching the token header 'kid' and verifies it is consistent with the
+    /// header 'alg' and meant for verifying signatures.
+    pub fn select<'a>(&'a self, header: &TokenMetadata) -> RustyJwtResult<AnyPublicKey<'a>> {
+        let alg = header.verify_jwt_header()?;
+        let kid = header.key_id().ok_or(RustyJwtError::MissingDpopHeader("kid"))?;
+        let jwk = self
+            .keys
+            .iter()
+            .find(|jwk| jwk.common.key_id.as_deref() == Some(kid))
+            .ok_or_else(|| RustyJwtError::NoMatchingJwk(kid.to_string()))?;
+
+        if matches!(&jwk.common.algorithm, Some(jwk_alg) if jwk_alg != &alg.to_string()) {
+            return Err(RustyJwtError::InvalidJwkSetKey("'alg' mismatches the token one"));
+        }
+        let is_alg_consistent = match (&jwk.algorithm, alg) {
+            (AlgorithmParameters::EllipticCurve(p), JwsAlgorithm::P256) => p.curve == EllipticCurve::P256,
+            (AlgorithmParameters::EllipticCurve(p), JwsAlgorithm::P384) => p.curve == EllipticCurve::P384,
+            (AlgorithmParameters::OctetKeyPair(p), JwsAlgorithm::Ed25519) => p.curve == EdwardCurve::Ed25519,
+            _ => false,
+        };
+        if !is_alg_consistent {
+            return Err(RustyJwtError::InvalidJwkSetKey("key type mismatches the token 'alg'"));
+        }
+        if matches!(&jwk.common.public_key_use, Some(u) if !matches!(u, PublicKeyUse::Signature)) {
+            return Err(RustyJwtError::InvalidJwkSetKey("'use' is not 'sig'"));
+        }
+        Ok(AnyPublicKey::from((alg, jwk)))
+    }
+}
+
+impl From<Vec<Jwk>> for JwkSet {
+    fn from(keys: Vec<Jwk>) -> Self {
+        Self { keys }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use wasm_bindgen_test::*;
+
+    use crate::{
+        jwt::{AudiencePolicy, ClaimsPolicy, TokenLimits, Verify, VerifyJwt},
+        test_utils::*,
+    };
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[apply(all_keys)]
+    #[wasm_bindgen_test]
+    fn should_verify_with_key_matching_kid(key: JwtKey) {
+        let other = key.create_another();
+        let jwks = JwkSet::from(vec![with_kid(other.to_jwk(), "a"), with_kid(key.to_jwk(), "b")]);
+
+        // should pick the key matching the 'kid'
+        let token = sign(&key, Some("b"));
+        assert!(verify(&token, &jwks).is_ok());
+
+        // should fail when the key matching the 'kid' did not sign the token
+        let token = sign(&key, Some("a"));
+        assert!(verify(&token, &jwks).is_err());
+
+        // should fail when no key matches
+        let token = sign(&key, Some("c"));
+        assert!(matches!(verify(&token, &jwks).unwrap_err(), RustyJwtError::NoMatchingJwk(kid) if kid == "c"));
+
+        // should fail when there is no 'kid'
+        let token = sign(&key, None);
+        assert!(matches!(
+            verify(&token, &jwks).unwrap_err(),
+            RustyJwtError::MissingDpopHeader("kid")
+        ));
+    }
+
+    #[apply(all_keys)]
+    #[wasm_bindgen_test]
+    fn should_fail_when_key_inconsistent(key: JwtKey) {
+        let token = sign(&key, Some("a"));
+
+        // 'alg' of the JWK differs from the token one
+        let [other_alg, _] = key.reverse_algorithms();
+        let mut jwk = with_kid(key.to_jwk(), "a");
+        jwk.common.algorithm = Some(other_alg.to_string());
+        let result = verify(&token, &vec![jwk].into());
+        assert!(matches!(result.unwrap_err(), RustyJwtError::InvalidJwkSetKey(_)));
+
+        // JWK is of another key type
+        let jwk = with_kid(JwtKey::new_key(other_alg).to_jwk(), "a");
+        let result = verify(&token, &vec![jwk].into());
+        assert!(matches!(result.unwrap_err(), RustyJwtError::InvalidJwkSetKey(_)));
+
+        // JWK is meant for encryption
+        let mut jwk = with_kid(key.to_jwk(), "a");
+        jwk.common.public_key_use = Some(PublicKeyUse::Encryption);
+        let result = verify(&token, &vec![jwk].into());
+        assert!(matches!(result.unwrap_err(), RustyJwtError::InvalidJwkSetKey(_)));
+
+        // should succeed when 'alg' & 'use' are consistent
+        let mut jwk = with_kid(key.to_jwk(), "a");
+        jwk.common.algorithm = Some(key.alg.to_string());
+        jwk.common.public_key_use = Some(PublicKeyUse::Signature);
+        assert!(verify(&token, &vec![jwk].into()).is_ok());
+    }
+
+    fn with_kid(mut jwk: Jwk, kid: &str) -> Jwk {
+        jwk.common.key_id = Some(kid.to_string());
+        jwk
+    }
+
+    fn sign(key: &JwtKey, kid: Option<&str>) -> String {
+        let claims = Claims::create(Duration::from_hours(1))
+            .with_subject(ClientId::default().to_uri())
+            .with_jwt_id(uuid::Uuid::new_v4().to_string());
+        let kp = key.kp.as_str();
+        match (key.alg, kid) {
+            (JwsAlgorithm::P256, Some(kid)) => ES256KeyPair::from_pem(kp).unwrap().with_key_id(kid).sign(claims),
+            (JwsAlgorithm::P256, None) => ES256KeyPair::from_pem(kp).unwrap().sign(claims),
+            (JwsAlgorithm::P384, Some(kid)) => ES384KeyPair::from_pem(kp).unwrap().with_key_id(kid).sign(claims),
+            (JwsAlgorithm::P384, None) => ES384KeyPair::from_pem(kp).unwrap().sign(claims),
+            (JwsAlgorithm::Ed25519, Some(kid)) => Ed25519KeyPair::from_pem(kp).unwrap().with_key_id(kid).sign(claims),
+            (JwsAlgorithm::Ed25519, None) => Ed25519KeyPair::from_pem(kp).unwrap().sign(claims),
+        }
+        .unwrap()
+    }
+
+    fn verify(token: &str, jwks: &JwkSet) -> RustyJwtResult<JWTClaims<NoCustomClaims>> {
+        let client_id = ClientId::default();
+        let verify = Verify {
+            client_id: &client_id,
+            backend_nonce: None,
+            leeway: 5,
+            issuers: None,
+            audience: AudiencePolicy::Ignore,
+            claims_policy: ClaimsPolicy::default(),
+            limits: TokenLimits::default(),
+            validators: vec![],
+            critical_headers: vec![],
+        };
+        token.verify_jwt_with_jwks::<NoCustomClaims>(jwks, u64::MAX, verify)
+    }
+}
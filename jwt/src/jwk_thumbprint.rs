@@ -24,20 +24,12 @@ impl JwkThumbprint {
     pub fn generate(jwk: &Jwk, alg: HashAlgorithm) -> RustyJwtResult<Self> {
         let json = Self::compute_json(jwk);
         let json = serde_json::to_vec(&json)?;
-        let kid = match alg {
-            HashAlgorithm::SHA256 => {
-                let mut hasher = sha2::Sha256::new();
-                hasher.update(json);
-                let hash = &hasher.finalize()[..];
-                base64::prelude::BASE64_URL_SAFE_NO_PAD.encode(hash)
-            }
-            HashAlgorithm::SHA384 => {
-                let mut hasher = sha2::Sha384::new();
-                hasher.update(json);
-                let hash = &hasher.finalize()[..];
-                base64::prelude::BASE64_URL_SAFE_NO_PAD.encode(hash)
-            }
+        let hash = match alg {
+            HashAlgorithm::SHA256 => sha2::Sha256::digest(json).to_vec(),
+            HashAlgorithm::SHA384 => sha2::Sha384::digest(json).to_vec(),
+            HashAlgorithm::SHA512 => sha2::Sha512::digest(json).to_vec(),
         };
+        let kid = base64::prelude::BASE64_URL_SAFE_NO_PAD.encode(hash);
         Ok(Self { kid })
     }
 
@@ -67,6 +59,59 @@ impl JwkThumbprint {
     }
 }
 
+/// How the key identifier ('kid') of a client key is derived. It has to be the same when
+/// generating the DPoP proof, when building the access token 'cnf' claim and when verifying it.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum KidStrategy {
+    /// [JwkThumbprint] of the client JWK with the given hash algorithm, the default
+    Thumbprint(HashAlgorithm),
+    /// base64url encoded SHA-256 hash of the DER encoded certificate of the client key, as in the
+    /// 'x5t#S256' header. The certificate has to certify the client JWK
+    ///
+    /// Specified in [RFC 7515 Section 4.1.8][1]
+    ///
+    /// [1]: https://www.rfc-editor.org/rfc/rfc7515#section-4.1.8
+    X5tS256(Vec<u8>),
+    /// Opaque identifier supplied by the caller. Since it is not derived from the client JWK, the
+    /// access token 'cnf' claim then always embeds the JWK itself so that it stays bound to the key
+    Custom(String),
+}
+
+impl KidStrategy {
+    /// Derives the key identifier of [jwk], a key of [alg]. Fails when the certificate of a
+    /// [KidStrategy::X5tS256] does not certify [jwk]
+    pub fn derive(&self, alg: JwsAlgorithm, jwk: &Jwk) -> RustyJwtResult<JwkThumbprint> {
+        Ok(match self {
+            Self::Thumbprint(hash) => JwkThumbprint::generate(jwk, *hash)?,
+            Self::X5tS256(cert) => {
+                use x509_cert::der::Decode as _;
+
+                let parsed = x509_cert::Certificate::from_der(cert)
+                    .map_err(|_| RustyJwtError::InvalidX5c("a certificate is not DER encoded"))?;
+                let cert_pk = ParsedPublicKey::try_from_certificate(alg, &parsed)?;
+                if AnyPublicKey::from(&cert_pk) != AnyPublicKey::from((alg, jwk)) {
+                    return Err(RustyJwtError::InvalidX5c("certificate does not certify the client key"));
+                }
+                JwkThumbprint {
+                    kid: base64::prelude::BASE64_URL_SAFE_NO_PAD.encode(sha2::Sha256::digest(cert)),
+                }
+            }
+            Self::Custom(kid) => JwkThumbprint { kid: kid.clone() },
+        })
+    }
+
+    /// Whether the key identifier alone binds the client JWK i.e. it cannot be derived from another key
+    pub fn binds_key(&self) -> bool {
+        !matches!(self, Self::Custom(_))
+    }
+}
+
+impl From<HashAlgorithm> for KidStrategy {
+    fn from(hash: HashAlgorithm) -> Self {
+        Self::Thumbprint(hash)
+    }
+}
+
 #[cfg(test)]
 pub mod tests {
     use wasm_bindgen_test::*;
@@ -126,6 +171,10 @@ pub mod tests {
                     &thumbprint.kid,
                     "EcgQUf2ct-84eLYyH0o-leu6RJ46Lq_5jlCCEa5RlAPVcLXgHoh4Q0RnwFqRuk3y"
                 ),
+                HashAlgorithm::SHA512 => assert_eq!(
+                    &thumbprint.kid,
+                    "1Lc52f5GlYxMNFaQ5fXlBfU-Ho-mUVgMhTwVAagvWRrSHZTQiy4jdA2zV4K79AKXCs1yx8TqDKwULNc_7_Z0Ng"
+                ),
             }
         }
 
@@ -151,6 +200,10 @@ pub mod tests {
                     &thumbprint.kid,
                     "tdNAT4Jr8cRlkxmgtYcum6EAGLWl6AXsflQs5izMSCY9gsFTD-cd5j1_vmev5_2X"
                 ),
+                HashAlgorithm::SHA512 => assert_eq!(
+                    &thumbprint.kid,
+                    "jDQEEK3aJ9Zq0KvJl-6mHfYuE_HiodVmY9Y--_h-QwXgkVk8Uwkhv9WavunCz8qhpQ8XKXHdDeqa-alWPaeqjQ"
+                ),
             }
         }
 
@@ -176,6 +229,10 @@ pub mod tests {
                     &thumbprint.kid,
                     "SDannkEbVekJlQtvocnp8oF38WVF23gEXj3tDqQnVlzJdinp2vgT-W-wbBN_wksO"
                 ),
+                HashAlgorithm::SHA512 => assert_eq!(
+                    &thumbprint.kid,
+                    "gIaQpxTXPJVE6XVzZRDG3AURbItjoEnxEVIJdICqXqtAzIAJJHvQNYxxKPZ6fvu4aEx-p7CvMimYkOtX0u4rLg"
+                ),
             }
         }
 
@@ -199,6 +256,10 @@ pub mod tests {
                     &thumbprint.kid,
                     "Ow8bJ-FJVEMr6XcEDsio9IYfeq8OpvIgJnsE-7vQs2rdk_sWnp4gGjxMxAqcEjMy"
                 ),
+                HashAlgorithm::SHA512 => assert_eq!(
+                    &thumbprint.kid,
+                    "SiJgzsfOnllS44TLJ_qVl-oTGeh1eqtNDvnIiMxNhhD-CgRE1UwQVRlk_PIT9Y4xGqAWWm-o3goS79ery6vHmQ"
+                ),
             }
         }
     }
@@ -257,4 +318,55 @@ pub mod tests {
             })
         )
     }
+
+    mod kid_strategy {
+        use super::*;
+
+        #[apply(all_ciphersuites)]
+        #[test]
+        #[wasm_bindgen_test]
+        fn thumbprint_should_be_jwk_thumbprint(ciphersuite: Ciphersuite) {
+            let jwk = RustyJwk::rand_jwk(ciphersuite.key.alg);
+            assert_eq!(
+                KidStrategy::from(ciphersuite.hash)
+                    .derive(ciphersuite.key.alg, &jwk)
+                    .unwrap(),
+                JwkThumbprint::generate(&jwk, ciphersuite.hash).unwrap()
+            );
+        }
+
+        #[test]
+        #[wasm_bindgen_test]
+        fn x5t_s256_should_hash_certificate() {
+            let key = JwtKey::from((JwsAlgorithm::P256, Pem::from(LEAF_KP.to_string())));
+            let kid = KidStrategy::X5tS256(cert_der(LEAF))
+                .derive(JwsAlgorithm::P256, &key.to_jwk())
+                .unwrap();
+            assert_eq!(&kid.kid, "Tk7IefAWbPEjeqlU9ppQu3NQEB-_xPBHgmn2L8B1jDw");
+        }
+
+        #[apply(all_ciphersuites)]
+        #[test]
+        #[wasm_bindgen_test]
+        fn x5t_s256_should_fail_when_certificate_does_not_certify_key(ciphersuite: Ciphersuite) {
+            let (alg, jwk) = (ciphersuite.key.alg, ciphersuite.key.to_jwk());
+            let result = KidStrategy::X5tS256(cert_der(LEAF)).derive(alg, &jwk);
+            assert!(matches!(result.unwrap_err(), RustyJwtError::InvalidX5c(_)));
+
+            // not even a certificate
+            let result = KidStrategy::X5tS256(b"abc".to_vec()).derive(alg, &jwk);
+            assert!(matches!(result.unwrap_err(), RustyJwtError::InvalidX5c(_)));
+        }
+
+        #[apply(all_ciphersuites)]
+        #[test]
+        #[wasm_bindgen_test]
+        fn custom_should_be_verbatim(ciphersuite: Ciphersuite) {
+            let jwk = RustyJwk::rand_jwk(ciphersuite.key.alg);
+            let kid = KidStrategy::Custom("client-key-1".to_string())
+                .derive(ciphersuite.key.alg, &jwk)
+                .unwrap();
+            assert_eq!(&kid.kid, "client-key-1");
+        }
+    }
 }
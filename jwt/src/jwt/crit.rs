@@ -0,0 +1,177 @@
+//! 'crit' header parameter
+
+use std::collections::HashSet;
+
+use base64::{prelude::BASE64_URL_SAFE_NO_PAD, Engine};
+
+use crate::prelude::*;
+
+/// Header parameters defined by [RFC 7515][1] and [RFC 7518][2] which must not be listed in 'crit'
+///
+/// [1]: https://www.rfc-editor.org/rfc/rfc7515#section-4.1
+/// [2]: https://www.rfc-editor.org/rfc/rfc7518#section-4.1
+const REGISTERED_HEADERS: [&str; 19] = [
+    "alg", "jku", "jwk", "kid", "x5u", "x5c", "x5t", "x5t#S256", "typ", "cty", "crit", "epk", "apu", "apv", "iv",
+    "tag", "p2s", "p2c", "enc",
+];
+
+/// Handler of an extension header parameter a token lists in its 'crit' header. A token listing a
+/// header parameter without a handler is rejected.
+///
+/// Specified in [RFC 7515 Section 4.1.11][1]
+///
+/// [1]: https://www.rfc-editor.org/rfc/rfc7515#section-4.1.11
+pub trait CriticalHeader {
+    /// Name of the header parameter it understands
+    fn name(&self) -> &str;
+
+    /// Rejects the token with a domain error when the header parameter value is not acceptable
+    fn verify(&self, value: &serde_json::Value) -> RustyJwtResult<()>;
+}
+
+/// Verifies the 'crit' header of [token], when present, and runs the handler of every header
+/// parameter it lists
+pub(crate) fn verify_critical_headers(token: &str, handlers: &[&dyn CriticalHeader]) -> RustyJwtResult<()> {
+    let protected = token.split('.').next().unwrap_or_default();
+    let header = BASE64_URL_SAFE_NO_PAD
+        .decode(protected)
+        .map_err(|_| RustyJwtError::InvalidCriticalHeader("header is not base64url encoded"))?;
+    let serde_json::Value::Object(header) = serde_json::from_slice::<serde_json::Value>(&header)? else {
+        return Err(RustyJwtError::InvalidCriticalHeader("header is not a JSON object"));
+    };
+    let Some(crit) = header.get("crit") else {
+        return Ok(());
+    };
+    let crit = crit
+        .as_array()
+        .filter(|crit| !crit.is_empty())
+        .ok_or(RustyJwtError::InvalidCriticalHeader("it is not a non-empty array"))?;
+
+    let mut seen = HashSet::with_capacity(crit.len());
+    for name in crit {
+        let name = name.as_str().ok_or(RustyJwtError::InvalidCriticalHeader(
+            "it lists something else than a name",
+        ))?;
+        if !seen.insert(name) {
+            return Err(RustyJwtError::InvalidCriticalHeader("it lists a name twice"));
+        }
+        if REGISTERED_HEADERS.contains(&name) {
+            return Err(RustyJwtError::InvalidCriticalHeader("it lists a registered header"));
+        }
+        let value = header
+            .get(name)
+            .ok_or(RustyJwtError::InvalidCriticalHeader("it lists an absent header"))?;
+        let handler = handlers
+            .iter()
+            .find(|h| h.name() == name)
+            .ok_or_else(|| RustyJwtError::UnsupportedCriticalHeader(name.to_string()))?;
+        handler.verify(value)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use jwt_simple::prelude::*;
+    use serde_json::json;
+    use wasm_bindgen_test::*;
+
+    use crate::{
+        jwt::{signature::sign_raw, VerifyJwt},
+        test_utils::*,
+    };
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    /// Accepts a boolean 'wire' header
+    struct WireHeader;
+
+    impl CriticalHeader for WireHeader {
+        fn name(&self) -> &str {
+            "wire"
+        }
+
+        fn verify(&self, value: &serde_json::Value) -> RustyJwtResult<()> {
+            value
+                .as_bool()
+                .map(|_| ())
+                .ok_or(RustyJwtError::InvalidCriticalHeader("'wire' is not a boolean"))
+        }
+    }
+
+    /// Signs a token with [header] verbatim, except for its 'alg'
+    fn token(key: &JwtKey, mut header: serde_json::Value) -> String {
+        header["alg"] = key.alg.to_string().into();
+        let now = Clock::now_since_epoch().as_secs();
+        let claims = json!({"sub": ClientId::default().to_uri(), "iat": now, "exp": now + 3600});
+        let protected = BASE64_URL_SAFE_NO_PAD.encode(header.to_string());
+        let payload = BASE64_URL_SAFE_NO_PAD.encode(claims.to_string());
+        let signing_input = format!("{protected}.{payload}");
+        let signature = sign_raw(key.alg, &key.kp, signing_input.as_bytes()).unwrap();
+        format!("{signing_input}.{}", BASE64_URL_SAFE_NO_PAD.encode(signature))
+    }
+
+    fn verify(key: &JwtKey, token: &str, handlers: Vec<&dyn CriticalHeader>) -> RustyJwtResult<()> {
+        let client_id = ClientId::default();
+        let verify = Verify {
+            client_id: &client_id,
+            backend_nonce: None,
+            leeway: 5,
+            issuers: None,
+            audience: AudiencePolicy::Ignore,
+            claims_policy: ClaimsPolicy {
+                jti: ClaimRequirement::Optional,
+                nbf: ClaimRequirement::Optional,
+                ..Default::default()
+            },
+            limits: TokenLimits::default(),
+            validators: vec![],
+            critical_headers: handlers,
+        };
+        let pk = AnyPublicKey::from((key.alg, &key.pk));
+        token.verify_jwt::<NoCustomClaims>(&pk, u64::MAX, verify).map(|_| ())
+    }
+
+    #[apply(all_keys)]
+    #[wasm_bindgen_test]
+    fn should_accept_handled_critical_header(key: JwtKey) {
+        let token = token(&key, json!({"alg": "", "crit": ["wire"], "wire": true}));
+        assert!(verify(&key, &token, vec![&WireHeader]).is_ok());
+
+        let token = self::token(&key, json!({"alg": "", "crit": ["wire"], "wire": "yes"}));
+        let result = verify(&key, &token, vec![&WireHeader]);
+        assert!(matches!(result.unwrap_err(), RustyJwtError::InvalidCriticalHeader(_)));
+    }
+
+    #[apply(all_keys)]
+    #[wasm_bindgen_test]
+    fn should_reject_unsupported_critical_header(key: JwtKey) {
+        let token = token(&key, json!({"alg": "", "crit": ["wire"], "wire": true}));
+        let result = verify(&key, &token, vec![]);
+        assert!(matches!(result.unwrap_err(), RustyJwtError::UnsupportedCriticalHeader(h) if h == "wire"));
+    }
+
+    #[apply(all_keys)]
+    #[wasm_bindgen_test]
+    fn should_reject_malformed_crit(key: JwtKey) {
+        for header in [
+            json!({"alg": "", "crit": []}),
+            json!({"alg": "", "crit": ["wire", "wire"], "wire": true}),
+            json!({"alg": "", "crit": ["typ"], "typ": "JWT"}),
+            json!({"alg": "", "crit": ["wire"]}),
+        ] {
+            let token = token(&key, header);
+            let result = verify(&key, &token, vec![&WireHeader]);
+            assert!(matches!(result.unwrap_err(), RustyJwtError::InvalidCriticalHeader(_)));
+        }
+    }
+
+    #[apply(all_keys)]
+    #[wasm_bindgen_test]
+    fn should_ignore_absent_crit(key: JwtKey) {
+        let token = token(&key, json!({"alg": ""}));
+        assert!(verify(&key, &token, vec![]).is_ok());
+    }
+}
@@ -0,0 +1,233 @@
+use base64::{prelude::BASE64_URL_SAFE_NO_PAD, Engine};
+use jwt_simple::prelude::*;
+
+use crate::{
+    jwk::TryIntoJwk,
+    jwt::{
+        crit::verify_critical_headers,
+        signature::{sign_raw, verify_raw},
+    },
+    prelude::*,
+};
+
+/// Header parameter telling whether the payload is base64url encoded in the signing input
+///
+/// Specified in [RFC 7797 Section 3][1]
+///
+/// [1]: https://www.rfc-editor.org/rfc/rfc7797#section-3
+const B64: &str = "b64";
+
+/// Understands the 'b64' header when listed in 'crit'. Its value is checked by [RustyJwtTools::verify_detached_jws]
+struct B64Header;
+
+impl CriticalHeader for B64Header {
+    fn name(&self) -> &str {
+        B64
+    }
+
+    fn verify(&self, value: &serde_json::Value) -> RustyJwtResult<()> {
+        value
+            .as_bool()
+            .map(|_| ())
+            .ok_or(RustyJwtError::InvalidDetachedJws("'b64' header is not a boolean"))
+    }
+}
+
+impl RustyJwtTools {
+    /// Signs a payload as a JWS with unencoded payload and omits it from the JWS, so that large
+    /// payloads e.g. a CSR or a certificate do not have to be embedded and encoded. The payload has
+    /// to be conveyed alongside the returned JWS.
+    ///
+    /// Specified in [RFC 7797][1] and [RFC 7515 Appendix F][2]
+    ///
+    /// # Arguments
+    /// * `alg` - signature algorithm
+    /// * `header` - protected header. Its 'alg' is overwritten and 'b64' & 'crit' are added
+    /// * `payload` - any data to sign
+    /// * `kp` - PEM format key pair of the signer
+    /// * `with_jwk` - whether to add the signer public key in the 'jwk' header
+    ///
+    /// [1]: https://www.rfc-editor.org/rfc/rfc7797
+    /// [2]: https://www.rfc-editor.org/rfc/rfc7515#appendix-F
+    pub fn generate_detached_jws(
+        alg: JwsAlgorithm,
+        header: JWTHeader,
+        payload: &[u8],
+        kp: &Pem,
+        with_jwk: bool,
+    ) -> RustyJwtResult<String> {
+        let header = JWTHeader {
+            algorithm: alg.to_string(),
+            ..header
+        };
+        let serde_json::Value::Object(mut header) = serde_json::to_value(header)? else {
+            return Err(RustyJwtError::ImplementationError);
+        };
+        if with_jwk {
+            let jwk = match alg {
+                JwsAlgorithm::P256 => ES256KeyPair::from_pem(kp)?.public_key().try_into_jwk()?,
+                JwsAlgorithm::P384 => ES384KeyPair::from_pem(kp)?.public_key().try_into_jwk()?,
+                JwsAlgorithm::Ed25519 => Ed25519KeyPair::from_pem(kp)?.public_key().try_into_jwk()?,
+            };
+            header.insert("jwk".to_string(), serde_json::to_value(jwk)?);
+        }
+        header.insert(B64.to_string(), false.into());
+        let mut crit = match header.remove("crit") {
+            Some(serde_json::Value::Array(crit)) => crit,
+            _ => vec![],
+        };
+        if !crit.iter().any(|c| c == B64) {
+            crit.push(B64.into());
+        }
+        header.insert("crit".to_string(), crit.into());
+
+        let protected = BASE64_URL_SAFE_NO_PAD.encode(serde_json::to_vec(&header)?);
+        let signature = sign_raw(alg, kp, &signing_input(&protected, payload, false))?;
+        Ok(format!("{protected}..{}", BASE64_URL_SAFE_NO_PAD.encode(signature)))
+    }
+
+    /// Verifies a JWS whose payload is detached. Accepts both unencoded ('b64' false) payloads as
+    /// produced by [RustyJwtTools::generate_detached_jws] and regular base64url encoded ones.
+    ///
+    /// # Arguments
+    /// * `jws` - JWS Compact Serialization format with an empty payload part e.g. `header..signature`
+    /// * `payload` - the detached payload, as signed
+    /// * `alg` - expected signature algorithm
+    /// * `pk` - PEM format public key of the signer
+    pub fn verify_detached_jws(jws: &str, payload: &[u8], alg: JwsAlgorithm, pk: &Pem) -> RustyJwtResult<()> {
+        let parts: [&str; 3] = jws
+            .split('.')
+            .collect::<Vec<_>>()
+            .try_into()
+            .map_err(|_| RustyJwtError::InvalidDetachedJws("not in JWS Compact Serialization format"))?;
+        let [protected, embedded, signature] = parts;
+        if !embedded.is_empty() {
+            return Err(RustyJwtError::InvalidDetachedJws("its payload is not detached"));
+        }
+        let header = serde_json::from_slice::<serde_json::Value>(&BASE64_URL_SAFE_NO_PAD.decode(protected)?)?;
+        if header.get("alg").and_then(serde_json::Value::as_str) != Some(alg.to_string().as_str()) {
+            return Err(RustyJwtError::UnsupportedAlgorithm);
+        }
+        let encoded = match header.get(B64) {
+            None => true,
+            Some(serde_json::Value::Bool(b64)) => *b64,
+            Some(_) => return Err(RustyJwtError::InvalidDetachedJws("'b64' header is not a boolean")),
+        };
+        let critical = header
+            .get("crit")
+            .and_then(serde_json::Value::as_array)
+            .map(|crit| crit.iter().any(|c| c == B64))
+            .unwrap_or_default();
+        if !encoded && !critical {
+            return Err(RustyJwtError::InvalidDetachedJws(
+                "'b64' header is not listed in 'crit'",
+            ));
+        }
+        verify_critical_headers(jws, &[&B64Header])?;
+        let signature = BASE64_URL_SAFE_NO_PAD.decode(signature)?;
+        verify_raw(alg, pk, &signing_input(protected, payload, encoded), &signature)
+    }
+}
+
+/// 'ASCII(BASE64URL(UTF8(JWS Protected Header)) || '.' || JWS Payload)', the JWS Payload being
+/// base64url encoded unless 'b64' is false
+fn signing_input(protected: &str, payload: &[u8], encoded: bool) -> Vec<u8> {
+    let payload = if encoded {
+        BASE64_URL_SAFE_NO_PAD.encode(payload).into_bytes()
+    } else {
+        payload.to_vec()
+    };
+    [protected.as_bytes(), b".", &payload].concat()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use wasm_bindgen_test::*;
+
+    use crate::test_utils::*;
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    const PAYLOAD: &[u8] =
+        b"-----BEGIN CERTIFICATE REQUEST-----\nMIIBAzCBtgIBADA...\n-----END CERTIFICATE REQUEST-----";
+
+    #[apply(all_keys)]
+    #[wasm_bindgen_test]
+    fn should_roundtrip(key: JwtKey) {
+        let jws = RustyJwtTools::generate_detached_jws(key.alg, JWTHeader::default(), PAYLOAD, &key.kp, true).unwrap();
+        let [protected, embedded, _]: [&str; 3] = jws.split('.').collect::<Vec<_>>().try_into().unwrap();
+        assert!(embedded.is_empty());
+        let header =
+            serde_json::from_slice::<serde_json::Value>(&BASE64_URL_SAFE_NO_PAD.decode(protected).unwrap()).unwrap();
+        assert_eq!(header["b64"], serde_json::json!(false));
+        assert_eq!(header["crit"], serde_json::json!(["b64"]));
+        assert!(header.get("jwk").is_some());
+
+        assert!(RustyJwtTools::verify_detached_jws(&jws, PAYLOAD, key.alg, &key.pk).is_ok());
+    }
+
+    #[apply(all_keys)]
+    #[wasm_bindgen_test]
+    fn should_verify_encoded_detached_payload(key: JwtKey) {
+        // a regular JWT whose payload gets detached afterwards
+        let claims = Claims::create(Duration::from_hours(1));
+        let jwt =
+            RustyJwtTools::generate_jwt::<NoCustomClaims>(key.alg, JWTHeader::default(), Some(claims), &key.kp, false)
+                .unwrap();
+        let [protected, payload, signature]: [&str; 3] = jwt.split('.').collect::<Vec<_>>().try_into().unwrap();
+        let jws = format!("{protected}..{signature}");
+        let payload = BASE64_URL_SAFE_NO_PAD.decode(payload).unwrap();
+        assert!(RustyJwtTools::verify_detached_jws(&jws, &payload, key.alg, &key.pk).is_ok());
+    }
+
+    #[apply(all_keys)]
+    #[wasm_bindgen_test]
+    fn should_fail_when_payload_differs(key: JwtKey) {
+        let jws = RustyJwtTools::generate_detached_jws(key.alg, JWTHeader::default(), PAYLOAD, &key.kp, false).unwrap();
+        let result = RustyJwtTools::verify_detached_jws(&jws, b"another payload", key.alg, &key.pk);
+        assert!(matches!(result.unwrap_err(), RustyJwtError::InvalidSignature));
+    }
+
+    #[apply(all_keys)]
+    #[wasm_bindgen_test]
+    fn should_fail_when_signed_by_another_key(key: JwtKey) {
+        let jws = RustyJwtTools::generate_detached_jws(key.alg, JWTHeader::default(), PAYLOAD, &key.kp, false).unwrap();
+        let other = key.create_another();
+        let result = RustyJwtTools::verify_detached_jws(&jws, PAYLOAD, key.alg, &other.pk);
+        assert!(matches!(result.unwrap_err(), RustyJwtError::InvalidSignature));
+    }
+
+    #[apply(all_keys)]
+    #[wasm_bindgen_test]
+    fn should_fail_when_b64_not_critical(key: JwtKey) {
+        let protected =
+            BASE64_URL_SAFE_NO_PAD.encode(serde_json::json!({"alg": key.alg.to_string(), "b64": false}).to_string());
+        let signature = sign_raw(key.alg, &key.kp, &signing_input(&protected, PAYLOAD, false)).unwrap();
+        let jws = format!("{protected}..{}", BASE64_URL_SAFE_NO_PAD.encode(signature));
+        let result = RustyJwtTools::verify_detached_jws(&jws, PAYLOAD, key.alg, &key.pk);
+        assert!(matches!(result.unwrap_err(), RustyJwtError::InvalidDetachedJws(_)));
+    }
+
+    #[apply(all_keys)]
+    #[wasm_bindgen_test]
+    fn should_fail_when_critical_header_unsupported(key: JwtKey) {
+        let header =
+            serde_json::json!({"alg": key.alg.to_string(), "b64": false, "crit": ["b64", "wire"], "wire": true});
+        let protected = BASE64_URL_SAFE_NO_PAD.encode(header.to_string());
+        let signature = sign_raw(key.alg, &key.kp, &signing_input(&protected, PAYLOAD, false)).unwrap();
+        let jws = format!("{protected}..{}", BASE64_URL_SAFE_NO_PAD.encode(signature));
+        let result = RustyJwtTools::verify_detached_jws(&jws, PAYLOAD, key.alg, &key.pk);
+        assert!(matches!(result.unwrap_err(), RustyJwtError::UnsupportedCriticalHeader(h) if h == "wire"));
+    }
+
+    #[apply(all_keys)]
+    #[wasm_bindgen_test]
+    fn should_fail_when_payload_embedded(key: JwtKey) {
+        let jws = RustyJwtTools::generate_detached_jws(key.alg, JWTHeader::default(), PAYLOAD, &key.kp, false).unwrap();
+        let jws = jws.replace("..", ".abcd.");
+        let result = RustyJwtTools::verify_detached_jws(&jws, PAYLOAD, key.alg, &key.pk);
+        assert!(matches!(result.unwrap_err(), RustyJwtError::InvalidDetachedJws(_)));
+    }
+}
@@ -0,0 +1,248 @@
+use jwt_simple::prelude::*;
+
+use crate::{
+    jwt::{crit::verify_critical_headers, verify::jwt_error_mapping},
+    prelude::*,
+};
+
+/// A JWS in JSON General Serialization, the only serialization allowing many signatures over the
+/// same payload e.g. a payload co-signed by the ACME account key and the MLS client key
+///
+/// Specified in [RFC 7515 Section 7.2.1][1]
+///
+/// [1]: https://www.rfc-editor.org/rfc/rfc7515#section-7.2.1
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct GeneralJws {
+    /// base64url encoded payload, common to every signature
+    pub payload: String,
+    /// one entry per signer
+    pub signatures: Vec<GeneralJwsSignature>,
+}
+
+/// A signature of a [GeneralJws]
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct GeneralJwsSignature {
+    /// base64url encoded JOSE header, integrity protected by the signature
+    pub protected: String,
+    /// JOSE header not integrity protected, ignored when verifying
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub header: Option<serde_json::Value>,
+    /// base64url encoded signature
+    pub signature: String,
+}
+
+/// One of the signers of a [GeneralJws]
+#[derive(Debug, Clone)]
+pub struct JwsSigner<'a> {
+    /// signature algorithm
+    pub alg: JwsAlgorithm,
+    /// protected header of this signature. Its 'alg' is overwritten with [Self::alg]
+    pub header: JWTHeader,
+    /// PEM format key pair of the signer
+    pub kp: &'a Pem,
+    /// whether to add the signer public key in the 'jwk' header
+    pub with_jwk: bool,
+}
+
+impl GeneralJws {
+    /// Each signature as a JWS Compact Serialization e.g. to verify it with a JWT library
+    pub fn to_compact(&self) -> Vec<String> {
+        self.signatures
+            .iter()
+            .map(|s| format!("{}.{}.{}", s.protected, self.payload, s.signature))
+            .collect()
+    }
+}
+
+impl RustyJwtTools {
+    /// Same as [RustyJwtTools::generate_jwt] but has the claims signed by every one of [signers]
+    /// and returns them in JSON General Serialization
+    ///
+    /// # Arguments
+    /// * `claims` - claims common to every signature
+    /// * `signers` - at least one signer, signatures are in the same order
+    pub fn generate_general_jws<T>(claims: Option<JWTClaims<T>>, signers: &[JwsSigner]) -> RustyJwtResult<GeneralJws>
+    where
+        T: Serialize + Clone,
+        for<'de> T: Deserialize<'de>,
+    {
+        if signers.is_empty() {
+            return Err(RustyJwtError::InvalidGeneralJws("it has no signature"));
+        }
+        let mut payload = None;
+        let signatures = signers
+            .iter()
+            .map(|signer| {
+                let header = JWTHeader {
+                    algorithm: signer.alg.to_string(),
+                    ..signer.header.clone()
+                };
+                let jwt = Self::generate_jwt(signer.alg, header, claims.clone(), signer.kp, signer.with_jwk)?;
+                let [protected, p, signature]: [&str; 3] = jwt
+                    .split('.')
+                    .collect::<Vec<_>>()
+                    .try_into()
+                    .map_err(|_| RustyJwtError::ImplementationError)?;
+                // claims serialization is deterministic so every signer should sign the same payload
+                if payload.get_or_insert_with(|| p.to_string()).as_str() != p {
+                    return Err(RustyJwtError::ImplementationError);
+                }
+                Ok(GeneralJwsSignature {
+                    protected: protected.to_string(),
+                    header: None,
+                    signature: signature.to_string(),
+                })
+            })
+            .collect::<RustyJwtResult<Vec<_>>>()?;
+        Ok(GeneralJws {
+            payload: payload.unwrap_or_default(),
+            signatures,
+        })
+    }
+
+    /// Verifies that a [GeneralJws] has been signed by every one of [pks], and only them, and
+    /// returns its claims. Time related claims are verified when present.
+    ///
+    /// # Arguments
+    /// * `jws` - JWS in JSON General Serialization
+    /// * `pks` - public keys of the expected signers, in the same order as the signatures
+    pub fn verify_general_jws<T>(jws: &GeneralJws, pks: &[AnyPublicKey]) -> RustyJwtResult<JWTClaims<T>>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        if jws.signatures.len() != pks.len() {
+            return Err(RustyJwtError::InvalidGeneralJws(
+                "it does not have one signature per signer",
+            ));
+        }
+        let mut verified = jws
+            .to_compact()
+            .iter()
+            .zip(pks)
+            .map(|(compact, pk)| {
+                verify_critical_headers(compact, &[])?;
+                pk.verify_token::<T>(compact, None).map_err(jwt_error_mapping)
+            })
+            .collect::<RustyJwtResult<Vec<_>>>()?;
+        verified
+            .pop()
+            .ok_or(RustyJwtError::InvalidGeneralJws("it has no signature"))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use base64::{prelude::BASE64_URL_SAFE_NO_PAD, Engine};
+    use serde_json::json;
+    use wasm_bindgen_test::*;
+
+    use crate::{jwt::signature::sign_raw, test_utils::*};
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[apply(all_keys)]
+    #[wasm_bindgen_test]
+    fn should_roundtrip_cosigned(key: JwtKey) {
+        let other = JwtKey::new_key(key.reverse_algorithms()[0]);
+        let jws = cosign(&key, &other);
+        assert_eq!(jws.signatures.len(), 2);
+
+        // survives JSON serialization
+        let jws = serde_json::from_value::<GeneralJws>(serde_json::to_value(&jws).unwrap()).unwrap();
+        let pks = [
+            AnyPublicKey::from((key.alg, &key.pk)),
+            AnyPublicKey::from((other.alg, &other.pk)),
+        ];
+        let claims = RustyJwtTools::verify_general_jws::<serde_json::Value>(&jws, &pks).unwrap();
+        assert_eq!(claims.custom, json!({"a": "b"}));
+    }
+
+    #[apply(all_keys)]
+    #[wasm_bindgen_test]
+    fn should_fail_when_signer_missing(key: JwtKey) {
+        let other = key.create_another();
+        let jws = cosign(&key, &other);
+        let pks = [AnyPublicKey::from((key.alg, &key.pk))];
+        let result = RustyJwtTools::verify_general_jws::<serde_json::Value>(&jws, &pks);
+        assert!(matches!(result.unwrap_err(), RustyJwtError::InvalidGeneralJws(_)));
+    }
+
+    #[apply(all_keys)]
+    #[wasm_bindgen_test]
+    fn should_fail_when_signed_by_another_key(key: JwtKey) {
+        let other = key.create_another();
+        let jws = cosign(&key, &other);
+        let pks = [
+            AnyPublicKey::from((key.alg, &key.pk)),
+            AnyPublicKey::from((key.alg, &key.create_another().pk)),
+        ];
+        assert!(RustyJwtTools::verify_general_jws::<serde_json::Value>(&jws, &pks).is_err());
+    }
+
+    #[apply(all_keys)]
+    #[wasm_bindgen_test]
+    fn should_fail_when_payload_tampered(key: JwtKey) {
+        let other = key.create_another();
+        let mut jws = cosign(&key, &other);
+        let claims = JWTClaims {
+            custom: json!({"a": "c"}),
+            ..Claims::with_custom_claims(json!({}), Duration::from_hours(1))
+        };
+        jws.payload = RustyJwtTools::generate_jwt(key.alg, JWTHeader::default(), Some(claims), &key.kp, false)
+            .unwrap()
+            .split('.')
+            .nth(1)
+            .unwrap()
+            .to_string();
+        let pks = [
+            AnyPublicKey::from((key.alg, &key.pk)),
+            AnyPublicKey::from((other.alg, &other.pk)),
+        ];
+        assert!(RustyJwtTools::verify_general_jws::<serde_json::Value>(&jws, &pks).is_err());
+    }
+
+    #[apply(all_keys)]
+    #[wasm_bindgen_test]
+    fn should_fail_when_critical_header_unsupported(key: JwtKey) {
+        let other = key.create_another();
+        let mut jws = cosign(&key, &other);
+        let header = json!({"alg": other.alg.to_string(), "crit": ["wire"], "wire": true});
+        let protected = BASE64_URL_SAFE_NO_PAD.encode(header.to_string());
+        let signature = sign_raw(other.alg, &other.kp, format!("{protected}.{}", jws.payload).as_bytes()).unwrap();
+        jws.signatures[1] = GeneralJwsSignature {
+            protected,
+            header: None,
+            signature: BASE64_URL_SAFE_NO_PAD.encode(signature),
+        };
+        let pks = [
+            AnyPublicKey::from((key.alg, &key.pk)),
+            AnyPublicKey::from((other.alg, &other.pk)),
+        ];
+        let result = RustyJwtTools::verify_general_jws::<serde_json::Value>(&jws, &pks);
+        assert!(matches!(result.unwrap_err(), RustyJwtError::UnsupportedCriticalHeader(h) if h == "wire"));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_fail_without_signer() {
+        let claims = Claims::with_custom_claims(json!({}), Duration::from_hours(1));
+        let result = RustyJwtTools::generate_general_jws(Some(claims), &[]);
+        assert!(matches!(result.unwrap_err(), RustyJwtError::InvalidGeneralJws(_)));
+    }
+
+    fn cosign(a: &JwtKey, b: &JwtKey) -> GeneralJws {
+        let claims = Claims::with_custom_claims(json!({"a": "b"}), Duration::from_hours(1));
+        RustyJwtTools::generate_general_jws(Some(claims), &[signer(a), signer(b)]).unwrap()
+    }
+
+    fn signer(key: &JwtKey) -> JwsSigner {
+        JwsSigner {
+            alg: key.alg,
+            header: JWTHeader::default(),
+            kp: &key.kp,
+            with_jwk: true,
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use base64::{prelude::BASE64_URL_SAFE_NO_PAD, Engine};
+use jwt_simple::prelude::*;
+
+use crate::prelude::*;
+
+/// JOSE header of a token, decoded without verifying the token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JwtHeader {
+    /// signature algorithm, as is since it might not be supported
+    #[serde(rename = "alg")]
+    pub alg: String,
+    /// media type of the token e.g. 'dpop+jwt'
+    #[serde(rename = "typ", skip_serializing_if = "Option::is_none")]
+    pub typ: Option<String>,
+    /// identifier of the signing key
+    #[serde(rename = "kid", skip_serializing_if = "Option::is_none")]
+    pub kid: Option<String>,
+    /// signing public key
+    #[serde(rename = "jwk", skip_serializing_if = "Option::is_none")]
+    pub jwk: Option<Jwk>,
+    /// base64 encoded DER certificate chain of the signing key, leaf first
+    #[serde(rename = "x5c", skip_serializing_if = "Option::is_none")]
+    pub x5c: Option<Vec<String>>,
+    /// any other header parameter
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl JwtHeader {
+    /// Signature algorithm, when supported
+    pub fn algorithm(&self) -> RustyJwtResult<JwsAlgorithm> {
+        JwsAlgorithm::try_from(self.alg.as_str())
+    }
+}
+
+impl RustyJwtTools {
+    /// Decodes the header of a token WITHOUT verifying it e.g. to pick the key to verify it with.
+    /// Nothing in it can be trusted until the token has been verified.
+    ///
+    /// # Arguments
+    /// * `token` - JWS Compact Serialization format
+    pub fn peek_header(token: &str) -> RustyJwtResult<JwtHeader> {
+        let protected = token
+            .split('.')
+            .next()
+            .filter(|p| !p.is_empty())
+            .ok_or(RustyJwtError::InvalidToken("it has no header".to_string()))?;
+        let header = BASE64_URL_SAFE_NO_PAD
+            .decode(protected)
+            .map_err(|_| RustyJwtError::InvalidToken("its header is not base64url encoded".to_string()))?;
+        Ok(serde_json::from_slice(&header)?)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use serde_json::json;
+    use wasm_bindgen_test::*;
+
+    use crate::test_utils::*;
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[apply(all_keys)]
+    #[wasm_bindgen_test]
+    fn should_peek_dpop_header(key: JwtKey) {
+        let token = DpopBuilder::from(key.clone()).build();
+        let header = RustyJwtTools::peek_header(&token).unwrap();
+        assert_eq!(header.alg, key.alg.to_string());
+        assert_eq!(header.algorithm().unwrap(), key.alg);
+        assert_eq!(header.typ.as_deref(), Some(Dpop::TYP));
+        assert!(header.jwk.is_some());
+        assert!(header.kid.is_none());
+        assert!(header.x5c.is_none());
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_keep_extra_params() {
+        let header = json!({"alg": "RS256", "kid": "a", "x5c": ["b"], "wire": {"c": 1}});
+        let token = format!("{}.e30.", BASE64_URL_SAFE_NO_PAD.encode(header.to_string()));
+        let header = RustyJwtTools::peek_header(&token).unwrap();
+        assert_eq!(header.kid.as_deref(), Some("a"));
+        assert_eq!(header.x5c, Some(vec!["b".to_string()]));
+        assert_eq!(header.extra.get("wire"), Some(&json!({"c": 1})));
+        // peeking does not require the algorithm to be supported
+        assert!(matches!(
+            header.algorithm().unwrap_err(),
+            RustyJwtError::UnsupportedAlgorithm
+        ));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_fail_when_not_a_token() {
+        assert!(RustyJwtTools::peek_header("").is_err());
+        assert!(RustyJwtTools::peek_header("not-base64!.e30.").is_err());
+        let token = format!("{}.e30.", BASE64_URL_SAFE_NO_PAD.encode("[]"));
+        assert!(RustyJwtTools::peek_header(&token).is_err());
+    }
+}
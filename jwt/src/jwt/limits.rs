@@ -0,0 +1,133 @@
+use base64::Engine;
+
+use crate::prelude::*;
+
+/// Bounds a token has to fit in before it gets parsed, so that a hostile client cannot make us
+/// allocate arbitrarily large or deeply nested JSON documents.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct TokenLimits {
+    /// Maximum length in bytes of the token in JWS Compact Serialization format
+    pub max_length: usize,
+    /// Maximum nesting depth of the JSON header and claims
+    pub max_depth: usize,
+}
+
+impl TokenLimits {
+    /// Default maximum length in bytes. An access token embeds a DPoP proof so it has to fit both.
+    pub const DEFAULT_MAX_LENGTH: usize = 16 * 1024;
+    /// Default maximum JSON nesting depth
+    pub const DEFAULT_MAX_DEPTH: usize = 16;
+
+    /// Verifies the token fits in those limits.
+    /// Malformed tokens are left to the parser which will reject them with a more accurate error.
+    pub fn verify(&self, token: &str) -> RustyJwtResult<()> {
+        if token.len() > self.max_length {
+            return Err(RustyJwtError::TokenTooLong(self.max_length));
+        }
+        // only the header and the claims are JSON, the signature is left untouched
+        for part in token.split('.').take(2) {
+            let Ok(json) = base64::prelude::BASE64_URL_SAFE_NO_PAD.decode(part) else {
+                continue;
+            };
+            if json_depth(&json) > self.max_depth {
+                return Err(RustyJwtError::TokenTooDeep(self.max_depth));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for TokenLimits {
+    fn default() -> Self {
+        Self {
+            max_length: Self::DEFAULT_MAX_LENGTH,
+            max_depth: Self::DEFAULT_MAX_DEPTH,
+        }
+    }
+}
+
+/// Computes the maximum nesting depth of a JSON document without parsing it
+fn json_depth(json: &[u8]) -> usize {
+    let (mut depth, mut max_depth) = (0usize, 0usize);
+    let (mut in_string, mut escaped) = (false, false);
+    for &b in json {
+        match b {
+            _ if escaped => escaped = false,
+            b'\\' if in_string => escaped = true,
+            b'"' => in_string = !in_string,
+            b'{' | b'[' if !in_string => {
+                depth += 1;
+                max_depth = max_depth.max(depth);
+            }
+            b'}' | b']' if !in_string => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    max_depth
+}
+
+#[cfg(test)]
+pub mod tests {
+    use wasm_bindgen_test::*;
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    fn token(header: &str, claims: &str) -> String {
+        let header = base64::prelude::BASE64_URL_SAFE_NO_PAD.encode(header);
+        let claims = base64::prelude::BASE64_URL_SAFE_NO_PAD.encode(claims);
+        format!("{header}.{claims}.c2lnbmF0dXJl")
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_reject_too_long_token() {
+        let limits = TokenLimits {
+            max_length: 64,
+            ..Default::default()
+        };
+        let short = token(r#"{"alg":"ES256"}"#, r#"{"a":1}"#);
+        assert!(limits.verify(&short).is_ok());
+
+        let long = token(r#"{"alg":"ES256"}"#, &format!(r#"{{"a":"{}"}}"#, "a".repeat(64)));
+        assert!(matches!(
+            limits.verify(&long).unwrap_err(),
+            RustyJwtError::TokenTooLong(64)
+        ));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_reject_too_deep_token() {
+        let limits = TokenLimits {
+            max_depth: 3,
+            ..Default::default()
+        };
+        let shallow = token(r#"{"alg":"ES256"}"#, r#"{"a":{"b":[1]}}"#);
+        assert!(limits.verify(&shallow).is_ok());
+
+        let deep = token(r#"{"alg":"ES256"}"#, r#"{"a":{"b":[{"c":1}]}}"#);
+        assert!(matches!(
+            limits.verify(&deep).unwrap_err(),
+            RustyJwtError::TokenTooDeep(3)
+        ));
+
+        let deep_header = token(r#"{"jwk":{"a":[[1]]}}"#, r#"{"a":1}"#);
+        assert!(matches!(
+            limits.verify(&deep_header).unwrap_err(),
+            RustyJwtError::TokenTooDeep(3)
+        ));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_ignore_brackets_in_strings() {
+        let limits = TokenLimits {
+            max_depth: 1,
+            ..Default::default()
+        };
+        let token = token(r#"{"alg":"ES256"}"#, r#"{"a":"{[{[\"{"}"#);
+        assert!(limits.verify(&token).is_ok());
+    }
+}
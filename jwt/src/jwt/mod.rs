@@ -1,9 +1,23 @@
 //! Generic crate for everything related to Jwt without any adherence to Dpop
 
-pub use verify::{Verify, VerifyJwt, VerifyJwtHeader};
+pub use crit::CriticalHeader;
+pub use general::{GeneralJws, GeneralJwsSignature, JwsSigner};
+pub use header::JwtHeader;
+pub use limits::TokenLimits;
+pub use report::{CheckReport, VerificationReport};
+pub use verify::{AudiencePolicy, ClaimRequirement, ClaimValidator, ClaimsPolicy, Verify, VerifyJwt, VerifyJwtHeader};
 
+mod crit;
+mod detached;
+mod general;
 pub(crate) mod generate;
+mod header;
+mod limits;
+pub(crate) mod report;
+pub(crate) mod signature;
 pub mod verify;
+#[cfg(feature = "x5c")]
+mod x5c;
 
 /// Generates a new jti
 pub fn new_jti() -> String {
@@ -0,0 +1,169 @@
+use base64::Engine;
+use jwt_simple::prelude::*;
+use serde::de::DeserializeOwned;
+
+use crate::prelude::*;
+
+/// Name of the access token in a [CheckReport]
+pub(crate) const ACCESS_TOKEN: &str = "access";
+/// Name of the DPoP proof in a [CheckReport]
+pub(crate) const DPOP_PROOF: &str = "dpop";
+
+/// Outcome of a single check performed while verifying a token
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct CheckReport {
+    /// Token the check applies to e.g. `access` or `dpop`
+    pub token: &'static str,
+    /// What got checked, usually a header field or a claim
+    pub check: &'static str,
+    /// Expected value as JSON, when relevant
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected: Option<String>,
+    /// Value found in the token as JSON, when relevant
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actual: Option<String>,
+    /// Why the check failed. `None` when it passed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl CheckReport {
+    /// Whether the check passed
+    pub fn passed(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Every check performed while verifying a token, in order, for debugging enrollment failures.
+///
+/// Unlike regular verification, it does not stop at the first failing check. When the signature
+/// is invalid, the remaining claims are still compared, without being trusted, to spot all the
+/// discrepancies at once. Verification only stops when the token cannot be decoded any further.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct VerificationReport {
+    /// Checks performed
+    pub checks: Vec<CheckReport>,
+    #[serde(skip)]
+    fail_fast: bool,
+}
+
+impl VerificationReport {
+    /// For regular verification: nothing gets recorded and the first error is returned
+    pub(crate) fn fail_fast() -> Self {
+        Self {
+            checks: vec![],
+            fail_fast: true,
+        }
+    }
+
+    /// Whether all the checks passed
+    pub fn is_valid(&self) -> bool {
+        self.checks.iter().all(CheckReport::passed)
+    }
+
+    /// Checks which failed
+    pub fn failures(&self) -> impl Iterator<Item = &CheckReport> {
+        self.checks.iter().filter(|c| !c.passed())
+    }
+
+    /// Records the outcome of a check. Expected and actual values are only computed when recording.
+    /// In fail-fast mode, the error is returned right away.
+    pub(crate) fn check(
+        &mut self,
+        token: &'static str,
+        check: &'static str,
+        result: RustyJwtResult<()>,
+        values: impl FnOnce() -> (Option<String>, Option<String>),
+    ) -> RustyJwtResult<()> {
+        if self.fail_fast {
+            return result;
+        }
+        let (expected, actual) = values();
+        self.checks.push(CheckReport {
+            token,
+            check,
+            expected,
+            actual,
+            error: result.err().map(|e| e.to_string()),
+        });
+        Ok(())
+    }
+
+    /// Records the outcome of the signature and registered claims verification. When it failed and
+    /// checks are recorded, falls back on the unverified claims so that the remaining ones can still be checked.
+    pub(crate) fn signature<T>(
+        &mut self,
+        token: &'static str,
+        jwt: &str,
+        verified: RustyJwtResult<JWTClaims<T>>,
+    ) -> RustyJwtResult<JWTClaims<T>>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        match verified {
+            Ok(claims) => {
+                self.check(token, "signature & registered claims", Ok(()), no_values)?;
+                Ok(claims)
+            }
+            Err(e) => {
+                self.check(token, "signature & registered claims", Err(e), no_values)?;
+                self.require(token, "claims", decode_unverified_claims(jwt))
+            }
+        }
+    }
+
+    /// Records the outcome of a check verification cannot proceed without. Its error is always returned.
+    pub(crate) fn require<T>(
+        &mut self,
+        token: &'static str,
+        check: &'static str,
+        result: RustyJwtResult<T>,
+    ) -> RustyJwtResult<T> {
+        if !self.fail_fast {
+            self.checks.push(CheckReport {
+                token,
+                check,
+                expected: None,
+                actual: None,
+                error: result.as_ref().err().map(|e| e.to_string()),
+            });
+        }
+        result
+    }
+}
+
+/// Builds the expected and actual values of a [CheckReport]
+pub(crate) fn values<E, A>(expected: &E, actual: &A) -> (Option<String>, Option<String>)
+where
+    E: Serialize + ?Sized,
+    A: Serialize + ?Sized,
+{
+    (serde_json::to_string(expected).ok(), serde_json::to_string(actual).ok())
+}
+
+/// No value worth reporting
+pub(crate) fn no_values() -> (Option<String>, Option<String>) {
+    (None, None)
+}
+
+/// Fails with the given error when the condition does not hold
+pub(crate) fn ensure(condition: bool, error: RustyJwtError) -> RustyJwtResult<()> {
+    if condition {
+        Ok(())
+    } else {
+        Err(error)
+    }
+}
+
+/// Decodes the claims of a token in JWS Compact Serialization format WITHOUT verifying its signature
+pub(crate) fn decode_unverified_claims<T>(token: &str) -> RustyJwtResult<JWTClaims<T>>
+where
+    T: Serialize + DeserializeOwned,
+{
+    let claims = token
+        .split('.')
+        .nth(1)
+        .ok_or_else(|| RustyJwtError::InvalidToken("not in JWS Compact Serialization format".to_string()))?;
+    let claims = base64::prelude::BASE64_URL_SAFE_NO_PAD.decode(claims)?;
+    Ok(serde_json::from_slice(&claims)?)
+}
@@ -0,0 +1,59 @@
+//! Raw signatures over arbitrary bytes, for the formats whose signing input is not the one of a
+//! JWS in Compact Serialization
+
+use jwt_simple::prelude::*;
+
+use crate::prelude::*;
+
+/// Signs [data] with [kp]. ECDSA signatures are the fixed size concatenation of 'r' and 's' as
+/// expected by JWS and COSE.
+pub(crate) fn sign_raw(alg: JwsAlgorithm, kp: &Pem, data: &[u8]) -> RustyJwtResult<Vec<u8>> {
+    use p256::ecdsa::signature::Signer as _;
+    let invalid_kp = |_| RustyJwtError::InvalidBackendKeys("Invalid key pair");
+    Ok(match alg {
+        JwsAlgorithm::P256 => {
+            let kp = ES256KeyPair::from_pem(kp).map_err(invalid_kp)?;
+            let sk: &p256::ecdsa::SigningKey = kp.key_pair().as_ref();
+            let signature: p256::ecdsa::Signature =
+                sk.try_sign(data).map_err(|_| RustyJwtError::ImplementationError)?;
+            signature.to_bytes().to_vec()
+        }
+        JwsAlgorithm::P384 => {
+            let kp = ES384KeyPair::from_pem(kp).map_err(invalid_kp)?;
+            let sk: &p384::ecdsa::SigningKey = kp.key_pair().as_ref();
+            let signature: p384::ecdsa::Signature =
+                sk.try_sign(data).map_err(|_| RustyJwtError::ImplementationError)?;
+            signature.to_bytes().to_vec()
+        }
+        JwsAlgorithm::Ed25519 => {
+            let kp = Ed25519KeyPair::from_pem(kp).map_err(invalid_kp)?;
+            kp.key_pair().as_ref().sk.sign(data, None).to_vec()
+        }
+    })
+}
+
+/// Verifies a signature produced by [sign_raw]
+pub(crate) fn verify_raw(alg: JwsAlgorithm, pk: &Pem, data: &[u8], signature: &[u8]) -> RustyJwtResult<()> {
+    use p256::ecdsa::signature::Verifier as _;
+    let invalid = || RustyJwtError::InvalidSignature;
+    match alg {
+        JwsAlgorithm::P256 => {
+            let pk = ES256PublicKey::from_pem(pk)?.public_key().to_bytes_uncompressed();
+            let pk = p256::ecdsa::VerifyingKey::from_sec1_bytes(&pk).map_err(|_| invalid())?;
+            let signature = p256::ecdsa::Signature::from_slice(signature).map_err(|_| invalid())?;
+            pk.verify(data, &signature).map_err(|_| invalid())
+        }
+        JwsAlgorithm::P384 => {
+            let pk = ES384PublicKey::from_pem(pk)?.public_key().to_bytes_uncompressed();
+            let pk = p384::ecdsa::VerifyingKey::from_sec1_bytes(&pk).map_err(|_| invalid())?;
+            let signature = p384::ecdsa::Signature::from_slice(signature).map_err(|_| invalid())?;
+            pk.verify(data, &signature).map_err(|_| invalid())
+        }
+        JwsAlgorithm::Ed25519 => {
+            let pk = Ed25519PublicKey::from_pem(pk)?.to_bytes();
+            let pk = ed25519_compact::PublicKey::from_slice(&pk).map_err(|_| invalid())?;
+            let signature = ed25519_compact::Signature::from_slice(signature).map_err(|_| invalid())?;
+            pk.verify(data, &signature).map_err(|_| invalid())
+        }
+    }
+}
@@ -3,35 +3,175 @@
 use jwt_simple::prelude::*;
 use serde::de::DeserializeOwned;
 
-use crate::prelude::*;
+use crate::{
+    jwk::JwkSet,
+    jwt::{
+        crit::{verify_critical_headers, CriticalHeader},
+        TokenLimits,
+    },
+    prelude::*,
+};
 
 /// Global trait to verify a Jwt token
-#[derive(Debug, Clone)]
-pub struct Verify<'a> {
+pub struct Verify<'a, T> {
     /// client_id
     pub client_id: &'a ClientId,
     /// nonce
     pub backend_nonce: Option<&'a BackendNonce>,
     /// leeway
     pub leeway: u16,
-    /// issuer
-    pub issuer: Option<Htu>,
+    /// accepted issuers, any of them is valid. Not verified when `None`
+    pub issuers: Option<Vec<Htu>>,
+    /// how the 'aud' claim is verified
+    pub audience: AudiencePolicy,
+    /// presence requirements of the standard claims
+    pub claims_policy: ClaimsPolicy,
+    /// size limits enforced before parsing the token
+    pub limits: TokenLimits,
+    /// additional checks run, in order, on the claims once the token has been verified
+    pub validators: Vec<&'a dyn ClaimValidator<T>>,
+    /// handlers of the extension header parameters the token may list in its 'crit' header
+    pub critical_headers: Vec<&'a dyn CriticalHeader>,
 }
 
-impl From<&Verify<'_>> for VerificationOptions {
-    fn from(v: &Verify<'_>) -> Self {
+impl<T> Clone for Verify<'_, T> {
+    fn clone(&self) -> Self {
+        Self {
+            client_id: self.client_id,
+            backend_nonce: self.backend_nonce,
+            leeway: self.leeway,
+            issuers: self.issuers.clone(),
+            audience: self.audience.clone(),
+            claims_policy: self.claims_policy,
+            limits: self.limits,
+            validators: self.validators.clone(),
+            critical_headers: self.critical_headers.clone(),
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for Verify<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Verify")
+            .field("client_id", &self.client_id)
+            .field("backend_nonce", &self.backend_nonce)
+            .field("leeway", &self.leeway)
+            .field("issuers", &self.issuers)
+            .field("audience", &self.audience)
+            .field("claims_policy", &self.claims_policy)
+            .field("limits", &self.limits)
+            .field("validators", &self.validators.len())
+            .field("critical_headers", &self.critical_headers.len())
+            .finish()
+    }
+}
+
+impl<T> From<&Verify<'_, T>> for VerificationOptions {
+    fn from(v: &Verify<'_, T>) -> Self {
         Self {
             accept_future: false,
             required_key_id: None, // we don't verify 'jti', just enforce its presence
-            required_subject: Some(v.client_id.to_uri()),
+            // verified afterwards, so that internationalized domains equal their punycode
+            required_subject: None,
             required_nonce: v.backend_nonce.map(|n| n.to_string()),
             time_tolerance: Some(UnixTimeStamp::from_secs(v.leeway as u64)),
-            allowed_issuers: v.issuer.as_ref().map(|i| HashSet::from([i.to_string()])),
+            allowed_issuers: v.issuers.as_ref().map(|i| i.iter().map(Htu::to_string).collect()),
             ..Default::default()
         }
     }
 }
 
+/// Verification policy of the 'aud' claim
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub enum AudiencePolicy {
+    /// 'aud' is not verified
+    #[default]
+    Ignore,
+    /// When present, 'aud' has to match any of those audiences
+    Optional(Vec<url::Url>),
+    /// 'aud' has to be present and match any of those audiences
+    Required(Vec<url::Url>),
+}
+
+impl AudiencePolicy {
+    /// Verifies the 'aud' claim of a token against this policy
+    pub fn verify(&self, audiences: Option<&Audiences>) -> RustyJwtResult<()> {
+        let (expected, audiences) = match (self, audiences) {
+            (Self::Ignore, _) | (Self::Optional(_), None) => return Ok(()),
+            (Self::Required(_), None) => return Err(RustyJwtError::MissingTokenClaim("aud")),
+            (Self::Optional(expected) | Self::Required(expected), Some(audiences)) => (expected, audiences),
+        };
+        let audiences = match audiences {
+            Audiences::AsString(aud) => HashSet::from([aud.clone()]),
+            Audiences::AsSet(aud) => aud.clone(),
+        };
+        let audiences = audiences
+            .iter()
+            .filter_map(|aud| aud.parse::<url::Url>().ok())
+            .collect::<Vec<_>>();
+        if audiences.is_empty() {
+            return Err(RustyJwtError::InvalidAudience);
+        }
+        if !audiences.iter().any(|aud| expected.contains(aud)) {
+            return Err(RustyJwtError::AudienceMismatch);
+        }
+        Ok(())
+    }
+}
+
+/// Whether a standard claim has to be present in a token
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub enum ClaimRequirement {
+    /// The claim has to be present
+    #[default]
+    Required,
+    /// The claim is verified only when present
+    Optional,
+    /// The claim must not be present
+    Forbidden,
+}
+
+impl ClaimRequirement {
+    /// Verifies the presence of a claim against this requirement
+    pub fn verify<T>(&self, claim: Option<T>, name: &'static str) -> RustyJwtResult<Option<T>> {
+        match (self, claim) {
+            (Self::Required, None) => Err(RustyJwtError::MissingTokenClaim(name)),
+            (Self::Forbidden, Some(_)) => Err(RustyJwtError::ForbiddenTokenClaim(name)),
+            (_, claim) => Ok(claim),
+        }
+    }
+}
+
+/// Presence requirements of the standard claims of a token. By default, they are all required.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct ClaimsPolicy {
+    /// 'jti' claim
+    pub jti: ClaimRequirement,
+    /// 'exp' claim
+    pub exp: ClaimRequirement,
+    /// 'iat' claim
+    pub iat: ClaimRequirement,
+    /// 'nbf' claim
+    pub nbf: ClaimRequirement,
+}
+
+/// Custom verification applied on the decoded claims after the signature and the standard claims have been verified.
+///
+/// Any closure `Fn(&JWTClaims<T>) -> RustyJwtResult<()>` is a [ClaimValidator]
+pub trait ClaimValidator<T> {
+    /// Rejects the claims with a domain error when they are not acceptable
+    fn validate(&self, claims: &JWTClaims<T>) -> RustyJwtResult<()>;
+}
+
+impl<T, F> ClaimValidator<T> for F
+where
+    F: Fn(&JWTClaims<T>) -> RustyJwtResult<()>,
+{
+    fn validate(&self, claims: &JWTClaims<T>) -> RustyJwtResult<()> {
+        self(claims)
+    }
+}
+
 /// Verifies JWT token standard headers
 pub trait VerifyJwtHeader {
     /// Verifies a Jwt token header
@@ -56,14 +196,22 @@ pub trait VerifyJwt {
     /// * `backend_nonce` - optional nonce generated by wire-server
     /// * `max_expiration` - token's 'exp' threshold
     /// * `leeway` - The maximum number of seconds of clock skew the implementation will allow
-    fn verify_jwt<T>(
+    /// * `verify` - expected claims and custom [ClaimValidator]s applied once all the standard verifications passed
+    fn verify_jwt<T>(&self, key: &AnyPublicKey, max_expiration: u64, verify: Verify<T>) -> RustyJwtResult<JWTClaims<T>>
+    where
+        T: Serialize + DeserializeOwned;
+
+    /// Verifies the JWT token with the key of the JWK Set matching its 'kid' header
+    ///
+    /// # Arguments
+    /// * `jwks` - Public signature keys, see [JwkSet::select] for how one gets selected
+    /// * `max_expiration` - token's 'exp' threshold
+    /// * `verify` - see [VerifyJwt::verify_jwt]
+    fn verify_jwt_with_jwks<T>(
         &self,
-        key: &AnyPublicKey,
+        jwks: &JwkSet,
         max_expiration: u64,
-        // expected_cnf: Option<&JwkThumbprint>,
-        // actual_cnf: Option<fn(&JWTClaims<T>) -> &JwkThumbprint>,
-        // custom: Option<fn(&JWTClaims<T>) -> RustyJwtResult<JWTClaims<T>>>,
-        verify: Verify,
+        verify: Verify<T>,
     ) -> RustyJwtResult<JWTClaims<T>>
     where
         T: Serialize + DeserializeOwned;
@@ -74,27 +222,53 @@ impl VerifyJwt for &str {
         &self,
         key: &AnyPublicKey<'_>,
         max_expiration: u64,
-        // expected_cnf: Option<&JwkThumbprint>,
-        // actual_cnf: Option<fn(&JWTClaims<T>) -> &JwkThumbprint>,
-        // custom: Option<fn(&JWTClaims<T>) -> RustyJwtResult<JWTClaims<T>>>,
-        verify: Verify,
+        verify: Verify<T>,
     ) -> RustyJwtResult<JWTClaims<T>>
     where
         T: Serialize + DeserializeOwned,
     {
+        verify.limits.verify(self)?;
+        verify_critical_headers(self, &verify.critical_headers)?;
         let verifications = Some(VerificationOptions::from(&verify));
         let claims = key.verify_token::<T>(self, verifications).map_err(jwt_error_mapping)?;
+        let sub = claims
+            .subject
+            .as_deref()
+            .ok_or(RustyJwtError::MissingTokenClaim("sub"))?;
+        if !matches!(ClientId::try_from_uri(sub), Ok(sub) if &sub == verify.client_id) {
+            return Err(RustyJwtError::TokenSubMismatch);
+        }
 
-        claims.jwt_id.as_ref().ok_or(RustyJwtError::MissingTokenClaim("jti"))?;
-        let exp = claims.expires_at.ok_or(RustyJwtError::MissingTokenClaim("exp"))?;
-        claims.issued_at.ok_or(RustyJwtError::MissingTokenClaim("iat"))?;
-        claims.invalid_before.ok_or(RustyJwtError::MissingTokenClaim("nbf"))?;
-        if exp > Duration::from_secs(max_expiration) {
+        let policy = &verify.claims_policy;
+        policy.jti.verify(claims.jwt_id.as_ref(), "jti")?;
+        let exp = policy.exp.verify(claims.expires_at, "exp")?;
+        policy.iat.verify(claims.issued_at, "iat")?;
+        policy.nbf.verify(claims.invalid_before, "nbf")?;
+        if matches!(exp, Some(exp) if exp > Duration::from_secs(max_expiration)) {
             return Err(RustyJwtError::TokenLivesTooLong);
         }
+        verify.audience.verify(claims.audiences.as_ref())?;
+        for validator in &verify.validators {
+            validator.validate(&claims)?;
+        }
 
         Ok(claims)
     }
+
+    fn verify_jwt_with_jwks<T>(
+        &self,
+        jwks: &JwkSet,
+        max_expiration: u64,
+        verify: Verify<T>,
+    ) -> RustyJwtResult<JWTClaims<T>>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        verify.limits.verify(self)?;
+        let header = Token::decode_metadata(self)?;
+        let key = jwks.select(&header)?;
+        self.verify_jwt(&key, max_expiration, verify)
+    }
 }
 
 /// Tries mapping 'jwt-simple' errors
@@ -127,3 +301,186 @@ pub fn jwt_error_mapping(e: jwt_simple::Error) -> RustyJwtError {
         _ => RustyJwtError::InvalidToken(reason),
     }
 }
+
+#[cfg(test)]
+pub mod tests {
+    use wasm_bindgen_test::*;
+
+    use crate::test_utils::*;
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    mod validators {
+        use super::*;
+
+        #[apply(all_keys)]
+        #[wasm_bindgen_test]
+        fn should_run_custom_validators(key: JwtKey) {
+            let token = DpopBuilder::from(key.clone()).build();
+            let pk = AnyPublicKey::from((key.alg, &key.pk));
+            let client_id = ClientId::default();
+            let nonce = BackendNonce::default();
+
+            let reject_handle = |claims: &JWTClaims<Dpop>| -> RustyJwtResult<()> {
+                if claims.custom.handle == QualifiedHandle::default() {
+                    return Err(RustyJwtError::DpopHandleMismatch);
+                }
+                Ok(())
+            };
+            let accept_all = |_: &JWTClaims<Dpop>| -> RustyJwtResult<()> { Ok(()) };
+
+            // should succeed when every validator accepts the claims
+            let verify = Verify {
+                client_id: &client_id,
+                backend_nonce: Some(&nonce),
+                leeway: 5,
+                issuers: None,
+                audience: AudiencePolicy::Ignore,
+                claims_policy: ClaimsPolicy::default(),
+                limits: TokenLimits::default(),
+                validators: vec![&accept_all],
+                critical_headers: vec![],
+            };
+            let result = token.as_str().verify_jwt::<Dpop>(&pk, u64::MAX, verify);
+            assert!(result.is_ok());
+
+            // should fail with the domain error of the first rejecting validator
+            let verify = Verify {
+                client_id: &client_id,
+                backend_nonce: Some(&nonce),
+                leeway: 5,
+                issuers: None,
+                audience: AudiencePolicy::Ignore,
+                claims_policy: ClaimsPolicy::default(),
+                limits: TokenLimits::default(),
+                validators: vec![&accept_all, &reject_handle],
+                critical_headers: vec![],
+            };
+            let result = token.as_str().verify_jwt::<Dpop>(&pk, u64::MAX, verify);
+            assert!(matches!(result.unwrap_err(), RustyJwtError::DpopHandleMismatch));
+        }
+    }
+
+    mod audience {
+        use super::*;
+
+        #[apply(all_keys)]
+        #[wasm_bindgen_test]
+        fn should_verify_audience(key: JwtKey) {
+            // the audience 'DpopBuilder' sets
+            let expected: url::Url = "https://stepca/acme/wire/challenge/aaa/bbb".parse().unwrap();
+            let other: url::Url = "https://other/acme/wire/challenge/aaa/bbb".parse().unwrap();
+            let pk = AnyPublicKey::from((key.alg, &key.pk));
+            let client_id = ClientId::default();
+            let nonce = BackendNonce::default();
+            let verify = |audience: AudiencePolicy, token: &str| {
+                let verify = Verify::<Dpop> {
+                    client_id: &client_id,
+                    backend_nonce: Some(&nonce),
+                    leeway: 5,
+                    issuers: None,
+                    audience,
+                    claims_policy: ClaimsPolicy::default(),
+                    limits: TokenLimits::default(),
+                    validators: vec![],
+                    critical_headers: vec![],
+                };
+                token.verify_jwt::<Dpop>(&pk, u64::MAX, verify)
+            };
+
+            let token = DpopBuilder::from(key.clone()).build();
+
+            // should succeed when any of the audiences matches
+            let required = AudiencePolicy::Required(vec![other.clone(), expected.clone()]);
+            assert!(verify(required, &token).is_ok());
+            let optional = AudiencePolicy::Optional(vec![expected]);
+            assert!(verify(optional, &token).is_ok());
+            assert!(verify(AudiencePolicy::Ignore, &token).is_ok());
+
+            // should fail when none of the audiences matches
+            let required = AudiencePolicy::Required(vec![other.clone()]);
+            let result = verify(required, &token);
+            assert!(matches!(result.unwrap_err(), RustyJwtError::AudienceMismatch));
+            let optional = AudiencePolicy::Optional(vec![other.clone()]);
+            let result = verify(optional, &token);
+            assert!(matches!(result.unwrap_err(), RustyJwtError::AudienceMismatch));
+        }
+
+        #[test]
+        #[wasm_bindgen_test]
+        fn should_handle_absent_audience() {
+            let expected: url::Url = "https://stepca/acme/wire/challenge/aaa/bbb".parse().unwrap();
+
+            assert!(AudiencePolicy::Ignore.verify(None).is_ok());
+            assert!(AudiencePolicy::Optional(vec![expected.clone()]).verify(None).is_ok());
+            let result = AudiencePolicy::Required(vec![expected.clone()]).verify(None);
+            assert!(matches!(result.unwrap_err(), RustyJwtError::MissingTokenClaim("aud")));
+
+            // should match against any audience of a set
+            let audiences = Audiences::AsSet(HashSet::from(["https://a.com".to_string(), expected.to_string()]));
+            assert!(AudiencePolicy::Required(vec![expected.clone()])
+                .verify(Some(&audiences))
+                .is_ok());
+
+            // should tell a malformed audience apart from an unexpected one
+            let malformed = Audiences::AsString("not a url".to_string());
+            let result = AudiencePolicy::Required(vec![expected]).verify(Some(&malformed));
+            assert!(matches!(result.unwrap_err(), RustyJwtError::InvalidAudience));
+        }
+    }
+
+    mod claims_policy {
+        use super::*;
+
+        #[apply(all_keys)]
+        #[wasm_bindgen_test]
+        fn should_tune_standard_claims_strictness(key: JwtKey) {
+            let pk = AnyPublicKey::from((key.alg, &key.pk));
+            let client_id = ClientId::default();
+            let nonce = BackendNonce::default();
+            let verify = |claims_policy: ClaimsPolicy, token: &str| {
+                let verify = Verify::<Dpop> {
+                    client_id: &client_id,
+                    backend_nonce: Some(&nonce),
+                    leeway: 5,
+                    issuers: None,
+                    audience: AudiencePolicy::Ignore,
+                    claims_policy,
+                    limits: TokenLimits::default(),
+                    validators: vec![],
+                    critical_headers: vec![],
+                };
+                token.verify_jwt::<Dpop>(&pk, u64::MAX, verify)
+            };
+            let without_nbf = DpopBuilder {
+                nbf: None,
+                ..key.clone().into()
+            }
+            .build();
+            let with_nbf = DpopBuilder::from(key.clone()).build();
+
+            // should fail by default when 'nbf' is absent
+            let result = verify(ClaimsPolicy::default(), &without_nbf);
+            assert!(matches!(result.unwrap_err(), RustyJwtError::MissingTokenClaim("nbf")));
+
+            // should succeed when 'nbf' is optional
+            let optional = ClaimsPolicy {
+                nbf: ClaimRequirement::Optional,
+                ..Default::default()
+            };
+            assert!(verify(optional, &without_nbf).is_ok());
+            assert!(verify(optional, &with_nbf).is_ok());
+
+            // should fail when 'nbf' is forbidden but present
+            let forbidden = ClaimsPolicy {
+                nbf: ClaimRequirement::Forbidden,
+                ..Default::default()
+            };
+            assert!(verify(forbidden, &without_nbf).is_ok());
+            let result = verify(forbidden, &with_nbf);
+            assert!(matches!(result.unwrap_err(), RustyJwtError::ForbiddenTokenClaim("nbf")));
+        }
+    }
+}
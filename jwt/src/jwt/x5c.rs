@@ -0,0 +1,277 @@
+use base64::{prelude::BASE64_STANDARD, Engine};
+use jwt_simple::prelude::*;
+use rusty_x509_check::revocation::{PkiEnvironment, PkiEnvironmentParams};
+use x509_cert::{anchor::TrustAnchorChoice, der::Decode, Certificate};
+
+use crate::{
+    jwt::{crit::verify_critical_headers, verify::jwt_error_mapping},
+    prelude::*,
+};
+
+impl RustyJwtTools {
+    /// Same as [RustyJwtTools::generate_jwt] but also embeds a certificate chain certifying the
+    /// signing key in the 'x5c' header, so that a verifier only has to trust the root CA.
+    ///
+    /// Specified in [RFC 7515 Section 4.1.6][1]
+    ///
+    /// # Arguments
+    /// * `alg` - signature algorithm
+    /// * `header` - protected header. Its 'x5c' is overwritten
+    /// * `claims` - claims to sign
+    /// * `kp` - PEM format key pair of the signer
+    /// * `chain` - DER encoded certificates, the leaf one certifying [kp] first, each following
+    /// one certifying the previous one
+    /// * `with_jwk` - whether to add the signer public key in the 'jwk' header
+    ///
+    /// [1]: https://www.rfc-editor.org/rfc/rfc7515#section-4.1.6
+    pub fn generate_jwt_with_x5c<T>(
+        alg: JwsAlgorithm,
+        header: JWTHeader,
+        claims: Option<JWTClaims<T>>,
+        kp: &Pem,
+        chain: &[Vec<u8>],
+        with_jwk: bool,
+    ) -> RustyJwtResult<String>
+    where
+        T: Serialize,
+        for<'de> T: Deserialize<'de>,
+    {
+        let leaf = chain
+            .first()
+            .ok_or(RustyJwtError::InvalidX5c("it has no certificate"))?;
+        let leaf =
+            Certificate::from_der(leaf).map_err(|_| RustyJwtError::InvalidX5c("a certificate is not DER encoded"))?;
+        let leaf_pk = ParsedPublicKey::try_from_certificate(alg, &leaf)?;
+        let signer_pk = match alg {
+            JwsAlgorithm::P256 => ParsedPublicKey::P256(ES256KeyPair::from_pem(kp)?.public_key()),
+            JwsAlgorithm::P384 => ParsedPublicKey::P384(ES384KeyPair::from_pem(kp)?.public_key()),
+            JwsAlgorithm::Ed25519 => ParsedPublicKey::Ed25519(Ed25519KeyPair::from_pem(kp)?.public_key()),
+        };
+        if AnyPublicKey::from(&leaf_pk) != AnyPublicKey::from(&signer_pk) {
+            return Err(RustyJwtError::InvalidX5c(
+                "leaf certificate does not certify the signing key",
+            ));
+        }
+        let header = JWTHeader {
+            certificate_chain: Some(chain.iter().map(|c| BASE64_STANDARD.encode(c)).collect()),
+            ..header
+        };
+        Self::generate_jwt(alg, header, claims, kp, with_jwk)
+    }
+
+    /// Verifies a JWT signed by the key certified by the leaf certificate of its 'x5c' header and
+    /// validates this certificate chain against [trust_anchors]. When the JWT also has a 'jwk'
+    /// header, it has to be the leaf certificate key. Time related claims are verified when present.
+    ///
+    /// # Arguments
+    /// * `token` - the JWT to verify
+    /// * `trust_anchors` - root CAs the certificate chain has to end up with
+    pub fn verify_jwt_with_x5c<T>(token: &str, trust_anchors: &[TrustAnchorChoice]) -> RustyJwtResult<JWTClaims<T>>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        let header = Token::decode_metadata(token)?;
+        let alg = JwsAlgorithm::try_from(header.algorithm())?;
+        verify_critical_headers(token, &[])?;
+        let chain = header
+            .certificate_chain()
+            .filter(|c| !c.is_empty())
+            .ok_or(RustyJwtError::InvalidX5c("'x5c' header is missing"))?
+            .iter()
+            .map(|c| {
+                let der = BASE64_STANDARD
+                    .decode(c)
+                    .map_err(|_| RustyJwtError::InvalidX5c("a certificate is not base64 encoded"))?;
+                Certificate::from_der(&der).map_err(|_| RustyJwtError::InvalidX5c("a certificate is not DER encoded"))
+            })
+            .collect::<RustyJwtResult<Vec<_>>>()?;
+        let (leaf, intermediates) = chain.split_first().ok_or(RustyJwtError::ImplementationError)?;
+
+        let leaf_pk = ParsedPublicKey::try_from_certificate(alg, leaf)?;
+        let leaf_pk = AnyPublicKey::from(&leaf_pk);
+        if let Some(jwk) = header.public_key() {
+            if AnyPublicKey::from((alg, jwk)) != leaf_pk {
+                return Err(RustyJwtError::InvalidX5c(
+                    "'jwk' header is not the leaf certificate key",
+                ));
+            }
+        }
+        let claims = leaf_pk.verify_token::<T>(token, None).map_err(jwt_error_mapping)?;
+
+        let env = PkiEnvironment::init(PkiEnvironmentParams {
+            intermediates,
+            trust_roots: trust_anchors,
+            ..Default::default()
+        })?;
+        env.validate_cert(leaf)?;
+        Ok(claims)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use serde_json::json;
+    use wasm_bindgen_test::*;
+    use x509_cert::der::Encode;
+
+    use crate::{jwt::signature::sign_raw, test_utils::*};
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    const ROOT: &str = r#"-----BEGIN CERTIFICATE-----
+MIIBuzCCAWGgAwIBAgIUEdfpej7DDqFIgs2yILQlqd9Hg6cwCgYIKoZIzj0EAwIw
+KjERMA8GA1UECgwId2lyZS5jb20xFTATBgNVBAMMDFdpcmUgUm9vdCBDQTAgFw0y
+NjEwMTYxNDE2MTJaGA8yMTI2MDkyMjE0MTYxMlowKjERMA8GA1UECgwId2lyZS5j
+b20xFTATBgNVBAMMDFdpcmUgUm9vdCBDQTBZMBMGByqGSM49AgEGCCqGSM49AwEH
+A0IABLdh0WuVRJgfnFcpnaOcZp6oAFHT4lkewEX2wiGogrJyFEDxgPI5GpGyUlX2
+nM5cH6t7PHa+qFpaMB1r93IsBWqjYzBhMB8GA1UdIwQYMBaAFL4p7jITFa6COV5c
+2krpdaY14FJwMA8GA1UdEwEB/wQFMAMBAf8wDgYDVR0PAQH/BAQDAgEGMB0GA1Ud
+DgQWBBS+Ke4yExWugjleXNpK6XWmNeBScDAKBggqhkjOPQQDAgNIADBFAiAihxsJ
+tJYzYu339heZYOMKIhnxQjJtn1oFnSyzTHLVWQIhALsHK/n7SwNYcNfQ+tjjS989
+E+kzHTxJnbQO8Did/nOE
+-----END CERTIFICATE-----"#;
+
+    /// Root CA which has not issued [LEAF]
+    const OTHER_ROOT: &str = r#"-----BEGIN CERTIFICATE-----
+MIIBvDCCAWOgAwIBAgIUU34+RVp/rZVYvKrDBBtBt062xmswCgYIKoZIzj0EAwIw
+KzERMA8GA1UECgwId2lyZS5jb20xFjAUBgNVBAMMDU90aGVyIFJvb3QgQ0EwIBcN
+MjYxMDE2MTQxNzIyWhgPMjEyNjA5MjIxNDE3MjJaMCsxETAPBgNVBAoMCHdpcmUu
+Y29tMRYwFAYDVQQDDA1PdGhlciBSb290IENBMFkwEwYHKoZIzj0CAQYIKoZIzj0D
+AQcDQgAE82jz+3/r79neyMPChFm77Xo8DJ0vd1cAawHJLFqCePemcTchKw9svNVr
+4ZZVKCYl7cqjp78w8zZ27LMwa6DQYaNjMGEwHQYDVR0OBBYEFJMkGER/m1cPY7wA
+D8EaieEoQCncMB8GA1UdIwQYMBaAFJMkGER/m1cPY7wAD8EaieEoQCncMA8GA1Ud
+EwEB/wQFMAMBAf8wDgYDVR0PAQH/BAQDAgEGMAoGCCqGSM49BAMCA0cAMEQCIHqY
+VFJKldgzr0jvGiKOVd7+ODC+2grAFpvr38qhhCU1AiBkkQuhzJYWD3Li8hxyr7Sc
+4wSKtlL1BGgaFnHNPkTDbQ==
+-----END CERTIFICATE-----"#;
+
+    fn der(pem: &str) -> Vec<u8> {
+        let cert = PkiEnvironment::decode_pem_cert(pem.to_string()).unwrap();
+        cert.to_der().unwrap()
+    }
+
+    fn trust_anchors() -> Vec<TrustAnchorChoice> {
+        anchor(ROOT)
+    }
+
+    fn anchor(pem: &str) -> Vec<TrustAnchorChoice> {
+        let root = PkiEnvironment::decode_pem_cert(pem.to_string()).unwrap();
+        vec![TrustAnchorChoice::Certificate(root)]
+    }
+
+    fn sign(kp: &str, chain: &[Vec<u8>]) -> RustyJwtResult<String> {
+        let claims = Claims::with_custom_claims(json!({"a": "b"}), Duration::from_hours(1));
+        RustyJwtTools::generate_jwt_with_x5c(
+            JwsAlgorithm::P256,
+            JWTHeader::default(),
+            Some(claims),
+            &Pem::from(kp.to_string()),
+            chain,
+            true,
+        )
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_roundtrip() {
+        let token = sign(LEAF_KP, &[der(LEAF)]).unwrap();
+        let header = Token::decode_metadata(&token).unwrap();
+        assert_eq!(
+            header.certificate_chain().unwrap(),
+            &[BASE64_STANDARD.encode(der(LEAF))]
+        );
+
+        let claims = RustyJwtTools::verify_jwt_with_x5c::<serde_json::Value>(&token, &trust_anchors()).unwrap();
+        assert_eq!(claims.custom, json!({"a": "b"}));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_fail_generating_when_leaf_does_not_certify_signing_key() {
+        let other = JwtKey::new_key(JwsAlgorithm::P256);
+        let result = sign(&other.kp, &[der(LEAF)]);
+        assert!(matches!(result.unwrap_err(), RustyJwtError::InvalidX5c(_)));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_fail_generating_without_certificate() {
+        let result = sign(LEAF_KP, &[]);
+        assert!(matches!(result.unwrap_err(), RustyJwtError::InvalidX5c(_)));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_fail_when_signed_by_another_key() {
+        // bypasses the check made when generating
+        let other = JwtKey::new_key(JwsAlgorithm::P256);
+        let header = JWTHeader {
+            certificate_chain: Some(vec![BASE64_STANDARD.encode(der(LEAF))]),
+            ..Default::default()
+        };
+        let claims = Claims::with_custom_claims(json!({}), Duration::from_hours(1));
+        let token = RustyJwtTools::generate_jwt(JwsAlgorithm::P256, header, Some(claims), &other.kp, false).unwrap();
+        let result = RustyJwtTools::verify_jwt_with_x5c::<serde_json::Value>(&token, &trust_anchors());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_fail_when_jwk_is_not_leaf_key() {
+        let other = JwtKey::new_key(JwsAlgorithm::P256);
+        let token = sign(LEAF_KP, &[der(LEAF)]).unwrap();
+        let [protected, payload, signature]: [&str; 3] = token.split('.').collect::<Vec<_>>().try_into().unwrap();
+        let mut header = serde_json::from_slice::<serde_json::Value>(
+            &base64::prelude::BASE64_URL_SAFE_NO_PAD.decode(protected).unwrap(),
+        )
+        .unwrap();
+        header["jwk"] = serde_json::to_value(other.to_jwk()).unwrap();
+        let protected = base64::prelude::BASE64_URL_SAFE_NO_PAD.encode(header.to_string());
+        let token = format!("{protected}.{payload}.{signature}");
+        let result = RustyJwtTools::verify_jwt_with_x5c::<serde_json::Value>(&token, &trust_anchors());
+        assert!(matches!(result.unwrap_err(), RustyJwtError::InvalidX5c(_)));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_fail_when_critical_header_unsupported() {
+        let token = sign(LEAF_KP, &[der(LEAF)]).unwrap();
+        let [protected, payload, _]: [&str; 3] = token.split('.').collect::<Vec<_>>().try_into().unwrap();
+        let mut header = serde_json::from_slice::<serde_json::Value>(
+            &base64::prelude::BASE64_URL_SAFE_NO_PAD.decode(protected).unwrap(),
+        )
+        .unwrap();
+        header["crit"] = json!(["wire"]);
+        header["wire"] = json!(true);
+        let protected = base64::prelude::BASE64_URL_SAFE_NO_PAD.encode(header.to_string());
+        let kp = Pem::from(LEAF_KP.to_string());
+        let signature = sign_raw(JwsAlgorithm::P256, &kp, format!("{protected}.{payload}").as_bytes()).unwrap();
+        let token = format!(
+            "{protected}.{payload}.{}",
+            base64::prelude::BASE64_URL_SAFE_NO_PAD.encode(signature)
+        );
+        let result = RustyJwtTools::verify_jwt_with_x5c::<serde_json::Value>(&token, &trust_anchors());
+        assert!(matches!(result.unwrap_err(), RustyJwtError::UnsupportedCriticalHeader(h) if h == "wire"));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_fail_when_chain_not_trusted() {
+        let token = sign(LEAF_KP, &[der(LEAF)]).unwrap();
+        let result = RustyJwtTools::verify_jwt_with_x5c::<serde_json::Value>(&token, &anchor(OTHER_ROOT));
+        assert!(matches!(result.unwrap_err(), RustyJwtError::X509CheckError(_)));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_fail_without_x5c() {
+        let key = JwtKey::new_key(JwsAlgorithm::P256);
+        let claims = Claims::with_custom_claims(json!({}), Duration::from_hours(1));
+        let token =
+            RustyJwtTools::generate_jwt(JwsAlgorithm::P256, JWTHeader::default(), Some(claims), &key.kp, true).unwrap();
+        let result = RustyJwtTools::verify_jwt_with_x5c::<serde_json::Value>(&token, &trust_anchors());
+        assert!(matches!(result.unwrap_err(), RustyJwtError::InvalidX5c(_)));
+    }
+}
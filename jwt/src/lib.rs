@@ -14,6 +14,8 @@ pub mod test_utils;
 // both imports above have to be defined at the beginning of the crate for rstest to work
 
 mod access;
+#[cfg(feature = "cwt")]
+mod cwt;
 mod dpop;
 mod error;
 #[cfg(feature = "jwe")]
@@ -23,20 +25,34 @@ pub mod jwk_thumbprint;
 pub mod jwt;
 mod model;
 mod oidc;
+#[cfg(feature = "schema")]
+mod schema;
 
 /// Prelude
 pub mod prelude {
+    pub use access::{
+        Access, AccessScope, AccessTokenBuilder, AccessTokenClaims, AccessTokenToVerify, Cnf, InMemoryRevocationList,
+        IntrospectedAccessToken, IntrospectionResult, NoRevocation, RevocationCheck, SupportedApiVersions,
+        VerifyAccessTokenOptions,
+    };
     pub use dpop::{Dpop, Htm, Htu};
     pub use error::{RustyJwtError, RustyJwtResult};
-    pub use jwk_thumbprint::JwkThumbprint;
+    pub use jwk_thumbprint::{JwkThumbprint, KidStrategy};
+    pub use jwt::{
+        AudiencePolicy, CheckReport, CriticalHeader, GeneralJws, GeneralJwsSignature, JwsSigner, JwtHeader,
+        VerificationReport,
+    };
     pub use model::{
         alg::{HashAlgorithm, JwsAlgorithm, JwsEcAlgorithm, JwsEdAlgorithm},
-        client_id::ClientId,
+        claims_version::ClaimsVersion,
+        client_id::{ClientId, ClientIdFormat, DeviceIdFormat},
+        display_name::{DisplayName, DisplayNameFolding},
+        domain::{self, DomainPolicy},
         handle::{Handle, QualifiedHandle},
         nonce::{AcmeNonce, BackendNonce},
         pem::Pem,
-        pk::AnyPublicKey,
-        team::Team,
+        pk::{AnyPublicKey, ParsedPublicKey},
+        team::{Team, TeamPolicy},
     };
     pub use oidc::{
         context::Context,
@@ -51,7 +67,7 @@ pub mod prelude {
     };
 
     #[cfg(feature = "jwe")]
-    pub use jwe::alg::JweAlgorithm;
+    pub use jwe::{alg::JweAlgorithm, JweRecipientKey};
 
     pub use super::RustyJwtTools;
     use super::*;
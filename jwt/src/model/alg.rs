@@ -154,12 +154,14 @@ pub enum HashAlgorithm {
     SHA256,
     /// SHA-384
     SHA384,
+    /// SHA-512
+    SHA512,
 }
 
 #[cfg(test)]
 impl HashAlgorithm {
-    pub fn values() -> [Self; 2] {
-        [Self::SHA256, Self::SHA384]
+    pub fn values() -> [Self; 3] {
+        [Self::SHA256, Self::SHA384, Self::SHA512]
     }
 }
 
@@ -168,6 +170,7 @@ impl std::fmt::Display for HashAlgorithm {
         let name = match self {
             HashAlgorithm::SHA256 => "SHA-256",
             HashAlgorithm::SHA384 => "SHA-384",
+            HashAlgorithm::SHA512 => "SHA-512",
         };
         write!(f, "{name}")
     }
@@ -180,6 +183,7 @@ impl FromStr for HashAlgorithm {
         Ok(match s {
             "SHA-256" => Self::SHA256,
             "SHA-384" => Self::SHA384,
+            "SHA-512" => Self::SHA512,
             _ => return Err(RustyJwtError::ImplementationError),
         })
     }
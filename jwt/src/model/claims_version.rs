@@ -0,0 +1,229 @@
+use jwt_simple::prelude::*;
+use serde::de::Error as _;
+
+use crate::prelude::*;
+
+/// Version of the schema of the claims of DPoP proofs and access tokens, carried in their 'cv'
+/// claim so that clients and servers operating on different versions can tell each other apart.
+/// Tokens without it are in [ClaimsVersion::V1].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+#[serde(into = "u64", try_from = "u64")]
+pub enum ClaimsVersion {
+    /// Legacy schema, without the 'cv' claim. The ACME challenge was named 'challenge' instead of 'chal'
+    V1,
+    /// Current schema
+    V2,
+}
+
+impl ClaimsVersion {
+    /// Name of the claim carrying the version
+    pub const CLAIM: &'static str = "cv";
+
+    /// Version of the tokens generated by this library
+    pub const CURRENT: Self = Self::V2;
+
+    /// Every known version, the verifier default
+    pub const ALL: [Self; 2] = [Self::V1, Self::V2];
+
+    /// Fails with the version when it is not one of [accepted]
+    pub fn verify(version: u64, accepted: &[Self]) -> RustyJwtResult<Self> {
+        Self::try_from(version)
+            .ok()
+            .filter(|v| accepted.contains(v))
+            .ok_or(RustyJwtError::UnsupportedClaimsVersion(version))
+    }
+}
+
+impl From<ClaimsVersion> for u64 {
+    fn from(version: ClaimsVersion) -> Self {
+        match version {
+            ClaimsVersion::V1 => 1,
+            ClaimsVersion::V2 => 2,
+        }
+    }
+}
+
+impl TryFrom<u64> for ClaimsVersion {
+    type Error = RustyJwtError;
+
+    fn try_from(version: u64) -> RustyJwtResult<Self> {
+        match version {
+            1 => Ok(Self::V1),
+            2 => Ok(Self::V2),
+            _ => Err(RustyJwtError::UnsupportedClaimsVersion(version)),
+        }
+    }
+}
+
+/// Claims whose names changed since [ClaimsVersion::V1]
+pub(crate) trait VersionedClaims: Serialize + DeserializeOwned {
+    /// Legacy name of a claim along with its current one
+    const LEGACY_NAMES: &'static [(&'static str, &'static str)];
+}
+
+/// Claims along with their schema version. Serialized with the 'cv' claim, deserialized from any
+/// version by mapping legacy claim names to the current ones.
+#[derive(Debug, Clone)]
+pub(crate) struct Versioned<T> {
+    /// as found in the token since it might not be supported
+    pub(crate) version: u64,
+    pub(crate) claims: T,
+}
+
+impl<T> Versioned<T> {
+    /// Marks claims generated by this library with [ClaimsVersion::CURRENT]
+    pub(crate) fn current(claims: JWTClaims<T>) -> JWTClaims<Self> {
+        Self::map(claims, |claims| Self {
+            version: ClaimsVersion::CURRENT.into(),
+            claims,
+        })
+    }
+
+    /// Fails when the claims are not in one of the [accepted] versions
+    pub(crate) fn verify(claims: JWTClaims<Self>, accepted: &[ClaimsVersion]) -> RustyJwtResult<JWTClaims<T>> {
+        ClaimsVersion::verify(claims.custom.version, accepted)?;
+        Ok(Self::unversioned(claims))
+    }
+
+    /// Drops the version, whatever it is
+    pub(crate) fn unversioned(claims: JWTClaims<Self>) -> JWTClaims<T> {
+        Self::map(claims, |versioned| versioned.claims)
+    }
+
+    fn map<A, B>(claims: JWTClaims<A>, f: impl FnOnce(A) -> B) -> JWTClaims<B> {
+        JWTClaims {
+            issued_at: claims.issued_at,
+            expires_at: claims.expires_at,
+            invalid_before: claims.invalid_before,
+            issuer: claims.issuer,
+            subject: claims.subject,
+            audiences: claims.audiences,
+            jwt_id: claims.jwt_id,
+            nonce: claims.nonce,
+            custom: f(claims.custom),
+        }
+    }
+}
+
+impl<T: Serialize> Serialize for Versioned<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Repr<'a, T> {
+            #[serde(rename = "cv")]
+            version: u64,
+            #[serde(flatten)]
+            claims: &'a T,
+        }
+        Repr {
+            version: self.version,
+            claims: &self.claims,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, T: VersionedClaims> Deserialize<'de> for Versioned<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut claims = serde_json::Map::<String, serde_json::Value>::deserialize(deserializer)?;
+        let version = match claims.remove(ClaimsVersion::CLAIM) {
+            None => ClaimsVersion::V1.into(),
+            Some(version) => version
+                .as_u64()
+                .ok_or_else(|| D::Error::custom("'cv' claim is not a version number"))?,
+        };
+        if version == u64::from(ClaimsVersion::V1) {
+            for (legacy, current) in T::LEGACY_NAMES {
+                if !claims.contains_key(*current) {
+                    if let Some(value) = claims.remove(*legacy) {
+                        claims.insert(current.to_string(), value);
+                    }
+                }
+            }
+        }
+        let claims = T::deserialize(serde_json::Value::Object(claims)).map_err(D::Error::custom)?;
+        Ok(Self { version, claims })
+    }
+}
+
+impl VersionedClaims for Dpop {
+    const LEGACY_NAMES: &'static [(&'static str, &'static str)] = &[("challenge", "chal")];
+}
+
+impl VersionedClaims for crate::access::Access {
+    const LEGACY_NAMES: &'static [(&'static str, &'static str)] = &[("challenge", "chal")];
+}
+
+#[cfg(test)]
+pub mod tests {
+    use serde_json::json;
+    use wasm_bindgen_test::*;
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    fn dpop(claims: serde_json::Value) -> Versioned<Dpop> {
+        serde_json::from_value(claims).unwrap()
+    }
+
+    fn claims(version: Option<u64>, challenge: &str) -> serde_json::Value {
+        let mut claims = serde_json::to_value(Dpop::default()).unwrap();
+        let chal = claims.as_object_mut().unwrap().remove("chal").unwrap();
+        claims[challenge] = chal;
+        if let Some(version) = version {
+            claims["cv"] = version.into();
+        }
+        claims
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_serialize_current_version() {
+        let claims = Versioned::current(Claims::with_custom_claims(Dpop::default(), Duration::from_hours(1)));
+        let claims = serde_json::to_value(claims).unwrap();
+        assert_eq!(claims["cv"], json!(2));
+        assert!(claims.get("chal").is_some());
+
+        let claims = serde_json::from_value::<JWTClaims<Versioned<Dpop>>>(claims).unwrap();
+        assert_eq!(claims.custom.version, 2);
+        // the marker is not mistaken for an extra claim
+        assert_eq!(claims.custom.claims, Dpop::default());
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_map_legacy_names() {
+        let legacy = dpop(claims(None, "challenge"));
+        assert_eq!(legacy.version, 1);
+        assert_eq!(legacy.claims, Dpop::default());
+
+        // tokens without the marker but with current names
+        let unmarked = dpop(claims(None, "chal"));
+        assert_eq!(unmarked.claims, Dpop::default());
+
+        // legacy names are only mapped in the legacy version
+        let result = serde_json::from_value::<Versioned<Dpop>>(claims(Some(2), "challenge"));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_accept_configured_versions() {
+        assert_eq!(
+            ClaimsVersion::verify(1, &ClaimsVersion::ALL).unwrap(),
+            ClaimsVersion::V1
+        );
+        assert_eq!(
+            ClaimsVersion::verify(2, &[ClaimsVersion::V2]).unwrap(),
+            ClaimsVersion::V2
+        );
+        assert!(matches!(
+            ClaimsVersion::verify(1, &[ClaimsVersion::V2]).unwrap_err(),
+            RustyJwtError::UnsupportedClaimsVersion(1)
+        ));
+        assert!(matches!(
+            ClaimsVersion::verify(3, &ClaimsVersion::ALL).unwrap_err(),
+            RustyJwtError::UnsupportedClaimsVersion(3)
+        ));
+    }
+}
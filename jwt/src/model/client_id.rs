@@ -1,4 +1,4 @@
-use crate::model::DEFAULT_URL;
+use crate::model::{domain, DEFAULT_URL};
 use base64::Engine;
 use const_format::concatcp;
 use percent_encoding::percent_decode_str;
@@ -7,17 +7,81 @@ use uuid::Uuid;
 
 use crate::prelude::*;
 
+/// Wire format a [ClientId] was parsed from, see [ClientId::parse_any]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum ClientIdFormat {
+    /// `wireapp://{userId}!{clientId}@{domain}` with a base64url encoded userId, see [ClientId::to_uri]
+    Uri,
+    /// `{userId}:{clientId}@{domain}` with a base64url encoded userId, see [ClientId::to_qualified]
+    Qualified,
+    /// `{userId}:{clientId}@{domain}` with a hyphenated UUID userId, as sent by older clients
+    Legacy,
+}
+
+/// Textual form of the device id of a [ClientId]. wire-server and clients have historically
+/// disagreed on it, hence [DeviceIdFormat::parse] is tolerant within a radix.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub enum DeviceIdFormat {
+    /// lowercase hex without leading zeros e.g. `1a2b`
+    #[default]
+    Hex,
+    /// lowercase hex zero-padded to 16 digits e.g. `0000000000001a2b`
+    PaddedHex,
+    /// decimal e.g. `6699`
+    Decimal,
+}
+
+impl DeviceIdFormat {
+    /// Renders [device_id] in this format
+    pub fn render(&self, device_id: u64) -> String {
+        match self {
+            Self::Hex => format!("{device_id:x}"),
+            Self::PaddedHex => format!("{device_id:016x}"),
+            Self::Decimal => device_id.to_string(),
+        }
+    }
+
+    /// Parses a device id in the radix of this format. Leading zeros are accepted whatever the
+    /// format and so are uppercase digits and a '0x' prefix for hex ones.
+    pub fn parse(&self, device_id: &str) -> RustyJwtResult<u64> {
+        let (digits, radix) = match self {
+            Self::Hex | Self::PaddedHex => {
+                let digits = device_id
+                    .strip_prefix("0x")
+                    .or_else(|| device_id.strip_prefix("0X"))
+                    .unwrap_or(device_id);
+                (digits, 16)
+            }
+            Self::Decimal => (device_id, 10),
+        };
+        // 'from_str_radix' would also accept a sign
+        if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+            return Err(RustyJwtError::InvalidClientId);
+        }
+        u64::from_str_radix(digits, radix).map_err(|_| RustyJwtError::InvalidClientId)
+    }
+}
+
 /// Unique user handle
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone)]
 pub struct ClientId {
     /// base64url encoded UUIDv4 unique user identifier
     pub user_id: Uuid,
-    /// the device id assigned by the backend in hex
+    /// the device id assigned by the backend, see [DeviceIdFormat] for its textual forms
     pub device_id: u64,
-    /// the backend domain of the client
+    /// the backend domain of the client, in its ASCII (punycode) form when internationalized
     pub domain: String,
 }
 
+// domains are compared in their ASCII form so that an internationalized one equals its punycode
+impl PartialEq for ClientId {
+    fn eq(&self, other: &Self) -> bool {
+        self.user_id == other.user_id && self.device_id == other.device_id && domain::eq(&self.domain, &other.domain)
+    }
+}
+
+impl Eq for ClientId {}
+
 impl ClientId {
     #[cfg(any(test, feature = "test-utils"))]
     /// Default user
@@ -38,17 +102,18 @@ impl ClientId {
     /// Constructor
     pub fn try_new(user_id: impl AsRef<str>, device_id: u64, domain: &str) -> RustyJwtResult<Self> {
         let user_id = uuid::Uuid::try_from(user_id.as_ref()).map_err(|_| RustyJwtError::InvalidClientId)?;
+        let domain = Self::parse_domain(domain)?;
         Ok(Self {
             user_id,
             device_id,
-            domain: domain.to_string(),
+            domain,
         })
     }
 
     /// Constructor
     pub fn try_from_raw_parts(user_id: &[u8], device_id: u64, domain: &[u8]) -> RustyJwtResult<Self> {
         let user_id = Uuid::from_slice(user_id)?;
-        let domain = core::str::from_utf8(domain)?.to_string();
+        let domain = Self::parse_domain(core::str::from_utf8(domain)?)?;
         Ok(Self {
             user_id,
             device_id,
@@ -58,6 +123,11 @@ impl ClientId {
 
     /// Parse from an URI e.g. `wireapp://{userId}%21{clientId}@{domain}` where '%21' is '!' percent encoded
     pub fn try_from_uri(client_id: &str) -> RustyJwtResult<Self> {
+        Self::try_from_uri_with(client_id, DeviceIdFormat::default())
+    }
+
+    /// Same as [ClientId::try_from_uri] with a device id in [format]
+    pub fn try_from_uri_with(client_id: &str, format: DeviceIdFormat) -> RustyJwtResult<Self> {
         let uri = client_id.parse::<Url>()?;
         if uri.scheme() != Self::URI_RAW_SCHEME {
             return Err(RustyJwtError::InvalidIdentifierScheme(uri.scheme().to_string()));
@@ -67,71 +137,121 @@ impl ClientId {
         let (user_id, device_id) = username.split_once('!').ok_or(RustyJwtError::InvalidClientId)?;
 
         let user_id = Self::parse_user_id(user_id)?;
-        let device_id = Self::parse_device_id(device_id)?;
-        let domain = uri.host_str().ok_or(RustyJwtError::InvalidClientId)?.to_string();
+        let device_id = format.parse(device_id)?;
+        let domain = Self::parse_domain(uri.host_str().ok_or(RustyJwtError::InvalidClientId)?)?;
         Ok(Self {
             user_id,
             device_id,
-            domain: domain.to_string(),
+            domain,
         })
     }
 
     /// Constructor for clientId usually used by Wire client application. It is not a URI (does not have a scheme)
     /// e.g. `wireapp://{userId}!{clientId}@{domain}`
     pub fn try_from_qualified(client_id: &str) -> RustyJwtResult<Self> {
+        Self::try_from_qualified_with(client_id, DeviceIdFormat::default())
+    }
+
+    /// Same as [ClientId::try_from_qualified] with a device id in [format]
+    pub fn try_from_qualified_with(client_id: &str, format: DeviceIdFormat) -> RustyJwtResult<Self> {
         let (user_id, rest) = client_id
             .split_once(Self::DELIMITER)
             .ok_or(RustyJwtError::InvalidClientId)?;
         let user_id = Self::parse_user_id(user_id)?;
         let (device_id, domain) = rest.split_once('@').ok_or(RustyJwtError::InvalidClientId)?;
-        let device_id = Self::parse_device_id(device_id)?;
+        let device_id = format.parse(device_id)?;
+        let domain = Self::parse_domain(domain)?;
         Ok(Self {
             user_id,
             device_id,
-            domain: domain.to_string(),
+            domain,
+        })
+    }
+
+    /// Parses any of the formats current or older clients use, see [ClientIdFormat]. Meant for
+    /// servers which have to accept tokens from clients of different versions.
+    pub fn parse_any(client_id: &str) -> RustyJwtResult<(Self, ClientIdFormat)> {
+        if client_id.starts_with(Self::URI_SCHEME) {
+            return Ok((Self::try_from_uri(client_id)?, ClientIdFormat::Uri));
+        }
+        if let Ok(parsed) = Self::try_from_qualified(client_id) {
+            return Ok((parsed, ClientIdFormat::Qualified));
+        }
+        Ok((Self::try_from_legacy(client_id)?, ClientIdFormat::Legacy))
+    }
+
+    /// Parses the format older clients used e.g. `{uuid}:{clientId}@{domain}`. Some of them also
+    /// used ':' instead of '@' before the domain.
+    fn try_from_legacy(client_id: &str) -> RustyJwtResult<Self> {
+        let (user_id, rest) = client_id
+            .split_once(Self::DELIMITER)
+            .ok_or(RustyJwtError::InvalidClientId)?;
+        let user_id = Uuid::try_parse(user_id).map_err(|_| RustyJwtError::InvalidClientId)?;
+        let (device_id, domain) = rest.split_once(['@', ':']).ok_or(RustyJwtError::InvalidClientId)?;
+        let device_id = DeviceIdFormat::Hex.parse(device_id)?;
+        let domain = Self::parse_domain(domain)?;
+        Ok(Self {
+            user_id,
+            device_id,
+            domain,
         })
     }
 
     /// Into JWT 'sub' claim
     pub fn to_uri(&self) -> String {
+        self.to_uri_with(DeviceIdFormat::default())
+    }
+
+    /// Same as [ClientId::to_uri] with the device id in [format]
+    pub fn to_uri_with(&self, format: DeviceIdFormat) -> String {
         // sadly this is the only way to have a Url builder :/
         let mut uri = DEFAULT_URL.clone();
         let user_id = self.base64_encoded_user_id();
-        let device_id = self.hex_encoded_device_id();
+        let device_id = format.render(self.device_id);
         let client_id = format!("{user_id}{}{device_id}", ClientId::URI_DELIMITER);
         uri.set_username(&client_id).unwrap();
-        uri.set_host(Some(&self.domain)).unwrap();
+        uri.set_host(Some(&self.ascii_domain())).unwrap();
         uri.to_string()
     }
 
     /// Without URI scheme
     pub fn to_qualified(&self) -> String {
+        self.to_qualified_with(DeviceIdFormat::default())
+    }
+
+    /// Same as [ClientId::to_qualified] with the device id in [format]
+    pub fn to_qualified_with(&self, format: DeviceIdFormat) -> String {
         let user_id = self.base64_encoded_user_id();
         let delimiter = Self::DELIMITER;
-        let device_id = self.hex_encoded_device_id();
-        let host = &self.domain;
+        let device_id = format.render(self.device_id);
+        let host = self.ascii_domain();
         format!("{user_id}{delimiter}{device_id}@{host}")
     }
 
+    /// Domain in its Unicode form, for display only e.g. `xn--bcher-kva.example` => `bücher.example`
+    pub fn display_domain(&self) -> String {
+        domain::to_unicode(&self.domain)
+    }
+
+    fn ascii_domain(&self) -> String {
+        domain::to_ascii(&self.domain).unwrap_or_else(|| self.domain.clone())
+    }
+
+    fn parse_domain(domain: &str) -> RustyJwtResult<String> {
+        domain::to_ascii(domain).ok_or(RustyJwtError::InvalidClientId)
+    }
+
     fn base64_encoded_user_id(&self) -> String {
         let user_id = self.user_id.as_bytes().as_slice();
         base64::prelude::BASE64_URL_SAFE_NO_PAD.encode(user_id)
     }
 
-    fn hex_encoded_device_id(&self) -> String {
-        format!("{:x}", self.device_id)
-    }
-
     fn parse_user_id(user_id: &str) -> RustyJwtResult<Uuid> {
         let user_id = base64::prelude::BASE64_URL_SAFE_NO_PAD
             .decode(user_id)
             .map_err(|_| RustyJwtError::InvalidClientId)?;
         Ok(Uuid::from_slice(&user_id)?)
     }
-
-    fn parse_device_id(device_id: &str) -> RustyJwtResult<u64> {
-        u64::from_str_radix(device_id, 16).map_err(|_| RustyJwtError::InvalidClientId)
-    }
 }
 
 #[cfg(any(test, feature = "test-utils"))]
@@ -338,6 +458,159 @@ pub mod tests {
             }
         }
 
+        mod device_id {
+            use super::*;
+
+            #[test]
+            #[wasm_bindgen_test]
+            fn should_render_in_format() {
+                let client_id = ClientId::try_new(ClientId::DEFAULT_USER.to_string(), 6699, DOMAIN).unwrap();
+                assert_eq!(client_id.to_uri_with(DeviceIdFormat::Hex), client_id.to_uri());
+                assert_eq!(
+                    client_id.to_uri_with(DeviceIdFormat::PaddedHex),
+                    format!("wireapp://{USER_ID}!0000000000001a2b@{DOMAIN}")
+                );
+                assert_eq!(
+                    client_id.to_qualified_with(DeviceIdFormat::Decimal),
+                    format!("{USER_ID}:6699@{DOMAIN}")
+                );
+                for format in [DeviceIdFormat::Hex, DeviceIdFormat::PaddedHex, DeviceIdFormat::Decimal] {
+                    let uri = client_id.to_uri_with(format);
+                    assert_eq!(ClientId::try_from_uri_with(&uri, format).unwrap(), client_id);
+                    let qualified = client_id.to_qualified_with(format);
+                    assert_eq!(
+                        ClientId::try_from_qualified_with(&qualified, format).unwrap(),
+                        client_id
+                    );
+                }
+            }
+
+            #[test]
+            #[wasm_bindgen_test]
+            fn should_parse_tolerantly() {
+                for device_id in [
+                    "1a2b",
+                    "1A2B",
+                    "0x1a2b",
+                    "0X1A2B",
+                    "0000000000001a2b",
+                    "00000000000000001a2b",
+                ] {
+                    assert_eq!(DeviceIdFormat::Hex.parse(device_id).unwrap(), 6699);
+                    assert_eq!(DeviceIdFormat::PaddedHex.parse(device_id).unwrap(), 6699);
+                }
+                assert_eq!(DeviceIdFormat::Decimal.parse("006699").unwrap(), 6699);
+                let subject = format!("wireapp://{USER_ID}!0x1A2B@{DOMAIN}");
+                assert_eq!(ClientId::try_from_uri(&subject).unwrap().device_id, 6699);
+            }
+
+            #[test]
+            #[wasm_bindgen_test]
+            fn should_fail_when_not_in_radix() {
+                for device_id in ["", "0x", "+1a2b", "-1", "1a2b ", "1g2g"] {
+                    assert!(matches!(
+                        DeviceIdFormat::Hex.parse(device_id).unwrap_err(),
+                        RustyJwtError::InvalidClientId
+                    ));
+                }
+                for device_id in ["1a2b", "0x1", "+1", "18446744073709551616"] {
+                    assert!(matches!(
+                        DeviceIdFormat::Decimal.parse(device_id).unwrap_err(),
+                        RustyJwtError::InvalidClientId
+                    ));
+                }
+            }
+        }
+
+        mod idn {
+            use super::*;
+
+            const IDN: &str = "bücher.example";
+            const PUNYCODE: &str = "xn--bcher-kva.example";
+
+            #[test]
+            #[wasm_bindgen_test]
+            fn should_serialize_punycode() {
+                let client_id = ClientId::try_new(ClientId::DEFAULT_USER.to_string(), 6699, IDN).unwrap();
+                assert_eq!(client_id.domain, PUNYCODE);
+                assert_eq!(client_id.display_domain(), IDN);
+                assert_eq!(
+                    client_id.to_uri(),
+                    format!("wireapp://{USER_ID}!{CLIENT_ID}@{PUNYCODE}")
+                );
+                assert_eq!(client_id.to_qualified(), format!("{USER_ID}:{CLIENT_ID}@{PUNYCODE}"));
+            }
+
+            #[test]
+            #[wasm_bindgen_test]
+            fn should_parse_both_forms() {
+                let expected = ClientId::try_new(ClientId::DEFAULT_USER.to_string(), 6699, PUNYCODE).unwrap();
+                for client_id in [
+                    format!("wireapp://{USER_ID}!{CLIENT_ID}@{IDN}"),
+                    format!("wireapp://{USER_ID}!{CLIENT_ID}@{PUNYCODE}"),
+                    format!("{USER_ID}:{CLIENT_ID}@{IDN}"),
+                ] {
+                    let (parsed, _) = ClientId::parse_any(&client_id).unwrap();
+                    assert_eq!(parsed, expected);
+                    assert_eq!(parsed.domain, PUNYCODE);
+                }
+
+                // even when not normalized
+                let unicode = ClientId {
+                    domain: IDN.to_string(),
+                    ..expected.clone()
+                };
+                assert_eq!(unicode, expected);
+                assert_eq!(unicode.to_uri(), expected.to_uri());
+            }
+        }
+
+        mod any {
+            use super::*;
+
+            fn expected() -> ClientId {
+                ClientId {
+                    user_id: ClientId::DEFAULT_USER,
+                    device_id: 6699,
+                    domain: DOMAIN.to_string(),
+                }
+            }
+
+            #[test]
+            #[wasm_bindgen_test]
+            fn should_detect_format() {
+                let uuid = ClientId::DEFAULT_USER;
+                for (client_id, format) in [
+                    (format!("wireapp://{USER_ID}!{CLIENT_ID}@{DOMAIN}"), ClientIdFormat::Uri),
+                    (
+                        format!("wireapp://{USER_ID}%21{CLIENT_ID}@{DOMAIN}"),
+                        ClientIdFormat::Uri,
+                    ),
+                    (format!("{USER_ID}:{CLIENT_ID}@{DOMAIN}"), ClientIdFormat::Qualified),
+                    (format!("{uuid}:{CLIENT_ID}@{DOMAIN}"), ClientIdFormat::Legacy),
+                    (format!("{uuid}:{CLIENT_ID}:{DOMAIN}"), ClientIdFormat::Legacy),
+                ] {
+                    assert_eq!(ClientId::parse_any(&client_id).unwrap(), (expected(), format));
+                }
+            }
+
+            #[test]
+            #[wasm_bindgen_test]
+            fn should_fail_when_invalid() {
+                let uuid = ClientId::DEFAULT_USER;
+                for client_id in [
+                    format!("http://{USER_ID}!{CLIENT_ID}@{DOMAIN}"),
+                    format!("{uuid}:1g2g@{DOMAIN}"),
+                    format!("{uuid}:{CLIENT_ID}@"),
+                    format!("{uuid}@{DOMAIN}"),
+                    format!("abcd:{CLIENT_ID}@{DOMAIN}"),
+                ] {
+                    let parsed = ClientId::parse_any(&client_id);
+                    assert!(matches!(parsed.unwrap_err(), RustyJwtError::InvalidClientId));
+                }
+            }
+        }
+
         mod qualified {
             use super::*;
 
@@ -0,0 +1,121 @@
+use serde::{Deserialize, Serialize};
+use unicode_normalization::UnicodeNormalization;
+
+/// Human readable name of a user e.g. `Smith, Alice M (QA)`, as found in the ID token of the
+/// Identity Provider and in the ACME order.
+///
+/// Names are compared once NFC normalized, see [Unicode Standard Annex #15][1], so that visually
+/// identical names written with precomposed or decomposed characters are equal.
+///
+/// [1]: https://unicode.org/reports/tr15/
+#[derive(Debug, Clone, Serialize, Deserialize, derive_more::From, derive_more::Into, derive_more::Deref)]
+#[serde(transparent)]
+pub struct DisplayName(String);
+
+/// Differences tolerated when comparing [DisplayName]s, on top of Unicode normalization
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct DisplayNameFolding {
+    /// Letters are compared lowercase
+    pub case: bool,
+    /// Leading and trailing whitespaces are ignored and inner runs of whitespaces are compared as a single space
+    pub whitespace: bool,
+}
+
+impl Default for DisplayNameFolding {
+    fn default() -> Self {
+        Self {
+            case: false,
+            whitespace: true,
+        }
+    }
+}
+
+impl DisplayNameFolding {
+    /// Only Unicode normalization, no folding
+    pub const NONE: Self = Self {
+        case: false,
+        whitespace: false,
+    };
+}
+
+impl DisplayName {
+    /// NFC normalized form of the name, folded according to [folding]
+    pub fn normalized(&self, folding: DisplayNameFolding) -> String {
+        let mut name = self.0.nfc().collect::<String>();
+        if folding.whitespace {
+            name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+        }
+        if folding.case {
+            name = name.to_lowercase();
+        }
+        name
+    }
+
+    /// Whether both names are the same once normalized and folded according to [folding]
+    pub fn matches(&self, other: &Self, folding: DisplayNameFolding) -> bool {
+        self.0 == other.0 || self.normalized(folding) == other.normalized(folding)
+    }
+}
+
+/// Compares with the default [DisplayNameFolding]
+impl PartialEq for DisplayName {
+    fn eq(&self, other: &Self) -> bool {
+        self.matches(other, DisplayNameFolding::default())
+    }
+}
+
+impl Eq for DisplayName {}
+
+impl From<&str> for DisplayName {
+    fn from(s: &str) -> Self {
+        s.to_string().into()
+    }
+}
+
+impl std::fmt::Display for DisplayName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    // 'é' precomposed and as 'e' followed by a combining acute accent
+    const PRECOMPOSED: &str = "Ren\u{e9}e Smith";
+    const DECOMPOSED: &str = "Rene\u{301}e Smith";
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_match_once_normalized() {
+        let (a, b) = (DisplayName::from(PRECOMPOSED), DisplayName::from(DECOMPOSED));
+        assert_ne!(PRECOMPOSED, DECOMPOSED);
+        assert_eq!(a, b);
+        assert!(a.matches(&b, DisplayNameFolding::NONE));
+        assert_eq!(b.normalized(DisplayNameFolding::NONE), PRECOMPOSED);
+        assert_ne!(a, DisplayName::from("Renee Smith"));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_fold_according_to_options() {
+        let name = DisplayName::from("Alice Smith");
+        let spaced = DisplayName::from(" Alice \t Smith ");
+        let lower = DisplayName::from("alice smith");
+
+        assert_eq!(name, spaced);
+        assert!(!name.matches(&spaced, DisplayNameFolding::NONE));
+
+        assert_ne!(name, lower);
+        let case = DisplayNameFolding {
+            case: true,
+            ..Default::default()
+        };
+        assert!(name.matches(&lower, case));
+        assert!(spaced.matches(&lower, case));
+    }
+}
@@ -0,0 +1,133 @@
+//! Internationalized domain names, see [RFC 5891][1]
+//!
+//! Domains are serialized in claims in their ASCII (punycode) form and converted back to Unicode
+//! for display only.
+//!
+//! [1]: https://www.rfc-editor.org/rfc/rfc5891
+
+use percent_encoding::percent_decode_str;
+
+use crate::prelude::*;
+
+/// Domains the 'sub' and 'handle' claims of a token may belong to, so that a multi-tenant backend
+/// cannot be tricked into accepting tokens for a foreign domain
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub enum DomainPolicy {
+    /// Any domain is accepted
+    #[default]
+    Any,
+    /// Only these domains are accepted, in either their Unicode or ASCII form
+    AllowList(Vec<String>),
+}
+
+impl DomainPolicy {
+    /// Domains accepted, none when any is
+    pub fn allowed(&self) -> Option<&[String]> {
+        match self {
+            Self::Any => None,
+            Self::AllowList(domains) => Some(domains),
+        }
+    }
+
+    /// Fails when [domain] is not accepted
+    pub fn verify(&self, domain: &str) -> RustyJwtResult<()> {
+        match self.allowed() {
+            Some(allowed) if !allowed.iter().any(|d| eq(d, domain)) => {
+                Err(RustyJwtError::DomainNotAllowed(domain.to_string()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Fails when the domain of [handle] is not accepted
+    pub fn verify_handle(&self, handle: &QualifiedHandle) -> RustyJwtResult<()> {
+        if *self == Self::Any {
+            return Ok(());
+        }
+        let domain = handle.ascii_domain().ok_or(RustyJwtError::InvalidHandle)?;
+        self.verify(&domain)
+    }
+}
+
+impl<S: Into<String>> FromIterator<S> for DomainPolicy {
+    fn from_iter<I: IntoIterator<Item = S>>(domains: I) -> Self {
+        Self::AllowList(domains.into_iter().map(Into::into).collect())
+    }
+}
+
+/// Converts a domain, either Unicode or already in ASCII, into its ASCII (punycode) form e.g.
+/// `bücher.example` => `xn--bcher-kva.example`. Percent encoded input, as found in the host of a
+/// 'wireapp' URI, is decoded first.
+pub(crate) fn to_ascii(domain: &str) -> Option<String> {
+    let domain = percent_decode_str(domain).decode_utf8().ok()?;
+    idna::domain_to_ascii(&domain).ok().filter(|d| !d.is_empty())
+}
+
+/// Converts a domain into its Unicode form, for display e.g. `xn--bcher-kva.example` =>
+/// `bücher.example`. Returned as is when it is not a valid domain.
+pub(crate) fn to_unicode(domain: &str) -> String {
+    match to_ascii(domain) {
+        Some(ascii) => idna::domain_to_unicode(&ascii).0,
+        None => domain.to_string(),
+    }
+}
+
+/// Whether both domains are the same once in their ASCII form
+pub fn eq(a: &str, b: &str) -> bool {
+    a == b || matches!((to_ascii(a), to_ascii(b)), (Some(a), Some(b)) if a == b)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_roundtrip() {
+        assert_eq!(to_ascii("bücher.example").unwrap(), "xn--bcher-kva.example");
+        assert_eq!(to_ascii("b%C3%BCcher.example").unwrap(), "xn--bcher-kva.example");
+        assert_eq!(to_ascii("Wire.com").unwrap(), "wire.com");
+        assert_eq!(to_unicode("xn--bcher-kva.example"), "bücher.example");
+        assert_eq!(to_unicode("wire.com"), "wire.com");
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_compare_both_forms() {
+        assert!(eq("bücher.example", "xn--bcher-kva.example"));
+        assert!(!eq("bücher.example", "bucher.example"));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_verify_allow_list() {
+        assert!(DomainPolicy::Any.verify("foreign.com").is_ok());
+
+        let policy = DomainPolicy::from_iter(["wire.com", "bücher.example"]);
+        assert!(policy.verify("wire.com").is_ok());
+        assert!(policy.verify("xn--bcher-kva.example").is_ok());
+        assert!(matches!(
+            policy.verify("foreign.com").unwrap_err(),
+            RustyJwtError::DomainNotAllowed(d) if d == "foreign.com"
+        ));
+
+        let handle = Handle::from("alice").try_to_qualified("foreign.com").unwrap();
+        assert!(DomainPolicy::Any.verify_handle(&handle).is_ok());
+        assert!(matches!(
+            policy.verify_handle(&handle).unwrap_err(),
+            RustyJwtError::DomainNotAllowed(_)
+        ));
+        let handle = Handle::from("alice").try_to_qualified("bücher.example").unwrap();
+        assert!(policy.verify_handle(&handle).is_ok());
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_fail_when_invalid() {
+        assert!(to_ascii("").is_none());
+        assert!(to_ascii("%FF.com").is_none());
+    }
+}
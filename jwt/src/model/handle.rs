@@ -1,4 +1,4 @@
-use crate::model::DEFAULT_URL;
+use crate::model::{domain, DEFAULT_URL};
 use crate::prelude::{ClientId, RustyJwtError, RustyJwtResult};
 use percent_encoding::percent_decode_str;
 use std::str::FromStr;
@@ -11,12 +11,14 @@ impl Handle {
     /// Present in front of the handle. It's '@' URL encoded
     pub const PREFIX: &'static str = "%40";
 
-    /// Converts the handle into i.e. `{handle}` => `wireapp://%40{handle}@{domain}`
+    /// Converts the handle into i.e. `{handle}` => `wireapp://%40{handle}@{domain}`. An
+    /// internationalized domain is converted to its ASCII (punycode) form.
     pub fn try_to_qualified(&self, host: &str) -> RustyJwtResult<QualifiedHandle> {
         // sadly this is the only way to have a Url builder :/
         let mut uri = DEFAULT_URL.clone();
 
-        uri.set_host(Some(host)).map_err(|_| RustyJwtError::InvalidHandle)?;
+        let host = domain::to_ascii(host).ok_or(RustyJwtError::InvalidHandle)?;
+        uri.set_host(Some(&host)).map_err(|_| RustyJwtError::InvalidHandle)?;
         let username = format!("@{}", self.0);
         uri.set_username(&username).map_err(|_| RustyJwtError::InvalidHandle)?;
         Ok(QualifiedHandle(uri.to_string()))
@@ -59,9 +61,40 @@ impl Default for Handle {
 }
 
 /// A handle represented as a URI e.g. `wireapp://%40beltram_wire@wire.com`
-#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize, derive_more::Deref)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, derive_more::Deref)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct QualifiedHandle(String);
 
+impl QualifiedHandle {
+    /// Domain in its Unicode form, for display only e.g. `xn--bcher-kva.example` => `bücher.example`
+    pub fn display_domain(&self) -> RustyJwtResult<String> {
+        let uri = url::Url::parse(&self.0)?;
+        let host = uri.host_str().ok_or(RustyJwtError::InvalidHandle)?;
+        Ok(domain::to_unicode(host))
+    }
+
+    /// Domain in its ASCII (punycode) form
+    pub(crate) fn ascii_domain(&self) -> Option<String> {
+        self.parts().map(|(_, host)| host)
+    }
+
+    fn parts(&self) -> Option<(String, String)> {
+        let uri = url::Url::parse(&self.0).ok()?;
+        let username = percent_decode_str(uri.username()).decode_utf8().ok()?.to_string();
+        let host = domain::to_ascii(uri.host_str()?)?;
+        Some((username, host))
+    }
+}
+
+// domains are compared in their ASCII form so that an internationalized one equals its punycode
+impl PartialEq for QualifiedHandle {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0 || matches!((self.parts(), other.parts()), (Some(a), Some(b)) if a == b)
+    }
+}
+
+impl Eq for QualifiedHandle {}
+
 impl FromStr for QualifiedHandle {
     type Err = RustyJwtError;
 
@@ -119,6 +152,23 @@ pub mod tests {
         assert_eq!(uri.username(), "%40beltram_wire");
     }
 
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_normalize_internationalized_domain() {
+        let handle = Handle::from("beltram_wire");
+        let qualified_handle = handle.try_to_qualified("bücher.example").unwrap();
+        assert_eq!(&qualified_handle.0, "wireapp://%40beltram_wire@xn--bcher-kva.example");
+        assert_eq!(qualified_handle.display_domain().unwrap(), "bücher.example");
+
+        // both representations are equal
+        let unicode = "wireapp://%40beltram_wire@bücher.example"
+            .parse::<QualifiedHandle>()
+            .unwrap();
+        assert_eq!(unicode, qualified_handle);
+        let other = handle.try_to_qualified("bucher.example").unwrap();
+        assert_ne!(other, qualified_handle);
+    }
+
     mod parse {
         use super::*;
 
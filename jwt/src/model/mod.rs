@@ -1,7 +1,10 @@
 use lazy_static::lazy_static;
 
 pub mod alg;
+pub mod claims_version;
 pub mod client_id;
+pub mod display_name;
+pub mod domain;
 pub mod handle;
 pub mod nonce;
 pub mod pem;
@@ -5,6 +5,7 @@ use crate::prelude::*;
 /// Nonce generated by the acme server.
 /// Also called `challenge`, it is used for authentication challenge
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct AcmeNonce(pub String);
 
 impl From<String> for AcmeNonce {
@@ -6,30 +6,44 @@ use crate::prelude::*;
 
 /// Abstraction over a public cryptographic key to upcast it in order to ease and factorize its usage with `jwt_simple`
 #[derive(Debug, Clone)]
-pub struct AnyPublicKey<'a>(JwsAlgorithm, Option<&'a Jwk>, Option<&'a Pem>);
+pub struct AnyPublicKey<'a>(JwsAlgorithm, PublicKeyRepr<'a>);
+
+#[derive(Debug, Clone)]
+enum PublicKeyRepr<'a> {
+    Jwk(&'a Jwk),
+    Pem(&'a Pem),
+    Parsed(&'a ParsedPublicKey),
+}
 
 impl AnyPublicKey<'_> {
     fn try_into_pem(&self) -> RustyJwtResult<Pem> {
-        if let Some(jwk) = self.1 {
-            return Ok(match self.0 {
+        Ok(match &self.1 {
+            PublicKeyRepr::Jwk(jwk) => match self.0 {
                 JwsAlgorithm::P256 => ES256PublicKey::try_from_jwk(jwk)?.to_pem()?.into(),
                 JwsAlgorithm::P384 => ES384PublicKey::try_from_jwk(jwk)?.to_pem()?.into(),
                 JwsAlgorithm::Ed25519 => Ed25519PublicKey::try_from_jwk(jwk)?.to_pem().into(),
-            });
-        }
-        self.2.cloned().ok_or(RustyJwtError::ImplementationError)
+            },
+            PublicKeyRepr::Pem(pem) => (*pem).clone(),
+            PublicKeyRepr::Parsed(parsed) => parsed.to_pem()?,
+        })
     }
 }
 
 impl<'a> From<(JwsAlgorithm, &'a Jwk)> for AnyPublicKey<'a> {
     fn from((alg, jwk): (JwsAlgorithm, &'a Jwk)) -> Self {
-        Self(alg, Some(jwk), None)
+        Self(alg, PublicKeyRepr::Jwk(jwk))
     }
 }
 
 impl<'a> From<(JwsAlgorithm, &'a Pem)> for AnyPublicKey<'a> {
     fn from((alg, pk): (JwsAlgorithm, &'a Pem)) -> Self {
-        Self(alg, None, Some(pk))
+        Self(alg, PublicKeyRepr::Pem(pk))
+    }
+}
+
+impl<'a> From<&'a ParsedPublicKey> for AnyPublicKey<'a> {
+    fn from(pk: &'a ParsedPublicKey) -> Self {
+        Self(pk.alg(), PublicKeyRepr::Parsed(pk))
     }
 }
 
@@ -52,21 +66,106 @@ impl AnyPublicKey<'_> {
     where
         T: Serialize + DeserializeOwned,
     {
-        let Self(alg, jwk, pk) = self;
-        if let Some(jwk) = jwk {
-            match alg {
+        let Self(alg, repr) = self;
+        match repr {
+            PublicKeyRepr::Jwk(jwk) => match alg {
                 JwsAlgorithm::P256 => ES256PublicKey::try_from_jwk(jwk)?.verify_token::<T>(token, options),
                 JwsAlgorithm::P384 => ES384PublicKey::try_from_jwk(jwk)?.verify_token::<T>(token, options),
                 JwsAlgorithm::Ed25519 => Ed25519PublicKey::try_from_jwk(jwk)?.verify_token::<T>(token, options),
-            }
-        } else if let Some(pk) = pk {
-            match alg {
+            },
+            PublicKeyRepr::Pem(pk) => match alg {
                 JwsAlgorithm::P256 => ES256PublicKey::from_pem(pk)?.verify_token::<T>(token, options),
                 JwsAlgorithm::P384 => ES384PublicKey::from_pem(pk)?.verify_token::<T>(token, options),
                 JwsAlgorithm::Ed25519 => Ed25519PublicKey::from_pem(pk)?.verify_token::<T>(token, options),
-            }
-        } else {
-            Err(jwt_simple::Error::msg("Implementation error"))
+            },
+            PublicKeyRepr::Parsed(pk) => pk.verify_token::<T>(token, options),
         }
     }
 }
+
+/// A public key parsed once, to verify many tokens without decoding it every time
+#[derive(Debug, Clone)]
+pub enum ParsedPublicKey {
+    /// ECDSA with P-256
+    P256(ES256PublicKey),
+    /// ECDSA with P-384
+    P384(ES384PublicKey),
+    /// EdDSA with Ed25519
+    Ed25519(Ed25519PublicKey),
+}
+
+impl ParsedPublicKey {
+    /// Parses a PEM encoded public key
+    pub fn try_from_pem(alg: JwsAlgorithm, pk: &Pem) -> RustyJwtResult<Self> {
+        Ok(match alg {
+            JwsAlgorithm::P256 => Self::P256(ES256PublicKey::from_pem(pk)?),
+            JwsAlgorithm::P384 => Self::P384(ES384PublicKey::from_pem(pk)?),
+            JwsAlgorithm::Ed25519 => Self::Ed25519(Ed25519PublicKey::from_pem(pk)?),
+        })
+    }
+
+    /// Parses the public key certified by [cert], which has to be one of [alg]
+    pub(crate) fn try_from_certificate(alg: JwsAlgorithm, cert: &x509_cert::Certificate) -> RustyJwtResult<Self> {
+        use x509_cert::der::Encode as _;
+
+        let spki = cert
+            .tbs_certificate
+            .subject_public_key_info
+            .to_der()
+            .map_err(|_| RustyJwtError::InvalidX5c("leaf certificate key is not DER encoded"))?;
+        let unexpected_key = |_| RustyJwtError::InvalidX5c("leaf certificate key is not one of 'alg'");
+        Ok(match alg {
+            JwsAlgorithm::P256 => Self::P256(ES256PublicKey::from_der(&spki).map_err(unexpected_key)?),
+            JwsAlgorithm::P384 => Self::P384(ES384PublicKey::from_der(&spki).map_err(unexpected_key)?),
+            JwsAlgorithm::Ed25519 => Self::Ed25519(Ed25519PublicKey::from_der(&spki).map_err(unexpected_key)?),
+        })
+    }
+
+    /// Parses a PEM encoded public key whose algorithm is not known in advance
+    fn try_from_any_pem(pk: &Pem) -> RustyJwtResult<Self> {
+        [JwsAlgorithm::P256, JwsAlgorithm::P384, JwsAlgorithm::Ed25519]
+            .into_iter()
+            .find_map(|alg| Self::try_from_pem(alg, pk).ok())
+            .ok_or(RustyJwtError::InvalidBackendKeys("Unsupported public key"))
+    }
+
+    /// Signature algorithm of this key
+    pub fn alg(&self) -> JwsAlgorithm {
+        match self {
+            Self::P256(_) => JwsAlgorithm::P256,
+            Self::P384(_) => JwsAlgorithm::P384,
+            Self::Ed25519(_) => JwsAlgorithm::Ed25519,
+        }
+    }
+
+    fn to_pem(&self) -> RustyJwtResult<Pem> {
+        Ok(match self {
+            Self::P256(pk) => pk.to_pem()?.into(),
+            Self::P384(pk) => pk.to_pem()?.into(),
+            Self::Ed25519(pk) => pk.to_pem().into(),
+        })
+    }
+
+    fn verify_token<T>(
+        &self,
+        token: &str,
+        options: Option<VerificationOptions>,
+    ) -> Result<JWTClaims<T>, jwt_simple::Error>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        match self {
+            Self::P256(pk) => pk.verify_token::<T>(token, options),
+            Self::P384(pk) => pk.verify_token::<T>(token, options),
+            Self::Ed25519(pk) => pk.verify_token::<T>(token, options),
+        }
+    }
+}
+
+impl TryFrom<&Pem> for ParsedPublicKey {
+    type Error = RustyJwtError;
+
+    fn try_from(pk: &Pem) -> RustyJwtResult<Self> {
+        Self::try_from_any_pem(pk)
+    }
+}
@@ -1,14 +1,41 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
 use crate::prelude::{RustyJwtError, RustyJwtResult};
 
-/// Represents a Wire team.
+/// Represents the Wire teams of a user.
+///
+/// A user does not necessarily belong to a team (personal user) and usually belongs to at most
+/// one. For backward compatibility the 'team' claim is serialized as `null` without a team, as a
+/// string with a single one and as an array only with several of them.
 ///
-/// There is a `AT MOST ONE` mapping between a user and a team but a user does not necessarily
-/// belong to a team.
-#[derive(
-    Debug, Clone, serde::Serialize, serde::Deserialize, derive_more::From, derive_more::Into, derive_more::Deref,
-)]
-#[serde(transparent)]
-pub struct Team(pub Option<String>);
+/// Breaking change: it used to wrap an `Option<String>`, [Team::as_ref] keeps returning the
+/// single team of the former representation.
+#[derive(Debug, Clone, Eq, PartialEq, derive_more::From, derive_more::Into, derive_more::Deref)]
+pub struct Team(pub Vec<String>);
+
+impl Team {
+    /// Personal user, without a team
+    pub fn none() -> Self {
+        Self(vec![])
+    }
+
+    /// First team of the user, the only one when belonging to at most one
+    #[allow(clippy::should_implement_trait)]
+    pub fn as_ref(&self) -> Option<&String> {
+        self.0.first()
+    }
+
+    /// Whether the user belongs to [team]
+    pub fn contains(&self, team: &str) -> bool {
+        self.0.iter().any(|t| t == team)
+    }
+}
+
+impl From<Option<String>> for Team {
+    fn from(team: Option<String>) -> Self {
+        Self(team.into_iter().collect())
+    }
+}
 
 impl From<String> for Team {
     fn from(s: String) -> Self {
@@ -30,23 +57,150 @@ impl TryFrom<&[u8]> for Team {
     }
 }
 
-impl Eq for Team {}
-
 #[cfg(test)]
 impl Default for Team {
     fn default() -> Self {
-        Self(Some("wire".to_string()))
+        "wire".into()
+    }
+}
+
+impl Serialize for Team {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0.as_slice() {
+            [] => serializer.serialize_none(),
+            [team] => serializer.serialize_str(team),
+            teams => teams.serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Team {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum OneOrMany {
+            One(String),
+            Many(Vec<String>),
+        }
+        Ok(match Option::<OneOrMany>::deserialize(deserializer)? {
+            None => Self::none(),
+            Some(OneOrMany::One(team)) => team.into(),
+            Some(OneOrMany::Many(teams)) => teams.into(),
+        })
+    }
+}
+
+#[cfg(feature = "schema")]
+impl schemars::JsonSchema for Team {
+    fn schema_name() -> String {
+        "Team".to_string()
+    }
+
+    fn json_schema(gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        use schemars::schema::{SchemaObject, SubschemaValidation};
+
+        // mirrors the Serialize impl: null without team, a string for a single one, an array otherwise
+        SchemaObject {
+            subschemas: Some(Box::new(SubschemaValidation {
+                any_of: Some(vec![
+                    gen.subschema_for::<()>(),
+                    gen.subschema_for::<String>(),
+                    gen.subschema_for::<Vec<String>>(),
+                ]),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+        .into()
     }
 }
 
-/// We want this to be lenient and backward compatible during the migration period
-impl PartialEq for Team {
-    fn eq(&self, server_team: &Self) -> bool {
-        match (&self.0, &server_team.0) {
-            // TODO: forbid this before releasing
-            (None, Some(_)) | (Some(_), None) => true, // this probably means that client & server are operating on different versions.
-            (Some(a), Some(b)) => a.eq(b),
-            (None, None) => true,
+/// How the 'team' claim of a DPoP proof is verified against the expected [Team]
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub enum TeamPolicy {
+    /// Lenient during the migration period, where client and server might operate on different
+    /// versions: passes when either side has no team, otherwise they have to share one
+    #[default]
+    Lenient,
+    /// Both sides have to list the same teams, in any order. A personal user has no team on
+    /// either side
+    Exact,
+    /// The 'team' claim is not verified
+    Ignore,
+}
+
+impl TeamPolicy {
+    /// Verifies the [team] claim against the [expected] one
+    pub fn verify(&self, expected: &Team, team: &Team) -> bool {
+        match self {
+            Self::Lenient => expected.is_empty() || team.is_empty() || team.iter().any(|t| expected.contains(t)),
+            Self::Exact => {
+                expected.len() == team.len()
+                    && team.iter().all(|t| expected.contains(t))
+                    && expected.iter().all(|t| team.contains(t))
+            }
+            Self::Ignore => true,
         }
     }
 }
+
+#[cfg(test)]
+pub mod tests {
+    use serde_json::json;
+    use wasm_bindgen_test::*;
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Claims {
+        team: Team,
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_serialize_backward_compatible() {
+        let ser = |team: Team| serde_json::to_value(Claims { team }).unwrap();
+        assert_eq!(ser(Team::none()), json!({"team": null}));
+        assert_eq!(ser("wire".into()), json!({"team": "wire"}));
+        let teams = Team::from(vec!["a".to_string(), "b".to_string()]);
+        assert_eq!(ser(teams), json!({"team": ["a", "b"]}));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_deserialize_any_form() {
+        let de = |v: serde_json::Value| serde_json::from_value::<Claims>(v).unwrap().team;
+        assert_eq!(de(json!({})), Team::none());
+        assert_eq!(de(json!({"team": null})), Team::none());
+        assert_eq!(de(json!({"team": "wire"})), Team::from("wire"));
+        assert_eq!(
+            de(json!({"team": ["a", "b"]})),
+            Team::from(vec!["a".to_string(), "b".to_string()])
+        );
+        assert!(serde_json::from_value::<Claims>(json!({"team": 1})).is_err());
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn should_verify_according_to_policy() {
+        let (none, wire) = (Team::none(), Team::from("wire"));
+        let many = Team::from(vec!["wire".to_string(), "other".to_string()]);
+
+        let lenient = TeamPolicy::Lenient;
+        assert!(lenient.verify(&wire, &none));
+        assert!(lenient.verify(&none, &wire));
+        assert!(lenient.verify(&wire, &many));
+        assert!(!lenient.verify(&wire, &Team::from("other-team")));
+
+        let exact = TeamPolicy::Exact;
+        assert!(exact.verify(&none, &none));
+        assert!(exact.verify(&many, &Team::from(vec!["other".to_string(), "wire".to_string()])));
+        assert!(!exact.verify(&wire, &none));
+        assert!(!exact.verify(&none, &wire));
+        assert!(!exact.verify(&wire, &many));
+
+        assert!(TeamPolicy::Ignore.verify(&wire, &Team::from("other-team")));
+    }
+}
@@ -0,0 +1,160 @@
+//! JSON Schemas of the claims of DPoP proofs and access tokens, so that components not written in
+//! Rust (wire-server, the ACME server) validate them against the same definitions as this library
+
+use schemars::{gen::SchemaSettings, schema::RootSchema, JsonSchema};
+
+use crate::prelude::*;
+
+/// Registered claims of a JWT as serialized by jwt-simple then the 'cv' claim and the custom
+/// claims, flattened in the same JSON object. Only describes the claims of [ClaimsVersion::CURRENT].
+#[derive(JsonSchema)]
+#[allow(dead_code)]
+struct JwtClaims<T> {
+    /// Issued at, in seconds since epoch
+    iat: u64,
+    /// Expiration, in seconds since epoch
+    exp: u64,
+    /// Not before, in seconds since epoch
+    nbf: Option<u64>,
+    /// Issuer
+    iss: Option<String>,
+    /// Subject i.e. the client identifier
+    sub: String,
+    /// Audience
+    aud: Audiences,
+    /// Unique identifier of the token
+    jti: String,
+    /// Nonce delivered by wire-server
+    nonce: String,
+    /// Version of the claims, see [ClaimsVersion]
+    cv: u64,
+    #[serde(flatten)]
+    claims: T,
+}
+
+/// Either a single audience or many of them
+#[derive(JsonSchema)]
+#[serde(untagged)]
+#[allow(dead_code)]
+enum Audiences {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl RustyJwtTools {
+    /// JSON Schema of the claims of a DPoP proof generated by [RustyJwtTools::generate_dpop_token]
+    pub fn dpop_claims_schema() -> RootSchema {
+        Self::claims_schema::<Dpop>()
+    }
+
+    /// JSON Schema of the claims of an access token generated by [RustyJwtTools::generate_access_token]
+    pub fn access_token_claims_schema() -> RootSchema {
+        Self::claims_schema::<Access>()
+    }
+
+    fn claims_schema<T: JsonSchema>() -> RootSchema {
+        SchemaSettings::draft07()
+            .into_generator()
+            .into_root_schema_for::<JwtClaims<T>>()
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use wasm_bindgen_test::*;
+
+    use super::*;
+    #[cfg(not(target_family = "wasm"))]
+    use crate::test_utils::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    fn required(schema: &RootSchema) -> Vec<&str> {
+        let object = schema.schema.object.as_ref().unwrap();
+        object.required.iter().map(String::as_str).collect()
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn dpop_schema_should_describe_generated_claims() {
+        let schema = RustyJwtTools::dpop_claims_schema();
+        let required = required(&schema);
+        for claim in [
+            "iat", "exp", "sub", "aud", "jti", "nonce", "cv", "htm", "htu", "chal", "handle", "team",
+        ] {
+            assert!(required.contains(&claim), "missing {claim}");
+        }
+        assert!(schema.definitions.contains_key("Htm"));
+    }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn access_token_schema_should_describe_generated_claims() {
+        let schema = RustyJwtTools::access_token_claims_schema();
+        let required = required(&schema);
+        for claim in [
+            "iat",
+            "exp",
+            "sub",
+            "cv",
+            "chal",
+            "cnf",
+            "proof",
+            "client_id",
+            "api_version",
+            "scope",
+        ] {
+            assert!(required.contains(&claim), "missing {claim}");
+        }
+        assert!(!required.contains(&"nbf"));
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    fn validate(schema: RootSchema, token: String) {
+        let schema = serde_json::to_value(schema).unwrap();
+        let schema = jsonschema::JSONSchema::compile(&schema).unwrap();
+        let claims = serde_json::Value::Object(jwt_claims(token));
+        if let Err(errors) = schema.validate(&claims) {
+            let errors = errors.map(|e| e.to_string()).collect::<Vec<_>>();
+            panic!("claims do not match the schema: {}", errors.join(", "));
+        }
+    }
+
+    #[apply(all_ciphersuites)]
+    #[test]
+    #[cfg(not(target_family = "wasm"))]
+    fn generated_tokens_should_match_schema(ciphersuite: Ciphersuite) {
+        let client_id = ClientId::default();
+        let backend_nonce = BackendNonce::default();
+        let audience = "https://stepca:32902/acme/wire/challenge/I16phsvAPGbruDHr5Bh6akQVPKP6OO5v/dF2LHNmGI20R8rzzcgnrCSv789XcFEyL".parse().unwrap();
+        let dpop = RustyJwtTools::generate_dpop_token(
+            Dpop::default(),
+            &client_id,
+            backend_nonce.clone(),
+            audience,
+            core::time::Duration::from_secs(3600),
+            ciphersuite.key.alg,
+            &ciphersuite.key.kp,
+        )
+        .unwrap();
+        validate(RustyJwtTools::dpop_claims_schema(), dpop.clone());
+
+        let access_token = AccessTokenBuilder::default()
+            .dpop_proof(&dpop)
+            .client_id(&client_id)
+            .handle(QualifiedHandle::default())
+            .team(Team::default())
+            .backend_nonce(backend_nonce)
+            .htu(Htu::default())
+            .htm(Htm::default())
+            .max_skew_secs(5)
+            .max_expiration(2136351646) // somewhere in 2037
+            .backend_keys(ciphersuite.key.create_another().kp)
+            .hash_algorithm(ciphersuite.hash)
+            .api_version(Access::DEFAULT_WIRE_SERVER_API_VERSION)
+            .expiry(core::time::Duration::from_secs(Access::DEFAULT_EXPIRY))
+            .build()
+            .unwrap();
+        validate(RustyJwtTools::access_token_claims_schema(), access_token);
+    }
+}
@@ -6,7 +6,7 @@ pub struct TestAccess {
     #[serde(rename = "chal", skip_serializing_if = "Option::is_none")]
     pub challenge: Option<AcmeNonce>,
     #[serde(rename = "cnf", skip_serializing_if = "Option::is_none")]
-    pub cnf: Option<JwkThumbprint>,
+    pub cnf: Option<Cnf>,
     #[serde(rename = "proof", skip_serializing_if = "Option::is_none")]
     pub proof: Option<String>,
     #[serde(rename = "client_id", skip_serializing_if = "Option::is_none")]
@@ -26,7 +26,7 @@ impl From<Ciphersuite> for TestAccess {
         let cnf = JwkThumbprint::generate(proof_jwk, ciphersuite.hash).unwrap();
         Self {
             challenge: Some(access.challenge),
-            cnf: Some(cnf),
+            cnf: Some(cnf.into()),
             proof: Some(proof),
             client_id: Some(ClientId::default()),
             api_version: Some(Access::DEFAULT_WIRE_SERVER_API_VERSION),
@@ -48,6 +48,7 @@ pub struct AccessBuilder {
     pub iat: Option<UnixTimeStamp>,
     pub exp: Option<UnixTimeStamp>,
     pub issuer: Option<Htu>,
+    pub audiences: Option<Audiences>,
 }
 
 impl From<Ciphersuite> for AccessBuilder {
@@ -67,6 +68,7 @@ impl From<Ciphersuite> for AccessBuilder {
             iat: Some(iat),
             exp: Some(exp),
             issuer: proof.dpop.htu,
+            audiences: None,
         }
     }
 }
@@ -108,6 +110,7 @@ impl AccessBuilder {
         claims.issued_at = self.iat;
         claims.expires_at = self.exp;
         claims.issuer = self.issuer.as_ref().map(|iss| iss.to_string());
+        claims.audiences = self.audiences.clone();
         claims
     }
 }
@@ -8,6 +8,7 @@ pub use dpop::*;
 #[allow(unused_imports)]
 pub use jwk::*;
 pub use utils::*;
+pub use x509::*;
 
 use crate::jwk::TryIntoJwk;
 use crate::jwk_thumbprint::JwkThumbprint;
@@ -17,6 +18,7 @@ pub mod access;
 pub mod dpop;
 pub mod jwk;
 pub mod utils;
+pub mod x509;
 
 #[template]
 #[export]
@@ -318,7 +320,12 @@ impl Default for Ciphersuite {
 
 #[template]
 #[export]
-#[rstest(hash, case::SHA256(HashAlgorithm::SHA256), case::SHA384(HashAlgorithm::SHA384))]
+#[rstest(
+    hash,
+    case::SHA256(HashAlgorithm::SHA256),
+    case::SHA384(HashAlgorithm::SHA384),
+    case::SHA512(HashAlgorithm::SHA512)
+)]
 #[allow(non_snake_case)]
 pub fn all_hash(hash: HashAlgorithm) {}
 
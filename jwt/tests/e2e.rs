@@ -45,22 +45,22 @@ fn e2e_jwt() {
         );
 
         // wire-server now validates the 'client_dpop' and generates an access token
-        let access_token = RustyJwtTools::generate_access_token(
-            &client_dpop,
-            &alice,
-            handle.clone(),
-            team.into(),
-            nonce.clone(),
-            htu.clone(),
-            htm,
-            leeway,
-            max_expiration,
-            backend_keys.clone(),
-            hash_alg,
-            5,
-            core::time::Duration::from_secs(360),
-        )
-        .unwrap();
+        let access_token = AccessTokenBuilder::default()
+            .dpop_proof(&client_dpop)
+            .client_id(&alice)
+            .handle(handle.clone())
+            .team(team.into())
+            .backend_nonce(nonce.clone())
+            .htu(htu.clone())
+            .htm(htm)
+            .max_skew_secs(leeway)
+            .max_expiration(max_expiration)
+            .backend_keys(backend_keys.clone())
+            .hash_algorithm(hash_alg)
+            .api_version(5)
+            .expiry(core::time::Duration::from_secs(360))
+            .build()
+            .unwrap();
 
         println!("2. generate access token:\nwire-server signature key:\n{backend_keys}\naccess token:\nhttps://jwt.io/#id_token={access_token}\n");
 
@@ -85,19 +85,21 @@ fn e2e_jwt() {
         let dpop_header = Token::decode_metadata(&client_dpop).unwrap();
         let dpop_jwk = dpop_header.public_key().unwrap();
         let kid = JwkThumbprint::generate(dpop_jwk, hash_alg).unwrap().kid;
-        let verify = RustyJwtTools::verify_access_token(
-            &access_token,
-            &alice,
-            &handle,
-            challenge,
-            leeway,
-            max_expiration,
-            htu.clone(),
-            backend_pk.clone(),
-            kid,
-            hash_alg,
-            5,
-        );
+        let token = AccessTokenToVerify {
+            access_token: &access_token,
+            client_id: &alice,
+            handle: &handle,
+            challenge: &challenge,
+            client_kid: &kid,
+        };
+        let options = VerifyAccessTokenOptions::default()
+            .max_skew_secs(leeway)
+            .max_expiration(max_expiration)
+            .issuers([htu.clone()])
+            .backend_pks([backend_pk.clone()])
+            .hash_algorithm(hash_alg)
+            .api_versions(5);
+        let verify = RustyJwtTools::verify_access_token(&token, &options);
         println!("3. verify access token\nwire-server public signature key:\n{backend_pk}");
         if verify.is_ok() {
             println!("✅ access token verified");